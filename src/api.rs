@@ -0,0 +1,432 @@
+//! Anthropic API backend module
+//!
+//! Sends prompts straight to the Anthropic Messages API instead of the
+//! `claude` CLI, for machines without the CLI and for the model parameters
+//! it cannot be given: the temperature and the most tokens of a response.
+//! [`ApiExecutor`] implements [`ClaudeExecutor`], so the dialog loop
+//! streams, records and saves its responses like the CLI's.
+//!
+//! The API key is read from `ANTHROPIC_API_KEY`, and requests go to
+//! [`DEFAULT_URL`] unless `ANTHROPIC_BASE_URL` points elsewhere, e.g. at a
+//! proxy. Like Ollama's, the API is stateless: the executor keeps the
+//! messages of the conversation in a [`ChatHistory`] and sends them with
+//! every prompt, and a command with
+//! [`SessionMode::Fresh`](crate::claude_executor::SessionMode::Fresh)
+//! starts over. The API runs no tools
+//! here, so the model can read the files included with `@file` but cannot
+//! edit the workspace.
+//!
+//! Unless the command asks for no prompt caching (`--no-prompt-cache`),
+//! the system prompt and the latest prompt, which carries the files of its
+//! `@file` references, are marked as cache breakpoints. The next turn then
+//! reads the system prompt and the conversation up to its prompt from the
+//! API's prompt cache, at a fraction of the input price; prompts shorter
+//! than the API's minimum are simply not cached.
+//!
+//! Responses are streamed as server-sent events:
+//!
+//! ```text
+//! event: content_block_delta
+//! data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}
+//! ```
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use claude_dialog::api::ApiExecutor;
+//! use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeOptions};
+//! use claude_dialog::pipeline::ResponsePipeline;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let api = ApiExecutor::from_env();
+//! let command = ClaudeInvocation {
+//!     prompt: "Why is the sky blue?".to_string(),
+//!     options: ClaudeOptions {
+//!         model: Some("claude-sonnet-4-5".to_string()),
+//!         temperature: Some(0.2),
+//!         max_tokens: Some(1024),
+//!         ..Default::default()
+//!     },
+//! };
+//!
+//! let response = api.execute(command, ResponsePipeline::terminal()).await?;
+//! println!("\n{} characters", response.error_for_status()?.text.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Result, Context, bail};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{BufRead, BufReader, Read};
+use std::time::Duration;
+use crate::chat::{ChatHistory, ChatMessage, ChatStream, HISTORY_BUDGET, failure};
+use crate::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeResponse};
+use crate::pipeline::ResponsePipeline;
+use crate::rate_limit;
+use crate::status::Usage;
+
+/// Address of the Anthropic API
+pub const DEFAULT_URL: &str = "https://api.anthropic.com";
+
+/// Environment variable holding the API key
+pub const KEY_VARIABLE: &str = "ANTHROPIC_API_KEY";
+
+/// Most tokens of a response unless `--max-tokens` says otherwise; the API
+/// needs a limit with every request
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Version of the Messages API the requests are written for
+const API_VERSION: &str = "2023-06-01";
+
+/// How long to wait for the API to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body of a request to `/v1/messages`
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<Vec<TextBlock>>,
+    messages: Vec<RequestMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    stream: bool,
+}
+
+/// A message of a request, its content as text or as blocks
+#[derive(Debug, Serialize)]
+struct RequestMessage {
+    role: String,
+    content: Content,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Blocks(Vec<TextBlock>),
+}
+
+/// A text content block, possibly a prompt caching breakpoint
+#[derive(Debug, Serialize)]
+struct TextBlock {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
+impl TextBlock {
+    fn new(text: String, cached: bool) -> Self {
+        Self {
+            kind: "text",
+            text,
+            cache_control: cached.then_some(CacheControl { kind: "ephemeral" }),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CacheControl {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+impl MessagesRequest {
+    /// A streamed request for `messages`, with caching breakpoints on the
+    /// system prompt and the last message if `cached`
+    fn new(model: String, max_tokens: u32, system: Option<String>, messages: Vec<ChatMessage>, temperature: Option<f32>, cached: bool) -> Self {
+        let last = messages.len().saturating_sub(1);
+        let messages = messages
+            .into_iter()
+            .enumerate()
+            .map(|(index, message)| RequestMessage {
+                content: if cached && index == last {
+                    Content::Blocks(vec![TextBlock::new(message.content, true)])
+                } else {
+                    Content::Text(message.content)
+                },
+                role: message.role,
+            })
+            .collect();
+
+        Self {
+            model,
+            max_tokens,
+            system: system.map(|system| vec![TextBlock::new(system, cached)]),
+            messages,
+            temperature,
+            stream: true,
+        }
+    }
+}
+
+/// The `data` of a streamed event; only the fields used are read
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    message: Option<EventMessage>,
+    delta: Option<Delta>,
+    usage: Option<EventUsage>,
+    error: Option<ApiError>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct EventMessage {
+    usage: Option<EventUsage>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Delta {
+    text: Option<String>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct EventUsage {
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cache_read_input_tokens: Option<u64>,
+    cache_creation_input_tokens: Option<u64>,
+}
+
+/// An error reported by the API, in a response body or a streamed event
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ErrorBody {
+    error: Option<ApiError>,
+}
+
+/// Executor sending prompts to the Anthropic Messages API
+///
+/// Responses are streamed unless the command asks for
+/// [`OutputFormat::StreamJson`](crate::claude_executor::OutputFormat::StreamJson),
+/// in which case the answer is sent once it is complete.
+#[derive(Debug)]
+pub struct ApiExecutor {
+    url: String,
+    key: Option<String>,
+    history: ChatHistory,
+}
+
+impl ApiExecutor {
+    /// Create an executor for the API at `url`, authenticating with `key`
+    pub fn new(url: &str, key: Option<String>) -> Self {
+        Self {
+            url: url.trim().trim_end_matches('/').to_string(),
+            key: key.filter(|key| !key.trim().is_empty()),
+            history: ChatHistory::new(),
+        }
+    }
+
+    /// Create an executor for the API in `ANTHROPIC_BASE_URL`, or
+    /// [`DEFAULT_URL`], with the key in [`KEY_VARIABLE`]
+    pub fn from_env() -> Self {
+        let url = env::var("ANTHROPIC_BASE_URL").ok().filter(|url| !url.trim().is_empty());
+        Self::new(url.as_deref().unwrap_or(DEFAULT_URL), env::var(KEY_VARIABLE).ok())
+    }
+
+    /// Address of the API
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Whether an API key was found
+    pub fn has_key(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Messages exchanged so far, oldest first
+    pub fn history(&self) -> Vec<ChatMessage> {
+        self.history.messages()
+    }
+}
+
+#[async_trait]
+impl ClaudeExecutor for ApiExecutor {
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+        let Some(key) = self.key.clone() else {
+            bail!("The api backend needs an API key in {}", KEY_VARIABLE);
+        };
+        let Some(model) = command.options.model.clone() else {
+            bail!("The api backend needs a model, e.g. --model claude-sonnet-4-5");
+        };
+        let system = command.options.system_prompt.clone().or_else(|| command.options.append_prompt.clone());
+        let max_tokens = command.options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        let temperature = command.options.temperature;
+        let cached = !command.options.no_prompt_cache;
+        let url = self.url.clone();
+
+        self.history
+            .exchange(&command, pipeline, HISTORY_BUDGET, move |messages, stream| {
+                let request = MessagesRequest::new(model.clone(), max_tokens, system.clone(), messages, temperature, cached);
+                post(&format!("{}/v1/messages", url), &key, &request, stream)
+                    .with_context(|| format!("Could not reach the Anthropic API at {}", url))
+            })
+            .await
+    }
+}
+
+/// Post a request and read the streamed answer
+///
+/// Every piece of the answer is sent to `stream` as it arrives, until the
+/// turn is cancelled. Errors reported by the API, such as an invalid key or an
+/// unknown model, are returned as an unsuccessful [`ClaudeResponse`]; the
+/// wait of a `Retry-After` header is included in the error for
+/// [`RateLimit::detect`](crate::rate_limit::RateLimit::detect).
+///
+/// # Errors
+///
+/// Returns an error if the API cannot be reached.
+fn post(url: &str, key: &str, request: &MessagesRequest, stream: &ChatStream) -> Result<ClaudeResponse> {
+    let agent = ureq::AgentBuilder::new().timeout_connect(CONNECT_TIMEOUT).build();
+    let body = match agent
+        .post(url)
+        .set("x-api-key", key)
+        .set("anthropic-version", API_VERSION)
+        .set("User-Agent", concat!("claude-dialog/", env!("CARGO_PKG_VERSION")))
+        .send_json(request)
+    {
+        Ok(response) => response.into_reader(),
+        Err(ureq::Error::Status(status, response)) => {
+            // Spelled out so that rate limit retries wait as long as asked
+            let retry_after = response
+                .header("retry-after")
+                .and_then(|value| rate_limit::parse_retry_after(value, Utc::now()))
+                .map(|wait| format!("; retry after {} seconds", wait.as_secs_f64().ceil()))
+                .unwrap_or_default();
+            let body = response.into_string().unwrap_or_default();
+            let message = serde_json::from_str::<ErrorBody>(&body)
+                .ok()
+                .and_then(|body| body.error)
+                .map_or(body, |error| error.message);
+            return Ok(failure(format!("Anthropic API error (HTTP {}): {}{}", status, message.trim(), retry_after)));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    read_events(body, stream)
+}
+
+/// Collect the answer from the events of a streamed response
+fn read_events(body: impl Read, stream: &ChatStream) -> Result<ClaudeResponse> {
+    let mut text = String::new();
+    let mut usage = Usage::default();
+    let mut reported = false;
+    let mut truncated = false;
+    for line in BufReader::new(body).lines() {
+        if stream.is_cancelled() {
+            bail!("The turn was cancelled");
+        }
+        let line = line.context("Failed to read the API response")?;
+        // `event:` lines repeat the type of the `data:` line that follows
+        let Some(data) = line.strip_prefix("data:") else {
+            continue;
+        };
+        let Ok(event) = serde_json::from_str::<Event>(data.trim()) else {
+            continue;
+        };
+
+        match event.kind.as_str() {
+            "message_start" => {
+                if let Some(counts) = event.message.and_then(|message| message.usage) {
+                    add_usage(&mut usage, &counts);
+                    reported = true;
+                }
+            }
+            "content_block_delta" => {
+                if let Some(piece) = event.delta.and_then(|delta| delta.text) {
+                    stream.send(&piece);
+                    text.push_str(&piece);
+                }
+            }
+            "message_delta" => {
+                if let Some(counts) = event.usage {
+                    // The final output count replaces the running one
+                    usage.output_tokens = 0;
+                    add_usage(&mut usage, &counts);
+                    reported = true;
+                }
+                truncated |= event.delta.and_then(|delta| delta.stop_reason).as_deref() == Some("max_tokens");
+            }
+            "error" => {
+                let message = event.error.map(|error| error.message).unwrap_or_default();
+                let mut response = failure(format!("Anthropic API error: {}", message));
+                response.text = text;
+                return Ok(response);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(ClaudeResponse {
+        usage: reported.then_some(usage),
+        truncated,
+        ..ClaudeResponse::new(text)
+    })
+}
+
+/// Add the token counts of an event to the usage of the response
+fn add_usage(usage: &mut Usage, counts: &EventUsage) {
+    let cache_read = counts.cache_read_input_tokens.unwrap_or(0);
+    let cache_write = counts.cache_creation_input_tokens.unwrap_or(0);
+    // Input tokens include cached ones, as the CLI reports them
+    usage.input_tokens += counts.input_tokens.unwrap_or(0) + cache_read + cache_write;
+    usage.output_tokens += counts.output_tokens.unwrap_or(0);
+    usage.cache_read_tokens += cache_read;
+    usage.cache_write_tokens += cache_write;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::PipelineCancel;
+
+    #[test]
+    fn test_url_and_key() {
+        let api = ApiExecutor::new("https://proxy.example.com/", Some(" ".to_string()));
+        assert_eq!(api.url(), "https://proxy.example.com");
+        assert!(!api.has_key());
+    }
+
+    #[test]
+    fn test_read_events() {
+        let body = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"cache_read_input_tokens\":100,\"output_tokens\":1}}}\n\n",
+            "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\", world\"}}\n\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":5}}\n\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+        let response = read_events(body.as_bytes(), &ChatStream::new(None, PipelineCancel::default())).unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.text, "Hello, world");
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens, usage.cache_read_tokens), (112, 5, 100));
+
+        let body = "data: {\"type\":\"content_block_delta\",\"delta\":{\"text\":\"Hi\"}}\n\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n";
+        let response = read_events(body.as_bytes(), &ChatStream::new(None, PipelineCancel::default())).unwrap();
+        assert!(!response.is_success());
+        assert_eq!(response.text, "Hi");
+        assert_eq!(response.stderr, "Anthropic API error: Overloaded");
+    }
+}
@@ -0,0 +1,275 @@
+//! Session archive module
+//!
+//! Heavy users collect a saved session per conversation (see
+//! [`crate::sessions`]). `claude-dialog sessions archive --older-than 30d`
+//! moves the sessions saved before then into the `archive` directory of the
+//! session store, each compressed with zstd, and records them in the
+//! archive's `index.json`: their ID, time, number of turns and title. `sessions
+//! list` reads the index rather than the compressed files, so archived
+//! sessions are still listed and found by ID; `sessions show`, `delete`,
+//! `merge` and `--from` read them as before.
+//!
+//! Compression is built with the `archive` feature; enable it with
+//! `--features archive` (or `full`). Without it, archived sessions are
+//! listed but cannot be read.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::archive::parse_age;
+//! use chrono::TimeDelta;
+//!
+//! assert_eq!(parse_age("30d").unwrap(), TimeDelta::days(30));
+//! assert_eq!(parse_age("2w").unwrap(), TimeDelta::weeks(2));
+//! assert!(parse_age("30").is_err());
+//! ```
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use crate::paths;
+
+/// Extension of archived session files
+pub const EXTENSION: &str = "zst";
+
+/// Name of the index of archived sessions
+pub const INDEX_FILE: &str = "index.json";
+
+/// zstd compression level: a good ratio for text while staying fast
+#[cfg(feature = "archive")]
+const LEVEL: i32 = 19;
+
+/// A session recorded in the archive's index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// ID of the session
+    pub id: String,
+
+    /// When the session was saved
+    pub saved_at: DateTime<Local>,
+
+    /// Number of turns of the conversation
+    pub turns: usize,
+
+    /// First line of the first prompt, shortened
+    pub title: String,
+}
+
+/// Sizes of a session before and after it was archived, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Savings {
+    /// Size of the saved session
+    pub original: u64,
+
+    /// Size of its compressed copy
+    pub compressed: u64,
+}
+
+/// The compressed sessions in a directory and their index
+#[derive(Debug, Clone)]
+pub struct Archive {
+    dir: PathBuf,
+}
+
+impl Archive {
+    /// The archive in `dir`, which is created when the first session is added
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory the archive is kept in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file an archived session is kept in, e.g. `20250101-120000.json.zst`
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json.{}", id, EXTENSION))
+    }
+
+    /// The archived sessions, in the order they were archived
+    ///
+    /// An archive whose index does not exist yet is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be read.
+    pub fn index(&self) -> Result<Vec<ArchiveEntry>> {
+        let path = self.dir.join(INDEX_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        serde_json::from_str(&text).with_context(|| format!("Invalid archive index {}", path.display()))
+    }
+
+    /// Compress the session file `path` into the archive as `entry`, and
+    /// remove the file
+    ///
+    /// The index is written before the file is removed, so an interrupted
+    /// run leaves the session in place rather than losing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or compressed, or the
+    /// archive cannot be written.
+    pub fn add(&self, path: &Path, entry: ArchiveEntry) -> Result<Savings> {
+        let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let compressed = compress(&data)?;
+        paths::create_private_dir(&self.dir).with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let archived = self.path(&entry.id);
+        paths::write_private(&archived, &compressed).with_context(|| format!("Failed to write {}", archived.display()))?;
+
+        let mut index = self.index()?;
+        index.retain(|existing| existing.id != entry.id);
+        index.push(entry);
+        self.write_index(&index)?;
+
+        fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        Ok(Savings {
+            original: data.len() as u64,
+            compressed: compressed.len() as u64,
+        })
+    }
+
+    /// Remove the archived session `id`
+    ///
+    /// # Returns
+    ///
+    /// Whether the archive held the session
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index or the file cannot be updated.
+    pub fn remove(&self, id: &str) -> Result<bool> {
+        let mut index = self.index()?;
+        let before = index.len();
+        index.retain(|entry| entry.id != id);
+        if index.len() == before {
+            return Ok(false);
+        }
+        self.write_index(&index)?;
+
+        let path = self.path(id);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to delete {}", path.display()))
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn write_index(&self, index: &[ArchiveEntry]) -> Result<()> {
+        let path = self.dir.join(INDEX_FILE);
+        let json = serde_json::to_string_pretty(index).expect("archive entries serialize to JSON");
+        paths::write_private(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Compress data with zstd
+///
+/// # Errors
+///
+/// Returns an error if compression fails.
+#[cfg(feature = "archive")]
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::encode_all(data, LEVEL).context("Failed to compress")
+}
+
+/// Compress data with zstd
+///
+/// # Errors
+///
+/// Always: this build has no archive support.
+#[cfg(not(feature = "archive"))]
+pub fn compress(_data: &[u8]) -> Result<Vec<u8>> {
+    bail!("This build of claude-dialog cannot archive sessions (the `archive` feature)")
+}
+
+/// Decompress zstd data
+///
+/// # Errors
+///
+/// Returns an error if the data is not zstd-compressed.
+#[cfg(feature = "archive")]
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::decode_all(data).context("Not a zstd-compressed file")
+}
+
+/// Decompress zstd data
+///
+/// # Errors
+///
+/// Always: this build has no archive support.
+#[cfg(not(feature = "archive"))]
+pub fn decompress(_data: &[u8]) -> Result<Vec<u8>> {
+    bail!("This build of claude-dialog cannot read archived sessions (the `archive` feature)")
+}
+
+/// Parse an age such as `30d`, `2w` or `12h`
+///
+/// # Errors
+///
+/// Returns an error if the text is not a whole number followed by `h`, `d`
+/// or `w`.
+pub fn parse_age(text: &str) -> Result<TimeDelta> {
+    let text = text.trim();
+    let split = text.len() - text.chars().last().map_or(0, char::len_utf8);
+    let (number, unit) = text.split_at(split);
+    let Ok(number) = number.parse::<i64>() else {
+        bail!("Expected an age like 30d, 2w or 12h, got '{}'", text);
+    };
+    let age = match unit {
+        "h" => TimeDelta::try_hours(number),
+        "d" => TimeDelta::try_days(number),
+        "w" => TimeDelta::try_weeks(number),
+        _ => None,
+    };
+    match age {
+        Some(age) if number >= 0 => Ok(age),
+        _ => bail!("Expected an age like 30d, 2w or 12h, got '{}'", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ages() {
+        assert_eq!(parse_age("12h").unwrap(), TimeDelta::hours(12));
+        assert_eq!(parse_age(" 0d ").unwrap(), TimeDelta::zero());
+        for invalid in ["", "d", "-1d", "30x", "1.5d", "30 days"] {
+            assert!(parse_age(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_add_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = dir.path().join("20250101-120000.json");
+        fs::write(&session, "{\"turns\":[]}".repeat(100)).unwrap();
+        let archive = Archive::new(dir.path().join("archive"));
+        let entry = ArchiveEntry {
+            id: "20250101-120000".to_string(),
+            saved_at: Local::now(),
+            turns: 0,
+            title: String::new(),
+        };
+
+        let savings = archive.add(&session, entry.clone()).unwrap();
+        assert!(!session.exists());
+        assert!(savings.compressed < savings.original);
+        assert_eq!(archive.index().unwrap(), [entry]);
+        assert_eq!(decompress(&fs::read(archive.path("20250101-120000")).unwrap()).unwrap().len() as u64, savings.original);
+
+        assert!(archive.remove("20250101-120000").unwrap());
+        assert!(!archive.remove("20250101-120000").unwrap());
+        assert!(archive.index().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+//! File attachment module
+//!
+//! `/attach <path>` and `--attach <path>` read a whole file and send it with
+//! the next prompt, under a header naming the file and in a fenced code
+//! block. Unlike `@file` references, attachments need no mention in the
+//! prompt text, which makes them handy for handing Claude the file under
+//! review before asking about it.
+//!
+//! Only text files up to [`MAX_ATTACHMENT_BYTES`] can be attached; binary
+//! files are recognized by NUL bytes or content that is not UTF-8.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::attach::Attachment;
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! let path = dir.path().join("main.rs");
+//! std::fs::write(&path, "fn main() {}\n").unwrap();
+//!
+//! let attachment = Attachment::read(&path).unwrap();
+//! assert_eq!(attachment.lines(), 1);
+//! assert!(attachment.render().ends_with("main.rs:\n\n```rs\nfn main() {}\n```"));
+//!
+//! std::fs::write(&path, b"\x7fELF\0\0").unwrap();
+//! assert!(Attachment::read(&path).is_err());
+//! ```
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Largest file that can be attached, in bytes
+pub const MAX_ATTACHMENT_BYTES: u64 = 256 * 1024;
+
+/// A text file to send with the next prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    path: PathBuf,
+    text: String,
+}
+
+impl Attachment {
+    /// Read the file at `path` for attaching
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is larger than
+    /// [`MAX_ATTACHMENT_BYTES`] or looks like a binary file.
+    pub fn read(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path).with_context(|| format!("Cannot attach {}", path.display()))?;
+        if metadata.is_dir() {
+            bail!("Cannot attach {}: it is a directory", path.display());
+        }
+        if metadata.len() > MAX_ATTACHMENT_BYTES {
+            bail!(
+                "Cannot attach {}: it has {} KB, more than the {} KB limit; include parts of it with @file#selector instead",
+                path.display(),
+                metadata.len().div_ceil(1024),
+                MAX_ATTACHMENT_BYTES / 1024
+            );
+        }
+
+        let bytes = fs::read(path).with_context(|| format!("Cannot attach {}", path.display()))?;
+        let text = match String::from_utf8(bytes) {
+            Ok(text) if !text.contains('\0') => text,
+            _ => bail!("Cannot attach {}: it looks like a binary file", path.display()),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            text,
+        })
+    }
+
+    /// The attached file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of lines of the file
+    pub fn lines(&self) -> usize {
+        self.text.lines().count()
+    }
+
+    /// The file as sent with the prompt: a header naming it and its content
+    /// in a fenced code block labelled with its extension
+    ///
+    /// The fence is longer than any run of backticks in the file, so code
+    /// blocks inside Markdown files cannot end it early.
+    ///
+    /// # Output Format
+    ///
+    /// ````text
+    /// Attached file src/main.rs:
+    ///
+    /// ```rs
+    /// fn main() {}
+    /// ```
+    /// ````
+    pub fn render(&self) -> String {
+        let language = self.path.extension().map(|ext| ext.to_string_lossy()).unwrap_or_default();
+        let fence = "`".repeat(longest_backtick_run(&self.text).max(2) + 1);
+        let mut text = self.text.clone();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        format!(
+            "Attached file {}:\n\n{}{}\n{}{}",
+            self.path.display(),
+            fence,
+            language,
+            text,
+            fence
+        )
+    }
+}
+
+/// Length of the longest run of backticks in a text
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_outlasts_backticks_in_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("README");
+        fs::write(&path, "Run:\n\n```sh\nmake\n```").unwrap();
+
+        let rendered = Attachment::read(&path).unwrap().render();
+        assert!(rendered.ends_with("README:\n\n````\nRun:\n\n```sh\nmake\n```\n````"));
+    }
+
+    #[test]
+    fn test_size_and_kind_guards() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        fs::write(&path, "x".repeat(MAX_ATTACHMENT_BYTES as usize + 1)).unwrap();
+        assert!(Attachment::read(&path).unwrap_err().to_string().contains("more than the 256 KB limit"));
+
+        let path = dir.path().join("latin1.txt");
+        fs::write(&path, b"caf\xe9").unwrap();
+        assert!(Attachment::read(&path).unwrap_err().to_string().ends_with("looks like a binary file"));
+
+        assert!(Attachment::read(dir.path()).unwrap_err().to_string().ends_with("it is a directory"));
+    }
+
+    #[test]
+    fn test_longest_backtick_run() {
+        assert_eq!(longest_backtick_run("no ticks"), 0);
+        assert_eq!(longest_backtick_run("`a` ``b``"), 2);
+    }
+}
@@ -0,0 +1,494 @@
+//! Batch module
+//!
+//! `--batch <file>` runs the prompts of a file one after another in the same
+//! session, as if they had been typed at the prompt, and writes every
+//! response to a JSON report, for scripted evaluation and regression runs
+//! of prompt files.
+//!
+//! # Prompt Files
+//!
+//! A `.yaml` or `.yml` file holds a list of prompts, which may span several
+//! lines:
+//!
+//! ```yaml
+//! - Summarize @CHANGELOG.md
+//! - |
+//!   Review this function:
+//!   fn add(a: i32, b: i32) -> i32 { a - b }
+//! ```
+//!
+//! Any other file has one prompt per line; blank lines and lines starting
+//! with `#` are skipped.
+//!
+//! # Report Format
+//!
+//! ```json
+//! {
+//!   "format": "claude-dialog-batch",
+//!   "source": "prompts.yaml",
+//!   "started_at": "2025-01-01T12:00:00Z",
+//!   "model": "opus",
+//!   "results": [
+//!     { "prompt": "Summarize @CHANGELOG.md", "response": "...", "exit_code": 0, "duration_ms": 5210 }
+//!   ]
+//! }
+//! ```
+//!
+//! A prompt that could not be sent, e.g. because a selected line range is
+//! outside its file, has an `error` instead of a response. The report is
+//! rewritten after every prompt, so an interrupted run keeps the results so
+//! far.
+//!
+//! # Repeated Runs
+//!
+//! `--repeat <n>` runs the whole file `n` times, each run in a new
+//! conversation, to see how stable the answers to its prompts are. Every
+//! result records its `run`, and once the second run is complete the report
+//! gains a `stability` entry per prompt: how many runs answered it, the
+//! spread of the responses' lengths and output tokens, and how far the
+//! responses are apart, as the word-level edit distance of every pair of
+//! them relative to the longer one, from 0 for identical responses to 1 for
+//! responses without a word in common:
+//!
+//! ```json
+//! "stability": [
+//!   {
+//!     "prompt": "Name a prime",
+//!     "runs": 3,
+//!     "length": { "min": 2, "max": 31, "mean": 12.0 },
+//!     "output_tokens": { "min": 3, "max": 12, "mean": 6.3 },
+//!     "mean_distance": 0.67,
+//!     "max_distance": 1.0
+//!   }
+//! ]
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::batch::parse_prompts;
+//!
+//! let prompts = parse_prompts("# smoke tests\nWhat is 2 + 2?\n\nName a prime\n", false).unwrap();
+//! assert_eq!(prompts, ["What is 2 + 2?", "Name a prime"]);
+//!
+//! let prompts = parse_prompts("- What is 2 + 2?\n- |\n  Two\n  lines\n", true).unwrap();
+//! assert_eq!(prompts, ["What is 2 + 2?", "Two\nlines"]);
+//! ```
+
+use anyhow::{Result, Context, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::status::Usage;
+
+/// Value of the `format` field identifying a batch report
+pub const FORMAT: &str = "claude-dialog-batch";
+
+/// The outcome of one prompt of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// The prompt as written in the file
+    pub prompt: String,
+
+    /// The response, also of a command that failed; `None` if the prompt
+    /// was not sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+
+    /// Exit code of the backend command, `None` if it was not run or was
+    /// terminated by a signal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// Why the prompt was not sent or the command failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// How long the prompt took, in milliseconds
+    pub duration_ms: u64,
+
+    /// Tokens and cost of the response, if the backend reported them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+
+    /// Which run of `--repeat` the result is from, counting from 1; `None`
+    /// for a single run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<usize>,
+}
+
+impl BatchResult {
+    /// Whether the prompt was answered by a successful command
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.exit_code == Some(0)
+    }
+}
+
+/// The results of a batch run, as written to the report file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchReport {
+    /// Always [`FORMAT`]
+    pub format: String,
+
+    /// The prompt file
+    pub source: PathBuf,
+
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+
+    /// Model the prompts were sent to, `None` for the backend's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// One result per prompt run so far, in file order, run after run
+    pub results: Vec<BatchResult>,
+
+    /// How much the responses to each prompt varied between the runs of
+    /// `--repeat`, in file order; see [`stability`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stability: Vec<Stability>,
+}
+
+/// The smallest, largest and mean of a set of numbers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Spread {
+    /// The smallest number
+    pub min: u64,
+
+    /// The largest number
+    pub max: u64,
+
+    /// The mean of the numbers
+    pub mean: f64,
+}
+
+impl Spread {
+    /// The spread of some numbers, `None` if there are none
+    pub fn of(numbers: &[u64]) -> Option<Self> {
+        Some(Self {
+            min: *numbers.iter().min()?,
+            max: *numbers.iter().max()?,
+            mean: numbers.iter().sum::<u64>() as f64 / numbers.len() as f64,
+        })
+    }
+}
+
+/// How much the responses to one prompt varied between runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stability {
+    /// The prompt as written in the file
+    pub prompt: String,
+
+    /// Number of runs that answered the prompt successfully
+    pub runs: usize,
+
+    /// Lengths of the responses, in characters
+    pub length: Spread,
+
+    /// Output tokens of the responses, `None` unless every response
+    /// reported its usage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<Spread>,
+
+    /// Mean distance between two responses, from 0 for identical ones to 1
+    /// for ones without a word in common; `None` with fewer than two
+    /// responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_distance: Option<f64>,
+
+    /// Largest distance between two responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_distance: Option<f64>,
+}
+
+impl BatchReport {
+    /// Start the report of a run of the prompts in `source`
+    pub fn new(source: &Path, model: Option<&str>) -> Self {
+        Self {
+            format: FORMAT.to_string(),
+            source: source.to_path_buf(),
+            started_at: Utc::now(),
+            model: model.map(String::from),
+            results: Vec::new(),
+            stability: Vec::new(),
+        }
+    }
+
+    /// Add the result of a prompt
+    pub fn push(&mut self, result: BatchResult) {
+        self.results.push(result);
+    }
+
+    /// Number of prompts that were not answered by a successful command
+    pub fn failures(&self) -> usize {
+        self.results.iter().filter(|result| !result.is_success()).count()
+    }
+
+    /// Write the report to a file as pretty-printed JSON, replacing it if
+    /// it exists
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self).expect("batch reports serialize to JSON");
+        fs::write(path, json).with_context(|| format!("Failed to write batch report: {}", path.display()))
+    }
+}
+
+/// How much the responses to each prompt varied between runs
+///
+/// The results of a run follow those of the run before, in file order, as
+/// in [`BatchReport::results`]. Only successful responses are compared;
+/// prompts no run answered are left out.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::batch::{BatchResult, stability};
+///
+/// let result = |run, response: &str| BatchResult {
+///     prompt: "Name a prime".to_string(),
+///     response: Some(response.to_string()),
+///     exit_code: Some(0),
+///     error: None,
+///     duration_ms: 0,
+///     usage: None,
+///     run: Some(run),
+/// };
+/// let results = [result(1, "7"), result(2, "7"), result(3, "Seven is prime")];
+///
+/// let stability = stability(&results);
+/// assert_eq!(stability[0].runs, 3);
+/// assert_eq!((stability[0].length.min, stability[0].length.max), (1, 14));
+/// assert_eq!(stability[0].max_distance, Some(1.0));
+/// ```
+pub fn stability(results: &[BatchResult]) -> Vec<Stability> {
+    // The results of each prompt, by its position in a run
+    let mut prompts: Vec<Vec<&BatchResult>> = Vec::new();
+    let mut run = None;
+    let mut position = 0;
+    for result in results {
+        if result.run != run {
+            run = result.run;
+            position = 0;
+        }
+        if prompts.len() <= position {
+            prompts.push(Vec::new());
+        }
+        prompts[position].push(result);
+        position += 1;
+    }
+
+    prompts
+        .into_iter()
+        .filter_map(|results| {
+            let answered: Vec<&BatchResult> = results.into_iter().filter(|result| result.is_success()).collect();
+            let responses: Vec<&str> = answered.iter().filter_map(|result| result.response.as_deref()).collect();
+            let lengths: Vec<u64> = responses.iter().map(|response| response.chars().count() as u64).collect();
+            let tokens: Option<Vec<u64>> = answered
+                .iter()
+                .map(|result| result.usage.map(|usage| usage.output_tokens))
+                .collect();
+
+            let words: Vec<Vec<&str>> = responses.iter().map(|response| response.split_whitespace().collect()).collect();
+            let mut distances = Vec::new();
+            for (index, a) in words.iter().enumerate() {
+                for b in &words[index + 1..] {
+                    distances.push(distance(a, b));
+                }
+            }
+
+            Some(Stability {
+                prompt: answered.first()?.prompt.clone(),
+                runs: answered.len(),
+                length: Spread::of(&lengths)?,
+                output_tokens: tokens.as_deref().and_then(Spread::of),
+                mean_distance: (!distances.is_empty()).then(|| distances.iter().sum::<f64>() / distances.len() as f64),
+                max_distance: distances.iter().copied().reduce(f64::max),
+            })
+        })
+        .collect()
+}
+
+/// The word-level edit distance of two responses relative to the longer
+/// one, from 0 for the same words to 1 for none in common
+fn distance(a: &[&str], b: &[&str]) -> f64 {
+    let longer = a.len().max(b.len());
+    if longer == 0 {
+        return 0.0;
+    }
+    // Two rows of the edit distance table are enough for its last cell
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, word) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, other) in b.iter().enumerate() {
+            let cost = usize::from(word != other);
+            current[j + 1] = (previous[j + 1] + 1).min(current[j] + 1).min(previous[j] + cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()] as f64 / longer as f64
+}
+
+/// The stability of the prompts as aligned lines, one per prompt
+///
+/// # Output Format
+///
+/// ```text
+/// #1  3 runs  length 2-31 (mean 12)  tokens 3-12  distance 0.67 (max 1.00)  Name a prime
+/// ```
+pub fn stability_table(stability: &[Stability]) -> String {
+    stability
+        .iter()
+        .enumerate()
+        .map(|(index, prompt)| {
+            let tokens = prompt
+                .output_tokens
+                .map(|tokens| format!("  tokens {}-{}", tokens.min, tokens.max))
+                .unwrap_or_default();
+            let distance = match (prompt.mean_distance, prompt.max_distance) {
+                (Some(mean), Some(max)) => format!("  distance {:.2} (max {:.2})", mean, max),
+                _ => String::new(),
+            };
+            format!(
+                "#{}  {} run(s)  length {}-{} (mean {:.0}){}{}  {}",
+                index + 1,
+                prompt.runs,
+                prompt.length.min,
+                prompt.length.max,
+                prompt.length.mean,
+                tokens,
+                distance,
+                prompt.prompt.lines().next().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Read the prompts of a prompt file
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is not a valid prompt
+/// file, or holds no prompts.
+pub fn load_prompts(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read prompt file: {}", path.display()))?;
+    let yaml = matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml"));
+    let prompts = parse_prompts(&text, yaml).with_context(|| format!("Invalid prompt file {}", path.display()))?;
+    if prompts.is_empty() {
+        bail!("The prompt file {} has no prompts", path.display());
+    }
+    Ok(prompts)
+}
+
+/// Parse the prompts of a prompt file, a YAML list or one prompt per line
+///
+/// Prompts are trimmed; empty ones are dropped.
+///
+/// # Errors
+///
+/// Returns an error if `yaml` is set and the text is not a list of strings.
+pub fn parse_prompts(text: &str, yaml: bool) -> Result<Vec<String>> {
+    let prompts: Vec<String> = if yaml {
+        serde_yaml_ng::from_str::<Option<Vec<String>>>(text)
+            .context("Expected a YAML list of prompts")?
+            .unwrap_or_default()
+    } else {
+        text.lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .map(String::from)
+            .collect()
+    };
+    Ok(prompts
+        .into_iter()
+        .map(|prompt| prompt.trim().to_string())
+        .filter(|prompt| !prompt.is_empty())
+        .collect())
+}
+
+/// Report file for a prompt file without `--batch-output`, next to it:
+/// `prompts.yaml` is reported in `prompts.results.json`
+pub fn default_output(source: &Path) -> PathBuf {
+    let stem = source.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    source.with_file_name(format!("{}.results.json", stem))
+}
+
+/// Milliseconds of a duration, for [`BatchResult::duration_ms`]
+pub fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_yaml() {
+        assert!(parse_prompts("prompt: one", true).is_err());
+        assert!(parse_prompts("- [nested]", true).is_err());
+        assert!(parse_prompts("", true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_load_rejects_files_without_prompts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.txt");
+        fs::write(&path, "# nothing yet\n\n").unwrap();
+        assert!(load_prompts(&path).unwrap_err().to_string().contains("has no prompts"));
+    }
+
+    #[test]
+    fn test_stability_by_prompt() {
+        let result = |run, prompt: &str, response: &str, tokens| BatchResult {
+            prompt: prompt.to_string(),
+            response: Some(response.to_string()),
+            exit_code: Some(0),
+            error: None,
+            duration_ms: 0,
+            usage: Some(Usage { output_tokens: tokens, ..Default::default() }),
+            run: Some(run),
+        };
+        let failed = BatchResult { exit_code: Some(1), ..result(2, "Name a prime", "", 0) };
+        let results = [
+            result(1, "Name a prime", "2 is prime", 4),
+            result(1, "Why?", "Only one and itself divide it", 9),
+            failed,
+            result(2, "Why?", "Only one and itself divide it", 7),
+            result(3, "Name a prime", "3 is prime", 4),
+        ];
+
+        let stability = stability(&results);
+        assert_eq!(stability.len(), 2);
+        assert_eq!(stability[0].runs, 2);
+        assert_eq!(stability[0].mean_distance, Some(1.0 / 3.0));
+        assert_eq!(stability[1].output_tokens, Some(Spread { min: 7, max: 9, mean: 8.0 }));
+        assert_eq!(stability[1].max_distance, Some(0.0));
+        assert_eq!(
+            stability_table(&stability).lines().nth(1).unwrap(),
+            "#2  2 run(s)  length 29-29 (mean 29)  tokens 7-9  distance 0.00 (max 0.00)  Why?"
+        );
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance(&[], &[]), 0.0);
+        assert_eq!(distance(&["a", "b", "c", "d"], &["a", "x", "c"]), 0.5);
+        assert_eq!(distance(&["a"], &["b", "c"]), 1.0);
+    }
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(default_output(Path::new("evals/prompts.yaml")), Path::new("evals/prompts.results.json"));
+        assert_eq!(default_output(Path::new("prompts")), Path::new("prompts.results.json"));
+    }
+}
@@ -0,0 +1,409 @@
+//! Session recording module
+//!
+//! `--record-cast <file.cast>` records a session as an
+//! [asciinema v2](https://docs.asciinema.org/manual/asciicast/v2/) cast: a
+//! JSON header with the terminal size, followed by one line per piece of
+//! output with the time it appeared. Casts can be played back with
+//! `asciinema play` and embedded in docs and blog posts with the asciinema
+//! player.
+//!
+//! While recording, the output of the session goes through a
+//! pseudo-terminal, so it is rendered exactly as in the terminal itself, and
+//! is passed on to the terminal as it arrives. What is typed is recorded as
+//! it is echoed at the prompt. When standard output is not a terminal, it is
+//! recorded through a pipe at a size of 80×24, the size also used for
+//! terminals that report none.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::cast::{CastHeader, CastWriter};
+//! use std::time::Duration;
+//!
+//! let mut cast = CastWriter::new(Vec::new(), &CastHeader::new(100, 30)).unwrap();
+//! cast.output(Duration::from_millis(1500), "Claude> ").unwrap();
+//!
+//! let text = String::from_utf8(cast.into_inner()).unwrap();
+//! let mut lines = text.lines();
+//! assert!(lines.next().unwrap().starts_with(r#"{"version":2,"width":100,"height":30,"#));
+//! assert_eq!(lines.next(), Some(r#"[1.500000,"o","Claude> "]"#));
+//! ```
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Terminal size of recordings whose output is not a terminal
+pub const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// Header line of an asciinema v2 cast
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CastHeader {
+    /// Format version, always 2
+    pub version: u8,
+
+    /// Terminal width in columns
+    pub width: u16,
+
+    /// Terminal height in rows
+    pub height: u16,
+
+    /// Unix time the recording started
+    pub timestamp: i64,
+
+    /// `SHELL` and `TERM` of the recorded terminal, if they are set
+    pub env: BTreeMap<String, String>,
+
+    /// Title shown by players
+    pub title: String,
+}
+
+impl CastHeader {
+    /// Header of a recording of a terminal of the given size, starting now
+    pub fn new(width: u16, height: u16) -> Self {
+        let env = ["SHELL", "TERM"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok().map(|value| (name.to_string(), value)))
+            .collect();
+        Self {
+            version: 2,
+            width,
+            height,
+            timestamp: chrono::Utc::now().timestamp(),
+            env,
+            title: "claude-dialog".to_string(),
+        }
+    }
+}
+
+/// Writes the events of a cast
+#[derive(Debug)]
+pub struct CastWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> CastWriter<W> {
+    /// Start a cast by writing its header
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be written.
+    pub fn new(mut out: W, header: &CastHeader) -> io::Result<Self> {
+        serde_json::to_writer(&mut out, header)?;
+        out.write_all(b"\n")?;
+        Ok(Self { out })
+    }
+
+    /// Record output shown `at` the given time since the recording started
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event cannot be written.
+    pub fn output(&mut self, at: Duration, data: &str) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.event(at, "o", data)
+    }
+
+    /// Record that the terminal was resized
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event cannot be written.
+    pub fn resize(&mut self, at: Duration, width: u16, height: u16) -> io::Result<()> {
+        self.event(at, "r", &format!("{}x{}", width, height))
+    }
+
+    /// Flush the events written so far
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cast cannot be written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// The destination of the cast
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn event(&mut self, at: Duration, kind: &str, data: &str) -> io::Result<()> {
+        let data = serde_json::to_string(data)?;
+        writeln!(self.out, "[{:.6},\"{}\",{}]", at.as_secs_f64(), kind, data)
+    }
+}
+
+#[cfg(unix)]
+pub use recorder::Recorder;
+
+#[cfg(unix)]
+mod recorder {
+    use anyhow::{Result, Context, anyhow};
+    use rustix::fs::OFlags;
+    use rustix::pty::{self, OpenptFlags};
+    use rustix::stdio;
+    use rustix::termios::{self, OptionalActions, Winsize};
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, BufWriter, IsTerminal, Read, Write};
+    use std::os::fd::{AsFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+    use std::time::{Duration, Instant};
+    use super::{CastHeader, CastWriter, DEFAULT_SIZE};
+    use crate::pipeline::Utf8Decoder;
+
+    type SharedCast = Arc<Mutex<CastWriter<BufWriter<File>>>>;
+
+    /// How often the terminal's size is compared with the recording's
+    const RESIZE_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Records the output of the process to a cast until it is finished
+    ///
+    /// Standard output, and standard error when it is a terminal, are
+    /// redirected while recording; [`Recorder::finish`] restores them.
+    #[derive(Debug)]
+    pub struct Recorder {
+        path: PathBuf,
+        original_stdout: Option<OwnedFd>,
+        original_stderr: Option<OwnedFd>,
+        copier: Option<JoinHandle<io::Result<()>>>,
+        resizer: Option<(Arc<AtomicBool>, JoinHandle<()>)>,
+    }
+
+    impl Recorder {
+        /// Start recording to a cast file
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the file cannot be created, or the output
+        /// cannot be redirected.
+        pub fn start(path: &Path) -> Result<Self> {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create the recording: {}", path.display()))?;
+            io::stdout().flush()?;
+            io::stderr().flush()?;
+
+            let original_stdout = io::stdout().as_fd().try_clone_to_owned()?;
+            let path = path.to_path_buf();
+            let result = if io::stdout().is_terminal() {
+                Self::start_terminal(path.clone(), file, original_stdout)
+            } else {
+                Self::start_pipe(path.clone(), file, original_stdout)
+            };
+            result.with_context(|| format!("Failed to record to {}", path.display()))
+        }
+
+        /// Record through a pseudo-terminal of the terminal's size
+        fn start_terminal(path: PathBuf, file: File, original_stdout: OwnedFd) -> Result<Self> {
+            let mut size = termios::tcgetwinsize(&original_stdout)?;
+            if size.ws_col == 0 || size.ws_row == 0 {
+                // Some terminals, such as those of editors, report no size
+                (size.ws_col, size.ws_row) = DEFAULT_SIZE;
+            }
+            let master = pty::openpt(OpenptFlags::RDWR | OpenptFlags::NOCTTY | OpenptFlags::CLOEXEC)?;
+            pty::grantpt(&master)?;
+            pty::unlockpt(&master)?;
+            let name = pty::ptsname(&master, Vec::new())?;
+            let slave = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags(OFlags::NOCTTY.bits() as i32)
+                .open(std::ffi::OsStr::from_bytes(name.as_bytes()))?;
+
+            // The session sees the same terminal, only through the recording
+            if let Ok(settings) = termios::tcgetattr(&original_stdout) {
+                let _ = termios::tcsetattr(&slave, OptionalActions::Now, &settings);
+            }
+            termios::tcsetwinsize(&slave, size)?;
+
+            let cast = shared_cast(file, size.ws_col, size.ws_row)?;
+            let started = Instant::now();
+            let master = File::from(master);
+            let stop = Arc::new(AtomicBool::new(false));
+            let resizer = spawn_resizer(original_stdout.try_clone()?, master.try_clone()?, cast.clone(), started, stop.clone());
+            let copier = spawn_copier(master, File::from(original_stdout.try_clone()?), cast, started, false);
+
+            let original_stderr = if io::stderr().is_terminal() {
+                let original = io::stderr().as_fd().try_clone_to_owned()?;
+                stdio::dup2_stderr(&slave)?;
+                Some(original)
+            } else {
+                None
+            };
+            stdio::dup2_stdout(&slave)?;
+
+            Ok(Self {
+                path,
+                original_stdout: Some(original_stdout),
+                original_stderr,
+                copier: Some(copier),
+                resizer: Some((stop, resizer)),
+            })
+        }
+
+        /// Record through a pipe, for output that is not a terminal
+        fn start_pipe(path: PathBuf, file: File, original_stdout: OwnedFd) -> Result<Self> {
+            let (reader, writer) = io::pipe()?;
+            let cast = shared_cast(file, DEFAULT_SIZE.0, DEFAULT_SIZE.1)?;
+            let reader = File::from(OwnedFd::from(reader));
+            let copier = spawn_copier(reader, File::from(original_stdout.try_clone()?), cast, Instant::now(), true);
+            stdio::dup2_stdout(&writer)?;
+
+            Ok(Self {
+                path,
+                original_stdout: Some(original_stdout),
+                original_stderr: None,
+                copier: Some(copier),
+                resizer: None,
+            })
+        }
+
+        /// Stop recording, restore the output and finish writing the cast
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the cast could not be written completely.
+        pub fn finish(mut self) -> Result<()> {
+            self.stop()
+        }
+
+        fn stop(&mut self) -> Result<()> {
+            let _ = io::stdout().flush();
+            let _ = io::stderr().flush();
+            if let Some((stop, resizer)) = self.resizer.take() {
+                stop.store(true, Ordering::Relaxed);
+                let _ = resizer.join();
+            }
+            if let Some(original) = self.original_stderr.take() {
+                stdio::dup2_stderr(&original)?;
+            }
+            if let Some(original) = self.original_stdout.take() {
+                stdio::dup2_stdout(&original)?;
+            }
+
+            // The copier ends once nothing writes to the recording any more
+            let Some(copier) = self.copier.take() else {
+                return Ok(());
+            };
+            copier
+                .join()
+                .map_err(|_| anyhow!("Recording stopped unexpectedly"))?
+                .with_context(|| format!("Failed to write the recording: {}", self.path.display()))
+        }
+    }
+
+    impl Drop for Recorder {
+        fn drop(&mut self) {
+            let _ = self.stop();
+        }
+    }
+
+    fn shared_cast(file: File, width: u16, height: u16) -> Result<SharedCast> {
+        let cast = CastWriter::new(BufWriter::new(file), &CastHeader::new(width, height))?;
+        Ok(Arc::new(Mutex::new(cast)))
+    }
+
+    /// Pass the recorded output on to `terminal`, adding it to the cast
+    ///
+    /// Output from a pipe has bare line feeds, which are recorded as a
+    /// terminal shows them with `translate_newlines`. Output keeps being
+    /// passed on after the cast cannot be written; the first error is
+    /// returned at the end.
+    fn spawn_copier(
+        mut source: File,
+        mut terminal: File,
+        cast: SharedCast,
+        started: Instant,
+        translate_newlines: bool,
+    ) -> JoinHandle<io::Result<()>> {
+        std::thread::spawn(move || {
+            let mut decoder = Utf8Decoder::default();
+            let mut buffer = [0u8; 8192];
+            let mut result = Ok(());
+            loop {
+                // A pseudo-terminal reports an error instead of the end once it is closed
+                let read = source.read(&mut buffer).unwrap_or(0);
+                let _ = terminal.write_all(&buffer[..read]);
+                let _ = terminal.flush();
+
+                let mut chunk = if read == 0 { decoder.finish() } else { decoder.decode(&buffer[..read]) };
+                if translate_newlines {
+                    chunk = chunk.replace('\n', "\r\n");
+                }
+                if result.is_ok() {
+                    let mut cast = cast.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    result = cast.output(started.elapsed(), &chunk);
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+            let mut cast = cast.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            result.and_then(|_| cast.flush())
+        })
+    }
+
+    /// Give the pseudo-terminal the terminal's new size whenever it is
+    /// resized, recording the change, until `stop` is set
+    ///
+    /// The size is polled rather than followed through `SIGWINCH`, which the
+    /// line editor takes over while it reads a prompt.
+    fn spawn_resizer(terminal: OwnedFd, master: File, cast: SharedCast, started: Instant, stop: Arc<AtomicBool>) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            let mut last = termios::tcgetwinsize(&master).ok();
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(RESIZE_INTERVAL);
+                let Ok(size) = termios::tcgetwinsize(&terminal) else {
+                    continue;
+                };
+                let size = Winsize { ws_xpixel: 0, ws_ypixel: 0, ..size };
+                let changed = last.is_none_or(|last| (last.ws_col, last.ws_row) != (size.ws_col, size.ws_row));
+                if size.ws_col == 0 || size.ws_row == 0 || !changed {
+                    continue;
+                }
+                if termios::tcsetwinsize(&master, size).is_ok() {
+                    last = Some(size);
+                    let mut cast = cast.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    let _ = cast.resize(started.elapsed(), size.ws_col, size.ws_row);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_escape_control_characters() {
+        let mut cast = CastWriter::new(Vec::new(), &CastHeader::new(80, 24)).unwrap();
+        cast.output(Duration::from_secs(2), "\x1b[1mbold\x1b[0m \"quoted\"\r\n").unwrap();
+        cast.output(Duration::from_secs(3), "").unwrap();
+        cast.resize(Duration::from_secs(4), 120, 40).unwrap();
+
+        let text = String::from_utf8(cast.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], r#"[2.000000,"o","\u001b[1mbold\u001b[0m \"quoted\"\r\n"]"#);
+        assert_eq!(lines[2], r#"[4.000000,"r","120x40"]"#);
+    }
+
+    #[test]
+    fn test_header_is_asciicast_v2() {
+        let header: serde_json::Value = serde_json::to_value(CastHeader::new(80, 24)).unwrap();
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 80);
+        assert_eq!(header["height"], 24);
+        assert!(header["timestamp"].as_i64().unwrap() > 0);
+        assert!(header["env"].is_object());
+    }
+}
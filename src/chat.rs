@@ -0,0 +1,391 @@
+//! Stateless chat backend module
+//!
+//! Ollama's chat API and the Anthropic Messages API keep no conversation:
+//! every request carries the messages exchanged so far. [`ChatHistory`]
+//! keeps those messages for the [`crate::ollama`] and [`crate::api`]
+//! executors and runs a request, blocking on its HTTP response, off the
+//! async runtime:
+//!
+//! - A command with [`SessionMode::Fresh`] starts the history over, just
+//!   like the CLI without `--continue`.
+//! - The answer is streamed to the pipeline as it arrives, unless the
+//!   command asks for [`OutputFormat::StreamJson`], in which case it is
+//!   sent once it is complete.
+//! - A turn that is cancelled, with Ctrl+C or by `--timeout`, drops the
+//!   future of the exchange; that stops the pipeline, and the request
+//!   stops reading at its next chunk, so nothing more reaches the terminal.
+//! - Successful exchanges are added to the history. Before a prompt is
+//!   sent, the oldest exchanges are trimmed according to the command's
+//!   [`TrimPolicy`] until the history and the prompt fit the executor's
+//!   budget, so a request never overflows the model's context and the
+//!   history does not grow without bound in a long session; a notice says
+//!   what was trimmed. The budget is the command's `history_budget`
+//!   (`--history-budget`), or what the executor derives from the model,
+//!   e.g. [`HISTORY_BUDGET`] for the Anthropic API.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::chat::{ChatHistory, ChatMessage, HISTORY_BUDGET};
+//! use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeResponse};
+//! use claude_dialog::pipeline::ResponsePipeline;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let history = ChatHistory::new();
+//! let command = ClaudeInvocation { prompt: "Hello".to_string(), options: Default::default() };
+//!
+//! // A stand-in for an HTTP request, answering with the number of messages
+//! let response = history
+//!     .exchange(&command, ResponsePipeline::new(), HISTORY_BUDGET, |messages, _stream| {
+//!         Ok(ClaudeResponse::new(messages.len().to_string()))
+//!     })
+//!     .await?;
+//!
+//! assert_eq!(response.text, "1");
+//! assert_eq!(history.messages(), [ChatMessage::new("user", "Hello"), ChatMessage::new("assistant", "1")]);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, MutexGuard};
+use crate::claude_executor::{ClaudeInvocation, ClaudeResponse, OutputFormat, SessionMode, TrimPolicy};
+use crate::context::estimate_tokens;
+use crate::pipeline::{PipelineCancel, ResponsePipeline};
+use crate::status::Usage;
+use crate::ui::UI;
+
+/// Most tokens, estimated, of the messages sent to a model with a large
+/// context window, such as Claude's, unless `--history-budget` says otherwise
+pub const HISTORY_BUDGET: usize = 100_000;
+
+/// Asks the model to summarize the exchanges trimmed with
+/// [`TrimPolicy::SummarizeOldest`]; the exchanges follow it
+const SUMMARY_PROMPT: &str = "Summarize the following part of our conversation in a few short paragraphs. \
+Keep names, decisions, code identifiers and open questions; leave out pleasantries.";
+
+/// Starts the message carrying the summary of trimmed exchanges
+const SUMMARY_HEADING: &str = "Summary of our earlier conversation:";
+
+/// A message of a chat
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// `system`, `user` or `assistant`
+    pub role: String,
+
+    /// Text of the message
+    pub content: String,
+}
+
+impl ChatMessage {
+    /// A message with the given role
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+/// Where a chat request streams its answer to, as it arrives
+pub struct ChatStream<'a> {
+    pipeline: Option<&'a ResponsePipeline>,
+    cancel: PipelineCancel,
+}
+
+impl<'a> ChatStream<'a> {
+    /// A stream to `pipeline`, or to nowhere for answers sent once they are
+    /// complete, stopped by `cancel`
+    pub fn new(pipeline: Option<&'a ResponsePipeline>, cancel: PipelineCancel) -> Self {
+        Self { pipeline, cancel }
+    }
+
+    /// Send a piece of the answer
+    pub fn send(&self, chunk: &str) {
+        if let Some(pipeline) = self.pipeline {
+            pipeline.send(chunk);
+        }
+    }
+
+    /// Whether the turn was cancelled; the request should stop reading
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+}
+
+/// Cancels a pipeline when dropped, which the future of an exchange is when
+/// its turn is cancelled
+struct CancelOnDrop(PipelineCancel);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// The messages of a conversation with a stateless chat backend
+#[derive(Debug, Default)]
+pub struct ChatHistory {
+    messages: Mutex<Vec<ChatMessage>>,
+}
+
+impl ChatHistory {
+    /// An empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages exchanged so far, oldest first
+    pub fn messages(&self) -> Vec<ChatMessage> {
+        self.lock().clone()
+    }
+
+    /// Send a command with the history and record the exchange
+    ///
+    /// `send` gets the history followed by the command's prompt, and the
+    /// stream to send the answer to as it arrives; it runs on a thread of
+    /// its own, so it may block on the request, and should stop once the
+    /// stream is cancelled. Before that, the history is trimmed to leave
+    /// room for the prompt within the command's `history_budget`, or
+    /// `budget` if it has none; with [`TrimPolicy::SummarizeOldest`], `send`
+    /// is first called to summarize the exchanges trimmed, and the usage of
+    /// that request is returned as the response's `background_usage`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `send`, or an error if the answer cannot be
+    /// written to the pipeline.
+    pub async fn exchange<F>(&self, command: &ClaudeInvocation, pipeline: ResponsePipeline, budget: usize, send: F) -> Result<ClaudeResponse>
+    where
+        F: Fn(Vec<ChatMessage>, &ChatStream) -> Result<ClaudeResponse> + Clone + Send + 'static,
+    {
+        if command.options.session == SessionMode::Fresh {
+            self.lock().clear();
+        }
+        let policy = command.options.trim_policy;
+        let budget = command.options.history_budget.unwrap_or(budget);
+        let prompt_tokens = estimate_tokens(&command.prompt);
+        if prompt_tokens > budget {
+            UI::print_warning(&format!(
+                "The prompt is ~{} tokens, over the conversation budget of {}; the model may not see all of it",
+                prompt_tokens, budget
+            ));
+        }
+        let trimmed = self.trim(budget.saturating_sub(prompt_tokens), policy);
+        let background_usage = if trimmed.is_empty() {
+            None
+        } else {
+            self.trimmed(trimmed, policy, budget, send.clone()).await
+        };
+
+        let mut messages = self.messages();
+        messages.push(ChatMessage::new("user", command.prompt.as_str()));
+        let incremental = command.options.output != OutputFormat::StreamJson;
+
+        // The request thread outlives this future if the turn is cancelled
+        let cancel = pipeline.canceller();
+        let _stop = CancelOnDrop(cancel.clone());
+        let (pipeline, result) = tokio::task::spawn_blocking(move || {
+            let result = send(messages, &ChatStream::new(incremental.then_some(&pipeline), cancel));
+            (pipeline, result)
+        })
+        .await
+        .context("Chat request task failed")?;
+
+        let response = result?;
+        if !incremental {
+            pipeline.send(&response.text);
+        }
+        pipeline.finish().await?;
+
+        if response.is_success() {
+            self.record(&command.prompt, &response.text);
+        }
+        Ok(ClaudeResponse { background_usage, ..response })
+    }
+
+    /// Add an exchange
+    fn record(&self, prompt: &str, answer: &str) {
+        let mut messages = self.lock();
+        messages.push(ChatMessage::new("user", prompt));
+        messages.push(ChatMessage::new("assistant", answer));
+    }
+
+    /// Trim the oldest exchanges until the history holds at most `budget`
+    /// tokens
+    ///
+    /// Returns the messages trimmed; with [`TrimPolicy::KeepPinned`] the
+    /// first exchange is kept.
+    fn trim(&self, budget: usize, policy: TrimPolicy) -> Vec<ChatMessage> {
+        let mut messages = self.lock();
+        let start = if policy == TrimPolicy::KeepPinned { 2 } else { 0 };
+        let mut tokens: usize = messages.iter().map(|message| estimate_tokens(&message.content)).sum();
+        let mut trimmed = Vec::new();
+        while tokens > budget && messages.len() >= start + 2 {
+            let oldest: Vec<ChatMessage> = messages.drain(start..start + 2).collect();
+            tokens -= oldest.iter().map(|message| estimate_tokens(&message.content)).sum::<usize>();
+            trimmed.extend(oldest);
+        }
+        trimmed
+    }
+
+    /// Replace trimmed exchanges with a summary if the policy asks for one,
+    /// and say what was trimmed
+    ///
+    /// Returns the usage of the request for the summary, if it reported one.
+    async fn trimmed<F>(&self, trimmed: Vec<ChatMessage>, policy: TrimPolicy, budget: usize, send: F) -> Option<Usage>
+    where
+        F: Fn(Vec<ChatMessage>, &ChatStream) -> Result<ClaudeResponse> + Send + 'static,
+    {
+        let exchanges = match trimmed.len() / 2 {
+            1 => "the oldest exchange".to_string(),
+            count => format!("the {} oldest exchanges", count),
+        };
+        let budget = format!("to keep it under {} tokens", budget);
+
+        match policy {
+            TrimPolicy::DropOldest => UI::print_info(&format!("Dropped {} of the conversation {}", exchanges, budget)),
+            TrimPolicy::KeepPinned => {
+                UI::print_info(&format!("Dropped {} after the first one of the conversation {}", exchanges, budget));
+            }
+            TrimPolicy::SummarizeOldest => {
+                let (summary, usage) = summarize(&trimmed, send).await;
+                match summary {
+                    Some(summary) => {
+                        // After a pinned first exchange there is none here
+                        self.lock().splice(0..0, [
+                            ChatMessage::new("user", format!("{}\n\n{}", SUMMARY_HEADING, summary)),
+                            ChatMessage::new("assistant", "Understood."),
+                        ]);
+                        UI::print_info(&format!("Summarized {} of the conversation {}", exchanges, budget));
+                    }
+                    None => UI::print_warning(&format!("Could not summarize {} of the conversation; dropped them {}", exchanges, budget)),
+                }
+                return usage;
+            }
+        }
+        None
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<ChatMessage>> {
+        // The history is only changed by whole exchanges, so a poisoned lock
+        // still holds a consistent conversation
+        self.messages.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Have the model summarize `messages`
+///
+/// Returns the summary, or `None` if the model could not write one, and
+/// the usage of the request.
+async fn summarize<F>(messages: &[ChatMessage], send: F) -> (Option<String>, Option<Usage>)
+where
+    F: Fn(Vec<ChatMessage>, &ChatStream) -> Result<ClaudeResponse> + Send + 'static,
+{
+    let conversation: Vec<String> = messages
+        .iter()
+        .map(|message| format!("{}: {}", message.role, message.content))
+        .collect();
+    let request = vec![ChatMessage::new("user", format!("{}\n\n{}", SUMMARY_PROMPT, conversation.join("\n\n")))];
+
+    let cancel = PipelineCancel::default();
+    let _stop = CancelOnDrop(cancel.clone());
+    let Ok(Ok(response)) = tokio::task::spawn_blocking(move || send(request, &ChatStream::new(None, cancel))).await else {
+        return (None, None);
+    };
+    let summary = Some(response.text.trim().to_string()).filter(|summary| response.is_success() && !summary.is_empty());
+    (summary, response.usage)
+}
+
+/// A failed response with an error message
+pub fn failure(message: String) -> ClaudeResponse {
+    ClaudeResponse {
+        stderr: message,
+        exit_code: Some(1),
+        ..ClaudeResponse::new("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An answer that nearly fills the history budget on its own
+    fn long() -> String {
+        "x".repeat((HISTORY_BUDGET - 4) * 4)
+    }
+
+    fn command(prompt: &str, trim_policy: TrimPolicy) -> ClaudeInvocation {
+        ClaudeInvocation {
+            prompt: prompt.to_string(),
+            options: crate::claude_executor::ClaudeOptions {
+                trim_policy,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_history_is_trimmed_to_the_budget() {
+        let history = ChatHistory::new();
+        history.record("first", "answer");
+        history.record("second", &long());
+        let trimmed = history.trim(HISTORY_BUDGET, TrimPolicy::DropOldest);
+        assert_eq!(trimmed, [ChatMessage::new("user", "first"), ChatMessage::new("assistant", "answer")]);
+        assert_eq!(history.messages().len(), 2);
+        assert_eq!(history.messages()[0].content, "second");
+
+        // Room for the next prompt is made before it is sent
+        let trimmed = history.trim(10, TrimPolicy::DropOldest);
+        assert_eq!(trimmed.len(), 2);
+        assert!(history.messages().is_empty());
+    }
+
+    #[test]
+    fn test_pinned_exchanges_are_kept() {
+        let history = ChatHistory::new();
+        history.record("The task", "Got it");
+        history.record("second", "answer");
+        history.record("third", &long());
+        let trimmed = history.trim(HISTORY_BUDGET, TrimPolicy::KeepPinned);
+
+        assert_eq!(trimmed[0].content, "second");
+        let prompts: Vec<String> = history.messages().into_iter().map(|message| message.content).step_by(2).collect();
+        assert_eq!(prompts, ["The task"]);
+    }
+
+    #[tokio::test]
+    async fn test_trimmed_exchanges_are_summarized() {
+        let history = ChatHistory::new();
+        // Answers summary requests with "Ada asked", anything else with the
+        // number of messages it was sent
+        let send = |messages: Vec<ChatMessage>, _: &ChatStream| {
+            let prompt = &messages.last().unwrap().content;
+            let answer = if prompt.starts_with(SUMMARY_PROMPT) { "Ada asked".to_string() } else { messages.len().to_string() };
+            Ok(ClaudeResponse {
+                usage: Some(Usage { input_tokens: 10, ..Default::default() }),
+                ..ClaudeResponse::new(answer)
+            })
+        };
+
+        history.exchange(&command("I am Ada", TrimPolicy::SummarizeOldest), ResponsePipeline::new(), 100, send).await.unwrap();
+        let response = history
+            .exchange(&command(&"x".repeat(396), TrimPolicy::SummarizeOldest), ResponsePipeline::new(), 100, send)
+            .await
+            .unwrap();
+        // The summary was sent in place of the first exchange
+        assert_eq!(response.text, "3");
+        assert_eq!(response.background_usage.unwrap().input_tokens, 10);
+        let messages = history.messages();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].content, format!("{}\n\nAda asked", SUMMARY_HEADING));
+
+        // Without a summary, the exchanges are dropped
+        let failing = |_: Vec<ChatMessage>, _: &ChatStream| Ok(failure("overloaded".to_string()));
+        let trimmed = vec![ChatMessage::new("user", "I am Ada"), ChatMessage::new("assistant", "Hello")];
+        assert_eq!(history.trimmed(trimmed, TrimPolicy::SummarizeOldest, 100, failing).await, None);
+        assert_eq!(history.messages().len(), 4);
+    }
+}
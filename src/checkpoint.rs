@@ -0,0 +1,331 @@
+//! Conversation checkpoint module
+//!
+//! Checkpoints give agentic sessions safe experimentation points. A
+//! checkpoint remembers how many turns the conversation had when it was
+//! taken and can optionally snapshot the files of the workspace so that both
+//! the conversation and the files Claude edited can be rolled back later.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::checkpoint::{Checkpoint, CheckpointStore};
+//!
+//! let mut store = CheckpointStore::new();
+//! store.save(Checkpoint {
+//!     name: "before-refactor".to_string(),
+//!     turn_count: 3,
+//!     files: None,
+//! });
+//!
+//! let checkpoint = store.get("before-refactor").unwrap();
+//! assert_eq!(checkpoint.turn_count, 3);
+//! ```
+
+use anyhow::{Result, Context, bail};
+use std::collections::BTreeMap;
+use std::fs::{self, Permissions};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use crate::ignore_rules;
+
+/// Most bytes of files a workspace snapshot holds in memory
+pub const MAX_SNAPSHOT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// A named point in the conversation that can be restored later
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// Name given by the user
+    pub name: String,
+
+    /// Number of conversation turns at the time of the checkpoint
+    pub turn_count: usize,
+
+    /// Optional snapshot of the workspace files
+    pub files: Option<WorkspaceSnapshot>,
+}
+
+/// Collection of checkpoints taken during a session
+///
+/// Saving a checkpoint under an existing name replaces the old one.
+#[derive(Debug, Default)]
+pub struct CheckpointStore {
+    checkpoints: BTreeMap<String, Checkpoint>,
+}
+
+impl CheckpointStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save a checkpoint, returning `true` if it replaced an existing one
+    pub fn save(&mut self, checkpoint: Checkpoint) -> bool {
+        self.checkpoints
+            .insert(checkpoint.name.clone(), checkpoint)
+            .is_some()
+    }
+
+    /// Look up a checkpoint by name
+    pub fn get(&self, name: &str) -> Option<&Checkpoint> {
+        self.checkpoints.get(name)
+    }
+
+    /// Names of all saved checkpoints in alphabetical order
+    pub fn names(&self) -> Vec<&str> {
+        self.checkpoints.keys().map(String::as_str).collect()
+    }
+}
+
+/// In-memory copy of the files below a workspace root
+///
+/// Files excluded by the [`ignore_rules`], such as those in `.gitignore`
+/// and `target` and `node_modules` directories, are skipped, and so is the
+/// `.git` directory; other hidden files like `.env` are kept. Files keep
+/// their permissions, and symbolic links are kept as links.
+///
+/// # Examples
+///
+/// ```no_run
+/// use claude_dialog::checkpoint::WorkspaceSnapshot;
+/// use std::path::Path;
+///
+/// let snapshot = WorkspaceSnapshot::capture(Path::new(".")).unwrap();
+/// // ... Claude edits some files ...
+/// let report = snapshot.restore().unwrap();
+/// println!("Restored {} file(s)", report.restored.len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorkspaceSnapshot {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, Entry>,
+}
+
+/// What a snapshot holds of a path
+#[derive(Debug, Clone, PartialEq)]
+enum Entry {
+    /// A file with its content and permissions
+    File { content: Vec<u8>, permissions: Permissions },
+
+    /// A symbolic link with the path it points to
+    Link(PathBuf),
+}
+
+/// Outcome of restoring a workspace snapshot
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    /// Files that were modified or deleted since the snapshot and have been restored
+    pub restored: Vec<PathBuf>,
+
+    /// Files created since the snapshot; they are left in place
+    pub untracked: Vec<PathBuf>,
+
+    /// Files left as they are because a directory on their path has become
+    /// a symbolic link since the snapshot, which could lead outside the
+    /// workspace
+    pub skipped: Vec<PathBuf>,
+}
+
+impl WorkspaceSnapshot {
+    /// Capture the contents of every file below `root`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a directory or file cannot be read, or if the
+    /// files add up to more than [`MAX_SNAPSHOT_BYTES`].
+    pub fn capture(root: &Path) -> Result<Self> {
+        Self::capture_within(root, MAX_SNAPSHOT_BYTES)
+    }
+
+    /// Capture the files below `root` if they add up to at most `limit` bytes
+    fn capture_within(root: &Path, limit: u64) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        let mut size = 0;
+        for path in collect_files(root)? {
+            let metadata = fs::symlink_metadata(&path)
+                .with_context(|| format!("Failed to snapshot file: {}", path.display()))?;
+            if metadata.file_type().is_symlink() {
+                let target = fs::read_link(&path)
+                    .with_context(|| format!("Failed to snapshot link: {}", path.display()))?;
+                files.insert(path, Entry::Link(target));
+                continue;
+            }
+
+            size += metadata.len();
+            if size > limit {
+                bail!(
+                    "The workspace files add up to more than {} MB, too much to snapshot; \
+                     add large files to .gitignore or .claude-dialog-ignore, or checkpoint without files",
+                    limit / (1024 * 1024)
+                );
+            }
+            let content = fs::read(&path)
+                .with_context(|| format!("Failed to snapshot file: {}", path.display()))?;
+            files.insert(path, Entry::File { content, permissions: metadata.permissions() });
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            files,
+        })
+    }
+
+    /// Number of files in the snapshot
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether the snapshot contains no files
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Write every snapshotted file back to disk
+    ///
+    /// Only files whose content or permissions changed (or that were
+    /// deleted) are written. A symbolic link that replaced a file is removed
+    /// rather than written through, and snapshotted links are recreated.
+    /// Files created after the snapshot are reported but never deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the workspace cannot be scanned or a file cannot be written.
+    pub fn restore(&self) -> Result<RestoreReport> {
+        let mut report = RestoreReport::default();
+
+        for (path, entry) in &self.files {
+            if self.behind_link(path) {
+                report.skipped.push(path.clone());
+                continue;
+            }
+            if current_entry(path).as_ref() == Some(entry) {
+                continue;
+            }
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+            }
+            // Writing through a link could change a file outside the workspace
+            let is_link = fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+            if is_link || matches!(entry, Entry::Link(_)) {
+                match fs::remove_file(path) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => {
+                        return Err(e).with_context(|| format!("Failed to restore file: {}", path.display()));
+                    }
+                    _ => {}
+                }
+            }
+            match entry {
+                Entry::File { content, permissions } => {
+                    fs::write(path, content)
+                        .and_then(|()| fs::set_permissions(path, permissions.clone()))
+                        .with_context(|| format!("Failed to restore file: {}", path.display()))?;
+                }
+                Entry::Link(target) => {
+                    create_link(target, path)
+                        .with_context(|| format!("Failed to restore link: {}", path.display()))?;
+                }
+            }
+            report.restored.push(path.clone());
+        }
+
+        report.untracked = collect_files(&self.root)?
+            .into_iter()
+            .filter(|path| !self.files.contains_key(path))
+            .collect();
+
+        Ok(report)
+    }
+
+    /// Whether a directory between the root and `path` is a symbolic link
+    fn behind_link(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+        let mut dir = self.root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            if components.peek().is_none() {
+                break;
+            }
+            dir.push(component);
+            if fs::symlink_metadata(&dir).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// What is at `path` now, in the terms of a snapshot
+fn current_entry(path: &Path) -> Option<Entry> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if metadata.file_type().is_symlink() {
+        return fs::read_link(path).ok().map(Entry::Link);
+    }
+    let content = fs::read(path).ok()?;
+    Some(Entry::File { content, permissions: metadata.permissions() })
+}
+
+/// Create a symbolic link at `path` pointing to `target`
+#[cfg(unix)]
+fn create_link(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_link(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)
+}
+
+/// The files and links below `root` not excluded by the [`ignore_rules`],
+/// hidden ones included, in path order
+fn collect_files(root: &Path) -> Result<Vec<PathBuf>> {
+    Ok(ignore_rules::workspace_files(root)?.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_replaces_existing() {
+        let mut store = CheckpointStore::new();
+        let checkpoint = Checkpoint {
+            name: "a".to_string(),
+            turn_count: 1,
+            files: None,
+        };
+
+        assert!(!store.save(checkpoint.clone()));
+        assert!(store.save(Checkpoint { turn_count: 2, ..checkpoint }));
+        assert_eq!(store.get("a").unwrap().turn_count, 2);
+        assert_eq!(store.names(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_collect_files_skips_ignored_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join(".git/HEAD"), "ref").unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+        fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+        fs::write(dir.path().join("build.log"), "output").unwrap();
+
+        let files = collect_files(dir.path()).unwrap();
+        assert_eq!(files, vec![dir.path().join(".gitignore"), dir.path().join("src/main.rs")]);
+    }
+
+    #[test]
+    fn test_snapshot_size_is_limited() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "x".repeat(600_000)).unwrap();
+        fs::write(dir.path().join("b.txt"), "x".repeat(600_000)).unwrap();
+
+        assert_eq!(WorkspaceSnapshot::capture_within(dir.path(), 2 * 1024 * 1024).unwrap().len(), 2);
+        let err = WorkspaceSnapshot::capture_within(dir.path(), 1024 * 1024).unwrap_err();
+        assert!(err.to_string().starts_with("The workspace files add up to more than 1 MB"));
+    }
+}
@@ -0,0 +1,361 @@
+//! File chunk selection module
+//!
+//! An `@file` reference can name the part of a file to send after a `#`:
+//!
+//! - `@src/main.rs#120-200` or `@src/main.rs#42` - a line range
+//! - `@src/main.rs#fn main` or `@src/main.rs#Config` - a symbol definition,
+//!   optionally with its keyword
+//! - `@README.md#Installation` - a Markdown section, up to the next heading
+//!   of the same or a higher level
+//!
+//! Symbols are found without parsing: the definition is the first line where
+//! a definition keyword (`fn`, `struct`, `class`, `def`, ...) is followed by
+//! the name, extended backwards over doc comments and attributes, and forwards
+//! to the matching closing brace, or over the indented block for languages
+//! such as Python. This covers ordinary code in most languages, but braces
+//! inside strings or comments can make a chunk longer or shorter than the
+//! definition.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::chunk::{Selector, select};
+//! use std::path::Path;
+//!
+//! let lines: Vec<String> = "use std::io;\n\n/// Entry point\nfn main() {\n    run();\n}\n"
+//!     .lines()
+//!     .map(String::from)
+//!     .collect();
+//!
+//! let selector: Selector = "fn main".parse().unwrap();
+//! let range = select(Path::new("src/main.rs"), &lines, &selector).unwrap();
+//! assert_eq!(range.to_string(), "3-6");
+//! ```
+
+use anyhow::{Result, anyhow, bail};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use crate::context::LineRange;
+
+/// Keywords that introduce a named definition
+pub const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn", "struct", "enum", "trait", "impl", "mod", "type", "union", "macro_rules",
+    "class", "interface", "def", "function", "func",
+];
+
+/// File extensions treated as Markdown
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdx"];
+
+/// The part of a file an `@file` reference selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// A range of lines
+    Lines(LineRange),
+
+    /// A symbol in source code, or a heading in Markdown
+    Name {
+        /// Definition keyword the symbol must be introduced with, if given
+        keyword: Option<String>,
+
+        /// Name of the symbol or text of the heading
+        name: String,
+    },
+}
+
+impl FromStr for Selector {
+    type Err = anyhow::Error;
+
+    /// Parse the part of a reference after the `#`
+    ///
+    /// Digits form a line range; anything else names a symbol or heading.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            bail!("Empty selector after '#'");
+        }
+
+        if s.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            let (start, end) = s.split_once('-').unwrap_or((s, s));
+            let parse = |n: &str| n.parse::<usize>().map_err(|_| anyhow!("Invalid line range '{}'", s));
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start == 0 || end < start {
+                bail!("Invalid line range '{}'; lines are numbered from 1", s);
+            }
+            return Ok(Self::Lines(LineRange { start, end }));
+        }
+
+        let (keyword, name) = match s.split_once(char::is_whitespace) {
+            Some((keyword, name)) if DEFINITION_KEYWORDS.contains(&keyword) => {
+                (Some(keyword.to_string()), name.trim())
+            }
+            _ => (None, s),
+        };
+
+        Ok(Self::Name {
+            keyword,
+            name: name.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Lines(range) => write!(f, "{}", range),
+            Selector::Name { keyword: Some(keyword), name } => write!(f, "{} {}", keyword, name),
+            Selector::Name { keyword: None, name } => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Find the lines of a file a selector refers to
+///
+/// Names select a section in Markdown files (by extension) and a symbol
+/// definition in all other files.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file, used to recognize Markdown
+/// * `lines` - The lines of the file
+/// * `selector` - The part to select
+///
+/// # Errors
+///
+/// Returns an error if the line range lies outside the file or the symbol
+/// or heading does not exist.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::chunk::{Selector, select};
+/// use std::path::Path;
+///
+/// let lines: Vec<String> = "# Guide\n## Install\ncargo install\n## Usage\nrun it\n"
+///     .lines()
+///     .map(String::from)
+///     .collect();
+///
+/// let range = select(Path::new("README.md"), &lines, &"install".parse().unwrap()).unwrap();
+/// assert_eq!(range.to_string(), "2-3");
+///
+/// assert!(select(Path::new("README.md"), &lines, &"9-12".parse().unwrap()).is_err());
+/// ```
+pub fn select(path: &Path, lines: &[String], selector: &Selector) -> Result<LineRange> {
+    match selector {
+        Selector::Lines(range) => {
+            if range.end > lines.len() {
+                bail!("Lines {} are outside the file ({} lines)", range, lines.len());
+            }
+            Ok(*range)
+        }
+        Selector::Name { name, .. } if is_markdown(path) => find_heading(lines, name)
+            .ok_or_else(|| anyhow!("No heading '{}'", name)),
+        Selector::Name { keyword, name } => find_symbol(lines, keyword.as_deref(), name)
+            .ok_or_else(|| anyhow!("No definition of '{}'", selector)),
+    }
+}
+
+/// Whether a selector keyword may be followed by a symbol name
+///
+/// Lets `@src/main.rs#fn main` be written without quotes.
+pub fn is_definition_keyword(word: &str) -> bool {
+    DEFINITION_KEYWORDS.contains(&word)
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| MARKDOWN_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Level and text of a Markdown ATX heading
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    let text = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with(' ')) {
+        return None;
+    }
+    Some((level, text.trim().trim_end_matches('#').trim()))
+}
+
+/// Find a Markdown section by its heading, ignoring case and trailing punctuation
+fn find_heading(lines: &[String], name: &str) -> Option<LineRange> {
+    let normalize = |text: &str| {
+        text.trim_start_matches('#')
+            .trim()
+            .trim_end_matches(['.', ':', '!', '?'])
+            .to_lowercase()
+    };
+    let wanted = normalize(name);
+
+    let mut in_fence = false;
+    let mut section: Option<(usize, usize)> = None;
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        let Some((level, text)) = heading(line).filter(|_| !in_fence) else {
+            continue;
+        };
+
+        match section {
+            Some((start, section_level)) if level <= section_level => {
+                return Some(LineRange { start: start + 1, end: index });
+            }
+            None if normalize(text) == wanted => section = Some((index, level)),
+            _ => {}
+        }
+    }
+
+    section.map(|(start, _)| LineRange { start: start + 1, end: lines.len() })
+}
+
+/// Find the definition of a symbol
+fn find_symbol(lines: &[String], keyword: Option<&str>, name: &str) -> Option<LineRange> {
+    let start = lines.iter().position(|line| defines(line, keyword, name))?;
+    let end = definition_end(lines, start);
+
+    // Include doc comments, attributes and decorators directly above
+    let documented = lines[..start]
+        .iter()
+        .rev()
+        .take_while(|line| {
+            let line = line.trim_start();
+            ["///", "//!", "/**", "*", "#[", "@", "\"\"\""]
+                .iter()
+                .any(|prefix| line.starts_with(prefix))
+        })
+        .count();
+
+    Some(LineRange {
+        start: start - documented + 1,
+        end: end + 1,
+    })
+}
+
+/// Whether a line defines `name` with `keyword` (or any definition keyword)
+fn defines(line: &str, keyword: Option<&str>, name: &str) -> bool {
+    let words: Vec<&str> = line
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '!'))
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    words.windows(2).any(|pair| {
+        let matches_keyword = match keyword {
+            Some(keyword) => pair[0] == keyword,
+            None => is_definition_keyword(pair[0]),
+        };
+        matches_keyword && pair[1] == name
+    })
+}
+
+/// Index of the last line of the definition starting at `start`
+fn definition_end(lines: &[String], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut opened = false;
+
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+
+        if opened && depth == 0 {
+            return index;
+        }
+        if !opened {
+            let trimmed = line.trim_end();
+            if trimmed.ends_with(';') {
+                return index;
+            }
+            if trimmed.ends_with(':') {
+                return indented_block_end(lines, index);
+            }
+        }
+    }
+
+    lines.len() - 1
+}
+
+/// Index of the last line indented deeper than the line at `header`
+fn indented_block_end(lines: &[String], header: usize) -> usize {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let header_indent = indent(&lines[header]);
+
+    let mut end = header;
+    for (index, line) in lines.iter().enumerate().skip(header + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indent(line) <= header_indent {
+            break;
+        }
+        end = index;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_selectors() {
+        assert_eq!("120-200".parse::<Selector>().unwrap(), Selector::Lines(LineRange { start: 120, end: 200 }));
+        assert_eq!("7".parse::<Selector>().unwrap(), Selector::Lines(LineRange { start: 7, end: 7 }));
+        assert!("0-3".parse::<Selector>().is_err());
+        assert!("9-3".parse::<Selector>().is_err());
+        assert!("1-2-3".parse::<Selector>().is_err());
+        assert!("".parse::<Selector>().is_err());
+
+        let selector: Selector = "fn main".parse().unwrap();
+        assert_eq!(selector.to_string(), "fn main");
+        let selector: Selector = "Getting started".parse().unwrap();
+        assert_eq!(selector, Selector::Name { keyword: None, name: "Getting started".to_string() });
+    }
+
+    #[test]
+    fn test_rust_symbols() {
+        let source = lines(
+            "#[derive(Debug)]\npub struct Config {\n    name: String,\n}\n\nimpl Config {\n    pub fn new() -> Self {\n        todo!()\n    }\n}\n\nstruct Marker;\n",
+        );
+        let find = |selector: &str| select(Path::new("lib.rs"), &source, &selector.parse().unwrap()).unwrap();
+
+        assert_eq!(find("struct Config").to_string(), "1-4");
+        assert_eq!(find("impl Config").to_string(), "6-10");
+        assert_eq!(find("new").to_string(), "7-9");
+        assert_eq!(find("Marker").to_string(), "12");
+        assert!(select(Path::new("lib.rs"), &source, &"fn Config".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_python_symbols() {
+        let source = lines("@cache\ndef load(path):\n    data = read(path)\n\n    return data\n\nprint(load('x'))\n");
+        let range = select(Path::new("app.py"), &source, &"def load".parse().unwrap()).unwrap();
+        assert_eq!(range.to_string(), "1-5");
+    }
+
+    #[test]
+    fn test_markdown_sections() {
+        let doc = lines("# Title\n## Setup\n```sh\n# not a heading\n```\n### Details\nmore\n## Usage\nrun\n");
+        let find = |selector: &str| select(Path::new("doc.md"), &doc, &selector.parse().unwrap());
+
+        assert_eq!(find("setup").unwrap().to_string(), "2-7");
+        assert_eq!(find("## Usage").unwrap().to_string(), "8-9");
+        assert_eq!(find("Title").unwrap().to_string(), "1-9");
+        assert_eq!(find("nope").unwrap_err().to_string(), "No heading 'nope'");
+    }
+}
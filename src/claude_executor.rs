@@ -3,19 +3,27 @@
 //! This module handles building and executing Claude CLI commands with the appropriate
 //! arguments for prompts, models, and tool permissions.
 //!
+//! The dialog loop runs commands through the [`ClaudeExecutor`] trait.
+//! [`SubprocessExecutor`] runs the `claude` CLI; tests and other backends
+//! can provide their own implementation (see
+//! [`DialogLoop::with_executor`](crate::dialog::DialogLoop::with_executor)).
+//!
 //! # Examples
 //!
 //! ```no_run
-//! use claude_dialog::claude_executor::{ClaudeCommand, execute_claude};
+//! use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeOptions, execute_claude};
 //!
 //! # #[tokio::main]
 //! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! // Create a basic Claude command
-//! let command = ClaudeCommand {
+//! let command = ClaudeInvocation {
 //!     prompt: "Hello, Claude!".to_string(),
-//!     system_prompt: None,
-//!     append_prompt: None,
-//!     model: Some("claude-3-opus".to_string()),
+//!     options: ClaudeOptions {
+//!         system_prompt: None,
+//!         append_prompt: None,
+//!         model: Some("claude-3-opus".to_string()),
+//!         ..Default::default()
+//!     },
 //! };
 //!
 //! // Execute the command
@@ -25,63 +33,264 @@
 //! ```
 
 use anyhow::{Result, Context};
-use tokio::process::Command;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use std::io::Write;
+use tokio::process::{ChildStderr, Command};
+use crate::pipeline::{ResponsePipeline, Utf8Decoder};
+use crate::status::Usage;
+use crate::stream_json::{self, Activity, IncrementalText, StreamEvent};
+use crate::ui::{Spinner, UI};
+
+/// The Claude CLI binary run unless `--claude-bin` names another, looked up
+/// on `PATH`
+pub const CLAUDE_BIN: &str = "claude";
+
+/// Tools Claude may use without asking during a regular turn, unless
+/// other tools are allowed with [`ClaudeOptions::allowed_tools`]
+pub const ALLOWED_TOOLS: &[&str] = &["Write", "Edit"];
+
+/// How often the progress spinner is redrawn in final-only mode
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How a command attaches to the Claude CLI's conversation sessions
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_executor::SessionMode;
+///
+/// assert_eq!(SessionMode::Continue.to_args(), ["--continue"]);
+/// assert_eq!(SessionMode::Resume("3f2a9c1e".to_string()).to_args(), ["--resume", "3f2a9c1e"]);
+/// assert!(SessionMode::Fresh.to_args().is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionMode {
+    /// Continue the most recent conversation (`--continue`)
+    #[default]
+    Continue,
+
+    /// Resume the conversation with the given session ID (`--resume <id>`)
+    Resume(String),
+
+    /// Start a brand new conversation
+    Fresh,
+}
+
+impl SessionMode {
+    /// The flags attaching a command to the session, which go before the
+    /// prompt
+    pub fn to_args(&self) -> Vec<String> {
+        match self {
+            SessionMode::Continue => vec!["--continue".to_string()],
+            SessionMode::Resume(id) => vec!["--resume".to_string(), id.clone()],
+            SessionMode::Fresh => Vec::new(),
+        }
+    }
+}
+
+/// Output format requested from the Claude CLI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Plain text of the final response
+    #[default]
+    Text,
+
+    /// The final response with its session and usage, as one JSON object
+    /// (`--output-format json`)
+    Json,
+
+    /// One JSON event per line (`--output-format stream-json --verbose`)
+    StreamJson,
+
+    /// One JSON event per line, including the text of each message while it
+    /// is generated (`--include-partial-messages`)
+    StreamJsonPartial,
+}
+
+/// How the ollama and api backends shorten a conversation that has grown
+/// too long to send with every prompt (see [`crate::chat`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum TrimPolicy {
+    /// Drop the oldest exchanges
+    #[default]
+    DropOldest,
+
+    /// Replace the oldest exchanges with a summary written by the model
+    SummarizeOldest,
+
+    /// Keep the first exchange, which usually sets up the task, and drop
+    /// the oldest ones after it
+    KeepPinned,
+}
+
+/// Where the prompts of a session are sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// The `claude` CLI
+    #[default]
+    Claude,
+
+    /// A local Ollama server (see [`crate::ollama`])
+    #[cfg(feature = "ollama")]
+    Ollama,
+
+    /// The Anthropic Messages API (see [`crate::api`])
+    #[cfg(feature = "api")]
+    Api,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Backend::Claude => "claude",
+            #[cfg(feature = "ollama")]
+            Backend::Ollama => "ollama",
+            #[cfg(feature = "api")]
+            Backend::Api => "api",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    /// Parse a backend name such as `claude` or `ollama`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "claude" => Ok(Backend::Claude),
+            #[cfg(feature = "ollama")]
+            "ollama" => Ok(Backend::Ollama),
+            #[cfg(feature = "api")]
+            "api" => Ok(Backend::Api),
+            other => anyhow::bail!("Unknown backend '{}'; expected {}", other, Backend::NAMES.join(" or ")),
+        }
+    }
+}
+
+impl Backend {
+    /// A model of the backend to suggest in messages
+    pub fn example_model(self) -> &'static str {
+        match self {
+            Backend::Claude => "sonnet",
+            #[cfg(feature = "ollama")]
+            Backend::Ollama => "llama3",
+            #[cfg(feature = "api")]
+            Backend::Api => "claude-sonnet-4-5",
+        }
+    }
+
+    /// Names of the backends included in this build
+    pub const NAMES: &[&str] = &[
+        "claude",
+        #[cfg(feature = "ollama")]
+        "ollama",
+        #[cfg(feature = "api")]
+        "api",
+    ];
+}
+
+/// Validate a list of tool names, dropping repeated names
+///
+/// Names are trimmed and kept in their original order. Tool names such as
+/// `Bash(git log:*)` may carry a permission pattern.
+///
+/// # Errors
+///
+/// Returns an error if a name is empty.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_executor::tool_list;
+///
+/// let tools = tool_list(["Edit", " Bash(git log:*)", "Edit"]).unwrap();
+/// assert_eq!(tools, ["Edit", "Bash(git log:*)"]);
+/// assert!(tool_list(["Edit", " "]).is_err());
+/// ```
+pub fn tool_list<I, S>(tools: I) -> Result<Vec<String>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut list: Vec<String> = Vec::new();
+    for tool in tools {
+        let tool = tool.as_ref().trim();
+        if tool.is_empty() {
+            anyhow::bail!("Tool names must not be empty");
+        }
+        if !list.iter().any(|existing| existing == tool) {
+            list.push(tool.to_string());
+        }
+    }
+    Ok(list)
+}
 
-/// Represents a Claude command with all necessary parameters
+/// A prompt for the Claude CLI and the options it is run with
 ///
 /// This structure encapsulates all the information needed to construct
-/// and execute a Claude CLI command, including prompts, model selection,
-/// and system prompt configuration.
+/// and execute a Claude CLI command: the prompt, and [`ClaudeOptions`]
+/// for model selection, system prompts, tools and the session.
 ///
 /// # Examples
 ///
 /// ```
-/// use claude_dialog::claude_executor::ClaudeCommand;
+/// use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeOptions};
 ///
-/// // Basic command with just a prompt
-/// let cmd = ClaudeCommand {
+/// // Basic invocation with just a prompt
+/// let cmd = ClaudeInvocation {
 ///     prompt: "What is Rust?".to_string(),
-///     system_prompt: None,
-///     append_prompt: None,
-///     model: None,
+///     ..Default::default()
 /// };
 ///
-/// // Command with custom system prompt and model
-/// let cmd = ClaudeCommand {
+/// // Invocation with custom system prompt and model
+/// let cmd = ClaudeInvocation {
 ///     prompt: "Explain memory safety".to_string(),
-///     system_prompt: Some("You are a Rust expert.".to_string()),
-///     append_prompt: None,
-///     model: Some("claude-3-opus".to_string()),
+///     options: ClaudeOptions {
+///         system_prompt: Some("You are a Rust expert.".to_string()),
+///         model: Some("claude-3-opus".to_string()),
+///         ..Default::default()
+///     },
 /// };
 ///
-/// // Command with append prompt
-/// let cmd = ClaudeCommand {
+/// // Invocation with append prompt
+/// let cmd = ClaudeInvocation {
 ///     prompt: "Write a function".to_string(),
-///     system_prompt: None,
-///     append_prompt: Some("Always use idiomatic Rust.".to_string()),
-///     model: None,
+///     options: ClaudeOptions {
+///         append_prompt: Some("Always use idiomatic Rust.".to_string()),
+///         ..Default::default()
+///     },
 /// };
 /// ```
-#[derive(Debug, Clone)]
-pub struct ClaudeCommand {
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClaudeInvocation {
     /// The main prompt to send to Claude
     pub prompt: String,
     
-    /// Optional system prompt that replaces the default
-    pub system_prompt: Option<String>,
-    
-    /// Optional prompt to append to the default system prompt
-    pub append_prompt: Option<String>,
-    
-    /// Optional model specification (e.g., "claude-3-opus")
-    pub model: Option<String>,
+    /// How the CLI is run for the prompt
+    pub options: ClaudeOptions,
 }
 
-impl ClaudeCommand {
+impl ClaudeInvocation {
     /// Build command-line arguments for the Claude CLI
     ///
-    /// Constructs a vector of arguments based on the command configuration,
-    /// including the prompt, system prompts, model selection, and allowed tools.
+    /// The session flags come first, then `-p` with the prompt, then the
+    /// arguments of the [options](ClaudeOptions::to_args).
     ///
     /// # Returns
     ///
@@ -90,26 +299,265 @@ impl ClaudeCommand {
     /// # Examples
     ///
     /// ```
-    /// use claude_dialog::claude_executor::ClaudeCommand;
+    /// use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeOptions};
     ///
-    /// let cmd = ClaudeCommand {
+    /// let cmd = ClaudeInvocation {
     ///     prompt: "Hello".to_string(),
-    ///     system_prompt: Some("Be helpful".to_string()),
-    ///     append_prompt: None,
-    ///     model: Some("claude-3-opus".to_string()),
+    ///     options: ClaudeOptions {
+    ///         system_prompt: Some("Be helpful".to_string()),
+    ///         model: Some("claude-3-opus".to_string()),
+    ///         allowed_tools: Some(Vec::new()),
+    ///         ..Default::default()
+    ///     },
+    /// };
+    ///
+    /// assert_eq!(
+    ///     cmd.to_args(),
+    ///     ["--continue", "-p", "Hello", "--system-prompt", "Be helpful", "--model", "claude-3-opus"]
+    /// );
+    /// ```
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = self.prompt_args();
+        args.extend(self.options.to_args());
+        args
+    }
+    
+    /// Build command-line arguments without granting any tools
+    fn to_args_without_tools(&self) -> Vec<String> {
+        let mut args = self.prompt_args();
+        args.extend(self.options.to_args_without_tools());
+        args
+    }
+    
+    /// The session flags followed by the prompt
+    fn prompt_args(&self) -> Vec<String> {
+        let mut args = self.options.session.to_args();
+        args.push("-p".to_string());
+        args.push(self.prompt.clone());
+        args
+    }
+    
+    /// The `claude` command line running this invocation, with the
+    /// arguments quoted for a POSIX shell
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeOptions};
+    ///
+    /// let cmd = ClaudeInvocation {
+    ///     prompt: "What's new?".to_string(),
+    ///     options: ClaudeOptions {
+    ///         model: Some("opus".to_string()),
+    ///         allowed_tools: Some(Vec::new()),
+    ///         ..Default::default()
+    ///     },
     /// };
+    /// assert_eq!(cmd.command_line(), r#"claude --continue -p 'What'\''s new?' --model opus"#);
+    /// ```
+    pub fn command_line(&self) -> String {
+        command_line(self.options.binary(), &self.to_args())
+    }
+}
+
+/// Permission mode the Claude CLI runs tools in (`--permission-mode`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    /// Ask before using tools that are not allowed
+    #[default]
+    Default,
+
+    /// Edit files without asking
+    AcceptEdits,
+
+    /// Use every tool without asking
+    BypassPermissions,
+
+    /// Plan changes without making them
+    Plan,
+}
+
+impl fmt::Display for PermissionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PermissionMode::Default => "default",
+            PermissionMode::AcceptEdits => "acceptEdits",
+            PermissionMode::BypassPermissions => "bypassPermissions",
+            PermissionMode::Plan => "plan",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Options of a Claude CLI invocation, everything but the prompt
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_executor::{ClaudeOptions, OutputFormat, PermissionMode};
+///
+/// let options = ClaudeOptions {
+///     model: Some("opus".to_string()),
+///     output: OutputFormat::Json,
+///     permission_mode: Some(PermissionMode::Plan),
+///     allowed_tools: Some(vec!["Read".to_string()]),
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     options.to_args(),
+///     ["--model", "opus", "--output-format", "json", "--permission-mode", "plan", "--allowedTools", "Read"]
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClaudeOptions {
+    /// Optional system prompt that replaces the default
+    pub system_prompt: Option<String>,
+    
+    /// Optional prompt to append to the default system prompt
+    pub append_prompt: Option<String>,
+    
+    /// Optional model specification (e.g., "claude-3-opus")
+    pub model: Option<String>,
+
+    /// Sampling temperature, used by the ollama and api backends; the claude
+    /// CLI has no such option, so it is not part of [`ClaudeOptions::to_args`]
+    pub temperature: Option<f32>,
+
+    /// Most tokens of a response, used by the api backend; like the
+    /// temperature, not part of [`ClaudeOptions::to_args`]
+    pub max_tokens: Option<u32>,
+
+    /// How the ollama and api backends shorten a long conversation; the
+    /// claude CLI manages its own
+    pub trim_policy: TrimPolicy,
+
+    /// Send the api backend's requests without prompt caching breakpoints
+    /// (`--no-prompt-cache`); the claude CLI places its own
+    pub no_prompt_cache: bool,
+
+    /// Most tokens, estimated, of the conversation the ollama and api
+    /// backends send with a prompt (`--history-budget`), or `None` for what
+    /// they derive from the model's context window
+    pub history_budget: Option<usize>,
+
+    /// Context window of the ollama model in tokens, sent as its `num_ctx`
+    /// (`--num-ctx`), or `None` for the server's default
+    pub num_ctx: Option<u32>,
+    
+    /// Whether to continue the previous conversation or start a fresh one
+    pub session: SessionMode,
+    
+    /// Output format requested from the CLI
+    pub output: OutputFormat,
+    
+    /// Backend the command is meant for, used by
+    /// [`BackendRouter`](crate::router::BackendRouter) to pick an executor
+    pub backend: Backend,
+    
+    /// Tools Claude may use without asking (`--allowedTools`), or `None`
+    /// for [`ALLOWED_TOOLS`]
+    pub allowed_tools: Option<Vec<String>>,
+    
+    /// Tools Claude must not use (`--disallowedTools`)
+    pub disallowed_tools: Vec<String>,
+    
+    /// Directories Claude may access besides the working directory
+    /// (`--add-dir`)
+    pub add_dirs: Vec<PathBuf>,
+    
+    /// Permission mode for tools, or `None` for the CLI's own setting
+    /// (`--permission-mode`)
+    pub permission_mode: Option<PermissionMode>,
+    
+    /// Files naming MCP servers whose tools Claude may use (`--mcp-config`)
+    pub mcp_config: Vec<PathBuf>,
+    
+    /// Use only the MCP servers of `mcp_config`, ignoring those configured
+    /// elsewhere (`--strict-mcp-config`)
+    pub strict_mcp_config: bool,
+    
+    /// Further arguments passed to the CLI as they are (`--claude-arg`), for
+    /// options this crate does not know
+    pub extra_args: Vec<String>,
+    
+    /// Directory the CLI runs in, or `None` for the current one; like the
+    /// temperature, not part of [`ClaudeOptions::to_args`]
+    pub working_dir: Option<PathBuf>,
+    
+    /// The CLI binary to run, or `None` for [`CLAUDE_BIN`] on `PATH`
+    /// (`--claude-bin`)
+    pub claude_bin: Option<PathBuf>,
+}
+
+impl ClaudeOptions {
+    /// The CLI binary the command runs
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::claude_executor::ClaudeOptions;
+    /// use std::path::Path;
     ///
-    /// let args = cmd.build_args();
-    /// assert!(args.contains(&"--continue".to_string()));
-    /// assert!(args.contains(&"-p".to_string()));
-    /// assert!(args.contains(&"Hello".to_string()));
-    /// assert!(args.contains(&"--system-prompt".to_string()));
-    /// assert!(args.contains(&"Be helpful".to_string()));
-    /// assert!(args.contains(&"--model".to_string()));
-    /// assert!(args.contains(&"claude-3-opus".to_string()));
+    /// assert_eq!(ClaudeOptions::default().binary(), Path::new("claude"));
     /// ```
-    pub fn build_args(&self) -> Vec<String> {
-        let mut args = vec!["--continue".to_string(), "-p".to_string(), self.prompt.clone()];
+    pub fn binary(&self) -> &Path {
+        self.claude_bin.as_deref().unwrap_or(Path::new(CLAUDE_BIN))
+    }
+    
+    /// Build the command-line arguments following the prompt
+    ///
+    /// The order is fixed: system prompt, appended system prompt, model,
+    /// output format, additional directories, permission mode, MCP
+    /// configuration, allowed and disallowed tools, then the extra
+    /// arguments. The session is not included; its flags go before the
+    /// prompt (see [`SessionMode::to_args`]).
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = self.prompt_and_output_args();
+        
+        if let Some(mode) = self.permission_mode {
+            args.push("--permission-mode".to_string());
+            args.push(mode.to_string());
+        }
+        
+        if !self.mcp_config.is_empty() {
+            args.push("--mcp-config".to_string());
+            args.extend(self.mcp_config.iter().map(|file| file.display().to_string()));
+        }
+        if self.strict_mcp_config {
+            args.push("--strict-mcp-config".to_string());
+        }
+        
+        // Add allowed and disallowed tools
+        let allowed = match &self.allowed_tools {
+            Some(tools) => tools.clone(),
+            None => ALLOWED_TOOLS.iter().map(|tool| tool.to_string()).collect(),
+        };
+        if !allowed.is_empty() {
+            args.push("--allowedTools".to_string());
+            args.extend(allowed);
+        }
+        if !self.disallowed_tools.is_empty() {
+            args.push("--disallowedTools".to_string());
+            args.extend(self.disallowed_tools.iter().cloned());
+        }
+        
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+    
+    /// Build the command-line arguments without granting any tools
+    ///
+    /// The permission mode, MCP servers and extra arguments are left out
+    /// too, so that no tool runs without asking.
+    fn to_args_without_tools(&self) -> Vec<String> {
+        self.prompt_and_output_args()
+    }
+    
+    /// The system prompt, model, output format and directory arguments
+    fn prompt_and_output_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
         
         if let Some(system_prompt) = &self.system_prompt {
             args.push("--system-prompt".to_string());
@@ -126,15 +574,123 @@ impl ClaudeCommand {
             args.push(model.clone());
         }
         
-        // Add allowed tools
-        args.push("--allowedTools".to_string());
-        args.push("Write".to_string());
-        args.push("Edit".to_string());
+        if self.output == OutputFormat::Json {
+            args.push("--output-format".to_string());
+            args.push("json".to_string());
+        }
+        if matches!(self.output, OutputFormat::StreamJson | OutputFormat::StreamJsonPartial) {
+            args.push("--output-format".to_string());
+            args.push("stream-json".to_string());
+            args.push("--verbose".to_string());
+        }
+        if self.output == OutputFormat::StreamJsonPartial {
+            args.push("--include-partial-messages".to_string());
+        }
+        
+        if !self.add_dirs.is_empty() {
+            args.push("--add-dir".to_string());
+            args.extend(self.add_dirs.iter().map(|dir| dir.display().to_string()));
+        }
         
         args
     }
 }
 
+/// Flags of the claude CLI that claude-dialog sets itself from its own
+/// options, or that would get around the tools and permissions it sets
+pub const RESERVED_FLAGS: &[&str] = &[
+    "-p", "--print",
+    "-c", "--continue",
+    "-r", "--resume",
+    "--session-id",
+    "--fork-session",
+    "--system-prompt",
+    "--append-system-prompt",
+    "--model",
+    "--output-format",
+    "--include-partial-messages",
+    "--permission-mode",
+    "--dangerously-skip-permissions",
+    "--permission-prompt-tool",
+    "--settings",
+    "--mcp-config",
+    "--strict-mcp-config",
+    "--allowedTools", "--allowed-tools",
+    "--disallowedTools", "--disallowed-tools",
+];
+
+/// Check the arguments passed on to the CLI as they are (`--claude-arg`)
+///
+/// Arguments repeating a flag of [`RESERVED_FLAGS`] would override or widen
+/// what the session's options set, e.g. grant tools a policy withholds, so
+/// they are refused. Long flags are matched also with their value after
+/// `=`, short ones also with their value right after them, as in `-rID`.
+///
+/// # Errors
+///
+/// Returns an error naming the first such argument.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_executor::check_extra_args;
+///
+/// assert!(check_extra_args(&["--max-turns".to_string(), "5".to_string()]).is_ok());
+/// assert!(check_extra_args(&["--add-dir".to_string(), "../shared".to_string()]).is_ok());
+/// assert!(check_extra_args(&["--allowedTools=Bash".to_string()]).is_err());
+/// assert!(check_extra_args(&["-r3f2a9c1e".to_string()]).is_err());
+/// ```
+pub fn check_extra_args(args: &[String]) -> Result<()> {
+    for arg in args {
+        if let Some(flag) = reserved_flag(arg) {
+            anyhow::bail!("--claude-arg {} is set by claude-dialog itself; use its own option instead", flag);
+        }
+    }
+    Ok(())
+}
+
+/// The flag of [`RESERVED_FLAGS`] an argument passes, if any
+fn reserved_flag(arg: &str) -> Option<&'static str> {
+    RESERVED_FLAGS.iter().copied().find(|&flag| {
+        if flag.starts_with("--") {
+            arg.strip_prefix(flag).is_some_and(|value| value.is_empty() || value.starts_with('='))
+        } else {
+            !arg.starts_with("--") && arg.starts_with(flag)
+        }
+    })
+}
+
+/// The command line running `binary` with the given arguments
+fn command_line(binary: &Path, args: &[String]) -> String {
+    let mut line = shell_quote(&binary.display().to_string()).into_owned();
+    for arg in args {
+        line.push(' ');
+        line.push_str(&shell_quote(arg));
+    }
+    line
+}
+
+/// Quote an argument for a POSIX shell, leaving it as it is when it needs
+/// no quotes
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_executor::shell_quote;
+///
+/// assert_eq!(shell_quote("--model"), "--model");
+/// assert_eq!(shell_quote("Bash(git log:*)"), "'Bash(git log:*)'");
+/// assert_eq!(shell_quote("it's"), r#"'it'\''s'"#);
+/// assert_eq!(shell_quote(""), "''");
+/// ```
+pub fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return Cow::Borrowed(arg);
+    }
+    Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+}
+
 /// Execute a Claude command asynchronously
 ///
 /// This function builds the command arguments and executes the Claude CLI
@@ -158,123 +714,1098 @@ impl ClaudeCommand {
 /// # Examples
 ///
 /// ```no_run
-/// use claude_dialog::claude_executor::{ClaudeCommand, execute_claude};
+/// use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeOptions, execute_claude};
 ///
 /// # #[tokio::main]
 /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let command = ClaudeCommand {
+/// let command = ClaudeInvocation {
 ///     prompt: "What is 2 + 2?".to_string(),
-///     system_prompt: None,
-///     append_prompt: None,
-///     model: None,
+///     options: ClaudeOptions {
+///         system_prompt: None,
+///         append_prompt: None,
+///         model: None,
+///         ..Default::default()
+///     },
 /// };
 ///
 /// execute_claude(command).await?;
 /// # Ok(())
 /// # }
 /// ```
-pub async fn execute_claude(command: ClaudeCommand) -> Result<()> {
-    let args = command.build_args();
+pub async fn execute_claude(command: ClaudeInvocation) -> Result<()> {
+    let args = command.to_args();
     
-    let mut cmd = Command::new("claude");
+    let mut cmd = claude_command(&command.options)?;
     cmd.args(&args);
     
-    let status = cmd.status()
-        .await
-        .context("Failed to execute claude command")?;
-    
-    if !status.success() {
-        anyhow::bail!("Claude command failed with status: {}", status);
-    }
-    
-    Ok(())
+    wait_for_success(cmd).await
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Response of a Claude command that ran to completion
+///
+/// A command that exits with a failure status still has a response, so its
+/// partial output and error messages can be logged or saved; use
+/// [`ClaudeResponse::error_for_status`] to treat it as an error.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_executor::ClaudeResponse;
+///
+/// let response = ClaudeResponse::new("4");
+/// assert!(response.is_success());
+///
+/// let failed = ClaudeResponse {
+///     text: String::new(),
+///     stderr: "Error: Invalid API key\n".to_string(),
+///     exit_code: Some(1),
+///     session_id: None,
+///     usage: None,
+///     background_usage: None,
+///     truncated: false,
+/// };
+/// let err = failed.error_for_status().unwrap_err();
+/// assert_eq!(err.to_string(), "Claude command failed with exit code 1: Error: Invalid API key");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaudeResponse {
+    /// The response text, only the final answer for
+    /// [`OutputFormat::StreamJson`] commands, or the text of every message
+    /// for [`OutputFormat::StreamJsonPartial`] commands
+    pub text: String,
 
-    #[test]
-    fn test_claude_command_creation() {
-        let cmd = ClaudeCommand {
-            prompt: "test".to_string(),
-            system_prompt: None,
-            append_prompt: None,
-            model: None,
-        };
-        assert_eq!(cmd.prompt, "test");
-    }
+    /// Everything the CLI wrote to standard error
+    pub stderr: String,
 
-    #[test]
-    fn test_build_args_basic() {
-        let cmd = ClaudeCommand {
-            prompt: "Hello, Claude!".to_string(),
-            system_prompt: None,
-            append_prompt: None,
-            model: None,
-        };
-        
-        let args = cmd.build_args();
-        assert_eq!(args[0], "--continue");
-        assert_eq!(args[1], "-p");
-        assert_eq!(args[2], "Hello, Claude!");
-        assert!(args.contains(&"--allowedTools".to_string()));
-        assert!(args.contains(&"Write".to_string()));
-        assert!(args.contains(&"Edit".to_string()));
+    /// Exit code of the CLI, or `None` if it was terminated by a signal
+    pub exit_code: Option<i32>,
+
+    /// ID of the Claude session the turn belongs to, if it was reported
+    pub session_id: Option<String>,
+
+    /// Tokens and cost of the turn, if they were reported
+    pub usage: Option<Usage>,
+
+    /// Tokens and cost of requests the backend made on its own for the
+    /// turn, such as summarizing trimmed history, if they were reported
+    pub background_usage: Option<Usage>,
+
+    /// Whether the answer was cut off at the most tokens allowed
+    /// (`--max-tokens`) rather than finished
+    pub truncated: bool,
+}
+
+impl ClaudeResponse {
+    /// A successful response with the given text and no error output
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            session_id: None,
+            usage: None,
+            background_usage: None,
+            truncated: false,
+        }
     }
 
-    #[test]
-    fn test_build_args_with_system_prompt() {
-        let cmd = ClaudeCommand {
-            prompt: "Test".to_string(),
-            system_prompt: Some("Custom system prompt".to_string()),
-            append_prompt: None,
-            model: None,
-        };
-        
-        let args = cmd.build_args();
-        assert!(args.contains(&"--system-prompt".to_string()));
-        assert!(args.contains(&"Custom system prompt".to_string()));
+    /// Whether the CLI exited successfully
+    pub fn is_success(&self) -> bool {
+        self.exit_code == Some(0)
     }
 
-    #[test]
-    fn test_build_args_with_append_prompt() {
-        let cmd = ClaudeCommand {
-            prompt: "Test".to_string(),
-            system_prompt: None,
-            append_prompt: Some("Additional instructions".to_string()),
-            model: None,
+    /// Return the response if the CLI exited successfully
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with the exit code and the last line of error
+    /// output if the CLI failed.
+    pub fn error_for_status(self) -> Result<Self> {
+        if self.is_success() {
+            return Ok(self);
+        }
+
+        let status = match self.exit_code {
+            Some(code) => format!("exit code {}", code),
+            None => "a signal".to_string(),
         };
-        
-        let args = cmd.build_args();
-        assert!(args.contains(&"--append-system-prompt".to_string()));
-        assert!(args.contains(&"Additional instructions".to_string()));
+        match self.stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => anyhow::bail!("Claude command failed with {}: {}", status, line.trim()),
+            None => anyhow::bail!("Claude command failed with {}", status),
+        }
     }
+}
 
-    #[test]
-    fn test_build_args_with_model() {
-        let cmd = ClaudeCommand {
-            prompt: "Test".to_string(),
-            system_prompt: None,
-            append_prompt: None,
-            model: Some("claude-3-opus".to_string()),
-        };
-        
-        let args = cmd.build_args();
+/// Something that can run Claude commands
+///
+/// # Examples
+///
+/// An executor that answers every prompt by echoing it, e.g. for tests:
+///
+/// ```
+/// use anyhow::Result;
+/// use async_trait::async_trait;
+/// use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeResponse};
+/// use claude_dialog::pipeline::ResponsePipeline;
+///
+/// struct Echo;
+///
+/// #[async_trait]
+/// impl ClaudeExecutor for Echo {
+///     async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+///         pipeline.send(&command.prompt);
+///         pipeline.finish().await?;
+///         Ok(ClaudeResponse::new(command.prompt))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ClaudeExecutor: Send + Sync {
+    /// Run a command, sending its response through `pipeline` as it arrives
+    ///
+    /// For [`OutputFormat::StreamJson`] commands only the final answer is
+    /// sent, for [`OutputFormat::StreamJsonPartial`] commands the text of
+    /// every message. The pipeline must be finished before returning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be run. A command that runs
+    /// but fails is returned as an unsuccessful [`ClaudeResponse`].
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse>;
+
+    /// Run a command only to rebuild conversation context
+    ///
+    /// The response is discarded, and tools must not be used so that replayed
+    /// prompts cannot edit workspace files a second time. The default
+    /// implementation executes the command with an empty pipeline.
+    ///
+    /// # Returns
+    ///
+    /// The response of the command. Its usage, if reported, counts toward
+    /// the session's usage, and its session ID, if reported, is the session
+    /// the conversation continues in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be run or fails.
+    async fn replay(&self, command: ClaudeInvocation) -> Result<ClaudeResponse> {
+        self.execute(command, ResponsePipeline::new()).await
+    }
+}
+
+/// Executor running the `claude` CLI found on `PATH`
+///
+/// Text commands are streamed with [`stream_claude`], json commands with
+/// [`stream_claude_json`], stream-json commands are rendered with
+/// [`stream_claude_final`], or with [`stream_claude_incremental`] if
+/// partial messages are requested, and replays use [`replay_claude`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubprocessExecutor;
+
+#[async_trait]
+impl ClaudeExecutor for SubprocessExecutor {
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+        match command.options.output {
+            OutputFormat::Text => stream_claude(command, pipeline).await,
+            OutputFormat::Json => stream_claude_json(command, pipeline).await,
+            OutputFormat::StreamJson => stream_claude_final(command, pipeline).await,
+            OutputFormat::StreamJsonPartial => stream_claude_incremental(command, pipeline).await,
+        }
+    }
+
+    async fn replay(&self, command: ClaudeInvocation) -> Result<ClaudeResponse> {
+        replay_claude(command).await
+    }
+}
+
+/// Executor printing the command line of each command instead of running it
+///
+/// Used by `--dry-run` to check which prompt, system prompt, model and tool
+/// flags would be passed to the `claude` CLI. Every command succeeds with
+/// an empty response.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation, DryRunExecutor};
+/// use claude_dialog::pipeline::ResponsePipeline;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let command = ClaudeInvocation { prompt: "Hello".to_string(), ..Default::default() };
+/// let response = DryRunExecutor.execute(command, ResponsePipeline::new()).await?;
+/// assert!(response.is_success() && response.text.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DryRunExecutor;
+
+#[async_trait]
+impl ClaudeExecutor for DryRunExecutor {
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+        pipeline.finish().await?;
+        UI::print_command_line(&dry_run_line(&command, command.to_args()));
+        Ok(ClaudeResponse::new(""))
+    }
+
+    async fn replay(&self, command: ClaudeInvocation) -> Result<ClaudeResponse> {
+        let line = dry_run_line(&command, command.to_args_without_tools());
+        UI::print_command_line(&format!("# replayed without tools to rebuild the conversation\n{}", line));
+        Ok(ClaudeResponse::new(""))
+    }
+}
+
+/// What `--dry-run` shows for a command: its command line, run in its
+/// working directory, or where it would go for backends other than Claude
+fn dry_run_line(command: &ClaudeInvocation, args: Vec<String>) -> String {
+    match command.options.backend {
+        Backend::Claude => match &command.options.working_dir {
+            Some(dir) => format!("cd {} && {}", shell_quote(&dir.display().to_string()), command_line(command.options.binary(), &args)),
+            None => command_line(command.options.binary(), &args),
+        },
+        #[cfg(any(feature = "ollama", feature = "api"))]
+        backend => format!(
+            "# sent to {} ({}), not the claude CLI",
+            backend,
+            command.options.model.as_deref().unwrap_or("default model")
+        ),
+    }
+}
+
+/// Execute a Claude command and capture its response
+///
+/// Standard output and standard error are collected instead of being shown,
+/// so the caller decides what to do with the response.
+///
+/// # Returns
+///
+/// * `Result<ClaudeResponse>` - The standard output as is, the error output
+///   and the exit code, also if the command failed
+///
+/// # Errors
+///
+/// Returns an error if the Claude CLI is not found or cannot be executed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use claude_dialog::claude_executor::{ClaudeInvocation, execute_claude_captured};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let command = ClaudeInvocation {
+///     prompt: "Name three Rust web frameworks".to_string(),
+///     ..Default::default()
+/// };
+///
+/// let response = execute_claude_captured(command).await?.error_for_status()?;
+/// println!("{}", response.text.to_uppercase());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn execute_claude_captured(command: ClaudeInvocation) -> Result<ClaudeResponse> {
+    let mut cmd = claude_command(&command.options)?;
+    cmd.args(command.to_args());
+    capture(cmd).await
+}
+
+/// Execute a Claude command and stream its response through a pipeline
+///
+/// Claude's standard output is read as it is produced and every chunk is
+/// sent to the sinks of `pipeline`; standard error is passed through to the
+/// terminal and captured as well. Once Claude exits, the pipeline is
+/// finished so that all sinks have processed the complete response before
+/// this function returns.
+///
+/// # Returns
+///
+/// * `Result<ClaudeResponse>` - The complete response text, the error output
+///   and the exit code, also if the command failed
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The Claude CLI is not found or cannot be executed
+/// - A sink fails to process the response
+///
+/// # Examples
+///
+/// ```no_run
+/// use claude_dialog::claude_executor::{ClaudeInvocation, stream_claude};
+/// use claude_dialog::pipeline::ResponsePipeline;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let command = ClaudeInvocation {
+///     prompt: "What is 2 + 2?".to_string(),
+///     ..Default::default()
+/// };
+///
+/// let response = stream_claude(command, ResponsePipeline::terminal()).await?;
+/// println!("\nReceived {} bytes", response.text.len());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn stream_claude(command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+    let args = command.to_args();
+    
+    let mut child = claude_command(&command.options)?
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute claude command")?;
+    
+    let mut stdout = child
+        .stdout
+        .take()
+        .context("Failed to capture claude output")?;
+    let stderr = tokio::spawn(tee_stderr(child.stderr.take()));
+    
+    let mut decoder = Utf8Decoder::default();
+    let mut response = String::new();
+    let mut buffer = [0u8; 8192];
+    
+    loop {
+        let read = stdout
+            .read(&mut buffer)
+            .await
+            .context("Failed to read claude output")?;
+        let chunk = if read == 0 { decoder.finish() } else { decoder.decode(&buffer[..read]) };
+        
+        pipeline.send(&chunk);
+        response.push_str(&chunk);
+        
+        if read == 0 {
+            break;
+        }
+    }
+    
+    let status = child.wait()
+        .await
+        .context("Failed to execute claude command")?;
+    pipeline.finish().await?;
+    
+    Ok(ClaudeResponse {
+        text: response,
+        stderr: stderr.await.unwrap_or_default(),
+        exit_code: status.code(),
+        session_id: None,
+        usage: None,
+        background_usage: None,
+        truncated: false,
+    })
+}
+
+/// Execute a Claude command and render its response with its usage
+///
+/// The command is run with `json` output, which the Claude CLI prints as a
+/// single result object once the turn is done, so the response is sent
+/// through `pipeline` all at once, as with text output. Output that is not
+/// a result object, e.g. from a wrapper script, is passed on as text.
+///
+/// # Returns
+///
+/// * `Result<ClaudeResponse>` - The response, the error output, the exit
+///   code, and the session ID and usage if they were reported, also if the
+///   command failed. An error result from Claude is returned as a failed
+///   response with the error message in `stderr`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The Claude CLI is not found or cannot be executed
+/// - A sink fails to process the response
+///
+/// # Examples
+///
+/// ```no_run
+/// use claude_dialog::claude_executor::{ClaudeInvocation, stream_claude_json};
+/// use claude_dialog::pipeline::ResponsePipeline;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let command = ClaudeInvocation {
+///     prompt: "What is 2 + 2?".to_string(),
+///     ..Default::default()
+/// };
+///
+/// let response = stream_claude_json(command, ResponsePipeline::terminal()).await?;
+/// if let Some(usage) = response.usage {
+///     println!("\n{} tokens in, {} out", usage.input_tokens, usage.output_tokens);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn stream_claude_json(mut command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+    command.options.output = OutputFormat::Json;
+    let args = command.to_args();
+    
+    let mut child = claude_command(&command.options)?
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute claude command")?;
+    
+    let mut stdout = child
+        .stdout
+        .take()
+        .context("Failed to capture claude output")?;
+    let stderr = tokio::spawn(tee_stderr(child.stderr.take()));
+    
+    let mut output = Vec::new();
+    stdout
+        .read_to_end(&mut output)
+        .await
+        .context("Failed to read claude output")?;
+    let output = String::from_utf8_lossy(&output).into_owned();
+    
+    let status = child.wait()
+        .await
+        .context("Failed to execute claude command")?;
+    
+    let mut result = None;
+    let mut reported = None;
+    for event in stream_json::parse_line(&output) {
+        match event {
+            StreamEvent::Result { text, is_error } => result = Some((text, is_error)),
+            StreamEvent::Usage { session_id, usage } => reported = Some((session_id, usage)),
+            _ => {}
+        }
+    }
+    let (session_id, usage) = reported.unzip();
+    let session_id = session_id.flatten();
+    let mut stderr = stderr.await.unwrap_or_default();
+    
+    match result {
+        Some((message, true)) => {
+            // Report an error result like error output, so it is handled the same
+            pipeline.finish().await?;
+            stderr.push_str(&message);
+            stderr.push('\n');
+            Ok(ClaudeResponse {
+                text: String::new(),
+                stderr,
+                exit_code: status.code().filter(|&code| code != 0).or(Some(1)),
+                session_id,
+                usage,
+                background_usage: None,
+                truncated: false,
+            })
+        }
+        Some((answer, false)) => {
+            pipeline.send(&answer);
+            pipeline.finish().await?;
+            Ok(ClaudeResponse {
+                text: answer,
+                stderr,
+                exit_code: status.code(),
+                session_id,
+                usage,
+                background_usage: None,
+                truncated: false,
+            })
+        }
+        None => {
+            pipeline.send(&output);
+            pipeline.finish().await?;
+            Ok(ClaudeResponse {
+                text: output,
+                stderr,
+                exit_code: status.code(),
+                session_id: None,
+                usage: None,
+                background_usage: None,
+                truncated: false,
+            })
+        }
+    }
+}
+
+/// Execute a Claude command and render only its final answer
+///
+/// Intended for agentic turns: the command is run with `stream-json` output,
+/// intermediate text, thinking and tool calls are not shown, and a spinner
+/// on standard error reports progress with the number of tool calls made so
+/// far. When Claude finishes, only the final assistant message is sent
+/// through `pipeline`.
+///
+/// # Returns
+///
+/// * `Result<ClaudeResponse>` - The final answer, the error output and the
+///   exit code, also if the command failed. An error result from Claude is
+///   returned as a failed response with the error message in `stderr`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The Claude CLI is not found or cannot be executed
+/// - A sink fails to process the response
+///
+/// # Examples
+///
+/// ```no_run
+/// use claude_dialog::claude_executor::{ClaudeInvocation, stream_claude_final};
+/// use claude_dialog::pipeline::ResponsePipeline;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let command = ClaudeInvocation {
+///     prompt: "Fix the failing test".to_string(),
+///     ..Default::default()
+/// };
+///
+/// let answer = stream_claude_final(command, ResponsePipeline::terminal()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn stream_claude_final(mut command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+    command.options.output = OutputFormat::StreamJson;
+    let args = command.to_args();
+    
+    let mut child = claude_command(&command.options)?
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute claude command")?;
+    
+    let stdout = child
+        .stdout
+        .take()
+        .context("Failed to capture claude output")?;
+    let stderr = tokio::spawn(tee_stderr(child.stderr.take()));
+    let mut lines = BufReader::new(stdout).lines();
+    
+    let mut activity = Activity::default();
+    let mut spinner = Spinner::new();
+    let mut ticker = tokio::time::interval(SPINNER_INTERVAL);
+    let mut last_text = None;
+    let mut result = None;
+    let mut reported = None;
+    
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line.context("Failed to read claude output")? else {
+                    break;
+                };
+                for event in stream_json::parse_line(&line) {
+                    activity.record(&event);
+                    match event {
+                        StreamEvent::Text(text) => last_text = Some(text),
+                        StreamEvent::Result { text, is_error } => result = Some((text, is_error)),
+                        StreamEvent::Usage { session_id, usage } => reported = Some((session_id, usage)),
+                        StreamEvent::TextDelta(_) | StreamEvent::Thinking | StreamEvent::ToolUse { .. } => {}
+                    }
+                }
+            }
+            _ = ticker.tick() => spinner.tick(&activity.to_string()),
+        }
+    }
+    spinner.clear();
+    
+    let status = child.wait()
+        .await
+        .context("Failed to execute claude command")?;
+    
+    let (answer, is_error) = result
+        .or_else(|| last_text.map(|text| (text, false)))
+        .unwrap_or_default();
+    if !is_error {
+        pipeline.send(&answer);
+    }
+    pipeline.finish().await?;
+    
+    let mut stderr = stderr.await.unwrap_or_default();
+    let (session_id, usage) = reported.unzip();
+    if is_error {
+        // Report an error result like error output, so it is handled the same
+        stderr.push_str(&answer);
+        stderr.push('\n');
+        return Ok(ClaudeResponse {
+            text: String::new(),
+            stderr,
+            exit_code: status.code().filter(|&code| code != 0).or(Some(1)),
+            session_id: session_id.flatten(),
+            usage,
+            background_usage: None,
+            truncated: false,
+        });
+    }
+    
+    Ok(ClaudeResponse {
+        text: answer,
+        stderr,
+        exit_code: status.code(),
+        session_id: session_id.flatten(),
+        usage,
+        background_usage: None,
+        truncated: false,
+    })
+}
+
+/// Execute a Claude command and render its text as it is generated
+///
+/// The command is run with `stream-json` output and partial messages, and
+/// every piece of assistant text is sent through `pipeline` as soon as its
+/// event arrives, so the response appears progressively instead of all at
+/// once when Claude exits. Text from every message of the turn is shown,
+/// separated by blank lines; thinking and tool calls are not.
+///
+/// # Returns
+///
+/// * `Result<ClaudeResponse>` - All text shown, the error output and the
+///   exit code, also if the command failed. An error result from Claude is
+///   returned as a failed response with the error message in `stderr`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The Claude CLI is not found or cannot be executed
+/// - A sink fails to process the response
+///
+/// # Examples
+///
+/// ```no_run
+/// use claude_dialog::claude_executor::{ClaudeInvocation, stream_claude_incremental};
+/// use claude_dialog::pipeline::ResponsePipeline;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let command = ClaudeInvocation {
+///     prompt: "Write a haiku about ownership".to_string(),
+///     ..Default::default()
+/// };
+///
+/// let response = stream_claude_incremental(command, ResponsePipeline::terminal()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn stream_claude_incremental(mut command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+    command.options.output = OutputFormat::StreamJsonPartial;
+    let args = command.to_args();
+    
+    let mut child = claude_command(&command.options)?
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute claude command")?;
+    
+    let stdout = child
+        .stdout
+        .take()
+        .context("Failed to capture claude output")?;
+    let stderr = tokio::spawn(tee_stderr(child.stderr.take()));
+    let mut lines = BufReader::new(stdout).lines();
+    
+    let mut text = IncrementalText::default();
+    let mut error = None;
+    let mut reported = None;
+    while let Some(line) = lines.next_line().await.context("Failed to read claude output")? {
+        for event in stream_json::parse_line(&line) {
+            match &event {
+                StreamEvent::Result { text: message, is_error: true } => error = Some(message.clone()),
+                StreamEvent::Usage { session_id, usage } => reported = Some((session_id.clone(), *usage)),
+                _ => {}
+            }
+            if let Some(chunk) = text.push(&event) {
+                pipeline.send(&chunk);
+            }
+        }
+    }
+    
+    let status = child.wait()
+        .await
+        .context("Failed to execute claude command")?;
+    pipeline.finish().await?;
+    
+    let mut stderr = stderr.await.unwrap_or_default();
+    let mut exit_code = status.code();
+    if let Some(message) = error {
+        // Report an error result like error output, so it is handled the same
+        stderr.push_str(&message);
+        stderr.push('\n');
+        exit_code = exit_code.filter(|&code| code != 0).or(Some(1));
+    }
+    
+    let (session_id, usage) = reported.unzip();
+    Ok(ClaudeResponse {
+        text: text.text().to_string(),
+        stderr,
+        exit_code,
+        session_id: session_id.flatten(),
+        usage,
+        background_usage: None,
+        truncated: false,
+    })
+}
+
+/// Replay a Claude command without printing its response
+///
+/// Used to rebuild conversation context in a fresh session, for example when
+/// restoring a checkpoint. Claude's answer is discarded and no tools are
+/// allowed, so replayed prompts cannot edit workspace files a second time.
+/// Errors are still reported on standard error.
+///
+/// # Returns
+///
+/// A response without text, carrying the session ID and the tokens and cost
+/// Claude reported for the replayed prompt.
+///
+/// # Errors
+///
+/// Returns an error under the same conditions as [`execute_claude`].
+pub async fn replay_claude(mut command: ClaudeInvocation) -> Result<ClaudeResponse> {
+    // The json output reports the usage of the prompt
+    command.options.output = OutputFormat::Json;
+    let args = command.to_args_without_tools();
+    
+    let output = claude_command(&command.options)?
+        .args(&args)
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to execute claude command")?;
+    
+    if !output.status.success() {
+        anyhow::bail!("Claude command failed with status: {}", output.status);
+    }
+    
+    let mut response = ClaudeResponse::new("");
+    response.exit_code = output.status.code();
+    for event in stream_json::parse_line(&String::from_utf8_lossy(&output.stdout)) {
+        if let StreamEvent::Usage { session_id, usage } = event {
+            response.session_id = session_id;
+            response.usage = Some(usage);
+        }
+    }
+    Ok(response)
+}
+
+/// The `claude` command, or the `--claude-bin` binary, run in the working
+/// directory of `options`
+///
+/// # Errors
+///
+/// Returns an error if the working directory does not exist, which the
+/// operating system would otherwise report as a missing `claude` CLI.
+fn claude_command(options: &ClaudeOptions) -> Result<Command> {
+    let mut cmd = Command::new(options.binary());
+    if let Some(dir) = &options.working_dir {
+        if !dir.is_dir() {
+            anyhow::bail!("The working directory {} does not exist", dir.display());
+        }
+        cmd.current_dir(dir);
+    }
+    Ok(cmd)
+}
+
+/// Run a command with its standard output and error collected
+async fn capture(mut cmd: Command) -> Result<ClaudeResponse> {
+    let output = cmd
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to execute claude command")?;
+    
+    Ok(ClaudeResponse {
+        text: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        exit_code: output.status.code(),
+        session_id: None,
+        usage: None,
+        background_usage: None,
+        truncated: false,
+    })
+}
+
+/// Pass a child's error output through to the terminal while collecting it
+async fn tee_stderr(stderr: Option<ChildStderr>) -> String {
+    let Some(mut stderr) = stderr else {
+        return String::new();
+    };
+    
+    let mut captured = Vec::new();
+    let mut buffer = [0u8; 4096];
+    while let Ok(read) = stderr.read(&mut buffer).await {
+        if read == 0 {
+            break;
+        }
+        let _ = std::io::stderr().write_all(&buffer[..read]);
+        captured.extend_from_slice(&buffer[..read]);
+    }
+    
+    String::from_utf8_lossy(&captured).into_owned()
+}
+
+async fn wait_for_success(mut cmd: Command) -> Result<()> {
+    let status = cmd.kill_on_drop(true)
+        .status()
+        .await
+        .context("Failed to execute claude command")?;
+    
+    if !status.success() {
+        anyhow::bail!("Claude command failed with status: {}", status);
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claude_command_creation() {
+        let cmd = ClaudeInvocation {
+            prompt: "test".to_string(),
+            options: ClaudeOptions {
+                system_prompt: None,
+                append_prompt: None,
+                model: None,
+                temperature: None,
+                max_tokens: None,
+                trim_policy: TrimPolicy::DropOldest,
+                no_prompt_cache: false,
+                history_budget: None,
+                num_ctx: None,
+                session: SessionMode::Continue,
+                output: OutputFormat::Text,
+                backend: Backend::Claude,
+                allowed_tools: None,
+                disallowed_tools: Vec::new(),
+                add_dirs: Vec::new(),
+                permission_mode: None,
+                mcp_config: Vec::new(),
+                strict_mcp_config: false,
+                extra_args: Vec::new(),
+                working_dir: None,
+                claude_bin: None,
+            },
+        };
+        assert_eq!(cmd.prompt, "test");
+    }
+
+    #[test]
+    fn test_to_args_configured_tools() {
+        let cmd = ClaudeInvocation {
+            prompt: "Hello".to_string(),
+            options: ClaudeOptions {
+                allowed_tools: Some(vec!["Read".to_string(), "Bash(git log:*)".to_string()]),
+                disallowed_tools: vec!["WebFetch".to_string()],
+                ..Default::default()
+            },
+        };
+        let args = cmd.to_args();
+        let allowed = args.iter().position(|arg| arg == "--allowedTools").unwrap();
+        assert_eq!(args[allowed + 1..allowed + 3], ["Read", "Bash(git log:*)"]);
+        assert_eq!(args[allowed + 3..], ["--disallowedTools", "WebFetch"]);
+
+        let options = ClaudeOptions { allowed_tools: Some(Vec::new()), disallowed_tools: Vec::new(), ..cmd.options };
+        let cmd = ClaudeInvocation { options, ..cmd };
+        assert!(!cmd.to_args().iter().any(|arg| arg.ends_with("Tools")));
+    }
+
+    #[test]
+    fn test_to_args_stream_json() {
+        let cmd = ClaudeInvocation {
+            prompt: "Refactor".to_string(),
+            options: ClaudeOptions {
+                output: OutputFormat::StreamJson,
+                ..Default::default()
+            },
+        };
+        let args = cmd.to_args();
+        let format = args.iter().position(|arg| arg == "--output-format").unwrap();
+        assert_eq!(args[format + 1], "stream-json");
+        assert!(args.contains(&"--verbose".to_string()));
+        assert!(!args.contains(&"--include-partial-messages".to_string()));
+
+        let cmd = ClaudeInvocation { options: ClaudeOptions { output: OutputFormat::StreamJsonPartial, ..cmd.options }, ..cmd };
+        let args = cmd.to_args();
+        assert!(args.contains(&"stream-json".to_string()));
+        assert!(args.contains(&"--include-partial-messages".to_string()));
+
+        let cmd = ClaudeInvocation { options: ClaudeOptions { output: OutputFormat::Json, ..cmd.options }, ..cmd };
+        let args = cmd.to_args();
+        let format = args.iter().position(|arg| arg == "--output-format").unwrap();
+        assert_eq!(args[format + 1], "json");
+        assert!(!args.contains(&"--verbose".to_string()));
+    }
+
+    #[test]
+    fn test_options_argument_order() {
+        let cmd = ClaudeInvocation {
+            prompt: "Plan it".to_string(),
+            options: ClaudeOptions {
+                extra_args: vec!["--max-turns".to_string(), "3".to_string()],
+                disallowed_tools: vec!["WebFetch".to_string()],
+                allowed_tools: Some(vec!["Read".to_string()]),
+                permission_mode: Some(PermissionMode::AcceptEdits),
+                mcp_config: vec![PathBuf::from("mcp.json")],
+                strict_mcp_config: true,
+                add_dirs: vec![PathBuf::from("../shared"), PathBuf::from("/tmp/notes")],
+                output: OutputFormat::Json,
+                model: Some("opus".to_string()),
+                temperature: Some(0.2),
+                max_tokens: Some(1024),
+                trim_policy: TrimPolicy::SummarizeOldest,
+                no_prompt_cache: false,
+                history_budget: None,
+                num_ctx: None,
+                append_prompt: Some("Be brief.".to_string()),
+                system_prompt: Some("You review code.".to_string()),
+                session: SessionMode::Resume("3f2a9c1e".to_string()),
+                backend: Backend::Claude,
+                working_dir: Some(PathBuf::from("/srv/shop")),
+                claude_bin: Some(PathBuf::from("/opt/claude/bin/claude")),
+            },
+        };
+        assert_eq!(
+            cmd.to_args(),
+            [
+                "--resume", "3f2a9c1e", "-p", "Plan it",
+                "--system-prompt", "You review code.",
+                "--append-system-prompt", "Be brief.",
+                "--model", "opus",
+                "--output-format", "json",
+                "--add-dir", "../shared", "/tmp/notes",
+                "--permission-mode", "acceptEdits",
+                "--mcp-config", "mcp.json", "--strict-mcp-config",
+                "--allowedTools", "Read",
+                "--disallowedTools", "WebFetch",
+                "--max-turns", "3",
+            ]
+        );
+
+        // Replays grant no tools, not even through the permission mode or
+        // the extra arguments
+        assert_eq!(cmd.to_args_without_tools()[12..], ["--add-dir", "../shared", "/tmp/notes"]);
+        assert!(cmd.command_line().starts_with("/opt/claude/bin/claude --resume 3f2a9c1e"));
+    }
+
+    #[test]
+    fn test_check_extra_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert!(check_extra_args(&args(&["--max-turns", "3", "--debug", "--settingsfile"])).is_ok());
+        assert!(check_extra_args(&args(&["--verbose"])).is_ok());
+        assert!(check_extra_args(&args(&["--add-dir", "../shared", "/tmp/notes"])).is_ok());
+        assert!(check_extra_args(&args(&["--add-dir=../shared"])).is_ok());
+        for (reserved, flag) in [
+            (&["--dangerously-skip-permissions"][..], "--dangerously-skip-permissions"),
+            (&["--permission-mode", "bypassPermissions"], "--permission-mode"),
+            (&["--allowedTools", "Bash"], "--allowedTools"),
+            (&["--allowedTools=Bash"], "--allowedTools"),
+            (&["-r", "abc"], "-r"),
+            (&["-rabc"], "-r"),
+            (&["-cp"], "-c"),
+            (&["--settings", "bypass.json"], "--settings"),
+            (&["--settings={\"permissions\":{}}"], "--settings"),
+            (&["--permission-prompt-tool", "mcp__approve__all"], "--permission-prompt-tool"),
+            (&["--session-id", "3f2a9c1e-0000-4000-8000-000000000000"], "--session-id"),
+            (&["--resume", "abc", "--fork-session"], "--resume"),
+            (&["--fork-session"], "--fork-session"),
+        ] {
+            let err = check_extra_args(&args(reserved)).unwrap_err();
+            assert!(err.to_string().starts_with(&format!("--claude-arg {} is set by claude-dialog", flag)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_to_args_basic() {
+        let cmd = ClaudeInvocation {
+            prompt: "Hello, Claude!".to_string(),
+            options: ClaudeOptions {
+                system_prompt: None,
+                append_prompt: None,
+                model: None,
+                ..Default::default()
+            },
+        };
+        
+        let args = cmd.to_args();
+        assert_eq!(args[0], "--continue");
+        assert_eq!(args[1], "-p");
+        assert_eq!(args[2], "Hello, Claude!");
+        assert!(args.contains(&"--allowedTools".to_string()));
+        assert!(args.contains(&"Write".to_string()));
+        assert!(args.contains(&"Edit".to_string()));
+    }
+
+    #[test]
+    fn test_to_args_with_system_prompt() {
+        let cmd = ClaudeInvocation {
+            prompt: "Test".to_string(),
+            options: ClaudeOptions {
+                system_prompt: Some("Custom system prompt".to_string()),
+                append_prompt: None,
+                model: None,
+                ..Default::default()
+            },
+        };
+        
+        let args = cmd.to_args();
+        assert!(args.contains(&"--system-prompt".to_string()));
+        assert!(args.contains(&"Custom system prompt".to_string()));
+    }
+
+    #[test]
+    fn test_to_args_with_append_prompt() {
+        let cmd = ClaudeInvocation {
+            prompt: "Test".to_string(),
+            options: ClaudeOptions {
+                system_prompt: None,
+                append_prompt: Some("Additional instructions".to_string()),
+                model: None,
+                ..Default::default()
+            },
+        };
+        
+        let args = cmd.to_args();
+        assert!(args.contains(&"--append-system-prompt".to_string()));
+        assert!(args.contains(&"Additional instructions".to_string()));
+    }
+
+    #[test]
+    fn test_to_args_with_model() {
+        let cmd = ClaudeInvocation {
+            prompt: "Test".to_string(),
+            options: ClaudeOptions {
+                system_prompt: None,
+                append_prompt: None,
+                model: Some("claude-3-opus".to_string()),
+                ..Default::default()
+            },
+        };
+        
+        let args = cmd.to_args();
         assert!(args.contains(&"--model".to_string()));
         assert!(args.contains(&"claude-3-opus".to_string()));
     }
 
     #[test]
-    fn test_build_args_full() {
-        let cmd = ClaudeCommand {
+    fn test_to_args_full() {
+        let cmd = ClaudeInvocation {
             prompt: "Complex test".to_string(),
-            system_prompt: Some("System".to_string()),
-            append_prompt: Some("Append".to_string()),
-            model: Some("claude-3-sonnet".to_string()),
+            options: ClaudeOptions {
+                system_prompt: Some("System".to_string()),
+                append_prompt: Some("Append".to_string()),
+                model: Some("claude-3-sonnet".to_string()),
+                ..Default::default()
+            },
         };
         
-        let args = cmd.build_args();
+        let args = cmd.to_args();
         assert!(args.contains(&"Complex test".to_string()));
         assert!(args.contains(&"--system-prompt".to_string()));
         assert!(args.contains(&"System".to_string()));
@@ -283,4 +1814,69 @@ mod tests {
         assert!(args.contains(&"--model".to_string()));
         assert!(args.contains(&"claude-3-sonnet".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_to_args_fresh_session() {
+        let cmd = ClaudeInvocation {
+            prompt: "Replay".to_string(),
+            options: ClaudeOptions {
+                session: SessionMode::Fresh,
+                ..Default::default()
+            },
+        };
+        
+        let args = cmd.to_args();
+        assert!(!args.contains(&"--continue".to_string()));
+        assert_eq!(args[0], "-p");
+        assert_eq!(args[1], "Replay");
+    }
+
+    #[test]
+    fn test_to_args_resumed_session() {
+        let cmd = ClaudeInvocation {
+            prompt: "Where were we?".to_string(),
+            options: ClaudeOptions {
+                session: SessionMode::Resume("3f2a9c1e".to_string()),
+                ..Default::default()
+            },
+        };
+        
+        let args = cmd.to_args();
+        assert_eq!(args[..4], ["--resume", "3f2a9c1e", "-p", "Where were we?"]);
+        assert!(!args.contains(&"--continue".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_capture_collects_output_and_exit_code() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo answer; echo warning >&2; exit 3"]);
+        
+        let response = capture(cmd).await.unwrap();
+        assert_eq!(response.text, "answer\n");
+        assert_eq!(response.stderr, "warning\n");
+        assert_eq!(response.exit_code, Some(3));
+        assert!(!response.is_success());
+    }
+
+    #[test]
+    fn test_error_for_status() {
+        assert!(ClaudeResponse::new("ok").error_for_status().is_ok());
+        
+        let failed = ClaudeResponse { text: String::new(), stderr: String::new(), exit_code: None, ..ClaudeResponse::new("") };
+        let err = failed.error_for_status().unwrap_err();
+        assert_eq!(err.to_string(), "Claude command failed with a signal");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("stream-json"), "stream-json");
+        assert_eq!(shell_quote("claude-3-5-sonnet@20241022"), "claude-3-5-sonnet@20241022");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("$HOME `id` \"x\""), "'$HOME `id` \"x\"'");
+        assert_eq!(shell_quote("line\nbreak"), "'line\nbreak'");
+        assert_eq!(shell_quote("'"), r#"''\'''"#);
+        assert_eq!(command_line(Path::new("claude"), &["-p".to_string(), "*".to_string()]), "claude -p '*'");
+        assert_eq!(command_line(Path::new("/opt/claude beta/claude"), &[]), "'/opt/claude beta/claude'");
+    }
+}
@@ -0,0 +1,307 @@
+//! Claude CLI version detection and capability gating module
+//!
+//! The options `claude-dialog` passes to the Claude CLI were introduced over
+//! several releases. On startup the installed version is detected with
+//! `claude --version` (cached per binary, so the CLI only runs again after it
+//! is updated) and the features the wrapper uses are checked against it.
+//! Unsupported options can then be dropped with a warning up front instead of
+//! failing mid-conversation with an unknown-flag error.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::claude_version::{Capabilities, Capability, ClaudeVersion};
+//!
+//! let version: ClaudeVersion = "1.0.35 (Claude Code)".parse().unwrap();
+//! assert_eq!(version, ClaudeVersion::new(1, 0, 35));
+//!
+//! let capabilities = Capabilities::for_version(Some(version));
+//! assert!(capabilities.supports(Capability::AppendSystemPrompt));
+//!
+//! // Without a known version nothing is gated
+//! assert!(Capabilities::for_version(None).supports(Capability::StreamJsonOutput));
+//! ```
+
+use anyhow::{Result, Context, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+/// Oldest Claude CLI release `claude-dialog` is known to work with
+pub const MINIMUM_VERSION: ClaudeVersion = ClaudeVersion::new(1, 0, 0);
+
+/// Name of the cache file storing the detected version
+const CACHE_FILE: &str = "claude-version.json";
+
+/// A Claude CLI version number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ClaudeVersion {
+    /// Major version
+    pub major: u64,
+    /// Minor version
+    pub minor: u64,
+    /// Patch version
+    pub patch: u64,
+}
+
+impl ClaudeVersion {
+    /// Create a version from its components
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self { major, minor, patch }
+    }
+}
+
+impl fmt::Display for ClaudeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for ClaudeVersion {
+    type Err = anyhow::Error;
+
+    /// Parse the output of `claude --version`, e.g. `1.0.35 (Claude Code)`
+    fn from_str(s: &str) -> Result<Self> {
+        let token = s
+            .split_whitespace()
+            .map(|word| word.trim_start_matches('v'))
+            .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+            .ok_or_else(|| anyhow!("No version number in '{}'", s.trim()))?;
+
+        // Ignore pre-release and build suffixes such as `-beta.1`
+        let core = token.split(['-', '+']).next().unwrap_or(token);
+        let mut parts = core.split('.').map(|part| {
+            part.parse::<u64>()
+                .map_err(|_| anyhow!("Invalid version number '{}'", token))
+        });
+
+        let major = parts.next().transpose()?.unwrap_or(0);
+        let minor = parts.next().transpose()?.unwrap_or(0);
+        let patch = parts.next().transpose()?.unwrap_or(0);
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+/// A Claude CLI feature used by `claude-dialog`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// `--system-prompt` in print mode
+    SystemPrompt,
+    /// `--append-system-prompt` in print mode
+    AppendSystemPrompt,
+    /// `--model`
+    Model,
+    /// `--output-format stream-json`
+    StreamJsonOutput,
+    /// `--include-partial-messages` with `stream-json` output
+    PartialMessages,
+}
+
+impl Capability {
+    /// The command-line flag providing this capability
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Capability::SystemPrompt => "--system-prompt",
+            Capability::AppendSystemPrompt => "--append-system-prompt",
+            Capability::Model => "--model",
+            Capability::StreamJsonOutput => "--output-format stream-json",
+            Capability::PartialMessages => "--include-partial-messages",
+        }
+    }
+
+    /// First Claude CLI version known to support this capability
+    pub fn minimum_version(&self) -> ClaudeVersion {
+        match self {
+            Capability::SystemPrompt => ClaudeVersion::new(1, 0, 0),
+            Capability::AppendSystemPrompt => ClaudeVersion::new(1, 0, 0),
+            Capability::Model => ClaudeVersion::new(1, 0, 0),
+            Capability::StreamJsonOutput => ClaudeVersion::new(1, 0, 0),
+            Capability::PartialMessages => ClaudeVersion::new(1, 0, 86),
+        }
+    }
+}
+
+/// The set of capabilities offered by the installed Claude CLI
+///
+/// When the version is unknown every capability is assumed to be supported,
+/// leaving it to the CLI to reject anything it does not understand.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    version: Option<ClaudeVersion>,
+}
+
+impl Capabilities {
+    /// Capabilities of the given CLI version
+    pub fn for_version(version: Option<ClaudeVersion>) -> Self {
+        Self { version }
+    }
+
+    /// The detected CLI version, if known
+    pub fn version(&self) -> Option<ClaudeVersion> {
+        self.version
+    }
+
+    /// Whether the CLI supports a capability
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.version
+            .is_none_or(|version| version >= capability.minimum_version())
+    }
+
+    /// Warnings about a CLI version known to be incompatible
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::claude_version::{Capabilities, ClaudeVersion};
+    ///
+    /// let old = Capabilities::for_version(Some(ClaudeVersion::new(0, 2, 9)));
+    /// assert_eq!(old.compatibility_warnings().len(), 1);
+    ///
+    /// let current = Capabilities::for_version(Some(ClaudeVersion::new(1, 0, 35)));
+    /// assert!(current.compatibility_warnings().is_empty());
+    /// ```
+    pub fn compatibility_warnings(&self) -> Vec<String> {
+        match self.version {
+            Some(version) if version < MINIMUM_VERSION => vec![format!(
+                "claude CLI {} is older than {}, the oldest version claude-dialog supports; please update it",
+                version, MINIMUM_VERSION
+            )],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Cached result of running `claude --version` for one binary
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    binary: PathBuf,
+    modified: u64,
+    version: ClaudeVersion,
+}
+
+/// Run `<binary> --version` and parse its output
+///
+/// # Errors
+///
+/// Returns an error if the binary cannot be run, exits unsuccessfully or
+/// prints something that is not a version number.
+pub fn detect_version(binary: &Path) -> Result<ClaudeVersion> {
+    let output = Command::new(binary)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Failed to run {} --version", binary.display()))?;
+
+    if !output.status.success() {
+        bail!("{} --version failed with status: {}", binary.display(), output.status);
+    }
+
+    String::from_utf8_lossy(&output.stdout).parse()
+}
+
+/// Detect the version of the Claude CLI `binary`, using a cache
+///
+/// `binary` is looked up as by [`find_claude`].
+/// The cache is keyed by the binary's path and modification time, so the
+/// CLI is only run again after it is updated or replaced. Cache problems are
+/// ignored; they only cost an extra `claude --version` run. The cache
+/// normally lives in [`Paths::cache_dir`](crate::paths::Paths::cache_dir).
+///
+/// # Errors
+///
+/// Returns an error if the binary is not found or its version cannot be
+/// determined.
+pub fn detect_cached(binary: &Path, cache_dir: Option<&Path>) -> Result<ClaudeVersion> {
+    let binary = find_claude(binary).ok_or_else(|| anyhow!("claude CLI {} not found", binary.display()))?;
+    let modified = fs::metadata(&binary)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let cache_path = cache_dir.map(|dir| dir.join(CACHE_FILE));
+    let cached = cache_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<CacheEntry>(&json).ok())
+        .filter(|entry| entry.binary == binary && entry.modified == modified);
+    if let Some(entry) = cached {
+        return Ok(entry.version);
+    }
+
+    let version = detect_version(&binary)?;
+
+    if let Some(path) = cache_path {
+        let entry = CacheEntry { binary, modified, version };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&entry) {
+            let _ = fs::write(path, json);
+        }
+    }
+
+    Ok(version)
+}
+
+/// Locate the Claude CLI executable
+///
+/// A bare name such as [`CLAUDE_BIN`](crate::claude_executor::CLAUDE_BIN) is looked up on `PATH`, as the
+/// operating system does when running it; a path is taken as it is.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::claude_version::find_claude;
+/// use std::path::Path;
+///
+/// assert!(find_claude(Path::new("/nonexistent/claude")).is_none());
+/// ```
+pub fn find_claude(binary: &Path) -> Option<PathBuf> {
+    if binary.components().count() > 1 {
+        return binary.is_file().then(|| binary.to_path_buf());
+    }
+    let mut name = binary.as_os_str().to_owned();
+    if binary.extension().is_none() {
+        name.push(env::consts::EXE_SUFFIX);
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&name))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!("1.0.35 (Claude Code)".parse::<ClaudeVersion>().unwrap(), ClaudeVersion::new(1, 0, 35));
+        assert_eq!("claude v2.1\n".parse::<ClaudeVersion>().unwrap(), ClaudeVersion::new(2, 1, 0));
+        assert_eq!("1.2.3-beta.4".parse::<ClaudeVersion>().unwrap(), ClaudeVersion::new(1, 2, 3));
+        assert!("Claude Code".parse::<ClaudeVersion>().is_err());
+        assert!("1.x.3".parse::<ClaudeVersion>().is_err());
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        assert!(ClaudeVersion::new(1, 0, 10) > ClaudeVersion::new(1, 0, 9));
+        assert!(ClaudeVersion::new(0, 9, 99) < MINIMUM_VERSION);
+        assert_eq!(ClaudeVersion::new(1, 2, 3).to_string(), "1.2.3");
+    }
+
+    #[test]
+    fn test_old_version_lacks_capabilities() {
+        let capabilities = Capabilities::for_version(Some(ClaudeVersion::new(0, 2, 0)));
+        assert!(!capabilities.supports(Capability::AppendSystemPrompt));
+        assert!(!capabilities.supports(Capability::StreamJsonOutput));
+
+        let capabilities = Capabilities::for_version(Some(ClaudeVersion::new(1, 0, 35)));
+        assert!(capabilities.supports(Capability::StreamJsonOutput));
+        assert!(!capabilities.supports(Capability::PartialMessages));
+    }
+}
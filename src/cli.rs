@@ -2,23 +2,55 @@
 //!
 //! This module handles parsing and validation of command-line arguments using the `clap` crate.
 //! It supports specifying custom system prompts, model selection, and prompt modification options.
+//! Arguments are accepted as `OsString`s, so prompt file paths that are not
+//! valid UTF-8 are preserved. File arguments have `~` and environment
+//! variables expanded and are checked for readability while parsing, so a
+//! mistyped path is reported as a usage error before anything else runs.
 //!
 //! # Examples
 //!
 //! ```
 //! use claude_dialog::cli::{Args, parse_args};
+//! use std::path::PathBuf;
 //!
+//! # let dir = tempfile::tempdir().unwrap();
+//! # let custom = dir.path().join("custom.md");
+//! # std::fs::write(&custom, "You are a Rust expert.").unwrap();
+//! # let custom = custom.to_str().unwrap();
 //! // Parse arguments with a custom system prompt
-//! let args = parse_args(vec!["claude-dialog", "--system-prompt", "custom.md"]).unwrap();
-//! assert_eq!(args.system_prompt_files, vec!["custom.md"]);
+//! let args = parse_args(vec!["claude-dialog", "--system-prompt", custom]).unwrap();
+//! assert_eq!(args.system_prompt_files, vec![PathBuf::from(custom)]);
+//!
+//! // Prompt files must exist
+//! assert!(parse_args(vec!["claude-dialog", "--system-prompt", "missing.md"]).is_err());
 //!
 //! // Parse arguments with a model specification
 //! let args = parse_args(vec!["claude-dialog", "--model", "claude-3-opus"]).unwrap();
 //! assert_eq!(args.model, Some("claude-3-opus".to_string()));
 //! ```
 
-use clap::Parser;
+use clap::builder::{NonEmptyStringValueParser, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::{Arg, ArgGroup, Parser, Subcommand};
 use anyhow::{Result, anyhow};
+use chrono::TimeDelta;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use crate::archive;
+use crate::claude_executor::{Backend, SessionMode, TrimPolicy};
+use crate::commands::CommandAlias;
+use crate::context::{BudgetStrategy, DEFAULT_CONTEXT_BUDGET};
+use crate::export::ImportMode;
+use crate::history;
+use crate::language::Language;
+use crate::mask::Masker;
+use crate::output::PrintFormat;
+use crate::paths;
+use crate::persona::Persona;
+use crate::rate_limit;
+use crate::scripted::TestBackend;
+use crate::ui::BannerStyle;
 
 /// Command-line arguments for the Claude Dialog application
 ///
@@ -31,9 +63,70 @@ use anyhow::{Result, anyhow};
 /// use claude_dialog::cli::Args;
 ///
 /// let args = Args {
-///     system_prompt_files: vec!["prompt1.md".to_string(), "prompt2.md".to_string()],
+///     system_prompt_files: vec!["prompt1.md".into(), "prompt2.md".into()],
 ///     append_prompt_file: None,
+///     recursive: false,
+///     preset: None,
 ///     model: Some("claude-3-opus".to_string()),
+///     backend: Default::default(),
+///     temperature: None,
+///     max_tokens: None,
+///     trim_policy: Default::default(),
+///     no_prompt_cache: false,
+///     history_budget: None,
+///     num_ctx: None,
+///     resume: None,
+///     new_session: false,
+///     recover: false,
+///     personas: vec![],
+///     allowed_tools: vec![],
+///     disallowed_tools: vec![],
+///     mcp_config: vec![],
+///     strict_mcp_config: false,
+///     claude_bin: None,
+///     claude_args: vec![],
+///     aliases: vec![],
+///     banner: Default::default(),
+///     final_only: false,
+///     stream: false,
+///     raw: false,
+///     no_highlight: false,
+///     quiz: false,
+///     show_usage: false,
+///     dry_run: false,
+///     test_backend: None,
+///     mask_pii: false,
+///     mask_words: vec![],
+///     mask_patterns: vec![],
+///     mask_transcripts: false,
+///     context_budget: 100_000,
+///     context_strategy: Default::default(),
+///     cwd: None,
+///     repo_map: false,
+///     attachments: vec![],
+///     language: None,
+///     spellcheck: false,
+///     dictionaries: vec![],
+///     send_unfinished: false,
+///     turn_metadata: false,
+///     suggest_follow_ups: false,
+///     from: None,
+///     from_mode: Default::default(),
+///     private: false,
+///     incognito: false,
+///     save_transcript: None,
+///     record_cast: None,
+///     tee: None,
+///     history_ignore: vec![],
+///     rate_limit_retries: 3,
+///     timeout: None,
+///     prompt: None,
+///     batch: None,
+///     batch_output: None,
+///     repeat: 1,
+///     output_format: Default::default(),
+///     profile: None,
+///     command: None,
 /// };
 ///
 /// assert_eq!(args.system_prompt_files.len(), 2);
@@ -44,21 +137,25 @@ use anyhow::{Result, anyhow};
     name = "claude-dialog",
     about = "Interactive CLI for Claude conversations with custom system prompts",
     long_about = None,
-    version
+    version,
+    // The options sending prompts without a conversation
+    group(ArgGroup::new("non_interactive").args(["prompt", "batch"]))
 )]
 pub struct Args {
     /// Custom system prompt files (can be specified multiple times)
     ///
     /// These files will completely replace the default system prompt.
     /// Multiple files can be specified and will be concatenated in order.
+    /// A directory stands for the `.md` files in it, in lexicographic order.
     ///
     /// # Example
     ///
     /// ```bash
     /// claude-dialog --system-prompt prompt1.md --system-prompt prompt2.md
+    /// claude-dialog --system-prompt prompts/
     /// ```
-    #[arg(long = "system-prompt", value_name = "FILE", action = clap::ArgAction::Append)]
-    pub system_prompt_files: Vec<String>,
+    #[arg(long = "system-prompt", value_name = "FILE|DIR", action = clap::ArgAction::Append, value_parser = ReadablePath)]
+    pub system_prompt_files: Vec<PathBuf>,
 
     /// Append to default system prompt
     ///
@@ -70,8 +167,25 @@ pub struct Args {
     /// ```bash
     /// claude-dialog --append-system-prompt additional_instructions.md
     /// ```
-    #[arg(long = "append-system-prompt", value_name = "FILE", conflicts_with = "system_prompt_files")]
-    pub append_prompt_file: Option<String>,
+    #[arg(long = "append-system-prompt", value_name = "FILE", conflicts_with = "system_prompt_files", value_parser = ReadableFile)]
+    pub append_prompt_file: Option<PathBuf>,
+
+    /// Also load `.md` files in subdirectories of system prompt directories
+    #[arg(long)]
+    pub recursive: bool,
+
+    /// Use a system prompt from the prompt library
+    ///
+    /// Loads `prompts/<NAME>.md` from the configuration directory;
+    /// `claude-dialog prompts list` shows the presets there.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --preset rust-reviewer
+    /// ```
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["system_prompt_files", "append_prompt_file"])]
+    pub preset: Option<String>,
 
     /// Claude model to use
     ///
@@ -85,16 +199,935 @@ pub struct Args {
     /// ```
     #[arg(long = "model", value_name = "MODEL")]
     pub model: Option<String>,
+
+    /// Resume the Claude conversation with this session ID
+    ///
+    /// By default the first prompt continues the most recent conversation
+    /// in the current directory. Later prompts continue the resumed one.
+    #[arg(long = "resume", value_name = "SESSION_ID", conflicts_with = "new_session", value_parser = NonEmptyStringValueParser::new())]
+    pub resume: Option<String>,
+
+    /// Start a new Claude conversation instead of continuing the most recent one
+    #[arg(long = "new-session")]
+    pub new_session: bool,
+
+    /// Recover the session that ended unexpectedly last, without asking
+    ///
+    /// Its turns are reloaded from the crash-recovery journal and the first
+    /// prompt resumes its Claude conversation.
+    #[arg(long = "recover", conflicts_with_all = ["resume", "new_session", "from", "incognito"])]
+    pub recover: bool,
+
+    /// Where to send prompts
+    ///
+    /// `claude` runs the Claude CLI. `ollama` sends prompts to a local
+    /// Ollama server (at `OLLAMA_HOST`, or localhost:11434) and needs
+    /// `--model`, e.g. `--model llama3`. `api` sends prompts to the
+    /// Anthropic Messages API with the key in `ANTHROPIC_API_KEY`, without
+    /// the Claude CLI, and needs a model ID, e.g.
+    /// `--model claude-sonnet-4-5`.
+    #[arg(long = "backend", value_name = "BACKEND", value_enum, default_value_t = Backend::Claude)]
+    pub backend: Backend,
+
+    /// Sampling temperature, instead of the one in the system prompt's
+    /// front matter
+    ///
+    /// Only the ollama and api backends have this setting; the Anthropic
+    /// API takes values from 0 to 1.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --backend api --model claude-sonnet-4-5 --temperature 0.2
+    /// ```
+    #[arg(long = "temperature", value_name = "T", value_parser = parse_temperature)]
+    pub temperature: Option<f32>,
+
+    /// Most tokens of a response; only the api backend has this setting,
+    /// and its default is 4096
+    #[arg(long = "max-tokens", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_tokens: Option<u32>,
+
+    /// How the ollama and api backends shorten the conversation once it
+    /// would take a prompt over the history budget (see `--history-budget`)
+    ///
+    /// `drop-oldest` drops the oldest exchanges, `summarize-oldest` has the
+    /// model summarize them first, and `keep-pinned` keeps the first
+    /// exchange and drops the oldest ones after it.
+    #[arg(long = "trim-policy", value_name = "POLICY", value_enum, default_value_t = TrimPolicy::DropOldest)]
+    pub trim_policy: TrimPolicy,
+
+    /// Send the api backend's requests without marking the system prompt
+    /// and the latest prompt for prompt caching
+    ///
+    /// With caching, repeated turns with a long system prompt or large
+    /// `@file` context read them from the API's cache at a fraction of the
+    /// input price, but the first turn costs a little more to write them.
+    #[arg(long = "no-prompt-cache")]
+    pub no_prompt_cache: bool,
+
+    /// Most tokens of the conversation the ollama and api backends send
+    /// with each prompt
+    ///
+    /// The oldest exchanges are trimmed (see `--trim-policy`) before a
+    /// prompt would take the conversation over the budget. By default it is
+    /// about 100,000 tokens for the api backend, and three quarters of the
+    /// model's context window (see `--num-ctx`) for ollama.
+    #[arg(long = "history-budget", value_name = "TOKENS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub history_budget: Option<u32>,
+
+    /// Context window of the ollama model in tokens, sent to the server as
+    /// its `num_ctx`
+    ///
+    /// Without it, Ollama's default of 4096 tokens is assumed, and the
+    /// conversation is trimmed to fit.
+    #[arg(long = "num-ctx", value_name = "TOKENS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub num_ctx: Option<u32>,
+
+    /// Define a persona that `/persona NAME` routes prompts to (can be specified multiple times)
+    ///
+    /// A persona names a backend and optionally a model, so one session can
+    /// use a local model for drafts and Claude for final answers. Turns the
+    /// backend has not seen are sent along with its first prompt.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --persona draft=ollama:llama3 --persona final=claude:opus
+    /// ```
+    #[arg(long = "persona", value_name = "NAME=BACKEND[:MODEL]", action = clap::ArgAction::Append)]
+    pub personas: Vec<Persona>,
+
+    /// Tools Claude may use without asking, instead of `Write` and `Edit`
+    ///
+    /// A comma-separated list that can be given multiple times. Tool names
+    /// may carry a permission pattern, e.g. `Bash(git log:*)`.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --allowed-tools Read,Edit --allowed-tools 'Bash(cargo test:*)'
+    /// ```
+    #[arg(long = "allowed-tools", value_name = "TOOLS", value_delimiter = ',', action = clap::ArgAction::Append, value_parser = parse_tool_name)]
+    pub allowed_tools: Vec<String>,
+
+    /// Tools Claude must not use, even when they are allowed
+    ///
+    /// A comma-separated list that can be given multiple times, e.g.
+    /// `--disallowed-tools Edit` to only let Claude write new files.
+    #[arg(long = "disallowed-tools", value_name = "TOOLS", value_delimiter = ',', action = clap::ArgAction::Append, value_parser = parse_tool_name)]
+    pub disallowed_tools: Vec<String>,
+
+    /// File naming MCP servers whose tools Claude may use, passed on to
+    /// `claude --mcp-config` (can be specified multiple times)
+    ///
+    /// `/mcp` lists the servers. Their tools are named
+    /// `mcp__<server>__<tool>`; allow them with `--allowed-tools` to let
+    /// Claude use them without asking.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --mcp-config mcp.json --allowed-tools mcp__github
+    /// ```
+    #[arg(long = "mcp-config", value_name = "FILE", action = clap::ArgAction::Append, value_parser = ReadableFile)]
+    pub mcp_config: Vec<PathBuf>,
+
+    /// Use only the MCP servers of `--mcp-config`, not those of the project
+    /// or added with `claude mcp add`
+    #[arg(long = "strict-mcp-config")]
+    pub strict_mcp_config: bool,
+
+    /// The Claude CLI to run instead of `claude` on PATH
+    ///
+    /// A path, or the name of another executable on PATH, for installs
+    /// that are not on PATH or to try a different version.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --claude-bin ~/.claude/local/claude
+    /// ```
+    #[arg(long = "claude-bin", value_name = "PATH")]
+    pub claude_bin: Option<PathBuf>,
+
+    /// Pass an argument to the Claude CLI as it is, after the ones
+    /// claude-dialog passes (can be specified multiple times)
+    ///
+    /// For options of the CLI that claude-dialog has no option for. Each
+    /// occurrence is one argument, so an option and its value take two.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --claude-arg --max-turns --claude-arg 5
+    /// ```
+    #[arg(long = "claude-arg", value_name = "ARG", action = clap::ArgAction::Append, allow_hyphen_values = true)]
+    pub claude_args: Vec<String>,
+
+    /// Define a slash command alias (can be specified multiple times)
+    ///
+    /// The expansion may use `{{args}}`, `{{1}}`, `{{2}}`, ... for the alias
+    /// arguments and `{{date}}` / `{{time}}` for the current date and time.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --alias 'cp=/checkpoint {{date}}-{{1}}'
+    /// ```
+    #[arg(long = "alias", value_name = "NAME=COMMAND", action = clap::ArgAction::Append)]
+    pub aliases: Vec<CommandAlias>,
+
+    /// How much of the welcome banner to show
+    ///
+    /// `full` lists the effective configuration, `minimal` prints only the
+    /// title and `off` prints nothing.
+    #[arg(long = "banner", value_name = "STYLE", value_enum, default_value_t = BannerStyle::Full)]
+    pub banner: BannerStyle,
+
+    /// Show only Claude's final answer
+    ///
+    /// Intermediate text, thinking and tool calls are replaced by a progress
+    /// spinner with a running count of tool calls. Useful for agentic tasks
+    /// where only the outcome matters.
+    #[arg(long = "final-only")]
+    pub final_only: bool,
+
+    /// Show Claude's response while it is being generated
+    ///
+    /// Text appears progressively instead of all at once when Claude is
+    /// done, including what Claude writes between tool calls. Requires a
+    /// Claude CLI with `--include-partial-messages`.
+    #[arg(long = "stream", conflicts_with = "final_only")]
+    pub stream: bool,
+
+    /// Print responses as Claude wrote them, without rendering their
+    /// Markdown
+    ///
+    /// Headings, emphasis, lists and tables are otherwise shown with
+    /// terminal styles when the output is a colored terminal.
+    #[arg(long = "raw")]
+    pub raw: bool,
+
+    /// Show code blocks of rendered responses without syntax highlighting
+    ///
+    /// Code blocks are otherwise highlighted in the language their fence
+    /// declares; useful for terminals with few or no colors.
+    #[arg(long = "no-highlight")]
+    pub no_highlight: bool,
+
+    /// Study with flashcards: keep each answer hidden until Enter is
+    /// pressed, with a timer running from when the question was sent
+    ///
+    /// Think of your own answer first, then compare. Answers are recorded,
+    /// saved and exported as usual.
+    #[arg(long = "quiz", conflicts_with_all = ["final_only", "stream", "prompt", "batch"])]
+    pub quiz: bool,
+
+    /// Show the tokens and estimated cost of each turn after its response
+    ///
+    /// `/stats` shows the totals of the session at any time, and they are
+    /// summed up when the session ends.
+    #[arg(long = "show-usage")]
+    pub show_usage: bool,
+
+    /// Print the `claude` command line of each prompt instead of running
+    /// it
+    ///
+    /// Arguments are quoted for a POSIX shell, so a line can be pasted into
+    /// one to run it. Responses are left empty.
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Answer prompts locally instead of sending them to a backend: `echo`
+    /// sends every prompt back as it would have been sent, a YAML fixture
+    /// file answers with its canned responses
+    ///
+    /// For tests of the whole session without a network connection or the
+    /// `claude` CLI. Can also be set with `CLAUDE_DIALOG_TEST_BACKEND`.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// echo 'Hello' | claude-dialog --test-backend echo
+    /// ```
+    #[arg(long = "test-backend", value_name = "echo|FILE", env = "CLAUDE_DIALOG_TEST_BACKEND", conflicts_with = "dry_run")]
+    pub test_backend: Option<TestBackend>,
+
+    /// Mask email addresses, phone numbers, card numbers, API keys and
+    /// other personal data in responses shown in the terminal
+    ///
+    /// Every character of a match is shown as `█`, for demos and screen
+    /// sharing. Conversations, exports and transcripts keep the original
+    /// text.
+    #[arg(long = "mask-pii")]
+    pub mask_pii: bool,
+
+    /// Word list whose words are masked in responses shown in the terminal,
+    /// one word per line (can be specified multiple times)
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --mask-words profanity.txt --mask-words customers.txt
+    /// ```
+    #[arg(long = "mask-words", value_name = "FILE", action = clap::ArgAction::Append, value_parser = ReadableFile)]
+    pub mask_words: Vec<PathBuf>,
+
+    /// Regular expression whose matches are masked in responses shown in
+    /// the terminal (can be specified multiple times)
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --mask-pattern 'ACME-\d+'
+    /// ```
+    #[arg(long = "mask-pattern", value_name = "REGEX", action = clap::ArgAction::Append, value_parser = parse_mask_pattern)]
+    pub mask_patterns: Vec<String>,
+
+    /// Mask responses in transcripts as well as in the terminal
+    #[arg(long = "mask-transcripts")]
+    pub mask_transcripts: bool,
+
+    /// Token budget for files included with `@file` (0 for no limit)
+    ///
+    /// When the included files exceed the budget, they are trimmed or
+    /// dropped according to `--context-strategy` instead of being sent whole.
+    #[arg(long = "context-budget", value_name = "TOKENS", default_value_t = DEFAULT_CONTEXT_BUDGET)]
+    pub context_budget: usize,
+
+    /// What to do when included files exceed the context budget
+    ///
+    /// `truncate` keeps the beginning of large files, `head-tail` keeps their
+    /// beginning and end, and `ask` lets you choose files to drop first.
+    #[arg(long = "context-strategy", value_name = "STRATEGY", value_enum, default_value_t = BudgetStrategy::HeadTail)]
+    pub context_strategy: BudgetStrategy,
+
+    /// Directory Claude runs in, e.g. a project other than the current
+    /// directory; `@file` references are read from it too
+    ///
+    /// `/cd` switches to another directory during the session.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --cwd ~/src/shop
+    /// ```
+    #[arg(long = "cwd", value_name = "DIR", value_parser = parse_working_dir)]
+    pub cwd: Option<PathBuf>,
+
+    /// Send a map of the repository's files and symbols with the first prompt
+    ///
+    /// The same as running `/repo-map` before the first prompt.
+    #[arg(long = "repo-map")]
+    pub repo_map: bool,
+
+    /// Send a text file whole with the first prompt (can be specified
+    /// multiple times)
+    ///
+    /// The same as running `/attach` before the first prompt.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --attach src/parser.rs --prompt "Review this file"
+    /// ```
+    #[arg(long = "attach", value_name = "FILE", action = clap::ArgAction::Append, value_parser = ReadableFile)]
+    pub attachments: Vec<PathBuf>,
+
+    /// Language responses are expected in, as a code such as `en` or a name
+    ///
+    /// A prompt written in another language is sent with a hint to reply in
+    /// that language, so switching languages mid-session needs no
+    /// reconfiguring.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --language en
+    /// ```
+    #[arg(long = "language", value_name = "LANG")]
+    pub language: Option<Language>,
+
+    /// Check prompts for likely typos before sending them, and choose to
+    /// send them anyway or edit them
+    ///
+    /// Words are looked up in the system's word list, and identifiers are
+    /// compared with those of the included files and the conversation.
+    /// Nothing is sent over the network.
+    #[arg(long = "spellcheck")]
+    pub spellcheck: bool,
+
+    /// Word list for `--spellcheck`, one word per line, instead of the
+    /// system's (can be specified multiple times)
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --spellcheck --dictionary /usr/share/dict/words --dictionary project-words.txt
+    /// ```
+    #[arg(long = "dictionary", value_name = "FILE", action = clap::ArgAction::Append, value_parser = ReadableFile)]
+    pub dictionaries: Vec<PathBuf>,
+
+    /// Send prompts that look unfinished without asking first
+    ///
+    /// A prompt whose last line leaves a quote or bracket open, that leaves
+    /// a code block open, or that looks like shell input such as `git
+    /// status` otherwise asks whether to send it anyway.
+    #[arg(long = "send-unfinished")]
+    pub send_unfinished: bool,
+
+    /// End every prompt with the current time, working directory and
+    /// operating system
+    ///
+    /// Lets Claude answer questions about "today" or relative paths
+    /// correctly. The footer is only sent, not recorded in the
+    /// conversation, history or transcripts.
+    #[arg(long = "turn-metadata")]
+    pub turn_metadata: bool,
+
+    /// Suggest follow-up questions after each answer; typing the number of
+    /// one at the prompt asks it
+    ///
+    /// Suggestions come from a short extra request to a small, cheap model
+    /// that sees only the last exchange. Claude sessions only.
+    #[arg(long = "suggest-follow-ups", conflicts_with_all = ["prompt", "batch"])]
+    pub suggest_follow_ups: bool,
+
+    /// Continue a conversation exported with `/export`
+    ///
+    /// The exported turns are loaded into a new session and made known to
+    /// the backend as chosen with `--from-mode`.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --from handoff.json --from-mode summary
+    /// ```
+    #[arg(long = "from", value_name = "FILE", conflicts_with = "resume", value_parser = ReadableFile)]
+    pub from: Option<PathBuf>,
+
+    /// How the conversation given with `--from` is made known to the backend
+    ///
+    /// `replay` sends every imported prompt again to a new session before
+    /// the first prompt; `summary` sends a condensed copy of the turns with
+    /// the first prompt instead, which is faster and cheaper for long
+    /// conversations.
+    #[arg(long = "from-mode", value_name = "MODE", value_enum, default_value_t = ImportMode::Replay, requires = "from")]
+    pub from_mode: ImportMode,
+
+    /// Do not save this session's input to the history file
+    #[arg(long = "private")]
+    pub private: bool,
+
+    /// Write nothing about this session to disk
+    ///
+    /// Disables the crash-recovery journal, the input history file and
+    /// transcripts; the banner and the input prompt show that the session is
+    /// incognito.
+    #[arg(long = "incognito")]
+    pub incognito: bool,
+
+    /// Write a Markdown transcript of the session to a file when it ends
+    ///
+    /// `/save` without a path writes the transcript to this file as well.
+    #[arg(long = "save-transcript", value_name = "PATH", conflicts_with = "incognito", value_parser = parse_output_path)]
+    pub save_transcript: Option<PathBuf>,
+
+    /// Record the session as an asciinema cast, for playable recordings in
+    /// docs and blog posts
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --record-cast demo.cast
+    /// asciinema play demo.cast
+    /// ```
+    #[arg(long = "record-cast", value_name = "FILE", conflicts_with_all = ["incognito", "prompt", "batch"], value_parser = parse_output_path)]
+    pub record_cast: Option<PathBuf>,
+
+    /// Mirror the conversation to a file or named pipe as it happens, so it
+    /// can be followed live from another terminal
+    ///
+    /// Prompts and raw, unrendered responses are appended to the file.
+    /// A named pipe waits for a reader before the session starts.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// mkfifo /tmp/dialog && claude-dialog --tee /tmp/dialog
+    /// cat /tmp/dialog    # in a second terminal
+    /// ```
+    #[arg(long = "tee", value_name = "PATH", conflicts_with = "incognito", value_parser = parse_output_path)]
+    pub tee: Option<PathBuf>,
+
+    /// Never save input matching a pattern to the history file (can be
+    /// specified multiple times)
+    ///
+    /// Patterns are globs matched case-insensitively against the whole line.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --history-ignore '*password*' --history-ignore '*token*'
+    /// ```
+    #[arg(long = "history-ignore", value_name = "PATTERN", action = clap::ArgAction::Append, value_parser = parse_history_pattern)]
+    pub history_ignore: Vec<String>,
+
+    /// How often to retry a prompt that hit a rate limit (0 to never retry)
+    ///
+    /// Before each retry, claude-dialog waits as long as Claude asked, or
+    /// 15 seconds doubling with every retry if it did not say.
+    #[arg(long = "rate-limit-retries", value_name = "N", default_value_t = rate_limit::DEFAULT_RETRIES)]
+    pub rate_limit_retries: usize,
+
+    /// Stop a `claude` command that has not finished after this many
+    /// seconds and report it as failed, instead of waiting for it forever
+    ///
+    /// Applies to each attempt, not to the waits between rate limit
+    /// retries. Ctrl+C stops a command at any time.
+    #[arg(long = "timeout", value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: Option<u64>,
+
+    /// Send a single prompt, print the response and exit, instead of
+    /// starting a conversation; `-` reads the prompt from standard input
+    ///
+    /// The exit status is the backend command's. The welcome banner is only
+    /// shown when standard output is a terminal.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --prompt "Summarize @CHANGELOG.md"
+    /// git diff | claude-dialog --prompt -
+    /// ```
+    #[arg(short = 'p', long = "prompt", value_name = "TEXT")]
+    pub prompt: Option<String>,
+
+    /// Send the prompts of a file one after another, write the responses to
+    /// a JSON report and exit
+    ///
+    /// `.yaml` and `.yml` files hold a list of prompts; other files have one
+    /// prompt per line, skipping blank lines and lines starting with `#`.
+    /// The exit status is 1 if any prompt failed.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --batch evals/prompts.yaml
+    /// ```
+    #[arg(long = "batch", value_name = "FILE", conflicts_with = "prompt", value_parser = ReadableFile)]
+    pub batch: Option<PathBuf>,
+
+    /// Where to write the report of `--batch`, instead of
+    /// `<file>.results.json` next to the prompt file
+    #[arg(long = "batch-output", value_name = "FILE", requires = "batch", value_parser = parse_output_path)]
+    pub batch_output: Option<PathBuf>,
+
+    /// Run the prompts of `--batch` this many times, each run in a new
+    /// conversation, and report how much their responses vary
+    ///
+    /// The report then compares the runs of each prompt: the spread of the
+    /// responses' lengths and output tokens, and how far apart they are.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --batch evals/prompts.yaml --repeat 5
+    /// ```
+    #[arg(long = "repeat", value_name = "N", default_value_t = 1, requires = "batch", conflicts_with = "resume", value_parser = clap::value_parser!(u32).range(1..))]
+    pub repeat: u32,
+
+    /// How `--prompt` and `--batch` print their turns
+    ///
+    /// `json` prints a JSON record of each turn per line instead of the
+    /// response: the prompt, response, model, duration, exit code and token
+    /// usage. Other messages go to standard error.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --prompt "What is 2 + 2?" --output-format json | jq -r .response
+    /// ```
+    #[arg(long = "output-format", value_name = "FORMAT", value_enum, default_value_t = PrintFormat::Text, requires = "non_interactive")]
+    pub output_format: PrintFormat,
+
+    /// Use the options of a profile from the configuration file
+    ///
+    /// Profiles are defined as `[profiles.NAME]` tables in `config.toml`
+    /// and override the file's defaults; command-line options override
+    /// both.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog --profile work
+    /// ```
+    #[arg(long = "profile", value_name = "NAME", value_parser = NonEmptyStringValueParser::new())]
+    pub profile: Option<String>,
+
+    /// Subcommand to run instead of starting a conversation
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-/// Parse command-line arguments from a vector of strings
+impl Args {
+    /// Parse the arguments of the running process
+    ///
+    /// Like [`Parser::parse`], this prints usage information and exits the
+    /// process on `--help`, `--version` or invalid arguments.
+    pub fn from_env() -> Self {
+        Self::parse_from(with_default_command(std::env::args_os()))
+    }
+
+    /// Conversation the first prompt attaches to, from `--resume` and `--new-session`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::cli::parse_args;
+    /// use claude_dialog::claude_executor::SessionMode;
+    ///
+    /// let args = parse_args(vec!["claude-dialog", "--resume", "3f2a9c1e"]).unwrap();
+    /// assert_eq!(args.session(), SessionMode::Resume("3f2a9c1e".to_string()));
+    ///
+    /// let args = parse_args(vec!["claude-dialog"]).unwrap();
+    /// assert_eq!(args.session(), SessionMode::Continue);
+    /// ```
+    pub fn session(&self) -> SessionMode {
+        match &self.resume {
+            Some(id) => SessionMode::Resume(id.clone()),
+            None if self.new_session => SessionMode::Fresh,
+            None => SessionMode::Continue,
+        }
+    }
+}
+
+/// Value parser for arguments naming a file that must be readable
+///
+/// Expands `~` and environment variables (see [`paths::expand`]) and rejects
+/// paths that do not exist, are directories or cannot be opened.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::cli::ReadableFile;
+/// use clap::{Arg, Command};
+///
+/// let cmd = Command::new("demo").arg(Arg::new("file").long("file").value_parser(ReadableFile));
+/// assert!(cmd.clone().try_get_matches_from(["demo", "--file", "Cargo.toml"]).is_ok());
+///
+/// let err = cmd.try_get_matches_from(["demo", "--file", "missing.md"]).unwrap_err();
+/// assert!(err.to_string().contains("invalid value 'missing.md' for '--file <file>'"));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadableFile;
+
+impl TypedValueParser for ReadableFile {
+    type Value = PathBuf;
+
+    fn parse_ref(&self, cmd: &clap::Command, arg: Option<&Arg>, value: &OsStr) -> Result<PathBuf, clap::Error> {
+        let invalid = |reason: String| invalid_value(cmd, arg, value, reason);
+
+        let path = paths::expand(value).map_err(|e| invalid(e.to_string()))?;
+        if path.is_dir() {
+            return Err(invalid(format!("{} is a directory", path.display())));
+        }
+        File::open(&path).map_err(|e| invalid(format!("cannot read {}: {}", path.display(), e)))?;
+        Ok(path)
+    }
+}
+
+/// Value parser for arguments naming a readable file or directory
+///
+/// Like [`ReadableFile`], but a directory is accepted as long as it can be
+/// listed.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::cli::ReadablePath;
+/// use clap::{Arg, Command};
+///
+/// let cmd = Command::new("demo").arg(Arg::new("path").long("path").value_parser(ReadablePath));
+/// assert!(cmd.clone().try_get_matches_from(["demo", "--path", "Cargo.toml"]).is_ok());
+/// assert!(cmd.clone().try_get_matches_from(["demo", "--path", "src"]).is_ok());
+/// assert!(cmd.try_get_matches_from(["demo", "--path", "missing"]).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadablePath;
+
+impl TypedValueParser for ReadablePath {
+    type Value = PathBuf;
+
+    fn parse_ref(&self, cmd: &clap::Command, arg: Option<&Arg>, value: &OsStr) -> Result<PathBuf, clap::Error> {
+        let invalid = |reason: String| invalid_value(cmd, arg, value, reason);
+
+        let path = paths::expand(value).map_err(|e| invalid(e.to_string()))?;
+        if path.is_dir() {
+            fs::read_dir(&path).map_err(|e| invalid(format!("cannot read {}: {}", path.display(), e)))?;
+        } else {
+            File::open(&path).map_err(|e| invalid(format!("cannot read {}: {}", path.display(), e)))?;
+        }
+        Ok(path)
+    }
+}
+
+/// The usage error for an invalid path argument
+fn invalid_value(cmd: &clap::Command, arg: Option<&Arg>, value: &OsStr, reason: String) -> clap::Error {
+    let arg = arg.map(|arg| arg.to_string()).unwrap_or_else(|| "...".to_string());
+    clap::Error::raw(
+        ErrorKind::ValueValidation,
+        format!("invalid value '{}' for '{}': {}\n", value.to_string_lossy(), arg, reason),
+    )
+    .with_cmd(cmd)
+}
+
+/// Subcommands of the Claude Dialog CLI
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Start a conversation; the default without a subcommand
+    ///
+    /// Takes the same options as `claude-dialog` alone.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog chat --model opus
+    /// ```
+    Chat,
+
+    /// Print the directories used for configuration, data, cache and state
+    Paths,
+
+    /// Update claude-dialog to the latest GitHub release
+    ///
+    /// Downloads the release binary for this platform, verifies its SHA-256
+    /// checksum and replaces the running executable.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog self-update --check
+    /// ```
+    SelfUpdate {
+        /// Only report whether an update is available
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Show the release notes of the installed version
+    Whatsnew,
+
+    /// Play back a saved conversation for a demo or talk, without calling
+    /// Claude
+    ///
+    /// Prompts are typed out and responses stream in as if live. Space
+    /// continues at each step and `q` ends the presentation.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog present demo.md --speed 1.5
+    /// ```
+    Present {
+        /// Transcript saved with `--save-transcript` or `/save`, or a
+        /// conversation saved with `/export`
+        #[arg(value_name = "FILE", value_parser = ReadableFile)]
+        file: PathBuf,
+
+        /// Playback speed; 2 plays twice as fast
+        #[arg(long, value_name = "FACTOR", default_value_t = 1.0, value_parser = parse_speed)]
+        speed: f64,
+
+        /// Continue after a short pause instead of waiting for Space
+        #[arg(long)]
+        auto: bool,
+    },
+
+    /// Read a saved conversation in a scrollable, searchable viewer,
+    /// without starting a Claude session
+    ///
+    /// The arrow keys and Space scroll, `/` searches and `q` closes it.
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog view notes.md
+    /// ```
+    View {
+        /// Transcript saved with `--save-transcript` or `/save`, a
+        /// conversation saved with `/export`, or the ID of a saved session
+        #[arg(value_name = "FILE|ID")]
+        source: String,
+    },
+
+    /// Manage the input history file
+    History {
+        #[command(subcommand)]
+        action: HistoryCommand,
+    },
+
+    /// Manage the prompt library used by `--preset` and `/preset`
+    Prompts {
+        #[command(subcommand)]
+        action: PromptsCommand,
+    },
+
+    /// Browse, merge and delete the conversations of past sessions
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog sessions show 20250101-120000
+    /// ```
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsCommand,
+    },
+}
+
+/// Actions of the `history` subcommand
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum HistoryCommand {
+    /// Delete the input history file
+    Clear,
+}
+
+/// Actions of the `prompts` subcommand
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum PromptsCommand {
+    /// List the presets with the first line of each
+    List,
+}
+
+/// Actions of the `sessions` subcommand
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum SessionsCommand {
+    /// List the saved sessions, newest first
+    List,
+
+    /// Print the conversation of a session
+    Show {
+        /// ID of the session, as listed by `sessions list`
+        id: String,
+    },
+
+    /// Delete a saved session
+    Delete {
+        /// ID of the session, as listed by `sessions list`
+        id: String,
+    },
+
+    /// Merge the conversations of sessions into one document, their turns
+    /// in the order they were sent and labeled with their session
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog sessions merge 20250101-090000 20250101-140000 --label research --label implementation --out feature.md
+    /// ```
+    Merge {
+        /// IDs of the sessions, as listed by `sessions list`, or
+        /// conversation files written by `/export`
+        #[arg(required = true, num_args = 2..)]
+        sources: Vec<String>,
+
+        /// Labels of the sessions, in order; by default their IDs or file
+        /// names
+        #[arg(long = "label", value_name = "NAME")]
+        labels: Vec<String>,
+
+        /// File to write: Markdown for `.md`, a web page for `.html`, and
+        /// otherwise a conversation file to continue with `--from`
+        #[arg(long = "out", value_name = "FILE", value_parser = parse_output_path)]
+        out: PathBuf,
+    },
+
+    /// Compress the sessions saved before a given age into the archive;
+    /// they are still listed, shown and reopened by ID
+    ///
+    /// # Example
+    ///
+    /// ```bash
+    /// claude-dialog sessions archive --older-than 30d
+    /// ```
+    Archive {
+        /// Age of the sessions to archive, in hours, days or weeks, e.g.
+        /// `12h`, `30d` or `2w`
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: TimeDelta,
+    },
+}
+
+/// Value parser for files written by claude-dialog, expanding `~` and
+/// environment variables
+fn parse_output_path(path: &str) -> Result<PathBuf, String> {
+    paths::expand(path.as_ref()).map_err(|e| format!("{:#}", e))
+}
+
+/// Value parser for `--cwd`, resolving the directory to an absolute path
+fn parse_working_dir(dir: &str) -> Result<PathBuf, String> {
+    let current = std::env::current_dir().map_err(|e| format!("cannot determine the current directory: {}", e))?;
+    paths::directory(dir.as_ref(), &current).map_err(|e| format!("{:#}", e))
+}
+
+/// Value parser for `sessions archive --older-than`
+fn parse_age(age: &str) -> Result<TimeDelta, String> {
+    archive::parse_age(age).map_err(|e| format!("{:#}", e))
+}
+
+/// Value parser for `--allowed-tools` and `--disallowed-tools`, rejecting
+/// empty tool names
+fn parse_tool_name(tool: &str) -> Result<String, String> {
+    let tool = tool.trim();
+    if tool.is_empty() {
+        return Err("tool names must not be empty".to_string());
+    }
+    Ok(tool.to_string())
+}
+
+/// Value parser for `--history-ignore`, rejecting invalid globs
+fn parse_history_pattern(pattern: &str) -> Result<String, String> {
+    history::ignore_pattern(pattern)
+        .map(|_| pattern.to_string())
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Value parser for `present --speed`, rejecting speeds that are not
+/// positive
+fn parse_speed(speed: &str) -> Result<f64, String> {
+    match speed.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err("the speed must be a positive number".to_string()),
+    }
+}
+
+/// Value parser for `--temperature`, rejecting negative temperatures
+fn parse_temperature(temperature: &str) -> Result<f32, String> {
+    match temperature.parse::<f32>() {
+        Ok(temperature) if temperature >= 0.0 && temperature.is_finite() => Ok(temperature),
+        _ => Err("the temperature must be a number of at least 0".to_string()),
+    }
+}
+
+/// Value parser for `--mask-pattern`, rejecting invalid regular
+/// expressions
+fn parse_mask_pattern(pattern: &str) -> Result<String, String> {
+    Masker::new(&[], &[pattern.to_string()], false)
+        .map(|_| pattern.to_string())
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Parse command-line arguments
 ///
 /// This function wraps the clap parser and converts parsing errors
 /// into anyhow errors for consistent error handling.
 ///
 /// # Arguments
 ///
-/// * `args` - A vector of string slices representing command-line arguments
+/// * `args` - The command-line arguments, including the program name; any
+///   iterator of strings or `OsString`s such as [`std::env::args_os`]
 ///
 /// # Returns
 ///
@@ -104,31 +1137,63 @@ pub struct Args {
 ///
 /// ```
 /// use claude_dialog::cli::parse_args;
+/// use std::path::PathBuf;
 ///
 /// // Basic usage
 /// let args = parse_args(vec!["claude-dialog"]).unwrap();
 /// assert!(args.system_prompt_files.is_empty());
 ///
+/// # let dir = tempfile::tempdir().unwrap();
+/// # for name in ["custom.md", "p1.md", "p2.md"] {
+/// #     std::fs::write(dir.path().join(name), "prompt").unwrap();
+/// # }
+/// # let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+/// # let (custom, p1, p2) = (path("custom.md"), path("p1.md"), path("p2.md"));
 /// // With system prompt
-/// let args = parse_args(vec!["claude-dialog", "--system-prompt", "custom.md"]).unwrap();
-/// assert_eq!(args.system_prompt_files, vec!["custom.md"]);
+/// let args = parse_args(vec!["claude-dialog", "--system-prompt", &custom]).unwrap();
+/// assert_eq!(args.system_prompt_files, vec![PathBuf::from(&custom)]);
 ///
 /// // With multiple options
 /// let args = parse_args(vec![
 ///     "claude-dialog",
-///     "--system-prompt", "p1.md",
-///     "--system-prompt", "p2.md",
+///     "--system-prompt", &p1,
+///     "--system-prompt", &p2,
 ///     "--model", "claude-3-opus"
 /// ]).unwrap();
 /// assert_eq!(args.system_prompt_files.len(), 2);
 /// assert_eq!(args.model, Some("claude-3-opus".to_string()));
+///
+/// // From OsStrings, e.g. paths that are not valid UTF-8
+/// use std::ffi::OsString;
+/// let args = parse_args([OsString::from("claude-dialog"), OsString::from("--model"), OsString::from("haiku")]).unwrap();
+/// assert_eq!(args.model, Some("haiku".to_string()));
 /// ```
-pub fn parse_args(args: Vec<&str>) -> Result<Args> {
-    let args = Args::try_parse_from(args)
+pub fn parse_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args = Args::try_parse_from(with_default_command(args))
         .map_err(|e| anyhow!(e.to_string()))?;
     Ok(args)
 }
 
+/// The arguments with a leading `chat` subcommand removed
+///
+/// `claude-dialog chat [OPTIONS]` is the same as `claude-dialog [OPTIONS]`,
+/// so the conversation's options can follow `chat`.
+fn with_default_command<I, T>(args: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    if args.get(1).is_some_and(|arg| arg == "chat") {
+        args.remove(1);
+    }
+    args
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,9 +1201,70 @@ mod tests {
     #[test]
     fn test_args_struct() {
         let args = Args {
-            system_prompt_files: vec!["test.md".to_string()],
+            system_prompt_files: vec!["test.md".into()],
             append_prompt_file: None,
+            recursive: false,
+            preset: None,
             model: Some("claude-3".to_string()),
+            backend: Default::default(),
+            temperature: None,
+            max_tokens: None,
+            trim_policy: Default::default(),
+            no_prompt_cache: false,
+            history_budget: None,
+            num_ctx: None,
+            resume: None,
+            new_session: false,
+            recover: false,
+            personas: vec![],
+            allowed_tools: vec![],
+            disallowed_tools: vec![],
+            mcp_config: vec![],
+            strict_mcp_config: false,
+            claude_bin: None,
+            claude_args: vec![],
+            aliases: vec![],
+            banner: Default::default(),
+            final_only: false,
+            stream: false,
+            raw: false,
+            no_highlight: false,
+            quiz: false,
+            show_usage: false,
+            dry_run: false,
+            test_backend: None,
+            mask_pii: false,
+            mask_words: vec![],
+            mask_patterns: vec![],
+            mask_transcripts: false,
+            context_budget: 100_000,
+            context_strategy: Default::default(),
+            cwd: None,
+            repo_map: false,
+            attachments: vec![],
+            language: None,
+            spellcheck: false,
+            dictionaries: vec![],
+            send_unfinished: false,
+            turn_metadata: false,
+            suggest_follow_ups: false,
+            from: None,
+            from_mode: Default::default(),
+            private: false,
+            incognito: false,
+            save_transcript: None,
+            record_cast: None,
+            tee: None,
+            history_ignore: vec![],
+            rate_limit_retries: 3,
+            timeout: None,
+            prompt: None,
+            batch: None,
+            batch_output: None,
+            repeat: 1,
+            output_format: Default::default(),
+            profile: None,
+            command: None,
         };
         assert_eq!(args.system_prompt_files.len(), 1);
         assert!(args.append_prompt_file.is_none());
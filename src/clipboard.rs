@@ -0,0 +1,138 @@
+//! System clipboard module
+//!
+//! `/copy` puts Claude's last response on the system clipboard and `/paste`
+//! sends the clipboard's text as, or after, a prompt. The clipboard is
+//! reached through [arboard](https://docs.rs/arboard) when claude-dialog is
+//! built with the `clipboard` feature; enable it with `--features clipboard`
+//! (or `full`).
+//!
+//! Where there is no clipboard, e.g. over SSH or on a headless server, or
+//! in builds without the feature, both commands report why and the session
+//! carries on.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::clipboard::pasted_prompt;
+//!
+//! let prompt = pasted_prompt(Some("What does this error mean?"), "error[E0502]: cannot borrow\n").unwrap();
+//! assert_eq!(prompt, "What does this error mean?\n\nerror[E0502]: cannot borrow");
+//!
+//! assert_eq!(pasted_prompt(None, "Explain closures").unwrap(), "Explain closures");
+//! assert!(pasted_prompt(None, " \n").is_err());
+//! ```
+
+use anyhow::{Result, bail};
+#[cfg(feature = "clipboard")]
+use anyhow::Context;
+
+/// The system clipboard, kept open for the rest of the session
+///
+/// On X11 and Wayland, copied text is served by the process that copied
+/// it, so the clipboard stays open after `/copy` for the text to remain
+/// available.
+pub struct SystemClipboard {
+    #[cfg(feature = "clipboard")]
+    clipboard: arboard::Clipboard,
+}
+
+impl SystemClipboard {
+    /// Open the system clipboard
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no clipboard to use, e.g. without a
+    /// display server, or the build has no clipboard support.
+    #[cfg(feature = "clipboard")]
+    pub fn open() -> Result<Self> {
+        let clipboard = arboard::Clipboard::new()
+            .context("No system clipboard is available, e.g. because there is no display")?;
+        Ok(Self { clipboard })
+    }
+
+    /// Open the system clipboard
+    ///
+    /// # Errors
+    ///
+    /// Always: this build has no clipboard support.
+    #[cfg(not(feature = "clipboard"))]
+    pub fn open() -> Result<Self> {
+        bail!("This build of claude-dialog has no clipboard support (the `clipboard` feature)")
+    }
+
+    /// Put `text` on the clipboard
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the clipboard refuses the text.
+    #[cfg(feature = "clipboard")]
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        self.clipboard.set_text(text).context("Failed to copy to the clipboard")
+    }
+
+    /// Put `text` on the clipboard
+    ///
+    /// # Errors
+    ///
+    /// Always: this build has no clipboard support.
+    #[cfg(not(feature = "clipboard"))]
+    pub fn copy(&mut self, _text: &str) -> Result<()> {
+        bail!("This build of claude-dialog has no clipboard support (the `clipboard` feature)")
+    }
+
+    /// The text on the clipboard
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the clipboard holds no text, e.g. an image.
+    #[cfg(feature = "clipboard")]
+    pub fn paste(&mut self) -> Result<String> {
+        self.clipboard.get_text().context("The clipboard holds no text")
+    }
+
+    /// The text on the clipboard
+    ///
+    /// # Errors
+    ///
+    /// Always: this build has no clipboard support.
+    #[cfg(not(feature = "clipboard"))]
+    pub fn paste(&mut self) -> Result<String> {
+        bail!("This build of claude-dialog has no clipboard support (the `clipboard` feature)")
+    }
+}
+
+impl std::fmt::Debug for SystemClipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SystemClipboard").finish_non_exhaustive()
+    }
+}
+
+/// The prompt `/paste` sends: the typed prompt, if any, followed by the
+/// clipboard's text after a blank line
+///
+/// # Errors
+///
+/// Returns an error if the clipboard text is blank.
+pub fn pasted_prompt(prompt: Option<&str>, clipboard: &str) -> Result<String> {
+    let clipboard = clipboard.trim_end();
+    if clipboard.trim().is_empty() {
+        bail!("The clipboard is empty");
+    }
+
+    Ok(match prompt.map(str::trim).filter(|prompt| !prompt.is_empty()) {
+        Some(prompt) => format!("{}\n\n{}", prompt, clipboard),
+        None => clipboard.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pasted_prompt_keeps_indentation() {
+        let code = "    let x = 1;\n    x + 1\n\n";
+        assert_eq!(pasted_prompt(Some("Simplify:  "), code).unwrap(), "Simplify:\n\n    let x = 1;\n    x + 1");
+        assert_eq!(pasted_prompt(Some(""), code).unwrap(), "    let x = 1;\n    x + 1");
+    }
+}
@@ -0,0 +1,1587 @@
+//! Slash command parsing module
+//!
+//! Input lines beginning with `/` followed by a command name are interpreted
+//! locally instead of being sent to Claude. This module owns the registry of
+//! known commands and turns raw input into a [`CommandAction`] for the dialog
+//! loop to carry out.
+//!
+//! Every command declares its positional arguments and flags, so argument
+//! parsing, usage errors and per-command `--help` behave the same way for all
+//! commands. Arguments are split on whitespace; single or double quotes group
+//! words containing spaces, `--flag=value` and `--flag value` are equivalent,
+//! and `--` ends flag parsing.
+//!
+//! User-defined aliases (see [`CommandAlias`]) are registered into the same
+//! registry and expand to a built-in command before parsing.
+//!
+//! The configuration file can turn commands off, e.g. for a locked-down
+//! profile (see [`CommandRegistry::restrict`]); the registry then refuses
+//! them, and aliases of them, when they are typed.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::commands::{CommandAction, CommandRegistry};
+//!
+//! let registry = CommandRegistry::new();
+//!
+//! let action = registry.parse("/checkpoint before-refactor").unwrap();
+//! assert_eq!(action, Some(CommandAction::Checkpoint {
+//!     name: "before-refactor".to_string(),
+//!     include_files: false,
+//! }));
+//!
+//! // Every command understands --help
+//! let action = registry.parse("/restore --help").unwrap();
+//! assert!(matches!(action, Some(CommandAction::Help(_))));
+//!
+//! // Regular prompts are not commands
+//! assert_eq!(registry.parse("Hello, Claude!").unwrap(), None);
+//! ```
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use crate::conversation::MAX_RATING;
+use crate::export::ExportFormat;
+
+/// An action requested through a slash command
+///
+/// The dialog loop is responsible for carrying out the action; parsing a
+/// command never has side effects.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandAction {
+    /// Display help text without contacting Claude
+    Help(String),
+
+    /// List the available commands, or show the help of one command
+    ShowHelp {
+        /// Name of the command to explain, without the leading slash
+        command: Option<String>,
+    },
+
+    /// Show or change the model used for the following prompts
+    Model {
+        /// The new model, `None` to show the current one
+        name: Option<String>,
+    },
+
+    /// List the personas, or route the following prompts to one of them
+    Persona {
+        /// Name of the persona, `None` to list them
+        name: Option<String>,
+    },
+
+    /// List the presets of the prompt library, or use one as the system prompt
+    Preset {
+        /// Name of the preset, `None` to list them
+        name: Option<String>,
+    },
+
+    /// Show or replace the system prompt used for the following prompts
+    System {
+        /// File or directory to read the new system prompt from
+        file: Option<PathBuf>,
+        /// Whether to go back to Claude's default system prompt
+        reset: bool,
+    },
+
+    /// Write the session transcript to a Markdown file
+    Save {
+        /// File to write, or `None` for the configured or a default file
+        path: Option<PathBuf>,
+    },
+
+    /// Attach a private note to the most recent turn
+    Note {
+        /// The note, as typed
+        text: String,
+    },
+
+    /// Rate the response to the most recent turn
+    Rate {
+        /// From 1 to [`MAX_RATING`]
+        rating: u8,
+    },
+
+    /// Write the conversation to a JSON file that `--from` can import, or
+    /// the transcript to an HTML page
+    Export {
+        /// What to write
+        format: ExportFormat,
+        /// File to write, or `None` for a default file
+        path: Option<PathBuf>,
+    },
+
+    /// Show the backend, session, usage and other state of the session
+    Status,
+
+    /// Show the tokens and estimated cost of the session so far
+    Stats,
+
+    /// List the MCP servers Claude is started with
+    Mcp,
+
+    /// Forget the conversation and start a new one with the next prompt
+    Clear,
+
+    /// Work in another directory, starting a new conversation there
+    Cd {
+        /// The directory, relative to the current working directory
+        dir: PathBuf,
+    },
+
+    /// End the dialog
+    Exit,
+
+    /// Snapshot the conversation (and optionally workspace files) under a name
+    Checkpoint {
+        /// Name of the checkpoint
+        name: String,
+        /// Whether workspace files should be snapshotted too
+        include_files: bool,
+    },
+
+    /// Roll the conversation back to a named checkpoint
+    Restore {
+        /// Name of the checkpoint to restore
+        name: String,
+    },
+
+    /// Show the prompt that would be sent and send it if confirmed
+    Preview {
+        /// The input to preview, as it would be typed at the prompt
+        text: String,
+    },
+
+    /// Send a map of the repository with the next prompt
+    RepoMap {
+        /// Whether the map should be printed as well
+        show: bool,
+    },
+
+    /// Copy the last response to the system clipboard
+    Copy,
+
+    /// Send the clipboard's text, after a prompt if one is given
+    Paste {
+        /// Prompt the clipboard's text follows
+        prompt: Option<String>,
+    },
+
+    /// Compose a prompt in an external editor and send it
+    Edit {
+        /// Text the prompt starts with
+        text: Option<String>,
+    },
+
+    /// Send a file with the next prompt
+    Attach {
+        /// File to attach
+        path: PathBuf,
+    },
+
+    /// Search the conversation for text
+    Find {
+        /// Text to search for, matched case-insensitively
+        query: String,
+        /// Whether matching turns should be redisplayed in full
+        full: bool,
+    },
+
+    /// List numbered matches of text with the lines around them, to pick a
+    /// turn to show by number
+    Search {
+        /// Text to search for, matched case-insensitively
+        query: String,
+        /// Lines shown before and after each matching line
+        context: usize,
+    },
+
+    /// Show a turn of the conversation in full
+    Turn {
+        /// Number of the turn, starting at 1
+        number: usize,
+    },
+
+    /// Show the response of a turn again, rendered as when it arrived
+    Show {
+        /// Number of the turn, starting at 1
+        number: usize,
+    },
+
+    /// Show the most recent response again
+    Last,
+}
+
+/// A positional argument accepted by a command
+#[derive(Debug, Clone)]
+pub struct Positional {
+    /// Name shown in usage text, e.g. `name` for `<name>`
+    pub name: &'static str,
+
+    /// Description shown in per-command help
+    pub help: &'static str,
+
+    /// Whether the argument must be given
+    pub required: bool,
+
+    /// Whether the argument takes all remaining values, joined with spaces
+    ///
+    /// Only meaningful for the last positional argument.
+    pub rest: bool,
+}
+
+/// A `--flag` accepted by a command
+#[derive(Debug, Clone)]
+pub struct Flag {
+    /// Long flag name without the leading dashes
+    pub long: &'static str,
+
+    /// Name of the flag's value, or `None` for boolean switches
+    pub value_name: Option<&'static str>,
+
+    /// Description shown in per-command help
+    pub help: &'static str,
+}
+
+/// Arguments of a command after parsing against its [`CommandSpec`]
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::commands::CommandRegistry;
+///
+/// let registry = CommandRegistry::new();
+/// let spec = registry.get("checkpoint").unwrap();
+///
+/// let args = spec.parse_args(&["snap", "--files"]).unwrap();
+/// assert_eq!(args.positional("name"), Some("snap"));
+/// assert!(args.flag("files"));
+/// ```
+#[derive(Debug, Default)]
+pub struct ParsedArgs {
+    positionals: HashMap<&'static str, String>,
+    flags: HashMap<&'static str, Option<String>>,
+}
+
+impl ParsedArgs {
+    /// Value of a positional argument, if given
+    pub fn positional(&self, name: &str) -> Option<&str> {
+        self.positionals.get(name).map(String::as_str)
+    }
+
+    /// Whether a flag was given
+    pub fn flag(&self, long: &str) -> bool {
+        self.flags.contains_key(long)
+    }
+
+    /// Value of a flag that takes a value, if given
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.flags.get(long).and_then(|value| value.as_deref())
+    }
+
+    /// Value of a required positional argument
+    ///
+    /// Positional arguments marked as required are checked during parsing,
+    /// so this only fails for a mismatched argument name.
+    fn required(&self, name: &str) -> Result<String> {
+        self.positional(name)
+            .map(String::from)
+            .ok_or_else(|| anyhow!("Missing argument <{}>", name))
+    }
+}
+
+/// Description of a single slash command
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::commands::CommandRegistry;
+///
+/// let registry = CommandRegistry::new();
+/// let spec = registry.get("restore").unwrap();
+/// assert_eq!(spec.usage(), "/restore <name>");
+/// ```
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    /// Command name without the leading slash
+    pub name: &'static str,
+
+    /// One-line description of the command
+    pub summary: &'static str,
+
+    /// Positional arguments in order
+    pub positionals: &'static [Positional],
+
+    /// Accepted flags
+    pub flags: &'static [Flag],
+
+    /// Whether the arguments are a prompt, passed to the single positional
+    /// argument exactly as typed instead of being split into words
+    pub verbatim: bool,
+
+    build: fn(&ParsedArgs) -> Result<CommandAction>,
+}
+
+impl CommandSpec {
+    /// Usage line generated from the declared arguments
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+
+        for positional in self.positionals {
+            let dots = if positional.rest { "..." } else { "" };
+            if positional.required {
+                usage.push_str(&format!(" <{}{}>", positional.name, dots));
+            } else {
+                usage.push_str(&format!(" [{}{}]", positional.name, dots));
+            }
+        }
+
+        for flag in self.flags {
+            match flag.value_name {
+                Some(value_name) => usage.push_str(&format!(" [--{} <{}>]", flag.long, value_name)),
+                None => usage.push_str(&format!(" [--{}]", flag.long)),
+            }
+        }
+
+        usage
+    }
+
+    /// Full help text listing the command's arguments and flags
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::commands::CommandRegistry;
+    ///
+    /// let registry = CommandRegistry::new();
+    /// let help = registry.get("checkpoint").unwrap().help();
+    /// assert!(help.contains("Usage: /checkpoint <name> [--files]"));
+    /// assert!(help.contains("--files"));
+    /// ```
+    pub fn help(&self) -> String {
+        let mut help = format!("{}\n\nUsage: {}", self.summary, self.usage());
+
+        if !self.positionals.is_empty() {
+            help.push_str("\n\nArguments:");
+            for positional in self.positionals {
+                help.push_str(&format!("\n  <{}>  {}", positional.name, positional.help));
+            }
+        }
+
+        help.push_str("\n\nOptions:");
+        for flag in self.flags {
+            let name = match flag.value_name {
+                Some(value_name) => format!("--{} <{}>", flag.long, value_name),
+                None => format!("--{}", flag.long),
+            };
+            help.push_str(&format!("\n  {}  {}", name, flag.help));
+        }
+        help.push_str("\n  --help  Show this help");
+
+        help
+    }
+
+    /// Parse already tokenized arguments against this command's declaration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for unknown flags, flags missing their value,
+    /// missing required arguments and surplus arguments.
+    pub fn parse_args(&self, tokens: &[&str]) -> Result<ParsedArgs> {
+        let mut args = ParsedArgs::default();
+        let mut values = Vec::new();
+        let mut tokens = tokens.iter();
+        let mut flags_done = false;
+
+        while let Some(&token) = tokens.next() {
+            let Some(flag_text) = token.strip_prefix("--").filter(|_| !flags_done) else {
+                values.push(token);
+                continue;
+            };
+
+            if flag_text.is_empty() {
+                flags_done = true;
+                continue;
+            }
+
+            let (long, inline_value) = match flag_text.split_once('=') {
+                Some((long, value)) => (long, Some(value.to_string())),
+                None => (flag_text, None),
+            };
+
+            let flag = self
+                .flags
+                .iter()
+                .find(|flag| flag.long == long)
+                .ok_or_else(|| anyhow!("Unknown option: --{}", long))?;
+
+            let value = match (flag.value_name, inline_value) {
+                (Some(_), Some(value)) => Some(value),
+                (Some(value_name), None) => {
+                    let value = tokens
+                        .next()
+                        .ok_or_else(|| anyhow!("Option --{} requires a value <{}>", long, value_name))?;
+                    Some(value.to_string())
+                }
+                (None, Some(_)) => bail!("Option --{} does not take a value", long),
+                (None, None) => None,
+            };
+
+            args.flags.insert(flag.long, value);
+        }
+
+        let mut values = values.into_iter();
+        for positional in self.positionals {
+            let value = if positional.rest {
+                let rest: Vec<&str> = values.by_ref().collect();
+                (!rest.is_empty()).then(|| rest.join(" "))
+            } else {
+                values.next().map(String::from)
+            };
+
+            match value {
+                Some(value) => {
+                    args.positionals.insert(positional.name, value);
+                }
+                None if positional.required => bail!("Missing argument <{}>", positional.name),
+                None => {}
+            }
+        }
+
+        if let Some(extra) = values.next() {
+            bail!("Unexpected argument: {}", extra);
+        }
+
+        Ok(args)
+    }
+}
+
+/// A user-defined shortcut for a built-in command
+///
+/// The expansion is a command line template. Before parsing, the following
+/// placeholders are substituted:
+///
+/// - `{{args}}` - all arguments given to the alias
+/// - `{{1}}`, `{{2}}`, ... - individual arguments given to the alias
+/// - `{{date}}` - the current local date (`YYYY-MM-DD`)
+/// - `{{time}}` - the current local time (`HHMMSS`)
+///
+/// When the template uses neither `{{args}}` nor numbered placeholders, the
+/// alias arguments are appended to the expansion.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::commands::CommandAlias;
+///
+/// let alias: CommandAlias = "cp=/checkpoint".parse().unwrap();
+/// assert_eq!(alias.name, "cp");
+/// assert_eq!(alias.expansion, "/checkpoint");
+/// assert_eq!(alias.expand("before-refactor").unwrap(), "/checkpoint before-refactor");
+/// assert_eq!(alias.to_string(), "cp=/checkpoint");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CommandAlias {
+    /// Alias name without the leading slash
+    pub name: String,
+
+    /// Command line template the alias expands to
+    pub expansion: String,
+}
+
+impl CommandAlias {
+    /// Expand the alias template with the given (unparsed) arguments
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments cannot be tokenized or a numbered
+    /// placeholder refers to an argument that was not given.
+    pub fn expand(&self, args: &str) -> Result<String> {
+        let tokens = tokenize(args)?;
+        let mut expanded = self
+            .expansion
+            .replace("{{date}}", &Local::now().format("%Y-%m-%d").to_string())
+            .replace("{{time}}", &Local::now().format("%H%M%S").to_string());
+
+        let mut uses_args = expanded.contains("{{args}}");
+        expanded = expanded.replace("{{args}}", args.trim());
+
+        while let Some(start) = expanded.find("{{") {
+            let Some(len) = expanded[start..].find("}}") else {
+                break;
+            };
+            let placeholder = &expanded[start + 2..start + len];
+            let index: usize = placeholder
+                .parse()
+                .map_err(|_| anyhow!("Unknown placeholder {{{{{}}}}} in alias /{}", placeholder, self.name))?;
+            let token = index
+                .checked_sub(1)
+                .and_then(|i| tokens.get(i))
+                .ok_or_else(|| anyhow!("Alias /{} expects argument {}", self.name, index))?;
+
+            let value = if token.contains(char::is_whitespace) {
+                format!("\"{}\"", token)
+            } else {
+                token.clone()
+            };
+            expanded.replace_range(start..start + len + 2, &value);
+            uses_args = true;
+        }
+
+        if !uses_args && !args.trim().is_empty() {
+            expanded.push(' ');
+            expanded.push_str(args.trim());
+        }
+
+        Ok(expanded)
+    }
+}
+
+impl fmt::Display for CommandAlias {
+    /// Format the alias in its `NAME=EXPANSION` definition form
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.expansion)
+    }
+}
+
+impl TryFrom<String> for CommandAlias {
+    type Error = anyhow::Error;
+
+    fn try_from(definition: String) -> Result<Self> {
+        definition.parse()
+    }
+}
+
+impl From<CommandAlias> for String {
+    fn from(alias: CommandAlias) -> Self {
+        alias.to_string()
+    }
+}
+
+impl FromStr for CommandAlias {
+    type Err = anyhow::Error;
+
+    /// Parse an alias definition of the form `NAME=EXPANSION`
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, expansion) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Alias must have the form NAME=COMMAND, got '{}'", s))?;
+
+        let name = name.trim().trim_start_matches('/');
+        if split_command(&format!("/{}", name)) != Some((name, "")) {
+            bail!("Invalid alias name: '{}'", name);
+        }
+
+        let expansion = expansion.trim();
+        if !expansion.starts_with('/') {
+            bail!("Alias /{} must expand to a slash command, got '{}'", name, expansion);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            expansion: expansion.to_string(),
+        })
+    }
+}
+
+/// Lines `/search` shows before and after each match unless `--context`
+/// says otherwise
+pub const DEFAULT_SEARCH_CONTEXT: usize = 1;
+
+/// Commands [`CommandRegistry::restrict`] never turns off, so a restricted
+/// session can still be explained and ended
+pub const ALWAYS_ENABLED: &[&str] = &["help", "exit"];
+
+/// Registry of all slash commands known to the dialog loop
+#[derive(Debug, Clone)]
+pub struct CommandRegistry {
+    commands: Vec<CommandSpec>,
+    aliases: Vec<CommandAlias>,
+    // Names of the commands turned off by `restrict`
+    disabled: Vec<&'static str>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandRegistry {
+    /// Create a registry containing the built-in commands
+    pub fn new() -> Self {
+        Self {
+            commands: vec![
+                CommandSpec {
+                    name: "help",
+                    summary: "List the available commands, or explain one of them",
+                    positionals: &[Positional {
+                        name: "command",
+                        help: "Command to explain, e.g. find",
+                        required: false,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_help,
+                },
+                CommandSpec {
+                    name: "checkpoint",
+                    summary: "Save the conversation state (and workspace files with --files)",
+                    positionals: &[Positional {
+                        name: "name",
+                        help: "Name of the checkpoint",
+                        required: true,
+                        rest: false,
+                    }],
+                    flags: &[Flag {
+                        long: "files",
+                        value_name: None,
+                        help: "Also snapshot the workspace files",
+                    }],
+                    verbatim: false,
+                    build: build_checkpoint,
+                },
+                CommandSpec {
+                    name: "restore",
+                    summary: "Roll the conversation back to a saved checkpoint",
+                    positionals: &[Positional {
+                        name: "name",
+                        help: "Name of the checkpoint to restore",
+                        required: true,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_restore,
+                },
+                CommandSpec {
+                    name: "find",
+                    summary: "Search the conversation and list the matching turns",
+                    positionals: &[Positional {
+                        name: "text",
+                        help: "Text to search for (case-insensitive)",
+                        required: true,
+                        rest: true,
+                    }],
+                    flags: &[Flag {
+                        long: "full",
+                        value_name: None,
+                        help: "Redisplay the matching turns in full",
+                    }],
+                    verbatim: false,
+                    build: build_find,
+                },
+                CommandSpec {
+                    name: "search",
+                    summary: "List numbered matches with the lines around them; type a number to show that turn",
+                    positionals: &[Positional {
+                        name: "text",
+                        help: "Text to search for (case-insensitive)",
+                        required: true,
+                        rest: true,
+                    }],
+                    flags: &[Flag {
+                        long: "context",
+                        value_name: Some("lines"),
+                        help: "Lines shown before and after each match (default 1)",
+                    }],
+                    verbatim: false,
+                    build: build_search,
+                },
+                CommandSpec {
+                    name: "turn",
+                    summary: "Show a turn of the conversation in full",
+                    positionals: &[Positional {
+                        name: "number",
+                        help: "Number of the turn, as shown by /find and /search",
+                        required: true,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_turn,
+                },
+                CommandSpec {
+                    name: "show",
+                    summary: "Show the response of an earlier turn again, rendered",
+                    positionals: &[Positional {
+                        name: "number",
+                        help: "Number of the turn, as shown after \"Claude\" above each response",
+                        required: true,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_show,
+                },
+                CommandSpec {
+                    name: "last",
+                    summary: "Show the most recent response again, e.g. after tool output scrolled it away",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Last),
+                },
+                CommandSpec {
+                    name: "repo-map",
+                    summary: "Send a map of the repository's files and symbols with the next prompt",
+                    positionals: &[],
+                    flags: &[Flag {
+                        long: "show",
+                        value_name: None,
+                        help: "Also print the map",
+                    }],
+                    verbatim: false,
+                    build: build_repo_map,
+                },
+                CommandSpec {
+                    name: "attach",
+                    summary: "Send a file's contents with the next prompt",
+                    positionals: &[Positional {
+                        name: "path",
+                        help: "Text file to attach, sent whole in a fenced code block",
+                        required: true,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_attach,
+                },
+                CommandSpec {
+                    name: "copy",
+                    summary: "Copy the last response to the system clipboard",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Copy),
+                },
+                CommandSpec {
+                    name: "paste",
+                    summary: "Send the clipboard's text, after a prompt if one is given",
+                    positionals: &[Positional {
+                        name: "prompt",
+                        help: "Prompt the clipboard's text follows, e.g. \"Why does this fail?\"",
+                        required: false,
+                        rest: true,
+                    }],
+                    flags: &[],
+                    verbatim: true,
+                    build: build_paste,
+                },
+                CommandSpec {
+                    name: "edit",
+                    summary: "Write a prompt in $VISUAL or $EDITOR and send it when the editor is closed",
+                    positionals: &[Positional {
+                        name: "text",
+                        help: "Text the prompt starts with",
+                        required: false,
+                        rest: true,
+                    }],
+                    flags: &[],
+                    verbatim: true,
+                    build: build_edit,
+                },
+                CommandSpec {
+                    name: "preview",
+                    summary: "Show exactly what would be sent for a prompt, then ask before sending it",
+                    positionals: &[Positional {
+                        name: "prompt",
+                        help: "The prompt, typed as it would be sent",
+                        required: true,
+                        rest: true,
+                    }],
+                    flags: &[],
+                    verbatim: true,
+                    build: build_preview,
+                },
+                CommandSpec {
+                    name: "model",
+                    summary: "Show the model, or switch models for the following prompts",
+                    positionals: &[Positional {
+                        name: "name",
+                        help: "Model name or alias, e.g. sonnet; `default` for the CLI's default",
+                        required: false,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_model,
+                },
+                CommandSpec {
+                    name: "persona",
+                    summary: "List the personas, or send the following prompts to one of them",
+                    positionals: &[Positional {
+                        name: "name",
+                        help: "Persona defined with --persona; `default` for the session's own backend and model",
+                        required: false,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_persona,
+                },
+                CommandSpec {
+                    name: "preset",
+                    summary: "List the prompt library's presets, or use one as the system prompt",
+                    positionals: &[Positional {
+                        name: "name",
+                        help: "Preset to use, from `claude-dialog prompts list`",
+                        required: false,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_preset,
+                },
+                CommandSpec {
+                    name: "system",
+                    summary: "Show the system prompt, or replace it with a file's contents",
+                    positionals: &[Positional {
+                        name: "file",
+                        help: "File containing the new system prompt, or a directory of .md files",
+                        required: false,
+                        rest: false,
+                    }],
+                    flags: &[Flag {
+                        long: "reset",
+                        value_name: None,
+                        help: "Go back to Claude's default system prompt",
+                    }],
+                    verbatim: false,
+                    build: build_system,
+                },
+                CommandSpec {
+                    name: "save",
+                    summary: "Save the session transcript as Markdown",
+                    positionals: &[Positional {
+                        name: "path",
+                        help: "File to write; defaults to the --save-transcript file or claude-dialog-<time>.md",
+                        required: false,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_save,
+                },
+                CommandSpec {
+                    name: "note",
+                    summary: "Attach a private note to the last turn; Claude never sees notes",
+                    positionals: &[Positional {
+                        name: "text",
+                        help: "The note, e.g. \"this answer was wrong\"",
+                        required: true,
+                        rest: true,
+                    }],
+                    flags: &[],
+                    verbatim: true,
+                    build: build_note,
+                },
+                CommandSpec {
+                    name: "rate",
+                    summary: "Rate the last response from 1 (poor) to 5 (excellent), shown in /status",
+                    positionals: &[Positional {
+                        name: "rating",
+                        help: "A whole number from 1 to 5",
+                        required: true,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_rate,
+                },
+                CommandSpec {
+                    name: "export",
+                    summary: "Export the conversation as JSON for --from to import, or the transcript as an HTML page",
+                    positionals: &[
+                        Positional {
+                            name: "format",
+                            help: "json (the default) or html; a single argument that is neither is the path",
+                            required: false,
+                            rest: false,
+                        },
+                        Positional {
+                            name: "path",
+                            help: "File to write; defaults to claude-dialog-<time>.json or .html",
+                            required: false,
+                            rest: false,
+                        },
+                    ],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_export,
+                },
+                CommandSpec {
+                    name: "status",
+                    summary: "Show the backend, model, session, usage and last error of this session",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Status),
+                },
+                CommandSpec {
+                    name: "mcp",
+                    summary: "List the MCP servers whose tools Claude can use",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Mcp),
+                },
+                CommandSpec {
+                    name: "stats",
+                    summary: "Show the tokens and estimated cost of this session, in total and per turn",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Stats),
+                },
+                CommandSpec {
+                    name: "cost",
+                    summary: "Show the estimated cost and prompt cache hits of this session, like /stats",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Stats),
+                },
+                CommandSpec {
+                    name: "clear",
+                    summary: "Forget the conversation and start a new one",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Clear),
+                },
+                CommandSpec {
+                    name: "cd",
+                    summary: "Switch Claude and @file references to another directory, starting a new conversation",
+                    positionals: &[Positional {
+                        name: "dir",
+                        help: "Directory to work in, e.g. another project",
+                        required: true,
+                        rest: false,
+                    }],
+                    flags: &[],
+                    verbatim: false,
+                    build: build_cd,
+                },
+                CommandSpec {
+                    name: "exit",
+                    summary: "End the conversation, like typing exit",
+                    positionals: &[],
+                    flags: &[],
+                    verbatim: false,
+                    build: |_| Ok(CommandAction::Exit),
+                },
+            ],
+            aliases: Vec::new(),
+            disabled: Vec::new(),
+        }
+    }
+
+    /// Register a user-defined alias
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the alias would shadow a built-in command or if it
+    /// does not expand to a built-in command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::commands::{CommandAction, CommandRegistry};
+    ///
+    /// let mut registry = CommandRegistry::new();
+    /// registry.register_alias("undo=/restore {{1}}".parse().unwrap()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     registry.parse("/undo start").unwrap(),
+    ///     Some(CommandAction::Restore { name: "start".to_string() })
+    /// );
+    ///
+    /// // Aliases must point at existing commands
+    /// assert!(registry.register_alias("r=/retry".parse().unwrap()).is_err());
+    /// ```
+    pub fn register_alias(&mut self, alias: CommandAlias) -> Result<()> {
+        if self.get(&alias.name).is_some() {
+            bail!("Alias /{} conflicts with a built-in command", alias.name);
+        }
+
+        let target = split_command(&alias.expansion).map(|(name, _)| name);
+        if target.and_then(|name| self.get(name)).is_none() {
+            bail!("Alias /{} refers to an unknown command: {}", alias.name, alias.expansion);
+        }
+
+        self.aliases.retain(|existing| existing.name != alias.name);
+        self.aliases.push(alias);
+        Ok(())
+    }
+
+    /// Turn off commands, e.g. for a locked-down profile
+    ///
+    /// With `allowed`, only the commands it names stay available; the
+    /// `disallowed` commands are turned off either way. [`ALWAYS_ENABLED`]
+    /// commands cannot be turned off. Disabled commands, and aliases of
+    /// them, are refused when typed and left out of `/help`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a name is not a built-in command or names one of
+    /// the [`ALWAYS_ENABLED`] commands in `disallowed`. The other names are
+    /// applied anyway, so a typo never enables a command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::commands::CommandRegistry;
+    ///
+    /// let mut registry = CommandRegistry::new();
+    /// registry.restrict(None, &["system".to_string(), "edit".to_string()]).unwrap();
+    ///
+    /// assert!(!registry.is_enabled("system"));
+    /// assert!(registry.parse("/system review.md").unwrap_err().to_string().contains("disabled"));
+    /// assert!(!registry.help(None).unwrap().contains("/system"));
+    ///
+    /// // Only the listed commands, with /help and /exit
+    /// registry.restrict(Some(&["find".to_string()]), &[]).unwrap();
+    /// assert!(registry.is_enabled("find") && registry.is_enabled("exit"));
+    /// assert!(!registry.is_enabled("export"));
+    /// ```
+    pub fn restrict(&mut self, allowed: Option<&[String]>, disallowed: &[String]) -> Result<()> {
+        let mut unknown = Vec::new();
+        let mut known = |names: &[String]| -> Vec<&'static str> {
+            names
+                .iter()
+                .map(|name| name.trim().trim_start_matches('/'))
+                .filter_map(|name| {
+                    let spec = self.commands.iter().find(|spec| spec.name == name);
+                    if spec.is_none() {
+                        unknown.push(format!("/{}", name));
+                    }
+                    spec.map(|spec| spec.name)
+                })
+                .collect()
+        };
+        let allowed = allowed.map(&mut known);
+        let disallowed = known(disallowed);
+
+        self.disabled = self
+            .commands
+            .iter()
+            .map(|spec| spec.name)
+            .filter(|name| !ALWAYS_ENABLED.contains(name))
+            .filter(|name| allowed.as_ref().is_some_and(|allowed| !allowed.contains(name)) || disallowed.contains(name))
+            .collect();
+
+        if !unknown.is_empty() {
+            bail!("Cannot restrict unknown command(s) {}", unknown.join(", "));
+        }
+        if let Some(name) = disallowed.iter().find(|name| ALWAYS_ENABLED.contains(name)) {
+            bail!("/{} cannot be disabled", name);
+        }
+        Ok(())
+    }
+
+    /// Whether a command may be used, i.e. is not turned off by
+    /// [`restrict`](Self::restrict)
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(&name)
+    }
+
+    /// The commands turned off by [`restrict`](Self::restrict), in
+    /// registration order
+    pub fn disabled(&self) -> &[&'static str] {
+        &self.disabled
+    }
+
+    /// Look up an alias by name (without the leading slash)
+    pub fn get_alias(&self, name: &str) -> Option<&CommandAlias> {
+        self.aliases.iter().find(|alias| alias.name == name)
+    }
+
+    /// All registered aliases in registration order
+    pub fn aliases(&self) -> &[CommandAlias] {
+        &self.aliases
+    }
+
+    /// Look up a command by name (without the leading slash)
+    pub fn get(&self, name: &str) -> Option<&CommandSpec> {
+        self.commands.iter().find(|spec| spec.name == name)
+    }
+
+    /// All registered commands in registration order
+    pub fn commands(&self) -> &[CommandSpec] {
+        &self.commands
+    }
+
+    /// Help text for `/help`
+    ///
+    /// Without a command, lists every enabled command with its usage and
+    /// summary, followed by the registered aliases of enabled commands.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `command` names neither a command nor an alias.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::commands::CommandRegistry;
+    ///
+    /// let registry = CommandRegistry::new();
+    /// assert!(registry.help(None).unwrap().contains("/restore <name>"));
+    /// assert!(registry.help(Some("restore")).unwrap().starts_with("Roll the conversation back"));
+    /// assert!(registry.help(Some("unknown")).is_err());
+    /// ```
+    pub fn help(&self, command: Option<&str>) -> Result<String> {
+        if let Some(name) = command {
+            let name = name.trim_start_matches('/');
+            if let Some(alias) = self.get_alias(name) {
+                return Ok(format!("/{} is an alias for {}", alias.name, alias.expansion));
+            }
+            if !self.is_enabled(name) {
+                bail!("/{} is disabled by the configuration", name);
+            }
+            return self
+                .get(name)
+                .map(CommandSpec::help)
+                .ok_or_else(|| anyhow!("Unknown command: /{}", name));
+        }
+
+        let commands: Vec<&CommandSpec> = self.commands.iter().filter(|spec| self.is_enabled(spec.name)).collect();
+        let usages: Vec<String> = commands.iter().map(|spec| spec.usage()).collect();
+        let width = usages.iter().map(|usage| usage.chars().count()).max().unwrap_or(0);
+
+        let mut help = String::from("Commands:");
+        for (spec, usage) in commands.iter().zip(&usages) {
+            help.push_str(&format!("\n  {:<width$}  {}", usage, spec.summary, width = width));
+        }
+
+        let aliases: Vec<&CommandAlias> = self
+            .aliases
+            .iter()
+            .filter(|alias| split_command(&alias.expansion).is_some_and(|(target, _)| self.is_enabled(target)))
+            .collect();
+        if !aliases.is_empty() {
+            help.push_str("\n\nAliases:");
+            for alias in aliases {
+                help.push_str(&format!("\n  /{}  {}", alias.name, alias.expansion));
+            }
+        }
+
+        help.push_str("\n\nType /<command> --help for details of a command.");
+        Ok(help)
+    }
+
+    /// Parse a line of user input
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(action))` - The input is a valid slash command
+    /// * `Ok(None)` - The input is a regular prompt for Claude
+    /// * `Err(_)` - The input is an unknown or malformed slash command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::commands::{CommandAction, CommandRegistry};
+    ///
+    /// let registry = CommandRegistry::new();
+    ///
+    /// assert_eq!(
+    ///     registry.parse("/restore \"before refactor\"").unwrap(),
+    ///     Some(CommandAction::Restore { name: "before refactor".to_string() })
+    /// );
+    /// assert!(registry.parse("/restore").is_err());
+    /// assert!(registry.parse("/unknown").is_err());
+    ///
+    /// // Paths are not mistaken for commands
+    /// assert_eq!(registry.parse("/usr/bin/env is missing").unwrap(), None);
+    /// ```
+    pub fn parse(&self, input: &str) -> Result<Option<CommandAction>> {
+        let Some((name, rest)) = split_command(input) else {
+            return Ok(None);
+        };
+
+        if let Some(alias) = self.get_alias(name) {
+            let expanded = alias.expand(rest)?;
+            return match split_command(&expanded) {
+                Some((target, rest)) => self.parse_builtin(target, rest),
+                None => Err(anyhow!("Alias /{} expanded to an invalid command: {}", name, expanded)),
+            };
+        }
+
+        self.parse_builtin(name, rest)
+    }
+
+    /// Parse the arguments of a built-in command
+    fn parse_builtin(&self, name: &str, rest: &str) -> Result<Option<CommandAction>> {
+        let spec = self
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown command: /{}", name))?;
+        if !self.is_enabled(name) {
+            bail!("/{} is disabled by the configuration", name);
+        }
+
+        let usage_error = |e: anyhow::Error| anyhow!("{}\nUsage: {}", e, spec.usage());
+
+        let tokens = if spec.verbatim {
+            // `--` keeps a prompt starting with dashes from being read as a flag
+            match rest.trim() {
+                "" => Vec::new(),
+                text => vec!["--".to_string(), text.to_string()],
+            }
+        } else {
+            tokenize(rest).map_err(usage_error)?
+        };
+        let tokens: Vec<&str> = tokens.iter().map(String::as_str).collect();
+
+        if tokens.contains(&"--help") {
+            return Ok(Some(CommandAction::Help(spec.help())));
+        }
+
+        let args = spec.parse_args(&tokens).map_err(usage_error)?;
+        (spec.build)(&args).map(Some).map_err(usage_error)
+    }
+}
+
+/// Split input into a command name and the unparsed rest of the line
+///
+/// Returns `None` when the input is not a slash command, i.e. when it does
+/// not start with `/` followed by a name made of letters, digits, `-` or `_`.
+fn split_command(input: &str) -> Option<(&str, &str)> {
+    let rest = input.trim().strip_prefix('/')?;
+    let (name, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+    let is_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_name {
+        return None;
+    }
+
+    Some((name, rest))
+}
+
+/// Split an argument string on whitespace, honouring single and double quotes
+fn tokenize(input: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quote = None;
+
+    for c in input.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_token = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        bail!("Unterminated quote");
+    }
+    if in_token {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
+
+fn build_help(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::ShowHelp {
+        command: args.positional("command").map(String::from),
+    })
+}
+
+fn build_checkpoint(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Checkpoint {
+        name: args.required("name")?,
+        include_files: args.flag("files"),
+    })
+}
+
+fn build_restore(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Restore {
+        name: args.required("name")?,
+    })
+}
+
+fn build_find(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Find {
+        query: args.required("text")?,
+        full: args.flag("full"),
+    })
+}
+
+fn build_search(args: &ParsedArgs) -> Result<CommandAction> {
+    let context = match args.value("context") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("--context must be a number of lines, got '{}'", value))?,
+        None => DEFAULT_SEARCH_CONTEXT,
+    };
+    Ok(CommandAction::Search {
+        query: args.required("text")?,
+        context,
+    })
+}
+
+fn build_turn(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Turn { number: turn_number(args)? })
+}
+
+fn build_show(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Show { number: turn_number(args)? })
+}
+
+/// The `number` argument of a command naming a turn
+fn turn_number(args: &ParsedArgs) -> Result<usize> {
+    let value = args.required("number")?;
+    match value.parse() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => bail!("Turn number must be a whole number from 1, got '{}'", value),
+    }
+}
+
+fn build_repo_map(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::RepoMap {
+        show: args.flag("show"),
+    })
+}
+
+fn build_cd(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Cd {
+        dir: PathBuf::from(args.required("dir")?),
+    })
+}
+
+fn build_attach(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Attach {
+        path: PathBuf::from(args.required("path")?),
+    })
+}
+
+fn build_paste(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Paste {
+        prompt: args.positional("prompt").map(String::from),
+    })
+}
+
+fn build_edit(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Edit {
+        text: args.positional("text").map(String::from),
+    })
+}
+
+fn build_preview(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Preview {
+        text: args.required("prompt")?,
+    })
+}
+
+fn build_note(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Note {
+        text: args.required("text")?,
+    })
+}
+
+fn build_rate(args: &ParsedArgs) -> Result<CommandAction> {
+    let value = args.required("rating")?;
+    match value.parse() {
+        Ok(rating) if (1..=MAX_RATING).contains(&rating) => Ok(CommandAction::Rate { rating }),
+        _ => bail!("Rating must be a whole number from 1 to {}, got '{}'", MAX_RATING, value),
+    }
+}
+
+fn build_export(args: &ParsedArgs) -> Result<CommandAction> {
+    let path = args.positional("path").map(PathBuf::from);
+    match args.positional("format") {
+        None => Ok(CommandAction::Export { format: ExportFormat::Json, path }),
+        // `/export handoff.json` predates the formats
+        Some(first) if path.is_none() && first.parse::<ExportFormat>().is_err() => Ok(CommandAction::Export {
+            format: ExportFormat::Json,
+            path: Some(PathBuf::from(first)),
+        }),
+        Some(format) => Ok(CommandAction::Export { format: format.parse()?, path }),
+    }
+}
+
+fn build_model(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Model {
+        name: args.positional("name").map(String::from),
+    })
+}
+
+fn build_persona(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Persona {
+        name: args.positional("name").map(String::from),
+    })
+}
+
+fn build_preset(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Preset {
+        name: args.positional("name").map(String::from),
+    })
+}
+
+fn build_save(args: &ParsedArgs) -> Result<CommandAction> {
+    Ok(CommandAction::Save {
+        path: args.positional("path").map(PathBuf::from),
+    })
+}
+
+fn build_system(args: &ParsedArgs) -> Result<CommandAction> {
+    let file = args.positional("file").map(PathBuf::from);
+    let reset = args.flag("reset");
+    if file.is_some() && reset {
+        bail!("Give either a file or --reset, not both");
+    }
+    Ok(CommandAction::System { file, reset })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(split_command("/restore a"), Some(("restore", "a")));
+        assert_eq!(split_command("  /checkpoint  a  --files "), Some(("checkpoint", " a  --files")));
+        assert_eq!(split_command("hello"), None);
+        assert_eq!(split_command("/"), None);
+        assert_eq!(split_command("/tmp/file.txt"), None);
+    }
+
+    #[test]
+    fn test_tokenize_quotes() {
+        assert_eq!(tokenize(r#"a "b c" 'd e'f"#).unwrap(), vec!["a", "b c", "d ef"]);
+        assert_eq!(tokenize(r#""""#).unwrap(), vec![""]);
+        assert!(tokenize(r#""open"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_flags_and_values() {
+        const SPEC: CommandSpec = CommandSpec {
+            name: "save",
+            summary: "Save",
+            positionals: &[Positional { name: "path", help: "", required: false, rest: false }],
+            flags: &[Flag { long: "format", value_name: Some("fmt"), help: "" }],
+            verbatim: false,
+            build: |_| Ok(CommandAction::Help(String::new())),
+        };
+
+        let args = SPEC.parse_args(&["--format", "json", "out.json"]).unwrap();
+        assert_eq!(args.value("format"), Some("json"));
+        assert_eq!(args.positional("path"), Some("out.json"));
+
+        let args = SPEC.parse_args(&["--format=md"]).unwrap();
+        assert_eq!(args.value("format"), Some("md"));
+        assert_eq!(args.positional("path"), None);
+
+        let args = SPEC.parse_args(&["--", "--weird-name"]).unwrap();
+        assert_eq!(args.positional("path"), Some("--weird-name"));
+
+        let err = SPEC.parse_args(&["--format"]).unwrap_err().to_string();
+        assert_eq!(err, "Option --format requires a value <fmt>");
+
+        assert_eq!(SPEC.usage(), "/save [path] [--format <fmt>]");
+    }
+
+    #[test]
+    fn test_parse_args_rest_positional() {
+        let registry = CommandRegistry::new();
+        let spec = registry.get("find").unwrap();
+        assert_eq!(spec.usage(), "/find <text...> [--full]");
+
+        let args = spec.parse_args(&["borrow", "--full", "checker"]).unwrap();
+        assert_eq!(args.positional("text"), Some("borrow checker"));
+        assert!(args.flag("full"));
+
+        let err = spec.parse_args(&["--full"]).unwrap_err().to_string();
+        assert_eq!(err, "Missing argument <text>");
+    }
+
+    #[test]
+    fn test_alias_expansion() {
+        let alias: CommandAlias = "s=/checkpoint {{date}}-{{1}}".parse().unwrap();
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(alias.expand("x").unwrap(), format!("/checkpoint {}-x", today));
+        assert!(alias.expand("").unwrap_err().to_string().contains("expects argument 1"));
+
+        let alias: CommandAlias = "c=/checkpoint {{args}} --files".parse().unwrap();
+        assert_eq!(alias.expand(" a ").unwrap(), "/checkpoint a --files");
+
+        let alias: CommandAlias = "c=/checkpoint {{1}}".parse().unwrap();
+        assert_eq!(alias.expand("'a b'").unwrap(), "/checkpoint \"a b\"");
+
+        let alias: CommandAlias = "c=/checkpoint {{oops}}".parse().unwrap();
+        assert!(alias.expand("").is_err());
+    }
+
+    #[test]
+    fn test_alias_serializes_as_definition() {
+        let alias: CommandAlias = "cp=/checkpoint {{1}}".parse().unwrap();
+        let json = serde_json::to_string(&alias).unwrap();
+        assert_eq!(json, r#""cp=/checkpoint {{1}}""#);
+        assert_eq!(serde_json::from_str::<CommandAlias>(&json).unwrap(), alias);
+
+        assert!(serde_json::from_str::<CommandAlias>(r#""cp=checkpoint""#).is_err());
+    }
+
+    #[test]
+    fn test_alias_definition_errors() {
+        assert!("r".parse::<CommandAlias>().is_err());
+        assert!("r=retry".parse::<CommandAlias>().is_err());
+        assert!("a b=/restore".parse::<CommandAlias>().is_err());
+        assert_eq!("/r = /restore".parse::<CommandAlias>().unwrap().name, "r");
+
+        let mut registry = CommandRegistry::new();
+        let err = registry.register_alias("restore=/checkpoint".parse().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("conflicts with a built-in command"));
+    }
+
+    #[test]
+    fn test_parse_checkpoint_with_files() {
+        let registry = CommandRegistry::new();
+        let action = registry.parse("/checkpoint snap --files").unwrap();
+        assert_eq!(action, Some(CommandAction::Checkpoint {
+            name: "snap".to_string(),
+            include_files: true,
+        }));
+    }
+
+    #[test]
+    fn test_parse_errors_include_usage() {
+        let registry = CommandRegistry::new();
+        let err = registry.parse("/checkpoint").unwrap_err().to_string();
+        assert!(err.contains("Missing argument <name>"));
+        assert!(err.contains("Usage: /checkpoint <name> [--files]"));
+
+        let err = registry.parse("/checkpoint a --all").unwrap_err().to_string();
+        assert!(err.contains("Unknown option: --all"));
+
+        let err = registry.parse("/checkpoint a --files=yes").unwrap_err().to_string();
+        assert!(err.contains("Option --files does not take a value"));
+    }
+}
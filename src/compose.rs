@@ -0,0 +1,142 @@
+//! External editor module
+//!
+//! `/edit` and Ctrl+X Ctrl+E at the `You>` prompt open the user's editor on
+//! a temporary Markdown file and send what is saved there as the prompt,
+//! for long, structured prompts that are tedious to type on one line.
+//! Ctrl+X Ctrl+E starts the file with the input typed so far, `/edit <text>`
+//! with the text.
+//!
+//! The editor is `$VISUAL`, else `$EDITOR`, else `vi` (`notepad` on
+//! Windows); its arguments are split at whitespace, so `code --wait` works.
+//! Saving an empty file, or quitting the editor with an error, sends
+//! nothing.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::compose::composed_prompt;
+//!
+//! let prompt = composed_prompt("Review this plan:\n\n1. Parse\n2. Render\n\n").unwrap();
+//! assert_eq!(prompt, "Review this plan:\n\n1. Parse\n2. Render");
+//!
+//! assert_eq!(composed_prompt("\n  \n"), None);
+//! ```
+
+use anyhow::{Context, Result, bail};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::Builder;
+use tokio::process::Command;
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+
+/// The editor command line: `$VISUAL`, else `$EDITOR`, else the platform's
+/// default editor
+pub fn editor_command() -> String {
+    editor_from(env::var("VISUAL").ok(), env::var("EDITOR").ok())
+}
+
+fn editor_from(visual: Option<String>, editor: Option<String>) -> String {
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .find(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Open the editor on a temporary file starting with `initial` and return
+/// the prompt saved in it
+///
+/// The editor runs in the terminal of the session, which waits until it
+/// exits. The temporary file is newly created, only accessible to the user
+/// as the prompt may hold secrets, and removed afterwards.
+///
+/// # Returns
+///
+/// The saved text as [`composed_prompt`] returns it; `None` if the file
+/// was left empty
+///
+/// # Errors
+///
+/// Returns an error if the temporary file cannot be written or read, or the
+/// editor cannot be started or exits with an error.
+pub async fn compose(initial: &str) -> Result<Option<String>> {
+    let mut file = Builder::new()
+        .prefix("claude-dialog-prompt-")
+        .suffix(".md")
+        .tempfile()
+        .context("Failed to create a file for the prompt")?;
+    let mut text = initial.to_string();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    file.write_all(text.as_bytes())
+        .and_then(|()| file.flush())
+        .with_context(|| format!("Failed to write {}", file.path().display()))?;
+
+    run_editor(&editor_command(), file.path()).await?;
+    // Editors may save to a new file in its place, so it is read again by path
+    let text = fs::read_to_string(file.path()).with_context(|| format!("Failed to read {}", file.path().display()))?;
+    Ok(composed_prompt(&text))
+}
+
+/// Run `command` on `path` and wait for it to exit without blocking the
+/// runtime
+async fn run_editor(command: &str, path: &Path) -> Result<()> {
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        bail!("No editor is set; set $VISUAL or $EDITOR");
+    };
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .await
+        .with_context(|| format!("Failed to start the editor '{}'; set $VISUAL or $EDITOR", command))?;
+    if !status.success() {
+        bail!("The editor '{}' exited with {}; nothing was sent", command, status);
+    }
+    Ok(())
+}
+
+/// The prompt in text saved from the editor: the text without trailing
+/// whitespace, keeping indentation, or `None` if it is blank
+pub fn composed_prompt(text: &str) -> Option<String> {
+    let text = text.trim_end();
+    let start = text.len() - text.trim_start_matches(['\n', '\r']).len();
+    Some(text[start..].to_string()).filter(|text| !text.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_preference() {
+        let some = |command: &str| Some(command.to_string());
+        assert_eq!(editor_from(some("code --wait"), some("nano")), "code --wait");
+        assert_eq!(editor_from(some(" "), some("nano")), "nano");
+        assert_eq!(editor_from(None, None), DEFAULT_EDITOR);
+    }
+
+    #[test]
+    fn test_composed_prompt_keeps_leading_indentation() {
+        assert_eq!(composed_prompt("\n    indented();\n").unwrap(), "    indented();");
+    }
+
+    #[tokio::test]
+    async fn test_failing_editor_sends_nothing() {
+        let dir = std::env::temp_dir();
+        assert!(run_editor("claude-dialog-no-such-editor", &dir).await.is_err());
+        assert!(run_editor("  ", &dir).await.unwrap_err().to_string().starts_with("No editor is set"));
+    }
+}
@@ -0,0 +1,511 @@
+//! Configuration file module
+//!
+//! `config.toml` in the configuration directory (see [`crate::paths`])
+//! holds defaults for options that would otherwise be repeated on every
+//! start, and named profiles that override them, selected with
+//! `--profile NAME`. Command-line options override both.
+//!
+//! ```toml
+//! model = "sonnet"
+//! allowed_tools = ["Read", "Edit", "Write"]
+//! language = "en"
+//! spellcheck = true
+//! color = "auto"
+//!
+//! [profiles.work]
+//! model = "opus"
+//! system_prompt = ["work.md"]
+//! disallowed_tools = ["WebFetch"]
+//!
+//! [profiles.offline]
+//! personas = ["draft=ollama:llama3"]
+//! color = "never"
+//!
+//! [profiles.support]
+//! disallowed_commands = ["system", "preset", "attach", "edit"]
+//! ```
+//!
+//! Relative system prompt paths are looked up in the `prompts` directory
+//! next to the file, and relative `mask_words` paths next to the file
+//! itself; `~` and environment variables are expanded. Unknown
+//! keys are rejected so that typos do not go unnoticed.
+//!
+//! The `version` key records the format of the file (see
+//! [`crate::migrate`]); a file without one predates it. Files in an older
+//! format are read as if they were current, and rewritten by [`upgrade`],
+//! after a backup next to them, only when the upgrade changes what they
+//! say.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::config::ConfigFile;
+//!
+//! let config = ConfigFile::parse(r#"
+//!     model = "sonnet"
+//!
+//!     [profiles.work]
+//!     model = "opus"
+//! "#).unwrap();
+//!
+//! assert_eq!(config.settings(None).unwrap().model.as_deref(), Some("sonnet"));
+//! assert_eq!(config.settings(Some("work")).unwrap().model.as_deref(), Some("opus"));
+//! assert!(config.settings(Some("home")).is_err());
+//! ```
+
+use anyhow::{Result, Context, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::language::Language;
+use crate::migrate::{self, Migration, Schema};
+use crate::paths;
+use crate::persona::Persona;
+
+/// Format of the configuration file and the migrations from older formats
+pub static SCHEMA: Schema<toml::Table> = Schema {
+    name: "the configuration file",
+    version: 1,
+    migrations: &[Migration {
+        from: 0,
+        summary: "added the `version` key",
+        apply: |_| Ok(()),
+    }],
+};
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Color unless `NO_COLOR` is set or the output is not a terminal
+    #[default]
+    Auto,
+
+    /// Always color, also when the output is redirected
+    Always,
+
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Make the choice effective for all output of the process
+    pub fn apply(self) {
+        match self {
+            ColorChoice::Auto => colored::control::unset_override(),
+            ColorChoice::Always => colored::control::set_override(true),
+            ColorChoice::Never => colored::control::set_override(false),
+        }
+    }
+}
+
+/// Options that can be set in the configuration file
+///
+/// Each option is `None` when it is not set, so that profiles only override
+/// the options they mention.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Claude model, like `--model`
+    pub model: Option<String>,
+
+    /// System prompt files, like `--system-prompt`
+    pub system_prompt: Option<Vec<PathBuf>>,
+
+    /// Tools Claude may use without asking, like `--allowed-tools`
+    pub allowed_tools: Option<Vec<String>>,
+
+    /// Tools Claude must not use, like `--disallowed-tools`
+    pub disallowed_tools: Option<Vec<String>>,
+
+    /// Slash commands that may be used, without the slash; `help` and
+    /// `exit` always may
+    pub allowed_commands: Option<Vec<String>>,
+
+    /// Slash commands that must not be used, without the slash
+    pub disallowed_commands: Option<Vec<String>>,
+
+    /// Personas, like `--persona`
+    pub personas: Option<Vec<Persona>>,
+
+    /// Language responses are expected in, like `--language`
+    pub language: Option<Language>,
+
+    /// Check prompts for likely typos before sending, like `--spellcheck`
+    pub spellcheck: Option<bool>,
+
+    /// Ask before sending prompts that look unfinished, `false` like
+    /// `--send-unfinished`
+    pub confirm_unfinished: Option<bool>,
+
+    /// End prompts with the time, working directory and OS, like
+    /// `--turn-metadata`
+    pub turn_metadata: Option<bool>,
+
+    /// Suggest follow-up questions after answers, like
+    /// `--suggest-follow-ups`
+    pub suggest_follow_ups: Option<bool>,
+
+    /// Print responses without rendering their Markdown, like `--raw`
+    pub raw: Option<bool>,
+
+    /// Syntax highlight code blocks, `false` like `--no-highlight`
+    pub highlight: Option<bool>,
+
+    /// Mark api requests for prompt caching, `false` like
+    /// `--no-prompt-cache`
+    pub prompt_cache: Option<bool>,
+
+    /// Show the usage of each turn after its response, like `--show-usage`
+    pub show_usage: Option<bool>,
+
+    /// Seconds after which a `claude` command is stopped, like `--timeout`
+    pub timeout: Option<u64>,
+
+    /// Mask personal data in responses, like `--mask-pii`
+    pub mask_pii: Option<bool>,
+
+    /// Word lists of words masked in responses, like `--mask-words`
+    pub mask_words: Option<Vec<PathBuf>>,
+
+    /// Patterns masked in responses, like `--mask-pattern`
+    pub mask_patterns: Option<Vec<String>>,
+
+    /// Mask responses in transcripts too, like `--mask-transcripts`
+    pub mask_transcripts: Option<bool>,
+
+    /// Save the lines typed to the history file, `false` like `--private`
+    pub history: Option<bool>,
+
+    /// Patterns of lines never saved to the history file, added to those
+    /// of `--history-ignore`
+    pub history_ignore: Option<Vec<String>>,
+
+    /// When to color the output
+    pub color: Option<ColorChoice>,
+}
+
+impl Settings {
+    /// These settings with every option set in `other` replaced
+    pub fn overlay(self, other: Settings) -> Settings {
+        Settings {
+            model: other.model.or(self.model),
+            system_prompt: other.system_prompt.or(self.system_prompt),
+            allowed_tools: other.allowed_tools.or(self.allowed_tools),
+            disallowed_tools: other.disallowed_tools.or(self.disallowed_tools),
+            allowed_commands: other.allowed_commands.or(self.allowed_commands),
+            disallowed_commands: other.disallowed_commands.or(self.disallowed_commands),
+            personas: other.personas.or(self.personas),
+            language: other.language.or(self.language),
+            spellcheck: other.spellcheck.or(self.spellcheck),
+            confirm_unfinished: other.confirm_unfinished.or(self.confirm_unfinished),
+            turn_metadata: other.turn_metadata.or(self.turn_metadata),
+            suggest_follow_ups: other.suggest_follow_ups.or(self.suggest_follow_ups),
+            raw: other.raw.or(self.raw),
+            highlight: other.highlight.or(self.highlight),
+            prompt_cache: other.prompt_cache.or(self.prompt_cache),
+            show_usage: other.show_usage.or(self.show_usage),
+            timeout: other.timeout.or(self.timeout),
+            mask_pii: other.mask_pii.or(self.mask_pii),
+            mask_words: other.mask_words.or(self.mask_words),
+            mask_patterns: other.mask_patterns.or(self.mask_patterns),
+            mask_transcripts: other.mask_transcripts.or(self.mask_transcripts),
+            history: other.history.or(self.history),
+            history_ignore: other.history_ignore.or(self.history_ignore),
+            color: other.color.or(self.color),
+        }
+    }
+
+    /// Resolve the system prompt files, looking up relative paths in `prompts_dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a path refers to an unset environment variable.
+    pub fn system_prompt_files(&self, prompts_dir: &Path) -> Result<Vec<PathBuf>> {
+        self.system_prompt
+            .iter()
+            .flatten()
+            .map(|path| Ok(prompts_dir.join(paths::expand(path.as_os_str())?)))
+            .collect()
+    }
+
+    /// Resolve the mask word lists, looking up relative paths in `config_dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a path refers to an unset environment variable.
+    pub fn mask_word_files(&self, config_dir: &Path) -> Result<Vec<PathBuf>> {
+        self.mask_words
+            .iter()
+            .flatten()
+            .map(|path| Ok(config_dir.join(paths::expand(path.as_os_str())?)))
+            .collect()
+    }
+}
+
+/// Contents of the configuration file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigFile {
+    /// Options used by every session
+    pub defaults: Settings,
+
+    /// Named sets of options overriding the defaults
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+impl ConfigFile {
+    /// Parse the TOML text of a configuration file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not valid TOML, a key is unknown or a
+    /// value has the wrong type, such as an invalid persona, or the file was
+    /// written by a newer release.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut table: toml::Table = text.parse()?;
+        let version = take_version(&mut table)?;
+        SCHEMA.migrate(&mut table, version)?;
+        let profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles
+                .into_iter()
+                .map(|(name, settings)| {
+                    let settings = settings.try_into().with_context(|| format!("Invalid profile '{}'", name))?;
+                    Ok((name, settings))
+                })
+                .collect::<Result<_>>()?,
+            Some(_) => bail!("`profiles` must be a table of [profiles.NAME] sections"),
+            None => BTreeMap::new(),
+        };
+        let defaults = toml::Value::Table(table).try_into()?;
+        Ok(Self { defaults, profiles })
+    }
+
+    /// Load the configuration file at `path`, or `None` if there is none
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Self::parse(&text)
+            .map(Some)
+            .with_context(|| format!("Invalid configuration file {}", path.display()))
+    }
+
+    /// The defaults, overridden by the options of `profile` if one is given
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the available profiles if there is no
+    /// profile called `profile`.
+    pub fn settings(&self, profile: Option<&str>) -> Result<Settings> {
+        let Some(name) = profile else {
+            return Ok(self.defaults.clone());
+        };
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            if names.is_empty() {
+                anyhow!("No profile named '{}'; the configuration file defines none", name)
+            } else {
+                anyhow!("No profile named '{}'; available profiles: {}", name, names.join(", "))
+            }
+        })?;
+        Ok(self.defaults.clone().overlay(profile.clone()))
+    }
+}
+
+/// A configuration file rewritten in the current format by [`upgrade`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upgrade {
+    /// Format version of the file before the upgrade
+    pub from: u32,
+
+    /// Copy of the file as it was before the upgrade
+    pub backup: PathBuf,
+
+    /// What the applied migrations changed, oldest first
+    pub changes: Vec<&'static str>,
+}
+
+/// Rewrite the configuration file at `path` in the current format, if it
+/// is in an older one
+///
+/// Files whose content an upgrade leaves unchanged, apart from the format
+/// version, are left as they are, comments included; they are upgraded in
+/// memory whenever they are loaded.
+///
+/// # Returns
+///
+/// The upgrade made, or `None` if the file was left as it is, including
+/// when there is no file or it cannot be parsed, which loading it reports
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be backed up or rewritten, or a
+/// migration fails.
+pub fn upgrade(path: &Path) -> Result<Option<Upgrade>> {
+    upgrade_with(path, &SCHEMA)
+}
+
+fn upgrade_with(path: &Path, schema: &Schema<toml::Table>) -> Result<Option<Upgrade>> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let Ok(mut table) = text.parse::<toml::Table>() else {
+        return Ok(None);
+    };
+    let from = match take_version(&mut table) {
+        Ok(from) if from < schema.version => from,
+        _ => return Ok(None),
+    };
+
+    let original = table.clone();
+    let changes = schema.migrate(&mut table, from)?;
+    if table == original {
+        return Ok(None);
+    }
+
+    let backup = migrate::backup(path, from)?;
+    let text = toml::to_string(&table).context("Failed to serialize the upgraded configuration")?;
+    fs::write(path, format!("version = {}\n{}", schema.version, text)).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(Some(Upgrade { from, backup, changes }))
+}
+
+/// Remove the `version` key from a parsed file and return the version; 0
+/// without one
+fn take_version(table: &mut toml::Table) -> Result<u32> {
+    match table.remove("version") {
+        None => Ok(0),
+        Some(toml::Value::Integer(version)) => {
+            u32::try_from(version).map_err(|_| anyhow!("`version` must be a format version such as 1"))
+        }
+        Some(_) => bail!("`version` must be a format version such as 1"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_override_only_what_they_set() {
+        let config = ConfigFile::parse(
+            r#"
+            model = "sonnet"
+            allowed_tools = ["Read"]
+            color = "never"
+
+            [profiles.work]
+            allowed_tools = []
+            personas = ["draft=claude:haiku"]
+            language = "ja"
+            spellcheck = true
+            confirm_unfinished = false
+            raw = false
+            highlight = false
+            mask_words = ["customers.txt"]
+            mask_patterns = ['ACME-\d+']
+            disallowed_commands = ["system", "edit"]
+            history = false
+            history_ignore = ["*password*"]
+            "#,
+        )
+        .unwrap();
+
+        let settings = config.settings(Some("work")).unwrap();
+        assert_eq!(settings.model.as_deref(), Some("sonnet"));
+        assert_eq!(settings.allowed_tools, Some(Vec::new()));
+        assert_eq!(settings.personas.as_ref().unwrap()[0].name, "draft");
+        assert_eq!(settings.color, Some(ColorChoice::Never));
+        assert_eq!(settings.language, Some(Language::Japanese));
+        assert_eq!(settings.spellcheck, Some(true));
+        assert_eq!(settings.confirm_unfinished, Some(false));
+        assert_eq!(settings.raw, Some(false));
+        assert_eq!(settings.highlight, Some(false));
+        assert_eq!(settings.mask_patterns, Some(vec![r"ACME-\d+".to_string()]));
+        assert_eq!(settings.disallowed_commands, Some(vec!["system".to_string(), "edit".to_string()]));
+        assert_eq!(config.settings(None).unwrap().disallowed_commands, None);
+        assert_eq!(settings.history, Some(false));
+        assert_eq!(settings.history_ignore, Some(vec!["*password*".to_string()]));
+        assert_eq!(settings.mask_word_files(Path::new("/config")).unwrap(), [Path::new("/config/customers.txt")]);
+
+        let err = config.settings(Some("home")).unwrap_err();
+        assert_eq!(err.to_string(), "No profile named 'home'; available profiles: work");
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(ConfigFile::parse("modle = \"opus\"").is_err());
+        assert!(ConfigFile::parse("[profiles.work]\ncolour = \"never\"").is_err());
+        assert!(ConfigFile::parse("personas = [\"draft\"]").is_err());
+        assert!(ConfigFile::parse("model = ").is_err());
+        assert!(ConfigFile::parse("language = \"elvish\"").is_err());
+        assert!(ConfigFile::parse("profiles = [\"work\"]").is_err());
+        assert_eq!(ConfigFile::parse("").unwrap(), ConfigFile::default());
+    }
+
+    #[test]
+    fn test_format_versions() {
+        let current = ConfigFile::parse("version = 1
+model = \"opus\"").unwrap();
+        assert_eq!(current, ConfigFile::parse("model = \"opus\"").unwrap());
+        assert!(ConfigFile::parse("version = \"1\"").is_err());
+        assert!(ConfigFile::parse("version = -1").is_err());
+
+        let err = ConfigFile::parse("version = 2
+model = \"opus\"").unwrap_err();
+        assert!(err.to_string().starts_with("The configuration file has format version 2"));
+    }
+
+    static RENAMING: Schema<toml::Table> = Schema {
+        name: "the configuration file",
+        version: 2,
+        migrations: &[
+            Migration { from: 0, summary: "added the `version` key", apply: |_| Ok(()) },
+            Migration {
+                from: 1,
+                summary: "renamed `tools` to `allowed_tools`",
+                apply: |table| {
+                    if let Some(tools) = table.remove("tools") {
+                        table.insert("allowed_tools".to_string(), tools);
+                    }
+                    Ok(())
+                },
+            },
+        ],
+    };
+
+    #[test]
+    fn test_upgrade_rewrites_only_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        fs::write(&path, "# mine\nmodel = \"opus\"\n").unwrap();
+        assert_eq!(upgrade(&path).unwrap(), None);
+        assert_eq!(upgrade_with(&path, &RENAMING).unwrap(), None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# mine\nmodel = \"opus\"\n");
+
+        fs::write(&path, "version = 1\ntools = [\"Read\"]\n").unwrap();
+        let upgrade = upgrade_with(&path, &RENAMING).unwrap().unwrap();
+        assert_eq!(upgrade.from, 1);
+        assert_eq!(upgrade.changes, ["renamed `tools` to `allowed_tools`"]);
+        assert_eq!(fs::read_to_string(&upgrade.backup).unwrap(), "version = 1\ntools = [\"Read\"]\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "version = 2\nallowed_tools = [\"Read\"]\n");
+    }
+
+    #[test]
+    fn test_system_prompt_files() {
+        let settings = Settings {
+            system_prompt: Some(vec!["review.md".into(), "/etc/prompt.md".into()]),
+            ..Default::default()
+        };
+        let files = settings.system_prompt_files(Path::new("/config/prompts")).unwrap();
+        assert_eq!(files, [PathBuf::from("/config/prompts/review.md"), PathBuf::from("/etc/prompt.md")]);
+    }
+}
@@ -0,0 +1,909 @@
+//! `@file` context inclusion module
+//!
+//! A prompt can pull files into the conversation by mentioning them with a
+//! leading `@`, e.g. `Why does @src/main.rs panic?`. Each referenced file is
+//! tagged with a footnote number in the prompt text and its content is
+//! appended in a `<file>` block, so Claude can tell which file a passage
+//! comes from. The inclusions are recorded as [`FileInclusion`]s, which the
+//! dialog loop renders as a "Sources" footer and keeps with the turn, so
+//! later readers know what context an answer was based on.
+//!
+//! A reference can also be a glob, e.g. `@src/**/*.rs`, which includes every
+//! matching file that is not excluded by [`crate::ignore_rules`].
+//!
+//! References that do not name an existing file, such as `@channel`, are left
+//! as they are. Trailing punctuation after a reference is ignored, and paths
+//! may use `~` and environment variables (see [`crate::paths::expand`]).
+//!
+//! Large inclusions can be kept within a token budget: see
+//! [`PromptContext::fit`] and [`BudgetStrategy`].
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::context::expand_file_references;
+//! # let dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(dir.path().join("notes.md"), "first\nsecond\n").unwrap();
+//!
+//! // `dir` contains notes.md with two lines
+//! let expanded = expand_file_references("Summarize @notes.md, please", dir.path()).unwrap();
+//!
+//! assert!(expanded.text.starts_with("Summarize notes.md [1], please"));
+//! assert!(expanded.text.contains("<file index=\"1\" path=\"notes.md\" lines=\"1-2\">\nfirst\nsecond\n</file>"));
+//! assert_eq!(expanded.sources[0].to_string(), "notes.md (lines 1-2)");
+//! ```
+
+#[cfg(feature = "dialog")]
+use anyhow::{Result, Context, anyhow};
+#[cfg(feature = "dialog")]
+use globset::GlobBuilder;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+#[cfg(feature = "dialog")]
+use std::fs;
+#[cfg(feature = "dialog")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "dialog")]
+use crate::chunk;
+#[cfg(feature = "dialog")]
+use crate::chunk::Selector;
+#[cfg(feature = "dialog")]
+use crate::ignore_rules;
+#[cfg(feature = "dialog")]
+use crate::paths;
+
+/// Token budget for included files used when none is configured
+pub const DEFAULT_CONTEXT_BUDGET: usize = 100_000;
+
+/// Characters ignored at the end of a reference, e.g. in `see @a.rs.`
+#[cfg(feature = "dialog")]
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '"', '\''];
+
+/// Characters that make a reference a glob
+#[cfg(feature = "dialog")]
+const GLOB_CHARS: &[char] = &['*', '?', '['];
+
+/// Largest number of files a glob reference may include
+#[cfg(feature = "dialog")]
+const MAX_GLOB_FILES: usize = 100;
+
+/// Rough number of characters per token, used for estimates
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Characters reserved for the marker left where lines were omitted
+#[cfg(feature = "dialog")]
+const OMISSION_MARKER_CHARS: usize = 32;
+
+/// Estimate the number of tokens in a text
+///
+/// The estimate assumes about four characters per token, which is close
+/// enough for English text and source code to decide what fits a budget.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::context::estimate_tokens;
+///
+/// assert_eq!(estimate_tokens(""), 0);
+/// assert_eq!(estimate_tokens("fn main() {}"), 3);
+/// ```
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// How to proceed when included files exceed the context budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum BudgetStrategy {
+    /// Keep the beginning of large files
+    Truncate,
+
+    /// Keep the beginning and the end of large files
+    #[default]
+    HeadTail,
+
+    /// Ask which files to drop, then trim the rest with head and tail
+    Ask,
+}
+
+impl fmt::Display for BudgetStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BudgetStrategy::Truncate => "truncate",
+            BudgetStrategy::HeadTail => "head-tail",
+            BudgetStrategy::Ask => "ask",
+        };
+        f.write_str(name)
+    }
+}
+
+/// An inclusive range of line numbers, starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRange {
+    /// First line of the range
+    pub start: usize,
+
+    /// Last line of the range
+    pub end: usize,
+}
+
+impl LineRange {
+    /// Number of lines in the range
+    pub fn len(&self) -> usize {
+        self.end + 1 - self.start
+    }
+
+    /// Whether the range contains no lines
+    ///
+    /// Ranges are never empty by construction; this exists for symmetry with
+    /// [`LineRange::len`].
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+}
+
+impl fmt::Display for LineRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+/// A file (or part of one) that was sent to Claude along with a prompt
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::context::{FileInclusion, LineRange};
+///
+/// let inclusion = FileInclusion::whole("src/main.rs", 120);
+/// assert_eq!(inclusion.to_string(), "src/main.rs (lines 1-120)");
+///
+/// let inclusion = FileInclusion {
+///     path: "src/main.rs".into(),
+///     lines: vec![LineRange { start: 1, end: 40 }, LineRange { start: 81, end: 120 }],
+///     total_lines: 120,
+/// };
+/// assert_eq!(inclusion.to_string(), "src/main.rs (lines 1-40, 81-120 of 120)");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileInclusion {
+    /// Path as written in the prompt
+    pub path: PathBuf,
+
+    /// Lines that were sent, in order; empty if none was
+    pub lines: Vec<LineRange>,
+
+    /// Number of lines in the file
+    pub total_lines: usize,
+}
+
+impl FileInclusion {
+    /// An inclusion of every line of a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path as written in the prompt
+    /// * `total_lines` - Number of lines in the file
+    pub fn whole(path: impl Into<PathBuf>, total_lines: usize) -> Self {
+        let lines = if total_lines == 0 {
+            Vec::new()
+        } else {
+            vec![LineRange { start: 1, end: total_lines }]
+        };
+
+        Self {
+            path: path.into(),
+            lines,
+            total_lines,
+        }
+    }
+
+    /// Whether every line of the file was sent
+    pub fn is_complete(&self) -> bool {
+        self.lines.iter().map(LineRange::len).sum::<usize>() == self.total_lines
+    }
+
+    /// Sent line ranges in `1-40, 81-120` form
+    pub fn line_ranges(&self) -> String {
+        self.lines
+            .iter()
+            .map(LineRange::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for FileInclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.display();
+        match self.lines.as_slice() {
+            [] if self.total_lines == 0 => write!(f, "{} (empty)", path),
+            [] => write!(f, "{} (not sent)", path),
+            [single] if single.len() == 1 && self.total_lines == 1 => write!(f, "{} (line 1)", path),
+            [single] if single.len() == 1 => write!(f, "{} (line {} of {})", path, single, self.total_lines),
+            _ if self.is_complete() => write!(f, "{} (lines {})", path, self.line_ranges()),
+            _ => write!(f, "{} (lines {} of {})", path, self.line_ranges(), self.total_lines),
+        }
+    }
+}
+
+/// A prompt with its `@file` references expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedPrompt {
+    /// The text to send to Claude
+    pub text: String,
+
+    /// Files referenced in the prompt, in footnote order
+    pub sources: Vec<FileInclusion>,
+}
+
+/// A file referenced in a prompt, with the lines selected for sending
+#[cfg(feature = "dialog")]
+#[derive(Debug, Clone)]
+pub struct ContextFile {
+    reference: String,
+    path: PathBuf,
+    lines: Vec<String>,
+    selected: Vec<LineRange>,
+}
+
+#[cfg(feature = "dialog")]
+impl ContextFile {
+    fn new(reference: &str, path: PathBuf, lines: Vec<String>, chunk: Option<LineRange>) -> Self {
+        let selected = match chunk {
+            Some(chunk) => vec![chunk],
+            None => FileInclusion::whole(&path, lines.len()).lines,
+        };
+
+        Self {
+            reference: reference.to_string(),
+            path,
+            lines,
+            selected,
+        }
+    }
+
+    /// Path as written in the prompt
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Estimated tokens of the selected lines
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.selected_text())
+    }
+
+    /// Description of the lines selected for sending
+    pub fn inclusion(&self) -> FileInclusion {
+        FileInclusion {
+            path: self.path.clone(),
+            lines: self.selected.clone(),
+            total_lines: self.lines.len(),
+        }
+    }
+
+    /// The selected lines, with a marker where lines were left out between them
+    fn selected_text(&self) -> String {
+        let mut text = String::new();
+        let mut previous_end = None;
+
+        for range in &self.selected {
+            if let Some(end) = previous_end {
+                text.push_str(&format!("[... {} lines omitted ...]\n", range.start - end - 1));
+            }
+            for line in &self.lines[range.start - 1..range.end] {
+                text.push_str(line);
+                text.push('\n');
+            }
+            previous_end = Some(range.end);
+        }
+
+        text
+    }
+
+    /// Select as many of the selected lines as fit in `tokens`, following `strategy`
+    fn trim(&mut self, tokens: usize, strategy: BudgetStrategy) {
+        let (Some(first), Some(last)) = (self.selected.first(), self.selected.last()) else {
+            return;
+        };
+        let start = first.start;
+        let window = &self.lines[start - 1..last.end];
+        let budget = tokens * CHARS_PER_TOKEN;
+
+        self.selected = match strategy {
+            BudgetStrategy::Truncate => {
+                let mut left = budget;
+                let head = count_fitting(window.iter(), &mut left);
+                ranges(start, head, window.len(), window.len())
+            }
+            BudgetStrategy::HeadTail | BudgetStrategy::Ask => {
+                // Half for the head, and whatever the head left over for the tail
+                let budget = budget.saturating_sub(OMISSION_MARKER_CHARS);
+                let mut left = budget / 2;
+                let head = count_fitting(window.iter(), &mut left);
+                left += budget - budget / 2;
+                let tail = count_fitting(window[head..].iter().rev(), &mut left);
+                ranges(start, head, window.len() - tail, window.len())
+            }
+        };
+    }
+}
+
+/// Count the leading lines that fit in `chars_left`, deducting them from it
+#[cfg(feature = "dialog")]
+fn count_fitting<'a>(lines: impl Iterator<Item = &'a String>, chars_left: &mut usize) -> usize {
+    lines
+        .take_while(|line| {
+            let chars = line.chars().count() + 1;
+            let fits = chars <= *chars_left;
+            if fits {
+                *chars_left -= chars;
+            }
+            fits
+        })
+        .count()
+}
+
+/// Ranges covering the first `head` of `len` lines starting at line `first`,
+/// and the lines from index `tail_start` to the end
+#[cfg(feature = "dialog")]
+fn ranges(first: usize, head: usize, tail_start: usize, len: usize) -> Vec<LineRange> {
+    let mut ranges = Vec::new();
+    if head > 0 {
+        ranges.push(LineRange { start: first, end: first + head - 1 });
+    }
+    if tail_start < len {
+        let tail = LineRange { start: first + tail_start, end: first + len - 1 };
+        match ranges.last_mut() {
+            Some(last) if last.end + 1 == tail.start => last.end = tail.end,
+            _ => ranges.push(tail),
+        }
+    }
+    ranges
+}
+
+/// A prompt with the files it references, before they are inlined
+///
+/// Use [`collect_file_references`] to create one, [`PromptContext::fit`] or
+/// [`PromptContext::drop_file`] to reduce what is sent, and
+/// [`PromptContext::render`] to produce the text for Claude.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::context::{BudgetStrategy, collect_file_references};
+/// # let dir = tempfile::tempdir().unwrap();
+/// # let log: String = (1..=1000).map(|n| format!("line {}\n", n)).collect();
+/// # std::fs::write(dir.path().join("build.log"), log).unwrap();
+///
+/// // build.log has 1000 lines, far more than 200 tokens
+/// let mut context = collect_file_references("Why did @build.log fail?", dir.path()).unwrap();
+/// assert!(context.tokens() > 200);
+///
+/// context.fit(200, BudgetStrategy::HeadTail);
+/// assert!(context.tokens() <= 200);
+///
+/// let expanded = context.render();
+/// assert!(expanded.sources[0].to_string().ends_with("of 1000)"));
+/// assert!(expanded.text.contains("lines omitted"));
+/// ```
+#[cfg(feature = "dialog")]
+#[derive(Debug, Clone)]
+pub struct PromptContext {
+    text: String,
+    files: Vec<ContextFile>,
+}
+
+#[cfg(feature = "dialog")]
+impl PromptContext {
+    /// Referenced files in footnote order
+    pub fn files(&self) -> &[ContextFile] {
+        &self.files
+    }
+
+    /// Estimated tokens of all selected file content
+    pub fn tokens(&self) -> usize {
+        self.files.iter().map(ContextFile::tokens).sum()
+    }
+
+    /// Send none of a file's content
+    ///
+    /// The file keeps its footnote number, and the footer reports it as not
+    /// sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Position of the file in [`PromptContext::files`]
+    pub fn drop_file(&mut self, index: usize) {
+        if let Some(file) = self.files.get_mut(index) {
+            file.selected.clear();
+        }
+    }
+
+    /// Trim the selected content until it fits in `budget` tokens
+    ///
+    /// The budget is shared evenly between the files; files smaller than
+    /// their share are kept whole and leave the rest to the larger ones.
+    /// [`BudgetStrategy::Ask`] needs an answer from the user, so here it
+    /// trims like [`BudgetStrategy::HeadTail`].
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Largest number of tokens to send
+    /// * `strategy` - Which lines of large files to keep
+    pub fn fit(&mut self, budget: usize, strategy: BudgetStrategy) {
+        let mut order: Vec<usize> = (0..self.files.len()).collect();
+        order.sort_by_key(|&index| self.files[index].tokens());
+
+        let mut remaining = budget;
+        for (position, &index) in order.iter().enumerate() {
+            let share = remaining / (order.len() - position);
+            let file = &mut self.files[index];
+            if file.tokens() > share {
+                file.trim(share, strategy);
+            }
+            remaining = remaining.saturating_sub(file.tokens());
+        }
+    }
+
+    /// Inline the selected file content into the prompt
+    pub fn render(self) -> ExpandedPrompt {
+        let mut text = self.text;
+        for (index, file) in self.files.iter().enumerate() {
+            if file.selected.is_empty() && !file.lines.is_empty() {
+                continue;
+            }
+
+            let inclusion = file.inclusion();
+            text.push_str(&format!(
+                "\n\n<file index=\"{}\" path=\"{}\" lines=\"{}\"",
+                index + 1,
+                inclusion.path.display(),
+                inclusion.line_ranges()
+            ));
+            if !inclusion.is_complete() {
+                text.push_str(&format!(" total_lines=\"{}\"", inclusion.total_lines));
+            }
+            text.push_str(">\n");
+            text.push_str(&file.selected_text());
+            text.push_str("</file>");
+        }
+
+        ExpandedPrompt {
+            text,
+            sources: self.files.iter().map(ContextFile::inclusion).collect(),
+        }
+    }
+}
+
+/// Find and read the `@file` references in a prompt
+///
+/// Every reference is replaced with the path followed by its footnote
+/// number, e.g. `src/main.rs [1]`. A reference may be written `@path`,
+/// `@file:path` or `@"path with spaces"`, and may select part of the file
+/// after a `#` (see [`crate::chunk`]). Identical references are read once.
+///
+/// # Arguments
+///
+/// * `prompt` - The prompt as typed by the user
+/// * `base_dir` - Directory relative paths are resolved against
+///
+/// # Errors
+///
+/// Returns an error if a referenced file exists but cannot be read as text,
+/// or the part it selects does not exist.
+#[cfg(feature = "dialog")]
+pub fn collect_file_references(prompt: &str, base_dir: &Path) -> Result<PromptContext> {
+    let mut text = String::with_capacity(prompt.len());
+    let mut files: Vec<ContextFile> = Vec::new();
+    let mut rest = prompt;
+    let mut at_word_start = true;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '@'
+            && at_word_start
+            && let Some((raw, len)) = scan_reference(&rest[1..])
+            && let Some(reference) = resolve(raw, base_dir)?
+        {
+            let mut indices = Vec::new();
+            for file in reference.read()? {
+                let index = match files.iter().position(|known| known.reference == file.reference) {
+                    Some(position) => position + 1,
+                    None => {
+                        files.push(file);
+                        files.len()
+                    }
+                };
+                indices.push(index.to_string());
+            }
+
+            text.push_str(&format!("{} [{}]{}", reference.text, indices.join(", "), reference.suffix));
+            rest = &rest[1 + len..];
+            at_word_start = false;
+            continue;
+        }
+
+        text.push(c);
+        at_word_start = c.is_whitespace();
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Ok(PromptContext { text, files })
+}
+
+/// Expand the `@file` references in a prompt
+///
+/// Shorthand for [`collect_file_references`] followed by
+/// [`PromptContext::render`], sending every referenced file in full.
+///
+/// # Arguments
+///
+/// * `prompt` - The prompt as typed by the user
+/// * `base_dir` - Directory relative paths are resolved against
+///
+/// # Errors
+///
+/// Returns an error if a referenced file exists but cannot be read as text.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::context::expand_file_references;
+///
+/// let expanded = expand_file_references("ping @here", ".".as_ref()).unwrap();
+/// assert_eq!(expanded.text, "ping @here");
+/// assert!(expanded.sources.is_empty());
+/// ```
+#[cfg(feature = "dialog")]
+pub fn expand_file_references(prompt: &str, base_dir: &Path) -> Result<ExpandedPrompt> {
+    Ok(collect_file_references(prompt, base_dir)?.render())
+}
+
+/// A reference that names existing files
+#[cfg(feature = "dialog")]
+struct Reference<'a> {
+    /// The reference without `file:` prefix and trailing punctuation
+    text: &'a str,
+    /// Punctuation following the reference
+    suffix: &'a str,
+    /// Named files: the path as written (or matched) and the path on disk
+    files: Vec<(PathBuf, PathBuf)>,
+    /// Whether the reference is a glob
+    glob: bool,
+    /// Part of the file to send, if not all of it
+    selector: Option<Selector>,
+}
+
+#[cfg(feature = "dialog")]
+impl Reference<'_> {
+    /// Read the files and select the referenced lines
+    ///
+    /// Files matched by a glob are referred to by their own path, so that a
+    /// file mentioned again by name is only included once.
+    fn read(&self) -> Result<Vec<ContextFile>> {
+        let context = || format!("Cannot include @{}", self.text);
+
+        let mut included = Vec::new();
+        for (path, file) in &self.files {
+            let content = fs::read_to_string(file).with_context(context)?;
+            let lines: Vec<String> = content.lines().map(String::from).collect();
+            let chunk = match &self.selector {
+                Some(selector) => Some(chunk::select(path, &lines, selector).with_context(context)?),
+                None => None,
+            };
+
+            let reference = if self.glob { path.to_string_lossy() } else { self.text.into() };
+            included.push(ContextFile::new(&reference, path.clone(), lines, chunk));
+        }
+
+        Ok(included)
+    }
+}
+
+/// Extent of the reference following an `@`
+///
+/// Returns the reference and the number of bytes it spans, including quotes.
+#[cfg(feature = "dialog")]
+fn scan_reference(after: &str) -> Option<(&str, usize)> {
+    if let Some(quoted) = after.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        return Some((&quoted[..end], end + 2));
+    }
+
+    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+    if end == 0 {
+        return None;
+    }
+
+    // `#fn main` continues with the symbol name after the space
+    if let Some((_, selector)) = after[..end].rsplit_once('#')
+        && chunk::is_definition_keyword(selector)
+    {
+        let next = &after[end..];
+        let spaces = next.len() - next.trim_start_matches(' ').len();
+        let name = next[spaces..].find(char::is_whitespace).unwrap_or(next.len() - spaces);
+        if spaces > 0 && name > 0 {
+            let len = end + spaces + name;
+            return Some((&after[..len], len));
+        }
+    }
+
+    Some((&after[..end], end))
+}
+
+/// Find the file a reference names, if any
+///
+/// # Errors
+///
+/// Returns an error if the reference names a file but its selector is invalid.
+#[cfg(feature = "dialog")]
+fn resolve<'a>(raw: &'a str, base_dir: &Path) -> Result<Option<Reference<'a>>> {
+    let raw = raw.strip_prefix("file:").unwrap_or(raw);
+    let trimmed = raw.trim_end_matches(TRAILING_PUNCTUATION);
+    let existing = |path: &str| {
+        let file = base_dir.join(paths::expand(path.as_ref()).ok()?);
+        file.is_file().then_some(file)
+    };
+
+    for candidate in [trimmed, raw].into_iter().filter(|candidate| !candidate.is_empty()) {
+        let suffix = &raw[candidate.len()..];
+
+        if let Some(file) = existing(candidate) {
+            let files = vec![(candidate.into(), file)];
+            return Ok(Some(Reference { text: candidate, suffix, files, glob: false, selector: None }));
+        }
+
+        if let Some((path, selector)) = candidate.split_once('#')
+            && let Some(file) = existing(path)
+        {
+            let selector = selector
+                .parse()
+                .with_context(|| format!("Cannot include @{}", candidate))?;
+            let files = vec![(path.into(), file)];
+            return Ok(Some(Reference { text: candidate, suffix, files, glob: false, selector: Some(selector) }));
+        }
+
+        if candidate.contains(GLOB_CHARS) {
+            let files = expand_glob(candidate, base_dir)
+                .with_context(|| format!("Cannot include @{}", candidate))?;
+            if !files.is_empty() {
+                return Ok(Some(Reference { text: candidate, suffix, files, glob: true, selector: None }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Files matching a glob such as `src/**/*.rs`, in path order
+///
+/// The leading components without wildcards name the directory that is
+/// searched; `*` does not match across `/` but `**` does. Files excluded by
+/// [`ignore_rules`] are never matched.
+///
+/// # Errors
+///
+/// Returns an error if the glob is invalid or matches more than
+/// [`MAX_GLOB_FILES`] files.
+#[cfg(feature = "dialog")]
+fn expand_glob(pattern: &str, base_dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let literal = components
+        .iter()
+        .take_while(|component| !component.contains(GLOB_CHARS))
+        .count();
+    let prefix = PathBuf::from(components[..literal].join("/"));
+    let root = base_dir.join(paths::expand(prefix.as_os_str())?);
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    // Without `**`, matches lie at a fixed depth below the directory
+    let rest = &components[literal..];
+    let max_depth = (!rest.iter().any(|component| component.contains("**"))).then_some(rest.len());
+    let matcher = GlobBuilder::new(&rest.join("/"))
+        .literal_separator(true)
+        .build()?
+        .compile_matcher();
+
+    let mut files = Vec::new();
+    for file in ignore_rules::files(&root, max_depth)? {
+        let Ok(relative) = file.strip_prefix(&root) else {
+            continue;
+        };
+        if matcher.is_match(relative) {
+            if files.len() == MAX_GLOB_FILES {
+                return Err(anyhow!("More than {} files match", MAX_GLOB_FILES));
+            }
+            files.push((prefix.join(relative), file));
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(all(test, feature = "dialog"))]
+mod tests {
+    use super::*;
+
+    fn numbered_file(lines: usize) -> ContextFile {
+        let lines = (1..=lines).map(|n| format!("{:03}", n)).collect();
+        ContextFile::new("f.txt", "f.txt".into(), lines, None)
+    }
+
+    #[test]
+    fn test_strategy_names_match_serde() {
+        for strategy in [BudgetStrategy::Truncate, BudgetStrategy::HeadTail, BudgetStrategy::Ask] {
+            let json = serde_json::to_string(&strategy).unwrap();
+            assert_eq!(json, format!("\"{}\"", strategy));
+        }
+    }
+
+    #[test]
+    fn test_inclusion_display() {
+        assert_eq!(FileInclusion::whole("a.txt", 0).to_string(), "a.txt (empty)");
+        assert_eq!(FileInclusion::whole("a.txt", 1).to_string(), "a.txt (line 1)");
+
+        let mut inclusion = FileInclusion::whole("a.txt", 3);
+        inclusion.lines.clear();
+        assert_eq!(inclusion.to_string(), "a.txt (not sent)");
+    }
+
+    #[test]
+    fn test_punctuation_and_repeated_references() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}").unwrap();
+        fs::write(dir.path().join("b.rs"), "").unwrap();
+
+        let expanded = expand_file_references("(@a.rs) vs @b.rs; again @a.rs.", dir.path()).unwrap();
+        let prompt = expanded.text.split("\n\n").next().unwrap();
+        assert_eq!(prompt, "(@a.rs) vs b.rs [1]; again a.rs [2].");
+        assert_eq!(expanded.sources.len(), 2);
+        assert!(expanded.text.ends_with("<file index=\"2\" path=\"a.rs\" lines=\"1\">\nfn a() {}\n</file>"));
+        assert!(expanded.text.contains("<file index=\"1\" path=\"b.rs\" lines=\"\">\n</file>"));
+    }
+
+    #[test]
+    fn test_directories_and_missing_files_are_not_references() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+
+        let expanded = expand_file_references("@src @missing.rs @ user@example.com", dir.path()).unwrap();
+        assert_eq!(expanded.text, "@src @missing.rs @ user@example.com");
+    }
+
+    #[test]
+    fn test_unreadable_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("image.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let err = expand_file_references("look at @image.bin", dir.path()).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot include @image.bin");
+    }
+
+    #[test]
+    fn test_trim_strategies() {
+        // Every line is "NNN\n", i.e. exactly one token
+        let mut file = numbered_file(10);
+        file.trim(4, BudgetStrategy::Truncate);
+        assert_eq!(file.inclusion().line_ranges(), "1-4");
+
+        // Head and tail leave room for the omission marker
+        let mut file = numbered_file(10);
+        file.trim(12, BudgetStrategy::HeadTail);
+        assert_eq!(file.inclusion().line_ranges(), "1-2, 9-10");
+        assert_eq!(file.selected_text(), "001\n002\n[... 6 lines omitted ...]\n009\n010\n");
+        assert!(file.tokens() <= 12);
+
+        let mut file = numbered_file(4);
+        file.trim(12, BudgetStrategy::HeadTail);
+        assert_eq!(file.inclusion().line_ranges(), "1-4");
+
+        let mut file = numbered_file(4);
+        file.trim(0, BudgetStrategy::HeadTail);
+        assert_eq!(file.inclusion().to_string(), "f.txt (not sent)");
+    }
+
+    #[test]
+    fn test_fit_shares_budget_between_files() {
+        let mut context = PromptContext {
+            text: String::new(),
+            files: vec![numbered_file(100), numbered_file(5), numbered_file(100)],
+        };
+        context.fit(45, BudgetStrategy::Truncate);
+
+        // The small file is kept whole and the rest is split evenly
+        let sent: Vec<usize> = context.files().iter().map(ContextFile::tokens).collect();
+        assert_eq!(sent, vec![20, 5, 20]);
+    }
+
+    #[test]
+    fn test_trim_within_chunk() {
+        let mut file = ContextFile::new("f.txt#11-30", "f.txt".into(), numbered_file(40).lines, Some(LineRange { start: 11, end: 30 }));
+        file.trim(12, BudgetStrategy::HeadTail);
+        assert_eq!(file.inclusion().to_string(), "f.txt (lines 11-12, 29-30 of 40)");
+    }
+
+    #[test]
+    fn test_scan_reference() {
+        assert_eq!(scan_reference("a.rs and more"), Some(("a.rs", 4)));
+        assert_eq!(scan_reference("\"my notes.md\" rest"), Some(("my notes.md", 13)));
+        assert_eq!(scan_reference("a.rs#fn main please"), Some(("a.rs#fn main", 12)));
+        assert_eq!(scan_reference("a.rs#fn"), Some(("a.rs#fn", 7)));
+        assert_eq!(scan_reference("\"unterminated"), None);
+        assert_eq!(scan_reference(" a.rs"), None);
+    }
+
+    #[test]
+    fn test_chunk_references() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "use x;\n\nfn main() {\n    run();\n}\n").unwrap();
+        fs::write(dir.path().join("my notes.md"), "# A\none\n# B\ntwo\n").unwrap();
+
+        let prompt = "Fix @file:main.rs#fn main, see @\"my notes.md#B\" and @main.rs#1.";
+        let expanded = expand_file_references(prompt, dir.path()).unwrap();
+
+        let text = expanded.text.split("\n\n").next().unwrap();
+        assert_eq!(text, "Fix main.rs#fn main [1], see my notes.md#B [2] and main.rs#1 [3].");
+        let sources: Vec<String> = expanded.sources.iter().map(ToString::to_string).collect();
+        assert_eq!(sources, vec!["main.rs (lines 3-5 of 5)", "my notes.md (lines 3-4 of 4)", "main.rs (line 1 of 5)"]);
+        assert!(expanded.text.contains("lines=\"3-5\" total_lines=\"5\">\nfn main() {\n    run();\n}\n</file>"));
+
+        let err = expand_file_references("@main.rs#fn nope", dir.path()).unwrap_err();
+        assert_eq!(format!("{:#}", err), "Cannot include @main.rs#fn nope: No definition of 'fn nope'");
+        assert!(expand_file_references("@main.rs#7-9", dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_dropped_file_is_not_rendered() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "alpha\n").unwrap();
+
+        let mut context = collect_file_references("see @a.txt", dir.path()).unwrap();
+        context.drop_file(0);
+        let expanded = context.render();
+
+        assert_eq!(expanded.text, "see a.txt [1]");
+        assert_eq!(expanded.sources[0].to_string(), "a.txt (not sent)");
+    }
+
+    #[test]
+    fn test_glob_references() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("src/bin/tool.rs"), "").unwrap();
+        fs::write(dir.path().join("src/secret.rs"), "").unwrap();
+        fs::write(dir.path().join(".claude-dialog-ignore"), "secret.rs\n").unwrap();
+
+        let context = collect_file_references("Compare @src/*.rs with @src/main.rs.", dir.path()).unwrap();
+        assert_eq!(context.text, "Compare src/*.rs [1, 2] with src/main.rs [2].");
+        let paths: Vec<_> = context.files().iter().map(|file| file.path().to_path_buf()).collect();
+        assert_eq!(paths, vec![PathBuf::from("src/lib.rs"), PathBuf::from("src/main.rs")]);
+
+        let context = collect_file_references("@src/**/*.rs", dir.path()).unwrap();
+        assert_eq!(context.files().len(), 3);
+
+        let expanded = expand_file_references("@src/*.py @*? @missing/*", dir.path()).unwrap();
+        assert_eq!(expanded.text, "@src/*.py @*? @missing/*");
+    }
+
+    #[test]
+    fn test_glob_matching_too_many_files_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        for n in 0..=MAX_GLOB_FILES {
+            fs::write(dir.path().join(format!("{}.txt", n)), "").unwrap();
+        }
+
+        let err = expand_file_references("@*.txt", dir.path()).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot include @*.txt");
+        assert_eq!(err.root_cause().to_string(), format!("More than {} files match", MAX_GLOB_FILES));
+    }
+}
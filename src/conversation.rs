@@ -0,0 +1,601 @@
+//! In-memory conversation state module
+//!
+//! This module keeps track of the turns exchanged during a dialog session so
+//! that features such as checkpoints can inspect and roll back the
+//! conversation, and `/find` can search it.
+//!
+//! Sessions that run for days can collect more text than is worth keeping
+//! in memory. Once the prompts and responses held exceed [`MEMORY_LIMIT`],
+//! the oldest turns are spilled: dropped from memory while the session's
+//! journal keeps them on disk, from where they are read back when the whole
+//! conversation is needed, e.g. for `/find` or `/export`.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::conversation::Conversation;
+//!
+//! let mut conversation = Conversation::new();
+//! conversation.push("Hello, Claude!");
+//! conversation.set_response("Hello! How can I help?");
+//! conversation.push("Explain ownership");
+//!
+//! assert_eq!(conversation.len(), 2);
+//! assert_eq!(conversation.prompts(), vec!["Hello, Claude!", "Explain ownership"]);
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use crate::context::FileInclusion;
+use crate::normalize::normalize;
+
+/// Highest rating `/rate` accepts; ratings start at 1
+pub const MAX_RATING: u8 = 5;
+
+/// Bytes of prompts and responses a session keeps in memory before it
+/// spills older turns to its journal
+pub const MEMORY_LIMIT: usize = 4 * 1024 * 1024;
+
+/// A single exchange within a conversation
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::conversation::Turn;
+///
+/// let turn = Turn::new("What is Rust?");
+/// assert_eq!(turn.prompt, "What is Rust?");
+/// assert!(turn.response.is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    /// The prompt the user sent to Claude
+    pub prompt: String,
+
+    /// Claude's response, once it has been received
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+
+    /// Files included in the prompt through `@file` references
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<FileInclusion>,
+
+    /// Private annotations added with `/note`, never sent to Claude
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+
+    /// Rating of the response from 1 to [`MAX_RATING`], given with `/rate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+
+    /// When the prompt was sent, `None` for turns recorded without a time,
+    /// e.g. recovered from a journal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<DateTime<Utc>>,
+
+    /// Label of the session the turn was merged from by
+    /// `claude-dialog sessions merge`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+
+    /// Languages of the code blocks of the response, in order of first
+    /// appearance, see [`crate::normalize`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub code_languages: Vec<String>,
+}
+
+impl Turn {
+    /// Create a new turn from the given prompt
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt sent to Claude
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            response: None,
+            sources: Vec::new(),
+            notes: Vec::new(),
+            rating: None,
+            time: None,
+            origin: None,
+            code_languages: Vec::new(),
+        }
+    }
+
+    /// Record Claude's response, normalized by [`normalize`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::Turn;
+    ///
+    /// let mut turn = Turn::new("Show me a test");
+    /// turn.set_response("```rust\n#[test]\nfn it_works() {}");
+    /// assert_eq!(turn.response.as_deref(), Some("```rust\n#[test]\nfn it_works() {}\n```"));
+    /// assert_eq!(turn.code_languages, ["rust"]);
+    /// ```
+    pub fn set_response(&mut self, response: &str) {
+        let normalized = normalize(response);
+        self.response = Some(normalized.text);
+        self.code_languages = normalized.code_languages;
+    }
+
+    /// Record the files that were included in the prompt
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::context::FileInclusion;
+    /// use claude_dialog::conversation::Turn;
+    ///
+    /// let source = FileInclusion::whole("notes.md", 2);
+    /// let turn = Turn::new("Summarize @notes.md").with_sources(vec![source]);
+    /// assert_eq!(turn.sources.len(), 1);
+    /// ```
+    pub fn with_sources(mut self, sources: Vec<FileInclusion>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Record when the prompt was sent
+    pub fn with_time(mut self, time: DateTime<Utc>) -> Self {
+        self.time = Some(time);
+        self
+    }
+}
+
+/// Ordered list of turns exchanged during a session
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::conversation::Conversation;
+///
+/// let mut conversation = Conversation::new();
+/// conversation.push("first");
+/// conversation.push("second");
+///
+/// // Roll back to the state after the first turn
+/// conversation.truncate(1);
+/// assert_eq!(conversation.prompts(), vec!["first"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Conversation {
+    // Number of turns before `turns` that were spilled from memory
+    #[serde(skip)]
+    spilled: usize,
+    turns: Vec<Turn>,
+}
+
+impl Conversation {
+    /// Create an empty conversation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new turn for the given prompt
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt sent to Claude
+    pub fn push(&mut self, prompt: impl Into<String>) {
+        self.push_turn(Turn::new(prompt));
+    }
+
+    /// Record a new turn
+    ///
+    /// # Arguments
+    ///
+    /// * `turn` - The turn, usually still without a response
+    pub fn push_turn(&mut self, turn: Turn) {
+        self.turns.push(turn);
+    }
+
+    /// Attach Claude's response to the most recent turn, normalized by
+    /// [`Turn::set_response`]
+    ///
+    /// Has no effect if no turn has been recorded yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The response text
+    pub fn set_response(&mut self, response: impl AsRef<str>) {
+        if let Some(turn) = self.turns.last_mut() {
+            turn.set_response(response.as_ref());
+        }
+    }
+
+    /// Attach a note to the most recent turn
+    ///
+    /// # Returns
+    ///
+    /// `false` if no turn has been recorded yet
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::Conversation;
+    ///
+    /// let mut conversation = Conversation::new();
+    /// assert!(!conversation.add_note("too early"));
+    ///
+    /// conversation.push("Explain ownership");
+    /// assert!(conversation.add_note("used in PR #42"));
+    /// assert_eq!(conversation.turns()[0].notes, ["used in PR #42"]);
+    /// ```
+    pub fn add_note(&mut self, note: impl Into<String>) -> bool {
+        match self.turns.last_mut() {
+            Some(turn) => {
+                turn.notes.push(note.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Rate the response of the most recent turn, replacing an earlier rating
+    ///
+    /// # Returns
+    ///
+    /// `false` if there is no turn yet or the most recent one has no response
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::Conversation;
+    ///
+    /// let mut conversation = Conversation::new();
+    /// conversation.push("Explain ownership");
+    /// assert!(!conversation.rate(4));
+    ///
+    /// conversation.set_response("Every value has a single owner.");
+    /// assert!(conversation.rate(4));
+    /// assert_eq!(conversation.turns()[0].rating, Some(4));
+    /// ```
+    pub fn rate(&mut self, rating: u8) -> bool {
+        match self.turns.last_mut() {
+            Some(turn) if turn.response.is_some() => {
+                turn.rating = Some(rating);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Number of turns recorded so far, including spilled ones
+    pub fn len(&self) -> usize {
+        self.spilled + self.turns.len()
+    }
+
+    /// Whether no turns have been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The recorded turns held in memory, in order: all of them, unless
+    /// the first [`Conversation::spilled`] turns were spilled
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// The prompts of the turns held in memory, in order
+    pub fn prompts(&self) -> Vec<&str> {
+        self.turns.iter().map(|turn| turn.prompt.as_str()).collect()
+    }
+
+    /// Drop every turn after the first `len` turns
+    ///
+    /// Has no effect if the conversation is already shorter than `len`.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Number of turns to keep
+    pub fn truncate(&mut self, len: usize) {
+        if len <= self.spilled {
+            self.spilled = len;
+            self.turns.clear();
+        } else {
+            self.turns.truncate(len - self.spilled);
+        }
+    }
+
+    /// Number of turns at the start of the conversation that were spilled
+    /// from memory
+    pub fn spilled(&self) -> usize {
+        self.spilled
+    }
+
+    /// Drop the oldest turns from memory while the prompts and responses
+    /// held take more than `limit` bytes, for a copy on disk to keep them
+    ///
+    /// Only turns before the turn numbered `before` (counting from 0) are
+    /// dropped, and never the most recent one, which notes and ratings
+    /// apply to. Spilled turns still count in [`Conversation::len`].
+    ///
+    /// # Returns
+    ///
+    /// The number of turns dropped
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::Conversation;
+    ///
+    /// let mut conversation = Conversation::new();
+    /// for prompt in ["first", "second", "third"] {
+    ///     conversation.push(prompt);
+    /// }
+    ///
+    /// assert_eq!(conversation.spill(5, 3), 2);
+    /// assert_eq!(conversation.len(), 3);
+    /// assert_eq!(conversation.prompts(), vec!["third"]);
+    /// ```
+    pub fn spill(&mut self, limit: usize, before: usize) -> usize {
+        let spillable = before
+            .saturating_sub(self.spilled)
+            .min(self.turns.len().saturating_sub(1));
+        let mut size: usize = self.turns.iter().map(text_len).sum();
+        let mut count = 0;
+        while count < spillable && size > limit {
+            size -= text_len(&self.turns[count]);
+            count += 1;
+        }
+
+        self.turns.drain(..count);
+        self.spilled += count;
+        count
+    }
+}
+
+/// Bytes of the prompt and response of a turn
+fn text_len(turn: &Turn) -> usize {
+    turn.prompt.len() + turn.response.as_ref().map_or(0, String::len)
+}
+
+/// Who wrote the text of a [`SearchHit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speaker {
+    /// The user's prompt
+    User,
+
+    /// Claude's response
+    Claude,
+}
+
+/// A line of the conversation containing the search text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    /// Index of the turn in the conversation, starting at 0
+    pub turn: usize,
+
+    /// Whether the line is part of the prompt or the response
+    pub speaker: Speaker,
+
+    /// The matching line
+    pub line: String,
+
+    /// Byte ranges of every occurrence of the search text in `line`
+    pub matches: Vec<Range<usize>>,
+
+    /// Lines of the same prompt or response right before `line`, as many as
+    /// the context asked for
+    pub before: Vec<String>,
+
+    /// Lines of the same prompt or response right after `line`
+    pub after: Vec<String>,
+}
+
+impl Conversation {
+    /// Find every line of the turns held in memory containing `query`
+    ///
+    /// Matching ignores case. Hits are returned in conversation order, the
+    /// prompt of a turn before its response. An empty query matches nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::{Conversation, Speaker};
+    ///
+    /// let mut conversation = Conversation::new();
+    /// conversation.push("What does the borrow checker do?");
+    /// conversation.set_response("It enforces the borrowing rules.\nBorrowing is checked at compile time.");
+    ///
+    /// let hits = conversation.find("BORROW");
+    /// assert_eq!(hits.len(), 3);
+    /// assert_eq!(hits[0].speaker, Speaker::User);
+    /// assert_eq!(hits[2].line, "Borrowing is checked at compile time.");
+    /// assert_eq!(hits[2].matches, vec![0..6]);
+    /// ```
+    pub fn find(&self, query: &str) -> Vec<SearchHit> {
+        self.find_with_context(query, 0)
+    }
+
+    /// Like [`find`](Self::find), with up to `context` lines before and
+    /// after each matching line
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::Conversation;
+    ///
+    /// let mut conversation = Conversation::new();
+    /// conversation.push("Why does this panic?");
+    /// conversation.set_response("The index:\nlet x = v[10];\nis out of bounds.");
+    ///
+    /// let hits = conversation.find_with_context("v[10]", 1);
+    /// assert_eq!(hits[0].before, ["The index:"]);
+    /// assert_eq!(hits[0].after, ["is out of bounds."]);
+    /// ```
+    pub fn find_with_context(&self, query: &str, context: usize) -> Vec<SearchHit> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits = Vec::new();
+        for (index, turn) in self.turns.iter().enumerate() {
+            let texts = [
+                (Speaker::User, Some(turn.prompt.as_str())),
+                (Speaker::Claude, turn.response.as_deref()),
+            ];
+            for (speaker, text) in texts {
+                let lines: Vec<&str> = text.into_iter().flat_map(str::lines).collect();
+                for (number, line) in lines.iter().enumerate() {
+                    let matches = find_ignore_case(line, query);
+                    if !matches.is_empty() {
+                        let owned = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect();
+                        hits.push(SearchHit {
+                            turn: self.spilled + index,
+                            speaker,
+                            line: line.to_string(),
+                            matches,
+                            before: owned(&lines[number.saturating_sub(context)..number]),
+                            after: owned(&lines[number + 1..(number + 1 + context).min(lines.len())]),
+                        });
+                    }
+                }
+            }
+        }
+        hits
+    }
+}
+
+/// Byte ranges of the non-overlapping occurrences of `needle` in `haystack`,
+/// ignoring case
+fn find_ignore_case(haystack: &str, needle: &str) -> Vec<Range<usize>> {
+    let same = |a: char, b: char| a == b || a.to_lowercase().eq(b.to_lowercase());
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < haystack.len() {
+        let mut candidate = haystack[start..].char_indices();
+        let mut end = start;
+        let found = needle.chars().all(|n| match candidate.next() {
+            Some((offset, c)) if same(c, n) => {
+                end = start + offset + c.len_utf8();
+                true
+            }
+            _ => false,
+        });
+
+        if found {
+            matches.push(start..end);
+            start = end;
+        } else {
+            start += haystack[start..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_len() {
+        let mut conversation = Conversation::new();
+        assert!(conversation.is_empty());
+
+        conversation.push("one");
+        conversation.push("two");
+        assert_eq!(conversation.len(), 2);
+        assert_eq!(conversation.turns()[1], Turn::new("two"));
+    }
+
+    #[test]
+    fn test_set_response() {
+        let mut conversation = Conversation::new();
+        conversation.set_response("ignored");
+        assert!(conversation.is_empty());
+
+        conversation.push("one");
+        conversation.set_response("first answer");
+        assert_eq!(conversation.turns()[0].response.as_deref(), Some("first answer"));
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut conversation = Conversation::new();
+        conversation.push("one");
+        conversation.push("two");
+        conversation.push("three");
+
+        conversation.truncate(1);
+        assert_eq!(conversation.prompts(), vec!["one"]);
+
+        conversation.truncate(5);
+        assert_eq!(conversation.len(), 1);
+    }
+
+    #[test]
+    fn test_spilled_turns() {
+        let mut conversation = Conversation::new();
+        for prompt in ["one", "two", "three", "four"] {
+            conversation.push(prompt);
+            conversation.set_response("answer");
+        }
+
+        // Turns from the one numbered `before` on stay in memory
+        assert_eq!(conversation.spill(0, 1), 1);
+        assert_eq!(conversation.spill(0, 1), 0);
+        assert_eq!(conversation.spill(0, 10), 2);
+        assert_eq!((conversation.spilled(), conversation.len()), (3, 4));
+        assert_eq!(conversation.find("four")[0].turn, 3);
+
+        conversation.truncate(2);
+        assert_eq!((conversation.spilled(), conversation.len()), (2, 2));
+        assert!(conversation.turns().is_empty());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut conversation = Conversation::new();
+        conversation.push("one");
+        conversation.set_response("first answer");
+        conversation.push("two");
+
+        let json = serde_json::to_string(&conversation).unwrap();
+        assert_eq!(
+            json,
+            r#"{"turns":[{"prompt":"one","response":"first answer"},{"prompt":"two"}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Conversation>(&json).unwrap(), conversation);
+    }
+
+    #[test]
+    fn test_find_ignore_case() {
+        assert_eq!(find_ignore_case("Rust rust RUST", "rust"), vec![0..4, 5..9, 10..14]);
+        assert_eq!(find_ignore_case("aaaa", "aa"), vec![0..2, 2..4]);
+        assert_eq!(find_ignore_case("Grüße ÜBER", "über"), vec![8..13]);
+        assert!(find_ignore_case("short", "shorter").is_empty());
+    }
+
+    #[test]
+    fn test_find_in_prompts_and_responses() {
+        let mut conversation = Conversation::new();
+        conversation.push("one");
+        conversation.set_response("first\nanswer one");
+        conversation.push("two");
+        assert!(conversation.find("").is_empty());
+
+        let hits = conversation.find("One");
+        let found: Vec<_> = hits.iter().map(|hit| (hit.turn, hit.speaker)).collect();
+        assert_eq!(found, vec![(0, Speaker::User), (0, Speaker::Claude)]);
+        assert_eq!(hits[1].line, "answer one");
+        assert!(hits[1].before.is_empty() && hits[1].after.is_empty());
+        assert!(conversation.find("three").is_empty());
+    }
+
+    #[test]
+    fn test_context_stops_at_the_ends_of_the_text() {
+        let mut conversation = Conversation::new();
+        conversation.push("match\nsecond\nthird");
+
+        let hits = conversation.find_with_context("MATCH", 5);
+        assert!(hits[0].before.is_empty());
+        assert_eq!(hits[0].after, ["second", "third"]);
+        assert_eq!(conversation.find_with_context("third", 1)[0].before, ["second"]);
+    }
+}
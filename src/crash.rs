@@ -0,0 +1,328 @@
+//! Crash report module
+//!
+//! A panic ends claude-dialog with little more than a message and a source
+//! line. [`install`] adds a panic hook that also writes a crash report to
+//! the `crashes/` directory of the state directory (see
+//! [`Paths::crash_dir`](crate::paths::Paths::crash_dir)) and prints its path
+//! with an invitation to attach it to an issue. The report holds the
+//! version and platform, the panic message, a backtrace, the session's
+//! options with anything personal left out (see [`sanitized`]) and the last
+//! action recorded with [`record_action`].
+//!
+//! Reports stay on the disk; nothing is sent anywhere. Incognito sessions
+//! install no hook and leave no report.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::Local;
+//! use claude_dialog::crash::CrashReport;
+//!
+//! let report = CrashReport {
+//!     time: Local::now(),
+//!     thread: "main".to_string(),
+//!     message: "index out of bounds".to_string(),
+//!     location: Some("src/dialog.rs:42:9".to_string()),
+//!     last_action: Some("Running /export".to_string()),
+//!     settings: vec![("backend", "claude".to_string())],
+//!     backtrace: String::new(),
+//! };
+//!
+//! let text = report.render();
+//! assert!(text.contains("Panic: index out of bounds at src/dialog.rs:42:9"));
+//! assert!(text.contains("Last action: Running /export"));
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::backtrace::Backtrace;
+use std::fmt::Write;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use crate::claude_executor::SessionMode;
+use crate::dialog::DialogConfig;
+use crate::paths;
+use crate::ui::UI;
+
+/// Where new issues are filed
+const ISSUES_URL: &str = concat!(env!("CARGO_PKG_REPOSITORY"), "/issues/new");
+
+/// What a crash report says about the session, kept up to date while it runs
+struct State {
+    last_action: Option<String>,
+    settings: Vec<(&'static str, String)>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    last_action: None,
+    settings: Vec::new(),
+});
+
+/// A crash report, written when claude-dialog panics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    /// When the panic happened
+    pub time: DateTime<Local>,
+
+    /// Name of the thread that panicked
+    pub thread: String,
+
+    /// The panic message
+    pub message: String,
+
+    /// Source location of the panic, as `file:line:column`
+    pub location: Option<String>,
+
+    /// What the session was doing, as recorded with [`record_action`]
+    pub last_action: Option<String>,
+
+    /// The session's options as [`sanitized`] returns them
+    pub settings: Vec<(&'static str, String)>,
+
+    /// Backtrace of the panicking thread
+    pub backtrace: String,
+}
+
+impl CrashReport {
+    /// Describe a panic, with the session state recorded so far
+    fn capture(info: &PanicHookInfo) -> Self {
+        // The hook may run while the state is locked, e.g. by a panic in
+        // another thread; the report is then written without it
+        let (last_action, settings) = match STATE.try_lock() {
+            Ok(state) => (state.last_action.clone(), state.settings.clone()),
+            Err(_) => (None, Vec::new()),
+        };
+        Self {
+            time: Local::now(),
+            thread: thread::current().name().unwrap_or("unnamed").to_string(),
+            message: info.payload_as_str().unwrap_or("(no message)").to_string(),
+            location: info.location().map(ToString::to_string),
+            last_action,
+            settings,
+            backtrace: Backtrace::force_capture().to_string(),
+        }
+    }
+
+    /// The report as written to its file
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    /// claude-dialog crash report
+    ///
+    /// Version: 0.1.0 (linux x86_64)
+    /// Time: 2024-01-01T10:32:00+00:00
+    /// Thread: main
+    /// Panic: index out of bounds at src/dialog.rs:42:9
+    /// Last action: Running /export
+    ///
+    /// Settings:
+    ///   backend: claude
+    ///
+    /// Backtrace:
+    /// ...
+    /// ```
+    pub fn render(&self) -> String {
+        let mut text = String::from("claude-dialog crash report\n\n");
+        let _ = writeln!(
+            text,
+            "Version: {} ({} {})",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        let _ = writeln!(text, "Time: {}", self.time.to_rfc3339());
+        let _ = writeln!(text, "Thread: {}", self.thread);
+        let _ = match &self.location {
+            Some(location) => writeln!(text, "Panic: {} at {}", self.message, location),
+            None => writeln!(text, "Panic: {}", self.message),
+        };
+        let _ = writeln!(text, "Last action: {}", self.last_action.as_deref().unwrap_or("none recorded"));
+
+        text.push_str("\nSettings:\n");
+        for (name, value) in &self.settings {
+            let _ = writeln!(text, "  {}: {}", name, value);
+        }
+        if self.settings.is_empty() {
+            text.push_str("  none recorded\n");
+        }
+
+        let _ = write!(text, "\nBacktrace:\n{}", self.backtrace);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Write the report to `dir` as `crash-<time>-<pid>.txt`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or the file
+    /// cannot be written.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        paths::create_private_dir(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(format!(
+            "crash-{}-{}.txt",
+            self.time.format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        ));
+        paths::write_private(&path, self.render()).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Write a crash report to `dir` whenever claude-dialog panics
+///
+/// The panic message is printed as before, followed by the path of the
+/// report and where to file an issue.
+pub fn install(dir: PathBuf) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        match CrashReport::capture(info).write(&dir) {
+            Ok(path) => UI::print_error(&format!(
+                "claude-dialog crashed. A crash report was written to {}\n\
+                 Please attach it to an issue at {}; it was not sent anywhere.",
+                path.display(),
+                ISSUES_URL
+            )),
+            Err(e) => UI::print_error(&format!("claude-dialog crashed, and the crash report failed: {:#}", e)),
+        }
+    }));
+}
+
+/// Record what the session is doing, for a crash report
+///
+/// Actions must not contain prompt text or other personal data.
+pub fn record_action(action: impl Into<String>) {
+    let mut state = STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    state.last_action = Some(action.into());
+}
+
+/// Record the session's options for a crash report, see [`sanitized`]
+pub fn record_settings(config: &DialogConfig) {
+    let settings = sanitized(config);
+    let mut state = STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    state.settings = settings;
+}
+
+/// The options of a session that help reproduce a crash, without prompts,
+/// paths, session IDs or masked words
+pub fn sanitized(config: &DialogConfig) -> Vec<(&'static str, String)> {
+    let text_length = |text: &Option<String>| match text {
+        Some(text) => format!("{} characters", text.chars().count()),
+        None => "none".to_string(),
+    };
+    let list = |items: &[String]| if items.is_empty() { "none".to_string() } else { items.join(", ") };
+
+    let flags: Vec<String> = [
+        ("stream", config.stream),
+        ("raw", config.raw),
+        ("final-only", config.final_only),
+        ("no-highlight", config.no_highlight),
+        ("quiz", config.quiz),
+        ("dry-run", config.dry_run),
+        ("test-backend", config.test_script.is_some()),
+        ("show-usage", config.show_usage),
+        ("turn-metadata", config.turn_metadata),
+        ("suggest-follow-ups", config.suggest_follow_ups),
+        ("repo-map", config.repo_map),
+        ("spellcheck", config.spellcheck),
+        ("send-unfinished", config.send_unfinished),
+        ("incognito", config.incognito),
+        ("masking", config.masker.is_some()),
+        ("policy", !config.policy.is_empty()),
+        ("mcp-config", !config.mcp_config.is_empty()),
+        ("strict-mcp-config", config.strict_mcp_config),
+        ("claude-bin", config.claude_bin.is_some()),
+        ("claude-arg", !config.claude_args.is_empty()),
+        ("tee", config.tee.is_some()),
+        ("journal", config.journal_dir.is_some()),
+        ("history", config.history_file.is_some()),
+        ("transcript", config.transcript_file.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, on)| *on)
+    .map(|(flag, _)| flag.to_string())
+    .collect();
+
+    vec![
+        ("backend", config.backend.to_string()),
+        ("model", config.model.clone().unwrap_or_else(|| "default".to_string())),
+        ("temperature", config.temperature.map_or("default".to_string(), |t| t.to_string())),
+        ("max tokens", config.max_tokens.map_or("default".to_string(), |tokens| tokens.to_string())),
+        (
+            "session",
+            match config.session {
+                SessionMode::Continue => "continue",
+                SessionMode::Resume(_) => "resume",
+                SessionMode::Fresh => "new",
+            }
+            .to_string(),
+        ),
+        ("personas", list(&config.personas.iter().map(|persona| persona.name.clone()).collect::<Vec<_>>())),
+        ("allowed tools", config.allowed_tools.as_deref().map_or("default".to_string(), list)),
+        ("disallowed tools", list(&config.disallowed_tools)),
+        ("system prompt", text_length(&config.system_prompt)),
+        ("appended prompt", text_length(&config.append_prompt)),
+        (
+            "context budget",
+            config
+                .context_budget
+                .map_or("none".to_string(), |budget| format!("{} tokens, {}", budget, config.context_strategy)),
+        ),
+        ("language", config.language.map_or("none".to_string(), |language| language.to_string())),
+        ("attachments", config.attachments.len().to_string()),
+        ("flags", list(&flags)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitized_settings_leave_out_personal_data() {
+        let config = DialogConfig {
+            system_prompt: Some("You review code for ACME Corp".to_string()),
+            session: SessionMode::Resume("3f2a9c1e".to_string()),
+            journal_dir: Some(PathBuf::from("/home/me/.local/state/claude-dialog")),
+            allowed_tools: Some(vec!["Read".to_string(), "Edit".to_string()]),
+            stream: true,
+            ..Default::default()
+        };
+
+        let settings = sanitized(&config);
+        let text = format!("{:?}", settings);
+        assert!(!text.contains("ACME") && !text.contains("3f2a9c1e") && !text.contains("/home/me"));
+        assert!(settings.contains(&("system prompt", "29 characters".to_string())));
+        assert!(settings.contains(&("session", "resume".to_string())));
+        assert!(settings.contains(&("allowed tools", "Read, Edit".to_string())));
+        assert!(settings.contains(&("flags", "stream, journal".to_string())));
+    }
+
+    #[test]
+    fn test_report_is_written_to_the_crash_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = CrashReport {
+            time: Local::now(),
+            thread: "tokio-runtime-worker".to_string(),
+            message: "boom".to_string(),
+            location: None,
+            last_action: None,
+            settings: Vec::new(),
+            backtrace: "disabled backtrace".to_string(),
+        };
+
+        let path = report.write(&dir.path().join("crashes")).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("crash-") && name.ends_with(".txt"));
+
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(text.contains("Panic: boom\nLast action: none recorded\n\nSettings:\n  none recorded\n"));
+        assert!(text.ends_with("Backtrace:\ndisabled backtrace\n"));
+    }
+}
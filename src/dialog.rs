@@ -14,18 +14,76 @@
 //!     system_prompt: Some("You are a helpful assistant.".to_string()),
 //!     append_prompt: None,
 //!     model: Some("claude-3-opus".to_string()),
+//!     ..Default::default()
 //! };
 //!
-//! let dialog = DialogLoop::new(config);
+//! let mut dialog = DialogLoop::new(config);
 //! dialog.run().await?;
 //! # Ok(())
 //! # }
 //! ```
 
-use anyhow::Result;
-use std::io::{self, BufRead, BufReader, Read, Write};
-use crate::claude_executor::{ClaudeCommand, execute_claude};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::attach::Attachment;
+use crate::batch::{self, BatchReport, BatchResult};
+use crate::checkpoint::{Checkpoint, CheckpointStore, WorkspaceSnapshot};
+use crate::claude_version::{Capabilities, Capability};
+use crate::claude_executor::{Backend, ClaudeExecutor, ClaudeInvocation, ClaudeOptions, ClaudeResponse, DryRunExecutor, OutputFormat, SessionMode, TrimPolicy, tool_list};
+use crate::clipboard::{self, SystemClipboard};
+use crate::commands::{CommandAction, CommandAlias, CommandRegistry};
+use crate::compose;
+use crate::context::{BudgetStrategy, ExpandedPrompt, PromptContext, collect_file_references, estimate_tokens};
+use crate::conversation::{Conversation, MAX_RATING, MEMORY_LIMIT, SearchHit, Speaker, Turn};
+use crate::crash;
+use crate::export::{self, ConversationExport, ExportFormat, ImportMode};
+use crate::html;
+use crate::follow_up;
+use crate::history::History;
+use crate::journal::{self, Journal, UnsavedSession};
+use crate::interrupt::{self, Interrupt};
+use crate::language::Language;
+use crate::line_editor::{Input, LineEditor};
+use crate::metadata::TurnMetadata;
+use crate::paths;
+use crate::palette::{self, Entry};
+use crate::persona::{self, DEFAULT_PERSONA, Persona};
+use crate::policy::Policy;
+use crate::preset::{self, PromptLibrary};
+use crate::prompt::{self, PromptMetadata, SystemPromptConfig};
+use crate::mask::Masker;
+use crate::mcp;
+use crate::output::{PrintFormat, TurnRecord};
+use crate::pipeline::{MarkdownSink, MaskedSink, ResponsePipeline, TerminalSink};
+use crate::quiz;
+use crate::rate_limit::{self, RateLimit};
+use crate::selection::{self, Selection};
+use crate::repo_map::{MAX_FILES, RepoMap};
+use crate::router::BackendRouter;
+use crate::scripted::{Script, ScriptedExecutor};
+use crate::sessions::SessionStore;
+use crate::spelling::{self, Speller};
+use crate::stats::{self, SessionStats};
+use crate::status::{SessionStatus, Usage};
+use crate::transcript::{self, Provenance, Transcript};
+use crate::tee::Tee;
 use crate::ui::UI;
+use crate::unfinished;
+use chrono::{DateTime, Local, Utc};
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
 
 /// Configuration for the dialog loop
 ///
@@ -42,6 +100,7 @@ use crate::ui::UI;
 ///     system_prompt: None,
 ///     append_prompt: None,
 ///     model: None,
+///     ..Default::default()
 /// };
 ///
 /// // Configuration with custom system prompt
@@ -49,6 +108,7 @@ use crate::ui::UI;
 ///     system_prompt: Some("You are an expert programmer.".to_string()),
 ///     append_prompt: None,
 ///     model: Some("claude-3-opus".to_string()),
+///     ..Default::default()
 /// };
 ///
 /// // Configuration with append prompt
@@ -56,9 +116,11 @@ use crate::ui::UI;
 ///     system_prompt: None,
 ///     append_prompt: Some("Always provide code examples.".to_string()),
 ///     model: None,
+///     ..Default::default()
 /// };
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DialogConfig {
     /// Optional system prompt to replace the default
     pub system_prompt: Option<String>,
@@ -66,14 +128,379 @@ pub struct DialogConfig {
     /// Optional prompt to append to the default system prompt
     pub append_prompt: Option<String>,
     
+    /// Where the system prompt or its addition came from, e.g.
+    /// `review.md (--system-prompt)`, as recorded in transcripts; `None`
+    /// for Claude's default
+    pub system_prompt_source: Option<String>,
+    
     /// Optional model specification
     pub model: Option<String>,
+    
+    /// Sampling temperature from `--temperature` or the system prompt's
+    /// front matter; only the ollama and api backends have this setting
+    pub temperature: Option<f32>,
+
+    /// Settings given on the command line, which the front matter of a
+    /// prompt chosen with `/system` or `/preset` does not override
+    pub flag_settings: FlagSettings,
+
+    /// Most tokens of a response (`--max-tokens`); only the api backend
+    /// has this setting
+    pub max_tokens: Option<u32>,
+
+    /// How the ollama and api backends shorten a long conversation
+    /// (`--trim-policy`)
+    pub trim_policy: TrimPolicy,
+    
+    /// Send the api backend's requests without prompt caching breakpoints
+    /// (`--no-prompt-cache`)
+    pub no_prompt_cache: bool,
+    
+    /// Most tokens of the conversation the ollama and api backends send
+    /// with a prompt (`--history-budget`), or `None` for their default
+    pub history_budget: Option<usize>,
+    
+    /// Context window of the ollama model (`--num-ctx`)
+    pub num_ctx: Option<u32>,
+    
+    /// Where prompts are sent
+    pub backend: Backend,
+    
+    /// Conversation the first prompt attaches to; later prompts continue
+    /// the conversation it started or resumed
+    pub session: SessionMode,
+    
+    /// Backends and models that `/persona` can route the following prompts to
+    pub personas: Vec<Persona>,
+    
+    /// Tools Claude may use without asking, or `None` for
+    /// [`ALLOWED_TOOLS`](crate::claude_executor::ALLOWED_TOOLS)
+    pub allowed_tools: Option<Vec<String>>,
+    
+    /// Tools Claude must not use
+    pub disallowed_tools: Vec<String>,
+    
+    /// Files naming MCP servers whose tools Claude may use
+    pub mcp_config: Vec<PathBuf>,
+    
+    /// Use only the MCP servers of `mcp_config`
+    pub strict_mcp_config: bool,
+    
+    /// The Claude CLI binary, or `None` for
+    /// [`CLAUDE_BIN`](crate::claude_executor::CLAUDE_BIN) on `PATH`
+    pub claude_bin: Option<PathBuf>,
+    
+    /// Arguments passed to the Claude CLI as they are, after its other
+    /// arguments
+    pub claude_args: Vec<String>,
+    
+    /// User-defined slash command aliases
+    pub aliases: Vec<CommandAlias>,
+    
+    /// Slash commands that may be used, without the slash, or `None` for
+    /// all of them (see [`CommandRegistry::restrict`])
+    pub allowed_commands: Option<Vec<String>>,
+    
+    /// Slash commands that must not be used, without the slash
+    pub disallowed_commands: Vec<String>,
+    
+    /// Limits of the organization's policy files; see
+    /// [`DialogConfig::apply_policy`]
+    pub policy: Policy,
+    
+    /// Directory for the crash-recovery journal, or `None` to disable journaling
+    pub journal_dir: Option<PathBuf>,
+    
+    /// Recover the last unsaved session from `journal_dir` without asking
+    pub recover: bool,
+    
+    /// Directory the conversation is saved to when the session ends, or
+    /// `None` not to save it
+    pub sessions_dir: Option<PathBuf>,
+    
+    /// Directory of the prompt library used by `/preset`, `None` if unknown
+    pub prompts_dir: Option<PathBuf>,
+    
+    /// Directory Claude runs in and `@file` references, repository maps and
+    /// checkpoints are read from, or `None` for the current one; `/cd`
+    /// changes it
+    pub working_dir: Option<PathBuf>,
+    
+    /// Show only Claude's final answer, with a progress spinner instead of
+    /// intermediate text and tool activity
+    pub final_only: bool,
+    
+    /// Show Claude's text while it is generated instead of when the command
+    /// exits; ignored with `final_only`
+    pub stream: bool,
+    
+    /// Print responses as received instead of rendering their Markdown
+    pub raw: bool,
+    
+    /// Render code blocks of responses without syntax highlighting
+    pub no_highlight: bool,
+    
+    /// Hide each response until Enter is pressed, timing how long that took
+    pub quiz: bool,
+    
+    /// Print the tokens and estimated cost of each turn after its response
+    pub show_usage: bool,
+    
+    /// Print the `claude` command line of each prompt instead of running it
+    pub dry_run: bool,
+    
+    /// Answer prompts from this script instead of a backend, for tests
+    #[serde(skip)]
+    pub test_script: Option<Script>,
+    
+    /// How [`DialogLoop::run_once`] and [`DialogLoop::run_batch`] print
+    /// their turns
+    pub output_format: PrintFormat,
+    
+    /// Masks content of responses shown in the terminal, or `None` to show
+    /// them as they are
+    ///
+    /// Built at startup from word lists and patterns, so it is never
+    /// serialized.
+    #[serde(skip)]
+    pub masker: Option<Masker>,
+    
+    /// Mask responses in transcripts too, not only in the terminal
+    pub mask_transcripts: bool,
+    
+    /// File or named pipe the conversation is mirrored to as it happens,
+    /// or `None` to mirror it nowhere
+    ///
+    /// Opened at startup, so it is never serialized.
+    #[serde(skip)]
+    pub tee: Option<Tee>,
+    
+    /// Token budget for files included with `@file`, or `None` to always
+    /// send them whole
+    pub context_budget: Option<usize>,
+    
+    /// What to do when included files exceed the context budget
+    pub context_strategy: BudgetStrategy,
+    
+    /// Send a map of the repository with the first prompt
+    pub repo_map: bool,
+    
+    /// Files sent whole with the first prompt
+    pub attachments: Vec<PathBuf>,
+    
+    /// Language responses are expected in; a prompt detected in another
+    /// language is sent with a hint to reply in it. `None` sends no hints.
+    pub language: Option<Language>,
+    
+    /// Check prompts for likely typos and ask before sending them
+    pub spellcheck: bool,
+    
+    /// Word lists for `spellcheck`, or empty for the system's
+    pub dictionaries: Vec<PathBuf>,
+    
+    /// Send prompts that look unfinished without asking first
+    pub send_unfinished: bool,
+    
+    /// End every prompt sent with the time, working directory and OS
+    pub turn_metadata: bool,
+    
+    /// Show suggested follow-up questions after each answer, picked by
+    /// typing their number
+    pub suggest_follow_ups: bool,
+    
+    /// Conversation file written by `/export` to continue, or `None` to
+    /// start without one
+    pub import_file: Option<PathBuf>,
+    
+    /// How the imported conversation is made known to the backend
+    pub import_mode: ImportMode,
+    
+    /// File the input history is saved to, or `None` to save no history
+    pub history_file: Option<PathBuf>,
+    
+    /// Input matching one of these patterns is never saved to the history
+    pub history_ignore: Vec<String>,
+    
+    /// Write nothing about the session to disk, regardless of `journal_dir`,
+    /// `history_file` and `transcript_file`
+    pub incognito: bool,
+    
+    /// File the session transcript is written to when the session ends,
+    /// and by `/save` without a path
+    pub transcript_file: Option<PathBuf>,
+    
+    /// How often a rate-limited prompt is retried after waiting, 0 to
+    /// report rate limits as errors right away
+    pub rate_limit_retries: usize,
+    
+    /// How long a command may run before it is stopped and reported as
+    /// failed, or `None` to wait until it finishes
+    pub timeout: Option<Duration>,
+    
+    /// Features supported by the installed Claude CLI
+    ///
+    /// Detected at startup, so it is never serialized.
+    #[serde(skip)]
+    pub capabilities: Capabilities,
+}
+
+/// Which of the settings a system prompt's front matter may set were given
+/// on the command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagSettings {
+    /// `--model`
+    pub model: bool,
+
+    /// `--allowed-tools`
+    pub allowed_tools: bool,
+
+    /// `--disallowed-tools`
+    pub disallowed_tools: bool,
+
+    /// `--temperature`
+    pub temperature: bool,
+}
+
+impl DialogConfig {
+    /// Drop options the installed Claude CLI does not support
+    ///
+    /// Checks the configured options against [`DialogConfig::capabilities`]
+    /// so that an old CLI is reported once at startup rather than failing on
+    /// every turn with an unknown-flag error.
+    ///
+    /// # Returns
+    ///
+    /// A warning for every option that was dropped
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::claude_version::{Capabilities, ClaudeVersion};
+    /// use claude_dialog::dialog::DialogConfig;
+    ///
+    /// let mut config = DialogConfig {
+    ///     model: Some("claude-3-opus".to_string()),
+    ///     capabilities: Capabilities::for_version(Some(ClaudeVersion::new(0, 2, 0))),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let warnings = config.apply_capabilities();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert!(config.model.is_none());
+    /// ```
+    pub fn apply_capabilities(&mut self) -> Vec<String> {
+        let capabilities = self.capabilities;
+        let version = capabilities
+            .version()
+            .map(|version| version.to_string())
+            .unwrap_or_default();
+        let mut warnings = Vec::new();
+        
+        let mut gate = |option: &mut Option<String>, capability: Capability| {
+            if option.is_some() && !capabilities.supports(capability) {
+                *option = None;
+                warnings.push(format!(
+                    "Ignoring {}: requires claude CLI {} or newer (found {})",
+                    capability.flag(),
+                    capability.minimum_version(),
+                    version
+                ));
+            }
+        };
+        
+        gate(&mut self.system_prompt, Capability::SystemPrompt);
+        gate(&mut self.append_prompt, Capability::AppendSystemPrompt);
+        gate(&mut self.model, Capability::Model);
+        
+        if self.final_only && !capabilities.supports(Capability::StreamJsonOutput) {
+            self.final_only = false;
+            warnings.push(format!(
+                "Ignoring --final-only: requires {} from claude CLI {} or newer (found {})",
+                Capability::StreamJsonOutput.flag(),
+                Capability::StreamJsonOutput.minimum_version(),
+                version
+            ));
+        }
+        
+        if self.stream && !self.final_only && !capabilities.supports(Capability::PartialMessages) {
+            self.stream = false;
+            warnings.push(format!(
+                "Ignoring --stream: requires {} from claude CLI {} or newer (found {})",
+                Capability::PartialMessages.flag(),
+                Capability::PartialMessages.minimum_version(),
+                version
+            ));
+        }
+        
+        warnings
+    }
+    
+    /// Bring the options within the limits of [`DialogConfig::policy`]
+    ///
+    /// Tools the policy does not permit are dropped, and its disallowed
+    /// tools and commands added; the context budget is lowered to its
+    /// maximum and transcripts are masked if it says so. The backends, the
+    /// masking of responses and the session's cost are checked where they
+    /// are used.
+    ///
+    /// # Returns
+    ///
+    /// A warning for every tool that was dropped
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::dialog::DialogConfig;
+    /// use claude_dialog::policy::Policy;
+    ///
+    /// let mut config = DialogConfig {
+    ///     allowed_tools: Some(vec!["Read".to_string(), "WebFetch".to_string()]),
+    ///     context_budget: None,
+    ///     policy: Policy::parse("allowed_tools = [\"Read\"]\nmax_context_budget = 20000").unwrap(),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let warnings = config.apply_policy();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(config.allowed_tools.unwrap(), ["Read"]);
+    /// assert_eq!(config.context_budget, Some(20000));
+    /// ```
+    pub fn apply_policy(&mut self) -> Vec<String> {
+        let (allowed, dropped) = self.policy.allowed_tools(self.allowed_tools.take());
+        self.allowed_tools = allowed;
+        self.disallowed_tools = self.policy.disallowed_tools(std::mem::take(&mut self.disallowed_tools));
+        for command in &self.policy.disallowed_commands {
+            if !self.disallowed_commands.contains(command) {
+                self.disallowed_commands.push(command.clone());
+            }
+        }
+        self.context_budget = self.policy.context_budget(self.context_budget);
+        self.mask_transcripts |= self.policy.mask_transcripts;
+        
+        dropped
+            .iter()
+            .map(|tool| format!("Ignoring allowed tool {}: not permitted by the {}", tool, self.policy.label()))
+            .collect()
+    }
+}
+
+/// Context sent with the next prompt, e.g. a repository map
+#[derive(Debug, Clone)]
+struct QueuedContext {
+    /// What the context is, as recorded in transcripts
+    label: String,
+    
+    /// The text appended to the prompt
+    text: String,
 }
 
 /// Main dialog loop for interactive conversations with Claude
 ///
 /// This struct manages the conversation flow, handling user input,
 /// executing Claude commands, and managing the session lifecycle.
+/// Input starting with a slash command (see [`crate::commands`]) is handled
+/// locally instead of being sent to Claude.
 ///
 /// # Examples
 ///
@@ -84,6 +511,7 @@ pub struct DialogConfig {
 ///     system_prompt: None,
 ///     append_prompt: None,
 ///     model: None,
+///     ..Default::default()
 /// };
 ///
 /// let dialog = DialogLoop::new(config);
@@ -91,11 +519,66 @@ pub struct DialogConfig {
 /// ```
 pub struct DialogLoop {
     config: DialogConfig,
+    executor: Arc<dyn ClaudeExecutor>,
+    commands: CommandRegistry,
+    conversation: Conversation,
+    checkpoints: CheckpointStore,
+    session: SessionMode,
+    // Turns the current backend has seen, and the session and seen turns of
+    // the other backends used so far; backends used for the first time
+    // attach with `joining_session`
+    seen: usize,
+    parked: HashMap<Backend, (SessionMode, usize)>,
+    joining_session: SessionMode,
+    persona: Option<String>,
+    default_route: (Backend, Option<String>),
+    journal: Option<Journal>,
+    // Cleared when a turn could not be journaled, which stops spilling
+    journal_intact: bool,
+    history: Option<History>,
+    pending_context: Vec<QueuedContext>,
+    transcript: Transcript,
+    // Shown by /status; the Claude session ID is known once a turn with
+    // json or stream-json output reports it
+    started: Instant,
+    prompts_sent: usize,
+    stats: SessionStats,
+    claude_session: Option<String>,
+    // The list shown last, which the next input may pick from by number
+    selection: Selection,
+    // Suggestions for the last answer and the usage of asking for them,
+    // asked for in the background in a private directory made for the session
+    follow_ups: Option<JoinHandle<(Vec<String>, Option<Usage>)>>,
+    follow_up_dir: Option<TempDir>,
+    last_error: Option<(DateTime<Local>, String)>,
+    interrupt: Interrupt,
+    // Set when Ctrl+C cancelled the last turn, so another Ctrl+C quits
+    turn_cancelled: bool,
+    speller: Option<Speller>,
+    // Word lists being read in the background until the first spell check
+    loading_speller: Option<thread::JoinHandle<Result<Speller>>>,
+    // A prompt sent back for editing by the spell check
+    draft: Option<String>,
+    // Reads the input and the answers to confirmations
+    editor: LineEditor,
+    // Opened by the first /copy or /paste, then kept open so copied text
+    // stays available
+    clipboard: Option<SystemClipboard>,
+    finished: bool,
 }
 
 impl DialogLoop {
     /// Create a new dialog loop with the given configuration
     ///
+    /// Prompts are routed to the configured [`Backend`], or a persona's:
+    /// the `claude` CLI, or an Ollama server found through `OLLAMA_HOST`.
+    /// With a `test_script`, every prompt is answered from the script
+    /// instead.
+    ///
+    /// Aliases from the configuration are registered as slash commands;
+    /// invalid aliases are reported and skipped. Commands that are not
+    /// allowed are turned off, with unknown names reported.
+    ///
     /// # Arguments
     ///
     /// * `config` - Configuration for the dialog session
@@ -113,12 +596,110 @@ impl DialogLoop {
     ///     system_prompt: Some("Be concise.".to_string()),
     ///     append_prompt: None,
     ///     model: None,
+    ///     ..Default::default()
     /// };
     ///
     /// let dialog = DialogLoop::new(config);
     /// ```
     pub fn new(config: DialogConfig) -> Self {
-        Self { config }
+        if config.dry_run {
+            return Self::with_executor(config, Box::new(DryRunExecutor));
+        }
+        if let Some(script) = config.test_script.clone() {
+            return Self::with_executor(config, Box::new(ScriptedExecutor::new(script)));
+        }
+        Self::with_executor(config, Box::new(BackendRouter::standard()))
+    }
+    
+    /// Create a dialog loop that runs Claude commands with `executor`
+    ///
+    /// [`DialogLoop::new`] uses the `claude` CLI; another executor can stand
+    /// in for it in tests or provide a different backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration for the dialog session
+    /// * `executor` - Runs the Claude commands of the session
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::claude_executor::SubprocessExecutor;
+    /// use claude_dialog::dialog::{DialogConfig, DialogLoop};
+    ///
+    /// let dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(SubprocessExecutor));
+    /// ```
+    pub fn with_executor(config: DialogConfig, executor: Box<dyn ClaudeExecutor>) -> Self {
+        let mut commands = CommandRegistry::new();
+        for alias in &config.aliases {
+            if let Err(e) = commands.register_alias(alias.clone()) {
+                UI::print_error(&format!("Ignoring alias: {}", e));
+            }
+        }
+        if let Err(e) = commands.restrict(config.allowed_commands.as_deref(), &config.disallowed_commands) {
+            UI::print_error(&format!("{:#}", e));
+        }
+        
+        Self {
+            session: config.session.clone(),
+            seen: 0,
+            parked: HashMap::new(),
+            joining_session: config.session.clone(),
+            persona: None,
+            default_route: (config.backend, config.model.clone()),
+            config,
+            executor: Arc::from(executor),
+            commands,
+            conversation: Conversation::new(),
+            checkpoints: CheckpointStore::new(),
+            journal: None,
+            journal_intact: true,
+            history: None,
+            pending_context: Vec::new(),
+            transcript: Transcript::new(),
+            started: Instant::now(),
+            prompts_sent: 0,
+            stats: SessionStats::default(),
+            claude_session: None,
+            selection: Selection::default(),
+            follow_ups: None,
+            follow_up_dir: None,
+            last_error: None,
+            interrupt: Interrupt::new(),
+            turn_cancelled: false,
+            speller: None,
+            loading_speller: None,
+            draft: None,
+            clipboard: None,
+            editor: LineEditor::new(),
+            finished: false,
+        }
+    }
+    
+    /// The turns exchanged so far in this session
+    pub fn conversation(&self) -> &Conversation {
+        &self.conversation
+    }
+    
+    /// Every turn of this session, including those forgotten by `/clear`
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+    
+    /// The configuration in effect, including changes made with `/model`
+    /// and `/system`
+    pub fn config(&self) -> &DialogConfig {
+        &self.config
+    }
+    
+    /// Whether `/exit` was entered
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    
+    /// A handle that cancels the running turn, as Ctrl+C does during [`DialogLoop::run`]
+    pub fn interrupt(&self) -> Interrupt {
+        self.interrupt.clone()
     }
     
     /// Check if the given input is an exit command
@@ -142,6 +723,7 @@ impl DialogLoop {
     ///     system_prompt: None,
     ///     append_prompt: None,
     ///     model: None,
+    ///     ..Default::default()
     /// });
     ///
     /// assert!(dialog.is_exit_command("exit"));
@@ -179,6 +761,7 @@ impl DialogLoop {
     ///     system_prompt: None,
     ///     append_prompt: None,
     ///     model: None,
+    ///     ..Default::default()
     /// });
     ///
     /// let mut input = Cursor::new("Hello, Claude!\n");
@@ -215,11 +798,21 @@ impl DialogLoop {
     ///
     /// # Behavior
     ///
+    /// When a journal directory is configured, the loop first offers to
+    /// recover a session that did not exit cleanly, then records every turn
+    /// in a new journal which is removed again on a normal exit.
+    ///
     /// 1. Displays a user prompt
-    /// 2. Reads user input
+    /// 2. Reads user input, with line editing and history recall when
+    ///    standard input is a terminal (see [`crate::line_editor`])
     /// 3. Checks for exit commands ("exit" or "quit")
-    /// 4. Executes Claude with the user's input
-    /// 5. Repeats until exit
+    /// 4. Handles slash commands locally
+    /// 5. Executes Claude with the user's input
+    /// 6. Repeats until exit
+    ///
+    /// Malformed slash commands are reported without ending the loop.
+    /// The loop also ends when standard input is closed, and when Ctrl+C
+    /// was pressed outside a turn (see [`crate::interrupt`]).
     ///
     /// Empty input lines are ignored and the loop continues.
     ///
@@ -234,26 +827,64 @@ impl DialogLoop {
     ///     system_prompt: None,
     ///     append_prompt: None,
     ///     model: None,
+    ///     ..Default::default()
     /// };
     ///
-    /// let dialog = DialogLoop::new(config);
+    /// let mut dialog = DialogLoop::new(config);
     /// dialog.run().await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn run(&self) -> Result<()> {
-        let stdin = io::stdin();
-        let mut stdout = io::stdout();
+    pub async fn run(&mut self) -> Result<()> {
+        self.interrupt.listen();
+        self.open_journal()?;
+        self.open_history();
+        self.load_speller();
+        
+        for entry in self.history.iter().flat_map(History::entries) {
+            self.editor.add_history(entry);
+        }
+        
+        self.load_context().await?;
         
+        let prompt = UI::user_prompt(self.config.incognito);
         loop {
-            // Show prompt
-            UI::print_user_prompt();
-            stdout.flush()?;
+            if self.interrupt.quit_requested() {
+                UI::print_exit_message();
+                break;
+            }
+            self.collect_follow_ups().await;
+            if let Some(draft) = self.draft.take() {
+                self.editor.set_draft(draft);
+            }
             
-            // Read input
-            let mut input = String::new();
-            stdin.read_line(&mut input)?;
-            let input = input.trim();
+            // Read input, treating end of input like an exit command
+            let input = match self.editor.read_input(&prompt)? {
+                // Plain line input is read to the end of the line, even
+                // after Ctrl+C
+                _ if self.interrupt.quit_requested() => continue,
+                Input::Text(text) => text,
+                Input::Interrupted if self.turn_cancelled => {
+                    UI::print_exit_message();
+                    break;
+                }
+                Input::Interrupted => continue,
+                Input::End => {
+                    UI::print_exit_message();
+                    break;
+                }
+                Input::Palette(query) => {
+                    if let Err(e) = self.open_palette(&query) {
+                        self.report_error(format!("{:#}", e));
+                    }
+                    continue;
+                }
+                Input::Compose(text) => match self.compose(&text).await {
+                    Some(text) => text,
+                    None => continue,
+                },
+            };
+            self.turn_cancelled = false;
             
             // Check for empty input
             if input.is_empty() {
@@ -261,87 +892,2199 @@ impl DialogLoop {
             }
             
             // Check for exit command
-            if self.is_exit_command(input) {
+            if self.is_exit_command(&input) {
                 UI::print_exit_message();
                 break;
             }
             
-            // Show Claude prompt
-            UI::print_claude_prompt();
-            
-            // Execute Claude command
-            let command = ClaudeCommand {
-                prompt: input.to_string(),
-                system_prompt: self.config.system_prompt.clone(),
-                append_prompt: self.config.append_prompt.clone(),
-                model: self.config.model.clone(),
-            };
+            self.editor.add_history(&input);
+            self.save_to_history(&input);
+            self.submit(&input).await?;
             
-            execute_claude(command).await?;
-            println!(); // Add newline after Claude response
+            if self.finished {
+                UI::print_exit_message();
+                break;
+            }
         }
         
-        Ok(())
+        if self.stats.reported() > 0 || self.stats.background().is_some() {
+            UI::print_usage(&self.stats.summary_line());
+        }
+        self.save_transcript_on_exit();
+        self.close_session()
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_dialog_loop_creation() {
-        let config = DialogConfig {
-            system_prompt: None,
-            append_prompt: None,
-            model: None,
-        };
-        let dialog = DialogLoop::new(config);
-        assert!(dialog.config.system_prompt.is_none());
+    
+    /// Save the conversation to the session store and remove the journal
+    ///
+    /// If the conversation could not be saved, the journal is its only copy:
+    /// it is kept, for the next start to offer recovering it.
+    fn close_session(&mut self) -> Result<()> {
+        let saved = self.save_session();
+        if let Err(e) = &saved {
+            UI::print_error(&format!("Failed to save the session: {:#}", e));
+        }
+        
+        match self.journal.take() {
+            Some(journal) if saved.is_ok() => journal.discard(),
+            Some(journal) => {
+                UI::print_info(&format!(
+                    "The conversation is kept in {}; the next start offers to recover it",
+                    journal.path().display()
+                ));
+                Ok(())
+            }
+            None => Ok(()),
+        }
     }
-
-    #[test]
-    fn test_dialog_config_with_values() {
-        let config = DialogConfig {
-            system_prompt: Some("System".to_string()),
-            append_prompt: Some("Append".to_string()),
-            model: Some("claude-3-opus".to_string()),
+    
+    /// Send a single prompt, print the response and return the exit code
+    ///
+    /// The non-interactive counterpart of [`DialogLoop::run`], used by
+    /// `--prompt`. `@file` references are included as in the loop, except
+    /// that over-budget files are trimmed rather than asked about, and an
+    /// imported conversation or repository map goes along with the prompt.
+    /// Only the response is printed; no journal or input history is written.
+    ///
+    /// # Returns
+    ///
+    /// The exit code of the backend command, 1 if it was terminated by a
+    /// signal, or [`interrupt::EXIT_CODE`] if the turn was cancelled with Ctrl+C
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt is empty, a referenced file cannot be
+    /// read or the command cannot be run.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use claude_dialog::dialog::{DialogConfig, DialogLoop};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut dialog = DialogLoop::new(DialogConfig::default());
+    /// let code = dialog.run_once("Summarize @CHANGELOG.md").await?;
+    /// std::process::exit(code);
+    /// # }
+    /// ```
+    pub async fn run_once(&mut self, input: &str) -> Result<i32> {
+        let input = input.trim();
+        if input.is_empty() {
+            bail!("The prompt is empty");
+        }
+        
+        self.interrupt.listen();
+        self.load_context().await?;
+        
+        let expanded = self.expand_prompt(input, false)?;
+        if self.interrupt.quit_requested() {
+            return Ok(interrupt::EXIT_CODE);
+        }
+        let started = Instant::now();
+        let Some(response) = self.exchange(input, &expanded).await? else {
+            println!();
+            return Ok(interrupt::EXIT_CODE);
         };
+        if self.config.output_format == PrintFormat::Json {
+            println!("{}", self.turn_record(input, &response, started.elapsed()).to_json_line());
+        } else if !response.text.is_empty() && !response.text.ends_with('\n') {
+            println!();
+        }
+        self.save_transcript_on_exit();
         
-        assert_eq!(config.system_prompt, Some("System".to_string()));
-        assert_eq!(config.append_prompt, Some("Append".to_string()));
-        assert_eq!(config.model, Some("claude-3-opus".to_string()));
+        Ok(response.exit_code.unwrap_or(1))
     }
-
-    #[test]
-    fn test_is_exit_command() {
-        let dialog = DialogLoop::new(DialogConfig {
-            system_prompt: None,
-            append_prompt: None,
-            model: None,
-        });
+    
+    /// Send prompts one after another in the same session and write their
+    /// responses to a report
+    ///
+    /// Used by `--batch`. Each prompt is handled like one of
+    /// [`DialogLoop::run_once`], and the report at `output` is rewritten
+    /// after every prompt. A prompt that cannot be sent, e.g. because of an
+    /// `@file` selector outside the file, or whose command fails is recorded
+    /// as failed and the run goes on; Ctrl+C stops it.
+    ///
+    /// With `repeat` above 1 (`--repeat`), the prompts are run that many
+    /// times, each time in a new conversation, and the report compares the
+    /// responses of the runs (see [`batch::stability`]).
+    ///
+    /// # Returns
+    ///
+    /// 0 if every prompt was answered successfully, 1 if one failed, or
+    /// [`interrupt::EXIT_CODE`] if the run was cancelled with Ctrl+C
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the report cannot be written or a command cannot
+    /// be run at all, e.g. because the CLI is missing.
+    pub async fn run_batch(&mut self, source: &Path, prompts: &[String], output: &Path, repeat: usize) -> Result<i32> {
+        self.interrupt.listen();
         
-        // Test various exit commands
-        assert!(dialog.is_exit_command("exit"));
-        assert!(dialog.is_exit_command("EXIT"));
-        assert!(dialog.is_exit_command("Exit"));
-        assert!(dialog.is_exit_command("quit"));
-        assert!(dialog.is_exit_command("QUIT"));
-        assert!(dialog.is_exit_command("Quit"));
+        let model = self.model_label();
+        let mut report = BatchReport::new(source, model.as_deref());
+        for run in 1..=repeat {
+            // Every run starts a new conversation, so that one cannot
+            // influence the next
+            if repeat > 1 {
+                self.reset_conversation();
+            }
+            self.load_context().await?;
+            
+            let run = (repeat > 1).then_some(run);
+            if let Some(code) = self.run_batch_prompts(prompts, output, run, repeat, &mut report).await? {
+                return Ok(code);
+            }
+            if run.is_some_and(|run| run > 1) {
+                report.stability = batch::stability(&report.results);
+                report.save(output)?;
+            }
+        }
+        self.save_transcript_on_exit();
         
-        // Test non-exit commands
-        assert!(!dialog.is_exit_command("hello"));
-        assert!(!dialog.is_exit_command("exit "));
-        assert!(!dialog.is_exit_command(" quit"));
-        assert!(!dialog.is_exit_command(""));
+        if !report.stability.is_empty() {
+            UI::print_info(&format!("Stability over {} runs:\n{}", repeat, batch::stability_table(&report.stability)));
+        }
+        let failures = report.failures();
+        UI::print_info(&format!(
+            "Ran {} prompt(s), {} failed; results written to {}",
+            report.results.len(),
+            failures,
+            output.display()
+        ));
+        Ok(if failures == 0 { 0 } else { 1 })
     }
-
-    #[test]
-    fn test_read_input() {
-        let dialog = DialogLoop::new(DialogConfig {
+    
+    /// Send the prompts of one run of [`DialogLoop::run_batch`], adding
+    /// their results to the report
+    ///
+    /// # Returns
+    ///
+    /// [`interrupt::EXIT_CODE`] if the run was cancelled with Ctrl+C,
+    /// otherwise `None`
+    async fn run_batch_prompts(
+        &mut self,
+        prompts: &[String],
+        output: &Path,
+        run: Option<usize>,
+        repeat: usize,
+        report: &mut BatchReport,
+    ) -> Result<Option<i32>> {
+        let model = self.model_label();
+        for (index, input) in prompts.iter().enumerate() {
+            if self.interrupt.quit_requested() {
+                return Self::cancel_batch(report, output);
+            }
+            let first_line = input.lines().next().unwrap_or_default();
+            match run {
+                Some(run) => UI::print_info(&format!("[run {}/{}, {}/{}] {}", run, repeat, index + 1, prompts.len(), first_line)),
+                None => UI::print_info(&format!("[{}/{}] {}", index + 1, prompts.len(), first_line)),
+            }
+            
+            let started = Instant::now();
+            let outcome = match self.expand_prompt(input, false) {
+                Ok(expanded) => self.exchange(input, &expanded).await,
+                Err(e) => Err(e),
+            };
+            let mut result = BatchResult {
+                prompt: input.clone(),
+                response: None,
+                exit_code: None,
+                error: None,
+                duration_ms: batch::millis(started.elapsed()),
+                usage: None,
+                run,
+            };
+            match outcome {
+                Ok(Some(response)) => {
+                    if self.config.output_format == PrintFormat::Json {
+                        println!("{}", self.turn_record(input, &response, started.elapsed()).to_json_line());
+                    } else if !response.text.is_empty() && !response.text.ends_with('\n') {
+                        println!();
+                    }
+                    result.exit_code = response.exit_code;
+                    result.usage = response.usage;
+                    result.response = Some(response.text.clone());
+                    result.error = response.error_for_status().err().map(|e| format!("{:#}", e));
+                }
+                Ok(None) => {
+                    println!();
+                    return Self::cancel_batch(report, output);
+                }
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    UI::print_error(&message);
+                    if self.config.output_format == PrintFormat::Json {
+                        let record = TurnRecord {
+                            prompt: input.clone(),
+                            model: model.clone(),
+                            duration_ms: result.duration_ms,
+                            error: Some(message.clone()),
+                            ..Default::default()
+                        };
+                        println!("{}", record.to_json_line());
+                    }
+                    result.error = Some(message);
+                }
+            }
+            report.push(result);
+            report.save(output)?;
+        }
+        Ok(None)
+    }
+    
+    /// Write the results of a batch cancelled with Ctrl+C so far
+    fn cancel_batch(report: &BatchReport, output: &Path) -> Result<Option<i32>> {
+        report.save(output)?;
+        UI::print_info(&format!("Batch cancelled; results so far written to {}", output.display()));
+        Ok(Some(interrupt::EXIT_CODE))
+    }
+    
+    /// The record of a turn printed with `--output-format json`
+    fn turn_record(&self, prompt: &str, response: &ClaudeResponse, duration: Duration) -> TurnRecord {
+        TurnRecord {
+            prompt: prompt.to_string(),
+            response: Some(response.text.clone()),
+            model: self.model_label(),
+            duration_ms: batch::millis(duration),
+            exit_code: response.exit_code,
+            session_id: response.session_id.clone(),
+            usage: response.usage,
+            error: response.clone().error_for_status().err().map(|e| format!("{:#}", e)),
+        }
+    }
+    
+    /// Make the conversation given with `--from` and the repository map of
+    /// `--repo-map` known before the first prompt
+    async fn load_context(&mut self) -> Result<()> {
+        if let Some(path) = self.config.import_file.clone() {
+            let mode = self.config.import_mode;
+            let imported = match ConversationExport::load(&path) {
+                Ok(export) => self.import(&export, mode).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = imported {
+                self.report_error(format!("{:#}", e));
+            }
+        }
+        
+        if self.config.repo_map {
+            self.queue_repo_map(false)?;
+        }
+        for path in self.config.attachments.clone() {
+            self.attach(&path)?;
+        }
+        Ok(())
+    }
+    
+    /// Write the transcript to the `--save-transcript` file, if one was given
+    fn save_transcript_on_exit(&self) {
+        if let Some(path) = self.config.transcript_file.as_ref().filter(|_| !self.config.incognito) {
+            match self.transcript.save(path) {
+                Ok(()) => UI::print_info(&format!("Transcript saved to {}", path.display())),
+                Err(e) => UI::print_error(&format!("{:#}", e)),
+            }
+        }
+    }
+    
+    /// Save the conversation to the session store, if it has any turns
+    ///
+    /// The journal is still open, so turns spilled from memory are saved
+    /// too.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the conversation has turns but could not be saved.
+    fn save_session(&self) -> Result<()> {
+        let Some(dir) = self.config.sessions_dir.as_ref().filter(|_| !self.config.incognito) else {
+            return Ok(());
+        };
+        if self.conversation.is_empty() {
+            return Ok(());
+        }
+        
+        let store = SessionStore::new(dir);
+        let id = store.save(&*self.whole_conversation()?, self.config.model.as_deref())?;
+        UI::print_info(&format!("Session saved as {}; `claude-dialog sessions show {}` shows it", id, id));
+        Ok(())
+    }
+    
+    /// Handle a line of input as if it had been typed at the prompt
+    ///
+    /// Slash commands are run locally and anything else is sent to Claude
+    /// with its `@file` references included. The number of an entry of the
+    /// list shown last, such as a suggested follow-up or a preset listed by
+    /// `/preset`, stands for that entry. Problems with the input, such as a
+    /// usage error or an unreadable file, are reported to the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Claude command fails, which ends [`DialogLoop::run`].
+    pub async fn submit(&mut self, input: &str) -> Result<()> {
+        // A list can only be picked from by the input right after it
+        self.collect_follow_ups().await;
+        let selection = std::mem::take(&mut self.selection);
+        let input = match selection.pick(input) {
+            Some(command) if command.starts_with('/') => {
+                UI::print_info(&format!("Running: {}", command));
+                command
+            }
+            Some(question) => {
+                UI::print_info(&format!("Asking: {}", question));
+                question
+            }
+            None => input,
+        };
+        
+        // Handle slash commands locally
+        match self.commands.parse(input) {
+            Ok(Some(action)) => {
+                // Only the command name, as its arguments may be personal
+                crash::record_action(format!("Running {}", input.split_whitespace().next().unwrap_or(input)));
+                if let Err(e) = self.handle_command(action).await {
+                    self.report_error(e.to_string());
+                }
+                return Ok(());
+            }
+            Ok(None) => {}
+            Err(e) => {
+                self.report_error(e.to_string());
+                return Ok(());
+            }
+        }
+        
+        if !self.confirm_unfinished(input)? {
+            return Ok(());
+        }
+        
+        // Inline @file references, keeping track of what was sent
+        let expanded = match self.expand_prompt(input, true) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                self.report_error(format!("{:#}", e));
+                return Ok(());
+            }
+        };
+        if !self.confirm_spelling(input, &expanded)? {
+            return Ok(());
+        }
+        
+        crash::record_action(format!(
+            "Sending a prompt of {} characters as turn {}",
+            input.chars().count(),
+            self.conversation.len() + 1
+        ));
+        let result = self.send(input, expanded).await;
+        if let Err(e) = &result {
+            self.last_error = Some((Local::now(), format!("{:#}", e)));
+        }
+        result
+    }
+    
+    /// Show an error and remember it for `/status`
+    fn report_error(&mut self, message: String) {
+        UI::print_error(&message);
+        self.last_error = Some((Local::now(), message));
+    }
+    
+    /// Offer to recover an unsaved session, then start journaling this one
+    ///
+    /// Journaling problems are reported but never prevent the session from starting.
+    fn open_journal(&mut self) -> Result<()> {
+        let Some(dir) = self.config.journal_dir.clone().filter(|_| !self.config.incognito) else {
+            return Ok(());
+        };
+        
+        match journal::find_unsaved(&dir) {
+            Ok(Some(unsaved)) if self.config.recover => self.recover(unsaved),
+            Ok(Some(unsaved)) => self.offer_recovery(unsaved)?,
+            Ok(None) if self.config.recover => UI::print_info("There is no unsaved session to recover"),
+            Ok(None) => {}
+            Err(e) => UI::print_error(&format!("Could not check for unsaved sessions: {:#}", e)),
+        }
+        
+        let journal = Journal::create(&dir).and_then(|mut journal| {
+            for turn in self.conversation.turns() {
+                journal.append(turn)?;
+            }
+            Ok(journal)
+        });
+        
+        match journal {
+            Ok(journal) => self.journal = Some(journal),
+            Err(e) => UI::print_error(&format!("Session journal disabled: {:#}", e)),
+        }
+        
+        Ok(())
+    }
+    
+    /// Load the input history, reporting (but tolerating) failures
+    fn open_history(&mut self) {
+        let Some(path) = self.config.history_file.as_ref().filter(|_| !self.config.incognito) else {
+            return;
+        };
+        
+        match History::open(path, &self.config.history_ignore) {
+            Ok(history) => self.history = Some(history),
+            Err(e) => UI::print_error(&format!("Input history disabled: {:#}", e)),
+        }
+    }
+    
+    /// Start loading the word lists for `--spellcheck`
+    ///
+    /// A system word list has a hundred thousand words or more, so the lists
+    /// are read in the background while the first prompt is typed.
+    fn load_speller(&mut self) {
+        if !self.config.spellcheck {
+            return;
+        }
+        
+        if self.config.dictionaries.is_empty() && spelling::system_word_list().is_none() {
+            UI::print_warning("No word list found; only identifiers are spell-checked (add one with --dictionary)");
+        }
+        let dictionaries = self.config.dictionaries.clone();
+        self.loading_speller = Some(thread::spawn(move || Speller::load(&dictionaries)));
+    }
+    
+    /// Wait for the word lists if they are still being read
+    ///
+    /// Problems are reported but never stop the session.
+    fn finish_loading_speller(&mut self) {
+        let Some(loading) = self.loading_speller.take() else {
+            return;
+        };
+        
+        match loading.join() {
+            Ok(Ok(speller)) => self.speller = Some(speller),
+            Ok(Err(e)) => UI::print_error(&format!("Spell check disabled: {:#}", e)),
+            Err(_) => UI::print_error("Spell check disabled: reading the word lists failed"),
+        }
+    }
+    
+    /// Check a prompt for likely typos and ask whether to send it anyway
+    ///
+    /// Words of the included files and of the conversation so far are known
+    /// to be right, so identifiers are checked against them.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the prompt should be sent, `false` if it was sent back to
+    /// the input prompt for editing.
+    fn confirm_spelling(&mut self, input: &str, expanded: &ExpandedPrompt) -> Result<bool> {
+        self.finish_loading_speller();
+        let Some(speller) = &self.speller else {
+            return Ok(true);
+        };
+        
+        let mut context: Vec<String> = expanded
+            .sources
+            .iter()
+            .filter_map(|source| fs::read_to_string(&source.path).ok())
+            .collect();
+        context.extend(self.pending_context.iter().map(|queued| queued.text.clone()));
+        for turn in self.conversation.turns() {
+            context.push(turn.prompt.clone());
+            context.extend(turn.response.clone());
+        }
+        
+        let typos = speller.check(input, &context.join("\n"));
+        if typos.is_empty() {
+            return Ok(true);
+        }
+        UI::print_typos(&typos);
+        let answer = self.editor.read_answer(&UI::typos_question())?.unwrap_or_default();
+        
+        if matches!(answer.to_lowercase().as_str(), "s" | "send") {
+            return Ok(true);
+        }
+        UI::print_info("Not sent; correct the prompt and send it again");
+        self.draft = Some(input.to_string());
+        Ok(false)
+    }
+    
+    /// Ask whether to send a prompt that looks unfinished, see
+    /// [`crate::unfinished`]
+    ///
+    /// # Returns
+    ///
+    /// `true` if the prompt should be sent, `false` if it was sent back to
+    /// the input prompt to finish.
+    fn confirm_unfinished(&mut self, input: &str) -> Result<bool> {
+        if self.config.send_unfinished {
+            return Ok(true);
+        }
+        let Some(reason) = unfinished::check(input) else {
+            return Ok(true);
+        };
+        let answer = self.editor.read_answer(&UI::unfinished_question(&reason))?.unwrap_or_default();
+        
+        if matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+            return Ok(true);
+        }
+        UI::print_info("Not sent; finish the prompt and send it again");
+        self.draft = Some(input.to_string());
+        Ok(false)
+    }
+    
+    /// Show the command palette entries matching `query` and put the one
+    /// picked by number at the prompt
+    ///
+    /// Without a pick, the query is put back to go on typing.
+    fn open_palette(&mut self, query: &str) -> Result<()> {
+        self.draft = Some(query.to_string());
+        let entries = self.palette_entries();
+        let found = palette::search(query, &entries);
+        if found.is_empty() {
+            UI::print_info(&format!("Nothing in the palette matches '{}'", query.trim()));
+            return Ok(());
+        }
+        
+        UI::print_info(&format!(
+            "Palette (type a number to edit it at the prompt):\n{}",
+            selection::numbered(found.iter().map(|entry| entry.line()))
+        ));
+        let answer = self.editor.read_answer(&UI::question("Number:"))?.unwrap_or_default();
+        if let Some(text) = Selection::new(found.iter().map(|entry| entry.text.clone())).pick(&answer) {
+            self.draft = Some(text.to_string());
+        }
+        Ok(())
+    }
+    
+    /// What the command palette lists: the enabled slash commands and
+    /// aliases, the presets of the prompt library and recent prompts,
+    /// latest first
+    fn palette_entries(&self) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = self
+            .commands
+            .commands()
+            .iter()
+            .filter(|spec| self.commands.is_enabled(spec.name))
+            .map(|spec| Entry::command(spec.name, spec.summary, !spec.positionals.is_empty()))
+            .collect();
+        entries.extend(
+            self.commands
+                .aliases()
+                .iter()
+                .map(|alias| Entry::command(&alias.name, &format!("= {}", alias.expansion), false)),
+        );
+        
+        let presets = self.config.prompts_dir.clone().map(PromptLibrary::new).and_then(|library| library.list().ok());
+        entries.extend(presets.iter().flatten().map(|preset| Entry::preset(&preset.name, &preset.summary)));
+        
+        let mut prompts: Vec<&str> = Vec::new();
+        let sent = self.conversation.prompts().into_iter().rev();
+        let typed = self.history.iter().flat_map(|history| history.entries().iter().rev().map(String::as_str));
+        for prompt in sent.chain(typed) {
+            if !prompt.starts_with('/') && !prompts.contains(&prompt) {
+                prompts.push(prompt);
+            }
+        }
+        entries.extend(prompts.into_iter().map(Entry::prompt));
+        entries
+    }
+    
+    /// Save a line of input to the history file, if history is enabled
+    fn save_to_history(&mut self, input: &str) {
+        if let Some(history) = &mut self.history
+            && let Err(e) = history.add(input)
+        {
+            UI::print_warning(&format!("{:#}", e));
+        }
+    }
+    
+    /// Ask whether to recover an unsaved session and load its turns if so
+    fn offer_recovery(&mut self, unsaved: UnsavedSession) -> Result<()> {
+        let question = UI::recovery_question(&unsaved.started, unsaved.conversation.len());
+        let answer = self.editor.read_answer(&question)?.unwrap_or_default();
+        
+        if matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+            self.recover(unsaved);
+        } else if let Err(e) = unsaved.discard() {
+            UI::print_error(&format!("{:#}", e));
+        }
+        
+        Ok(())
+    }
+    
+    /// Load the turns of an unsaved session and resume its Claude session
+    /// with the next prompt, if the journal recorded one
+    ///
+    /// The old journal is removed; the turns go on in this session's.
+    fn recover(&mut self, unsaved: UnsavedSession) {
+        self.conversation = unsaved.conversation.clone();
+        self.seen = self.conversation.len();
+        
+        let mut message = format!("Recovered {} turn(s)", self.conversation.len());
+        if let Some(id) = &unsaved.claude_session {
+            let resume = (SessionMode::Resume(id.clone()), self.seen);
+            if self.config.backend == Backend::Claude {
+                (self.session, self.seen) = resume;
+            } else {
+                self.parked.insert(Backend::Claude, resume);
+            }
+            self.claude_session = Some(id.clone());
+            message.push_str(&format!("; resuming Claude session {}", id));
+        }
+        UI::print_info(&message);
+        
+        if let Err(e) = unsaved.discard() {
+            UI::print_error(&format!("{:#}", e));
+        }
+    }
+    
+    /// Attach a response to the latest turn in the conversation and the journal
+    fn record_response(&mut self, response: &str) {
+        if let Some(journal) = &mut self.journal
+            && let Err(e) = journal.append_response(response)
+        {
+            UI::print_error(&format!("{:#}", e));
+            self.journal_intact = false;
+        }
+        
+        self.conversation.set_response(response);
+        if self.config.mask_transcripts {
+            self.transcript.set_response(self.displayed(response));
+        } else {
+            self.transcript.set_response(response);
+        }
+        self.spill_conversation();
+    }
+    
+    /// Drop the oldest turns from memory once the conversation holds more
+    /// than [`MEMORY_LIMIT`], leaving them to the journal
+    ///
+    /// Turns the current backend has not seen stay in memory for the
+    /// catch-up of the next prompt. Without a journal that has every turn,
+    /// e.g. in incognito sessions, the whole conversation stays in memory.
+    fn spill_conversation(&mut self) {
+        if self.journal.is_some() && self.journal_intact {
+            self.conversation.spill(MEMORY_LIMIT, self.seen);
+        }
+    }
+    
+    /// The whole conversation, with the turns spilled from memory read back
+    /// from the journal
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal cannot be read.
+    fn whole_conversation(&self) -> Result<Cow<'_, Conversation>> {
+        let spilled = self.conversation.spilled();
+        let Some(journal) = self.journal.as_ref().filter(|_| spilled > 0) else {
+            return Ok(Cow::Borrowed(&self.conversation));
+        };
+        
+        let mut whole = journal.conversation()?;
+        if whole.len() < spilled {
+            bail!("The journal {} is missing turns of the conversation", journal.path().display());
+        }
+        whole.truncate(spilled);
+        for turn in self.conversation.turns() {
+            whole.push_turn(turn.clone());
+        }
+        Ok(Cow::Owned(whole))
+    }
+    
+    /// Add a turn to the conversation, the transcript and the journal
+    fn record_turn(&mut self, turn: Turn) {
+        if let Some(journal) = &mut self.journal
+            && let Err(e) = journal.append(&turn)
+        {
+            UI::print_error(&format!("{:#}", e));
+            self.journal_intact = false;
+        }
+        
+        let model = self.model_label();
+        self.transcript.record(turn.clone(), model.as_deref());
+        self.conversation.push_turn(turn);
+    }
+    
+    /// The model of the next prompt as recorded in transcripts and exports:
+    /// Claude models by name, others with their backend
+    fn model_label(&self) -> Option<String> {
+        if self.config.backend == Backend::Claude {
+            self.config.model.clone()
+        } else {
+            Some(persona::route_label(self.config.backend, self.config.model.as_deref()))
+        }
+    }
+    
+    /// Add the turns of an exported conversation and make them known to the backend
+    ///
+    /// With [`ImportMode::Replay`], the prompts of the whole conversation are
+    /// sent again to a new session, as when a checkpoint is restored, but
+    /// without the files of `@file` references, which may not exist on this
+    /// machine. With [`ImportMode::Summary`], a condensed copy of the
+    /// conversation is sent with the next prompt, which starts a new session.
+    ///
+    /// The imported turns are journaled but not added to the transcript,
+    /// which only holds the turns of this session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a prompt cannot be replayed; the conversation is
+    /// then left as it was.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use claude_dialog::dialog::{DialogConfig, DialogLoop};
+    /// use claude_dialog::export::{ConversationExport, ImportMode};
+    /// use std::path::Path;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let export = ConversationExport::load(Path::new("handoff.json"))?;
+    /// let mut dialog = DialogLoop::new(DialogConfig::default());
+    /// dialog.import(&export, ImportMode::Summary).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn import(&mut self, export: &ConversationExport, mode: ImportMode) -> Result<()> {
+        let mut conversation = self.conversation.clone();
+        for turn in &export.turns {
+            conversation.push_turn(turn.clone());
+        }
+        
+        let how = match mode {
+            ImportMode::Replay => {
+                if !conversation.is_empty() {
+                    UI::print_info(&format!("Replaying {} turn(s)...", conversation.len()));
+                }
+                for (index, prompt) in conversation.prompts().into_iter().enumerate() {
+                    let session = if index == 0 { SessionMode::Fresh } else { SessionMode::Continue };
+                    self.replay_prompt(prompt, session).await?;
+                }
+                self.session = if conversation.is_empty() { SessionMode::Fresh } else { SessionMode::Continue };
+                "replayed"
+            }
+            ImportMode::Summary => {
+                if !conversation.is_empty() {
+                    self.pending_context.push(QueuedContext {
+                        label: "summary of the imported conversation".to_string(),
+                        text: ConversationExport::new(&conversation, None).summary(),
+                    });
+                }
+                self.session = SessionMode::Fresh;
+                "summarized for the next prompt"
+            }
+        };
+        
+        if let Some(journal) = &mut self.journal {
+            for turn in &export.turns {
+                if let Err(e) = journal.append(turn) {
+                    UI::print_error(&format!("{:#}", e));
+                    break;
+                }
+            }
+        }
+        self.conversation = conversation;
+        self.seen = self.conversation.len();
+        self.parked.clear();
+        self.joining_session = SessionMode::Fresh;
+        self.claude_session = None;
+        
+        UI::print_info(&format!("Imported {} turn(s), {}", export.turns.len(), how));
+        Ok(())
+    }
+    
+    /// Inline the `@file` references of a prompt within the context budget
+    ///
+    /// Only an `interactive` expansion asks which files to drop; otherwise
+    /// [`BudgetStrategy::Ask`] trims like [`BudgetStrategy::HeadTail`].
+    fn expand_prompt(&mut self, prompt: &str, interactive: bool) -> Result<ExpandedPrompt> {
+        let mut context = collect_file_references(prompt, &self.working_dir()?)?;
+        
+        let tokens = context.tokens();
+        let Some(budget) = self.config.context_budget.filter(|&budget| tokens > budget) else {
+            return Ok(context.render());
+        };
+        
+        if interactive && self.config.context_strategy == BudgetStrategy::Ask {
+            Self::ask_files_to_drop(&mut self.editor, &mut context, budget)?;
+        }
+        if context.tokens() > budget {
+            context.fit(budget, self.config.context_strategy);
+            UI::print_warning(&format!(
+                "Included files are ~{} tokens, over the context budget of {}; trimmed to ~{} tokens",
+                tokens,
+                budget,
+                context.tokens()
+            ));
+        }
+        
+        Ok(context.render())
+    }
+    
+    /// Let the user choose files to leave out of an over-budget prompt
+    fn ask_files_to_drop(editor: &mut LineEditor, context: &mut PromptContext, budget: usize) -> Result<()> {
+        UI::print_context_files(context.files(), budget);
+        let answer = editor.read_answer(&UI::drop_files_question())?.unwrap_or_default();
+        
+        for word in answer.split([' ', ',']).map(str::trim).filter(|word| !word.is_empty()) {
+            match word.parse::<usize>() {
+                Ok(number) if (1..=context.files().len()).contains(&number) => {
+                    context.drop_file(number - 1)
+                }
+                _ => UI::print_warning(&format!("Ignoring '{}': not a file number", word)),
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Send a prompt to Claude and stream the response to the terminal
+    ///
+    /// In quiz mode the response is shown only once Enter is pressed.
+    async fn send(&mut self, input: &str, expanded: ExpandedPrompt) -> Result<()> {
+        self.check_cost()?;
+        self.cancel_follow_ups();
+        let started = Instant::now();
+        let response = if self.config.quiz {
+            quiz::timed(started, self.exchange(input, &expanded)).await?
+        } else {
+            // Show Claude prompt, numbered like the turn about to be recorded
+            UI::print_numbered_claude_prompt(self.conversation.len() + 1);
+            self.exchange(input, &expanded).await?
+        };
+        
+        let Some(response) = response else {
+            println!();
+            self.turn_cancelled = true;
+            UI::print_info("Turn cancelled; press Ctrl+C again or type exit to quit");
+            return Ok(());
+        };
+        if self.config.quiz {
+            self.reveal(&response, started).await?;
+        }
+        println!(); // Add newline after Claude response
+        if self.config.show_usage
+            && let Some(usage) = self.stats.last()
+        {
+            UI::print_usage(&stats::format_turn(usage, &self.stats.total()));
+        }
+        let response = response.error_for_status()?;
+        UI::print_sources(&expanded.sources);
+        self.suggest_follow_ups(input, &response.text);
+        Ok(())
+    }
+    
+    /// Ask for follow-up questions to the last exchange in the background
+    ///
+    /// Only done for Claude sessions, and not with `--dry-run` or a test
+    /// script, which would print or answer the request as if it were a turn,
+    /// or once the session has reached the cost limit of the policy.
+    /// The suggestions are printed when they arrive, above the prompt if the
+    /// user is already typing, and picked up with the usage of the request
+    /// by [`DialogLoop::collect_follow_ups`]. The request runs in a session
+    /// of its own in [`follow_up::working_dir`], leaving the session of the
+    /// conversation as it is. Failures are shown as warnings.
+    fn suggest_follow_ups(&mut self, input: &str, answer: &str) {
+        if !self.config.suggest_follow_ups
+            || self.config.backend != Backend::Claude
+            || self.config.dry_run
+            || self.config.test_script.is_some()
+            || self.check_cost().is_err()
+        {
+            return;
+        }
+        if self.follow_up_dir.is_none() {
+            match follow_up::working_dir() {
+                Ok(dir) => self.follow_up_dir = Some(dir),
+                Err(e) => {
+                    UI::print_warning(&format!("No follow-up suggestions: could not create a directory for them: {}", e));
+                    return;
+                }
+            }
+        }
+        let working_dir = self.follow_up_dir.as_ref().map(|dir| dir.path().to_path_buf());
+        
+        let command = ClaudeInvocation {
+            prompt: follow_up::request(input, answer),
+            options: ClaudeOptions {
+                model: Some(follow_up::MODEL.to_string()),
+                session: SessionMode::Fresh,
+                output: OutputFormat::Json,
+                allowed_tools: Some(Vec::new()),
+                working_dir,
+                claude_bin: self.config.claude_bin.clone(),
+                ..Default::default()
+            },
+        };
+        let executor = Arc::clone(&self.executor);
+        let mut printer = self.editor.printer();
+        self.follow_ups = Some(tokio::spawn(async move {
+            // Whether the text could be printed above the prompt
+            let mut above_prompt = |text: String| printer.as_mut().is_some_and(|printer| printer.print(format!("{}\n", text)));
+            let response = executor.execute(command, ResponsePipeline::new()).await;
+            let usage = response.as_ref().ok().and_then(|response| response.usage);
+            match response.and_then(ClaudeResponse::error_for_status) {
+                Ok(response) => {
+                    let suggestions = follow_up::parse(&response.text);
+                    if !suggestions.is_empty() && !above_prompt(UI::suggestions(&suggestions)) {
+                        UI::print_suggestions(&suggestions);
+                    }
+                    (suggestions, usage)
+                }
+                Err(e) => {
+                    let message = format!("No follow-up suggestions: {:#}", e);
+                    if !above_prompt(UI::warning(&message)) {
+                        UI::print_warning(&message);
+                    }
+                    (Vec::new(), usage)
+                }
+            }
+        }));
+    }
+    
+    /// Make the follow-up suggestions pickable once they have arrived, and
+    /// add the usage of asking for them to the session totals
+    ///
+    /// Without a line editor they cannot be printed above the prompt, so
+    /// they are waited for before the next input is read.
+    async fn collect_follow_ups(&mut self) {
+        let editing = self.editor.is_editing();
+        let Some(task) = self.follow_ups.take_if(|task| task.is_finished() || !editing) else {
+            return;
+        };
+        let Ok((suggestions, usage)) = task.await else {
+            return;
+        };
+        self.record_background_usage(usage);
+        if !suggestions.is_empty() {
+            self.selection = Selection::new(suggestions);
+        }
+    }
+    
+    /// Stop asking for suggestions to an answer that is no longer the last
+    ///
+    /// Suggestions that have already arrived are not needed any more, but
+    /// the usage of asking for them is still counted. A request still
+    /// running is stopped, which ends its `claude` process.
+    fn cancel_follow_ups(&mut self) {
+        let Some(mut task) = self.follow_ups.take() else {
+            return;
+        };
+        task.abort();
+        // A finished task is ready without waiting
+        let mut context = Context::from_waker(Waker::noop());
+        if let Poll::Ready(Ok((_, usage))) = Pin::new(&mut task).poll(&mut context) {
+            self.record_background_usage(usage);
+        }
+    }
+    
+    /// Show a quiz answer once Enter is pressed
+    ///
+    /// What a failed command printed is shown right away; it is no answer
+    /// to think about.
+    async fn reveal(&self, response: &ClaudeResponse, started: Instant) -> Result<()> {
+        if response.is_success() {
+            UI::print_info("Answer ready; press Enter to reveal it");
+            let elapsed = quiz::wait_for_reveal(started).await?;
+            UI::print_info(&format!("Revealed after {}", quiz::format_elapsed(elapsed)));
+        }
+        
+        UI::print_numbered_claude_prompt(self.conversation.len());
+        let pipeline = self.display_pipeline();
+        pipeline.send(&response.text);
+        pipeline.finish().await
+    }
+    
+    /// Send an expanded input as the next turn, streaming the response to
+    /// the terminal, and record the turn and its response
+    ///
+    /// # Returns
+    ///
+    /// The response, also of a failed command, or `None` if the turn was
+    /// cancelled with Ctrl+C
+    async fn exchange(&mut self, input: &str, expanded: &ExpandedPrompt) -> Result<Option<ClaudeResponse>> {
+        self.check_cost()?;
+        
+        // Build the prompt first: the turn being sent is not a missed turn
+        let (prompt, provenance) = self.outgoing_prompt(input, expanded);
+        
+        // Record the turn before sending so a crash cannot lose it
+        self.record_turn(Turn::new(input).with_sources(expanded.sources.clone()).with_time(Utc::now()));
+        self.transcript.set_provenance(provenance);
+        if let Some(tee) = &self.config.tee {
+            tee.prompt(input);
+        }
+        
+        self.pending_context.clear();
+        self.prompts_sent += 1;
+        let response = self.interrupt.guard(self.execute_with_retries(&prompt)).await;
+        self.seen = self.conversation.len();
+        let Some(response) = response.transpose()? else {
+            return Ok(None);
+        };
+        self.record_usage(&response);
+        
+        // Keep whatever was received, even from a failed command
+        self.record_response(&response.text);
+        if response.truncated {
+            UI::print_warning("The response was cut off at the most tokens allowed; raise --max-tokens for longer ones");
+        }
+        if response.is_success() {
+            self.session = SessionMode::Continue;
+        }
+        Ok(Some(response))
+    }
+    
+    /// Check that the session is within the cost limit of the policy
+    fn check_cost(&self) -> Result<()> {
+        self.config.policy.check_cost(self.stats.total().cost_usd.unwrap_or_default())
+    }
+    
+    /// Add the usage of a response, and of the requests the backend made
+    /// for it, to the session totals
+    fn record_usage(&mut self, response: &ClaudeResponse) {
+        self.stats.record(response.usage);
+        self.record_background_usage(response.background_usage);
+        if self.config.backend == Backend::Claude
+            && response.session_id.is_some()
+            && response.session_id != self.claude_session
+        {
+            self.claude_session = response.session_id.clone();
+            if let (Some(journal), Some(id)) = (&mut self.journal, &self.claude_session)
+                && let Err(e) = journal.append_session(id)
+            {
+                UI::print_error(&format!("{:#}", e));
+            }
+        }
+    }
+    
+    /// Add the usage of a request that is not a turn of its own, such as a
+    /// replay, a history summary or follow-up suggestions, to the session
+    /// totals
+    ///
+    /// The totals include it when the cost limit of the policy is checked.
+    fn record_background_usage(&mut self, usage: Option<Usage>) {
+        self.stats.record_background(usage);
+    }
+    
+    /// Replay `prompt` to rebuild the context of a session, within the cost
+    /// limit of the policy, and add its usage to the session totals
+    ///
+    /// Returns the ID of the Claude session the prompt was replayed into,
+    /// if it was reported.
+    async fn replay_prompt(&mut self, prompt: &str, session: SessionMode) -> Result<Option<String>> {
+        self.check_cost()?;
+        let response = self.executor.replay(self.build_command(prompt, session)).await?;
+        self.record_background_usage(response.usage);
+        Ok(response.session_id)
+    }
+    
+    /// The current state of the session, as shown by `/status`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::dialog::{DialogConfig, DialogLoop};
+    ///
+    /// let dialog = DialogLoop::new(DialogConfig::default());
+    /// let status = dialog.status();
+    /// assert_eq!(status.backend, "claude");
+    /// assert_eq!(status.session, "continues the most recent conversation");
+    /// assert_eq!(status.turns, 0);
+    /// ```
+    pub fn status(&self) -> SessionStatus {
+        let session = match (self.config.backend, &self.claude_session, &self.session) {
+            (Backend::Claude, Some(id), SessionMode::Continue) => id.clone(),
+            (Backend::Claude, _, SessionMode::Resume(id)) => format!("{} (resuming)", id),
+            (Backend::Claude, _, SessionMode::Continue) => "continues the most recent conversation".to_string(),
+            (Backend::Claude, _, SessionMode::Fresh) => "new conversation with the next prompt".to_string(),
+            #[cfg(any(feature = "ollama", feature = "api"))]
+            (backend, _, _) => format!("none; {} has no sessions and gets the history with each prompt", backend),
+        };
+        
+        SessionStatus {
+            backend: self.config.backend.to_string(),
+            model: self.config.model.clone(),
+            persona: self.persona.clone(),
+            session,
+            turns: self.conversation.len(),
+            prompts_sent: self.prompts_sent,
+            ratings: self.transcript.entries().iter().filter_map(|entry| entry.turn.rating).collect(),
+            usage: self.stats.total(),
+            prompts_reported: self.stats.reported(),
+            elapsed: self.started.elapsed(),
+            queued: self.pending_context.len(),
+            last_error: self.last_error.clone(),
+        }
+    }
+    
+    /// The pipeline a response is streamed to: the terminal, or nothing in
+    /// quiz mode, where responses are shown when they are revealed
+    fn response_pipeline(&self) -> ResponsePipeline {
+        if self.config.quiz || self.config.output_format == PrintFormat::Json {
+            return ResponsePipeline::new();
+        }
+        self.display_pipeline()
+    }
+    
+    /// The pipeline showing a response in the terminal, rendering its
+    /// Markdown unless `raw` is set or the output is not colored, and
+    /// mirroring it to the tee
+    fn display_pipeline(&self) -> ResponsePipeline {
+        let mut pipeline = self.screen_pipeline();
+        match (self.config.masker.clone(), &self.config.tee) {
+            (Some(masker), Some(tee)) => pipeline.add_sink(MaskedSink::new(masker, tee.sink())),
+            (None, Some(tee)) => pipeline.add_sink(tee.sink()),
+            (_, None) => {}
+        }
+        pipeline
+    }
+    
+    /// The pipeline showing a response in the terminal only, as
+    /// [`DialogLoop::display_pipeline`] does
+    fn screen_pipeline(&self) -> ResponsePipeline {
+        let colored = io::stdout().is_terminal() && colored::control::SHOULD_COLORIZE.should_colorize();
+        let render = colored && !self.config.raw;
+        
+        let mut pipeline = ResponsePipeline::new();
+        match (self.config.masker.clone(), render) {
+            (Some(masker), true) => pipeline.add_sink(MaskedSink::new(masker, MarkdownSink::new(!self.config.no_highlight))),
+            (Some(masker), false) => pipeline.add_sink(MaskedSink::new(masker, TerminalSink)),
+            (None, true) => pipeline.add_sink(MarkdownSink::new(!self.config.no_highlight)),
+            (None, false) => pipeline.add_sink(TerminalSink),
+        }
+        pipeline
+    }
+    
+    /// A response as shown in the terminal, masked if masking is enabled
+    fn displayed<'a>(&self, response: &'a str) -> Cow<'a, str> {
+        match &self.config.masker {
+            Some(masker) => masker.mask(response),
+            None => Cow::Borrowed(response),
+        }
+    }
+    
+    /// Run a prompt, waiting and retrying while Claude reports a rate limit
+    ///
+    /// Waits as long as the error output asks, or backs off exponentially,
+    /// and gives up after `rate_limit_retries` retries or if the wait would
+    /// exceed [`rate_limit::MAX_WAIT`].
+    async fn execute_with_retries(&self, prompt: &str) -> Result<ClaudeResponse> {
+        let mut attempt = 0;
+        loop {
+            let command = self.build_command(prompt, self.session.clone());
+            let response = self.execute(command, self.response_pipeline()).await?;
+            if response.is_success() || attempt == self.config.rate_limit_retries {
+                return Ok(response);
+            }
+            
+            let output = format!("{}\n{}", response.stderr, response.text);
+            let Some(limit) = RateLimit::detect(&output, Utc::now()) else {
+                return Ok(response);
+            };
+            let delay = limit.delay(attempt);
+            if delay > rate_limit::MAX_WAIT {
+                let reset = Local::now() + delay;
+                UI::print_warning(&format!(
+                    "Rate limited by Claude until {}; not retrying automatically",
+                    reset.format("%H:%M")
+                ));
+                return Ok(response);
+            }
+            
+            attempt += 1;
+            UI::print_warning(&format!(
+                "Rate limited by Claude; retrying in {}s (retry {} of {})",
+                delay.as_secs_f64().ceil(),
+                attempt,
+                self.config.rate_limit_retries
+            ));
+            rate_limit::countdown(delay).await;
+        }
+    }
+    
+    /// Run a command, stopping it once it has run longer than the timeout
+    ///
+    /// Dropping the command's future kills the `claude` process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be run or did not finish in
+    /// time.
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+        let execution = self.executor.execute(command, pipeline);
+        let Some(timeout) = self.config.timeout else {
+            return execution.await;
+        };
+        match tokio::time::timeout(timeout, execution).await {
+            Ok(response) => response,
+            Err(_) => {
+                println!();
+                bail!("Claude did not answer within {}s; the command was stopped (--timeout)", timeout.as_secs())
+            }
+        }
+    }
+    
+    /// The prompt text sent for an expanded input, including queued context,
+    /// the turns the current backend has not seen, a hint to reply in the
+    /// language of the input if it is not the configured one and the turn
+    /// metadata footer if enabled, with what it is sent with for the
+    /// transcript
+    fn outgoing_prompt(&self, input: &str, expanded: &ExpandedPrompt) -> (String, Provenance) {
+        let mut provenance = Provenance {
+            system_prompt: self.config.system_prompt_source.clone(),
+            temperature: self.config.temperature.filter(|_| self.config.backend != Backend::Claude),
+            layers: Vec::new(),
+        };
+        
+        // Unseen turns are never spilled
+        let unseen = self
+            .conversation
+            .turns()
+            .get(self.seen.saturating_sub(self.conversation.spilled())..)
+            .unwrap_or_default();
+        let mut prompt = persona::catch_up(unseen, &expanded.text);
+        if !unseen.is_empty() {
+            provenance.layers.push(format!("{} turn(s) answered by another model", unseen.len()));
+        }
+        for queued in &self.pending_context {
+            prompt.push_str("\n\n");
+            prompt.push_str(&queued.text);
+            provenance.layers.push(queued.label.clone());
+        }
+        if let Some(configured) = self.config.language
+            && let Some(language) = Language::detect(input).filter(|&language| language != configured)
+        {
+            prompt.push_str("\n\n");
+            prompt.push_str(&language.hint());
+            provenance.layers.push(format!("language hint ({})", language));
+        }
+        if self.config.turn_metadata {
+            prompt.push_str("\n\n");
+            let mut metadata = TurnMetadata::current();
+            if let Some(dir) = &self.config.working_dir {
+                metadata.cwd = Some(dir.clone());
+            }
+            prompt.push_str(&metadata.footer());
+            provenance.layers.push("turn metadata".to_string());
+        }
+        (prompt, provenance)
+    }
+    
+    /// Show what would be sent for `input` and send it if confirmed
+    async fn preview(&mut self, input: &str) -> Result<()> {
+        let expanded = self.expand_prompt(input, true)?;
+        let (prompt, _) = self.outgoing_prompt(input, &expanded);
+        UI::print_preview(&prompt);
+        let answer = self.editor.read_answer(&UI::send_question())?.unwrap_or_default();
+        
+        if matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+            self.send(input, expanded).await
+        } else {
+            UI::print_info("Not sent");
+            Ok(())
+        }
+    }
+    
+    /// Build the Claude command for a prompt using the dialog configuration
+    ///
+    /// The system prompts go with every command, also when it continues a
+    /// session: the Claude CLI keeps the turns of a session but not the
+    /// system prompt it was started with, so leaving them out would fall
+    /// back to the default one.
+    fn build_command(&self, prompt: &str, session: SessionMode) -> ClaudeInvocation {
+        ClaudeInvocation {
+            prompt: prompt.to_string(),
+            options: ClaudeOptions {
+                system_prompt: self.config.system_prompt.clone(),
+                append_prompt: self.config.append_prompt.clone(),
+                model: self.config.model.clone(),
+                temperature: self.config.temperature,
+                max_tokens: self.config.max_tokens,
+                trim_policy: self.config.trim_policy,
+                no_prompt_cache: self.config.no_prompt_cache,
+                history_budget: self.config.history_budget,
+                num_ctx: self.config.num_ctx,
+                backend: self.config.backend,
+                allowed_tools: self.config.allowed_tools.clone(),
+                disallowed_tools: self.config.disallowed_tools.clone(),
+                mcp_config: self.config.mcp_config.clone(),
+                strict_mcp_config: self.config.strict_mcp_config,
+                extra_args: self.config.claude_args.clone(),
+                claude_bin: self.config.claude_bin.clone(),
+                session,
+                output: match (self.config.final_only, self.config.stream) {
+                    (true, _) => OutputFormat::StreamJson,
+                    (false, true) => OutputFormat::StreamJsonPartial,
+                    (false, false) => OutputFormat::Json,
+                },
+                working_dir: self.config.working_dir.clone(),
+                ..Default::default()
+            },
+        }
+    }
+    
+    /// Carry out an action requested through a slash command
+    async fn handle_command(&mut self, action: CommandAction) -> Result<()> {
+        match action {
+            CommandAction::Help(text) => {
+                UI::print_info(&text);
+                Ok(())
+            }
+            CommandAction::ShowHelp { command } => {
+                UI::print_info(&self.commands.help(command.as_deref())?);
+                Ok(())
+            }
+            CommandAction::Model { name } => self.switch_model(name),
+            CommandAction::Persona { name } => self.switch_persona(name),
+            CommandAction::Preset { name } => self.switch_preset(name),
+            CommandAction::System { file, reset } => self.switch_system_prompt(file, reset),
+            CommandAction::Save { path } => self.save_transcript(path),
+            CommandAction::Note { text } => self.add_note(text),
+            CommandAction::Rate { rating } => self.rate(rating),
+            CommandAction::Export { format: ExportFormat::Json, path } => self.export_conversation(path),
+            CommandAction::Export { format: ExportFormat::Html, path } => self.export_html(path),
+            CommandAction::Status => {
+                UI::print_info(self.status().to_string().trim_end());
+                Ok(())
+            }
+            CommandAction::Stats => {
+                UI::print_info(self.stats.to_string().trim_end());
+                Ok(())
+            }
+            CommandAction::Mcp => self.list_mcp_servers(),
+            CommandAction::Clear => {
+                self.clear();
+                Ok(())
+            }
+            CommandAction::Cd { dir } => self.change_dir(&dir),
+            CommandAction::Exit => {
+                self.finished = true;
+                Ok(())
+            }
+            CommandAction::Checkpoint { name, include_files } => {
+                self.save_checkpoint(name, include_files)
+            }
+            CommandAction::Restore { name } => self.restore_checkpoint(&name).await,
+            CommandAction::Find { query, full } => self.find(&query, full),
+            CommandAction::Search { query, context } => self.search(&query, context),
+            CommandAction::Turn { number } => self.show_turn(number),
+            CommandAction::Show { number } => self.show_response(number).await,
+            CommandAction::Last => self.show_last_response().await,
+            CommandAction::RepoMap { show } => self.queue_repo_map(show),
+            CommandAction::Edit { text } => self.edit(text.as_deref().unwrap_or_default()).await,
+            CommandAction::Attach { path } => self.attach(&path),
+            CommandAction::Copy => self.copy_response(),
+            CommandAction::Paste { prompt } => self.paste(prompt).await,
+            CommandAction::Preview { text } => self.preview(&text).await,
+        }
+    }
+    
+    /// Show the model, or use `name` for the following prompts
+    fn switch_model(&mut self, name: Option<String>) -> Result<()> {
+        let Some(name) = name else {
+            let model = self.config.model.as_deref().unwrap_or("default");
+            UI::print_info(&format!("Model: {}", model));
+            return Ok(());
+        };
+        
+        if name == "default" {
+            self.config.model = None;
+            UI::print_info("Using the Claude CLI's default model");
+            return Ok(());
+        }
+        if !self.config.capabilities.supports(Capability::Model) {
+            bail!(
+                "Switching models requires {} from claude CLI {} or newer",
+                Capability::Model.flag(),
+                Capability::Model.minimum_version()
+            );
+        }
+        
+        UI::print_info(&format!("Using model {} for the following prompts", name));
+        self.config.model = Some(name);
+        Ok(())
+    }
+    
+    /// List the personas, or route the following prompts to the one named `name`
+    fn switch_persona(&mut self, name: Option<String>) -> Result<()> {
+        let Some(name) = name else {
+            let active = self.persona.as_deref().unwrap_or(DEFAULT_PERSONA);
+            let (backend, model) = &self.default_route;
+            let mut routes = vec![(DEFAULT_PERSONA, persona::route_label(*backend, model.as_deref()))];
+            routes.extend(self.config.personas.iter().map(|persona| (persona.name.as_str(), persona.route())));
+            
+            let lines = routes.iter().map(|(name, route)| {
+                let marker = if *name == active { '*' } else { ' ' };
+                format!("{} {:<10} {}", marker, name, route)
+            });
+            UI::print_info(&format!("Personas (type a number to switch):\n{}", selection::numbered(lines)));
+            self.selection = Selection::new(routes.iter().map(|(name, _)| format!("/persona {}", name)));
+            return Ok(());
+        };
+        
+        let (backend, model) = if name == DEFAULT_PERSONA {
+            self.default_route.clone()
+        } else {
+            let persona = self
+                .config
+                .personas
+                .iter()
+                .find(|persona| persona.name == name)
+                .ok_or_else(|| anyhow!("No persona named '{}'; /persona lists them", name))?;
+            (persona.backend, persona.model.clone())
+        };
+        self.config.policy.check_backend(backend)?;
+        
+        if backend != self.config.backend {
+            let (session, seen) = self
+                .parked
+                .remove(&backend)
+                .unwrap_or_else(|| (self.joining_session.clone(), 0));
+            let previous = (std::mem::replace(&mut self.session, session), std::mem::replace(&mut self.seen, seen));
+            self.parked.insert(self.config.backend, previous);
+            self.config.backend = backend;
+        }
+        self.config.model = model;
+        
+        UI::print_info(&format!(
+            "Sending the following prompts to {} ({})",
+            name,
+            persona::route_label(backend, self.config.model.as_deref())
+        ));
+        self.persona = (name != DEFAULT_PERSONA).then_some(name);
+        Ok(())
+    }
+    
+    /// List the presets of the prompt library, or use the one named `name`
+    /// as the system prompt
+    fn switch_preset(&mut self, name: Option<String>) -> Result<()> {
+        let dir = self
+            .config
+            .prompts_dir
+            .clone()
+            .ok_or_else(|| anyhow!("The prompt library directory is unknown"))?;
+        let library = PromptLibrary::new(dir);
+        
+        let Some(name) = name else {
+            let presets = library.list()?;
+            if presets.is_empty() {
+                UI::print_info(&format!(
+                    "There are no presets yet; save system prompts as Markdown files in {}",
+                    library.dir().display()
+                ));
+            } else {
+                let table = preset::table(&presets);
+                UI::print_info(&format!("Presets (type a number to use one):\n{}", selection::numbered(table.lines())));
+                self.selection = Selection::new(presets.iter().map(|preset| format!("/preset {}", preset.name)));
+            }
+            return Ok(());
+        };
+        
+        let preset = library.find(&name)?;
+        self.switch_system_prompt(Some(preset.path), false)?;
+        self.config.system_prompt_source = Some(format!("{} (/preset)", preset.name));
+        Ok(())
+    }
+    
+    /// Show the system prompt, or replace it with the contents of `file`
+    fn switch_system_prompt(&mut self, file: Option<PathBuf>, reset: bool) -> Result<()> {
+        if reset {
+            self.config.system_prompt = None;
+            self.config.append_prompt = None;
+            self.config.system_prompt_source = None;
+            UI::print_info("Using Claude's default system prompt");
+            return Ok(());
+        }
+        
+        let Some(file) = file else {
+            match (&self.config.system_prompt, &self.config.append_prompt) {
+                (Some(prompt), _) => UI::print_info(&format!("Custom system prompt (~{} tokens):\n{}", estimate_tokens(prompt), prompt)),
+                (None, Some(prompt)) => UI::print_info(&format!("Default system prompt with these additions (~{} tokens):\n{}", estimate_tokens(prompt), prompt)),
+                (None, None) => UI::print_info("Using Claude's default system prompt"),
+            }
+            return Ok(());
+        };
+        
+        if !self.config.capabilities.supports(Capability::SystemPrompt) {
+            bail!(
+                "Replacing the system prompt requires {} from claude CLI {} or newer",
+                Capability::SystemPrompt.flag(),
+                Capability::SystemPrompt.minimum_version()
+            );
+        }
+        let path = paths::expand(file.as_os_str())?;
+        let files = prompt::prompt_files(std::slice::from_ref(&path), false)?;
+        let prompt = prompt::load_system_prompt(SystemPromptConfig {
+            system_prompt_files: files.clone(),
+            append_prompt_file: None,
+            recursive: false,
+        })?;
+        
+        let source = if path.is_dir() {
+            let names = files
+                .iter()
+                .filter_map(|file| file.file_name())
+                .map(|name| name.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{} ({})", path.display(), names)
+        } else {
+            path.display().to_string()
+        };
+        UI::print_info(&format!(
+            "Using the system prompt from {} (~{} tokens) for the following prompts",
+            source,
+            estimate_tokens(&prompt.body)
+        ));
+        self.config.system_prompt = Some(prompt.body);
+        self.config.append_prompt = None;
+        self.config.system_prompt_source = Some(format!("{} (/system)", source));
+        self.apply_prompt_metadata(prompt.metadata)
+    }
+    
+    /// Use the settings from the front matter of a new system prompt
+    ///
+    /// Settings given on the command line win, as they do at startup; the
+    /// front matter's are reported and ignored.
+    fn apply_prompt_metadata(&mut self, mut metadata: PromptMetadata) -> Result<()> {
+        let flags = self.config.flag_settings;
+        let mut ignored = Vec::new();
+        if flags.model && metadata.model.take().is_some() {
+            ignored.push("model (--model)");
+        }
+        if flags.allowed_tools && metadata.allowed_tools.take().is_some() {
+            ignored.push("allowed tools (--allowed-tools)");
+        }
+        if flags.disallowed_tools && metadata.disallowed_tools.take().is_some() {
+            ignored.push("disallowed tools (--disallowed-tools)");
+        }
+        if flags.temperature && metadata.temperature.take().is_some() {
+            ignored.push("temperature (--temperature)");
+        }
+        if !ignored.is_empty() {
+            UI::print_warning(&format!("Keeping the {} set on the command line; the prompt's front matter does not override it", ignored.join(", ")));
+        }
+
+        if let Some(model) = metadata.model {
+            self.switch_model(Some(model))?;
+        }
+        if let Some(tools) = metadata.allowed_tools {
+            let (tools, dropped) = self.config.policy.allowed_tools(Some(tool_list(tools)?));
+            let tools = tools.unwrap_or_default();
+            for tool in dropped {
+                UI::print_warning(&format!("Ignoring allowed tool {}: not permitted by the {}", tool, self.config.policy.label()));
+            }
+            UI::print_info(&format!("Allowed tools: {}", if tools.is_empty() { "none".to_string() } else { tools.join(", ") }));
+            self.config.allowed_tools = Some(tools);
+        }
+        if let Some(tools) = metadata.disallowed_tools {
+            let tools = self.config.policy.disallowed_tools(tool_list(tools)?);
+            UI::print_info(&format!("Disallowed tools: {}", tools.join(", ")));
+            self.config.disallowed_tools = tools;
+        }
+        if let Some(temperature) = metadata.temperature {
+            UI::print_info(&format!("Temperature: {} (ollama and api turns only)", temperature));
+            self.config.temperature = Some(temperature);
+        }
+        Ok(())
+    }
+    
+    /// Write the transcript to `path`, the configured file or a new file
+    fn save_transcript(&mut self, path: Option<PathBuf>) -> Result<()> {
+        if self.config.incognito {
+            bail!("Transcripts are not saved in incognito sessions");
+        }
+        
+        let path = match path {
+            Some(path) => paths::expand(path.as_os_str())?,
+            None => self
+                .config
+                .transcript_file
+                .clone()
+                .unwrap_or_else(|| transcript::default_file_name(self.transcript.started())),
+        };
+        self.transcript.save(&path)?;
+        UI::print_info(&format!("Saved {} turn(s) to {}", self.transcript.len(), path.display()));
+        Ok(())
+    }
+    
+    /// Attach a note to the last turn, in the conversation, the transcript
+    /// and the journal
+    fn add_note(&mut self, text: String) -> Result<()> {
+        if !self.conversation.add_note(text.clone()) {
+            bail!("There is no turn to attach a note to yet");
+        }
+        if let Some(journal) = &mut self.journal
+            && let Err(e) = journal.append_note(&text)
+        {
+            UI::print_error(&format!("{:#}", e));
+        }
+        self.transcript.add_note(text);
+        UI::print_info(&format!("Noted on turn {}; notes are never sent to Claude", self.conversation.len()));
+        Ok(())
+    }
+    
+    /// Rate the response to the most recent turn
+    fn rate(&mut self, rating: u8) -> Result<()> {
+        if !self.conversation.rate(rating) {
+            bail!("There is no response to rate yet");
+        }
+        if let Some(journal) = &mut self.journal
+            && let Err(e) = journal.append_rating(rating)
+        {
+            UI::print_error(&format!("{:#}", e));
+        }
+        self.transcript.rate(rating);
+        UI::print_info(&format!("Rated turn {} {}/{}", self.conversation.len(), rating, MAX_RATING));
+        Ok(())
+    }
+    
+    /// Write the conversation to `path` or a new file for `--from` to import
+    fn export_conversation(&mut self, path: Option<PathBuf>) -> Result<()> {
+        if self.config.incognito {
+            bail!("Conversations are not exported in incognito sessions");
+        }
+        if self.conversation.is_empty() {
+            bail!("There is no conversation to export yet");
+        }
+        
+        let path = match path {
+            Some(path) => paths::expand(path.as_os_str())?,
+            None => export::default_file_name(Local::now()),
+        };
+        let model = self.model_label();
+        let conversation = self.whole_conversation()?;
+        ConversationExport::new(&conversation, model.as_deref()).save(&path)?;
+        UI::print_info(&format!(
+            "Exported {} turn(s) to {}; continue with claude-dialog --from {}",
+            self.conversation.len(),
+            path.display(),
+            path.display()
+        ));
+        Ok(())
+    }
+    
+    /// Write the transcript to `path` or a new file as an HTML page
+    fn export_html(&mut self, path: Option<PathBuf>) -> Result<()> {
+        if self.config.incognito {
+            bail!("Conversations are not exported in incognito sessions");
+        }
+        if self.transcript.is_empty() {
+            bail!("There is no conversation to export yet");
+        }
+        
+        let path = match path {
+            Some(path) => paths::expand(path.as_os_str())?,
+            None => html::default_file_name(Local::now()),
+        };
+        html::save(&self.transcript, &path)?;
+        UI::print_info(&format!(
+            "Exported {} turn(s) to {}; open it in a browser to read or share it",
+            self.transcript.len(),
+            path.display()
+        ));
+        Ok(())
+    }
+    
+    /// Forget the conversation so the next prompt starts a new one, as
+    /// `/clear` asks
+    fn clear(&mut self) {
+        self.reset_conversation();
+        UI::print_info("Cleared the conversation; the next prompt starts a new one");
+    }
+    
+    /// Forget the conversation so the next prompt starts a new one
+    ///
+    /// Checkpoints refer to turns of the forgotten conversation, so they are
+    /// dropped as well.
+    fn reset_conversation(&mut self) {
+        self.conversation = Conversation::new();
+        self.checkpoints = CheckpointStore::new();
+        self.pending_context.clear();
+        self.session = SessionMode::Fresh;
+        self.seen = 0;
+        self.parked.clear();
+        self.joining_session = SessionMode::Fresh;
+        self.claude_session = None;
+        self.selection = Selection::default();
+        self.cancel_follow_ups();
+        if let Some(journal) = &mut self.journal
+            && let Err(e) = journal.truncate(0)
+        {
+            UI::print_error(&format!("Could not clear the session journal: {:#}", e));
+        }
+    }
+    
+    /// List the MCP servers Claude is started with
+    fn list_mcp_servers(&self) -> Result<()> {
+        let project_dir = if self.config.strict_mcp_config { None } else { Some(self.working_dir()?) };
+        let servers = mcp::servers(&self.config.mcp_config, project_dir.as_deref())?;
+        if servers.is_empty() {
+            UI::print_info("No MCP servers in --mcp-config files or the project's .mcp.json");
+        } else {
+            UI::print_info(&format!("MCP servers:\n{}", mcp::table(&servers)));
+        }
+        
+        if self.config.backend != Backend::Claude {
+            UI::print_warning(&format!("MCP servers are only used by the Claude backend, not {}", self.config.backend));
+        } else if self.config.strict_mcp_config {
+            UI::print_info("Only these servers are used (--strict-mcp-config)");
+        } else {
+            UI::print_info("Servers added with `claude mcp add` are used as well; `claude mcp list` shows them");
+        }
+        Ok(())
+    }
+    
+    /// The directory Claude works in, see [`DialogConfig::working_dir`]
+    fn working_dir(&self) -> Result<PathBuf> {
+        match &self.config.working_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(env::current_dir()?),
+        }
+    }
+    
+    /// Switch to another working directory, e.g. another project
+    ///
+    /// Claude keeps its sessions per directory, so the conversation of the
+    /// old one cannot be continued in the new one; it is cleared and the
+    /// next prompt starts a new conversation there.
+    fn change_dir(&mut self, dir: &Path) -> Result<()> {
+        let current = self.working_dir()?;
+        let dir = paths::directory(dir.as_os_str(), &current)?;
+        if dir == current {
+            UI::print_info(&format!("Already in {}", dir.display()));
+            return Ok(());
+        }
+        
+        if !self.conversation.is_empty() {
+            self.clear();
+        }
+        UI::print_info(&format!("Working in {}", dir.display()));
+        self.config.working_dir = Some(dir);
+        Ok(())
+    }
+    
+    /// Generate a map of the working directory to send with the next prompt
+    fn queue_repo_map(&mut self, show: bool) -> Result<()> {
+        let map = RepoMap::generate(&self.working_dir()?)?;
+        let rendered = map.render();
+        if show {
+            println!("{}", rendered);
+        }
+        
+        let mut message = format!(
+            "Repository map of {} file(s) (~{} tokens) will be sent with the next prompt",
+            map.file_count(),
+            estimate_tokens(&rendered)
+        );
+        if map.is_truncated() {
+            message.push_str(&format!(" (only the first {} files are listed)", MAX_FILES));
+        }
+        UI::print_info(&message);
+        
+        self.pending_context.push(QueuedContext {
+            label: "repository map".to_string(),
+            text: rendered,
+        });
+        Ok(())
+    }
+    
+    /// Read a file to send with the next prompt
+    fn attach(&mut self, path: &Path) -> Result<()> {
+        let attachment = Attachment::read(&paths::expand(path.as_os_str())?)?;
+        let rendered = attachment.render();
+        UI::print_info(&format!(
+            "{} ({} lines, ~{} tokens) will be sent with the next prompt",
+            attachment.path().display(),
+            attachment.lines(),
+            estimate_tokens(&rendered)
+        ));
+        
+        self.pending_context.push(QueuedContext {
+            label: format!("attached file {}", attachment.path().display()),
+            text: rendered,
+        });
+        Ok(())
+    }
+    
+    /// The system clipboard, opened on first use
+    fn clipboard(&mut self) -> Result<&mut SystemClipboard> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(SystemClipboard::open()?);
+        }
+        Ok(self.clipboard.as_mut().expect("the clipboard was just opened"))
+    }
+    
+    /// Copy the last response, as shown in the terminal, to the clipboard
+    fn copy_response(&mut self) -> Result<()> {
+        let response = self
+            .transcript
+            .entries()
+            .iter()
+            .rev()
+            .find_map(|entry| entry.turn.response.as_deref())
+            .ok_or_else(|| anyhow!("There is no response to copy yet"))?;
+        let text = self.displayed(response).into_owned();
+        
+        self.clipboard()?.copy(&text)?;
+        UI::print_info(&format!("Copied the last response ({} lines) to the clipboard", text.lines().count()));
+        Ok(())
+    }
+    
+    /// Send the clipboard's text, after `prompt` if one is given
+    async fn paste(&mut self, prompt: Option<String>) -> Result<()> {
+        let contents = self.clipboard()?.paste()?;
+        let input = clipboard::pasted_prompt(prompt.as_deref(), &contents)?;
+        UI::print_info(&format!("Pasted {} line(s) from the clipboard", contents.trim_end().lines().count()));
+        
+        let expanded = self.expand_prompt(&input, true)?;
+        self.send(&input, expanded).await
+    }
+    
+    /// Compose a prompt starting with `initial` in the external editor and
+    /// send it
+    async fn edit(&mut self, initial: &str) -> Result<()> {
+        let Some(input) = compose::compose(initial).await? else {
+            UI::print_info("The prompt is empty; nothing was sent");
+            return Ok(());
+        };
+        
+        let expanded = self.expand_prompt(&input, true)?;
+        self.send(&input, expanded).await
+    }
+    
+    /// Continue `text` in the external editor, for Ctrl+X Ctrl+E
+    ///
+    /// Like `/edit`, this is refused when the configuration disables that
+    /// command. `None`, with the reason reported, if there is nothing to send.
+    async fn compose(&mut self, text: &str) -> Option<String> {
+        if !self.commands.is_enabled("edit") {
+            self.report_error("/edit is disabled by the configuration".to_string());
+            return None;
+        }
+        match compose::compose(text).await {
+            Ok(Some(text)) => Some(text),
+            Ok(None) => {
+                UI::print_info("The prompt is empty; nothing was sent");
+                None
+            }
+            Err(e) => {
+                self.report_error(format!("{:#}", e));
+                None
+            }
+        }
+    }
+    
+    /// List the lines of the conversation containing `query`
+    ///
+    /// With `full`, every matching turn is redisplayed completely instead.
+    fn find(&self, query: &str, full: bool) -> Result<()> {
+        let conversation = self.whole_conversation()?;
+        let hits = conversation.find(query);
+        if hits.is_empty() {
+            UI::print_info(&format!("No matches for '{}'", query));
+            return Ok(());
+        }
+        
+        let mut turns: Vec<usize> = hits.iter().map(|hit| hit.turn).collect();
+        turns.dedup();
+        
+        if full {
+            for index in turns {
+                self.print_turn(index + 1, &conversation.turns()[index]);
+            }
+        } else {
+            for hit in &hits {
+                UI::print_search_hit(&self.displayed_hit(hit));
+            }
+            UI::print_info(&format!(
+                "{} match(es) in {} turn(s); use /find {} --full to show them",
+                hits.len(),
+                turns.len(),
+                query
+            ));
+        }
+        Ok(())
+    }
+    
+    /// List the numbered matches of `query` in the whole session, with
+    /// `context` lines around each, for the next input to pick a turn by
+    /// its number
+    fn search(&mut self, query: &str, context: usize) -> Result<()> {
+        let conversation = self.whole_conversation()?;
+        let hits = conversation.find_with_context(query, context);
+        if hits.is_empty() {
+            UI::print_info(&format!("No matches for '{}'", query));
+            return Ok(());
+        }
+        
+        for (index, hit) in hits.iter().enumerate() {
+            UI::print_search_match(index + 1, &self.displayed_hit(hit));
+        }
+        let mut turns: Vec<usize> = hits.iter().map(|hit| hit.turn).collect();
+        turns.dedup();
+        UI::print_info(&format!(
+            "{} match(es) in {} turn(s); type a number to show the turn of that match",
+            hits.len(),
+            turns.len()
+        ));
+        self.selection = Selection::new(hits.iter().map(|hit| format!("/turn {}", hit.turn + 1)));
+        Ok(())
+    }
+    
+    /// Show turn `number` of the whole session in full
+    fn show_turn(&self, number: usize) -> Result<()> {
+        let conversation = self.whole_conversation()?;
+        match number.checked_sub(1).and_then(|index| conversation.turns().get(index)) {
+            Some(turn) => {
+                self.print_turn(number, turn);
+                Ok(())
+            }
+            None => bail!("There is no turn {}; the conversation has {} turn(s)", number, conversation.len()),
+        }
+    }
+    
+    /// Show the response of turn `number` again, rendered as it was shown
+    /// when it arrived
+    async fn show_response(&self, number: usize) -> Result<()> {
+        let conversation = self.whole_conversation()?;
+        let Some(turn) = number.checked_sub(1).and_then(|index| conversation.turns().get(index)) else {
+            bail!("There is no turn {}; the conversation has {} turn(s)", number, conversation.len());
+        };
+        let Some(response) = &turn.response else {
+            bail!("Turn {} has no response", number);
+        };
+        self.replay_response(number, response).await
+    }
+    
+    /// Show the most recent response again
+    async fn show_last_response(&self) -> Result<()> {
+        let conversation = self.whole_conversation()?;
+        let last = conversation
+            .turns()
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, turn)| Some((index + 1, turn.response.as_ref()?)));
+        let Some((number, response)) = last else {
+            bail!("There is no response yet");
+        };
+        self.replay_response(number, response).await
+    }
+    
+    /// Render a response in the terminal, not mirrored to the tee
+    async fn replay_response(&self, number: usize, response: &str) -> Result<()> {
+        UI::print_numbered_claude_prompt(number);
+        let pipeline = self.screen_pipeline();
+        pipeline.send(response);
+        pipeline.finish().await?;
+        println!();
+        Ok(())
+    }
+    
+    /// Redisplay a turn, with its response masked as on screen
+    fn print_turn(&self, number: usize, turn: &Turn) {
+        let mut turn = turn.clone();
+        turn.response = turn.response.map(|response| self.displayed(&response).into_owned());
+        UI::print_turn(number, &turn);
+    }
+    
+    /// A search hit as shown on screen: lines of responses masked, and the
+    /// matches no longer highlighted if masking changed the line
+    fn displayed_hit(&self, hit: &SearchHit) -> SearchHit {
+        if hit.speaker == Speaker::User {
+            return hit.clone();
+        }
+        let masked = |lines: &[String]| lines.iter().map(|line| self.displayed(line).into_owned()).collect();
+        let hit = SearchHit {
+            before: masked(&hit.before),
+            after: masked(&hit.after),
+            ..hit.clone()
+        };
+        match self.displayed(&hit.line) {
+            // Masking moves the matches, so they are not highlighted
+            Cow::Owned(line) => SearchHit { line, matches: Vec::new(), ..hit },
+            Cow::Borrowed(_) => hit,
+        }
+    }
+    
+    /// Snapshot the conversation (and optionally the workspace) under a name
+    fn save_checkpoint(&mut self, name: String, include_files: bool) -> Result<()> {
+        let files = if include_files {
+            Some(WorkspaceSnapshot::capture(&self.working_dir()?)?)
+        } else {
+            None
+        };
+        
+        let mut message = format!(
+            "Checkpoint '{}' saved at turn {}",
+            name,
+            self.conversation.len()
+        );
+        if let Some(files) = &files {
+            message.push_str(&format!(" with {} workspace file(s)", files.len()));
+        }
+        
+        let checkpoint = Checkpoint {
+            name,
+            turn_count: self.conversation.len(),
+            files,
+        };
+        if self.checkpoints.save(checkpoint) {
+            message.push_str(" (replaced existing checkpoint)");
+        }
+        
+        UI::print_info(&message);
+        Ok(())
+    }
+    
+    /// Roll the conversation and workspace back to a named checkpoint
+    ///
+    /// When the checkpoint includes a snapshot, workspace files are restored
+    /// first, so that `@file` references in the replayed prompts read the
+    /// files as they were at the checkpoint. The conversation is then rebuilt
+    /// by replaying the prompts recorded up to the checkpoint into a fresh
+    /// Claude session.
+    async fn restore_checkpoint(&mut self, name: &str) -> Result<()> {
+        let checkpoint = self
+            .checkpoints
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No checkpoint named '{}'", name))?;
+        
+        let mut conversation = self.whole_conversation()?.into_owned();
+        conversation.truncate(checkpoint.turn_count);
+        let prompts: Vec<String> = conversation
+            .prompts()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        
+        if let Some(files) = &checkpoint.files {
+            let report = files.restore()?;
+            UI::print_info(&format!("Restored {} workspace file(s)", report.restored.len()));
+            for path in &report.untracked {
+                UI::print_info(&format!("  created since checkpoint (kept): {}", path.display()));
+            }
+            for path in &report.skipped {
+                UI::print_warning(&format!("  not restored, a directory on its path is now a symbolic link: {}", path.display()));
+            }
+        }
+        
+        // The conversation and journal are only cut back once the replay
+        // succeeded, so a failed restore loses no turns
+        if !prompts.is_empty() {
+            UI::print_info(&format!("Replaying {} turn(s)...", prompts.len()));
+        }
+        let replayed = match self.replay(&prompts).await {
+            Ok(replayed) => replayed,
+            Err(e) => {
+                // The replay started a new Claude session; go back to the
+                // conversation's own if its ID is known
+                if let Some(id) = &self.claude_session {
+                    self.session = SessionMode::Resume(id.clone());
+                }
+                let files = if checkpoint.files.is_some() { ", though its workspace files were restored" } else { "" };
+                return Err(e.context(format!("Could not restore checkpoint '{}'; the conversation was kept{}", checkpoint.name, files)));
+            }
+        };
+        self.conversation = conversation;
+        // Cutting the journal back also forgets the session it recorded, so
+        // a recovery resumes the replayed session rather than the discarded
+        // branch
+        if let Some(journal) = &mut self.journal {
+            journal.truncate(checkpoint.turn_count)?;
+            if let Some(id) = &replayed {
+                journal.append_session(id)?;
+            }
+        }
+        
+        // With nothing replayed, the next prompt must start a new session;
+        // other backends start over when they are next used
+        self.session = if prompts.is_empty() { SessionMode::Fresh } else { SessionMode::Continue };
+        self.seen = prompts.len();
+        self.parked.clear();
+        self.joining_session = SessionMode::Fresh;
+        self.claude_session = replayed;
+        
+        UI::print_info(&format!(
+            "Restored checkpoint '{}' at turn {}",
+            checkpoint.name, checkpoint.turn_count
+        ));
+        Ok(())
+    }
+    
+    /// Replay `prompts` into a fresh session to rebuild its context
+    ///
+    /// Returns the ID of the Claude session the last prompt was replayed
+    /// into, if it was reported.
+    async fn replay(&mut self, prompts: &[String]) -> Result<Option<String>> {
+        let mut replayed = None;
+        for (index, prompt) in prompts.iter().enumerate() {
+            let session = if index == 0 { SessionMode::Fresh } else { SessionMode::Continue };
+            let expanded = self.expand_prompt(prompt, false)?;
+            replayed = self.replay_prompt(&expanded.text, session).await?;
+        }
+        Ok(replayed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialog_loop_creation() {
+        let config = DialogConfig {
+            system_prompt: None,
+            append_prompt: None,
+            model: None,
+            ..Default::default()
+        };
+        let dialog = DialogLoop::new(config);
+        assert!(dialog.config.system_prompt.is_none());
+    }
+
+    #[test]
+    fn test_dialog_config_with_values() {
+        let config = DialogConfig {
+            system_prompt: Some("System".to_string()),
+            append_prompt: Some("Append".to_string()),
+            model: Some("claude-3-opus".to_string()),
+            ..Default::default()
+        };
+        
+        assert_eq!(config.system_prompt, Some("System".to_string()));
+        assert_eq!(config.append_prompt, Some("Append".to_string()));
+        assert_eq!(config.model, Some("claude-3-opus".to_string()));
+    }
+
+    #[test]
+    fn test_is_exit_command() {
+        let dialog = DialogLoop::new(DialogConfig {
+            system_prompt: None,
+            append_prompt: None,
+            model: None,
+            ..Default::default()
+        });
+        
+        // Test various exit commands
+        assert!(dialog.is_exit_command("exit"));
+        assert!(dialog.is_exit_command("EXIT"));
+        assert!(dialog.is_exit_command("Exit"));
+        assert!(dialog.is_exit_command("quit"));
+        assert!(dialog.is_exit_command("QUIT"));
+        assert!(dialog.is_exit_command("Quit"));
+        
+        // Test non-exit commands
+        assert!(!dialog.is_exit_command("hello"));
+        assert!(!dialog.is_exit_command("exit "));
+        assert!(!dialog.is_exit_command(" quit"));
+        assert!(!dialog.is_exit_command(""));
+    }
+
+    #[test]
+    fn test_read_input() {
+        let dialog = DialogLoop::new(DialogConfig {
             system_prompt: None,
             append_prompt: None,
             model: None,
+            ..Default::default()
         });
         
         // Test normal input
@@ -364,4 +3107,103 @@ mod tests {
         let result = dialog.read_input(&mut cursor).unwrap();
         assert_eq!(result, Some("test".to_string()));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_checkpoint_restore_without_turns() {
+        let mut dialog = DialogLoop::new(DialogConfig {
+            system_prompt: None,
+            append_prompt: None,
+            model: None,
+            ..Default::default()
+        });
+        
+        dialog.save_checkpoint("start".to_string(), false).unwrap();
+        assert!(dialog.checkpoints.get("start").is_some());
+        
+        dialog.restore_checkpoint("start").await.unwrap();
+        assert!(dialog.conversation().is_empty());
+        assert_eq!(dialog.session, SessionMode::Fresh);
+    }
+
+    /// An executor answering in one Claude session and replaying into another
+    struct Branching;
+
+    #[async_trait::async_trait]
+    impl ClaudeExecutor for Branching {
+        async fn execute(&self, _command: ClaudeInvocation, _pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+            Ok(ClaudeResponse { session_id: Some("branch".to_string()), ..ClaudeResponse::new("Answer") })
+        }
+
+        async fn replay(&self, _command: ClaudeInvocation) -> Result<ClaudeResponse> {
+            Ok(ClaudeResponse { session_id: Some("replayed".to_string()), ..ClaudeResponse::new("") })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_records_the_replayed_session_in_the_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(Branching));
+        dialog.journal = Some(Journal::create(dir.path()).unwrap());
+        
+        dialog.submit("one").await.unwrap();
+        dialog.submit("/checkpoint first").await.unwrap();
+        dialog.submit("two").await.unwrap();
+        dialog.submit("/restore first").await.unwrap();
+        assert_eq!(dialog.claude_session.as_deref(), Some("replayed"));
+        // Dropping the dialog without discarding its journal simulates a crash
+        drop(dialog);
+        
+        let unsaved = journal::find_unsaved(dir.path()).unwrap().unwrap();
+        assert_eq!(unsaved.conversation.prompts(), vec!["one"]);
+        assert_eq!(unsaved.claude_session.as_deref(), Some("replayed"));
+    }
+
+    #[tokio::test]
+    async fn test_journal_is_kept_when_the_session_cannot_be_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        // A file where the sessions directory should be
+        let sessions_dir = dir.path().join("sessions");
+        std::fs::write(&sessions_dir, "").unwrap();
+        let config = DialogConfig { sessions_dir: Some(sessions_dir), ..Default::default() };
+        let mut dialog = DialogLoop::with_executor(config, Box::new(Branching));
+        let journal = Journal::create(dir.path()).unwrap();
+        let path = journal.path().to_path_buf();
+        dialog.journal = Some(journal);
+        
+        dialog.submit("one").await.unwrap();
+        dialog.close_session().unwrap();
+        assert!(path.exists());
+        
+        let state_dir = tempfile::tempdir().unwrap();
+        dialog.config.sessions_dir = Some(dir.path().join("saved"));
+        dialog.journal = Some(Journal::create(state_dir.path()).unwrap());
+        let path = dialog.journal.as_ref().unwrap().path().to_path_buf();
+        dialog.close_session().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_compose_key_follows_a_disabled_edit_command() {
+        let mut dialog = DialogLoop::new(DialogConfig {
+            disallowed_commands: vec!["edit".to_string()],
+            ..Default::default()
+        });
+        
+        assert_eq!(dialog.compose("Draft").await, None);
+        let (_, message) = dialog.last_error.as_ref().unwrap();
+        assert_eq!(message, "/edit is disabled by the configuration");
+    }
+
+    #[tokio::test]
+    async fn test_restore_unknown_checkpoint() {
+        let mut dialog = DialogLoop::new(DialogConfig {
+            system_prompt: None,
+            append_prompt: None,
+            model: None,
+            ..Default::default()
+        });
+        
+        let err = dialog.restore_checkpoint("missing").await.unwrap_err();
+        assert!(err.to_string().contains("No checkpoint named 'missing'"));
+    }
+}
@@ -0,0 +1,423 @@
+//! Conversation export module
+//!
+//! `/export` writes the current conversation to a versioned JSON file, and
+//! `--from <file>` seeds a new session with it, so a conversation can be
+//! handed to someone else or picked up on another machine. Claude does not
+//! know the imported turns until they are sent again, which happens in one of
+//! two ways (see [`ImportMode`]): every prompt is replayed into a new Claude
+//! session, or a condensed copy of the turns goes along with the first
+//! prompt.
+//!
+//! `/export html` writes the session transcript as a web page instead (see
+//! [`crate::html`]), which is for reading, not importing.
+//!
+//! # File Format
+//!
+//! ```json
+//! {
+//!   "format": "claude-dialog-conversation",
+//!   "version": 1,
+//!   "exported_at": "2025-01-01T12:00:00Z",
+//!   "model": "opus",
+//!   "turns": [
+//!     { "prompt": "Review @src/lib.rs", "response": "Looks good.", "sources": [...], "rating": 4, "notes": [...], "time": "2025-01-01T11:58:30Z" }
+//!   ]
+//! }
+//! ```
+//!
+//! `model` is omitted when the CLI's default model was used, and `rating` and
+//! `notes` when a turn has none; notes stay in the file but are never sent to
+//! Claude. `time` is when the prompt was sent, missing in files from before
+//! it was recorded, and turns merged by `claude-dialog sessions merge` have
+//! the label of their session as `origin`. Responses are recorded normalized
+//! (see [`crate::normalize`]), with the languages of their code blocks as
+//! `code_languages`. Files of a newer
+//! format version are rejected instead of being misread.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::conversation::Conversation;
+//! use claude_dialog::export::ConversationExport;
+//!
+//! let mut conversation = Conversation::new();
+//! conversation.push("What is a trait?");
+//! conversation.set_response("A set of methods a type can implement.");
+//!
+//! let json = ConversationExport::new(&conversation, Some("opus")).to_json();
+//! let imported = ConversationExport::from_json(&json).unwrap();
+//! assert_eq!(imported.conversation(), conversation);
+//! ```
+
+use anyhow::{Result, Context, bail};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::archive;
+use crate::conversation::{Conversation, Turn};
+use crate::transcript::{Provenance, Transcript, TranscriptEntry};
+
+/// Value of the `format` field identifying a conversation file
+pub const FORMAT: &str = "claude-dialog-conversation";
+
+/// Version of the file format written by this build
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Lines of each response kept by [`ConversationExport::summary`]
+const SUMMARY_RESPONSE_LINES: usize = 20;
+
+/// How an imported conversation is made known to Claude
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportMode {
+    /// Send every imported prompt again, without tools, to a new Claude session
+    #[default]
+    Replay,
+
+    /// Send a condensed copy of the imported turns with the first prompt
+    Summary,
+}
+
+impl fmt::Display for ImportMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ImportMode::Replay => "replay",
+            ImportMode::Summary => "summary",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What `/export` writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// The conversation as JSON, for `--from` to import
+    #[default]
+    Json,
+
+    /// The session transcript as a standalone HTML page, for sharing (see
+    /// [`crate::html`])
+    Html,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "html" => Ok(ExportFormat::Html),
+            _ => bail!("Unknown export format '{}'; expected json or html", s),
+        }
+    }
+}
+
+/// A conversation as written to an export file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationExport {
+    /// Always [`FORMAT`]
+    pub format: String,
+
+    /// Format version the file was written with
+    pub version: u32,
+
+    /// When the conversation was exported
+    pub exported_at: DateTime<Utc>,
+
+    /// Model of the exporting session, `None` for the CLI's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// The turns of the conversation, oldest first
+    pub turns: Vec<Turn>,
+}
+
+impl ConversationExport {
+    /// Export the turns of a conversation
+    pub fn new(conversation: &Conversation, model: Option<&str>) -> Self {
+        Self {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            exported_at: Utc::now(),
+            model: model.map(String::from),
+            turns: conversation.turns().to_vec(),
+        }
+    }
+
+    /// The file contents, as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("conversation exports serialize to JSON")
+    }
+
+    /// Read an export from JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not a conversation file, or was
+    /// written with a newer format version.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(text).context("Not a JSON file")?;
+        if value["format"] != FORMAT {
+            bail!("Not a claude-dialog conversation file");
+        }
+        match value["version"].as_u64() {
+            Some(version) if version <= u64::from(FORMAT_VERSION) => {}
+            Some(version) => bail!(
+                "The conversation file has format version {}, but this claude-dialog reads version {} at most; update claude-dialog to import it",
+                version,
+                FORMAT_VERSION
+            ),
+            None => bail!("The conversation file has no format version"),
+        }
+        serde_json::from_value(value).context("Invalid conversation file")
+    }
+
+    /// Write the export to a file, replacing it if it exists
+    ///
+    /// Missing parent directories are created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write conversation: {}", path.display()))
+    }
+
+    /// Read an export from a file
+    ///
+    /// Sessions compressed by `sessions archive` (`.zst` files) are
+    /// decompressed first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a conversation
+    /// file this build can read.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut data = fs::read(path).with_context(|| format!("Failed to read conversation: {}", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == archive::EXTENSION) {
+            data = archive::decompress(&data).with_context(|| format!("Cannot import {}", path.display()))?;
+        }
+        let text = String::from_utf8(data).with_context(|| format!("Cannot import {}: not UTF-8", path.display()))?;
+        Self::from_json(&text).with_context(|| format!("Cannot import {}", path.display()))
+    }
+
+    /// The exported turns as a conversation
+    pub fn conversation(&self) -> Conversation {
+        let mut conversation = Conversation::new();
+        for turn in &self.turns {
+            conversation.push_turn(turn.clone());
+        }
+        conversation
+    }
+
+    /// The conversations of several sessions as one, their turns
+    /// interleaved in the order they were sent
+    ///
+    /// Each turn is labeled with the name given with its conversation,
+    /// unless it was labeled by an earlier merge. Turns of one conversation
+    /// stay in their order; those recorded without a time count as sent
+    /// when their conversation was exported. The model is kept if all
+    /// conversations used the same one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use claude_dialog::conversation::{Conversation, Turn};
+    /// use claude_dialog::export::ConversationExport;
+    ///
+    /// let at = |minute| Utc.with_ymd_and_hms(2025, 1, 1, 12, minute, 0).unwrap();
+    /// let session = |prompts: &[(&str, u32)]| {
+    ///     let mut conversation = Conversation::new();
+    ///     for (prompt, minute) in prompts {
+    ///         conversation.push_turn(Turn::new(*prompt).with_time(at(*minute)));
+    ///     }
+    ///     ConversationExport::new(&conversation, None)
+    /// };
+    ///
+    /// let merged = ConversationExport::merge(vec![
+    ///     ("research".to_string(), session(&[("Compare the crates", 0), ("Which is faster?", 20)])),
+    ///     ("implementation".to_string(), session(&[("Add the dependency", 10)])),
+    /// ]);
+    /// let prompts: Vec<&str> = merged.turns.iter().map(|turn| turn.prompt.as_str()).collect();
+    /// assert_eq!(prompts, ["Compare the crates", "Add the dependency", "Which is faster?"]);
+    /// assert_eq!(merged.turns[1].origin.as_deref(), Some("implementation"));
+    /// ```
+    pub fn merge(sources: Vec<(String, ConversationExport)>) -> Self {
+        let model = sources.first().and_then(|(_, export)| export.model.clone());
+        let same_model = sources.iter().all(|(_, export)| export.model == model);
+
+        let mut queues: Vec<_> = sources
+            .into_iter()
+            .map(|(label, export)| {
+                let exported_at = export.exported_at;
+                export
+                    .turns
+                    .into_iter()
+                    .map(move |mut turn| {
+                        turn.origin.get_or_insert_with(|| label.clone());
+                        (turn.time.unwrap_or(exported_at), turn)
+                    })
+                    .peekable()
+            })
+            .collect();
+
+        let mut turns = Vec::new();
+        loop {
+            // The earliest next turn; ties go to the conversation given first
+            let next = queues
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(index, queue)| queue.peek().map(|(time, _)| (*time, index)))
+                .min();
+            let Some((_, index)) = next else {
+                break;
+            };
+            turns.extend(queues[index].next().map(|(_, turn)| turn));
+        }
+
+        Self {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            exported_at: Utc::now(),
+            model: model.filter(|_| same_model),
+            turns,
+        }
+    }
+
+    /// The turns as a transcript, for Markdown and HTML files
+    ///
+    /// Turns recorded without a time are shown at the time of the export.
+    pub fn transcript(&self) -> Transcript {
+        let time = |turn: &Turn| turn.time.unwrap_or(self.exported_at).with_timezone(&Local);
+        let entries: Vec<TranscriptEntry> = self
+            .turns
+            .iter()
+            .map(|turn| TranscriptEntry {
+                time: time(turn),
+                model: self.model.clone(),
+                turn: turn.clone(),
+                provenance: Provenance::default(),
+            })
+            .collect();
+        let started = entries.first().map_or_else(|| self.exported_at.with_timezone(&Local), |entry| entry.time);
+        Transcript::from_entries(started, entries)
+    }
+
+    /// A condensed copy of the turns to send along with a prompt
+    ///
+    /// Prompts are kept whole; responses are cut after their first
+    /// lines.
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    /// For context, this conversation continues an earlier one, imported from a file:
+    ///
+    /// User: Review @src/lib.rs
+    ///
+    /// Assistant: Looks good.
+    /// [... 12 more lines]
+    /// ```
+    pub fn summary(&self) -> String {
+        let mut text = String::from("For context, this conversation continues an earlier one, imported from a file:\n");
+        for turn in &self.turns {
+            text.push_str(&format!("\nUser: {}\n", turn.prompt.trim_end()));
+            let Some(response) = &turn.response else {
+                continue;
+            };
+
+            let lines: Vec<&str> = response.trim_end().lines().collect();
+            let kept = lines.len().min(SUMMARY_RESPONSE_LINES);
+            text.push_str(&format!("\nAssistant: {}\n", lines[..kept].join("\n")));
+            if lines.len() > kept {
+                text.push_str(&format!("[... {} more lines]\n", lines.len() - kept));
+            }
+        }
+        text
+    }
+}
+
+/// File name for a conversation exported without a path, e.g.
+/// `claude-dialog-20250101-120000.json`
+pub fn default_file_name(time: DateTime<Local>) -> PathBuf {
+    PathBuf::from(format!("claude-dialog-{}.json", time.format("%Y%m%d-%H%M%S")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(ConversationExport::from_json("[]").is_err());
+        assert!(ConversationExport::from_json(r#"{"format":"other","version":1,"turns":[]}"#).is_err());
+
+        let newer = format!(r#"{{"format":"{}","version":2,"exported_at":"2025-01-01T12:00:00Z","turns":[]}}"#, FORMAT);
+        let err = ConversationExport::from_json(&newer).unwrap_err();
+        assert!(err.to_string().contains("format version 2"));
+
+        let current = newer.replace("\"version\":2", "\"version\":1");
+        assert!(ConversationExport::from_json(&current).unwrap().turns.is_empty());
+    }
+
+    #[test]
+    fn test_summary_cuts_long_responses() {
+        let mut conversation = Conversation::new();
+        conversation.push("Count to 25");
+        let numbers: Vec<String> = (1..=25).map(|n| n.to_string()).collect();
+        conversation.set_response(numbers.join("\n"));
+        conversation.push("Unanswered");
+
+        let summary = ConversationExport::new(&conversation, None).summary();
+        assert!(summary.contains("\nUser: Count to 25\n\nAssistant: 1\n2\n"));
+        assert!(summary.contains("\n20\n[... 5 more lines]\n\nUser: Unanswered\n"));
+        assert!(!summary.contains("21"));
+    }
+
+    #[test]
+    fn test_merge_keeps_the_order_of_each_conversation() {
+        let at = |minute| DateTime::parse_from_rfc3339(&format!("2025-01-01T12:{:02}:00Z", minute)).unwrap().with_timezone(&Utc);
+        let mut research = Conversation::new();
+        research.push_turn(Turn::new("first").with_time(at(30)));
+        research.push_turn(Turn::new("recovered"));
+        let mut research = ConversationExport::new(&research, Some("opus"));
+        research.exported_at = at(40);
+
+        let mut implementation = Conversation::new();
+        implementation.push_turn(Turn::new("early").with_time(at(10)));
+        implementation.push_turn(Turn::new("late").with_time(at(50)));
+        let implementation = ConversationExport::new(&implementation, None);
+
+        let merged = ConversationExport::merge(vec![("research".to_string(), research), ("impl".to_string(), implementation)]);
+        let prompts: Vec<&str> = merged.turns.iter().map(|turn| turn.prompt.as_str()).collect();
+        assert_eq!(prompts, ["early", "first", "recovered", "late"]);
+        assert_eq!(merged.turns[2].origin.as_deref(), Some("research"));
+        assert!(merged.model.is_none());
+
+        let markdown = merged.transcript().to_markdown();
+        assert!(markdown.contains(", research)\n\nrecovered\n"));
+        assert_eq!(ConversationExport::from_json(&merged.to_json()).unwrap(), merged);
+    }
+}
@@ -0,0 +1,132 @@
+//! Follow-up suggestions module
+//!
+//! With `--suggest-follow-ups`, each answer is followed by a few questions
+//! the user might ask next, numbered so that typing a number at the `You>`
+//! prompt sends that question (see [`crate::selection`]). The suggestions come from a separate, short
+//! request to a small model that sees only the last exchange, so they cost
+//! little and never become part of the conversation. The request runs in
+//! the background while the user reads the answer, in a private directory of
+//! its own (see [`working_dir`]), and its usage counts toward the session's.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::follow_up;
+//!
+//! let suggestions = follow_up::parse("1. How do I test it?\n2. What about errors?\n\n- Is it fast?\n4. One more");
+//! assert_eq!(suggestions, ["How do I test it?", "What about errors?", "Is it fast?"]);
+//! ```
+
+use std::io;
+use tempfile::{Builder, TempDir};
+
+/// Model asked for suggestions, chosen for being fast and cheap
+pub const MODEL: &str = "haiku";
+
+/// Most suggestions shown after an answer
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Characters of the answer included in the request; the start of a long
+/// answer says enough about what it covered
+const MAX_ANSWER_CHARS: usize = 4000;
+
+/// The prompt asking for follow-up questions to an exchange
+///
+/// # Arguments
+///
+/// * `prompt` - The prompt of the exchange, as typed
+/// * `answer` - The answer to it
+pub fn request(prompt: &str, answer: &str) -> String {
+    let answer: String = answer.chars().take(MAX_ANSWER_CHARS).collect();
+    format!(
+        "Suggest {} short follow-up questions the user could ask next about this exchange. \
+         Write them in the language of the question, one per line, without numbers or any other text.\n\n\
+         Question:\n{}\n\nAnswer:\n{}",
+        MAX_SUGGESTIONS, prompt, answer
+    )
+}
+
+/// Prefix of the name of the directory the request runs in
+pub const DIR_PREFIX: &str = "claude-dialog-follow-ups";
+
+/// Create the directory the requests for suggestions run in
+///
+/// The Claude CLI's `--continue` picks the most recent session of its
+/// working directory. The request starts a session of its own, so it runs
+/// outside the workspace to keep `--continue` on the conversation, even
+/// when the next turn starts before the suggestions arrive.
+///
+/// The CLI reads `CLAUDE.md` and `.claude/settings.json` from its working
+/// directory, so the directory is newly created with a random name and is
+/// only accessible to the user; it is removed when dropped.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be created.
+pub fn working_dir() -> io::Result<TempDir> {
+    let mut builder = Builder::new();
+    builder.prefix(DIR_PREFIX);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o700));
+    }
+    builder.tempdir()
+}
+
+/// Read the suggestions from the model's reply
+///
+/// Lines are stripped of list markers such as `1.`, `2)` or `-`; empty
+/// lines are skipped and at most [`MAX_SUGGESTIONS`] are kept.
+pub fn parse(reply: &str) -> Vec<String> {
+    reply
+        .lines()
+        .map(strip_marker)
+        .filter(|line| !line.is_empty())
+        .take(MAX_SUGGESTIONS)
+        .map(str::to_string)
+        .collect()
+}
+
+/// A line without its list marker and surrounding whitespace
+fn strip_marker(line: &str) -> &str {
+    let line = line.trim();
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = if digits > 0 {
+        line[digits..].strip_prefix(['.', ')']).unwrap_or(line)
+    } else {
+        line.strip_prefix(['-', '*', '•']).unwrap_or(line)
+    };
+    rest.trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_marker() {
+        assert_eq!(strip_marker("  3) Why?"), "Why?");
+        assert_eq!(strip_marker("* Why?"), "Why?");
+        assert_eq!(strip_marker("2024 was a leap year?"), "2024 was a leap year?");
+        assert_eq!(strip_marker("Why?"), "Why?");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_working_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = working_dir().unwrap();
+        let mode = dir.path().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert_ne!(working_dir().unwrap().path(), dir.path());
+    }
+
+    #[test]
+    fn test_request_trims_long_answers() {
+        let request = request("Explain", &"x".repeat(10_000));
+        assert!(request.starts_with("Suggest 3 short follow-up questions"));
+        assert!(request.ends_with(&format!("Answer:\n{}", "x".repeat(MAX_ANSWER_CHARS))));
+    }
+}
@@ -0,0 +1,193 @@
+//! Syntax highlighting module
+//!
+//! Highlights the code blocks of rendered responses in the language their
+//! fence declares, such as ```` ```rust ````, with the syntax definitions
+//! and a dark theme bundled with [syntect](https://docs.rs/syntect). Blocks
+//! without a language, or in a language syntect does not know, are shown as
+//! written.
+//!
+//! Colors are 24-bit when the terminal says it supports them through
+//! `COLORTERM`, and the closest of the 256 standard colors otherwise.
+//! `--no-highlight` turns highlighting off for terminals that show neither.
+//! Pages written by `/export html` color code blocks with HTML styles
+//! instead (see [`CodeHighlighter::highlight_html`]).
+//!
+//! The syntax definitions are loaded when the first code block is
+//! highlighted, so sessions without code do not pay for them.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::highlight::CodeHighlighter;
+//!
+//! let mut code = CodeHighlighter::new("rust").unwrap();
+//! let line = code.highlight("let answer = 42;");
+//! assert!(line.contains("\x1b[38;"));
+//! assert!(line.contains("answer"));
+//!
+//! assert!(CodeHighlighter::new("no-such-language").is_none());
+//! ```
+
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use crate::html;
+
+/// Bundled theme used for highlighting
+pub const THEME: &str = "base16-ocean.dark";
+
+/// Highlights the lines of one code block, in order
+///
+/// Lines must be highlighted one after another, because a line's colors can
+/// depend on the lines before it, e.g. inside a multi-line comment.
+pub struct CodeHighlighter {
+    lines: HighlightLines<'static>,
+    true_color: bool,
+}
+
+impl fmt::Debug for CodeHighlighter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodeHighlighter").field("true_color", &self.true_color).finish_non_exhaustive()
+    }
+}
+
+impl CodeHighlighter {
+    /// Create a highlighter for a code block
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The language of the block's fence, as a name or file
+    ///   extension such as `rust`, `rs` or `Python`
+    ///
+    /// # Returns
+    ///
+    /// `None` if the language is not known.
+    pub fn new(language: &str) -> Option<Self> {
+        let language = language.trim();
+        if language.is_empty() {
+            return None;
+        }
+        let syntax = syntaxes().find_syntax_by_token(language)?;
+        Some(Self {
+            lines: HighlightLines::new(syntax, theme()),
+            true_color: supports_true_color(),
+        })
+    }
+
+    /// Highlight the next line of the block, given without its line break
+    ///
+    /// A line that cannot be highlighted is returned as it is.
+    pub fn highlight(&mut self, line: &str) -> String {
+        let true_color = self.true_color;
+        self.paint(line, |color, text| format!("\x1b[{}m{}\x1b[0m", foreground(color, true_color), text))
+            .unwrap_or_else(|| line.to_string())
+    }
+
+    /// Highlight the next line of the block as HTML, with a `<span>` in the
+    /// theme's color around each token
+    ///
+    /// The text is escaped; a line that cannot be highlighted is only
+    /// escaped.
+    pub fn highlight_html(&mut self, line: &str) -> String {
+        self.paint(line, |color, text| {
+            format!("<span style=\"color:#{:02x}{:02x}{:02x}\">{}</span>", color.r, color.g, color.b, html::escape(text))
+        })
+        .unwrap_or_else(|| html::escape(line))
+    }
+
+    /// The next line with every token styled by `paint` and whitespace
+    /// left as it is, or `None` if the line cannot be highlighted
+    fn paint(&mut self, line: &str, paint: impl Fn(Color, &str) -> String) -> Option<String> {
+        // The bundled syntaxes expect lines with their line break
+        let line = format!("{}\n", line);
+        let regions = self.lines.highlight_line(&line, syntaxes()).ok()?;
+
+        let mut output = String::new();
+        for (style, text) in regions {
+            let text = text.trim_end_matches('\n');
+            if text.is_empty() {
+                continue;
+            }
+            if text.trim().is_empty() {
+                output.push_str(text);
+                continue;
+            }
+            output.push_str(&paint(style.foreground, text));
+        }
+        Some(output)
+    }
+}
+
+/// The bundled syntax definitions, loaded on first use
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// The highlighting theme, loaded on first use
+fn theme() -> &'static Theme {
+    static THEME_DATA: OnceLock<Theme> = OnceLock::new();
+    THEME_DATA.get_or_init(|| {
+        ThemeSet::load_defaults()
+            .themes
+            .remove(THEME)
+            .expect("the bundled themes include the highlighting theme")
+    })
+}
+
+/// Whether the terminal announces 24-bit colors
+fn supports_true_color() -> bool {
+    env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+}
+
+/// SGR parameters for a foreground color
+fn foreground(color: Color, true_color: bool) -> String {
+    if true_color {
+        format!("38;2;{};{};{}", color.r, color.g, color.b)
+    } else {
+        format!("38;5;{}", ansi256(color))
+    }
+}
+
+/// The closest color of the 6×6×6 cube of the 256-color palette
+fn ansi256(color: Color) -> u8 {
+    let level = |value: u8| ((u16::from(value) * 5 + 127) / 255) as u8;
+    16 + 36 * level(color.r) + 6 * level(color.g) + level(color.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_languages_are_found_by_name_or_extension() {
+        assert!(CodeHighlighter::new("rust").is_some());
+        assert!(CodeHighlighter::new("rs").is_some());
+        assert!(CodeHighlighter::new("Python").is_some());
+        assert!(CodeHighlighter::new("sh").is_some());
+        assert!(CodeHighlighter::new("").is_none());
+        assert!(CodeHighlighter::new("klingon").is_none());
+    }
+
+    #[test]
+    fn test_highlighting_keeps_the_text() {
+        let mut code = CodeHighlighter::new("python").unwrap();
+        let escapes = regex::Regex::new("\x1b\\[[0-9;]*m").unwrap();
+        let lines = ["def add(a, b):", "    \"\"\"Sum", "    of two\"\"\"", "    return a + b"];
+        for line in lines {
+            let highlighted = code.highlight(line);
+            assert_eq!(escapes.replace_all(&highlighted, ""), line);
+        }
+    }
+
+    #[test]
+    fn test_256_color_approximation() {
+        assert_eq!(ansi256(Color { r: 0, g: 0, b: 0, a: 0xff }), 16);
+        assert_eq!(ansi256(Color { r: 255, g: 255, b: 255, a: 0xff }), 231);
+        assert_eq!(ansi256(Color { r: 255, g: 0, b: 0, a: 0xff }), 196);
+        assert_eq!(foreground(Color { r: 1, g: 2, b: 3, a: 0xff }, true), "38;2;1;2;3");
+    }
+}
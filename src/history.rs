@@ -0,0 +1,279 @@
+//! Input history module
+//!
+//! Every line typed at the `You>` prompt is saved to the history file in the
+//! state directory (see [`Paths::history_file`](crate::paths::Paths::history_file)),
+//! one entry per line with the most recent last. Entering a line again moves
+//! it to the end instead of storing a duplicate, and only the most recent
+//! [`MAX_ENTRIES`] lines are kept.
+//!
+//! Sensitive prompts can be kept out of the file:
+//!
+//! - lines matching an ignore pattern are never saved; patterns are globs
+//!   matched case-insensitively against the whole line, e.g. `*password*`
+//! - `--private` disables the history file for a session
+//! - `history_ignore` and `history = false` in `config.toml` do the same
+//!   for every session (see [`crate::config`])
+//! - `claude-dialog history clear` deletes the file
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::history::History;
+//! # let dir = tempfile::tempdir().unwrap();
+//! # let path = dir.path().join("history");
+//!
+//! let mut history = History::open(&path, &["*token*".to_string()]).unwrap();
+//! history.add("Explain lifetimes").unwrap();
+//! history.add("My TOKEN is abc123, why is it rejected?").unwrap();
+//! history.add("Explain lifetimes").unwrap();
+//!
+//! assert_eq!(history.entries(), ["Explain lifetimes"]);
+//! assert_eq!(std::fs::read_to_string(&path).unwrap(), "Explain lifetimes\n");
+//! ```
+
+use anyhow::{Result, Context};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use crate::paths;
+
+/// Largest number of lines kept in the history file
+pub const MAX_ENTRIES: usize = 1000;
+
+/// Input history backed by a file
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+    entries: Vec<String>,
+    ignore: GlobSet,
+}
+
+impl History {
+    /// Load the history file at `path`
+    ///
+    /// A missing file is treated as an empty history; it is created when the
+    /// first line is added.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The history file
+    /// * `ignore_patterns` - Lines matching one of these globs are never saved
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pattern is invalid or the file cannot be read.
+    pub fn open(path: &Path, ignore_patterns: &[String]) -> Result<Self> {
+        let mut ignore = GlobSetBuilder::new();
+        for pattern in ignore_patterns {
+            ignore.add(ignore_pattern(pattern)?);
+        }
+
+        let entries = match fs::read_to_string(path) {
+            Ok(content) => content.lines().map(String::from).collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read history: {}", path.display()));
+            }
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+            ignore: ignore.build()?,
+        })
+    }
+
+    /// Saved lines, oldest first
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Whether a line matches one of the ignore patterns
+    pub fn is_ignored(&self, line: &str) -> bool {
+        self.ignore.is_match(line)
+    }
+
+    /// Save a line to the history
+    ///
+    /// # Returns
+    ///
+    /// `false` if the line was not saved because it is ignored, empty or
+    /// the same as the previous line
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the history file cannot be written.
+    pub fn add(&mut self, line: &str) -> Result<bool> {
+        let line = line.trim();
+        if line.is_empty() || line.contains('\n') || self.is_ignored(line) {
+            return Ok(false);
+        }
+        if self.entries.last().is_some_and(|last| last == line) {
+            return Ok(false);
+        }
+
+        let previous = self.entries.iter().position(|entry| entry == line);
+        if let Some(index) = previous {
+            self.entries.remove(index);
+        }
+        self.entries.push(line.to_string());
+
+        if previous.is_some() || self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+            self.entries.drain(..excess);
+            self.rewrite()?;
+        } else {
+            self.append(line)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Delete the history file at `path`
+    ///
+    /// # Returns
+    ///
+    /// Whether there was a file to delete
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be deleted.
+    pub fn clear(path: &Path) -> Result<bool> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to delete history: {}", path.display())),
+        }
+    }
+
+    fn append(&self, line: &str) -> Result<()> {
+        self.create_dir()?;
+        let mut file = paths::private_file()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to write history: {}", self.path.display()))?;
+        writeln!(file, "{}", line)
+            .with_context(|| format!("Failed to write history: {}", self.path.display()))
+    }
+
+    fn rewrite(&self) -> Result<()> {
+        self.create_dir()?;
+        let content: String = self.entries.iter().map(|entry| format!("{}\n", entry)).collect();
+        paths::write_private(&self.path, content)
+            .with_context(|| format!("Failed to write history: {}", self.path.display()))
+    }
+
+    fn create_dir(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            paths::create_private_dir(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Compile a history ignore pattern
+///
+/// Patterns are globs matched case-insensitively against the whole line;
+/// `*` also matches `/`.
+///
+/// # Errors
+///
+/// Returns an error if the pattern is not a valid glob.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::history::ignore_pattern;
+///
+/// let glob = ignore_pattern("*api_key=*").unwrap();
+/// assert!(glob.compile_matcher().is_match("use API_KEY=sk-123 for /v1/models"));
+/// assert!(ignore_pattern("[unclosed").is_err());
+/// ```
+pub fn ignore_pattern(pattern: &str) -> Result<Glob> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .with_context(|| format!("Invalid history ignore pattern '{}'", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_lines_move_to_the_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data/history");
+
+        let mut history = History::open(&path, &[]).unwrap();
+        assert!(history.add("one").unwrap());
+        assert!(history.add("two").unwrap());
+        assert!(!history.add("two").unwrap());
+        assert!(history.add("one").unwrap());
+        assert!(!history.add("   ").unwrap());
+
+        assert_eq!(history.entries(), ["two", "one"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\none\n");
+        assert_eq!(History::open(&path, &[]).unwrap().entries(), ["two", "one"]);
+    }
+
+    #[test]
+    fn test_oldest_entries_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        let content: String = (0..MAX_ENTRIES).map(|n| format!("line {}\n", n)).collect();
+        fs::write(&path, content).unwrap();
+
+        let mut history = History::open(&path, &[]).unwrap();
+        history.add("newest").unwrap();
+
+        let saved = History::open(&path, &[]).unwrap();
+        assert_eq!(saved.entries().len(), MAX_ENTRIES);
+        assert_eq!(saved.entries()[0], "line 1");
+        assert_eq!(saved.entries().last().unwrap(), "newest");
+    }
+
+    #[test]
+    fn test_ignored_lines_are_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        let patterns = ["*password*".to_string(), "/login *".to_string()];
+
+        let mut history = History::open(&path, &patterns).unwrap();
+        assert!(!history.add("My Password is hunter2").unwrap());
+        assert!(!history.add("/login alice").unwrap());
+        assert!(history.add("/find login").unwrap());
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "/find login\n");
+        assert!(History::open(&path, &["{a,".to_string()]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_history_is_readable_only_by_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/history");
+        let mut history = History::open(&path, &[]).unwrap();
+        history.add("Explain lifetimes").unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir.path().join("state")), 0o700);
+        assert_eq!(mode(&path), 0o600);
+    }
+
+    #[test]
+    fn test_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        fs::write(&path, "secret\n").unwrap();
+
+        assert!(History::clear(&path).unwrap());
+        assert!(!path.exists());
+        assert!(!History::clear(&path).unwrap());
+    }
+}
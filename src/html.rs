@@ -0,0 +1,286 @@
+//! HTML transcript module
+//!
+//! `/export html` renders the session transcript (see
+//! [`crate::transcript`]) as a standalone HTML page for sharing a session
+//! with people who don't use claude-dialog, e.g. a debugging session with
+//! teammates. Prompts and responses are shown as chat bubbles, responses
+//! with their Markdown rendered and code blocks highlighted in the language
+//! of their fence (with the `highlight` feature). The styles are part of
+//! the page, so the file can be mailed or attached as it is.
+//!
+//! HTML in prompts and responses is shown as text, never run.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::conversation::Turn;
+//! use claude_dialog::html;
+//! use claude_dialog::transcript::Transcript;
+//!
+//! let mut transcript = Transcript::new();
+//! transcript.record(Turn::new("Why is <b> ignored?"), Some("opus"));
+//! transcript.set_response("Use `<strong>` instead.");
+//!
+//! let page = html::to_html(&transcript);
+//! assert!(page.starts_with("<!DOCTYPE html>"));
+//! assert!(page.contains("Why is &lt;b&gt; ignored?"));
+//! assert!(page.contains("<p>Use <code>&lt;strong&gt;</code> instead.</p>"));
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::conversation::MAX_RATING;
+#[cfg(feature = "highlight")]
+use crate::highlight::CodeHighlighter;
+use crate::transcript::Transcript;
+
+/// Styles of the page; code blocks use the background of the highlighting
+/// theme
+const STYLE: &str = "
+body { margin: 0; background: #f4f5f7; color: #1f2328; font: 16px/1.5 -apple-system, BlinkMacSystemFont, 'Segoe UI', Helvetica, Arial, sans-serif; }
+main { max-width: 860px; margin: 0 auto; padding: 24px 16px 48px; }
+h1 { font-size: 1.5em; margin: 0 0 4px; }
+.summary { color: #59636e; margin: 0 0 24px; }
+.turn { display: flex; flex-direction: column; gap: 12px; margin-bottom: 24px; }
+.bubble { max-width: 85%; padding: 12px 16px; border-radius: 14px; box-shadow: 0 1px 2px rgba(0, 0, 0, 0.08); overflow-wrap: anywhere; }
+.user { align-self: flex-end; background: #dbeafe; border-bottom-right-radius: 4px; }
+.assistant { align-self: flex-start; background: #ffffff; border-bottom-left-radius: 4px; }
+.speaker { font-size: 0.8em; font-weight: 600; color: #59636e; margin-bottom: 4px; }
+.prompt { white-space: pre-wrap; }
+.meta { font-size: 0.85em; font-style: italic; color: #59636e; margin: 8px 0 0; }
+.note { margin: 8px 0 0; padding: 4px 12px; border-left: 3px solid #d0d7de; color: #59636e; }
+.response > :first-child { margin-top: 0; }
+.response > :last-child { margin-bottom: 0; }
+pre { background: #2b303b; color: #c0c5ce; padding: 12px; border-radius: 8px; overflow-x: auto; }
+code { font: 0.9em/1.45 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+:not(pre) > code { background: rgba(175, 184, 193, 0.25); padding: 1px 4px; border-radius: 4px; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 4px 10px; }
+blockquote { margin: 0; padding-left: 12px; border-left: 3px solid #d0d7de; color: #59636e; }
+";
+
+/// Render a transcript as a standalone HTML page
+///
+/// # Output Format
+///
+/// ```text
+/// <main>
+/// <h1>Claude Dialog transcript</h1>
+/// <p class="summary">Started 2025-01-01 12:00, 1 turn</p>
+/// <section class="turn">
+/// <div class="bubble user">
+/// <div class="speaker">You · 12:00:05</div>
+/// <div class="prompt">Review @src/main.rs</div>
+/// <p class="meta">Files: src/main.rs (lines 1-120)</p>
+/// </div>
+/// <div class="bubble assistant">
+/// <div class="speaker">Claude (opus)</div>
+/// <div class="response"><p>The error handling in <code>main</code> ...</p></div>
+/// <p class="meta">Rated 4/5</p>
+/// </div>
+/// </section>
+/// </main>
+/// ```
+pub fn to_html(transcript: &Transcript) -> String {
+    let started = transcript.started().format("%Y-%m-%d %H:%M");
+    let turns = if transcript.len() == 1 { "turn" } else { "turns" };
+
+    let mut page = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    page.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    let _ = writeln!(page, "<title>Claude Dialog transcript, {}</title>", started);
+    let _ = writeln!(page, "<style>{}</style>\n</head>\n<body>\n<main>", STYLE);
+    page.push_str("<h1>Claude Dialog transcript</h1>\n");
+    let _ = writeln!(page, "<p class=\"summary\">Started {}, {} {}</p>", started, transcript.len(), turns);
+
+    for entry in transcript.entries() {
+        page.push_str("<section class=\"turn\">\n<div class=\"bubble user\">\n");
+        let mut speaker = format!("You · {}", entry.time.format("%H:%M:%S"));
+        if let Some(origin) = &entry.turn.origin {
+            speaker = format!("{} · {}", speaker, escape(origin));
+        }
+        let _ = writeln!(page, "<div class=\"speaker\">{}</div>", speaker);
+        let _ = writeln!(page, "<div class=\"prompt\">{}</div>", escape(entry.turn.prompt.trim_end()));
+        if !entry.turn.sources.is_empty() {
+            let files: Vec<String> = entry.turn.sources.iter().map(ToString::to_string).collect();
+            let _ = writeln!(page, "<p class=\"meta\">Files: {}</p>", escape(&files.join(", ")));
+        }
+        if !entry.provenance.is_empty() {
+            let _ = writeln!(page, "<p class=\"meta\">Sent with: {}</p>", escape(&entry.provenance.to_string()));
+        }
+        page.push_str("</div>\n<div class=\"bubble assistant\">\n");
+
+        match &entry.model {
+            Some(model) => {
+                let _ = writeln!(page, "<div class=\"speaker\">Claude ({})</div>", escape(model));
+            }
+            None => page.push_str("<div class=\"speaker\">Claude</div>\n"),
+        }
+        match &entry.turn.response {
+            Some(response) => {
+                let _ = writeln!(page, "<div class=\"response\">\n{}</div>", markdown_to_html(response));
+            }
+            None => page.push_str("<p class=\"meta\">No response</p>\n"),
+        }
+        if let Some(rating) = entry.turn.rating {
+            let _ = writeln!(page, "<p class=\"meta\">Rated {}/{}</p>", rating, MAX_RATING);
+        }
+        for note in &entry.turn.notes {
+            let _ = writeln!(page, "<div class=\"note\"><strong>Note:</strong> {}</div>", escape(note.trim()));
+        }
+        page.push_str("</div>\n</section>\n");
+    }
+
+    page.push_str("</main>\n</body>\n</html>\n");
+    page
+}
+
+/// Write a transcript to an HTML file, replacing it if it exists
+///
+/// Missing parent directories are created.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn save(transcript: &Transcript, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    }
+    fs::write(path, to_html(transcript)).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// File name for a page exported without a path, e.g.
+/// `claude-dialog-20250101-120000.html`
+pub fn default_file_name(time: DateTime<Local>) -> PathBuf {
+    PathBuf::from(format!("claude-dialog-{}.html", time.format("%Y%m%d-%H%M%S")))
+}
+
+/// Render Markdown as HTML, with code blocks highlighted and raw HTML
+/// shown as text
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut events = Vec::new();
+    // Language and text of the code block being read
+    let mut code: Option<(String, String)> = None;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or_default().to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((language, String::new()));
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, body)) = &mut code {
+                    body.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, body)) = code.take() {
+                    events.push(Event::Html(code_block(&language, &body).into()));
+                }
+            }
+            Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+            event => events.push(event),
+        }
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    html
+}
+
+/// A code block as a `<pre>` element
+fn code_block(language: &str, code: &str) -> String {
+    let class = if language.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape(language))
+    };
+    format!("<pre><code{}>{}</code></pre>\n", class, highlighted(language, code.trim_end_matches('\n')))
+}
+
+/// The lines of a code block with their tokens colored, if the language is
+/// known
+#[cfg(feature = "highlight")]
+fn highlighted(language: &str, code: &str) -> String {
+    match CodeHighlighter::new(language) {
+        Some(mut highlighter) => code.lines().map(|line| highlighter.highlight_html(line)).collect::<Vec<_>>().join("\n"),
+        None => escape(code),
+    }
+}
+
+/// The lines of a code block, escaped
+#[cfg(not(feature = "highlight"))]
+fn highlighted(_language: &str, code: &str) -> String {
+    escape(code)
+}
+
+/// Escape text for HTML element content and quoted attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::FileInclusion;
+    use crate::conversation::Turn;
+
+    #[test]
+    fn test_page_has_a_bubble_per_speaker() {
+        let mut transcript = Transcript::new();
+        transcript.record(Turn::new("Review @notes.md").with_sources(vec![FileInclusion::whole("notes.md", 3)]), None);
+        transcript.set_response("Looks good.");
+        transcript.rate(4);
+        transcript.add_note("checked <by hand>");
+        transcript.record(Turn::new("Thanks"), Some("haiku"));
+
+        let page = to_html(&transcript);
+        assert_eq!(page.matches("<div class=\"bubble user\">").count(), 2);
+        assert_eq!(page.matches("<div class=\"bubble assistant\">").count(), 2);
+        assert!(page.contains("<p class=\"meta\">Files: notes.md (lines 1-3)</p>"));
+        assert!(page.contains("<p class=\"meta\">Rated 4/5</p>\n<div class=\"note\"><strong>Note:</strong> checked &lt;by hand&gt;</div>"));
+        assert!(page.contains("<div class=\"speaker\">Claude (haiku)</div>\n<p class=\"meta\">No response</p>"));
+        assert!(page.ends_with("</main>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_html_in_responses_is_not_run() {
+        let html = markdown_to_html("<script>alert(1)</script>\n\nSee <img src=x onerror=alert(1)>");
+        assert!(!html.contains("<script>") && !html.contains("<img"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    }
+
+    #[test]
+    fn test_code_blocks_keep_their_text() {
+        let html = markdown_to_html("```rust\nlet s = \"<&>\";\n```\n\n```\nplain < text\n```\n");
+        assert!(html.contains("<pre><code class=\"language-rust\">"));
+        assert!(html.contains("<pre><code>plain &lt; text</code></pre>"));
+        let tags = regex::Regex::new("<[^>]*>").unwrap();
+        assert!(tags.replace_all(&html, "").contains("let s = &quot;&lt;&amp;&gt;&quot;;"));
+    }
+
+    #[cfg(feature = "highlight")]
+    #[test]
+    fn test_code_blocks_are_highlighted() {
+        let html = markdown_to_html("```python\ndef add(a, b):\n    return a + b\n```");
+        assert!(html.contains("<span style=\"color:#"));
+    }
+}
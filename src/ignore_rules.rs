@@ -0,0 +1,183 @@
+//! Ignore rules module
+//!
+//! Context that is gathered automatically, such as repository maps and the
+//! files matched by an `@src/*.rs` glob, and workspace snapshots respect
+//! the same rules as git:
+//!
+//! - `.gitignore` files in the directory and its parents, also outside of git
+//!   repositories
+//! - `.git/info/exclude` and the global gitignore file
+//! - `.claude-dialog-ignore` files, written like `.gitignore`, for files that
+//!   are tracked but should never be sent to Claude, e.g. `secrets/`
+//!
+//! Hidden files and directories are skipped as well, and so are `target` and
+//! `node_modules` directories, so build artifacts stay out of prompts even in
+//! projects without a `.gitignore`. Files mentioned by name are always sent.
+//! Workspace snapshots keep hidden files apart from the `.git` directory
+//! (see [`workspace_files`]).
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::ignore_rules;
+//! # let dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(dir.path().join(".claude-dialog-ignore"), "*.key\n").unwrap();
+//! # std::fs::write(dir.path().join("main.rs"), "").unwrap();
+//! # std::fs::write(dir.path().join("server.key"), "").unwrap();
+//!
+//! // `dir` contains main.rs and server.key, which .claude-dialog-ignore excludes
+//! let files: Vec<_> = ignore_rules::files(dir.path(), None).unwrap().collect();
+//! assert_eq!(files, vec![dir.path().join("main.rs")]);
+//! ```
+
+use anyhow::{Result, Context};
+use ignore::{DirEntry, WalkBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the ignore files specific to `claude-dialog`
+pub const IGNORE_FILE: &str = ".claude-dialog-ignore";
+
+/// Directories skipped even when no ignore file mentions them
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// Files below `root` that are not excluded by ignore rules, in path order
+///
+/// Directories are listed depth-first with their entries sorted by name.
+/// Entries that cannot be read are skipped.
+///
+/// # Arguments
+///
+/// * `root` - Directory to list
+/// * `max_depth` - How many directory levels to descend; `Some(1)` lists
+///   only the files directly in `root`
+///
+/// # Errors
+///
+/// Returns an error if `root` is not a readable directory.
+pub fn files(root: &Path, max_depth: Option<usize>) -> Result<impl Iterator<Item = PathBuf>> {
+    Ok(walk(root, max_depth, true)?
+        .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.into_path()))
+}
+
+/// Files and symbolic links of a workspace below `root`, in path order
+///
+/// Like [`files`], but hidden files such as `.env` or those in `.github/`
+/// are included, as a workspace snapshot would miss edits to them otherwise.
+/// Only the `.git` directory is left out. Symbolic links are listed rather
+/// than followed.
+///
+/// # Errors
+///
+/// Returns an error if `root` is not a readable directory.
+pub fn workspace_files(root: &Path) -> Result<impl Iterator<Item = PathBuf>> {
+    Ok(walk(root, None, false)?
+        .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file() || file_type.is_symlink()))
+        .map(|entry| entry.into_path()))
+}
+
+/// The entries below `root` that are not ignored, skipping hidden ones
+/// when `skip_hidden`
+fn walk(root: &Path, max_depth: Option<usize>, skip_hidden: bool) -> Result<impl Iterator<Item = DirEntry>> {
+    fs::read_dir(root).with_context(|| format!("Failed to read directory: {}", root.display()))?;
+
+    let walk = WalkBuilder::new(root)
+        .max_depth(max_depth)
+        .hidden(skip_hidden)
+        .require_git(false)
+        .ignore(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            let skipped_dir = entry.depth() > 0
+                && entry.file_type().is_some_and(|file_type| file_type.is_dir())
+                && (SKIPPED_DIRS.iter().any(|name| entry.file_name() == *name) || entry.file_name() == ".git");
+            !skipped_dir
+        })
+        .build();
+
+    Ok(walk.filter_map(|entry| entry.ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(root: &Path) -> Vec<String> {
+        files(root, None)
+            .unwrap()
+            .map(|path| path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn test_ignore_files_are_respected() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            "src/main.rs",
+            "src/generated.rs",
+            "dist/bundle.js",
+            "secrets/token.txt",
+            "notes.md",
+            ".env",
+            "target/debug/app",
+            "web/node_modules/x/index.js",
+        ];
+        for path in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        fs::write(dir.path().join(".gitignore"), "dist/\n").unwrap();
+        fs::write(dir.path().join(IGNORE_FILE), "secrets/\n").unwrap();
+        fs::write(dir.path().join("src/.gitignore"), "generated.rs\n").unwrap();
+
+        assert_eq!(listed(dir.path()), vec!["notes.md", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_negated_patterns_include_files_again() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "").unwrap();
+        fs::write(dir.path().join("keep.log"), "").unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+        fs::write(dir.path().join(IGNORE_FILE), "!keep.log\n").unwrap();
+
+        assert_eq!(listed(dir.path()), vec!["keep.log"]);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("top.rs"), "").unwrap();
+        fs::write(dir.path().join("src/nested.rs"), "").unwrap();
+
+        let top: Vec<_> = files(dir.path(), Some(1)).unwrap().collect();
+        assert_eq!(top, vec![dir.path().join("top.rs")]);
+    }
+
+    #[test]
+    fn test_workspace_files_include_hidden_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in [".env", ".github/workflows/ci.yml", ".git/HEAD", "main.rs", "debug.log"] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+
+        let listed: Vec<String> = workspace_files(dir.path())
+            .unwrap()
+            .map(|path| path.strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert_eq!(listed, [".env", ".github/workflows/ci.yml", ".gitignore", "main.rs"]);
+    }
+
+    #[test]
+    fn test_missing_root_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(files(&dir.path().join("missing"), None).is_err());
+    }
+}
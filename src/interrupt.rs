@@ -0,0 +1,183 @@
+//! Interrupt module
+//!
+//! Ctrl+C while Claude is answering cancels the turn instead of ending the
+//! session: the future running the turn is dropped, which kills the `claude`
+//! process (commands are spawned with `kill_on_drop`) or, with the Ollama
+//! and API backends, stops the streamed answer, and the dialog loop returns
+//! to the `You>` prompt. At the `You>` prompt and at confirmations, the line
+//! editor reads Ctrl+C itself (see [`crate::line_editor`]). One that
+//! arrives while nothing is read and no turn is running, e.g. while a
+//! checkpoint is replayed, asks the dialog loop to end the session as soon
+//! as it is done, saving it as `exit` would; pressing Ctrl+C again before
+//! then ends the program at once.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::interrupt::Interrupt;
+//! use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let interrupt = Interrupt::new();
+//!
+//! let answer = interrupt.guard(async { 42 }).await;
+//! assert_eq!(answer, Some(42));
+//!
+//! let canceller = interrupt.clone();
+//! tokio::spawn(async move {
+//!     tokio::time::sleep(Duration::from_millis(10)).await;
+//!     canceller.cancel();
+//! });
+//! let slow = interrupt.guard(tokio::time::sleep(Duration::from_secs(60))).await;
+//! assert_eq!(slow, None);
+//! # }
+//! ```
+
+use std::future::Future;
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+use crate::ui::UI;
+
+/// Exit code of a program ended by Ctrl+C, as shells report it
+pub const EXIT_CODE: i32 = 130;
+
+#[derive(Debug, Default)]
+struct State {
+    running: AtomicBool,
+    cancelled: AtomicBool,
+    quit: AtomicBool,
+    notify: Notify,
+}
+
+/// Cancels the running turn when Ctrl+C is pressed
+///
+/// Clones share the turn they guard, so one can cancel what another runs.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    state: Arc<State>,
+}
+
+impl Interrupt {
+    /// Create an interrupt that cancels turns only through
+    /// [`Interrupt::cancel`] until it [listens](Interrupt::listen) for Ctrl+C
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the running turn whenever Ctrl+C is pressed
+    ///
+    /// Ctrl+C while no turn is running [requests to quit](Interrupt::request_quit);
+    /// pressed again before the request is picked up, it exits with
+    /// [`EXIT_CODE`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn listen(&self) {
+        let listener = self.clone();
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if listener.cancel() {
+                    continue;
+                }
+                println!();
+                if listener.request_quit() {
+                    process::exit(EXIT_CODE);
+                }
+                UI::print_info("Ending the session once this is done; press Ctrl+C again to quit at once");
+            }
+        });
+    }
+
+    /// Run `turn` until it completes or is cancelled
+    ///
+    /// # Returns
+    ///
+    /// * `Some(output)` - The output of the turn
+    /// * `None` - The turn was cancelled; it has been dropped, also if it
+    ///   completed at the same time
+    pub async fn guard<F: Future>(&self, turn: F) -> Option<F::Output> {
+        let notified = self.state.notify.notified();
+        tokio::pin!(notified);
+        // Registered before the turn starts, so no cancellation is missed
+        notified.as_mut().enable();
+
+        self.state.cancelled.store(false, Ordering::SeqCst);
+        self.state.running.store(true, Ordering::SeqCst);
+        let output = tokio::select! {
+            output = turn => Some(output),
+            _ = notified => None,
+        };
+        self.state.running.store(false, Ordering::SeqCst);
+
+        // The `claude` process receives Ctrl+C too and may exit before the
+        // notification arrives; its failure is a cancellation as well
+        if self.state.cancelled.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        output
+    }
+
+    /// Cancel the running turn
+    ///
+    /// # Returns
+    ///
+    /// `true` if a turn was running, `false` if there was nothing to cancel.
+    pub fn cancel(&self) -> bool {
+        if !self.state.running.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+        true
+    }
+
+    /// Ask the dialog loop to end the session when it is next able to
+    ///
+    /// # Returns
+    ///
+    /// `true` if quitting had been requested already.
+    pub fn request_quit(&self) -> bool {
+        self.state.quit.swap(true, Ordering::SeqCst)
+    }
+
+    /// Whether quitting was requested with [`Interrupt::request_quit`]
+    pub fn quit_requested(&self) -> bool {
+        self.state.quit.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nothing_to_cancel_between_turns() {
+        let interrupt = Interrupt::new();
+        assert!(!interrupt.cancel());
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_does_not_outlive_its_turn() {
+        let interrupt = Interrupt::new();
+        let canceller = interrupt.clone();
+        let turn = async move {
+            assert!(canceller.cancel());
+            "done"
+        };
+        assert_eq!(interrupt.guard(turn).await, None);
+        assert_eq!(interrupt.guard(async { "next" }).await, Some("next"));
+    }
+
+    #[test]
+    fn test_quit_requests() {
+        let interrupt = Interrupt::new();
+        assert!(!interrupt.quit_requested());
+        assert!(!interrupt.clone().request_quit());
+        assert!(interrupt.quit_requested());
+        assert!(interrupt.request_quit());
+    }
+}
@@ -0,0 +1,517 @@
+//! Session journal module for crash recovery
+//!
+//! Every turn of a session is appended to an on-disk journal as soon as it is
+//! sent, so a crash, dropped SSH connection or power loss loses nothing. The
+//! journal is removed when the session ends normally. A running session holds
+//! an exclusive lock on its journal; a journal that can be locked on the next
+//! start therefore belongs to a session that did not exit cleanly and can be
+//! offered for recovery.
+//!
+//! Journals are JSON Lines files stored in the state directory (see
+//! [`Paths::journal_dir`](crate::paths::Paths::journal_dir)). The start
+//! entry records the journal's format version (see [`crate::migrate`]):
+//! journals of older releases are migrated as they are read, and journals
+//! of newer releases are left alone for the release that wrote them.
+//!
+//! The journal also records the ID the Claude CLI gives the session, so a
+//! recovered conversation can be continued where Claude left it instead of
+//! starting over without its context.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use claude_dialog::conversation::Turn;
+//! use claude_dialog::journal::{self, Journal};
+//! use claude_dialog::paths::Paths;
+//!
+//! let dir = Paths::resolve().unwrap().journal_dir();
+//!
+//! if let Some(unsaved) = journal::find_unsaved(&dir).unwrap() {
+//!     println!("Found {} unsaved turn(s)", unsaved.conversation.len());
+//! }
+//!
+//! let mut journal = Journal::create(&dir).unwrap();
+//! journal.append(&Turn::new("Hello, Claude!")).unwrap();
+//! journal.discard().unwrap();
+//! ```
+
+use anyhow::{Result, Context};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use crate::context::FileInclusion;
+use crate::conversation::{Conversation, Turn};
+use crate::migrate::{Migration, Schema};
+use crate::paths;
+
+/// File name prefix shared by all journals
+const JOURNAL_PREFIX: &str = "journal-";
+
+/// File name extension shared by all journals
+const JOURNAL_EXTENSION: &str = "jsonl";
+
+/// Format of journal entries and the migrations from older formats
+pub static SCHEMA: Schema<serde_json::Value> = Schema {
+    name: "the journal",
+    version: 1,
+    migrations: &[Migration {
+        from: 0,
+        summary: "added the format version to the start entry",
+        apply: |_| Ok(()),
+    }],
+};
+
+/// A single line of a journal file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Entry {
+    /// The session started
+    Start {
+        timestamp: DateTime<Local>,
+        #[serde(default)]
+        version: u32,
+    },
+
+    /// A turn was sent to Claude
+    Turn {
+        timestamp: DateTime<Local>,
+        prompt: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        sources: Vec<FileInclusion>,
+    },
+
+    /// Claude finished responding to the most recent turn
+    Response { text: String },
+
+    /// A note was attached to the most recent turn
+    Note { text: String },
+
+    /// The response to the most recent turn was rated
+    Rating { rating: u8 },
+
+    /// The conversation was rolled back to the given number of turns
+    Truncate { turns: usize },
+
+    /// The Claude CLI reported the ID of the session the turns are sent in
+    Session { id: String },
+}
+
+/// Append-only journal of the current session
+///
+/// The journal keeps an exclusive lock on its file for as long as it is
+/// alive, marking the session as running.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// Create a new journal for the current session in `dir`
+    ///
+    /// The directory is created if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or journal file cannot be created or locked.
+    pub fn create(dir: &Path) -> Result<Self> {
+        paths::create_private_dir(dir)
+            .with_context(|| format!("Failed to create journal directory: {}", dir.display()))?;
+
+        let started = Local::now();
+        let path = dir.join(format!(
+            "{}{}-{}.{}",
+            JOURNAL_PREFIX,
+            started.format("%Y%m%d-%H%M%S"),
+            process::id(),
+            JOURNAL_EXTENSION
+        ));
+
+        // Read access lets spilled turns be read back through the locked file
+        let file = paths::private_file()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create journal: {}", path.display()))?;
+        file.try_lock()
+            .with_context(|| format!("Failed to lock journal: {}", path.display()))?;
+
+        let mut journal = Self { path, file };
+        journal.write(&Entry::Start {
+            timestamp: started,
+            version: SCHEMA.version,
+        })?;
+        Ok(journal)
+    }
+
+    /// Path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a turn, including its response if it already has one
+    ///
+    /// The entry is flushed to disk before returning.
+    pub fn append(&mut self, turn: &Turn) -> Result<()> {
+        self.write(&Entry::Turn {
+            timestamp: Local::now(),
+            prompt: turn.prompt.clone(),
+            sources: turn.sources.clone(),
+        })?;
+
+        if let Some(response) = &turn.response {
+            self.append_response(response)?;
+        }
+        if let Some(rating) = turn.rating {
+            self.append_rating(rating)?;
+        }
+        for note in &turn.notes {
+            self.append_note(note)?;
+        }
+        Ok(())
+    }
+
+    /// Record Claude's response to the most recent turn
+    pub fn append_response(&mut self, text: &str) -> Result<()> {
+        self.write(&Entry::Response { text: text.to_string() })
+    }
+
+    /// Record a note attached to the most recent turn
+    pub fn append_note(&mut self, text: &str) -> Result<()> {
+        self.write(&Entry::Note { text: text.to_string() })
+    }
+
+    /// Record a rating of the response to the most recent turn
+    pub fn append_rating(&mut self, rating: u8) -> Result<()> {
+        self.write(&Entry::Rating { rating })
+    }
+
+    /// Record that the conversation was rolled back to `turns` turns
+    pub fn truncate(&mut self, turns: usize) -> Result<()> {
+        self.write(&Entry::Truncate { turns })
+    }
+
+    /// Record the ID of the Claude CLI session the turns are sent in, so a
+    /// recovered session can resume it
+    pub fn append_session(&mut self, id: &str) -> Result<()> {
+        self.write(&Entry::Session { id: id.to_string() })
+    }
+
+    /// The conversation as journaled so far, including turns spilled from
+    /// memory (see [`Conversation::spill`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal cannot be read.
+    pub fn conversation(&self) -> Result<Conversation> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0))
+            .with_context(|| format!("Failed to read journal: {}", self.path.display()))?;
+        let replayed = replay(file)?
+            .with_context(|| format!("Failed to read journal: {}", self.path.display()))?;
+        Ok(replayed.conversation)
+    }
+
+    /// Remove the journal after the session ended normally
+    pub fn discard(self) -> Result<()> {
+        fs::remove_file(&self.path)
+            .with_context(|| format!("Failed to remove journal: {}", self.path.display()))
+    }
+
+    fn write(&mut self, entry: &Entry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("Failed to write journal: {}", self.path.display()))
+    }
+}
+
+/// A session recovered from the journal of a previous run
+#[derive(Debug)]
+pub struct UnsavedSession {
+    /// Path of the orphaned journal
+    pub path: PathBuf,
+
+    /// When the session started
+    pub started: DateTime<Local>,
+
+    /// The turns recorded before the session ended
+    pub conversation: Conversation,
+
+    /// The Claude CLI session the turns were last sent in, if one was
+    /// recorded since the conversation was last rolled back
+    pub claude_session: Option<String>,
+}
+
+impl UnsavedSession {
+    /// Remove the orphaned journal, whether or not it was recovered
+    pub fn discard(self) -> Result<()> {
+        fs::remove_file(&self.path)
+            .with_context(|| format!("Failed to remove journal: {}", self.path.display()))
+    }
+}
+
+/// Find the most recent journal left behind by a session that did not exit cleanly
+///
+/// Journals of sessions that are still running (and therefore locked) and
+/// journals written by a newer release are ignored, as are journals without
+/// any turns, which are removed.
+///
+/// # Errors
+///
+/// Returns an error if the directory exists but cannot be read, or if a
+/// journal cannot be parsed.
+pub fn find_unsaved(dir: &Path) -> Result<Option<UnsavedSession>> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+
+    let mut journals = Vec::new();
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("Failed to read journal directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let is_journal = path.extension().is_some_and(|ext| ext == JOURNAL_EXTENSION)
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(JOURNAL_PREFIX));
+        if is_journal {
+            journals.push(path);
+        }
+    }
+
+    // File names embed the start time, so the newest journal sorts last
+    journals.sort();
+
+    while let Some(path) = journals.pop() {
+        let Some(session) = read_orphan(&path)? else {
+            continue;
+        };
+
+        if session.conversation.is_empty() {
+            session.discard()?;
+            continue;
+        }
+
+        return Ok(Some(session));
+    }
+
+    Ok(None)
+}
+
+/// Read a journal if no running session holds its lock and it was not
+/// written by a newer release
+fn read_orphan(path: &Path) -> Result<Option<UnsavedSession>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open journal: {}", path.display()))?;
+    if file.try_lock().is_err() {
+        return Ok(None);
+    }
+
+    let Some(replayed) = replay(&file)? else {
+        return Ok(None);
+    };
+    let started = match replayed.started {
+        Some(started) => started,
+        None => fs::metadata(path)?.modified()?.into(),
+    };
+
+    Ok(Some(UnsavedSession {
+        path: path.to_path_buf(),
+        started,
+        conversation: replayed.conversation,
+        claude_session: replayed.claude_session,
+    }))
+}
+
+/// What replaying a journal found
+#[derive(Debug, Default)]
+struct Replayed {
+    // When the session started, if the journal records it
+    started: Option<DateTime<Local>>,
+    conversation: Conversation,
+    claude_session: Option<String>,
+}
+
+/// Rebuild the session recorded in a journal
+///
+/// Returns `None` for a journal written by a newer release.
+fn replay(journal: impl Read) -> Result<Option<Replayed>> {
+    let mut replayed = Replayed::default();
+    let mut version = 0;
+
+    for line in BufReader::new(journal).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // A crash may leave a partially written last line behind
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&line) else {
+            break;
+        };
+        if value["event"] == "start" {
+            version = value["version"].as_u64().map_or(0, |version| version.try_into().unwrap_or(u32::MAX));
+        }
+        if version > SCHEMA.version {
+            return Ok(None);
+        }
+        SCHEMA.migrate(&mut value, version)?;
+        let Ok(entry) = serde_json::from_value::<Entry>(value) else {
+            break;
+        };
+
+        let conversation = &mut replayed.conversation;
+        match entry {
+            Entry::Start { timestamp, .. } => replayed.started = Some(timestamp),
+            Entry::Turn { prompt, sources, .. } => {
+                conversation.push_turn(Turn::new(prompt).with_sources(sources))
+            }
+            Entry::Response { text } => conversation.set_response(text),
+            Entry::Note { text } => {
+                conversation.add_note(text);
+            }
+            Entry::Rating { rating } => {
+                conversation.rate(rating);
+            }
+            // Rolling back replays the remaining turns into a new session
+            Entry::Truncate { turns } => {
+                conversation.truncate(turns);
+                replayed.claude_session = None;
+            }
+            Entry::Session { id } => replayed.claude_session = Some(id),
+        }
+    }
+
+    Ok(Some(replayed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_format() {
+        let line = serde_json::to_string(&Entry::Truncate { turns: 2 }).unwrap();
+        assert_eq!(line, r#"{"event":"truncate","turns":2}"#);
+    }
+
+    #[test]
+    fn test_journals_of_newer_releases_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal-20240101-103200-1.jsonl");
+        let lines = [
+            r#"{"event":"start","timestamp":"2024-01-01T10:32:00+00:00","version":2}"#,
+            r#"{"event":"turn","timestamp":"2024-01-01T10:32:00+00:00","prompt":"one"}"#,
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        assert!(find_unsaved(dir.path()).unwrap().is_none());
+        assert!(path.exists());
+
+        fs::write(&path, lines.join("\n").replace(r#","version":2"#, "")).unwrap();
+        let unsaved = find_unsaved(dir.path()).unwrap().unwrap();
+        assert_eq!(unsaved.conversation.len(), 1);
+    }
+
+    #[test]
+    fn test_conversation_is_read_back_while_journaling() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = Journal::create(dir.path()).unwrap();
+        for prompt in ["one", "two", "three"] {
+            journal.append(&Turn::new(prompt)).unwrap();
+            journal.append_response("answer").unwrap();
+        }
+        journal.truncate(2).unwrap();
+
+        let conversation = journal.conversation().unwrap();
+        assert_eq!(conversation.prompts(), vec!["one", "two"]);
+        assert_eq!(conversation.turns()[1].response.as_deref(), Some("answer"));
+
+        journal.append(&Turn::new("four")).unwrap();
+        assert_eq!(journal.conversation().unwrap().len(), 3);
+        journal.discard().unwrap();
+    }
+
+    #[test]
+    fn test_running_journal_is_not_unsaved() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = Journal::create(dir.path()).unwrap();
+        journal.append(&Turn::new("hello")).unwrap();
+
+        assert!(find_unsaved(dir.path()).unwrap().is_none());
+
+        journal.discard().unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_journal_is_readable_only_by_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::create(&dir.path().join("state")).unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir.path().join("state")), 0o700);
+        assert_eq!(mode(journal.path()), 0o600);
+        journal.discard().unwrap();
+    }
+
+    #[test]
+    fn test_notes_and_ratings_are_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal-20240101-103200-1.jsonl");
+        let lines = [
+            r#"{"event":"turn","timestamp":"2024-01-01T10:32:00+00:00","prompt":"one"}"#,
+            r#"{"event":"response","text":"Answer"}"#,
+            r#"{"event":"rating","rating":2}"#,
+            r#"{"event":"note","text":"wrong"}"#,
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let unsaved = find_unsaved(dir.path()).unwrap().unwrap();
+        assert_eq!(unsaved.conversation.turns()[0].notes, ["wrong"]);
+        assert_eq!(unsaved.conversation.turns()[0].rating, Some(2));
+    }
+
+    #[test]
+    fn test_claude_session_is_recovered_until_rolled_back() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut journal = Journal::create(dir.path()).unwrap();
+            journal.append(&Turn::new("one")).unwrap();
+            journal.append_session("3f2a9c1e").unwrap();
+        }
+        let unsaved = find_unsaved(dir.path()).unwrap().unwrap();
+        assert_eq!(unsaved.claude_session.as_deref(), Some("3f2a9c1e"));
+
+        let mut journal = Journal::create(dir.path()).unwrap();
+        journal.append(&Turn::new("one")).unwrap();
+        journal.append_session("3f2a9c1e").unwrap();
+        journal.truncate(0).unwrap();
+        assert_eq!(replay(fs::File::open(journal.path()).unwrap()).unwrap().unwrap().claude_session, None);
+    }
+
+    #[test]
+    fn test_partial_last_line_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal-20240101-103200-1.jsonl");
+        fs::write(
+            &path,
+            "{\"event\":\"turn\",\"timestamp\":\"2024-01-01T10:32:00+00:00\",\"prompt\":\"one\"}\n{\"event\":\"tu",
+        )
+        .unwrap();
+
+        let unsaved = find_unsaved(dir.path()).unwrap().unwrap();
+        assert_eq!(unsaved.conversation.prompts(), vec!["one"]);
+    }
+}
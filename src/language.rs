@@ -0,0 +1,358 @@
+//! Language module
+//!
+//! Recognizes the language a prompt is written in, so that a session
+//! configured for one response language (`--language`) can still be
+//! answered in another when the user switches languages mid-session: a
+//! prompt detected in a different language gets a hint asking Claude to
+//! reply in it.
+//!
+//! Detection is a lightweight heuristic, not a classifier. Scripts such as
+//! kana, Hangul or Cyrillic identify a language on their own; Latin-script
+//! languages are told apart by their most frequent short words. Code blocks,
+//! inline code, `@file` references and URLs are ignored, and prompts too
+//! short to tell are not classified at all, so a hint is only added when the
+//! language is clear.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::language::Language;
+//!
+//! assert_eq!(Language::detect("Wie kann ich diese Funktion testen, ohne die Datei zu ändern?"), Some(Language::German));
+//! assert_eq!(Language::detect("このエラーの原因を教えてください"), Some(Language::Japanese));
+//! assert_eq!(Language::detect("ok"), None);
+//!
+//! let language: Language = "ja".parse().unwrap();
+//! assert_eq!(language.hint(), "(Reply in Japanese, the language of this message.)");
+//! ```
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Letters a prompt needs before its language is detected
+const MIN_LETTERS: usize = 12;
+
+/// Frequent words a Latin-script language needs before it is detected
+const MIN_WORD_HITS: usize = 2;
+
+/// A language prompts can be recognized in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Portuguese,
+    Dutch,
+    Russian,
+    Greek,
+    Arabic,
+    Hebrew,
+    Hindi,
+    Thai,
+    Chinese,
+    Japanese,
+    Korean,
+}
+
+/// Every language with its ISO 639-1 code and English name
+const LANGUAGES: &[(Language, &str, &str)] = &[
+    (Language::English, "en", "English"),
+    (Language::German, "de", "German"),
+    (Language::French, "fr", "French"),
+    (Language::Spanish, "es", "Spanish"),
+    (Language::Italian, "it", "Italian"),
+    (Language::Portuguese, "pt", "Portuguese"),
+    (Language::Dutch, "nl", "Dutch"),
+    (Language::Russian, "ru", "Russian"),
+    (Language::Greek, "el", "Greek"),
+    (Language::Arabic, "ar", "Arabic"),
+    (Language::Hebrew, "he", "Hebrew"),
+    (Language::Hindi, "hi", "Hindi"),
+    (Language::Thai, "th", "Thai"),
+    (Language::Chinese, "zh", "Chinese"),
+    (Language::Japanese, "ja", "Japanese"),
+    (Language::Korean, "ko", "Korean"),
+];
+
+/// Frequent words of the Latin-script languages, in lowercase
+const FREQUENT_WORDS: &[(Language, &[&str])] = &[
+    (Language::English, &[
+        "the", "is", "are", "and", "of", "to", "it", "this", "that", "what", "how", "why", "with",
+        "for", "you", "can", "does", "not", "be", "should", "my", "i", "do", "there", "which",
+    ]),
+    (Language::German, &[
+        "der", "die", "das", "und", "ist", "nicht", "ich", "wie", "was", "warum", "mit", "ein",
+        "eine", "für", "auf", "es", "sie", "diese", "dieser", "kann", "zu", "den", "dem",
+        "wird", "ohne", "auch", "bitte",
+    ]),
+    (Language::French, &[
+        "le", "la", "les", "et", "est", "une", "un", "des", "pour", "pas", "qui", "dans", "ce",
+        "cette", "je", "vous", "comment", "pourquoi", "avec", "sur", "du", "au", "il", "ne",
+        "mon", "ma", "quoi", "peux",
+    ]),
+    (Language::Spanish, &[
+        "el", "los", "las", "es", "por", "para", "una", "cómo", "qué", "porque", "con", "esto",
+        "está", "pero", "del", "se", "mi", "puedo", "hay", "esta", "este", "muy", "también",
+    ]),
+    (Language::Italian, &[
+        "il", "lo", "gli", "è", "che", "non", "per", "una", "sono", "come", "perché", "questo",
+        "questa", "della", "con", "mi", "si", "ho", "anche", "nel", "alla", "posso",
+    ]),
+    (Language::Portuguese, &[
+        "os", "é", "não", "um", "uma", "para", "com", "como", "isso", "está", "você", "do", "da",
+        "em", "no", "na", "mas", "meu", "minha", "posso", "também", "esse", "essa",
+    ]),
+    (Language::Dutch, &[
+        "de", "het", "een", "en", "is", "niet", "van", "wat", "hoe", "waarom", "dit", "dat",
+        "met", "voor", "ik", "je", "zijn", "kan", "op", "ook", "mijn", "deze", "wordt",
+    ]),
+];
+
+impl Language {
+    /// ISO 639-1 code, e.g. `ja`
+    pub fn code(self) -> &'static str {
+        Self::entry(self).1
+    }
+
+    /// English name, e.g. `Japanese`
+    pub fn name(self) -> &'static str {
+        Self::entry(self).2
+    }
+
+    fn entry(self) -> &'static (Language, &'static str, &'static str) {
+        LANGUAGES
+            .iter()
+            .find(|(language, _, _)| *language == self)
+            .expect("every language is listed")
+    }
+
+    /// Recognize the language `text` is written in
+    ///
+    /// # Returns
+    ///
+    /// `None` if the text is too short or its language is unclear
+    pub fn detect(text: &str) -> Option<Language> {
+        let prose = prose(text);
+        let mut scripts = ScriptCounts::default();
+        for c in prose.chars() {
+            scripts.add(c);
+        }
+        if scripts.letters() < MIN_LETTERS {
+            return None;
+        }
+        if let Some(language) = scripts.dominant() {
+            return Some(language);
+        }
+        latin_language(&prose)
+    }
+
+    /// Text asking Claude to reply in this language, appended to a prompt
+    pub fn hint(self) -> String {
+        format!("(Reply in {}, the language of this message.)", self.name())
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    /// Parse an ISO 639-1 code or English name, ignoring case
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        LANGUAGES
+            .iter()
+            .find(|(_, code, name)| code.eq_ignore_ascii_case(s) || name.eq_ignore_ascii_case(s))
+            .map(|(language, _, _)| *language)
+            .ok_or_else(|| {
+                let codes: Vec<&str> = LANGUAGES.iter().map(|(_, code, _)| *code).collect();
+                anyhow!("Unknown language '{}'; use one of {}", s, codes.join(", "))
+            })
+    }
+}
+
+impl TryFrom<String> for Language {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Language> for String {
+    fn from(language: Language) -> Self {
+        language.code().to_string()
+    }
+}
+
+/// Letters of each script in a text
+#[derive(Debug, Default)]
+struct ScriptCounts {
+    latin: usize,
+    kana: usize,
+    han: usize,
+    hangul: usize,
+    cyrillic: usize,
+    greek: usize,
+    arabic: usize,
+    hebrew: usize,
+    devanagari: usize,
+    thai: usize,
+}
+
+impl ScriptCounts {
+    fn add(&mut self, c: char) {
+        let count = match c {
+            'a'..='z' | 'A'..='Z' | '\u{C0}'..='\u{24F}' => &mut self.latin,
+            '\u{3040}'..='\u{30FF}' => &mut self.kana,
+            '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' => &mut self.han,
+            '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' => &mut self.hangul,
+            '\u{400}'..='\u{4FF}' => &mut self.cyrillic,
+            '\u{370}'..='\u{3FF}' => &mut self.greek,
+            '\u{600}'..='\u{6FF}' => &mut self.arabic,
+            '\u{590}'..='\u{5FF}' => &mut self.hebrew,
+            '\u{900}'..='\u{97F}' => &mut self.devanagari,
+            '\u{E00}'..='\u{E7F}' => &mut self.thai,
+            _ => return,
+        };
+        *count += 1;
+    }
+
+    fn letters(&self) -> usize {
+        // A CJK character carries about as much as a short word
+        let cjk = (self.kana + self.han + self.hangul) * 3;
+        self.latin + cjk + self.cyrillic + self.greek + self.arabic + self.hebrew + self.devanagari + self.thai
+    }
+
+    /// The language of the most used non-Latin script, if it outweighs
+    /// the Latin letters, e.g. of identifiers in the prompt
+    fn dominant(&self) -> Option<Language> {
+        let cjk = self.kana + self.han;
+        let candidates = [
+            // Japanese mixes kana into Han characters; Chinese has none
+            (if self.kana > 0 { Language::Japanese } else { Language::Chinese }, cjk * 3),
+            (Language::Korean, self.hangul * 3),
+            (Language::Russian, self.cyrillic),
+            (Language::Greek, self.greek),
+            (Language::Arabic, self.arabic),
+            (Language::Hebrew, self.hebrew),
+            (Language::Hindi, self.devanagari),
+            (Language::Thai, self.thai),
+        ];
+        candidates
+            .into_iter()
+            .filter(|&(_, count)| count > 0 && count >= self.latin)
+            .max_by_key(|&(_, count)| count)
+            .map(|(language, _)| language)
+    }
+}
+
+/// The Latin-script language whose frequent words occur most often in
+/// `prose`, if it clearly beats the others
+fn latin_language(prose: &str) -> Option<Language> {
+    let words: Vec<String> = prose
+        .split(|c: char| !c.is_alphabetic() && c != '\'')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut hits: Vec<(Language, usize)> = FREQUENT_WORDS
+        .iter()
+        .map(|(language, frequent)| {
+            let count = words.iter().filter(|word| frequent.contains(&word.as_str())).count();
+            (*language, count)
+        })
+        .collect();
+    hits.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    match hits.as_slice() {
+        [(language, best), (_, second), ..] if *best >= MIN_WORD_HITS && best > second => Some(*language),
+        _ => None,
+    }
+}
+
+/// `text` without fenced code blocks, inline code, `@file` references and URLs
+pub(crate) fn prose(text: &str) -> String {
+    let mut prose = String::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_block = !in_block;
+            continue;
+        }
+        if in_block {
+            continue;
+        }
+
+        // Odd parts are between backticks
+        for (index, part) in line.split('`').enumerate() {
+            if index % 2 == 1 {
+                continue;
+            }
+            let words = part
+                .split_whitespace()
+                .filter(|word| !word.starts_with('@') && !word.contains("://"));
+            for word in words {
+                prose.push_str(word);
+                prose.push(' ');
+            }
+        }
+        prose.push('\n');
+    }
+    prose
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_latin_languages() {
+        let samples = [
+            ("How do I read a JSON file and what should I do with the errors?", Language::English),
+            ("Warum ist dieser Test nicht stabil, und wie kann ich das beheben?", Language::German),
+            ("Pourquoi est-ce que cette fonction ne compile pas avec la nouvelle version ?", Language::French),
+            ("¿Cómo puedo leer un archivo JSON y qué hago con los errores?", Language::Spanish),
+            ("Perché questo test non è stabile e come posso sistemarlo?", Language::Italian),
+            ("Como posso ler um arquivo JSON? Isso não está funcionando para mim.", Language::Portuguese),
+            ("Hoe kan ik dit bestand lezen en waarom werkt het niet met de nieuwe versie?", Language::Dutch),
+        ];
+        for (text, language) in samples {
+            assert_eq!(Language::detect(text), Some(language), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_detects_scripts() {
+        assert_eq!(Language::detect("Почему этот тест падает на сервере?"), Some(Language::Russian));
+        assert_eq!(Language::detect("为什么这个测试在服务器上失败"), Some(Language::Chinese));
+        assert_eq!(Language::detect("この関数はなぜ遅いのですか"), Some(Language::Japanese));
+        assert_eq!(Language::detect("이 함수가 왜 느린가요"), Some(Language::Korean));
+        assert_eq!(Language::detect("`parse_config` が `None` を返すのはなぜ？"), Some(Language::Japanese));
+    }
+
+    #[test]
+    fn test_ignores_code_and_unclear_text() {
+        assert_eq!(Language::detect("fix it"), None);
+        assert_eq!(Language::detect("@src/main.rs @src/lib.rs"), None);
+        assert_eq!(Language::detect("```\nfn main() { println!(\"the is and of\"); }\n```"), None);
+        assert_eq!(Language::detect("Refactor parse_config_file into smaller helpers"), None);
+    }
+
+    #[test]
+    fn test_parse_language() {
+        assert_eq!("DE".parse::<Language>().unwrap(), Language::German);
+        assert_eq!("japanese".parse::<Language>().unwrap(), Language::Japanese);
+        assert!("klingon".parse::<Language>().unwrap_err().to_string().contains("use one of en, de"));
+        assert_eq!(String::from(Language::Korean), "ko");
+    }
+}
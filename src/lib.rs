@@ -9,9 +9,62 @@
 //! The library is organized into the following modules:
 //!
 //! - [`cli`]: Command-line argument parsing and validation
+//! - [`config`]: Configuration file defaults and profiles
+//! - [`policy`]: Organization limits that settings and options cannot lift
 //! - [`prompt`]: System prompt configuration and loading
+//! - [`preset`]: Library of named system prompts
 //! - [`claude_executor`]: Claude command building and execution
+//! - [`chat`]: Conversation history shared by the stateless backends
+//! - [`ollama`]: Local Ollama server as an alternative backend
+//! - [`api`]: Anthropic Messages API as an alternative backend
+//! - [`router`]: Dispatch of commands to the executor of their backend
+//! - [`persona`]: Named backends and models that turns can be routed to
+//! - [`claude_version`]: Claude CLI version detection and capability gating
+//! - [`pipeline`]: Fan-out of streamed responses to concurrent sinks
+//! - [`markdown`]: Rendering of Markdown responses for the terminal
+//! - [`highlight`]: Syntax highlighting of code blocks in responses
+//! - [`mask`]: Masking of words, patterns and personal data in displayed responses
+//! - [`stream_json`]: Parsing of the Claude CLI's `stream-json` events
+//! - [`rate_limit`]: Recognition of rate limits and waiting before retries
+//! - [`interrupt`]: Cancellation of the running turn with Ctrl+C
+//! - [`quiz`]: Flashcard sessions with hidden answers and a timer
+//! - [`present`]: Playback of saved conversations for demos and talks
+//! - [`viewer`]: Scrollable, searchable viewer of saved conversations
+//! - [`cast`]: asciinema recordings of sessions
+//! - [`commands`]: Slash command parsing
+//! - [`context`]: Inclusion of `@file` references in prompts
+//! - [`language`]: Detection of the language a prompt is written in
+//! - [`metadata`]: Time, working directory and OS footer for prompts
+//! - [`follow_up`]: Suggested follow-up questions picked by number
+//! - [`selection`]: Lists picked from by typing a number at the prompt
+//! - [`palette`]: The Ctrl+P command palette of slash commands, presets and
+//!   recent prompts
+//! - [`spelling`]: Local check of prompts for likely typos before sending
+//! - [`unfinished`]: Confirmation before sending prompts that look unfinished
+//! - [`chunk`]: Selection of line ranges, symbols and sections in `@file` references
+//! - [`repo_map`]: Condensed outline of a repository for context
+//! - [`attach`]: Whole files sent with the next prompt
+//! - [`mcp`]: MCP servers passed on to the Claude CLI and listed by `/mcp`
+//! - [`clipboard`]: `/copy` and `/paste` through the system clipboard
+//! - [`compose`]: Prompts written in an external editor with `/edit` or Ctrl+X Ctrl+E
+//! - [`ignore_rules`]: `.gitignore` and `.claude-dialog-ignore` rules for gathered context
+//! - [`history`]: Input history file with privacy controls
+//! - [`line_editor`]: Line editing and history recall at the input prompt
+//! - [`conversation`]: In-memory record of the turns in a session
+//! - [`status`]: Session state and usage shown by `/status`
+//! - [`stats`]: Running tally of token usage and estimated cost
+//! - [`transcript`]: Markdown transcripts of whole sessions
+//! - [`html`]: Standalone HTML pages of transcripts for sharing
+//! - [`batch`]: Runs of the prompts in a file with a JSON report of the responses
+//! - [`export`]: Versioned JSON conversation files for hand-off between sessions
+//! - [`checkpoint`]: Named conversation and workspace checkpoints
+//! - [`journal`]: On-disk session journal for crash recovery
+//! - [`migrate`]: Format versions of kept files and migrations from older formats
+//! - [`paths`]: Per-platform configuration, data, cache and state directories
+//! - [`preflight`]: Startup validation of the CLI, prompts and configuration
 //! - [`dialog`]: Main dialog loop and conversation flow management
+//! - [`update`]: Self-update from GitHub releases
+//! - [`tips`]: Banner tips and release notes
 //! - [`ui`]: Terminal user interface with colored output
 //!
 //! # Example Usage
@@ -23,26 +76,91 @@
 //! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! // Parse command-line arguments
 //! let args = cli::parse_args(vec!["claude-dialog", "--model", "claude-3-opus"])?;
+//! let session = args.session();
 //!
 //! // Load system prompt
 //! let prompt_config = prompt::SystemPromptConfig {
 //!     system_prompt_files: args.system_prompt_files,
 //!     append_prompt_file: args.append_prompt_file,
+//!     recursive: args.recursive,
 //! };
 //! let system_prompt = prompt::load_system_prompt(prompt_config)?;
 //!
 //! // Create dialog configuration
 //! let config = dialog::DialogConfig { 
-//!     system_prompt: if !system_prompt.is_empty() { Some(system_prompt) } else { None },
+//!     system_prompt: Some(system_prompt.body).filter(|body| !body.is_empty()),
 //!     append_prompt: None,
-//!     model: args.model,
+//!     system_prompt_source: None,
+//!     // Set before `model` and `temperature` take the options
+//!     flag_settings: dialog::FlagSettings {
+//!         model: args.model.is_some(),
+//!         temperature: args.temperature.is_some(),
+//!         ..Default::default()
+//!     },
+//!     model: args.model.or(system_prompt.metadata.model),
+//!     temperature: args.temperature.or(system_prompt.metadata.temperature),
+//!     max_tokens: args.max_tokens,
+//!     trim_policy: args.trim_policy,
+//!     no_prompt_cache: args.no_prompt_cache,
+//!     history_budget: args.history_budget.map(|budget| budget as usize),
+//!     num_ctx: args.num_ctx,
+//!     backend: args.backend,
+//!     session,
+//!     personas: args.personas,
+//!     allowed_tools: Some(args.allowed_tools).filter(|tools| !tools.is_empty()).or(system_prompt.metadata.allowed_tools),
+//!     disallowed_tools: args.disallowed_tools,
+//!     mcp_config: args.mcp_config,
+//!     strict_mcp_config: args.strict_mcp_config,
+//!     claude_bin: args.claude_bin,
+//!     claude_args: args.claude_args,
+//!     aliases: args.aliases,
+//!     allowed_commands: None,
+//!     disallowed_commands: Vec::new(),
+//!     policy: Default::default(),
+//!     journal_dir: None,
+//!     recover: args.recover,
+//!     sessions_dir: None,
+//!     prompts_dir: None,
+//!     working_dir: args.cwd,
+//!     final_only: args.final_only,
+//!     stream: args.stream,
+//!     raw: args.raw,
+//!     no_highlight: args.no_highlight,
+//!     quiz: args.quiz,
+//!     show_usage: args.show_usage,
+//!     dry_run: args.dry_run,
+//!     test_script: None,
+//!     output_format: args.output_format,
+//!     masker: None,
+//!     mask_transcripts: args.mask_transcripts,
+//!     tee: None,
+//!     context_budget: Some(args.context_budget),
+//!     context_strategy: args.context_strategy,
+//!     repo_map: args.repo_map,
+//!     attachments: args.attachments,
+//!     language: args.language,
+//!     spellcheck: args.spellcheck,
+//!     dictionaries: args.dictionaries,
+//!     send_unfinished: args.send_unfinished,
+//!     turn_metadata: args.turn_metadata,
+//!     suggest_follow_ups: args.suggest_follow_ups,
+//!     import_file: args.from,
+//!     import_mode: args.from_mode,
+//!     history_file: None,
+//!     history_ignore: args.history_ignore,
+//!     incognito: args.incognito,
+//!     transcript_file: args.save_transcript,
+//!     rate_limit_retries: args.rate_limit_retries,
+//!     timeout: args.timeout.map(std::time::Duration::from_secs),
+//!     capabilities: Default::default(),
 //! };
 //!
 //! // Display welcome message
-//! ui::UI::print_welcome("Using default prompt", config.model.as_ref());
+//! let banner = ui::Banner::new().entry("System Prompt", "Default");
+//! ui::UI::print_welcome(&banner, args.banner);
 //!
 //! // Start the dialog loop
-//! let dialog_loop = dialog::DialogLoop::new(config);
+//! let mut dialog_loop = dialog::DialogLoop::new(config);
 //! dialog_loop.run().await?;
 //! # Ok(())
 //! # }
@@ -52,21 +170,276 @@
 //!
 //! - Interactive conversation mode with Claude AI
 //! - Custom system prompts from file or command line
+//! - A library of named system prompts used with `--preset` and `/preset`
+//! - A configuration file with defaults and named profiles
 //! - Colored terminal output for better readability
 //! - Session management with proper command building
+//! - Named checkpoints to roll back conversations and workspace files
+//! - Crash recovery through an on-disk session journal
+//! - Local crash reports with a backtrace when claude-dialog panics
+//! - Markdown transcripts of sessions, and HTML pages of them to share
+//! - Batch runs of prompt files with a JSON report for evaluations
+//! - Conversation export and import to hand a conversation to another session
+//! - `@file` references with a footer citing the files sent
+//! - An opt-in check of prompts for likely typos and misspelled identifiers
+//! - A confirmation before sending prompts that look cut off or meant for a shell
+//! - An opt-in footer with the time, working directory and OS on every prompt
+//! - Suggested follow-up questions after each answer, sent by typing their number
+//! - Streamed responses fanned out to concurrent sinks
+//! - Markdown responses rendered with terminal styles, or printed raw with `--raw`
+//! - A quiz mode hiding each answer until it is revealed, with a timer
+//! - `present` subcommand replaying a saved conversation with live pacing
+//! - `view` subcommand paging through a saved conversation with search
+//! - asciinema recordings of sessions for docs and blog posts
+//! - Syntax highlighting of code blocks in the language of their fence
+//! - Masking of profanity, chosen words and personal data on screen for demos
+//! - Token usage and estimated cost per turn and per session
+//! - Automatic retries after rate limits, honoring the requested wait
+//! - Ctrl+C cancels the turn being answered without ending the session
 //! - Support for various Claude models and parameters
+//! - A local Ollama backend for offline sessions
+//! - A direct Anthropic API backend with temperature and token limits
+//! - Personas routing turns to different backends and models in one session
+//! - Self-update of release binaries
+//! - Rotating tips and `whatsnew` release notes
+//! - Serde support for configuration, commands and conversations
+//!
+//! # Cargo Features
+//!
+//! The default `cli` feature builds the command-line application with its
+//! core dependencies; the other features are opt-in, and `full` enables all
+//! of them. Embedders that only need [`claude_executor`] and the response
+//! [`pipeline`] can depend on the crate with `default-features = false`;
+//! those embedding the [`dialog`] loop enable `dialog`, which leaves out
+//! `clap`.
+//!
+//! - `cli` (default): [`cli`] argument parsing with `clap`; required by the
+//!   binary, and enables `dialog` and `line-editor`
+//! - `dialog`: the [`dialog`] loop and the other modules it is built from
+//! - `line-editor`: editing input typed at a terminal with `rustyline` (see
+//!   [`line_editor`]); without it, input is read as plain lines
+//! - `self-update`: the [`update`] module and `self-update` subcommand
+//! - `ollama`: the [`ollama`] module and `--backend ollama`
+//! - `api`: the [`api`] module and `--backend api`
+//! - `highlight`: the [`highlight`] module, highlighting code blocks of
+//!   rendered responses with `syntect`; enables `dialog`
+//! - `clipboard`: `/copy` and `/paste` through the system clipboard
+//! - `archive`: zstd compression for [`archive`], used by
+//!   `sessions archive` and to read archived sessions
 
 /// Command-line interface module for parsing arguments
+#[cfg(feature = "cli")]
 pub mod cli;
 
+/// Configuration file module
+#[cfg(feature = "dialog")]
+pub mod config;
+
+/// Organization policy module
+#[cfg(feature = "dialog")]
+pub mod policy;
+
 /// System prompt configuration and loading module
+#[cfg(feature = "dialog")]
 pub mod prompt;
 
+/// Prompt library module for reusable system prompts
+#[cfg(feature = "dialog")]
+pub mod preset;
+
 /// Claude command execution module
 pub mod claude_executor;
 
+/// Stateless chat backend module
+#[cfg(any(feature = "ollama", feature = "api"))]
+pub mod chat;
+
+/// Ollama backend module
+#[cfg(feature = "ollama")]
+pub mod ollama;
+
+/// Anthropic API backend module
+#[cfg(feature = "api")]
+pub mod api;
+
+/// Backend routing module
+pub mod router;
+
+/// Persona module
+pub mod persona;
+
+/// Claude CLI version detection module
+pub mod claude_version;
+
+/// Response pipeline module
+pub mod pipeline;
+
+/// Claude CLI `stream-json` output parsing module
+pub mod stream_json;
+
+/// Terminal Markdown rendering module
+pub mod markdown;
+
+/// Response normalization module
+pub mod normalize;
+
+/// Code block syntax highlighting module
+#[cfg(feature = "highlight")]
+pub mod highlight;
+
+/// Response masking module
+#[cfg(feature = "dialog")]
+pub mod mask;
+
+/// Rate limit detection and retry timing module
+pub mod rate_limit;
+
+/// Ctrl+C cancellation of running turns module
+pub mod interrupt;
+
+/// Quiz mode module
+pub mod quiz;
+
+/// Conversation playback module
+#[cfg(feature = "dialog")]
+pub mod present;
+
+/// Transcript viewer module
+#[cfg(feature = "dialog")]
+pub mod viewer;
+
+/// Session recording module
+pub mod cast;
+
+/// Conversation tee module
+pub mod tee;
+
+/// Slash command parsing module
+pub mod commands;
+
+/// `@file` context inclusion module
+pub mod context;
+
+/// Prompt language detection module
+pub mod language;
+
+/// Turn metadata module
+pub mod metadata;
+
+/// Follow-up suggestions module
+pub mod follow_up;
+
+/// Numbered selection module for picking from lists by number
+pub mod selection;
+
+/// Command palette module
+pub mod palette;
+
+/// Prompt spell checking module
+pub mod spelling;
+
+/// Unfinished input module
+pub mod unfinished;
+
+/// MCP server module
+pub mod mcp;
+
+/// File chunk selection module
+pub mod chunk;
+
+/// Repository map module
+#[cfg(feature = "dialog")]
+pub mod repo_map;
+
+/// File attachment module
+pub mod attach;
+
+/// External editor module
+pub mod compose;
+
+/// System clipboard module
+pub mod clipboard;
+
+/// Ignore rules module
+#[cfg(feature = "dialog")]
+pub mod ignore_rules;
+
+/// Input history module
+#[cfg(feature = "dialog")]
+pub mod history;
+
+/// Line editor module for the input prompt
+#[cfg(feature = "dialog")]
+pub mod line_editor;
+
+/// In-memory conversation state module
+pub mod conversation;
+
+/// Session status module
+pub mod status;
+
+/// Usage statistics module
+pub mod stats;
+
+/// Session transcript module
+pub mod transcript;
+
+/// HTML transcript module
+#[cfg(feature = "dialog")]
+pub mod html;
+
+/// Batch prompt run module
+#[cfg(feature = "dialog")]
+pub mod batch;
+
+/// Conversation export module
+pub mod export;
+
+/// Conversation checkpoint module
+#[cfg(feature = "dialog")]
+pub mod checkpoint;
+
+/// Session journal module for crash recovery
+#[cfg(feature = "dialog")]
+pub mod journal;
+
+/// Format migration module
+pub mod migrate;
+
+/// Crash report module
+#[cfg(feature = "dialog")]
+pub mod crash;
+
+/// Session store module
+pub mod sessions;
+
+/// Session archive module
+pub mod archive;
+
+/// Machine-readable output module
+pub mod output;
+
+/// Scripted backend module
+#[cfg(feature = "dialog")]
+pub mod scripted;
+
+/// Application directories module
+pub mod paths;
+
+/// Startup validation module
+#[cfg(feature = "dialog")]
+pub mod preflight;
+
 /// Dialog loop and conversation management module
+#[cfg(feature = "dialog")]
 pub mod dialog;
 
+/// Binary self-update module
+#[cfg(feature = "self-update")]
+pub mod update;
+
+/// Banner tips and release notes module
+pub mod tips;
+
 /// Terminal user interface module with colored output
 pub mod ui;
\ No newline at end of file
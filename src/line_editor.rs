@@ -0,0 +1,414 @@
+//! Line editor module
+//!
+//! When standard input is a terminal, input at the `You>` prompt is read
+//! with a line editor: the arrow keys move through the line and recall
+//! earlier input, Emacs-style keys edit it (Ctrl+A and Ctrl+E jump to its
+//! start and end, Ctrl+W deletes a word, Ctrl+U the line) and Ctrl+R
+//! searches the history. Ctrl+C discards the input being typed, or ends the
+//! session right after a turn was cancelled with it (see
+//! [`crate::interrupt`]); Ctrl+D on an empty line ends the session.
+//! Ctrl+X Ctrl+E continues the input in an external editor (see
+//! [`crate::compose`]), and Ctrl+P opens the command palette with the
+//! input typed so far as its query (see [`crate::palette`]); earlier input
+//! is still recalled with the Up arrow.
+//!
+//! The editor's history starts with the input history file (see
+//! [`crate::history`]) and grows with every input entered. Piped input is
+//! read line by line without editing, as other prompts read it, and so is
+//! all input in builds without the `line-editor` feature.
+//!
+//! # Multi-line Input
+//!
+//! Input can span several lines in three ways:
+//!
+//! - text pasted into a terminal with bracketed paste, which most terminals
+//!   support, keeps its line breaks
+//! - Alt+Enter starts a new line without sending the input
+//! - a block between lines starting and ending with `"""` is read as one
+//!   input; this also works for piped input
+//!
+//! ```text
+//! You> """Why does this not compile?
+//! ...  fn main() {
+//! ...      let s = String::from("hi");
+//! ...  }"""
+//! ```
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use claude_dialog::line_editor::{Input, LineEditor};
+//! use claude_dialog::ui::UI;
+//!
+//! let mut editor = LineEditor::new();
+//! editor.add_history("Explain lifetimes");
+//!
+//! while let Input::Text(text) = editor.read_input(&UI::user_prompt(false)).unwrap() {
+//!     editor.add_history(&text);
+//! }
+//! ```
+
+use anyhow::Result;
+#[cfg(feature = "line-editor")]
+use rustyline::config::Config;
+#[cfg(feature = "line-editor")]
+use rustyline::error::ReadlineError;
+#[cfg(feature = "line-editor")]
+use rustyline::history::DefaultHistory;
+#[cfg(feature = "line-editor")]
+use rustyline::{
+    Cmd, ConditionalEventHandler, Editor, Event, EventContext, EventHandler, ExternalPrinter, KeyCode, KeyEvent,
+    Modifiers, RepeatCount,
+};
+use std::io::{self, Write};
+#[cfg(feature = "line-editor")]
+use std::io::IsTerminal;
+#[cfg(feature = "line-editor")]
+use std::sync::Arc;
+#[cfg(feature = "line-editor")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "line-editor")]
+use crate::history::MAX_ENTRIES;
+use crate::ui::UI;
+
+/// Starts and ends a block of input spanning several lines
+pub const BLOCK_DELIMITER: &str = "\"\"\"";
+
+/// Input read from the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// The text entered
+    Text(String),
+    /// Ctrl+C was pressed, discarding the text
+    Interrupted,
+    /// Input ended, e.g. with Ctrl+D
+    End,
+    /// Ctrl+X Ctrl+E was pressed to continue the text in an external editor
+    Compose(String),
+    /// Ctrl+P was pressed to look the text up in the command palette
+    Palette(String),
+}
+
+/// Reader of the lines typed at the user prompt
+pub struct LineEditor {
+    // `None` when standard input is not a terminal
+    #[cfg(feature = "line-editor")]
+    editor: Option<Editor<(), DefaultHistory>>,
+    draft: Option<String>,
+    // Set by Ctrl+X Ctrl+E, which accepts the line typed so far
+    #[cfg(feature = "line-editor")]
+    compose: Arc<AtomicBool>,
+    // Set by Ctrl+P, which accepts the line typed so far
+    #[cfg(feature = "line-editor")]
+    palette: Arc<AtomicBool>,
+}
+
+/// Prints text above the prompt while input is read, see
+/// [`LineEditor::printer`]
+pub struct Printer {
+    #[cfg(feature = "line-editor")]
+    printer: Box<dyn ExternalPrinter + Send>,
+}
+
+impl Printer {
+    /// Print `text` above the prompt, returning whether it could be printed
+    pub fn print(&mut self, text: String) -> bool {
+        #[cfg(feature = "line-editor")]
+        return self.printer.print(text).is_ok();
+        #[cfg(not(feature = "line-editor"))]
+        {
+            let _ = text;
+            false
+        }
+    }
+}
+
+impl LineEditor {
+    /// Create a line editor, or a plain line reader if standard input is
+    /// not a terminal or the terminal cannot be used for editing
+    #[cfg(feature = "line-editor")]
+    pub fn new() -> Self {
+        let compose = Arc::new(AtomicBool::new(false));
+        let palette = Arc::new(AtomicBool::new(false));
+        let editor = io::stdin()
+            .is_terminal()
+            .then(|| Self::terminal_editor(&compose, &palette))
+            .flatten();
+        Self { editor, draft: None, compose, palette }
+    }
+
+    /// Create a plain line reader
+    #[cfg(not(feature = "line-editor"))]
+    pub fn new() -> Self {
+        Self { draft: None }
+    }
+
+    #[cfg(feature = "line-editor")]
+    fn terminal_editor(compose: &Arc<AtomicBool>, palette: &Arc<AtomicBool>) -> Option<Editor<(), DefaultHistory>> {
+        let config = Config::builder()
+            .max_history_size(MAX_ENTRIES)
+            .ok()?
+            .history_ignore_dups(true)
+            .ok()?
+            .auto_add_history(false)
+            .build();
+        let mut editor = Editor::with_config(config).ok()?;
+        // The default helper lets the editor show the colored prompt
+        editor.set_helper(Some(()));
+        editor.bind_sequence(KeyEvent(KeyCode::Enter, Modifiers::ALT), Cmd::Newline);
+        editor.bind_sequence(
+            Event::KeySeq(vec![KeyEvent::ctrl('X'), KeyEvent::ctrl('E')]),
+            EventHandler::Conditional(Box::new(AcceptHandler(Arc::clone(compose)))),
+        );
+        editor.bind_sequence(
+            KeyEvent::ctrl('P'),
+            EventHandler::Conditional(Box::new(AcceptHandler(Arc::clone(palette)))),
+        );
+        Some(editor)
+    }
+
+    /// Whether input is edited, rather than read as plain lines
+    pub fn is_editing(&self) -> bool {
+        #[cfg(feature = "line-editor")]
+        return self.editor.is_some();
+        #[cfg(not(feature = "line-editor"))]
+        false
+    }
+
+    /// A printer for text arriving while input is read, such as results of
+    /// background work
+    ///
+    /// Text printed while a line is being typed appears above the prompt,
+    /// which is then drawn again with the line. `None` when input is not
+    /// edited, or the terminal cannot print from another task.
+    #[cfg(feature = "line-editor")]
+    pub fn printer(&mut self) -> Option<Printer> {
+        let printer = self.editor.as_mut()?.create_external_printer().ok()?;
+        Some(Printer { printer: Box::new(printer) })
+    }
+
+    /// A printer for text arriving while input is read; plain line input
+    /// has none
+    #[cfg(not(feature = "line-editor"))]
+    pub fn printer(&mut self) -> Option<Printer> {
+        None
+    }
+
+    /// Make a line available to the arrow keys and Ctrl+R
+    ///
+    /// Lines are kept in memory only; the input history file is written by
+    /// [`History`](crate::history::History).
+    pub fn add_history(&mut self, line: &str) {
+        #[cfg(feature = "line-editor")]
+        if let Some(editor) = &mut self.editor {
+            // Adding to an in-memory history cannot fail
+            let _ = editor.add_history_entry(line);
+        }
+        #[cfg(not(feature = "line-editor"))]
+        let _ = line;
+    }
+
+    /// Start the next input with `text` already typed, for editing before
+    /// it is sent again
+    ///
+    /// Plain line input cannot be edited, so there the draft is discarded
+    /// and the input typed again.
+    pub fn set_draft(&mut self, text: impl Into<String>) {
+        self.draft = Some(text.into()).filter(|_| self.is_editing());
+    }
+
+    /// Show `prompt` and read the next input
+    ///
+    /// A line starting with [`BLOCK_DELIMITER`] begins a block, which is
+    /// read up to the line ending with the delimiter, or the end of input.
+    /// The lines in between keep their indentation.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt without and with colors, see
+    ///   [`UI::user_prompt`](crate::ui::UI::user_prompt)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Input::Text(text))` - The input, trimmed
+    /// * `Ok(Input::Interrupted)` - Ctrl+C was pressed
+    /// * `Ok(Input::End)` - End of input, after moving to a new line
+    /// * `Ok(Input::Compose(text))` - Ctrl+X Ctrl+E was pressed after
+    ///   typing `text`
+    /// * `Ok(Input::Palette(text))` - Ctrl+P was pressed after typing
+    ///   `text` on the first line
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if standard input cannot be read.
+    pub fn read_input(&mut self, prompt: &(String, String)) -> Result<Input> {
+        let first = match self.read_line(prompt)? {
+            Input::Text(line) => line,
+            other => return Ok(other),
+        };
+        let Some(opening) = first.trim_start().strip_prefix(BLOCK_DELIMITER) else {
+            return Ok(Input::Text(first.trim().to_string()));
+        };
+        if let Some(text) = opening.trim_end().strip_suffix(BLOCK_DELIMITER) {
+            return Ok(Input::Text(text.trim().to_string()));
+        }
+
+        let continuation = UI::continuation_prompt();
+        let mut lines = vec![opening.to_string()];
+        loop {
+            match self.read_line(&continuation)? {
+                Input::Text(line) => match line.trim_end().strip_suffix(BLOCK_DELIMITER) {
+                    Some(last) => {
+                        lines.push(last.to_string());
+                        break;
+                    }
+                    None => lines.push(line),
+                },
+                Input::Compose(line) => {
+                    lines.push(line);
+                    return Ok(Input::Compose(block_text(&lines)));
+                }
+                // The palette is for single lines; the block goes on
+                Input::Palette(line) => lines.push(line),
+                Input::Interrupted => return Ok(Input::Interrupted),
+                Input::End => break,
+            }
+        }
+        Ok(Input::Text(block_text(&lines)))
+    }
+
+    /// Ask `question` and read the answer, trimmed
+    ///
+    /// Answers are read like input, with the line editor when standard
+    /// input is a terminal, but are not added to the history, and a draft
+    /// is kept for the next input.
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - The question without and with colors, see
+    ///   [`UI::question`]
+    ///
+    /// # Returns
+    ///
+    /// The answer, or `None` if Ctrl+C was pressed or input ended.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if standard input cannot be read.
+    pub fn read_answer(&mut self, question: &(String, String)) -> Result<Option<String>> {
+        let draft = self.draft.take();
+        let answer = self.read_line(question);
+        self.draft = draft;
+        match answer? {
+            Input::Text(answer) | Input::Compose(answer) | Input::Palette(answer) => Ok(Some(answer.trim().to_string())),
+            Input::Interrupted | Input::End => Ok(None),
+        }
+    }
+
+    /// Show `prompt` and read a line, without its line break
+    #[cfg(feature = "line-editor")]
+    fn read_line(&mut self, prompt: &(String, String)) -> Result<Input> {
+        let Some(editor) = &mut self.editor else {
+            return read_plain_line(prompt);
+        };
+
+        let line = match self.draft.take() {
+            Some(draft) => editor.readline_with_initial(prompt, (&draft, "")),
+            None => editor.readline(prompt),
+        };
+        match line {
+            Ok(line) if self.compose.swap(false, Ordering::Relaxed) => Ok(Input::Compose(line)),
+            Ok(line) if self.palette.swap(false, Ordering::Relaxed) => Ok(Input::Palette(line)),
+            Ok(line) => Ok(Input::Text(line)),
+            Err(ReadlineError::Interrupted) => Ok(Input::Interrupted),
+            Err(ReadlineError::Eof) => Ok(Input::End),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Show `prompt` and read a line, without its line break
+    #[cfg(not(feature = "line-editor"))]
+    fn read_line(&mut self, prompt: &(String, String)) -> Result<Input> {
+        read_plain_line(prompt)
+    }
+}
+
+/// Show `prompt` and read a line from standard input without editing
+fn read_plain_line(prompt: &(String, String)) -> Result<Input> {
+    print!("{}", prompt.1);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(Input::End);
+    }
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    Ok(Input::Text(line.strip_suffix('\r').unwrap_or(line).to_string()))
+}
+
+/// Key binding accepting the line typed so far, marked with its flag for
+/// composing in an external editor or looking it up in the palette
+#[cfg(feature = "line-editor")]
+struct AcceptHandler(Arc<AtomicBool>);
+
+#[cfg(feature = "line-editor")]
+impl ConditionalEventHandler for AcceptHandler {
+    fn handle(&self, _event: &Event, _count: RepeatCount, _positive: bool, _context: &EventContext) -> Option<Cmd> {
+        self.0.store(true, Ordering::Relaxed);
+        Some(Cmd::AcceptLine)
+    }
+}
+
+/// The lines of a block without blank lines at its start and end and
+/// trailing whitespace, keeping the indentation
+fn block_text(lines: &[String]) -> String {
+    let is_blank = |line: &&String| line.trim().is_empty();
+    let start = lines.iter().position(|line| !is_blank(&line)).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|line| !is_blank(&line)).map_or(start, |index| index + 1);
+    lines[start..end]
+        .iter()
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "line-editor")]
+    use rustyline::history::History;
+
+    #[cfg(feature = "line-editor")]
+    #[test]
+    fn test_history_skips_repeated_lines() {
+        let compose = Arc::new(AtomicBool::new(false));
+        let palette = Arc::new(AtomicBool::new(false));
+        let editor = LineEditor::terminal_editor(&compose, &palette);
+        let mut editor = LineEditor { editor, draft: None, compose, palette };
+        assert!(editor.is_editing());
+        editor.add_history("one");
+        editor.add_history("one");
+        editor.add_history("two");
+        assert_eq!(editor.editor.unwrap().history().len(), 2);
+
+        let mut plain = LineEditor { editor: None, draft: None, compose: Arc::default(), palette: Arc::default() };
+        plain.add_history("one");
+        assert!(!plain.is_editing());
+        plain.set_draft("one");
+        assert!(plain.draft.is_none());
+    }
+
+    #[test]
+    fn test_block_text() {
+        let lines: Vec<String> = ["", "fn main() {", "    run();  ", "}", "  "].map(String::from).into();
+        assert_eq!(block_text(&lines), "fn main() {\n    run();\n}");
+        assert_eq!(block_text(&[String::new()]), "");
+    }
+}
@@ -21,61 +21,375 @@
 //!
 //! # Specify a model
 //! claude-dialog --model claude-3-opus
+//!
+//! # Talk to a local Ollama model instead of Claude
+//! claude-dialog --backend ollama --model llama3
+//!
+//! # Resume a Claude conversation by its session ID, or start a new one
+//! claude-dialog --resume 3f2a9c1e-7b4d-4e1a-9c2f-5d8e6a0b1c3d
+//! claude-dialog --new-session
+//!
+//! # Draft with a local model, then switch to Claude with /persona final
+//! claude-dialog --persona draft=ollama:llama3 --persona final=claude:opus
+//!
+//! # Let Claude read files and run git log, but never fetch web pages
+//! claude-dialog --allowed-tools 'Read,Edit,Bash(git log:*)' --disallowed-tools WebFetch
+//!
+//! # Use the options of the `work` profile from config.toml
+//! claude-dialog --profile work
+//!
+//! # Send one prompt and exit with the status of the claude command
+//! claude-dialog --prompt "Summarize @CHANGELOG.md"
+//! git diff | claude-dialog --prompt -
+//!
+//! # Run the prompts of a file and write the responses to prompts.results.json
+//! claude-dialog --batch prompts.yaml
+//!
+//! # Continue a conversation exported with /export
+//! claude-dialog --from handoff.json
+//!
+//! # Show responses while they are generated
+//! claude-dialog --stream
+//!
+//! # Define a slash command alias
+//! claude-dialog --alias 'cp=/checkpoint {{date}}-{{1}}'
+//!
+//! # Show where configuration, history and sessions are stored
+//! claude-dialog paths
+//!
+//! # Update to the latest release (or only check with --check)
+//! claude-dialog self-update
+//!
+//! # Show what changed in the installed version
+//! claude-dialog whatsnew
+//!
+//! # Keep this session's input out of the history file, or delete the file
+//! claude-dialog --private
+//! claude-dialog history clear
+//!
+//! # Write nothing about this session to disk
+//! claude-dialog --incognito
+//!
+//! # Save a Markdown transcript of the session when it ends
+//! claude-dialog --save-transcript notes/session.md
 //! ```
 //!
 //! # Exit
 //!
 //! Type "exit" or "quit" during the conversation to end the session.
+//!
+//! # Crash Recovery
+//!
+//! Every turn is journaled to the state directory while the session runs.
+//! If the previous session did not exit cleanly, the next start offers to
+//! recover its turns.
+//!
+//! # Checkpoints
+//!
+//! Type `/checkpoint <name>` to save the conversation state (add `--files` to
+//! also snapshot workspace files) and `/restore <name>` to roll back to it.
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Result, Context};
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
 use claude_dialog::{
-    cli::Args,
-    prompt::{load_system_prompt, SystemPromptConfig},
-    dialog::{DialogLoop, DialogConfig},
-    ui::UI,
+    batch,
+    cli::{Args, Command, HistoryCommand, PromptsCommand, SessionsCommand},
+    commands::CommandRegistry,
+    claude_executor::{ALLOWED_TOOLS, Backend, SessionMode, shell_quote, tool_list},
+    config::{self, ConfigFile, Settings},
+    crash,
+    history::History,
+    prompt::{load_system_prompt, prompt_files, LoadedPrompt, SystemPromptConfig},
+    dialog::{DialogLoop, DialogConfig, FlagSettings},
+    export::ConversationExport,
+    html,
+    mask::Masker,
+    mcp,
+    output::PrintFormat,
+    paths::Paths,
+    policy::{self, Policy},
+    preflight::{self, Report},
+    present::{self, Playback},
+    preset::{self, PromptLibrary},
+    scripted::TestBackend,
+    sessions::{self, SessionStore},
+    tee::{self, Tee},
+    tips,
+    ui::{Banner, UI},
+    viewer,
 };
+#[cfg(feature = "ollama")]
+use claude_dialog::ollama::OllamaExecutor;
+#[cfg(feature = "api")]
+use claude_dialog::api::{ApiExecutor, DEFAULT_MAX_TOKENS};
+#[cfg(feature = "self-update")]
+use claude_dialog::update::{self, UpdateOutcome};
+#[cfg(unix)]
+use claude_dialog::cast::Recorder;
+
+/// Where options from the front matter of the system prompt files came from
+const FRONT_MATTER: &str = "system prompt front matter";
 
 /// Main entry point for the Claude Dialog application
 ///
 /// This function orchestrates the entire application flow:
-/// 1. Parses command-line arguments
-/// 2. Loads and configures system prompts
-/// 3. Displays welcome information
-/// 4. Starts the interactive dialog loop
+/// 1. Parses command-line arguments, running a subcommand if one was given
+/// 2. Loads the configuration file for options not given on the command line
+/// 3. Loads and configures system prompts
+/// 4. Detects the Claude CLI version and drops unsupported options
+/// 5. Validates the configuration, reporting every problem at once
+/// 6. Sends the `--prompt` prompt and exits with the backend's status, if
+///    one was given, or the `--batch` prompts and exits with 1 if one failed
+/// 7. Displays welcome information
+/// 8. Starts the interactive dialog loop
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - System prompt files cannot be read
+/// - Startup validation finds a problem, such as an unreadable system
+///   prompt file or a missing Claude CLI
 /// - The dialog loop encounters an error during execution
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse command line arguments
-    let args = Args::parse();
+    let args = Args::from_env();
+    let paths = Paths::resolve();
+    
+    // Panics leave a crash report, except in sessions that leave no trace
+    if !args.incognito && let Some(paths) = &paths {
+        crash::install(paths.crash_dir());
+    }
     
-    // Load system prompt
+    match args.command {
+        Some(Command::Chat) | None => {}
+        Some(command) => return run_command(command, paths.as_ref()).await,
+    }
+    
+    let session = args.session();
+    let mut report = Report::new();
+    
+    // With a cold version cache, checking the Claude CLI runs `claude
+    // --version`, the slowest step of startup, so it runs while the options
+    // are loaded. A test backend answers without it.
+    let cache_dir = paths.as_ref().map(|paths| paths.cache_dir.clone());
+    let claude_check = (args.backend == Backend::Claude && args.test_backend.is_none()).then(|| {
+        let (claude_bin, cache_dir) = (args.claude_bin.clone(), cache_dir.clone());
+        thread::spawn(move || preflight::check_claude(claude_bin.as_deref(), cache_dir.as_deref()))
+    });
+    
+    // Options missing on the command line come from the configuration file
+    let (settings, config_source) = load_settings(paths.as_ref(), args.profile.as_deref()).unwrap_or_else(|e| {
+        report.error(format!("{:#}", e));
+        (Settings::default(), String::new())
+    });
+    settings.color.unwrap_or_default().apply();
+    
+    // The organization's policy limits what the settings and options can
+    // ask for; a policy that cannot be read stops the start
+    let policy = Policy::load_all(&policy::files()).unwrap_or_else(|e| {
+        report.error(format!("{:#}", e));
+        Policy::default()
+    });
+    
+    let test_script = args.test_backend.as_ref().map(TestBackend::script).transpose().unwrap_or_else(|e| {
+        report.error(format!("{:#}", e));
+        None
+    });
+    
+    let prompts_dir = paths.as_ref().map(Paths::prompts_dir).unwrap_or_default();
+    let config_prompt_files = settings.system_prompt_files(&prompts_dir).unwrap_or_else(|e| {
+        report.error(format!("{:#}", e));
+        Vec::new()
+    });
+    let config_dir = paths.as_ref().map(|paths| paths.config_dir.clone()).unwrap_or_default();
+    let config_mask_words = settings.mask_word_files(&config_dir).unwrap_or_else(|e| {
+        report.error(format!("{:#}", e));
+        Vec::new()
+    });
+    let preset_files = match &args.preset {
+        Some(name) => match PromptLibrary::new(&prompts_dir).find(name) {
+            Ok(preset) => vec![preset.path],
+            Err(e) => {
+                report.error(format!("{:#}", e));
+                Vec::new()
+            }
+        },
+        None => args.system_prompt_files.clone(),
+    };
+    let cli_prompt_files = !args.system_prompt_files.is_empty() || args.append_prompt_file.is_some() || args.preset.is_some();
+    let (system_prompt_files, prompt_source) = pick(
+        Some(preset_files).filter(|_| cli_prompt_files),
+        Some(config_prompt_files).filter(|_| settings.system_prompt.is_some()),
+        if args.preset.is_some() { "--preset" } else { "--system-prompt" },
+        &config_source,
+    );
+    // Directories stand for the .md files in them, which the banner lists
+    let system_prompt_files = prompt_files(&system_prompt_files.unwrap_or_default(), args.recursive).unwrap_or_else(|e| {
+        report.error(format!("{:#}", e));
+        Vec::new()
+    });
+    
+    // Load system prompt, whose front matter may set further options
     let prompt_config = SystemPromptConfig {
-        system_prompt_files: args.system_prompt_files.clone(),
+        system_prompt_files: system_prompt_files.clone(),
         append_prompt_file: args.append_prompt_file.clone(),
+        recursive: args.recursive,
     };
     
-    let system_prompt = load_system_prompt(prompt_config)?;
+    let LoadedPrompt { body: system_prompt, metadata: prompt_metadata } = load_system_prompt(prompt_config).unwrap_or_else(|e| {
+        report.error(format!("{:#}", e));
+        LoadedPrompt::default()
+    });
     
-    // Determine system prompt info for display
-    let system_prompt_info = if !args.system_prompt_files.is_empty() {
-        args.system_prompt_files.join(", ")
-    } else if let Some(append_file) = &args.append_prompt_file {
-        format!("Default + {}", append_file)
+    let (model, model_source) = pick_with_front_matter(args.model, prompt_metadata.model, settings.model, "--model", &config_source);
+    let (temperature, temperature_source) = pick(args.temperature, prompt_metadata.temperature, "--temperature", FRONT_MATTER);
+    let (personas, _) = pick(Some(args.personas).filter(|personas| !personas.is_empty()), settings.personas, "--persona", &config_source);
+    let personas = personas.unwrap_or_default();
+    let mut backends = vec![args.backend];
+    for persona in &personas {
+        if !backends.contains(&persona.backend) {
+            backends.push(persona.backend);
+        }
+    }
+    for backend in backends {
+        if let Err(e) = policy.check_backend(backend) {
+            report.error(format!("{:#}", e));
+        }
+    }
+    let (language, language_source) = pick(args.language, settings.language, "--language", &config_source);
+    let (spellcheck, spellcheck_source) = pick(args.spellcheck.then_some(true), settings.spellcheck, "--spellcheck", &config_source);
+    let (mask_words, _) = pick(
+        Some(args.mask_words).filter(|words| !words.is_empty()),
+        Some(config_mask_words).filter(|_| settings.mask_words.is_some()),
+        "--mask-words",
+        &config_source,
+    );
+    let (mask_patterns, _) = pick(
+        Some(args.mask_patterns).filter(|patterns| !patterns.is_empty()),
+        settings.mask_patterns,
+        "--mask-pattern",
+        &config_source,
+    );
+    // Ignore patterns add up, so the command line cannot drop a configured one
+    let mut history_ignore = settings.history_ignore.unwrap_or_default();
+    history_ignore.extend(args.history_ignore);
+    let mut mask_words = mask_words.unwrap_or_default();
+    mask_words.extend(policy.mask_words.iter().cloned());
+    let mut mask_patterns = mask_patterns.unwrap_or_default();
+    mask_patterns.extend(policy.mask_patterns.iter().cloned());
+    let mask_pii = args.mask_pii || settings.mask_pii.unwrap_or(false) || policy.mask_pii;
+    // Masking fails closed: a list that cannot be read stops the start
+    let masker = match Masker::load(&mask_words, &mask_patterns, mask_pii) {
+        Ok(masker) => Some(masker).filter(|masker| !masker.is_empty()),
+        Err(e) => {
+            report.error(format!("{:#}", e));
+            None
+        }
+    };
+    // Opening a named pipe waits until a reader opens it
+    if let Some(path) = &args.tee && tee::is_fifo(path) {
+        UI::print_info(&format!("Waiting for a reader on {}...", path.display()));
+    }
+    let tee = match args.tee.as_deref().map(Tee::open).transpose() {
+        Ok(tee) => tee,
+        Err(e) => {
+            report.error(format!("{:#}", e));
+            None
+        }
+    };
+    let (allowed_tools, allowed_source) = pick_with_front_matter(
+        Some(args.allowed_tools).filter(|tools| !tools.is_empty()),
+        prompt_metadata.allowed_tools,
+        settings.allowed_tools,
+        "--allowed-tools",
+        &config_source,
+    );
+    let (disallowed_tools, disallowed_source) = pick_with_front_matter(
+        Some(args.disallowed_tools).filter(|tools| !tools.is_empty()),
+        prompt_metadata.disallowed_tools,
+        settings.disallowed_tools,
+        "--disallowed-tools",
+        &config_source,
+    );
+    let tools = |tools: Option<Vec<String>>, report: &mut Report| match tools.as_deref().map(tool_list).transpose() {
+        Ok(tools) => tools,
+        Err(e) => {
+            report.error(format!("{:#}", e));
+            None
+        }
+    };
+    let allowed_tools = tools(allowed_tools, &mut report);
+    let disallowed_tools = tools(disallowed_tools, &mut report).unwrap_or_default();
+    
+    // Slash commands turned off by the configuration; a misspelled name
+    // stops the start rather than leaving a command on
+    let allowed_commands = settings.allowed_commands;
+    let disallowed_commands = settings.disallowed_commands.unwrap_or_default();
+    if let Err(e) = CommandRegistry::new().restrict(allowed_commands.as_deref(), &disallowed_commands) {
+        report.error(format!("{:#} ({})", e, config_source));
+    }
+    if let Err(e) = CommandRegistry::new().restrict(None, &policy.disallowed_commands) {
+        report.error(format!("{:#} ({})", e, policy.label()));
+    }
+    // Options the policy narrowed name it as their source too
+    let limited = |source: String, applies: bool| match (applies, source.is_empty()) {
+        (false, _) => source,
+        (true, true) => policy.label(),
+        (true, false) => format!("{}, limited by the {}", source, policy.label()),
+    };
+    let flag_settings = FlagSettings {
+        model: model_source == "--model",
+        allowed_tools: allowed_source == "--allowed-tools",
+        disallowed_tools: disallowed_source == "--disallowed-tools",
+        temperature: temperature_source == "--temperature",
+    };
+    let sources = Sources {
+        model: model_source,
+        temperature: temperature_source,
+        allowed_tools: limited(allowed_source, policy.allowed_tools.is_some()),
+        disallowed_tools: limited(disallowed_source, !policy.disallowed_tools.is_empty()),
+        commands: limited(config_source.clone(), !policy.disallowed_commands.is_empty()),
+        language: language_source,
+        spellcheck: spellcheck_source,
+    };
+    
+    // Determine where the system prompt came from, with the option that
+    // named it, for display and transcripts
+    let system_prompt_source = if !system_prompt_files.is_empty() {
+        let files = system_prompt_files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(format!("{} ({})", files, prompt_source))
     } else {
-        "Default".to_string()
+        args.append_prompt_file
+            .as_ref()
+            .map(|append_file| format!("Default + {} (--append-system-prompt)", append_file.display()))
     };
+    let system_prompt_info = system_prompt_source.clone().unwrap_or_else(|| "Default".to_string());
     
-    // Print welcome message
-    UI::print_welcome(&system_prompt_info, args.model.as_ref());
+    // Check the installed Claude CLI and detect its version, unless no
+    // prompt is going to be sent to Claude
+    let uses_claude = args.test_backend.is_none()
+        && (args.backend == Backend::Claude || personas.iter().any(|persona| persona.backend == Backend::Claude));
+    let capabilities = if uses_claude {
+        let (capabilities, claude_report) = match claude_check.map(thread::JoinHandle::join) {
+            Some(Ok(checked)) => checked,
+            _ => preflight::check_claude(args.claude_bin.as_deref(), cache_dir.as_deref()),
+        };
+        report.extend(claude_report);
+        capabilities
+    } else {
+        Default::default()
+    };
     
     // Create dialog configuration
-    let dialog_config = DialogConfig {
+    let mut dialog_config = DialogConfig {
         system_prompt: if !system_prompt.is_empty() {
             Some(system_prompt.clone())
         } else {
@@ -86,12 +400,570 @@ async fn main() -> Result<()> {
         } else {
             None
         },
-        model: args.model,
+        system_prompt_source,
+        model,
+        temperature,
+        flag_settings,
+        max_tokens: args.max_tokens,
+        trim_policy: args.trim_policy,
+        no_prompt_cache: args.no_prompt_cache || settings.prompt_cache == Some(false),
+        history_budget: args.history_budget.map(|budget| budget as usize),
+        num_ctx: args.num_ctx,
+        backend: args.backend,
+        session,
+        personas,
+        allowed_tools,
+        disallowed_tools,
+        mcp_config: args.mcp_config,
+        strict_mcp_config: args.strict_mcp_config,
+        claude_bin: args.claude_bin.clone(),
+        claude_args: args.claude_args,
+        aliases: args.aliases,
+        allowed_commands,
+        disallowed_commands,
+        policy,
+        journal_dir: paths.as_ref().map(Paths::journal_dir).filter(|_| !args.incognito),
+        recover: args.recover,
+        sessions_dir: paths.as_ref().map(Paths::sessions_dir),
+        prompts_dir: paths.as_ref().map(Paths::prompts_dir),
+        working_dir: args.cwd,
+        final_only: args.final_only,
+        stream: args.stream,
+        raw: args.raw || settings.raw.unwrap_or(false),
+        no_highlight: args.no_highlight || settings.highlight == Some(false),
+        quiz: args.quiz,
+        show_usage: args.show_usage || settings.show_usage.unwrap_or(false),
+        dry_run: args.dry_run,
+        test_script,
+        output_format: args.output_format,
+        masker,
+        mask_transcripts: args.mask_transcripts || settings.mask_transcripts.unwrap_or(false),
+        tee,
+        context_budget: Some(args.context_budget).filter(|&budget| budget > 0),
+        context_strategy: args.context_strategy,
+        repo_map: args.repo_map,
+        attachments: args.attachments,
+        language,
+        spellcheck: spellcheck.unwrap_or(false),
+        dictionaries: args.dictionaries,
+        send_unfinished: args.send_unfinished || settings.confirm_unfinished == Some(false),
+        turn_metadata: args.turn_metadata || settings.turn_metadata.unwrap_or(false),
+        suggest_follow_ups: args.suggest_follow_ups || settings.suggest_follow_ups.unwrap_or(false),
+        import_file: args.from,
+        import_mode: args.from_mode,
+        history_file: paths.as_ref().map(Paths::history_file).filter(|_| !args.private && settings.history != Some(false)),
+        history_ignore,
+        incognito: args.incognito,
+        transcript_file: args.save_transcript,
+        rate_limit_retries: args.rate_limit_retries,
+        timeout: args.timeout.or(settings.timeout).map(Duration::from_secs),
+        capabilities,
     };
     
+    // Drop options the installed CLI does not understand
+    for warning in dialog_config.apply_capabilities() {
+        report.warning(warning);
+    }
+    for warning in dialog_config.apply_policy() {
+        report.warning(warning);
+    }
+    crash::record_settings(&dialog_config);
+    if dialog_config.output_format == PrintFormat::Json {
+        UI::reserve_stdout();
+    }
+    
+    // Report every problem before starting
+    report.extend(preflight::check_config(&dialog_config));
+    let batch = args.batch.map(|source| match batch::load_prompts(&source) {
+        Ok(prompts) => Some((source, prompts)),
+        Err(e) => {
+            report.error(format!("{:#}", e));
+            None
+        }
+    });
+    report.print();
+    if report.has_errors() {
+        anyhow::bail!("{} problem(s) found; not starting the conversation", report.error_count());
+    }
+    
+    // Send a single prompt instead of starting the conversation
+    if let Some(prompt) = args.prompt {
+        let prompt = if prompt == "-" {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).context("Failed to read the prompt from standard input")?;
+            text
+        } else {
+            prompt
+        };
+        if io::stdout().is_terminal() && dialog_config.output_format == PrintFormat::Text {
+            UI::print_welcome(&welcome_banner(system_prompt_info, &sources, &dialog_config), args.banner);
+        }
+        
+        let code = DialogLoop::new(dialog_config).run_once(&prompt).await?;
+        process::exit(code);
+    }
+    
+    // Run the prompts of a file instead of starting the conversation
+    if let Some((source, prompts)) = batch.flatten() {
+        let output = args.batch_output.unwrap_or_else(|| batch::default_output(&source));
+        if io::stdout().is_terminal() && dialog_config.output_format == PrintFormat::Text {
+            UI::print_welcome(&welcome_banner(system_prompt_info, &sources, &dialog_config), args.banner);
+        }
+        
+        let code = DialogLoop::new(dialog_config).run_batch(&source, &prompts, &output, args.repeat as usize).await?;
+        process::exit(code);
+    }
+    
+    // Record everything shown from the banner on
+    #[cfg(unix)]
+    let recorder = args.record_cast.as_deref().map(|path| Recorder::start(path).map(|recorder| (recorder, path))).transpose()?;
+    #[cfg(not(unix))]
+    if args.record_cast.is_some() {
+        anyhow::bail!("--record-cast is only supported on Unix");
+    }
+    
+    // Print welcome message
+    let mut banner = welcome_banner(system_prompt_info, &sources, &dialog_config);
+    if let Some(path) = &args.record_cast {
+        banner = banner.entry("Recording", format!("{} (asciinema cast)", path.display()));
+    }
+    UI::print_welcome(&banner, args.banner);
+    
     // Run the dialog loop
-    let dialog = DialogLoop::new(dialog_config);
-    dialog.run().await?;
+    let mut dialog = DialogLoop::new(dialog_config);
+    let result = dialog.run().await;
     
-    Ok(())
-}
\ No newline at end of file
+    #[cfg(unix)]
+    if let Some((recorder, path)) = recorder {
+        recorder.finish()?;
+        UI::print_info(&format!("Recording saved to {}; play it with asciinema play", path.display()));
+    }
+    result
+}
+
+/// Load the configuration file and apply the selected profile
+///
+/// Returns the settings, and the label shown in the banner for options
+/// taken from them.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or parsed, or if `profile`
+/// is not defined in it.
+fn load_settings(paths: Option<&Paths>, profile: Option<&str>) -> Result<(Settings, String)> {
+    let path = paths.map(Paths::config_file);
+    if let Some(path) = &path
+        && let Some(upgrade) = config::upgrade(path)?
+    {
+        UI::print_info(&format!(
+            "Upgraded {} from format version {} ({}); the previous version is kept as {}",
+            path.display(),
+            upgrade.from,
+            upgrade.changes.join(", "),
+            upgrade.backup.display()
+        ));
+    }
+    let config = match &path {
+        Some(path) => ConfigFile::load(path)?,
+        None => None,
+    };
+    let Some(config) = config else {
+        if let Some(profile) = profile {
+            let location = path.map(|path| path.display().to_string()).unwrap_or_else(|| "config.toml".to_string());
+            anyhow::bail!("Cannot use profile '{}': there is no configuration file at {}", profile, location);
+        }
+        return Ok((Settings::default(), String::new()));
+    };
+    
+    let source = match profile {
+        Some(profile) => format!("profile {}", profile),
+        None => "config.toml".to_string(),
+    };
+    Ok((config.settings(profile)?, source))
+}
+
+/// Where options shown in the banner came from: their command-line flag,
+/// or the configuration file
+struct Sources {
+    model: String,
+    temperature: String,
+    allowed_tools: String,
+    disallowed_tools: String,
+    commands: String,
+    language: String,
+    spellcheck: String,
+}
+
+/// Take an option from the command line or else from the configuration
+/// file, along with the label of where it came from
+fn pick<T>(cli: Option<T>, config: Option<T>, flag: &str, config_source: &str) -> (Option<T>, String) {
+    match (cli, config) {
+        (Some(value), _) => (Some(value), flag.to_string()),
+        (None, Some(value)) => (Some(value), config_source.to_string()),
+        (None, None) => (None, String::new()),
+    }
+}
+
+/// Like [`pick`], with the front matter of the system prompt between the
+/// command line and the configuration file
+fn pick_with_front_matter<T>(
+    cli: Option<T>,
+    front_matter: Option<T>,
+    config: Option<T>,
+    flag: &str,
+    config_source: &str,
+) -> (Option<T>, String) {
+    match pick(cli, front_matter, flag, FRONT_MATTER) {
+        (None, _) => pick(None, config, flag, config_source),
+        picked => picked,
+    }
+}
+
+/// Describe the effective configuration for the welcome banner
+fn welcome_banner(system_prompt_info: String, sources: &Sources, config: &DialogConfig) -> Banner {
+    let model = match &config.model {
+        Some(model) => format!("{} ({})", model, sources.model),
+        None => "default".to_string(),
+    };
+    let mut claude_version = config
+        .capabilities
+        .version()
+        .map(|version| version.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    if let Some(binary) = &config.claude_bin {
+        claude_version = format!("{} ({}, --claude-bin)", claude_version, binary.display());
+    }
+    
+    let mut banner = Banner::new();
+    if config.incognito {
+        banner = banner.entry("Incognito", "nothing about this session is saved to disk");
+    }
+    if !config.policy.is_empty() {
+        let files = config.policy.sources.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ");
+        let policy = match config.policy.max_session_cost_usd {
+            Some(limit) => format!("{}; prompts stop at an estimated ${:.2}", files, limit),
+            None => files,
+        };
+        banner = banner.entry("Policy", policy);
+    }
+    
+    banner = banner
+        .entry("System Prompt", system_prompt_info)
+        .entry("Model", model);
+    if let Some(temperature) = config.temperature {
+        banner = banner.entry("Temperature", format!("{} ({})", temperature, sources.temperature));
+    }
+    banner = match config.backend {
+        Backend::Claude => banner
+            .entry("Claude CLI", claude_version)
+            .entry("Session", match &config.session {
+                SessionMode::Continue => "continues the most recent conversation".to_string(),
+                SessionMode::Resume(id) => format!("resumes {} (--resume)", id),
+                SessionMode::Fresh => "new conversation (--new-session)".to_string(),
+            })
+            .entry("Allowed Tools", match &config.allowed_tools {
+                Some(tools) if tools.is_empty() => format!("none ({})", sources.allowed_tools),
+                Some(tools) => format!("{} ({})", tools.join(", "), sources.allowed_tools),
+                None => ALLOWED_TOOLS.join(", "),
+            }),
+        #[cfg(feature = "ollama")]
+        Backend::Ollama => banner.entry("Backend", format!("ollama at {} (--backend)", OllamaExecutor::from_env().host())),
+        #[cfg(feature = "api")]
+        Backend::Api => banner
+            .entry("Backend", format!("Anthropic API at {} (--backend)", ApiExecutor::from_env().url()))
+            .entry("Max Tokens", match config.max_tokens {
+                Some(tokens) => format!("{} (--max-tokens)", tokens),
+                None => DEFAULT_MAX_TOKENS.to_string(),
+            }),
+    };
+    
+    if let Some(dir) = &config.working_dir {
+        banner = banner.entry("Working Directory", format!("{} (--cwd)", dir.display()));
+    }
+    if config.backend == Backend::Claude && !config.claude_args.is_empty() {
+        let args: Vec<_> = config.claude_args.iter().map(|arg| shell_quote(arg)).collect();
+        banner = banner.entry("Claude Arguments", format!("{} (--claude-arg)", args.join(" ")));
+    }
+    if config.backend == Backend::Claude && !config.disallowed_tools.is_empty() {
+        banner = banner.entry("Disallowed Tools", format!("{} ({})", config.disallowed_tools.join(", "), sources.disallowed_tools));
+    }
+    if config.backend == Backend::Claude && !config.mcp_config.is_empty() {
+        let names: Vec<String> = mcp::servers(&config.mcp_config, None)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|server| server.name)
+            .collect();
+        let strict = if config.strict_mcp_config { ", only these" } else { "" };
+        banner = banner.entry("MCP Servers", format!("{} (--mcp-config{})", names.join(", "), strict));
+    }
+    let mut commands = CommandRegistry::new();
+    let _ = commands.restrict(config.allowed_commands.as_deref(), &config.disallowed_commands);
+    let names = |names: Vec<&str>| names.iter().map(|name| format!("/{}", name)).collect::<Vec<_>>().join(", ");
+    if config.allowed_commands.is_some() {
+        let enabled = commands.commands().iter().map(|spec| spec.name).filter(|name| commands.is_enabled(name)).collect();
+        banner = banner.entry("Commands", format!("only {} ({})", names(enabled), sources.commands));
+    } else if !commands.disabled().is_empty() {
+        banner = banner.entry("Commands", format!("{} disabled ({})", names(commands.disabled().to_vec()), sources.commands));
+    }
+    
+    if config.final_only {
+        banner = banner.entry("Output", "final answer only (--final-only)");
+    } else if config.stream {
+        banner = banner.entry("Output", "streamed as generated (--stream)");
+    } else if config.quiz {
+        banner = banner.entry("Output", "answers hidden until Enter is pressed, timed (--quiz)");
+    }
+    if config.raw {
+        banner = banner.entry("Markdown", "printed as written, not rendered");
+    } else if config.no_highlight {
+        banner = banner.entry("Markdown", "rendered, code blocks not highlighted");
+    }
+    if config.show_usage {
+        banner = banner.entry("Usage", "tokens and estimated cost shown after each turn");
+    }
+    if let Some(timeout) = config.timeout {
+        banner = banner.entry("Timeout", format!("commands stopped after {}s", timeout.as_secs()));
+    }
+    if config.dry_run {
+        banner = banner.entry("Dry run", "claude command lines printed, not run");
+    }
+    if config.test_script.is_some() {
+        banner = banner.entry("Test backend", "prompts answered locally, nothing sent (--test-backend)");
+    }
+    if config.masker.is_some() {
+        let transcripts = if config.mask_transcripts { "and transcripts" } else { "only, not in transcripts" };
+        banner = banner.entry("Masking", format!("responses on screen {}", transcripts));
+    }
+    if let Some(tee) = &config.tee {
+        banner = banner.entry("Tee", format!("conversation mirrored to {}", tee.path().display()));
+    }
+    
+    let context = match config.context_budget {
+        Some(budget) => format!("{} tokens, {}", budget, config.context_strategy),
+        None => "unlimited".to_string(),
+    };
+    banner = banner.entry("Context", context);
+    
+    if let Some(language) = config.language {
+        banner = banner.entry("Language", format!("{} ({}); prompts in other languages ask for replies in them", language, sources.language));
+    }
+    
+    if config.spellcheck {
+        let word_lists = if config.dictionaries.is_empty() {
+            "system word list".to_string()
+        } else {
+            config.dictionaries.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+        };
+        banner = banner.entry("Spell check", format!("{} ({}); prompts with likely typos ask before sending", word_lists, sources.spellcheck));
+    }
+    
+    if config.turn_metadata {
+        banner = banner.entry("Metadata", "time, working directory and OS sent with every prompt");
+    }
+    if config.suggest_follow_ups {
+        banner = banner.entry("Follow-ups", "suggested after each answer; type a number to ask one");
+    }
+    
+    if let Some(path) = &config.import_file {
+        banner = banner.entry("Import", format!("{} ({}, --from)", path.display(), config.import_mode));
+    }
+    
+    if !config.personas.is_empty() {
+        let personas = config.personas
+            .iter()
+            .map(|persona| format!("{} ({})", persona.name, persona.route()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        banner = banner.entry("Personas", personas);
+    }
+    
+    if !config.aliases.is_empty() {
+        let aliases = config.aliases
+            .iter()
+            .map(|alias| format!("/{}", alias.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        banner = banner.entry("Aliases", aliases);
+    }
+    
+    let describe = |path: Option<&PathBuf>| match path {
+        _ if config.incognito => "off (--incognito)".to_string(),
+        Some(path) => path.display().to_string(),
+        None => "off".to_string(),
+    };
+    let journal = describe(config.journal_dir.as_ref());
+    let history = describe(config.history_file.as_ref());
+    if let Some(path) = &config.transcript_file {
+        banner = banner.entry("Transcript", path.display().to_string());
+    }
+    banner
+        .entry("Journal", journal)
+        .entry("History", history)
+        .tip(tips::rotating_tip())
+}
+
+/// Run a subcommand instead of the conversation
+async fn run_command(command: Command, paths: Option<&Paths>) -> Result<()> {
+    match command {
+        Command::Chat => unreachable!("conversations are started by main"),
+        Command::Paths => {
+            let paths = paths.context("Could not determine the home directory")?;
+            for (label, path) in paths.entries() {
+                println!("{:<8} {}", label, path.display());
+            }
+            if let Some(path) = policy::system_file() {
+                println!("{:<8} {}", "policy", path.display());
+            }
+            Ok(())
+        }
+        #[cfg(feature = "self-update")]
+        Command::SelfUpdate { check } => {
+            let outcome = tokio::task::spawn_blocking(move || update::self_update(check))
+                .await
+                .context("Self-update task failed")??;
+
+            match outcome {
+                UpdateOutcome::UpToDate(version) => {
+                    UI::print_info(&format!("claude-dialog {} is up to date", version));
+                }
+                UpdateOutcome::Available { current, latest } => {
+                    UI::print_info(&format!(
+                        "claude-dialog {} is available (installed: {}); run `claude-dialog self-update` to install it",
+                        latest, current
+                    ));
+                }
+                UpdateOutcome::Updated { from, to } => {
+                    UI::print_info(&format!("Updated claude-dialog from {} to {}", from, to));
+                }
+            }
+            Ok(())
+        }
+        #[cfg(not(feature = "self-update"))]
+        Command::SelfUpdate { .. } => {
+            anyhow::bail!("This build of claude-dialog does not include self-update; reinstall it with cargo or from a release binary")
+        }
+        Command::Whatsnew => {
+            let version = env!("CARGO_PKG_VERSION");
+            match tips::release_notes(version) {
+                Some(notes) => println!("{}", notes),
+                None => UI::print_info(&format!("There are no release notes for claude-dialog {}", version)),
+            }
+            Ok(())
+        }
+        Command::Present { file, speed, auto } => {
+            let turns = present::load(&file)?;
+            present::play(&turns, Playback { speed, auto }).await
+        }
+        Command::View { source } => {
+            // Files are named by their path, saved sessions by their ID
+            let path = Path::new(&source);
+            let turns = if path.is_file() {
+                present::load(path)?
+            } else {
+                let paths = paths.context("Could not determine the home directory")?;
+                SessionStore::new(paths.sessions_dir()).load(&source)?.turns
+            };
+            viewer::view(viewer::lines(&turns))
+        }
+        Command::Prompts { action: PromptsCommand::List } => {
+            let paths = paths.context("Could not determine the home directory")?;
+            let library = PromptLibrary::new(paths.prompts_dir());
+            let presets = library.list()?;
+            if presets.is_empty() {
+                UI::print_info(&format!(
+                    "There are no presets yet; save system prompts as Markdown files in {}",
+                    library.dir().display()
+                ));
+            } else {
+                println!("{}", preset::table(&presets));
+            }
+            Ok(())
+        }
+        Command::Sessions { action } => {
+            let paths = paths.context("Could not determine the home directory")?;
+            let store = SessionStore::new(paths.sessions_dir());
+            match action {
+                SessionsCommand::List => {
+                    let sessions = store.list()?;
+                    if sessions.is_empty() {
+                        UI::print_info("There are no saved sessions yet; sessions are saved when they end");
+                    } else {
+                        println!("{}", sessions::table(&sessions));
+                    }
+                }
+                SessionsCommand::Show { id } => {
+                    let export = store.load(&id)?;
+                    for turn in &export.turns {
+                        println!("You> {}\n", turn.prompt.trim_end());
+                        if let Some(response) = &turn.response {
+                            println!("Claude> {}\n", response.trim_end());
+                        }
+                    }
+                    UI::print_info(&format!(
+                        "Continue this conversation with: claude-dialog --from {}",
+                        store.find(&id)?.display()
+                    ));
+                }
+                SessionsCommand::Delete { id } => {
+                    store.delete(&id)?;
+                    UI::print_info(&format!("Deleted session {}", id));
+                }
+                SessionsCommand::Merge { sources, labels, out } => {
+                    if labels.len() > sources.len() {
+                        anyhow::bail!("{} labels given for {} sessions", labels.len(), sources.len());
+                    }
+                    let mut conversations = Vec::new();
+                    for (index, source) in sources.iter().enumerate() {
+                        // Exported files are named by their path, saved sessions by their ID
+                        let path = Path::new(source);
+                        let (name, export) = if path.is_file() {
+                            let name = path.file_stem().map_or_else(|| source.clone(), |stem| stem.to_string_lossy().into_owned());
+                            (name, ConversationExport::load(path)?)
+                        } else {
+                            (source.clone(), store.load(source)?)
+                        };
+                        conversations.push((labels.get(index).cloned().unwrap_or(name), export));
+                    }
+                    
+                    let merged = ConversationExport::merge(conversations);
+                    match out.extension().and_then(|ext| ext.to_str()) {
+                        Some("md") => merged.transcript().save(&out)?,
+                        Some("html") => html::save(&merged.transcript(), &out)?,
+                        _ => merged.save(&out)?,
+                    }
+                    UI::print_info(&format!(
+                        "Merged {} turn(s) of {} sessions into {}",
+                        merged.turns.len(),
+                        sources.len(),
+                        out.display()
+                    ));
+                }
+                SessionsCommand::Archive { older_than } => {
+                    let (archived, savings) = store.archive(older_than)?;
+                    if archived.is_empty() {
+                        UI::print_info("There are no sessions to archive");
+                    } else {
+                        UI::print_info(&format!(
+                            "Archived {} session(s) into {} ({} KB compressed to {} KB)",
+                            archived.len(),
+                            store.archived().dir().display(),
+                            savings.original.div_ceil(1024),
+                            savings.compressed.div_ceil(1024)
+                        ));
+                    }
+                }
+            }
+            Ok(())
+        }
+        Command::History { action: HistoryCommand::Clear } => {
+            let paths = paths.context("Could not determine the home directory")?;
+            let path = paths.history_file();
+            if History::clear(&path)? {
+                UI::print_info(&format!("Deleted {}", path.display()));
+            } else {
+                UI::print_info("There is no input history to delete");
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,650 @@
+//! Markdown module
+//!
+//! Renders the Markdown of Claude's responses for the terminal: headings,
+//! bold, italic and struck-through text, inline code, links, lists, block
+//! quotes, rules and tables are shown with terminal styles instead of their
+//! markup. Code blocks keep their text as written, so they can be copied,
+//! and are syntax highlighted in the language of their fence (see
+//! [`crate::highlight`]) unless highlighting is turned off.
+//!
+//! Rendering works on a response as it streams: [`MarkdownRenderer::push`]
+//! renders every complete line, and as much of the line still arriving as
+//! cannot change: up to the last space before any markup left open, such
+//! as `**bold` whose closing `**` is yet to come. A line whose start may
+//! still turn out to be a heading, rule, table row or code fence waits
+//! until that is clear, and headings, code lines and table rows are
+//! rendered once complete. Table rows are held back until the table ends,
+//! to align its columns.
+//!
+//! `--raw` prints responses as they were received instead, which is also
+//! what happens when the output is not a colored terminal.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::markdown::MarkdownRenderer;
+//!
+//! colored::control::set_override(false);
+//!
+//! let mut renderer = MarkdownRenderer::new();
+//! let mut output = renderer.push("## Steps\n- run `cargo");
+//! assert_eq!(output, "Steps\n• run ");
+//! output += &renderer.push(" test`\n- fix **every** failure");
+//! output += &renderer.finish();
+//!
+//! assert_eq!(output, "Steps\n• run cargo test\n• fix every failure");
+//! ```
+
+use colored::*;
+use unicode_width::UnicodeWidthStr;
+#[cfg(feature = "highlight")]
+use crate::highlight::CodeHighlighter;
+
+/// Width of a horizontal rule, in columns
+const RULE_WIDTH: usize = 40;
+
+/// Renders Markdown for the terminal as it streams in
+#[derive(Debug)]
+pub struct MarkdownRenderer {
+    // Text after the last complete line, without what was rendered of it
+    pending: String,
+    // Whether the start of the pending line has been rendered
+    started: bool,
+    // Fence of the code block being rendered
+    fence: Option<String>,
+    table: Vec<String>,
+    #[cfg_attr(not(feature = "highlight"), allow(dead_code))]
+    highlight: bool,
+    // Highlighter of the code block being rendered, if its language is known
+    #[cfg(feature = "highlight")]
+    code: Option<CodeHighlighter>,
+}
+
+impl Default for MarkdownRenderer {
+    fn default() -> Self {
+        Self {
+            pending: String::new(),
+            started: false,
+            fence: None,
+            table: Vec::new(),
+            highlight: true,
+            #[cfg(feature = "highlight")]
+            code: None,
+        }
+    }
+}
+
+impl MarkdownRenderer {
+    /// Create a renderer at the start of a response
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Choose whether code blocks are syntax highlighted
+    ///
+    /// They are by default, as long as output is colored. Builds without the
+    /// `highlight` feature never highlight.
+    pub fn highlighting(mut self, highlight: bool) -> Self {
+        self.highlight = highlight;
+        self
+    }
+
+    /// Render a chunk of the response
+    ///
+    /// # Returns
+    ///
+    /// The rendered complete lines, each ending with a line break, followed
+    /// by what can be rendered yet of the line still arriving; empty if the
+    /// chunk settled nothing or only table rows.
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+        let mut output = String::new();
+        if let Some(end) = self.pending.rfind('\n') {
+            let complete: String = self.pending.drain(..=end).collect();
+            for line in complete.lines() {
+                self.render_line(line, &mut output);
+            }
+        }
+        self.render_partial(&mut output);
+        output
+    }
+
+    /// Render the part of the line still arriving that cannot change
+    fn render_partial(&mut self, output: &mut String) {
+        if self.fence.is_some() || !self.table.is_empty() {
+            return;
+        }
+        if !self.started {
+            let Some((prefix, start)) = line_start(&self.pending) else {
+                return;
+            };
+            output.push_str(&prefix);
+            self.pending.drain(..start);
+            self.started = true;
+        }
+        let (_, settled) = scan_spans(&self.pending);
+        if settled > 0 {
+            let text: String = self.pending.drain(..settled).collect();
+            output.push_str(&render_inline(&text));
+        }
+    }
+
+    /// Render what is left at the end of the response
+    ///
+    /// A last line without a line break is rendered without one.
+    pub fn finish(&mut self) -> String {
+        let mut output = String::new();
+        let last = std::mem::take(&mut self.pending);
+        if !last.is_empty() {
+            self.render_line(&last, &mut output);
+        }
+        self.flush_table(&mut output);
+        if !last.is_empty() {
+            output.pop();
+        }
+        self.started = false;
+        self.fence = None;
+        #[cfg(feature = "highlight")]
+        {
+            self.code = None;
+        }
+        output
+    }
+
+    fn render_line(&mut self, line: &str, output: &mut String) {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if self.started {
+            self.started = false;
+            output.push_str(&render_inline(line));
+            output.push('\n');
+            return;
+        }
+        let trimmed = line.trim_start();
+
+        if let Some(fence) = &self.fence {
+            if trimmed.starts_with(fence.as_str()) && trimmed.trim_end().chars().all(|c| c == '`' || c == '~') {
+                self.fence = None;
+                #[cfg(feature = "highlight")]
+                {
+                    self.code = None;
+                }
+                output.push_str(&format!("{}\n", line.dimmed()));
+            } else {
+                output.push_str(&self.render_code_line(line));
+                output.push('\n');
+            }
+            return;
+        }
+
+        if trimmed.starts_with('|') {
+            self.table.push(trimmed.trim_end().to_string());
+            return;
+        }
+        self.flush_table(output);
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = trimmed.chars().next().unwrap_or('`');
+            let fence: String = trimmed.chars().take_while(|&c| c == marker).collect();
+            #[cfg(feature = "highlight")]
+            if self.highlight && colored::control::SHOULD_COLORIZE.should_colorize() {
+                let language = trimmed[fence.len()..].split_whitespace().next().unwrap_or("");
+                self.code = CodeHighlighter::new(language);
+            }
+            self.fence = Some(fence);
+            output.push_str(&format!("{}\n", line.dimmed()));
+            return;
+        }
+
+        output.push_str(&render_block_line(line));
+        output.push('\n');
+    }
+
+    /// Render a line inside a code block
+    fn render_code_line(&mut self, line: &str) -> String {
+        #[cfg(feature = "highlight")]
+        if let Some(code) = &mut self.code {
+            return code.highlight(line);
+        }
+        line.to_string()
+    }
+
+    /// Render the table rows held back, aligning their columns
+    fn flush_table(&mut self, output: &mut String) {
+        let rows = std::mem::take(&mut self.table);
+        if rows.is_empty() {
+            return;
+        }
+
+        let cells: Vec<Vec<&str>> = rows.iter().map(|row| split_row(row)).collect();
+        let Some(alignments) = cells.get(1).and_then(|row| alignments(row)) else {
+            // Not a table after all
+            for row in &rows {
+                output.push_str(&render_inline(row));
+                output.push('\n');
+            }
+            return;
+        };
+
+        let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let rendered: Vec<Vec<(String, usize)>> = cells
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != 1)
+            .map(|(_, row)| row.iter().map(|cell| (render_inline(cell), plain_inline(cell).width())).collect())
+            .collect();
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| rendered.iter().filter_map(|row| row.get(column)).map(|&(_, width)| width).max().unwrap_or(0))
+            .collect();
+
+        for (index, row) in rendered.iter().enumerate() {
+            let line: Vec<String> = (0..columns)
+                .map(|column| {
+                    let (mut text, width) = row.get(column).cloned().unwrap_or_default();
+                    if index == 0 {
+                        text = cells[0].get(column).map(|cell| plain_inline(cell).bold().to_string()).unwrap_or_default();
+                    }
+                    let alignment = alignments.get(column).copied().unwrap_or(Alignment::Left);
+                    pad(&text, width, widths[column], alignment)
+                })
+                .collect();
+            output.push_str(line.join(&format!(" {} ", "│".dimmed())).trim_end());
+            output.push('\n');
+
+            if index == 0 {
+                let rule: Vec<String> = widths.iter().map(|&width| "─".repeat(width)).collect();
+                output.push_str(&format!("{}\n", rule.join("─┼─").dimmed()));
+            }
+        }
+    }
+}
+
+/// Render a whole Markdown text for the terminal
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::markdown::render;
+///
+/// colored::control::set_override(false);
+/// assert_eq!(render("# Title\n\n> **Note:** see [docs](https://docs.rs)\n"), "Title\n\n│ Note: see docs (https://docs.rs)\n");
+/// ```
+pub fn render(markdown: &str) -> String {
+    let mut renderer = MarkdownRenderer::new();
+    let output = renderer.push(markdown);
+    output + &renderer.finish()
+}
+
+/// How the cells of a table column are aligned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// The cells of a table row, without the outer pipes
+fn split_row(row: &str) -> Vec<&str> {
+    let inner = row.trim();
+    let inner = inner.strip_prefix('|').unwrap_or(inner);
+    let inner = inner.strip_suffix('|').unwrap_or(inner);
+    inner.split('|').map(str::trim).collect()
+}
+
+/// The column alignments of a table's separator row, or `None` if the row
+/// is not one
+fn alignments(row: &[&str]) -> Option<Vec<Alignment>> {
+    row.iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (false, true) => Alignment::Right,
+                _ => Alignment::Left,
+            })
+        })
+        .collect()
+}
+
+/// Pad rendered text of `width` columns to `column_width`
+fn pad(text: &str, width: usize, column_width: usize, alignment: Alignment) -> String {
+    let space = column_width.saturating_sub(width);
+    let (before, after) = match alignment {
+        Alignment::Left => (0, space),
+        Alignment::Right => (space, 0),
+        Alignment::Center => (space / 2, space - space / 2),
+    };
+    format!("{}{}{}", " ".repeat(before), text, " ".repeat(after))
+}
+
+/// Render a line outside code blocks and tables
+fn render_block_line(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+        let title = plain_inline(trimmed[hashes..].trim().trim_end_matches('#').trim_end());
+        return match hashes {
+            1 | 2 => title.bold().underline().to_string(),
+            _ => title.bold().to_string(),
+        };
+    }
+
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        return format!("{}{} {}", indent, "│".dimmed(), render_inline(quote.trim_start()));
+    }
+
+    let rule = trimmed.replace(' ', "");
+    if rule.len() >= 3 && ["-", "*", "_"].iter().any(|marker| rule.chars().all(|c| c.to_string() == *marker)) {
+        return format!("{}{}", indent, "─".repeat(RULE_WIDTH).dimmed());
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            let (marker, item) = if let Some(item) = item.strip_prefix("[ ] ") {
+                ("☐", item)
+            } else if let Some(item) = item.strip_prefix("[x] ").or_else(|| item.strip_prefix("[X] ")) {
+                ("☑", item)
+            } else {
+                ("•", item)
+            };
+            return format!("{}{} {}", indent, marker, render_inline(item));
+        }
+    }
+
+    format!("{}{}", indent, render_inline(trimmed))
+}
+
+/// The rendered start of an unfinished line and where its inline text
+/// begins, as [`render_block_line`] renders them
+///
+/// `None` while the line may still become a heading, rule, table row, code
+/// fence or task list item, and for headings, which are rendered whole.
+fn line_start(line: &str) -> Option<(String, usize)> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let first = trimmed.chars().next()?;
+
+    if trimmed.chars().count() < 2
+        || first == '|'
+        || trimmed.starts_with("```")
+        || trimmed.starts_with("~~~")
+        || (matches!(first, '-' | '*' | '_' | '`' | '~') && trimmed.chars().all(|c| c == first || c == ' '))
+    {
+        return None;
+    }
+
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if hashes == trimmed.len() || ((1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ')) {
+        return None;
+    }
+
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        let text = quote.trim_start();
+        return (!text.is_empty()).then(|| (format!("{}{} ", indent, "│".dimmed()), line.len() - text.len()));
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            if ["[ ] ", "[x] ", "[X] "].iter().any(|task| task.starts_with(item)) {
+                return None;
+            }
+            let (marker, text) = if let Some(text) = item.strip_prefix("[ ] ") {
+                ("☐", text)
+            } else if let Some(text) = item.strip_prefix("[x] ").or_else(|| item.strip_prefix("[X] ")) {
+                ("☑", text)
+            } else {
+                ("•", item)
+            };
+            return Some((format!("{}{} ", indent, marker), line.len() - text.len()));
+        }
+    }
+
+    Some((indent.to_string(), indent.len()))
+}
+
+/// A piece of inline Markdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Span {
+    Plain(String),
+    Bold(String),
+    Italic(String),
+    Strike(String),
+    Code(String),
+    Link { text: String, url: String },
+}
+
+/// Render inline Markdown with terminal styles
+fn render_inline(text: &str) -> String {
+    spans(text)
+        .into_iter()
+        .map(|span| match span {
+            Span::Plain(text) => text,
+            Span::Bold(text) => text.bold().to_string(),
+            Span::Italic(text) => text.italic().to_string(),
+            Span::Strike(text) => text.strikethrough().to_string(),
+            Span::Code(code) => code.cyan().to_string(),
+            Span::Link { text, url } if text == url => url.underline().to_string(),
+            Span::Link { text, url } => format!("{} {}", text.underline(), format!("({})", url).dimmed()),
+        })
+        .collect()
+}
+
+/// Inline Markdown without its markup, as shown without styles
+fn plain_inline(text: &str) -> String {
+    spans(text)
+        .into_iter()
+        .map(|span| match span {
+            Span::Plain(text) | Span::Bold(text) | Span::Italic(text) | Span::Strike(text) | Span::Code(text) => text,
+            Span::Link { text, url } if text == url => url,
+            Span::Link { text, url } => format!("{} ({})", text, url),
+        })
+        .collect()
+}
+
+/// Split inline Markdown into styled pieces
+///
+/// Markup without its closing counterpart is kept as text.
+fn spans(text: &str) -> Vec<Span> {
+    scan_spans(text).0
+}
+
+/// Split inline Markdown into styled pieces, and find how much of it
+/// renders the same whatever text follows
+///
+/// # Returns
+///
+/// The pieces, and the length of the text up to the last space outside
+/// them before the first markup left open that may yet be closed
+fn scan_spans(text: &str) -> (Vec<Span>, usize) {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    let mut settled = 0;
+    let mut open = false;
+
+    while let Some(c) = rest.chars().next() {
+        let after_word = plain.chars().last().is_some_and(char::is_alphanumeric);
+        let span = match c {
+            '\\' if rest[1..].starts_with(|c: char| c.is_ascii_punctuation()) => {
+                plain.push_str(&rest[1..2]);
+                rest = &rest[2..];
+                continue;
+            }
+            '`' => delimited(rest, "`").map(|(code, len)| (Span::Code(code.to_string()), len)),
+            '*' | '_' if rest.starts_with("**") || rest.starts_with("__") => {
+                delimited(rest, &rest[..2]).map(|(inner, len)| (Span::Bold(plain_inline(inner)), len))
+            }
+            '*' | '_' if !(c == '_' && after_word) => delimited(rest, &rest[..1])
+                .filter(|(inner, len)| {
+                    !inner.starts_with(' ') && (c == '*' || !rest[*len..].starts_with(char::is_alphanumeric))
+                })
+                .map(|(inner, len)| (Span::Italic(plain_inline(inner)), len)),
+            '~' if rest.starts_with("~~") => {
+                delimited(rest, "~~").map(|(inner, len)| (Span::Strike(plain_inline(inner)), len))
+            }
+            '[' => link(rest),
+            _ => None,
+        };
+
+        match span {
+            Some((span, len)) => {
+                if !plain.is_empty() {
+                    spans.push(Span::Plain(std::mem::take(&mut plain)));
+                }
+                spans.push(span);
+                rest = &rest[len..];
+            }
+            None => {
+                // A closing marker may still arrive, unless the marker is a
+                // product or an underscore within a word
+                open |= match c {
+                    '`' | '[' => true,
+                    '~' => rest.starts_with("~~"),
+                    '*' | '_' => !rest[1..].starts_with(' ') && (c == '*' || !after_word),
+                    _ => false,
+                };
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+                if !open && matches!(c, ' ' | '\t') {
+                    settled = text.len() - rest.len();
+                }
+            }
+        }
+    }
+
+    if !plain.is_empty() {
+        spans.push(Span::Plain(plain));
+    }
+    (spans, settled)
+}
+
+/// The text between `marker` at the start of `text` and the next `marker`,
+/// and the length of the whole
+fn delimited<'a>(text: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let inner = &text[marker.len()..];
+    let end = inner.find(marker)?;
+    (end > 0).then(|| (&inner[..end], marker.len() * 2 + end))
+}
+
+/// A `[text](url)` link at the start of `text`, and its length
+fn link(text: &str) -> Option<(Span, usize)> {
+    let close = text.find("](")?;
+    let end = close + 2 + text[close + 2..].find(')')?;
+    let label = &text[1..close];
+    let url = &text[close + 2..end];
+    if label.is_empty() || url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+    let span = Span::Link {
+        text: plain_inline(label),
+        url: url.to_string(),
+    };
+    Some((span, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_markup() {
+        assert_eq!(
+            spans("Use **bold**, *it*, `a*b`, ~~old~~ and [docs](https://docs.rs)"),
+            vec![
+                Span::Plain("Use ".to_string()),
+                Span::Bold("bold".to_string()),
+                Span::Plain(", ".to_string()),
+                Span::Italic("it".to_string()),
+                Span::Plain(", ".to_string()),
+                Span::Code("a*b".to_string()),
+                Span::Plain(", ".to_string()),
+                Span::Strike("old".to_string()),
+                Span::Plain(" and ".to_string()),
+                Span::Link { text: "docs".to_string(), url: "https://docs.rs".to_string() },
+            ]
+        );
+        // Identifiers, products and unclosed markers are text
+        assert_eq!(plain_inline("snake_case_name and 2 * 3 * 4 and a *b"), "snake_case_name and 2 * 3 * 4 and a *b");
+        assert_eq!(plain_inline(r"\*not italic\*"), "*not italic*");
+    }
+
+    #[test]
+    fn test_block_lines() {
+        colored::control::set_override(false);
+        assert_eq!(render_block_line("### Notes ###"), "Notes");
+        assert_eq!(render_block_line("  * [x] done"), "  ☑ done");
+        assert_eq!(render_block_line("1. first"), "1. first");
+        assert_eq!(render_block_line("- - -"), "─".repeat(RULE_WIDTH));
+        assert_eq!(render_block_line("#hashtag"), "#hashtag");
+    }
+
+    #[test]
+    fn test_code_blocks_are_kept_as_written() {
+        colored::control::set_override(false);
+        let markdown = "```rust\n# not a heading\n| not | a table |\nlet x = **y;\n```\n- item\n";
+        assert_eq!(render(markdown), "```rust\n# not a heading\n| not | a table |\nlet x = **y;\n```\n• item\n");
+    }
+
+    #[test]
+    fn test_tables_are_aligned() {
+        colored::control::set_override(false);
+        let markdown = "| Name | Size |\n|:-----|-----:|\n| `a.rs` | 12 |\n| longer name | 3 |\nAfter";
+        assert_eq!(
+            render(markdown),
+            "Name        │ Size\n────────────┼─────\na.rs        │   12\nlonger name │    3\nAfter"
+        );
+        // Rows without a separator are not a table
+        assert_eq!(render("| just | pipes |\n"), "| just | pipes |\n");
+    }
+
+    #[test]
+    fn test_streamed_chunks_render_like_the_whole() {
+        colored::control::set_override(false);
+        let markdown = "# Plan\n\n1. Read **all**\n| a | b |\n|---|---|\n| 1 | 2 |\n\n```\ncode\n```\nDone";
+        let mut renderer = MarkdownRenderer::new();
+        let mut streamed = String::new();
+        for chunk in markdown.as_bytes().chunks(3) {
+            streamed += &renderer.push(std::str::from_utf8(chunk).unwrap());
+        }
+        streamed += &renderer.finish();
+        assert_eq!(streamed, render(markdown));
+    }
+
+    #[test]
+    fn test_lines_render_while_they_arrive() {
+        colored::control::set_override(false);
+        let mut renderer = MarkdownRenderer::new();
+        assert_eq!(renderer.push("Use **two words** and `cargo"), "Use two words and ");
+        assert_eq!(renderer.push(" test` to check"), "cargo test to ");
+        assert_eq!(renderer.push(" it\n"), "check it\n");
+
+        // Undecided starts wait: a rule, a task, a fence, a heading
+        for start in ["- -", "- [", "``", "###", "  "] {
+            assert_eq!(MarkdownRenderer::new().push(start), "", "{:?}", start);
+        }
+        assert_eq!(MarkdownRenderer::new().push("> quoted text"), "│ quoted ");
+        assert_eq!(MarkdownRenderer::new().push("#hashtag and more"), "#hashtag and ");
+        assert_eq!(MarkdownRenderer::new().push("snake_case names, *a* b"), "snake_case names, a ");
+    }
+
+    #[test]
+    fn test_streamed_inline_markup_renders_like_the_whole() {
+        colored::control::set_override(false);
+        let markdown = "Intro with **bold text**, *italic*, a_b and [the docs](https://docs.rs) here\n\
+                        - [x] done ~~old~~ item\n> quote `x = 2 * 3` end\n1. **unclosed bold\n\
+                        tail _under_ and `open";
+        for size in 1..8 {
+            let mut renderer = MarkdownRenderer::new();
+            let mut streamed = String::new();
+            for chunk in markdown.as_bytes().chunks(size) {
+                streamed += &renderer.push(std::str::from_utf8(chunk).unwrap());
+            }
+            streamed += &renderer.finish();
+            assert_eq!(streamed, render(markdown), "chunks of {}", size);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! MCP server module
+//!
+//! The Claude CLI can use the tools of MCP (Model Context Protocol)
+//! servers. `--mcp-config <file>` hands it configuration files naming
+//! servers, in the format of a project's `.mcp.json`:
+//!
+//! ```json
+//! {
+//!   "mcpServers": {
+//!     "github": { "command": "npx", "args": ["-y", "@modelcontextprotocol/server-github"] },
+//!     "sentry": { "type": "http", "url": "https://mcp.sentry.dev/mcp" }
+//!   }
+//! }
+//! ```
+//!
+//! With `--strict-mcp-config`, Claude uses only those servers, not the ones
+//! of the project or added with `claude mcp add`. `/mcp` lists the servers
+//! of the files and of the working directory's `.mcp.json`; servers the
+//! user added to the Claude CLI itself are listed by `claude mcp list`.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::mcp;
+//! use std::fs;
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! let file = dir.path().join("mcp.json");
+//! fs::write(&file, r#"{"mcpServers": {"sentry": {"type": "http", "url": "https://mcp.sentry.dev/mcp"}}}"#).unwrap();
+//!
+//! let servers = mcp::load(&file).unwrap();
+//! assert_eq!(servers[0].name, "sentry");
+//! assert_eq!(servers[0].transport, "http");
+//! assert_eq!(servers[0].target, "https://mcp.sentry.dev/mcp");
+//! ```
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::claude_executor::shell_quote;
+
+/// The file of a project's MCP servers, in its root directory
+pub const PROJECT_FILE: &str = ".mcp.json";
+
+/// An MCP server named in a configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McpServer {
+    /// Name of the server; its tools are named `mcp__<name>__<tool>`
+    pub name: String,
+
+    /// How Claude talks to it: `stdio`, `http` or `sse`
+    pub transport: String,
+
+    /// The command line starting the server, or its URL
+    pub target: String,
+
+    /// The file naming the server
+    pub source: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(rename = "mcpServers", default)]
+    servers: BTreeMap<String, ServerEntry>,
+}
+
+// Unknown keys such as `env` and `headers` are the CLI's business
+#[derive(Debug, Deserialize)]
+struct ServerEntry {
+    #[serde(rename = "type")]
+    transport: Option<String>,
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    url: Option<String>,
+}
+
+/// Read the servers of an MCP configuration file, by name
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is not an MCP
+/// configuration, or names a server with neither a command nor a URL.
+pub fn load(path: &Path) -> Result<Vec<McpServer>> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read MCP configuration {}", path.display()))?;
+    let config: ConfigFile =
+        serde_json::from_str(&text).with_context(|| format!("Invalid MCP configuration {}", path.display()))?;
+
+    config
+        .servers
+        .into_iter()
+        .map(|(name, entry)| {
+            let (transport, target) = match (entry.command, entry.url) {
+                (Some(command), _) => {
+                    let mut line = shell_quote(&command).into_owned();
+                    for arg in &entry.args {
+                        line.push(' ');
+                        line.push_str(&shell_quote(arg));
+                    }
+                    (entry.transport.unwrap_or_else(|| "stdio".to_string()), line)
+                }
+                (None, Some(url)) => (entry.transport.unwrap_or_else(|| "http".to_string()), url),
+                (None, None) => bail!(
+                    "MCP server '{}' in {} has neither a command nor a url",
+                    name,
+                    path.display()
+                ),
+            };
+            Ok(McpServer {
+                name,
+                transport,
+                target,
+                source: path.to_path_buf(),
+            })
+        })
+        .collect()
+}
+
+/// The servers Claude is started with
+///
+/// # Arguments
+///
+/// * `files` - The `--mcp-config` files, in order
+/// * `project_dir` - The working directory whose `.mcp.json`, if any, is
+///   used as well, or `None` with `--strict-mcp-config`
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read, see [`load`].
+pub fn servers(files: &[PathBuf], project_dir: Option<&Path>) -> Result<Vec<McpServer>> {
+    let mut servers = Vec::new();
+    for file in files {
+        servers.extend(load(file)?);
+    }
+    if let Some(project_file) = project_dir.map(|dir| dir.join(PROJECT_FILE)).filter(|file| file.is_file()) {
+        servers.extend(load(&project_file)?);
+    }
+    Ok(servers)
+}
+
+/// The servers as aligned `name  transport  target  (file)` lines
+///
+/// # Output Format
+///
+/// ```text
+/// github  stdio  npx -y @modelcontextprotocol/server-github  (mcp.json)
+/// sentry  http   https://mcp.sentry.dev/mcp                  (mcp.json)
+/// ```
+pub fn table(servers: &[McpServer]) -> String {
+    let name_width = servers.iter().map(|server| server.name.chars().count()).max().unwrap_or(0);
+    let transport_width = servers.iter().map(|server| server.transport.len()).max().unwrap_or(0);
+    let target_width = servers.iter().map(|server| server.target.chars().count()).max().unwrap_or(0);
+    servers
+        .iter()
+        .map(|server| {
+            let file = server.source.file_name().map_or_else(|| server.source.display().to_string(), |name| name.to_string_lossy().into_owned());
+            format!(
+                "{:<name_width$}  {:<transport_width$}  {:<target_width$}  ({})",
+                server.name, server.transport, server.target, file
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_servers_of_files_and_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("tools.json");
+        fs::write(&file, r#"{"mcpServers": {"github": {"command": "npx", "args": ["-y", "server github"], "env": {"TOKEN": "x"}}}}"#).unwrap();
+        fs::write(dir.path().join(PROJECT_FILE), r#"{"mcpServers": {"docs": {"type": "sse", "url": "http://localhost:8080/sse"}}}"#).unwrap();
+
+        let servers = servers(std::slice::from_ref(&file), Some(dir.path())).unwrap();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].target, "npx -y 'server github'");
+        assert_eq!(servers[0].transport, "stdio");
+        assert_eq!(table(&servers).lines().nth(1).unwrap(), "docs    sse    http://localhost:8080/sse  (.mcp.json)");
+        assert_eq!(super::servers(&[file], None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_configurations() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("mcp.json");
+        fs::write(&file, r#"{"mcpServers": {"broken": {"args": []}}}"#).unwrap();
+        assert!(load(&file).unwrap_err().to_string().contains("neither a command nor a url"));
+
+        fs::write(&file, r#"{"mcpServers": ["github"]}"#).unwrap();
+        assert!(load(&file).unwrap_err().to_string().starts_with("Invalid MCP configuration"));
+    }
+}
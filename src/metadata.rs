@@ -0,0 +1,93 @@
+//! Turn metadata module
+//!
+//! With `--turn-metadata`, every prompt sent ends with a short footer
+//! giving the local time, the working directory and the operating system,
+//! so that questions about "today", "this week" or relative paths are
+//! answered correctly without restating them each turn. The footer is added
+//! when the prompt is sent and is not part of the conversation: the input
+//! history, journal, transcripts and exports keep the prompt as typed.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::{FixedOffset, TimeZone};
+//! use claude_dialog::metadata::TurnMetadata;
+//!
+//! let offset = FixedOffset::east_opt(9 * 3600).unwrap();
+//! let metadata = TurnMetadata {
+//!     time: offset.with_ymd_and_hms(2026, 10, 17, 14, 2, 0).unwrap(),
+//!     cwd: Some("/home/ada/shop".into()),
+//!     os: "linux",
+//! };
+//! assert_eq!(
+//!     metadata.footer(),
+//!     "(Current time: Sat 2026-10-17 14:02 +09:00, CWD: /home/ada/shop, OS: linux)"
+//! );
+//! ```
+
+use chrono::{DateTime, FixedOffset, Local};
+use std::env;
+use std::path::PathBuf;
+
+/// The circumstances a prompt is sent in
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnMetadata {
+    /// Local time the prompt is sent at
+    pub time: DateTime<FixedOffset>,
+
+    /// Working directory, `None` if it cannot be determined
+    pub cwd: Option<PathBuf>,
+
+    /// Operating system, as in [`std::env::consts::OS`]
+    pub os: &'static str,
+}
+
+impl TurnMetadata {
+    /// Metadata of a prompt sent now
+    pub fn current() -> Self {
+        Self {
+            time: Local::now().fixed_offset(),
+            cwd: env::current_dir().ok(),
+            os: env::consts::OS,
+        }
+    }
+
+    /// The footer appended to the prompt
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    /// (Current time: Sat 2026-10-17 14:02 +09:00, CWD: /home/ada/shop, OS: linux)
+    /// ```
+    pub fn footer(&self) -> String {
+        let mut footer = format!("(Current time: {}", self.time.format("%a %Y-%m-%d %H:%M %:z"));
+        if let Some(cwd) = &self.cwd {
+            footer.push_str(&format!(", CWD: {}", cwd.display()));
+        }
+        footer.push_str(&format!(", OS: {})", self.os));
+        footer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_footer_without_working_directory() {
+        let metadata = TurnMetadata {
+            time: FixedOffset::west_opt(5 * 3600).unwrap().with_ymd_and_hms(2026, 1, 5, 9, 30, 0).unwrap(),
+            cwd: None,
+            os: "macos",
+        };
+        assert_eq!(metadata.footer(), "(Current time: Mon 2026-01-05 09:30 -05:00, OS: macos)");
+    }
+
+    #[test]
+    fn test_current() {
+        let metadata = TurnMetadata::current();
+        assert_eq!(metadata.os, env::consts::OS);
+        assert_eq!(metadata.cwd, env::current_dir().ok());
+    }
+}
@@ -0,0 +1,179 @@
+//! Format migration module
+//!
+//! Files claude-dialog keeps between sessions carry a format version: the
+//! `version` key of `config.toml` and the `version` of a journal's start
+//! entry. A [`Schema`] knows the current version of a format and the
+//! [`Migration`]s that upgrade older documents one version at a time, so a
+//! file written by an older release is read as the current release expects
+//! instead of being rejected or misread. Files without a version are
+//! version 0, from before formats were versioned.
+//!
+//! A file written by a newer release is refused with a message asking for
+//! an update, never rewritten. Files rewritten by a migration are first
+//! copied with [`backup`], so nothing the user wrote can be lost.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::migrate::{Migration, Schema};
+//!
+//! // Version 1 renamed `tools` to `allowed_tools`
+//! static SCHEMA: Schema<Vec<(String, String)>> = Schema {
+//!     name: "the settings",
+//!     version: 1,
+//!     migrations: &[Migration {
+//!         from: 0,
+//!         summary: "renamed `tools` to `allowed_tools`",
+//!         apply: |settings| {
+//!             for (key, _) in settings.iter_mut().filter(|(key, _)| key == "tools") {
+//!                 *key = "allowed_tools".to_string();
+//!             }
+//!             Ok(())
+//!         },
+//!     }],
+//! };
+//!
+//! let mut settings = vec![("tools".to_string(), "Read".to_string())];
+//! let applied = SCHEMA.migrate(&mut settings, 0).unwrap();
+//! assert_eq!(applied, ["renamed `tools` to `allowed_tools`"]);
+//! assert_eq!(settings[0].0, "allowed_tools");
+//!
+//! assert!(SCHEMA.migrate(&mut settings, 1).unwrap().is_empty());
+//! assert!(SCHEMA.migrate(&mut settings, 2).is_err());
+//! ```
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An upgrade of a document from one format version to the next
+pub struct Migration<T: 'static> {
+    /// Version the migration upgrades from, to `from + 1`
+    pub from: u32,
+
+    /// What the migration changes, shown when a file is rewritten
+    pub summary: &'static str,
+
+    /// Change a document of version `from` into one of the next version
+    pub apply: fn(&mut T) -> Result<()>,
+}
+
+/// A versioned file format and the migrations up to its current version
+pub struct Schema<T: 'static> {
+    /// What the documents are, for messages, e.g. "The configuration file"
+    pub name: &'static str,
+
+    /// Version written by this release
+    pub version: u32,
+
+    /// One migration for each version before [`Schema::version`], in order
+    pub migrations: &'static [Migration<T>],
+}
+
+impl<T> Schema<T> {
+    /// Upgrade a document of version `from` to the current version
+    ///
+    /// # Returns
+    ///
+    /// The summaries of the migrations applied, oldest first; empty if the
+    /// document is current already
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document was written by a newer release, or
+    /// a migration fails.
+    pub fn migrate(&self, document: &mut T, from: u32) -> Result<Vec<&'static str>> {
+        if from > self.version {
+            bail!(
+                "{} has format version {}, but this claude-dialog reads version {} at most; update claude-dialog to use it",
+                capitalized(self.name),
+                from,
+                self.version
+            );
+        }
+
+        let mut applied = Vec::new();
+        for version in from..self.version {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|migration| migration.from == version)
+                .ok_or_else(|| anyhow!("No migration of {} from format version {}", self.name, version))?;
+            (migration.apply)(document)
+                .with_context(|| format!("Failed to migrate {} from format version {}", self.name, version))?;
+            applied.push(migration.summary);
+        }
+        Ok(applied)
+    }
+}
+
+/// Copy a file about to be rewritten by a migration next to it, as
+/// `<name>.v<version>-<time>.bak`
+///
+/// # Errors
+///
+/// Returns an error if the copy cannot be written.
+pub fn backup(path: &Path, version: u32) -> Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}-{}.bak", version, Local::now().format("%Y%m%d-%H%M%S")));
+    let backup = path.with_file_name(name);
+
+    fs::copy(path, &backup).with_context(|| format!("Failed to back up {} to {}", path.display(), backup.display()))?;
+    Ok(backup)
+}
+
+/// `name` with its first letter in upper case, to start a sentence
+fn capitalized(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static STEPS: Schema<Vec<u32>> = Schema {
+        name: "the step list",
+        version: 2,
+        migrations: &[
+            Migration { from: 0, summary: "first", apply: |steps| { steps.push(1); Ok(()) } },
+            Migration { from: 1, summary: "second", apply: |steps| { steps.push(2); Ok(()) } },
+        ],
+    };
+
+    #[test]
+    fn test_migrations_run_in_order_from_the_document_version() {
+        let mut steps = Vec::new();
+        assert_eq!(STEPS.migrate(&mut steps, 0).unwrap(), ["first", "second"]);
+        assert_eq!(steps, [1, 2]);
+
+        let mut steps = Vec::new();
+        assert_eq!(STEPS.migrate(&mut steps, 1).unwrap(), ["second"]);
+        assert_eq!(steps, [2]);
+    }
+
+    #[test]
+    fn test_newer_documents_are_refused() {
+        let err = STEPS.migrate(&mut Vec::new(), 3).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The step list has format version 3, but this claude-dialog reads version 2 at most; update claude-dialog to use it"
+        );
+    }
+
+    #[test]
+    fn test_backup_keeps_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "# my settings\nmodel = \"opus\"\n").unwrap();
+
+        let backup = backup(&path, 0).unwrap();
+        let name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("config.toml.v0-") && name.ends_with(".bak"));
+        assert_eq!(fs::read_to_string(backup).unwrap(), "# my settings\nmodel = \"opus\"\n");
+    }
+}
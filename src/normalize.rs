@@ -0,0 +1,160 @@
+//! Response normalization module
+//!
+//! Claude's Markdown is usually well formed, but a response cut short, or
+//! one the model slipped on, can leave a code block open, which swallows
+//! the rest of an exported transcript, and tabs in prose render at
+//! whatever width the viewer picks. Before a response is recorded in the
+//! conversation and the transcript, [`normalize`]:
+//!
+//! - Closes a code block left open at the end, with the fence it was opened
+//!   with.
+//! - Replaces tabs outside code blocks with [`TAB_WIDTH`] spaces. Tabs in
+//!   code blocks are kept, as a Makefile needs them.
+//! - Collects the languages of the code blocks, which are recorded with the
+//!   turn (see [`crate::conversation::Turn::code_languages`]).
+//!
+//! Responses are shown in the terminal as they arrive; only what is
+//! recorded is normalized. Normalizing a normalized response changes
+//! nothing.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::normalize::normalize;
+//!
+//! let response = normalize("Run:\n\n```sh\ncargo test");
+//! assert_eq!(response.text, "Run:\n\n```sh\ncargo test\n```");
+//! assert_eq!(response.code_languages, ["sh"]);
+//! ```
+
+/// Spaces a tab outside code blocks is replaced with
+pub const TAB_WIDTH: usize = 4;
+
+/// A response as it is recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized {
+    /// The response with its code blocks closed and stray tabs replaced
+    pub text: String,
+
+    /// Languages of the code blocks, lowercased, in order of first
+    /// appearance; blocks without one are not counted
+    pub code_languages: Vec<String>,
+}
+
+/// The fence a code block was opened with, e.g. ```` ``` ```` or `~~~~`
+struct Fence {
+    marker: char,
+    length: usize,
+}
+
+impl Fence {
+    /// The fence a line opens a code block with, if it does, and the info
+    /// string following it
+    fn open(line: &str) -> Option<(Fence, &str)> {
+        let trimmed = indented(line)?;
+        let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+        let length = trimmed.chars().take_while(|&c| c == marker).count();
+        let info = trimmed[length..].trim();
+        // A backtick fence's info string cannot hold backticks: ```a``` is
+        // inline code
+        if length < 3 || (marker == '`' && info.contains('`')) {
+            return None;
+        }
+        Some((Fence { marker, length }, info))
+    }
+
+    /// Whether a line closes the code block opened with this fence
+    fn closes(&self, line: &str) -> bool {
+        indented(line).is_some_and(|trimmed| {
+            let trimmed = trimmed.trim_end();
+            trimmed.chars().all(|c| c == self.marker) && trimmed.chars().count() >= self.length
+        })
+    }
+
+    fn to_line(&self) -> String {
+        self.marker.to_string().repeat(self.length)
+    }
+}
+
+/// The line without the up to three spaces a fence may be indented by
+fn indented(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start_matches(' ');
+    (line.len() - trimmed.len() <= 3).then_some(trimmed)
+}
+
+/// Normalize a response for the record
+///
+/// # Arguments
+///
+/// * `response` - The response as Claude sent it
+pub fn normalize(response: &str) -> Normalized {
+    let mut text = String::with_capacity(response.len());
+    let mut code_languages: Vec<String> = Vec::new();
+    let mut fence: Option<Fence> = None;
+
+    for line in response.split_inclusive('\n') {
+        match &fence {
+            Some(open) => {
+                if open.closes(line) {
+                    fence = None;
+                }
+                text.push_str(line);
+            }
+            None => {
+                if let Some((open, info)) = Fence::open(line) {
+                    if let Some(language) = info.split_whitespace().next().map(str::to_lowercase)
+                        && !code_languages.contains(&language)
+                    {
+                        code_languages.push(language);
+                    }
+                    fence = Some(open);
+                    text.push_str(line);
+                } else {
+                    text.push_str(&line.replace('\t', &" ".repeat(TAB_WIDTH)));
+                }
+            }
+        }
+    }
+
+    if let Some(open) = fence {
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&open.to_line());
+    }
+
+    Normalized { text, code_languages }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unclosed_code_blocks() {
+        assert_eq!(normalize("```\nfn main() {}\n").text, "```\nfn main() {}\n```");
+        assert_eq!(normalize("~~~~toml\n```\n[package]").text, "~~~~toml\n```\n[package]\n~~~~");
+        assert_eq!(normalize("````md\n```rust\n```\n````\n").text, "````md\n```rust\n```\n````\n");
+        assert_eq!(normalize("Use ```a``` here").text, "Use ```a``` here");
+    }
+
+    #[test]
+    fn test_tabs() {
+        let response = "Steps:\n\t1. Build\n```make\nall:\n\tcargo build\n```\n";
+        assert_eq!(normalize(response).text, "Steps:\n    1. Build\n```make\nall:\n\tcargo build\n```\n");
+    }
+
+    #[test]
+    fn test_code_languages() {
+        let response = "```Rust\n```\n```\n```\n```toml title=Cargo.toml\n```\n```rust\n```";
+        assert_eq!(normalize(response).code_languages, ["rust", "toml"]);
+    }
+
+    #[test]
+    fn test_idempotent() {
+        for response in ["Plain text", "```py\nprint(1)", "a\tb\n```\n\tc", ""] {
+            let once = normalize(response);
+            assert_eq!(normalize(&once.text), once, "{:?}", response);
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Ollama backend module
+//!
+//! Sends prompts to a local [Ollama](https://ollama.com) server instead of
+//! the `claude` CLI, for offline or privacy-sensitive sessions with the same
+//! interface. [`OllamaExecutor`] implements [`ClaudeExecutor`], so the dialog
+//! loop streams, records and saves its responses like Claude's.
+//!
+//! Ollama's chat API is stateless: the executor keeps the messages of the
+//! conversation in a [`ChatHistory`] and sends them with every prompt. A
+//! command with [`SessionMode::Fresh`](crate::claude_executor::SessionMode::Fresh)
+//! starts over, just like the CLI without `--continue`. Ollama runs no
+//! tools, so the model can read the files included with `@file` but cannot
+//! edit the workspace.
+//!
+//! Local models have small context windows, and Ollama silently cuts off
+//! what does not fit. The conversation sent with a prompt is therefore kept
+//! within [`history_budget`] of the model's window: `--num-ctx`, which is
+//! also sent to the server as `num_ctx`, or else [`DEFAULT_NUM_CTX`].
+//!
+//! The server is expected at [`DEFAULT_HOST`] unless the `OLLAMA_HOST`
+//! environment variable, which Ollama itself reads, points elsewhere.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeOptions};
+//! use claude_dialog::ollama::OllamaExecutor;
+//! use claude_dialog::pipeline::ResponsePipeline;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let ollama = OllamaExecutor::from_env();
+//! let command = ClaudeInvocation {
+//!     prompt: "Why is the sky blue?".to_string(),
+//!     options: ClaudeOptions {
+//!         model: Some("llama3".to_string()),
+//!         ..Default::default()
+//!     },
+//! };
+//!
+//! let response = ollama.execute(command, ResponsePipeline::terminal()).await?;
+//! println!("\n{} characters", response.error_for_status()?.text.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Result, Context, bail};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{BufRead, BufReader, Read};
+use std::time::Duration;
+pub use crate::chat::ChatMessage;
+use crate::chat::{ChatHistory, ChatStream, failure};
+use crate::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeResponse};
+use crate::context::estimate_tokens;
+use crate::pipeline::ResponsePipeline;
+use crate::status::Usage;
+
+/// Address of a local Ollama server with the default configuration
+pub const DEFAULT_HOST: &str = "http://localhost:11434";
+
+/// Context window in tokens that Ollama gives a model unless told otherwise
+pub const DEFAULT_NUM_CTX: u32 = 4096;
+
+/// How long to wait for the server to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Body of a request to `/api/chat`
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<ChatOptions>,
+}
+
+/// Model parameters of a request to `/api/chat`
+#[derive(Debug, Serialize)]
+struct ChatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+}
+
+/// A line of a streamed `/api/chat` response
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ChatChunk {
+    message: Option<ChatMessage>,
+    error: Option<String>,
+    // Token counts, sent with the last chunk
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+}
+
+/// Executor sending prompts to an Ollama server
+///
+/// Responses are streamed unless the command asks for
+/// [`OutputFormat::StreamJson`](crate::claude_executor::OutputFormat::StreamJson),
+/// in which case the answer is sent once it is complete.
+#[derive(Debug)]
+pub struct OllamaExecutor {
+    host: String,
+    history: ChatHistory,
+}
+
+impl OllamaExecutor {
+    /// Create an executor for the server at `host`, e.g. `http://localhost:11434`
+    ///
+    /// A host without a scheme is reached over `http`.
+    pub fn new(host: &str) -> Self {
+        let host = host.trim().trim_end_matches('/');
+        let host = if host.contains("://") {
+            host.to_string()
+        } else {
+            format!("http://{}", host)
+        };
+
+        Self {
+            host,
+            history: ChatHistory::new(),
+        }
+    }
+
+    /// Create an executor for the server in `OLLAMA_HOST`, or [`DEFAULT_HOST`]
+    pub fn from_env() -> Self {
+        match env::var("OLLAMA_HOST") {
+            Ok(host) if !host.trim().is_empty() => Self::new(&host),
+            _ => Self::new(DEFAULT_HOST),
+        }
+    }
+
+    /// Address of the server, including the scheme
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Messages exchanged so far, oldest first
+    pub fn history(&self) -> Vec<ChatMessage> {
+        self.history.messages()
+    }
+}
+
+#[async_trait]
+impl ClaudeExecutor for OllamaExecutor {
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+        let Some(model) = command.options.model.clone() else {
+            bail!("The ollama backend needs a model, e.g. --model llama3");
+        };
+        let system = command.options.system_prompt.clone().or_else(|| command.options.append_prompt.clone());
+        let temperature = command.options.temperature;
+        let num_ctx = command.options.num_ctx;
+        let budget = history_budget(num_ctx.unwrap_or(DEFAULT_NUM_CTX), system.as_deref());
+        let url = format!("{}/api/chat", self.host);
+        let host = self.host.clone();
+
+        self.history
+            .exchange(&command, pipeline, budget, move |history, stream| {
+                let mut messages = Vec::new();
+                if let Some(system) = &system {
+                    messages.push(ChatMessage::new("system", system.as_str()));
+                }
+                messages.extend(history);
+                let request = ChatRequest {
+                    model: model.clone(),
+                    messages,
+                    stream: true,
+                    options: (temperature.is_some() || num_ctx.is_some()).then_some(ChatOptions { temperature, num_ctx }),
+                };
+                chat(&url, &request, stream)
+                    .with_context(|| format!("Could not reach Ollama at {}; is `ollama serve` running?", host))
+            })
+            .await
+    }
+}
+
+/// Most tokens of the conversation sent with a prompt to a model with a
+/// context window of `num_ctx` tokens
+///
+/// A quarter of the window is left for the answer, and the system prompt
+/// is sent with every prompt as well.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::ollama::history_budget;
+///
+/// assert_eq!(history_budget(4096, None), 3072);
+/// assert_eq!(history_budget(4096, Some(&"x".repeat(4000))), 2072);
+/// ```
+pub fn history_budget(num_ctx: u32, system: Option<&str>) -> usize {
+    let window = num_ctx as usize;
+    (window - window / 4).saturating_sub(system.map_or(0, estimate_tokens))
+}
+
+/// Post a chat request and read the streamed answer
+///
+/// Every piece of the answer is sent to `stream` as it arrives, until the
+/// turn is cancelled. Errors reported by the server, such as an unknown model, are
+/// returned as an unsuccessful [`ClaudeResponse`].
+///
+/// # Errors
+///
+/// Returns an error if the server cannot be reached.
+fn chat(url: &str, request: &ChatRequest, stream: &ChatStream) -> Result<ClaudeResponse> {
+    let agent = ureq::AgentBuilder::new().timeout_connect(CONNECT_TIMEOUT).build();
+    let body = match agent.post(url).send_json(request) {
+        Ok(response) => response.into_reader(),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            let message = serde_json::from_str::<ChatChunk>(&body)
+                .ok()
+                .and_then(|chunk| chunk.error)
+                .unwrap_or(body);
+            return Ok(failure(format!("Ollama error (HTTP {}): {}", status, message.trim())));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    read_chunks(body, stream)
+}
+
+/// Collect the answer from the lines of a streamed response
+fn read_chunks(body: impl Read, stream: &ChatStream) -> Result<ClaudeResponse> {
+    let mut text = String::new();
+    let mut usage = None;
+    for line in BufReader::new(body).lines() {
+        if stream.is_cancelled() {
+            bail!("The turn was cancelled");
+        }
+        let line = line.context("Failed to read the Ollama response")?;
+        let Ok(chunk) = serde_json::from_str::<ChatChunk>(&line) else {
+            continue;
+        };
+
+        if let Some(error) = chunk.error {
+            let mut response = failure(format!("Ollama error: {}", error));
+            response.text = text;
+            return Ok(response);
+        }
+        if let Some(message) = chunk.message {
+            stream.send(&message.content);
+            text.push_str(&message.content);
+        }
+        if chunk.prompt_eval_count.is_some() || chunk.eval_count.is_some() {
+            usage = Some(Usage {
+                input_tokens: chunk.prompt_eval_count.unwrap_or(0),
+                output_tokens: chunk.eval_count.unwrap_or(0),
+                cost_usd: None,
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(ClaudeResponse { usage, ..ClaudeResponse::new(text) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::PipelineCancel;
+
+    #[test]
+    fn test_host_normalization() {
+        assert_eq!(OllamaExecutor::new("http://localhost:11434/").host(), DEFAULT_HOST);
+        assert_eq!(OllamaExecutor::new("127.0.0.1:9999").host(), "http://127.0.0.1:9999");
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let body = concat!(
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"Hello\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\", world\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"eval_count\":3}\n",
+        );
+        let response = read_chunks(body.as_bytes(), &ChatStream::new(None, PipelineCancel::default())).unwrap();
+        assert_eq!(response.text, "Hello, world");
+        assert_eq!(response.usage, Some(Usage { input_tokens: 0, output_tokens: 3, cost_usd: None, ..Default::default() }));
+
+        let body = "{\"message\":{\"role\":\"assistant\",\"content\":\"Hi\"}}\n{\"error\":\"out of memory\"}\n";
+        let response = read_chunks(body.as_bytes(), &ChatStream::new(None, PipelineCancel::default())).unwrap();
+        assert!(!response.is_success());
+        assert_eq!(response.text, "Hi");
+        assert_eq!(response.stderr, "Ollama error: out of memory");
+    }
+}
@@ -0,0 +1,128 @@
+//! Machine-readable output module
+//!
+//! `--output-format json` makes `--prompt` and `--batch` print a record of
+//! every turn to standard output instead of the response text, one JSON
+//! object per line (JSON Lines), for scripts to parse. Responses are
+//! captured rather than shown, and messages such as the progress of a
+//! batch go to standard error, so standard output holds nothing but
+//! records.
+//!
+//! # Record Format
+//!
+//! ```json
+//! {"prompt":"Summarize @CHANGELOG.md","response":"...","model":"opus","duration_ms":5210,"exit_code":0,"session_id":"3f2a9c1e","usage":{"input_tokens":2000,"output_tokens":400,"cache_read_tokens":0,"cache_write_tokens":0,"cost_usd":0.012}}
+//! ```
+//!
+//! `model` is omitted for the CLI's default model, and `session_id` and
+//! `usage` when the backend did not report them. A prompt that could not
+//! be sent has an `error` instead of a response; a failed command has both.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::output::TurnRecord;
+//!
+//! let record = TurnRecord {
+//!     prompt: "What is 2 + 2?".to_string(),
+//!     response: Some("4".to_string()),
+//!     duration_ms: 812,
+//!     exit_code: Some(0),
+//!     ..Default::default()
+//! };
+//! assert_eq!(
+//!     record.to_json_line(),
+//!     r#"{"prompt":"What is 2 + 2?","response":"4","duration_ms":812,"exit_code":0}"#
+//! );
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::status::Usage;
+
+/// How `--prompt` and `--batch` print their turns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum PrintFormat {
+    /// The response text, as in a conversation
+    #[default]
+    Text,
+
+    /// A JSON record of each turn, one per line
+    Json,
+}
+
+impl fmt::Display for PrintFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PrintFormat::Text => "text",
+            PrintFormat::Json => "json",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A turn as printed with `--output-format json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    /// The prompt as given, before `@file` references were included
+    pub prompt: String,
+
+    /// The response, also of a command that failed; `None` if the prompt
+    /// was not sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+
+    /// Model the prompt was sent to, `None` for the CLI's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Time from sending the prompt to the end of the response
+    pub duration_ms: u64,
+
+    /// Exit code of the backend command, `None` if it was not run or was
+    /// terminated by a signal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// ID of the Claude session the turn belongs to, if it was reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+
+    /// Tokens and cost of the turn, if they were reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+
+    /// Why the prompt was not sent or the command failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TurnRecord {
+    /// The record as one line of JSON, without the line break
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).expect("turn records serialize to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_keep_multiline_responses_on_one_line() {
+        let record = TurnRecord {
+            prompt: "List two".to_string(),
+            response: Some("- a\n- b\n".to_string()),
+            usage: Some(Usage { input_tokens: 10, output_tokens: 4, ..Default::default() }),
+            error: Some("claude exited with status 1".to_string()),
+            ..Default::default()
+        };
+
+        let line = record.to_json_line();
+        assert!(!line.contains('\n'));
+        assert!(line.contains(r#""response":"- a\n- b\n""#));
+        assert!(line.contains(r#""usage":{"input_tokens":10,"output_tokens":4,"cache_read_tokens":0,"cache_write_tokens":0,"cost_usd":null}"#));
+        assert_eq!(serde_json::from_str::<TurnRecord>(&line).unwrap(), record);
+    }
+}
@@ -0,0 +1,179 @@
+//! Command palette module
+//!
+//! Ctrl+P at the `You>` prompt opens the command palette: the slash
+//! commands, the presets of the prompt library and recent prompts that
+//! match what was typed so far, best matches first and numbered. Typing the
+//! number of one puts it at the prompt to be edited and sent (see
+//! [`crate::line_editor`]). A query matches an entry when its characters
+//! appear in the entry in order, ignoring case, so `rsto` finds `/restore`;
+//! entries where they are close together and early come first.
+//!
+//! Saved sessions are not listed: they are resumed with `--from` when
+//! claude-dialog starts, not from within a session.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::palette::{self, Entry};
+//!
+//! let entries = [
+//!     Entry::command("restore", "Restore a checkpoint", true),
+//!     Entry::command("status", "Show the session status", false),
+//!     Entry::prompt("Refactor the parser"),
+//! ];
+//! let found = palette::search("rsto", &entries);
+//! assert_eq!(found.len(), 1);
+//! assert_eq!(found[0].text, "/restore ");
+//! ```
+
+/// Most entries shown for a query
+pub const MAX_ENTRIES: usize = 9;
+
+/// Characters of a prompt shown in the palette
+const MAX_PROMPT_CHARS: usize = 60;
+
+/// What an entry of the palette is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A slash command
+    Command,
+    /// A preset of the prompt library
+    Preset,
+    /// A prompt sent before
+    Prompt,
+}
+
+/// Something the palette can put at the prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// What kind of entry it is
+    pub kind: Kind,
+
+    /// The text put at the prompt when the entry is picked
+    pub text: String,
+
+    /// What the entry does, shown next to it
+    pub summary: String,
+}
+
+impl Entry {
+    /// The slash command `name`, followed by a space if it takes arguments
+    pub fn command(name: &str, summary: &str, takes_arguments: bool) -> Self {
+        let space = if takes_arguments { " " } else { "" };
+        Self {
+            kind: Kind::Command,
+            text: format!("/{}{}", name, space),
+            summary: summary.to_string(),
+        }
+    }
+
+    /// The preset `name`, used with `/preset`
+    pub fn preset(name: &str, summary: &str) -> Self {
+        Self {
+            kind: Kind::Preset,
+            text: format!("/preset {}", name),
+            summary: summary.to_string(),
+        }
+    }
+
+    /// A prompt sent before
+    pub fn prompt(prompt: &str) -> Self {
+        Self {
+            kind: Kind::Prompt,
+            text: prompt.to_string(),
+            summary: String::new(),
+        }
+    }
+
+    /// The line listing the entry
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    /// /restore                 Restore a checkpoint
+    /// prompt                   Refactor the parser so that…
+    /// ```
+    pub fn line(&self) -> String {
+        match self.kind {
+            Kind::Command | Kind::Preset => format!("{:<24} {}", self.text.trim_end(), self.summary),
+            Kind::Prompt => {
+                let first = self.text.lines().next().unwrap_or_default();
+                let mut shown: String = first.chars().take(MAX_PROMPT_CHARS).collect();
+                if shown.len() < self.text.len() {
+                    shown.push('…');
+                }
+                format!("{:<24} {}", "prompt", shown)
+            }
+        }
+    }
+}
+
+/// The entries matching `query`, best first, at most [`MAX_ENTRIES`]
+///
+/// An empty query matches every entry, in the order given.
+pub fn search<'a>(query: &str, entries: &'a [Entry]) -> Vec<&'a Entry> {
+    let query = query.trim();
+    let mut found: Vec<(usize, usize, &Entry)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| score(query, &entry.text).map(|score| (score, index, entry)))
+        .collect();
+    found.sort_by_key(|&(score, index, _)| (score, index));
+    found.into_iter().take(MAX_ENTRIES).map(|(_, _, entry)| entry).collect()
+}
+
+/// How far `text` is from `query`, lower being closer, or `None` if the
+/// characters of `query` do not appear in `text` in order
+///
+/// The score is the position of the first matched character plus the
+/// characters skipped between the matched ones.
+fn score(query: &str, text: &str) -> Option<usize> {
+    let mut text = text.chars().flat_map(char::to_lowercase).enumerate();
+    let mut first = None;
+    let mut previous = 0;
+    let mut skipped = 0;
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        let (position, _) = text.find(|&(_, c)| c == wanted)?;
+        match first {
+            None => first = Some(position),
+            Some(_) => skipped += position - previous - 1,
+        }
+        previous = position;
+    }
+    Some(first.unwrap_or(0) + skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        assert_eq!(score("", "/restore"), Some(0));
+        assert_eq!(score("/res", "/restore"), Some(0));
+        assert_eq!(score("rsto", "/restore"), Some(2));
+        assert_eq!(score("STAT", "/status"), Some(1));
+        assert_eq!(score("xyz", "/restore"), None);
+        assert_eq!(score("ts", "/status"), Some(5));
+    }
+
+    #[test]
+    fn test_closest_entries_come_first() {
+        let entries = [
+            Entry::prompt("Show the tests that fail"),
+            Entry::command("stats", "Show token usage", false),
+            Entry::command("status", "Show the session status", false),
+            Entry::preset("strict", "Strict reviewer"),
+        ];
+        let found: Vec<&str> = search("stat", &entries).iter().map(|entry| entry.text.as_str()).collect();
+        assert_eq!(found, ["/stats", "/status", "Show the tests that fail"]);
+        assert_eq!(search("", &entries).len(), 4);
+    }
+
+    #[test]
+    fn test_entry_lines() {
+        assert_eq!(Entry::command("cd", "Change directory", true).line(), format!("{:<24} Change directory", "/cd"));
+        let long = "x".repeat(80);
+        assert_eq!(Entry::prompt(&long).line(), format!("{:<24} {}…", "prompt", "x".repeat(60)));
+    }
+}
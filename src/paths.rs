@@ -0,0 +1,406 @@
+//! Application directories module
+//!
+//! Everything `claude-dialog` keeps on disk lives in one of four directories,
+//! resolved per platform with the `directories` crate: the XDG base
+//! directories on Linux, `~/Library` on macOS and the known folders on
+//! Windows. Package managers and installers can therefore rely on a single
+//! well-defined location for each kind of file.
+//!
+//! | Directory | Contents                           | Override                    |
+//! |-----------|------------------------------------|-----------------------------|
+//! | config    | `config.toml`, `prompts/`          | `CLAUDE_DIALOG_CONFIG_DIR`  |
+//! | data      | saved `sessions/`                  | `CLAUDE_DIALOG_DATA_DIR`    |
+//! | cache     | detected Claude CLI version        | `CLAUDE_DIALOG_CACHE_DIR`   |
+//! | state     | input history, recovery journals,  | `CLAUDE_DIALOG_STATE_DIR`   |
+//! |           | `crashes/` reports                 |                             |
+//!
+//! Run `claude-dialog paths` to print the resolved locations.
+//!
+//! User-supplied paths are passed through [`expand`], which resolves a
+//! leading `~` and `$VAR` / `${VAR}` references.
+//!
+//! The input history, recovery journals, saved sessions and crash reports
+//! hold prompts and answers, so they are created with [`private_file`] in
+//! directories made with [`create_private_dir`]: on Unix, readable only by
+//! the user whatever the umask.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use claude_dialog::paths::Paths;
+//!
+//! let paths = Paths::resolve().expect("home directory");
+//! println!("Prompts are loaded from {}", paths.prompts_dir().display());
+//! ```
+
+#[cfg(feature = "dialog")]
+use anyhow::{Context, Result, anyhow, bail};
+#[cfg(feature = "dialog")]
+use directories::{BaseDirs, ProjectDirs};
+#[cfg(feature = "dialog")]
+use std::env;
+#[cfg(feature = "dialog")]
+use std::ffi::{OsStr, OsString};
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+#[cfg(feature = "dialog")]
+use std::path::PathBuf;
+
+/// Directories used by `claude-dialog`
+#[cfg(feature = "dialog")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    /// User configuration
+    pub config_dir: PathBuf,
+
+    /// Persistent user data such as saved sessions
+    pub data_dir: PathBuf,
+
+    /// Disposable cached data
+    pub cache_dir: PathBuf,
+
+    /// Runtime state that should survive a restart, such as the input
+    /// history and journals
+    pub state_dir: PathBuf,
+}
+
+#[cfg(feature = "dialog")]
+impl Paths {
+    /// Resolve the directories for the current user
+    ///
+    /// Each directory can be overridden with its environment variable.
+    /// Returns `None` if a directory is not overridden and no home directory
+    /// can be determined.
+    pub fn resolve() -> Option<Self> {
+        Self::resolve_with(
+            |name| env::var_os(name),
+            ProjectDirs::from("", "", "claude-dialog"),
+        )
+    }
+
+    fn resolve_with(
+        var: impl Fn(&str) -> Option<OsString>,
+        project: Option<ProjectDirs>,
+    ) -> Option<Self> {
+        let dir = |name: &str, default: fn(&ProjectDirs) -> PathBuf| {
+            var(name)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| project.as_ref().map(default))
+        };
+
+        Some(Self {
+            config_dir: dir("CLAUDE_DIALOG_CONFIG_DIR", |p| p.config_dir().to_path_buf())?,
+            data_dir: dir("CLAUDE_DIALOG_DATA_DIR", |p| p.data_dir().to_path_buf())?,
+            cache_dir: dir("CLAUDE_DIALOG_CACHE_DIR", |p| p.cache_dir().to_path_buf())?,
+            // Only Linux has a dedicated state directory
+            state_dir: dir("CLAUDE_DIALOG_STATE_DIR", |p| {
+                p.state_dir()
+                    .map(|dir| dir.to_path_buf())
+                    .unwrap_or_else(|| p.data_local_dir().join("state"))
+            })?,
+        })
+    }
+
+    /// Configuration file
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    /// Directory of user-provided prompt files
+    pub fn prompts_dir(&self) -> PathBuf {
+        self.config_dir.join("prompts")
+    }
+
+    /// Input history file
+    pub fn history_file(&self) -> PathBuf {
+        self.state_dir.join("history")
+    }
+
+    /// Directory of saved sessions
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.data_dir.join("sessions")
+    }
+
+    /// Directory of crash recovery journals
+    pub fn journal_dir(&self) -> PathBuf {
+        self.state_dir.clone()
+    }
+
+    /// Directory of crash reports
+    pub fn crash_dir(&self) -> PathBuf {
+        self.state_dir.join("crashes")
+    }
+
+    /// Label and path of every location, for display
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use claude_dialog::paths::Paths;
+    ///
+    /// for (label, path) in Paths::resolve().unwrap().entries() {
+    ///     println!("{:<8} {}", label, path.display());
+    /// }
+    /// ```
+    pub fn entries(&self) -> Vec<(&'static str, PathBuf)> {
+        vec![
+            ("config", self.config_file()),
+            ("prompts", self.prompts_dir()),
+            ("history", self.history_file()),
+            ("sessions", self.sessions_dir()),
+            ("cache", self.cache_dir.clone()),
+            ("state", self.state_dir.clone()),
+            ("crashes", self.crash_dir()),
+        ]
+    }
+}
+
+/// Expand a leading `~` and environment variable references in a path
+///
+/// `$NAME` and `${NAME}` are replaced with the value of the environment
+/// variable; a `$` not followed by a variable name is kept as is. Paths that
+/// are not valid UTF-8 are returned unchanged.
+///
+/// # Errors
+///
+/// Returns an error if a referenced variable is not set, or the path starts
+/// with `~` and no home directory can be determined.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::paths::expand;
+/// use std::path::PathBuf;
+///
+/// assert_eq!(expand("prompts/$$1.md".as_ref()).unwrap(), PathBuf::from("prompts/$$1.md"));
+/// assert!(expand("~/prompts".as_ref()).unwrap().ends_with("prompts"));
+/// assert!(expand("${CLAUDE_DIALOG_SURELY_UNSET}/x".as_ref()).is_err());
+/// ```
+#[cfg(feature = "dialog")]
+pub fn expand(path: &OsStr) -> Result<PathBuf> {
+    let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    expand_with(path, home.as_deref(), |name| env::var(name).ok())
+}
+
+/// Resolve a directory to work in, such as `--cwd` or `/cd` names
+///
+/// The path is [expanded](expand) and taken relative to `base`.
+///
+/// # Returns
+///
+/// The absolute path of the directory, with symbolic links resolved
+///
+/// # Errors
+///
+/// Returns an error if the path cannot be expanded, does not exist or is
+/// not a directory.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::paths::directory;
+///
+/// let base = std::env::current_dir().unwrap();
+/// assert_eq!(directory("src".as_ref(), &base).unwrap(), base.join("src").canonicalize().unwrap());
+/// assert!(directory("Cargo.toml".as_ref(), &base).is_err());
+/// ```
+#[cfg(feature = "dialog")]
+pub fn directory(path: &OsStr, base: &Path) -> Result<PathBuf> {
+    let path = base.join(expand(path)?);
+    let resolved = path
+        .canonicalize()
+        .with_context(|| format!("No such directory: {}", path.display()))?;
+    if !resolved.is_dir() {
+        bail!("{} is not a directory", path.display());
+    }
+    Ok(resolved)
+}
+
+#[cfg(feature = "dialog")]
+fn expand_with(
+    path: &OsStr,
+    home: Option<&Path>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<PathBuf> {
+    let Some(text) = path.to_str() else {
+        return Ok(PathBuf::from(path));
+    };
+
+    let (home_relative, text) = match text.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(std::path::is_separator) => (true, rest),
+        _ => (false, text),
+    };
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let (name, consumed) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+
+        let value = var(name).ok_or_else(|| anyhow!("Environment variable ${} is not set", name))?;
+        expanded.push_str(&value);
+        rest = &after[consumed..];
+    }
+    expanded.push_str(rest);
+
+    if !home_relative {
+        return Ok(PathBuf::from(expanded));
+    }
+
+    let home = home.ok_or_else(|| anyhow!("Could not determine the home directory"))?;
+    Ok(home.join(expanded.trim_start_matches(std::path::is_separator)))
+}
+
+/// Create a directory and its missing parents, readable only by the user
+///
+/// On Unix the directories created get mode `0700`; existing ones are left
+/// as they are.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be created.
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Options for opening a file that, when they create it, is readable only
+/// by the user (mode `0600` on Unix)
+///
+/// # Examples
+///
+/// ```no_run
+/// use claude_dialog::paths::private_file;
+/// use std::io::Write;
+///
+/// let mut file = private_file().create(true).append(true).open("history")?;
+/// writeln!(file, "Explain lifetimes")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn private_file() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
+
+/// Write `contents` to a file created with [`private_file`], replacing the
+/// file if it exists
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or written.
+pub fn write_private(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    private_file()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?
+        .write_all(contents.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "dialog")]
+    fn expand_test(path: &str) -> Result<PathBuf> {
+        expand_with(path.as_ref(), Some(Path::new("/home/me")), |name| {
+            (name == "PROJECT").then(|| "demo".to_string())
+        })
+    }
+
+    #[cfg(feature = "dialog")]
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand_test("~").unwrap(), PathBuf::from("/home/me"));
+        assert_eq!(expand_test("~/p/$PROJECT.md").unwrap(), PathBuf::from("/home/me/p/demo.md"));
+        assert_eq!(expand_test("${PROJECT}_v2/$1/$").unwrap(), PathBuf::from("demo_v2/$1/$"));
+        assert_eq!(expand_test("~other/x").unwrap(), PathBuf::from("~other/x"));
+        assert_eq!(expand_test("a/${PROJECT").unwrap(), PathBuf::from("a/${PROJECT"));
+
+        let err = expand_test("$MISSING/x").unwrap_err();
+        assert_eq!(err.to_string(), "Environment variable $MISSING is not set");
+        assert!(expand_with("~/x".as_ref(), None, |_| None).is_err());
+    }
+
+    #[cfg(feature = "dialog")]
+    #[test]
+    fn test_overrides_take_precedence() {
+        let paths = Paths::resolve_with(
+            |name| Some(OsString::from(format!("/tmp/{}", name))),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(paths.config_file(), PathBuf::from("/tmp/CLAUDE_DIALOG_CONFIG_DIR/config.toml"));
+        assert_eq!(paths.sessions_dir(), PathBuf::from("/tmp/CLAUDE_DIALOG_DATA_DIR/sessions"));
+        assert_eq!(paths.journal_dir(), PathBuf::from("/tmp/CLAUDE_DIALOG_STATE_DIR"));
+        assert_eq!(paths.history_file(), PathBuf::from("/tmp/CLAUDE_DIALOG_STATE_DIR/history"));
+        assert_eq!(paths.crash_dir(), PathBuf::from("/tmp/CLAUDE_DIALOG_STATE_DIR/crashes"));
+    }
+
+    #[cfg(feature = "dialog")]
+    #[test]
+    fn test_missing_home_without_overrides() {
+        assert!(Paths::resolve_with(|_| None, None).is_none());
+        assert!(Paths::resolve_with(|_| Some(OsString::new()), None).is_none());
+    }
+
+    #[cfg(feature = "dialog")]
+    #[test]
+    fn test_platform_defaults() {
+        let project = ProjectDirs::from("", "", "claude-dialog");
+        let Some(paths) = Paths::resolve_with(|_| None, project.clone()) else {
+            return;
+        };
+
+        let project = project.unwrap();
+        assert_eq!(paths.config_dir, project.config_dir());
+        assert_eq!(paths.cache_dir, project.cache_dir());
+        assert_eq!(paths.entries().len(), 7);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_files_are_readable_only_by_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state/crashes");
+        create_private_dir(&state).unwrap();
+        let file = state.join("crash.txt");
+        write_private(&file, "secret").unwrap();
+        write_private(&file, "replaced").unwrap();
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir.path().join("state")), 0o700);
+        assert_eq!(mode(&state), 0o700);
+        assert_eq!(mode(&file), 0o600);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "replaced");
+    }
+}
@@ -0,0 +1,185 @@
+//! Persona module
+//!
+//! A persona names a backend and model, e.g. a local model for quick drafts
+//! and Claude for final answers. `/persona <name>` sends the following
+//! prompts to that persona within the same session; `/persona default` goes
+//! back to the backend and model the session started with.
+//!
+//! Each backend keeps its own conversation, so a backend joining the session
+//! has not seen the turns answered elsewhere. Those turns are sent along with
+//! its next prompt (see [`catch_up`]), so the final answer can build on the
+//! draft.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::claude_executor::Backend;
+//! use claude_dialog::persona::Persona;
+//!
+//! let persona: Persona = "final=claude:opus".parse().unwrap();
+//! assert_eq!(persona.name, "final");
+//! assert_eq!(persona.backend, Backend::Claude);
+//! assert_eq!(persona.model.as_deref(), Some("opus"));
+//! assert_eq!(persona.to_string(), "final=claude:opus");
+//! ```
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use crate::claude_executor::Backend;
+use crate::conversation::Turn;
+
+/// Persona name that stands for the session's own backend and model
+pub const DEFAULT_PERSONA: &str = "default";
+
+/// A named backend and model that prompts can be routed to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Persona {
+    /// Name used with `/persona`
+    pub name: String,
+
+    /// Where the persona's prompts are sent
+    pub backend: Backend,
+
+    /// Model of the persona, `None` for the backend's default
+    pub model: Option<String>,
+}
+
+impl Persona {
+    /// The backend and model, e.g. `ollama:llama3`
+    pub fn route(&self) -> String {
+        route_label(self.backend, self.model.as_deref())
+    }
+}
+
+impl fmt::Display for Persona {
+    /// Format the persona in its `NAME=BACKEND[:MODEL]` definition form
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.route())
+    }
+}
+
+impl TryFrom<String> for Persona {
+    type Error = anyhow::Error;
+
+    fn try_from(definition: String) -> Result<Self> {
+        definition.parse()
+    }
+}
+
+impl From<Persona> for String {
+    fn from(persona: Persona) -> Self {
+        persona.to_string()
+    }
+}
+
+impl FromStr for Persona {
+    type Err = anyhow::Error;
+
+    /// Parse a persona definition of the form `NAME=BACKEND[:MODEL]`
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, route) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Persona must have the form NAME=BACKEND[:MODEL], got '{}'", s))?;
+
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!("Invalid persona name: '{}'", name);
+        }
+        if name == DEFAULT_PERSONA {
+            bail!("'{}' is reserved for the session's own backend and model", DEFAULT_PERSONA);
+        }
+
+        let (backend, model) = match route.split_once(':') {
+            Some((backend, model)) => (backend, Some(model.trim())),
+            None => (route, None),
+        };
+        if model == Some("") {
+            bail!("Persona {} has an empty model name", name);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            backend: backend.parse()?,
+            model: model.map(String::from),
+        })
+    }
+}
+
+/// Describe a backend and model, e.g. `ollama:llama3` or `claude`
+pub fn route_label(backend: Backend, model: Option<&str>) -> String {
+    match model {
+        Some(model) => format!("{}:{}", backend, model),
+        None => backend.to_string(),
+    }
+}
+
+/// Prefix a prompt with turns the receiving backend has not seen
+///
+/// # Arguments
+///
+/// * `turns` - Turns answered by other backends, oldest first
+/// * `prompt` - The prompt to send
+///
+/// # Output Format
+///
+/// ```text
+/// Earlier in this conversation, answered by another model:
+///
+/// User: Draft a release note for 0.2
+///
+/// Assistant: ## 0.2 ...
+///
+/// ---
+///
+/// Polish the draft
+/// ```
+pub fn catch_up(turns: &[Turn], prompt: &str) -> String {
+    if turns.is_empty() {
+        return prompt.to_string();
+    }
+
+    let mut text = String::from("Earlier in this conversation, answered by another model:\n");
+    for turn in turns {
+        text.push_str(&format!("\nUser: {}\n", turn.prompt.trim_end()));
+        if let Some(response) = &turn.response {
+            text.push_str(&format!("\nAssistant: {}\n", response.trim_end()));
+        }
+    }
+    text.push_str("\n---\n\n");
+    text.push_str(prompt);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_definitions() {
+        assert!("final".parse::<Persona>().is_err());
+        assert!("=claude".parse::<Persona>().is_err());
+        assert!("default=claude".parse::<Persona>().is_err());
+        assert!("final=claude:".parse::<Persona>().is_err());
+        assert!("final=gpt:4".parse::<Persona>().is_err());
+
+        let persona: Persona = "final=Claude".parse().unwrap();
+        assert_eq!(persona.model, None);
+        assert_eq!(persona.to_string(), "final=claude");
+    }
+
+    #[test]
+    fn test_catch_up() {
+        assert_eq!(catch_up(&[], "Polish it"), "Polish it");
+
+        let mut draft = Turn::new("Draft a haiku");
+        draft.response = Some("Rust never sleeps\n".to_string());
+        let text = catch_up(&[draft, Turn::new("Unanswered")], "Polish it");
+        assert_eq!(
+            text,
+            "Earlier in this conversation, answered by another model:\n\nUser: Draft a haiku\n\nAssistant: Rust never sleeps\n\nUser: Unanswered\n\n---\n\nPolish it"
+        );
+    }
+}
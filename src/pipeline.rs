@@ -0,0 +1,445 @@
+//! Response pipeline module
+//!
+//! Claude's response is delivered in chunks as it is produced. The pipeline
+//! fans every chunk out to any number of [`ResponseSink`]s over a broadcast
+//! channel. Each sink runs in its own task, so a slow consumer such as a
+//! transcript writer or webhook does not hold up the terminal renderer, and
+//! all consumers see the response as it streams rather than after the fact.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::pipeline::{ResponsePipeline, ResponseSink};
+//! use anyhow::Result;
+//! use std::sync::{Arc, Mutex};
+//!
+//! struct WordCounter(Arc<Mutex<usize>>);
+//!
+//! impl ResponseSink for WordCounter {
+//!     fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+//!         *self.0.lock().unwrap() += chunk.split_whitespace().count();
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<()> {
+//! let words = Arc::new(Mutex::new(0));
+//!
+//! let mut pipeline = ResponsePipeline::new();
+//! pipeline.add_sink(WordCounter(words.clone()));
+//!
+//! pipeline.send("Hello there, ");
+//! pipeline.send("how are you?");
+//! pipeline.finish().await?;
+//!
+//! assert_eq!(*words.lock().unwrap(), 5);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Result, anyhow};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use crate::markdown::MarkdownRenderer;
+#[cfg(feature = "dialog")]
+use crate::mask::Masker;
+
+/// Number of chunks buffered per sink before a slow sink starts losing data
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// An event flowing through the pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseEvent {
+    /// A piece of response text
+    Chunk(Arc<str>),
+
+    /// The response is complete
+    Done,
+}
+
+/// A consumer of streamed response text
+///
+/// Sinks run in their own task and receive every chunk in order, followed by
+/// a single call to [`ResponseSink::finish`] once the response is complete.
+pub trait ResponseSink: Send + 'static {
+    /// Handle the next chunk of the response
+    fn write_chunk(&mut self, chunk: &str) -> Result<()>;
+
+    /// Handle the end of the response
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink that renders the response on standard output as it arrives
+#[derive(Debug, Default)]
+pub struct TerminalSink;
+
+impl ResponseSink for TerminalSink {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        write_stdout(chunk)
+    }
+}
+
+/// Sink that renders the response's Markdown on standard output as it
+/// arrives, see [`MarkdownRenderer`]
+#[derive(Debug, Default)]
+pub struct MarkdownSink {
+    renderer: MarkdownRenderer,
+}
+
+impl MarkdownSink {
+    /// Create a sink, choosing whether code blocks are syntax highlighted
+    pub fn new(highlight: bool) -> Self {
+        Self {
+            renderer: MarkdownRenderer::new().highlighting(highlight),
+        }
+    }
+}
+
+impl ResponseSink for MarkdownSink {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        write_stdout(&self.renderer.push(chunk))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        write_stdout(&self.renderer.finish())
+    }
+}
+
+/// Sink that masks a response a line at a time before handing it on
+///
+/// Lines are held back until they are complete, so that a match split
+/// across chunks is still masked.
+#[cfg(feature = "dialog")]
+#[derive(Debug)]
+pub struct MaskedSink<S> {
+    masker: Masker,
+    pending: String,
+    inner: S,
+}
+
+#[cfg(feature = "dialog")]
+impl<S: ResponseSink> MaskedSink<S> {
+    /// Mask the response before `inner` receives it
+    pub fn new(masker: Masker, inner: S) -> Self {
+        Self {
+            masker,
+            pending: String::new(),
+            inner,
+        }
+    }
+}
+
+#[cfg(feature = "dialog")]
+impl<S: ResponseSink> ResponseSink for MaskedSink<S> {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        self.pending.push_str(chunk);
+        let Some(end) = self.pending.rfind('\n') else {
+            return Ok(());
+        };
+        let lines: String = self.pending.drain(..=end).collect();
+        self.inner.write_chunk(&self.masker.mask(&lines))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let rest = std::mem::take(&mut self.pending);
+        if !rest.is_empty() {
+            self.inner.write_chunk(&self.masker.mask(&rest))?;
+        }
+        self.inner.finish()
+    }
+}
+
+/// Write text to standard output right away
+fn write_stdout(text: &str) -> Result<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    let mut stdout = io::stdout();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Stops a pipeline from outside the code feeding it
+///
+/// Once cancelled, chunks sent to the pipeline are dropped and sinks stop
+/// without writing the chunks still queued for them, e.g. because the turn
+/// the response belongs to was cancelled while a request thread still
+/// owns the pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineCancel(Arc<AtomicBool>);
+
+impl PipelineCancel {
+    /// Stop the pipeline
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the pipeline was stopped
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Fan-out of response chunks to concurrently running sinks
+///
+/// Sinks must be added before the first chunk is sent.
+pub struct ResponsePipeline {
+    sender: broadcast::Sender<ResponseEvent>,
+    tasks: Vec<JoinHandle<Result<()>>>,
+    cancel: PipelineCancel,
+}
+
+impl Default for ResponsePipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponsePipeline {
+    /// Create a pipeline without any sinks
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            tasks: Vec::new(),
+            cancel: PipelineCancel::default(),
+        }
+    }
+
+    /// Create a pipeline that renders to the terminal
+    pub fn terminal() -> Self {
+        let mut pipeline = Self::new();
+        pipeline.add_sink(TerminalSink);
+        pipeline
+    }
+
+    /// Create a pipeline that renders the response's Markdown to the
+    /// terminal
+    pub fn markdown() -> Self {
+        let mut pipeline = Self::new();
+        pipeline.add_sink(MarkdownSink::default());
+        pipeline
+    }
+
+    /// Number of sinks attached to the pipeline
+    pub fn sink_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// A handle that stops the pipeline, see [`PipelineCancel`]
+    pub fn canceller(&self) -> PipelineCancel {
+        self.cancel.clone()
+    }
+
+    /// Whether the pipeline was stopped through its [`PipelineCancel`]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Attach a sink, spawning the task that feeds it
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn add_sink<S: ResponseSink>(&mut self, mut sink: S) {
+        let mut receiver = self.sender.subscribe();
+        let cancel = self.cancel.clone();
+
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                let event = receiver.recv().await;
+                if cancel.is_cancelled() {
+                    return Ok(());
+                }
+                match event {
+                    Ok(ResponseEvent::Chunk(chunk)) => sink.write_chunk(&chunk)?,
+                    Ok(ResponseEvent::Done) | Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(skipped)) => {
+                        return Err(anyhow!("Response sink fell behind and missed {} chunk(s)", skipped));
+                    }
+                }
+            }
+            sink.finish()
+        }));
+    }
+
+    /// Send a chunk of response text to every sink
+    pub fn send(&self, chunk: &str) {
+        if chunk.is_empty() || self.is_cancelled() {
+            return;
+        }
+
+        // Sending only fails when no sink is attached, which is not an error
+        let _ = self.sender.send(ResponseEvent::Chunk(Arc::from(chunk)));
+    }
+
+    /// Signal the end of the response and wait for every sink to finish
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reported by a sink; all sinks are still
+    /// allowed to finish.
+    pub async fn finish(self) -> Result<()> {
+        let _ = self.sender.send(ResponseEvent::Done);
+        drop(self.sender);
+
+        let mut first_error = None;
+        for task in self.tasks {
+            let result = task
+                .await
+                .map_err(|e| anyhow!("Response sink task failed: {}", e))
+                .and_then(|result| result);
+            if let Err(e) = result {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Incremental UTF-8 decoder for byte chunks read from a pipe
+///
+/// A multi-byte character may be split across two reads; the incomplete
+/// tail is kept until the next chunk arrives. Invalid sequences are replaced
+/// with U+FFFD.
+#[derive(Debug, Default)]
+pub(crate) struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// Decode as much of `bytes` (plus any pending tail) as possible
+    pub(crate) fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+
+        let valid_up_to = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+
+        let complete: Vec<u8> = self.pending.drain(..valid_up_to).collect();
+        String::from_utf8_lossy(&complete).into_owned()
+    }
+
+    /// Decode whatever is left at the end of the stream
+    pub(crate) fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        String::from_utf8_lossy(&rest).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Collect(Arc<Mutex<String>>);
+
+    impl ResponseSink for Collect {
+        fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+            self.0.lock().unwrap().push_str(chunk);
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl ResponseSink for Failing {
+        fn write_chunk(&mut self, _chunk: &str) -> Result<()> {
+            Err(anyhow!("disk full"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_pipelines_write_nothing_more() {
+        let text = Arc::new(Mutex::new(String::new()));
+        let mut pipeline = ResponsePipeline::new();
+        pipeline.add_sink(Collect(text.clone()));
+
+        pipeline.send("Hello");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        pipeline.canceller().cancel();
+        pipeline.send(", world");
+        pipeline.finish().await.unwrap();
+        assert_eq!(*text.lock().unwrap(), "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_every_sink_receives_every_chunk() {
+        let first = Arc::new(Mutex::new(String::new()));
+        let second = Arc::new(Mutex::new(String::new()));
+
+        let mut pipeline = ResponsePipeline::new();
+        pipeline.add_sink(Collect(first.clone()));
+        pipeline.add_sink(Collect(second.clone()));
+        assert_eq!(pipeline.sink_count(), 2);
+
+        pipeline.send("Hello, ");
+        pipeline.send("");
+        pipeline.send("world");
+        pipeline.finish().await.unwrap();
+
+        assert_eq!(*first.lock().unwrap(), "Hello, world");
+        assert_eq!(*second.lock().unwrap(), "Hello, world");
+    }
+
+    #[cfg(feature = "dialog")]
+    #[tokio::test]
+    async fn test_masked_sink_masks_matches_split_across_chunks() {
+        let output = Arc::new(Mutex::new(String::new()));
+        let masker = Masker::new(&["secret".to_string()], &[], false).unwrap();
+
+        let mut pipeline = ResponsePipeline::new();
+        pipeline.add_sink(MaskedSink::new(masker, Collect(output.clone())));
+        pipeline.send("The sec");
+        pipeline.send("ret is\nstill secret");
+        pipeline.finish().await.unwrap();
+
+        assert_eq!(*output.lock().unwrap(), "The ██████ is\nstill ██████");
+    }
+
+    #[tokio::test]
+    async fn test_sink_errors_are_reported() {
+        let text = Arc::new(Mutex::new(String::new()));
+
+        let mut pipeline = ResponsePipeline::new();
+        pipeline.add_sink(Failing);
+        pipeline.add_sink(Collect(text.clone()));
+
+        pipeline.send("data");
+        let err = pipeline.finish().await.unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+        assert_eq!(*text.lock().unwrap(), "data");
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_without_sinks() {
+        let pipeline = ResponsePipeline::new();
+        pipeline.send("ignored");
+        pipeline.finish().await.unwrap();
+    }
+
+    #[test]
+    fn test_utf8_decoder_split_character() {
+        let bytes = "héllo".as_bytes();
+        let mut decoder = Utf8Decoder::default();
+
+        assert_eq!(decoder.decode(&bytes[..2]), "h");
+        assert_eq!(decoder.decode(&bytes[2..]), "éllo");
+        assert_eq!(decoder.finish(), "");
+
+        assert_eq!(decoder.decode(&[0xff, b'a']), "\u{fffd}a");
+        assert_eq!(decoder.decode(&[0xc3]), "");
+        assert_eq!(decoder.finish(), "\u{fffd}");
+    }
+}
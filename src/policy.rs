@@ -0,0 +1,388 @@
+//! Organization policy module
+//!
+//! An administrator can put a policy file where users cannot change it,
+//! `/etc/claude-dialog/policy.toml` (`%ProgramData%\claude-dialog\policy.toml`
+//! on Windows), to set limits that `config.toml`, profiles and command-line
+//! options cannot lift:
+//!
+//! ```toml
+//! max_context_budget = 50000           # tokens of included files per prompt
+//! max_session_cost_usd = 5.0           # estimated cost before prompts stop
+//! allowed_tools = ["Read", "Bash(git log:*)"]
+//! disallowed_tools = ["WebFetch"]
+//! disallowed_backends = ["ollama"]
+//! disallowed_commands = ["paste"]
+//! mask_pii = true
+//! mask_patterns = ['ACME-\d{6}']
+//! mask_words = ["customers.txt"]       # relative to the policy file
+//! mask_transcripts = true
+//! ```
+//!
+//! Every key is optional. Tools outside `allowed_tools` are dropped from
+//! whatever the session would allow, also when a system prompt's front
+//! matter changes them later; lists are added to the session's own and
+//! masking is turned on. A session whose backend or personas use a
+//! disallowed backend, or that passes `--claude-arg` or `--claude-bin`,
+//! does not start. claude-dialog only ever reads the
+//! file.
+//!
+//! `CLAUDE_DIALOG_POLICY_FILE` names a further policy file, applied on top
+//! of the system one: it can tighten the limits but not loosen them. A
+//! policy file that cannot be read or has unknown keys stops the start, so
+//! a typo cannot switch a limit off.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::claude_executor::Backend;
+//! use claude_dialog::policy::Policy;
+//!
+//! let policy = Policy::parse(r#"
+//!     max_context_budget = 50000
+//!     allowed_tools = ["Read", "Bash"]
+//!     disallowed_backends = ["ollama"]
+//! "#).unwrap();
+//!
+//! assert_eq!(policy.context_budget(Some(100_000)), Some(50_000));
+//! assert_eq!(policy.context_budget(None), Some(50_000));
+//!
+//! let (allowed, dropped) = policy.allowed_tools(Some(vec!["Read".into(), "Bash(git log:*)".into(), "Edit".into()]));
+//! assert_eq!(allowed.unwrap(), ["Read", "Bash(git log:*)"]);
+//! assert_eq!(dropped, ["Edit"]);
+//!
+//! assert!(policy.check_backend(Backend::Claude).is_ok());
+//! # #[cfg(feature = "ollama")]
+//! assert!(policy.check_backend(Backend::Ollama).is_err());
+//! ```
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::claude_executor::{ALLOWED_TOOLS, Backend};
+
+/// Names of every backend, including those left out of this build
+const BACKEND_NAMES: &[&str] = &["claude", "ollama", "api"];
+
+/// Environment variable naming a policy file applied on top of the system one
+pub const FILE_VAR: &str = "CLAUDE_DIALOG_POLICY_FILE";
+
+/// The system-wide policy file, which only an administrator can write
+///
+/// `None` on Windows if `%ProgramData%` is not set.
+pub fn system_file() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("claude-dialog").join("policy.toml"))
+    } else {
+        Some(PathBuf::from("/etc/claude-dialog/policy.toml"))
+    }
+}
+
+/// The policy files that apply, in order: the system file, then the one
+/// named by [`FILE_VAR`]
+pub fn files() -> Vec<PathBuf> {
+    let extra = env::var_os(FILE_VAR).filter(|value| !value.is_empty()).map(PathBuf::from);
+    system_file().into_iter().chain(extra).collect()
+}
+
+/// Limits set by the organization, which settings and options cannot lift
+///
+/// The default policy sets none.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Largest context budget, in tokens; also applies when the session
+    /// asked for none
+    pub max_context_budget: Option<usize>,
+
+    /// Estimated cost, in US dollars, after which a session sends no more
+    /// prompts; backends that report no cost are not limited
+    pub max_session_cost_usd: Option<f64>,
+
+    /// Tools Claude may be allowed at most; a name without arguments such as
+    /// `Bash` also covers its narrower forms like `Bash(git log:*)`
+    pub allowed_tools: Option<Vec<String>>,
+
+    /// Tools Claude must never use
+    pub disallowed_tools: Vec<String>,
+
+    /// Names of the backends that prompts must not be sent to, e.g. `ollama`
+    ///
+    /// Names are kept as written, so that a policy also loads in builds
+    /// without the backends it disallows.
+    pub disallowed_backends: Vec<String>,
+
+    /// Slash commands that must not be used, without the slash
+    pub disallowed_commands: Vec<String>,
+
+    /// Always mask personal data, like `--mask-pii`
+    pub mask_pii: bool,
+
+    /// Patterns always masked, like `--mask-pattern`
+    pub mask_patterns: Vec<String>,
+
+    /// Word list files always masked, like `--mask-words`
+    pub mask_words: Vec<PathBuf>,
+
+    /// Always mask saved transcripts, like `--mask-transcripts`
+    pub mask_transcripts: bool,
+
+    /// Files the policy was read from; empty if there is none
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+impl Policy {
+    /// Parse the text of a policy file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not valid TOML, has unknown keys or
+    /// disallows a backend claude-dialog does not know.
+    pub fn parse(text: &str) -> Result<Self> {
+        let policy: Self = toml::from_str(text)?;
+        if let Some(name) = policy.disallowed_backends.iter().find(|name| !BACKEND_NAMES.contains(&name.as_str())) {
+            bail!("Unknown backend '{}' in disallowed_backends; expected one of {}", name, BACKEND_NAMES.join(", "));
+        }
+        Ok(policy)
+    }
+
+    /// Read a policy file, `None` if it does not exist
+    ///
+    /// Relative `mask_words` paths are resolved next to the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or is not a
+    /// valid policy.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read the policy file {}", path.display())),
+        };
+        let mut policy = Self::parse(&text).with_context(|| format!("Invalid policy file {}", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for words in &mut policy.mask_words {
+            *words = dir.join(&*words);
+        }
+        policy.sources = vec![path.to_path_buf()];
+        Ok(Some(policy))
+    }
+
+    /// Read the policy files that exist among `paths`, combined with
+    /// [`Policy::merge`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the files cannot be read or is not a
+    /// valid policy.
+    pub fn load_all(paths: &[PathBuf]) -> Result<Self> {
+        let mut policy = Self::default();
+        for path in paths {
+            if let Some(other) = Self::load(path)? {
+                policy.merge(other);
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Add the limits of `other`, keeping the stricter of each
+    pub fn merge(&mut self, other: Policy) {
+        fn smaller<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+                (a, b) => a.or(b),
+            }
+        }
+        fn union<T: PartialEq>(list: &mut Vec<T>, other: Vec<T>) {
+            for item in other {
+                if !list.contains(&item) {
+                    list.push(item);
+                }
+            }
+        }
+
+        self.max_context_budget = smaller(self.max_context_budget, other.max_context_budget);
+        self.max_session_cost_usd = smaller(self.max_session_cost_usd, other.max_session_cost_usd);
+        self.allowed_tools = match (self.allowed_tools.take(), other.allowed_tools) {
+            (Some(mine), Some(theirs)) => {
+                let mut tools: Vec<String> = mine.iter().filter(|tool| covers(&theirs, tool)).cloned().collect();
+                union(&mut tools, theirs.iter().filter(|tool| covers(&mine, tool)).cloned().collect());
+                Some(tools)
+            }
+            (mine, theirs) => mine.or(theirs),
+        };
+        union(&mut self.disallowed_tools, other.disallowed_tools);
+        union(&mut self.disallowed_backends, other.disallowed_backends);
+        union(&mut self.disallowed_commands, other.disallowed_commands);
+        self.mask_pii |= other.mask_pii;
+        union(&mut self.mask_patterns, other.mask_patterns);
+        union(&mut self.mask_words, other.mask_words);
+        self.mask_transcripts |= other.mask_transcripts;
+        union(&mut self.sources, other.sources);
+    }
+
+    /// Whether no policy file was found
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// The policy files, for messages, e.g. `policy /etc/claude-dialog/policy.toml`
+    pub fn label(&self) -> String {
+        let files: Vec<String> = self.sources.iter().map(|path| path.display().to_string()).collect();
+        if files.is_empty() {
+            "policy".to_string()
+        } else {
+            format!("policy {}", files.join(", "))
+        }
+    }
+
+    /// Check that prompts may be sent to `backend`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the policy disallows the backend.
+    pub fn check_backend(&self, backend: Backend) -> Result<()> {
+        if self.disallowed_backends.contains(&backend.to_string()) {
+            bail!("The {} backend is disallowed by the {}", backend, self.label());
+        }
+        Ok(())
+    }
+
+    /// Check that a session that has cost `cost_usd` so far may send
+    /// another prompt
+    ///
+    /// # Errors
+    ///
+    /// Returns an error once the cost has reached the policy's limit.
+    pub fn check_cost(&self, cost_usd: f64) -> Result<()> {
+        if let Some(limit) = self.max_session_cost_usd
+            && cost_usd >= limit
+        {
+            bail!(
+                "This session has reached the cost limit of ${:.2} set by the {} (estimated ${:.4} so far)",
+                limit,
+                self.label(),
+                cost_usd
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether the policy lets Claude be allowed `tool`
+    pub fn permits_tool(&self, tool: &str) -> bool {
+        self.allowed_tools.as_deref().is_none_or(|allowed| covers(allowed, tool))
+    }
+
+    /// The tools a session asking for `requested` may allow, `None` standing
+    /// for [`ALLOWED_TOOLS`]
+    ///
+    /// # Returns
+    ///
+    /// The tools allowed, and those dropped because the policy does not
+    /// permit them
+    pub fn allowed_tools(&self, requested: Option<Vec<String>>) -> (Option<Vec<String>>, Vec<String>) {
+        if self.allowed_tools.is_none() {
+            return (requested, Vec::new());
+        }
+        let requested = requested.unwrap_or_else(|| ALLOWED_TOOLS.iter().map(ToString::to_string).collect());
+        let (allowed, dropped) = requested.into_iter().partition(|tool| self.permits_tool(tool));
+        (Some(allowed), dropped)
+    }
+
+    /// The tools a session asking for `requested` must not use: those and
+    /// the policy's
+    pub fn disallowed_tools(&self, mut requested: Vec<String>) -> Vec<String> {
+        for tool in &self.disallowed_tools {
+            if !requested.contains(tool) {
+                requested.push(tool.clone());
+            }
+        }
+        requested
+    }
+
+    /// The context budget of a session asking for `requested`, `None` for
+    /// no limit
+    pub fn context_budget(&self, requested: Option<usize>) -> Option<usize> {
+        match (requested, self.max_context_budget) {
+            (Some(requested), Some(max)) => Some(requested.min(max)),
+            (requested, max) => requested.or(max),
+        }
+    }
+}
+
+/// Whether `allowed` lists `tool`, or its name without arguments
+fn covers(allowed: &[String], tool: &str) -> bool {
+    let name = tool.split('(').next().unwrap_or(tool).trim();
+    allowed.iter().any(|allowed| allowed == tool || allowed == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_files_and_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Policy::load(&dir.path().join("policy.toml")).unwrap().is_none());
+        assert!(Policy::load_all(&[dir.path().join("policy.toml")]).unwrap().is_empty());
+
+        let path = dir.path().join("typo.toml");
+        fs::write(&path, "max_budget = 10\n").unwrap();
+        let error = format!("{:#}", Policy::load(&path).unwrap_err());
+        assert!(error.starts_with(&format!("Invalid policy file {}", path.display())), "{}", error);
+    }
+
+    #[test]
+    fn test_disallowed_backends_are_checked_by_name() {
+        let policy = Policy::parse("disallowed_backends = [\"ollama\", \"api\"]").unwrap();
+        assert!(policy.check_backend(Backend::Claude).is_ok());
+
+        let error = Policy::parse("disallowed_backends = [\"olama\"]").unwrap_err();
+        assert_eq!(error.to_string(), "Unknown backend 'olama' in disallowed_backends; expected one of claude, ollama, api");
+    }
+
+    #[test]
+    fn test_mask_words_are_relative_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.toml");
+        fs::write(&path, "mask_words = [\"customers.txt\"]\nmask_pii = true\n").unwrap();
+
+        let policy = Policy::load(&path).unwrap().unwrap();
+        assert_eq!(policy.mask_words, [dir.path().join("customers.txt")]);
+        assert!(policy.mask_pii);
+        assert_eq!(policy.label(), format!("policy {}", path.display()));
+    }
+
+    #[test]
+    fn test_merged_policies_keep_the_stricter_limits() {
+        let mut policy = Policy::parse("max_context_budget = 50000\nallowed_tools = [\"Read\", \"Bash\"]\n").unwrap();
+        policy.merge(
+            Policy::parse("max_context_budget = 80000\nmax_session_cost_usd = 2.5\nallowed_tools = [\"Bash(git log:*)\", \"Edit\"]\nmask_transcripts = true\n")
+                .unwrap(),
+        );
+
+        assert_eq!(policy.max_context_budget, Some(50_000));
+        assert_eq!(policy.max_session_cost_usd, Some(2.5));
+        assert_eq!(policy.allowed_tools.as_deref().unwrap(), ["Bash(git log:*)"]);
+        assert!(policy.mask_transcripts);
+    }
+
+    #[test]
+    fn test_tools_and_costs() {
+        let policy = Policy::parse("allowed_tools = [\"Read\"]\ndisallowed_tools = [\"WebFetch\"]\nmax_session_cost_usd = 1.0\n").unwrap();
+
+        // The default tools are not permitted either
+        assert_eq!(policy.allowed_tools(None), (Some(Vec::new()), vec!["Write".to_string(), "Edit".to_string()]));
+        assert_eq!(policy.disallowed_tools(vec!["WebFetch".to_string(), "Bash".to_string()]), ["WebFetch", "Bash"]);
+        assert_eq!(Policy::default().allowed_tools(None), (None, Vec::new()));
+
+        assert!(policy.check_cost(0.99).is_ok());
+        assert!(policy.check_cost(1.0).unwrap_err().to_string().starts_with("This session has reached the cost limit of $1.00"));
+        assert_eq!(Policy::default().context_budget(None), None);
+    }
+}
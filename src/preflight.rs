@@ -0,0 +1,686 @@
+//! Startup validation module
+//!
+//! Before the welcome banner is printed, everything that can be checked up
+//! front is checked: the Claude CLI, the system prompt files, the model name,
+//! the configuration and the directories the session writes to. All problems
+//! are collected into a single [`Report`] so they can be fixed in one go
+//! instead of being discovered one failed start (or one failed turn) at a
+//! time.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::dialog::DialogConfig;
+//! use claude_dialog::preflight;
+//!
+//! let config = DialogConfig {
+//!     model: Some("".to_string()),
+//!     aliases: vec!["checkpoint=/restore".parse().unwrap()],
+//!     ..Default::default()
+//! };
+//!
+//! let report = preflight::check_config(&config);
+//! assert!(report.has_errors());
+//! assert_eq!(report.problems().len(), 2);
+//! ```
+
+use anyhow::{Result, Context, bail};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use crate::claude_executor::{Backend, CLAUDE_BIN, TrimPolicy};
+use crate::claude_version::{self, Capabilities};
+use crate::commands::CommandRegistry;
+use crate::dialog::DialogConfig;
+use crate::export::ConversationExport;
+use crate::history;
+use crate::mcp;
+use crate::ui::UI;
+
+/// Model aliases accepted by the Claude CLI
+pub const MODEL_ALIASES: &[&str] = &["sonnet", "opus", "haiku"];
+
+/// How serious a problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The session can start, but probably not as intended
+    Warning,
+
+    /// The session cannot start
+    Error,
+}
+
+/// A single problem found during validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// How serious the problem is
+    pub severity: Severity,
+
+    /// Description of the problem
+    pub message: String,
+}
+
+/// Problems collected during startup validation
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    problems: Vec<Problem>,
+}
+
+impl Report {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a problem that prevents the session from starting
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.problems.push(Problem {
+            severity: Severity::Error,
+            message: message.into(),
+        });
+    }
+
+    /// Record a problem the session can start with
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.problems.push(Problem {
+            severity: Severity::Warning,
+            message: message.into(),
+        });
+    }
+
+    /// Add every problem of another report
+    pub fn extend(&mut self, other: Report) {
+        self.problems.extend(other.problems);
+    }
+
+    /// All problems in the order they were found
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// Number of problems that prevent the session from starting
+    pub fn error_count(&self) -> usize {
+        self.problems
+            .iter()
+            .filter(|problem| problem.severity == Severity::Error)
+            .count()
+    }
+
+    /// Whether any problem prevents the session from starting
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    /// Print every problem to standard error
+    pub fn print(&self) {
+        for problem in &self.problems {
+            match problem.severity {
+                Severity::Warning => UI::print_warning(&problem.message),
+                Severity::Error => UI::print_error(&format!("Error: {}", problem.message)),
+            }
+        }
+    }
+}
+
+/// Check a model name for obvious mistakes
+///
+/// Empty names are an error. Names that are neither a CLI alias nor a full
+/// `claude-` model name are a warning, since new models may be released
+/// after this version of `claude-dialog`.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::preflight::{check_model, Severity};
+///
+/// assert!(check_model("sonnet").is_none());
+/// assert!(check_model("claude-3-opus-20240229").is_none());
+/// assert_eq!(check_model("gpt-4").unwrap().severity, Severity::Warning);
+/// assert_eq!(check_model(" ").unwrap().severity, Severity::Error);
+/// ```
+pub fn check_model(model: &str) -> Option<Problem> {
+    let model = model.trim();
+    if model.is_empty() {
+        return Some(Problem {
+            severity: Severity::Error,
+            message: "Model name must not be empty".to_string(),
+        });
+    }
+
+    if MODEL_ALIASES.contains(&model) || model.starts_with("claude-") {
+        return None;
+    }
+
+    Some(Problem {
+        severity: Severity::Warning,
+        message: format!(
+            "Unknown model '{}'; expected one of {} or a full model name such as claude-sonnet-4-20250514",
+            model,
+            MODEL_ALIASES.join(", ")
+        ),
+    })
+}
+
+/// Check the installed Claude CLI and detect its capabilities
+///
+/// A missing `claude` binary is an error. If its version cannot be
+/// determined, a warning is reported and no capability is gated.
+///
+/// # Arguments
+///
+/// * `claude_bin` - The `--claude-bin` binary, or `None` for `claude` on
+///   `PATH`
+/// * `cache_dir` - Directory for the version cache, if any
+pub fn check_claude(claude_bin: Option<&Path>, cache_dir: Option<&Path>) -> (Capabilities, Report) {
+    let mut report = Report::new();
+
+    let binary = claude_bin.unwrap_or(Path::new(CLAUDE_BIN));
+    if claude_version::find_claude(binary).is_none() {
+        match claude_bin {
+            Some(binary) => report.error(format!("claude CLI {} not found (--claude-bin)", binary.display())),
+            None => report.error("claude CLI not found on PATH; install Claude Code first"),
+        }
+        return (Capabilities::default(), report);
+    }
+
+    let capabilities = match claude_version::detect_cached(binary, cache_dir) {
+        Ok(version) => Capabilities::for_version(Some(version)),
+        Err(e) => {
+            report.warning(format!("Could not determine claude CLI version: {:#}", e));
+            Capabilities::default()
+        }
+    };
+    for warning in capabilities.compatibility_warnings() {
+        report.warning(warning);
+    }
+
+    (capabilities, report)
+}
+
+/// Check that files can be created in a directory, without writing anything
+///
+/// A directory that does not exist yet passes if its nearest existing
+/// ancestor is a directory it can be created in, as it is only created
+/// when the first file is written.
+///
+/// # Errors
+///
+/// Returns an error if the directory, or the ancestor it would be created
+/// in, is not a writable directory.
+pub fn check_writable(dir: &Path) -> Result<()> {
+    let mut existing = dir;
+    loop {
+        match fs::metadata(existing) {
+            Ok(metadata) if metadata.is_dir() => break,
+            Ok(_) if existing == dir => bail!("Cannot write to directory {}: it is a file", dir.display()),
+            Ok(_) => bail!("Cannot create directory {}: {} is a file", dir.display(), existing.display()),
+            // A file on the way is found when its path is reached
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => {
+                existing = match existing.parent() {
+                    Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+                    Some(parent) => parent,
+                    None => bail!("Cannot create directory {}", dir.display()),
+                };
+            }
+            Err(e) => return Err(e).with_context(|| format!("Cannot access directory {}", existing.display())),
+        }
+    }
+
+    if !is_writable(existing) {
+        if existing == dir {
+            bail!("Cannot write to directory {}", dir.display());
+        }
+        bail!("Cannot create directory {}: {} is not writable", dir.display(), existing.display());
+    }
+    Ok(())
+}
+
+/// Check that a file can be written, replacing it if it exists, without
+/// writing anything
+///
+/// # Errors
+///
+/// Returns an error if the path is a directory or a file that cannot be
+/// written, or the directory it would be created in is not writable.
+pub fn check_writable_file(path: &Path) -> Result<()> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => bail!("Cannot write to {}: it is a directory", path.display()),
+        Ok(_) if !is_writable(path) => bail!("Cannot write to {}", path.display()),
+        Ok(_) => Ok(()),
+        Err(_) => match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => check_writable(dir),
+            _ => check_writable(Path::new(".")),
+        },
+    }
+}
+
+/// Whether the user may write to an existing file, or create files in an
+/// existing directory
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    use rustix::fs::{Access, access};
+
+    let mode = if path.is_dir() { Access::WRITE_OK | Access::EXEC_OK } else { Access::WRITE_OK };
+    access(path, mode).is_ok()
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
+}
+
+/// Check the model of a backend, `subject` naming what uses them in messages
+fn check_route(backend: Backend, model: Option<&str>, subject: &str) -> Report {
+    let mut report = Report::new();
+    if backend == Backend::Claude {
+        if let Some(problem) = model.and_then(check_model) {
+            report.problems.push(problem);
+        }
+    } else if model.is_none_or(|model| model.trim().is_empty()) {
+        // Only Claude has a default model
+        report.error(format!("{} needs a model, e.g. --model {}", subject, backend.example_model()));
+    }
+    // The API knows models by their IDs only
+    #[cfg(feature = "api")]
+    if backend == Backend::Api
+        && let Some(alias) = model.map(str::trim).filter(|model| MODEL_ALIASES.contains(model))
+    {
+        report.error(format!(
+            "{} needs a model ID, not the claude CLI alias {}; e.g. --model {}",
+            subject,
+            alias,
+            backend.example_model()
+        ));
+    }
+    report
+}
+
+/// Whether the session or one of its personas sends prompts to Ollama
+#[cfg(feature = "ollama")]
+fn uses_ollama(config: &DialogConfig) -> bool {
+    config.backend == Backend::Ollama || config.personas.iter().any(|persona| persona.backend == Backend::Ollama)
+}
+
+#[cfg(not(feature = "ollama"))]
+fn uses_ollama(_config: &DialogConfig) -> bool {
+    false
+}
+
+/// Whether the session or one of its personas sends prompts to the
+/// Anthropic API
+#[cfg(feature = "api")]
+fn uses_api(config: &DialogConfig) -> bool {
+    config.backend == Backend::Api || config.personas.iter().any(|persona| persona.backend == Backend::Api)
+}
+
+#[cfg(not(feature = "api"))]
+fn uses_api(_config: &DialogConfig) -> bool {
+    false
+}
+
+/// Check a dialog configuration
+///
+/// Validates the model names for the configured backend and personas, the
+/// allowed and disallowed tools, the slash command aliases, the history
+/// ignore patterns, the conversation file to import and write access to the
+/// journal and session directories and the transcript file.
+pub fn check_config(config: &DialogConfig) -> Report {
+    let mut report = Report::new();
+
+    report.extend(check_route(config.backend, config.model.as_deref(), &format!("The {} backend", config.backend)));
+    if config.backend != Backend::Claude
+        && let crate::claude_executor::SessionMode::Resume(id) = &config.session
+    {
+        report.error(format!("Cannot resume Claude session {} with the {} backend", id, config.backend));
+    }
+    
+    for (index, persona) in config.personas.iter().enumerate() {
+        if config.personas[..index].iter().any(|other| other.name == persona.name) {
+            report.error(format!("Persona {} is defined more than once", persona.name));
+        }
+        report.extend(check_route(persona.backend, persona.model.as_deref(), &format!("Persona {}", persona.name)));
+    }
+
+    if config.temperature.is_some() && config.backend == Backend::Claude {
+        report.warning("The claude CLI has no temperature setting; the temperature only applies to ollama and api turns");
+    }
+    if uses_api(config) {
+        #[cfg(feature = "api")]
+        if !crate::api::ApiExecutor::from_env().has_key() {
+            report.error(format!("The api backend needs an API key in {}", crate::api::KEY_VARIABLE));
+        }
+        if config.temperature.is_some_and(|temperature| temperature > 1.0) {
+            report.error("The Anthropic API takes temperatures from 0 to 1");
+        }
+    } else if config.max_tokens.is_some() {
+        report.warning("Only the api backend has a max tokens setting; --max-tokens is ignored");
+    }
+    if config.trim_policy != TrimPolicy::default() && config.backend == Backend::Claude {
+        report.warning("The claude CLI manages its own context; --trim-policy only applies to ollama and api turns");
+    }
+    if config.history_budget.is_some() && config.backend == Backend::Claude {
+        report.warning("The claude CLI manages its own context; --history-budget only applies to ollama and api turns");
+    }
+    if config.num_ctx.is_some() && !uses_ollama(config) {
+        report.warning("Only the ollama backend has a context window setting; --num-ctx is ignored");
+    }
+
+    // Disallowing one of the default tools is how it is turned off, but a
+    // tool allowed and disallowed by name is probably a mistake
+    for tool in config.allowed_tools.iter().flatten() {
+        if config.disallowed_tools.contains(tool) {
+            report.warning(format!("Tool {} is both allowed and disallowed; Claude will not use it", tool));
+        }
+    }
+
+    if let Err(e) = crate::claude_executor::check_extra_args(&config.claude_args) {
+        report.error(e.to_string());
+    }
+    // Unknown arguments or another binary could get around the policy's
+    // tool limits
+    if !config.policy.is_empty() && (!config.claude_args.is_empty() || config.claude_bin.is_some()) {
+        report.error(format!("--claude-arg and --claude-bin are not allowed under the {}", config.policy.label()));
+    }
+
+    // A broken MCP file would fail every prompt
+    for file in &config.mcp_config {
+        if let Err(e) = mcp::load(file) {
+            report.error(format!("{:#}", e));
+        }
+    }
+    if config.strict_mcp_config && config.mcp_config.is_empty() {
+        report.warning("--strict-mcp-config without --mcp-config leaves Claude no MCP servers");
+    }
+
+    let mut commands = CommandRegistry::new();
+    for alias in &config.aliases {
+        if let Err(e) = commands.register_alias(alias.clone()) {
+            report.error(e.to_string());
+        }
+    }
+
+    // Patterns from the configuration file are not checked by the parser
+    for pattern in &config.history_ignore {
+        if let Err(e) = history::ignore_pattern(pattern) {
+            report.error(format!("{:#}", e));
+        }
+    }
+
+    if let Some(path) = &config.import_file
+        && let Err(e) = ConversationExport::load(path)
+    {
+        report.error(format!("{:#}", e));
+    }
+
+    // Incognito sessions keep no journal, save no session and write no
+    // transcript, so nothing is written there
+    if let Some(dir) = config.journal_dir.as_ref().filter(|_| !config.incognito)
+        && let Err(e) = check_writable(dir)
+    {
+        report.error(format!(
+            "{:#}; crash recovery needs a writable state directory (set CLAUDE_DIALOG_STATE_DIR to use another)",
+            e
+        ));
+    }
+    // Found only at the end, these would lose the session
+    if let Some(dir) = config.sessions_dir.as_ref().filter(|_| !config.incognito)
+        && let Err(e) = check_writable(dir)
+    {
+        report.error(format!(
+            "{:#}; sessions are saved there when they end (set CLAUDE_DIALOG_DATA_DIR to use another directory)",
+            e
+        ));
+    }
+    if let Some(path) = config.transcript_file.as_ref().filter(|_| !config.incognito)
+        && let Err(e) = check_writable_file(path)
+    {
+        report.error(format!("{:#}; --save-transcript needs a file that can be written", e));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_counts() {
+        let mut report = Report::new();
+        assert!(!report.has_errors());
+
+        report.warning("careful");
+        assert!(!report.has_errors());
+
+        let mut other = Report::new();
+        other.error("broken");
+        other.error("also broken");
+        report.extend(other);
+
+        assert_eq!(report.error_count(), 2);
+        assert_eq!(report.problems()[0].message, "careful");
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b");
+        check_writable(&nested).unwrap();
+        check_writable(dir.path()).unwrap();
+        // Nothing is created
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert_eq!(check_writable(&file).unwrap_err().to_string(), format!("Cannot write to directory {}: it is a file", file.display()));
+        let err = check_writable(&file.join("sessions")).unwrap_err();
+        assert!(err.to_string().ends_with("is a file"), "{}", err);
+
+        check_writable_file(&file).unwrap();
+        check_writable_file(&nested.join("transcript.md")).unwrap();
+        assert!(check_writable_file(dir.path()).is_err());
+        assert!(check_writable_file(&file.join("transcript.md")).is_err());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_session_and_transcript_locations_are_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        let mut config = DialogConfig {
+            sessions_dir: Some(file.join("sessions")),
+            transcript_file: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let messages: Vec<String> = check_config(&config).problems().iter().map(|problem| problem.message.clone()).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with("sessions are saved there when they end (set CLAUDE_DIALOG_DATA_DIR to use another directory)"));
+        assert!(messages[1].starts_with(&format!("Cannot write to {}: it is a directory", dir.path().display())));
+
+        config.incognito = true;
+        assert!(check_config(&config).problems().is_empty());
+    }
+
+    #[test]
+    fn test_valid_config_has_no_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DialogConfig {
+            model: Some("opus".to_string()),
+            aliases: vec!["cp=/checkpoint".parse().unwrap()],
+            journal_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        assert!(check_config(&config).problems().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_incognito_needs_no_writable_state_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state");
+        fs::create_dir(&state).unwrap();
+        fs::set_permissions(&state, fs::Permissions::from_mode(0o555)).unwrap();
+        let config = DialogConfig {
+            journal_dir: Some(state.join("journal")),
+            incognito: true,
+            ..Default::default()
+        };
+
+        assert!(check_config(&config).problems().is_empty());
+        assert_eq!(fs::read_dir(&state).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_import_file_must_be_a_conversation() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.json");
+        fs::write(&file, "{}").unwrap();
+        let config = DialogConfig {
+            import_file: Some(file),
+            ..Default::default()
+        };
+
+        let report = check_config(&config);
+        assert_eq!(report.error_count(), 1);
+        assert!(report.problems()[0].message.contains("Not a claude-dialog conversation file"));
+    }
+
+    #[test]
+    fn test_persona_problems() {
+        let config = DialogConfig {
+            personas: vec!["final=claude:opus".parse().unwrap(), "final=claude:gpt-4".parse().unwrap()],
+            ..Default::default()
+        };
+
+        let report = check_config(&config);
+        assert_eq!(report.error_count(), 1);
+        assert_eq!(report.problems()[0].message, "Persona final is defined more than once");
+        assert!(report.problems()[1].message.starts_with("Unknown model 'gpt-4'"));
+    }
+
+    #[test]
+    fn test_tools_allowed_and_disallowed() {
+        let mut config = DialogConfig {
+            disallowed_tools: vec!["Edit".to_string()],
+            ..Default::default()
+        };
+        assert!(check_config(&config).problems().is_empty());
+
+        config.allowed_tools = Some(vec!["Read".to_string(), "Edit".to_string()]);
+        let report = check_config(&config);
+        assert!(!report.has_errors());
+        assert_eq!(report.problems()[0].message, "Tool Edit is both allowed and disallowed; Claude will not use it");
+    }
+
+    #[cfg(feature = "ollama")]
+    #[test]
+    fn test_ollama_needs_a_model() {
+        let mut config = DialogConfig {
+            backend: Backend::Ollama,
+            ..Default::default()
+        };
+        assert_eq!(check_config(&config).error_count(), 1);
+
+        config.model = Some("llama3".to_string());
+        assert!(check_config(&config).problems().is_empty());
+
+        config.temperature = Some(0.2);
+        assert!(check_config(&config).problems().is_empty());
+    }
+
+    #[test]
+    fn test_temperature_with_claude() {
+        let config = DialogConfig {
+            temperature: Some(0.2),
+            ..Default::default()
+        };
+        let report = check_config(&config);
+        assert!(!report.has_errors());
+        assert!(report.problems()[0].message.starts_with("The claude CLI has no temperature setting"));
+    }
+
+    #[cfg(feature = "api")]
+    #[test]
+    fn test_api_models() {
+        let mut config = DialogConfig {
+            backend: Backend::Api,
+            model: Some("sonnet".to_string()),
+            temperature: Some(1.5),
+            ..Default::default()
+        };
+        let messages: Vec<String> = check_config(&config).problems().iter().map(|problem| problem.message.clone()).collect();
+        assert!(messages.contains(&"The api backend needs a model ID, not the claude CLI alias sonnet; e.g. --model claude-sonnet-4-5".to_string()));
+        assert!(messages.contains(&"The Anthropic API takes temperatures from 0 to 1".to_string()));
+
+        config.model = None;
+        config.temperature = Some(0.2);
+        config.max_tokens = Some(1024);
+        let report = check_config(&config);
+        assert!(report.problems().iter().any(|problem| problem.message.starts_with("The api backend needs a model, e.g.")));
+        assert!(report.problems().iter().all(|problem| problem.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_max_tokens_without_api() {
+        let config = DialogConfig {
+            max_tokens: Some(1024),
+            ..Default::default()
+        };
+        let report = check_config(&config);
+        assert!(!report.has_errors());
+        assert_eq!(report.problems()[0].message, "Only the api backend has a max tokens setting; --max-tokens is ignored");
+    }
+
+    #[test]
+    fn test_trim_policy_without_chat_backends() {
+        let config = DialogConfig {
+            trim_policy: TrimPolicy::KeepPinned,
+            history_budget: Some(8000),
+            num_ctx: Some(8192),
+            ..Default::default()
+        };
+        let report = check_config(&config);
+        assert!(!report.has_errors());
+        let messages: Vec<&str> = report.problems().iter().map(|problem| problem.message.as_str()).collect();
+        assert_eq!(messages, [
+            "The claude CLI manages its own context; --trim-policy only applies to ollama and api turns",
+            "The claude CLI manages its own context; --history-budget only applies to ollama and api turns",
+            "Only the ollama backend has a context window setting; --num-ctx is ignored",
+        ]);
+    }
+
+    #[test]
+    fn test_history_ignore_patterns() {
+        let config = DialogConfig {
+            history_ignore: vec!["*password*".to_string(), "[unclosed".to_string()],
+            ..Default::default()
+        };
+        let report = check_config(&config);
+        assert_eq!(report.error_count(), 1);
+        assert!(report.problems()[0].message.starts_with("Invalid history ignore pattern '[unclosed'"));
+    }
+
+    #[test]
+    fn test_claude_args() {
+        let mut config = DialogConfig {
+            claude_args: vec!["--max-turns".to_string(), "5".to_string()],
+            ..Default::default()
+        };
+        assert!(check_config(&config).problems().is_empty());
+
+        config.claude_args.push("--dangerously-skip-permissions".to_string());
+        assert_eq!(check_config(&config).error_count(), 1);
+
+        config.claude_args.truncate(2);
+        config.policy = crate::policy::Policy {
+            sources: vec!["/etc/claude-dialog/policy.toml".into()],
+            ..Default::default()
+        };
+        let report = check_config(&config);
+        assert_eq!(report.problems()[0].message, "--claude-arg and --claude-bin are not allowed under the policy /etc/claude-dialog/policy.toml");
+    }
+}
@@ -0,0 +1,341 @@
+//! Presentation module
+//!
+//! `claude-dialog present <file>` plays back a saved conversation as if it
+//! were happening live, for demos and talks without live API calls or
+//! waiting for Claude. Each prompt is typed out at `You>` with a typist's
+//! rhythm, and each response streams in a word at a time and is rendered
+//! like a live one. Nothing is sent to Claude.
+//!
+//! The file is a Markdown transcript written by `--save-transcript` or
+//! `/save`, or a conversation written by `/export`.
+//!
+//! # Controls
+//!
+//! Playback stops before each prompt is typed and before each response
+//! streams in: Space, Enter or → continues, `q` or Esc ends the
+//! presentation. With `--auto`, or when standard input is not a terminal,
+//! playback continues by itself after a short pause.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::present::parse_transcript;
+//!
+//! let markdown = "## You (12:00:05)\n\nWhat is a slice?\n\n## Claude (opus)\n\nA view into a sequence.\n\n*Rated 5/5*\n";
+//! let turns = parse_transcript(markdown);
+//! assert_eq!(turns[0].prompt, "What is a slice?");
+//! assert_eq!(turns[0].response.as_deref(), Some("A view into a sequence."));
+//! ```
+
+use anyhow::{Result, Context, bail};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+use crate::conversation::Turn;
+use crate::export::ConversationExport;
+use crate::pipeline::ResponsePipeline;
+use crate::ui::UI;
+
+/// Time to type a letter at normal speed
+const KEYSTROKE: Duration = Duration::from_millis(45);
+
+/// Time to stream a word of a response at normal speed
+const WORD: Duration = Duration::from_millis(30);
+
+/// Pause between a prompt and its response, and between steps with `--auto`
+const PAUSE: Duration = Duration::from_millis(1200);
+
+/// How a conversation is played back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playback {
+    /// Speed relative to the default pacing: 2.0 plays twice as fast
+    pub speed: f64,
+
+    /// Continue after a pause instead of waiting for a key
+    pub auto: bool,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self { speed: 1.0, auto: false }
+    }
+}
+
+impl Playback {
+    /// How long to wait after typing `c`
+    ///
+    /// Typists pause a little after words and longer after sentences.
+    pub fn keystroke(&self, c: char) -> Duration {
+        let pause = match c {
+            '.' | '?' | '!' | '\n' => KEYSTROKE * 5,
+            ',' | ';' | ':' => KEYSTROKE * 3,
+            ' ' => KEYSTROKE * 2,
+            _ => KEYSTROKE,
+        };
+        self.scaled(pause)
+    }
+
+    fn scaled(&self, duration: Duration) -> Duration {
+        duration.div_f64(self.speed)
+    }
+}
+
+/// What the presenter chose at a stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Next,
+    Quit,
+}
+
+/// Read the turns of a saved conversation
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is an invalid export, or
+/// holds no turns.
+pub fn load(path: &Path) -> Result<Vec<Turn>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read conversation: {}", path.display()))?;
+    let turns = if text.trim_start().starts_with('{') {
+        ConversationExport::from_json(&text)
+            .with_context(|| format!("Invalid conversation file {}", path.display()))?
+            .turns
+    } else {
+        parse_transcript(&text)
+    };
+    if turns.is_empty() {
+        bail!(
+            "{} has no turns to present; expected a transcript saved with --save-transcript or /save, or an /export file",
+            path.display()
+        );
+    }
+    Ok(turns)
+}
+
+/// Parse the turns of a Markdown transcript written by
+/// [`Transcript::to_markdown`](crate::transcript::Transcript::to_markdown)
+///
+/// File lists, what prompts were sent with, ratings and notes are dropped;
+/// a response of `*No response*` is `None`.
+pub fn parse_transcript(markdown: &str) -> Vec<Turn> {
+    let mut turns: Vec<Turn> = Vec::new();
+    let mut text = String::new();
+    let mut in_response = false;
+
+    for line in markdown.lines() {
+        if is_prompt_heading(line) {
+            finish_section(&mut turns, &mut text, in_response);
+            turns.push(Turn::new(""));
+            in_response = false;
+        } else if !turns.is_empty() && !in_response && is_response_heading(line) {
+            finish_section(&mut turns, &mut text, in_response);
+            in_response = true;
+        } else {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    finish_section(&mut turns, &mut text, in_response);
+    turns
+}
+
+/// `## You (12:00:05)`
+fn is_prompt_heading(line: &str) -> bool {
+    line.strip_prefix("## You (")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .is_some_and(|time| time.len() == 8 && time.chars().all(|c| c.is_ascii_digit() || c == ':'))
+}
+
+/// `## Claude` or `## Claude (model)`
+fn is_response_heading(line: &str) -> bool {
+    line == "## Claude" || line.strip_prefix("## Claude (").is_some_and(|rest| rest.ends_with(')'))
+}
+
+/// Move the text read so far into the prompt or response of the last turn
+fn finish_section(turns: &mut [Turn], text: &mut String, in_response: bool) {
+    let section = std::mem::take(text);
+    let Some(turn) = turns.last_mut() else {
+        return;
+    };
+
+    let mut lines: Vec<&str> = section.trim().lines().collect();
+    if in_response {
+        // Ratings and notes follow the response
+        loop {
+            while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                lines.pop();
+            }
+            let Some(last) = lines.last() else { break };
+            if last.starts_with("*Rated ") && last.ends_with('*') {
+                lines.pop();
+            } else if let Some(start) = note_start(&lines) {
+                lines.truncate(start);
+            } else {
+                break;
+            }
+        }
+        let response = lines.join("\n");
+        turn.response = (response != "*No response*").then_some(response);
+    } else {
+        // File lists and what the prompt was sent with follow the prompt
+        while lines.last().is_some_and(|line| {
+            line.trim().is_empty() || (line.starts_with("*Files: ") || line.starts_with("*Sent with: ")) && line.ends_with('*')
+        }) {
+            lines.pop();
+        }
+        turn.prompt = lines.join("\n").trim_end().to_string();
+    }
+}
+
+/// Index of the first line of a `> **Note:**` block ending the lines
+fn note_start(lines: &[&str]) -> Option<usize> {
+    let start = lines.iter().rposition(|line| line.starts_with("> **Note:** "))?;
+    lines[start..].iter().all(|line| line.starts_with('>')).then_some(start)
+}
+
+/// Play back a conversation
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be read from or written to.
+pub async fn play(turns: &[Turn], playback: Playback) -> Result<()> {
+    let interactive = !playback.auto && io::stdin().is_terminal();
+    if interactive {
+        UI::print_info(&format!("Presenting {} turn(s); press Space to continue, q to quit", turns.len()));
+    }
+
+    for turn in turns {
+        if stop(interactive, playback).await? == Step::Quit {
+            return Ok(());
+        }
+        UI::print_user_prompt();
+        type_out(&turn.prompt, playback).await?;
+        println!();
+
+        if stop(interactive, playback).await? == Step::Quit {
+            return Ok(());
+        }
+        UI::print_claude_prompt();
+        if let Some(response) = &turn.response {
+            stream(response, playback).await?;
+        }
+        println!();
+        println!();
+    }
+
+    UI::print_info("End of the conversation");
+    Ok(())
+}
+
+/// Wait for the presenter, or pause when playing back by itself
+async fn stop(interactive: bool, playback: Playback) -> Result<Step> {
+    if !interactive {
+        tokio::time::sleep(playback.scaled(PAUSE)).await;
+        return Ok(Step::Next);
+    }
+    io::stdout().flush()?;
+
+    tokio::task::spawn_blocking(|| {
+        terminal::enable_raw_mode().context("Failed to read keys from the terminal")?;
+        let step = read_step();
+        terminal::disable_raw_mode().context("Failed to restore the terminal")?;
+        step
+    })
+    .await
+    .context("Failed to wait for a key")?
+}
+
+/// The next key that continues or ends the presentation
+fn read_step() -> Result<Step> {
+    loop {
+        let Event::Key(key) = event::read().context("Failed to read a key")? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char(' ') | KeyCode::Enter | KeyCode::Right | KeyCode::Down | KeyCode::PageDown => {
+                return Ok(Step::Next);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Step::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(Step::Quit),
+            _ => {}
+        }
+    }
+}
+
+/// Type out a prompt a character at a time
+async fn type_out(prompt: &str, playback: Playback) -> Result<()> {
+    let mut stdout = io::stdout();
+    for c in prompt.chars() {
+        write!(stdout, "{}", c)?;
+        stdout.flush()?;
+        tokio::time::sleep(playback.keystroke(c)).await;
+    }
+    Ok(())
+}
+
+/// Stream a response a word at a time, rendered like a live response
+async fn stream(response: &str, playback: Playback) -> Result<()> {
+    let pipeline = if io::stdout().is_terminal() && colored::control::SHOULD_COLORIZE.should_colorize() {
+        ResponsePipeline::markdown()
+    } else {
+        ResponsePipeline::terminal()
+    };
+    for word in response.split_inclusive(char::is_whitespace) {
+        pipeline.send(word);
+        tokio::time::sleep(playback.scaled(WORD)).await;
+    }
+    pipeline.finish().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::FileInclusion;
+    use crate::transcript::{Provenance, Transcript};
+
+    #[test]
+    fn test_transcripts_parse_back_into_their_turns() {
+        let mut transcript = Transcript::new();
+        transcript.record(Turn::new("Review this:\n\n## You (not a heading)"), Some("opus"));
+        transcript.set_response("## Claude\n\nLooks fine.\n\n> quoted");
+        transcript.rate(4);
+        transcript.add_note("shown in the talk");
+        transcript.record(Turn::new("And now?").with_sources(vec![FileInclusion::whole("notes.md", 3)]), None);
+        transcript.set_provenance(Provenance {
+            layers: vec!["turn metadata".to_string()],
+            ..Default::default()
+        });
+
+        let turns = parse_transcript(&transcript.to_markdown());
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].prompt, "Review this:\n\n## You (not a heading)");
+        assert_eq!(turns[0].response.as_deref(), Some("## Claude\n\nLooks fine.\n\n> quoted"));
+        assert_eq!(turns[1].prompt, "And now?");
+        assert_eq!(turns[1].response, None);
+    }
+
+    #[test]
+    fn test_load_rejects_files_without_turns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        fs::write(&path, "# Meeting notes\n").unwrap();
+        assert!(load(&path).unwrap_err().to_string().contains("has no turns to present"));
+
+        let path = dir.path().join("other.json");
+        fs::write(&path, "{\"format\": \"something else\"}").unwrap();
+        assert!(load(&path).is_err());
+    }
+
+    #[test]
+    fn test_pacing_scales_with_speed() {
+        let fast = Playback { speed: 2.0, auto: true };
+        assert_eq!(fast.keystroke('a'), KEYSTROKE / 2);
+        assert!(Playback::default().keystroke('.') > Playback::default().keystroke(' '));
+    }
+}
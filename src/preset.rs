@@ -0,0 +1,204 @@
+//! Prompt library module
+//!
+//! Reusable system prompts are kept as Markdown files in the `prompts`
+//! directory of the configuration directory (see
+//! [`Paths::prompts_dir`](crate::paths::Paths::prompts_dir)), one file per
+//! preset: `prompts/rust-reviewer.md` is the preset `rust-reviewer`.
+//! `--preset` starts a session with one, `/preset` switches to one
+//! mid-dialog and `claude-dialog prompts list` shows which there are.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::preset::PromptLibrary;
+//! use std::fs;
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! fs::write(dir.path().join("rust-reviewer.md"), "# Review Rust code strictly\n\nYou are...").unwrap();
+//! fs::write(dir.path().join("tutor.md"), "---\ndescription: Patient teacher\n---\nExplain...").unwrap();
+//!
+//! let library = PromptLibrary::new(dir.path());
+//! let presets = library.list().unwrap();
+//! assert_eq!(presets.len(), 2);
+//! assert_eq!(presets[0].name, "rust-reviewer");
+//! assert_eq!(presets[0].summary, "Review Rust code strictly");
+//!
+//! let preset = library.find("tutor").unwrap();
+//! assert_eq!(preset.path, dir.path().join("tutor.md"));
+//! assert_eq!(preset.summary, "Patient teacher");
+//! assert!(library.find("missing").is_err());
+//! ```
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use crate::prompt::split_front_matter;
+
+/// Extension of preset files
+const EXTENSION: &str = "md";
+
+/// A reusable system prompt in the prompt library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    /// Name used with `--preset` and `/preset`, the file name without `.md`
+    pub name: String,
+
+    /// File holding the prompt
+    pub path: PathBuf,
+
+    /// The `description` of the prompt's front matter, or else its first
+    /// non-empty line without a Markdown heading marker
+    pub summary: String,
+}
+
+/// The presets in a directory
+#[derive(Debug, Clone)]
+pub struct PromptLibrary {
+    dir: PathBuf,
+}
+
+impl PromptLibrary {
+    /// The library of presets in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory the presets are kept in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The presets in the library, sorted by name
+    ///
+    /// A library whose directory does not exist yet is empty. Hidden files
+    /// and subdirectories are not presets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or a preset cannot be read.
+    pub fn list(&self) -> Result<Vec<Preset>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read the prompt library {}", self.dir.display()));
+            }
+        };
+
+        let mut presets = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() || path.extension().is_none_or(|ext| ext != EXTENSION) {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if !name.starts_with('.') {
+                presets.push(load(name.to_string(), path.clone())?);
+            }
+        }
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(presets)
+    }
+
+    /// The preset named `name`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is not a plain file name or there is no
+    /// such preset.
+    pub fn find(&self, name: &str) -> Result<Preset> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            bail!("Invalid preset name '{}'", name);
+        }
+
+        let path = self.dir.join(format!("{}.{}", name, EXTENSION));
+        if !path.is_file() {
+            bail!(
+                "No preset named '{}' in {}; `claude-dialog prompts list` shows them",
+                name,
+                self.dir.display()
+            );
+        }
+        load(name.to_string(), path)
+    }
+}
+
+/// The presets as aligned `name  summary` lines
+///
+/// # Output Format
+///
+/// ```text
+/// rust-reviewer  Review Rust code strictly
+/// tutor          Explain like a patient teacher.
+/// ```
+pub fn table(presets: &[Preset]) -> String {
+    let width = presets.iter().map(|preset| preset.name.chars().count()).max().unwrap_or(0);
+    presets
+        .iter()
+        .map(|preset| format!("{:<width$}  {}", preset.name, preset.summary).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Read the preset stored in `path`
+fn load(name: String, path: PathBuf) -> Result<Preset> {
+    let prompt = fs::read_to_string(&path).with_context(|| format!("Failed to read preset {}", path.display()))?;
+    let (metadata, body) = split_front_matter(&prompt).with_context(|| format!("In preset {}", path.display()))?;
+    Ok(Preset {
+        name,
+        summary: metadata.description.unwrap_or_else(|| summary(body)),
+        path,
+    })
+}
+
+/// The first non-empty line of a prompt, without a heading marker
+fn summary(prompt: &str) -> String {
+    prompt
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(summary("\n\n## Reviewer  \nText"), "Reviewer");
+        assert_eq!(summary("Be brief."), "Be brief.");
+        assert_eq!(summary("  \n"), "");
+    }
+
+    #[test]
+    fn test_find_rejects_paths() {
+        let library = PromptLibrary::new("/config/prompts");
+        for name in ["", "../secret", "a/b", ".hidden"] {
+            assert!(library.find(name).unwrap_err().to_string().starts_with("Invalid preset name"));
+        }
+    }
+
+    #[test]
+    fn test_missing_library_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(PromptLibrary::new(dir.path().join("prompts")).list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_table() {
+        let preset = |name: &str, summary: &str| Preset {
+            name: name.to_string(),
+            path: PathBuf::new(),
+            summary: summary.to_string(),
+        };
+        assert_eq!(
+            table(&[preset("rust-reviewer", "Review Rust code"), preset("tutor", "")]),
+            "rust-reviewer  Review Rust code\ntutor"
+        );
+    }
+}
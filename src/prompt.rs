@@ -2,6 +2,23 @@
 //!
 //! This module handles loading and managing system prompts from files.
 //! It supports both complete prompt replacement and appending to default prompts.
+//! A system prompt path may also name a directory, whose `.md` files are
+//! loaded in lexicographic order.
+//!
+//! A prompt file may start with YAML front matter carrying settings that
+//! belong with the prompt, such as the model it was written for:
+//!
+//! ```text
+//! ---
+//! description: Review Rust code strictly
+//! model: opus
+//! allowed_tools: [Read, Grep]
+//! ---
+//! You are a meticulous Rust reviewer.
+//! ```
+//!
+//! The front matter is not part of the prompt; see [`PromptMetadata`] for
+//! the keys it may contain.
 //!
 //! # Examples
 //!
@@ -10,21 +27,34 @@
 //!
 //! // Load a single system prompt file
 //! let config = SystemPromptConfig {
-//!     system_prompt_files: vec!["prompt.md".to_string()],
+//!     system_prompt_files: vec!["prompt.md".into()],
 //!     append_prompt_file: None,
+//!     recursive: false,
 //! };
 //! let prompt = load_system_prompt(config).unwrap();
+//! println!("{} (model {:?})", prompt.body, prompt.metadata.model);
 //!
 //! // Load multiple system prompt files
 //! let config = SystemPromptConfig {
-//!     system_prompt_files: vec!["base.md".to_string(), "specific.md".to_string()],
+//!     system_prompt_files: vec!["base.md".into(), "specific.md".into()],
 //!     append_prompt_file: None,
+//!     recursive: false,
+//! };
+//! let prompt = load_system_prompt(config).unwrap();
+//!
+//! // Load every .md file in a directory, including subdirectories
+//! let config = SystemPromptConfig {
+//!     system_prompt_files: vec!["prompts".into()],
+//!     append_prompt_file: None,
+//!     recursive: true,
 //! };
 //! let prompt = load_system_prompt(config).unwrap();
 //! ```
 
-use anyhow::{Result, Context};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::{Path, PathBuf};
 
 /// Configuration for system prompt loading
 ///
@@ -40,29 +70,136 @@ use std::fs;
 ///
 /// // Configuration for multiple system prompts
 /// let config = SystemPromptConfig {
-///     system_prompt_files: vec!["base.md".to_string(), "custom.md".to_string()],
+///     system_prompt_files: vec!["base.md".into(), "custom.md".into()],
 ///     append_prompt_file: None,
+///     recursive: false,
 /// };
 ///
 /// // Configuration for appending to default prompt
 /// let config = SystemPromptConfig {
 ///     system_prompt_files: vec![],
-///     append_prompt_file: Some("additions.md".to_string()),
+///     append_prompt_file: Some("additions.md".into()),
+///     recursive: false,
 /// };
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SystemPromptConfig {
     /// List of system prompt files to load (replaces default prompt)
     ///
     /// When provided, these files completely replace the default system prompt.
     /// Multiple files are concatenated with double newlines between them.
-    pub system_prompt_files: Vec<String>,
+    /// A directory stands for the `.md` files in it, see [`prompt_files`].
+    pub system_prompt_files: Vec<PathBuf>,
     
     /// Optional file to append to the default prompt
     ///
     /// When provided (and system_prompt_files is empty), this file's contents
     /// are appended to the default system prompt rather than replacing it.
-    pub append_prompt_file: Option<String>,
+    pub append_prompt_file: Option<PathBuf>,
+
+    /// Whether directories among `system_prompt_files` are searched for
+    /// `.md` files in their subdirectories too
+    pub recursive: bool,
+}
+
+/// Settings given in the front matter of a system prompt file
+///
+/// Options given on the command line take precedence over these, and these
+/// over the configuration file.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::prompt::split_front_matter;
+///
+/// let (metadata, body) = split_front_matter("---\nmodel: opus\ntemperature: 0.2\n---\nBe brief.\n").unwrap();
+/// assert_eq!(metadata.model.as_deref(), Some("opus"));
+/// assert_eq!(metadata.temperature, Some(0.2));
+/// assert_eq!(body, "Be brief.\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptMetadata {
+    /// What the prompt is for, shown by `claude-dialog prompts list`
+    pub description: Option<String>,
+
+    /// Model the prompt was written for, like `--model`
+    pub model: Option<String>,
+
+    /// Tools Claude may use without asking, like `--allowed-tools`
+    pub allowed_tools: Option<Vec<String>>,
+
+    /// Tools Claude must not use, like `--disallowed-tools`
+    pub disallowed_tools: Option<Vec<String>>,
+
+    /// Sampling temperature; only the ollama backend has this setting
+    pub temperature: Option<f32>,
+}
+
+impl PromptMetadata {
+    /// Combine with the metadata of a file loaded after this one, whose
+    /// settings win
+    pub fn merge(self, later: PromptMetadata) -> PromptMetadata {
+        PromptMetadata {
+            description: later.description.or(self.description),
+            model: later.model.or(self.model),
+            allowed_tools: later.allowed_tools.or(self.allowed_tools),
+            disallowed_tools: later.disallowed_tools.or(self.disallowed_tools),
+            temperature: later.temperature.or(self.temperature),
+        }
+    }
+}
+
+/// A system prompt together with the settings of its files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadedPrompt {
+    /// The prompt, without front matter
+    pub body: String,
+
+    /// Settings from the front matter of the prompt files
+    pub metadata: PromptMetadata,
+}
+
+/// Separate the YAML front matter from the rest of a prompt file
+///
+/// Front matter is a block of YAML at the very start of the file, between
+/// two `---` lines. A file without one has default metadata.
+///
+/// # Errors
+///
+/// Returns an error if the closing `---` line is missing, or the front
+/// matter is not valid YAML or has keys [`PromptMetadata`] does not know.
+pub fn split_front_matter(text: &str) -> Result<(PromptMetadata, &str)> {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return Ok((PromptMetadata::default(), text));
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = &rest[..offset];
+            let metadata = if yaml.trim().is_empty() {
+                PromptMetadata::default()
+            } else {
+                serde_yaml_ng::from_str(yaml).context("Invalid front matter")?
+            };
+            return Ok((metadata, &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    bail!("The front matter has no closing --- line")
+}
+
+/// Read a prompt file and separate its front matter
+fn read_prompt_file(path: &Path, kind: &str) -> Result<LoadedPrompt> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {} file: {}", kind, path.display()))?;
+    let (metadata, body) = split_front_matter(&content).with_context(|| format!("In {}", path.display()))?;
+    Ok(LoadedPrompt {
+        body: body.to_string(),
+        metadata,
+    })
 }
 
 /// Load system prompt based on the provided configuration
@@ -70,7 +207,10 @@ pub struct SystemPromptConfig {
 /// This function handles three scenarios:
 /// 1. Multiple system prompt files - loads and concatenates them
 /// 2. Single append file - loads it for appending to default prompt
-/// 3. No files specified - returns empty string
+/// 3. No files specified - returns an empty prompt
+///
+/// The front matter of the files is combined, later files overriding
+/// earlier ones.
 ///
 /// # Arguments
 ///
@@ -78,11 +218,12 @@ pub struct SystemPromptConfig {
 ///
 /// # Returns
 ///
-/// * `Result<String>` - The loaded prompt content or an error
+/// * `Result<LoadedPrompt>` - The loaded prompt and its settings, or an error
 ///
 /// # Errors
 ///
-/// Returns an error if any specified file cannot be read.
+/// Returns an error if any specified file cannot be read or has invalid
+/// front matter, or a directory contains no `.md` files.
 ///
 /// # Examples
 ///
@@ -96,38 +237,109 @@ pub struct SystemPromptConfig {
 ///
 /// // Load multiple prompts
 /// let config = SystemPromptConfig {
-///     system_prompt_files: vec!["test1.md".to_string(), "test2.md".to_string()],
+///     system_prompt_files: vec!["test1.md".into(), "test2.md".into()],
 ///     append_prompt_file: None,
+///     recursive: false,
 /// };
 /// let result = load_system_prompt(config).unwrap();
-/// assert_eq!(result, "First prompt\n\nSecond prompt");
+/// assert_eq!(result.body, "First prompt\n\nSecond prompt");
 ///
 /// // Clean up
 /// fs::remove_file("test1.md").unwrap();
 /// fs::remove_file("test2.md").unwrap();
 /// ```
-pub fn load_system_prompt(config: SystemPromptConfig) -> Result<String> {
+pub fn load_system_prompt(config: SystemPromptConfig) -> Result<LoadedPrompt> {
     if !config.system_prompt_files.is_empty() {
         // Load and concatenate multiple system prompt files
         let mut prompts = Vec::new();
+        let mut metadata = PromptMetadata::default();
         
-        for file_path in &config.system_prompt_files {
-            let content = fs::read_to_string(file_path)
-                .with_context(|| format!("Failed to read system prompt file: {}", file_path))?;
-            prompts.push(content);
+        for file_path in &prompt_files(&config.system_prompt_files, config.recursive)? {
+            let prompt = read_prompt_file(file_path, "system prompt")?;
+            metadata = metadata.merge(prompt.metadata);
+            prompts.push(prompt.body);
         }
         
         // Join with double newlines between files
-        Ok(prompts.join("\n\n"))
+        Ok(LoadedPrompt {
+            body: prompts.join("\n\n"),
+            metadata,
+        })
     } else if let Some(append_file) = config.append_prompt_file {
         // Load append prompt file
-        let content = fs::read_to_string(&append_file)
-            .with_context(|| format!("Failed to read append prompt file: {}", append_file))?;
-        Ok(content)
+        read_prompt_file(&append_file, "append prompt")
     } else {
         // No custom prompt
-        Ok(String::new())
+        Ok(LoadedPrompt::default())
+    }
+}
+
+/// The system prompt files `paths` stand for
+///
+/// Files are kept as given; a directory is replaced by the `.md` files in
+/// it, sorted by path. Hidden files and directories are skipped, and
+/// subdirectories are only searched when `recursive` is set.
+///
+/// # Arguments
+///
+/// * `paths` - System prompt files and directories, in order
+/// * `recursive` - Whether to search subdirectories of directories
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read or contains no `.md` files.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::prompt::prompt_files;
+/// use std::fs;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// fs::write(dir.path().join("20-style.md"), "Be brief.").unwrap();
+/// fs::write(dir.path().join("10-role.md"), "You review Rust code.").unwrap();
+/// fs::write(dir.path().join("notes.txt"), "Not a prompt").unwrap();
+///
+/// let files = prompt_files(&[dir.path().to_path_buf()], false).unwrap();
+/// assert_eq!(files, [dir.path().join("10-role.md"), dir.path().join("20-style.md")]);
+/// ```
+pub fn prompt_files(paths: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        
+        let mut found = Vec::new();
+        collect_markdown(path, recursive, &mut found)?;
+        if found.is_empty() {
+            bail!("No .md files in system prompt directory: {}", path.display());
+        }
+        found.sort();
+        files.extend(found);
     }
+    Ok(files)
+}
+
+/// Add the `.md` files in `dir` to `files`
+fn collect_markdown(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read system prompt directory: {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+            continue;
+        }
+        if path.is_dir() {
+            if recursive {
+                collect_markdown(&path, recursive, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md")) {
+            files.push(path);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -137,10 +349,69 @@ mod tests {
     #[test]
     fn test_system_prompt_config() {
         let config = SystemPromptConfig {
-            system_prompt_files: vec!["test.md".to_string()],
+            system_prompt_files: vec!["test.md".into()],
             append_prompt_file: None,
+            recursive: false,
         };
         assert_eq!(config.system_prompt_files.len(), 1);
         assert!(config.append_prompt_file.is_none());
     }
+
+    #[test]
+    fn test_prompt_files_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("b/nested")).unwrap();
+        fs::create_dir(dir.path().join(".hidden")).unwrap();
+        fs::write(dir.path().join("c.md"), "c").unwrap();
+        fs::write(dir.path().join("b/nested/d.MD"), "d").unwrap();
+        fs::write(dir.path().join(".hidden/e.md"), "e").unwrap();
+        fs::write(dir.path().join("a.md"), "a").unwrap();
+        
+        let root = dir.path().to_path_buf();
+        assert_eq!(
+            prompt_files(std::slice::from_ref(&root), true).unwrap(),
+            [root.join("a.md"), root.join("b/nested/d.MD"), root.join("c.md")]
+        );
+        assert_eq!(prompt_files(std::slice::from_ref(&root), false).unwrap(), [root.join("a.md"), root.join("c.md")]);
+    }
+
+    #[test]
+    fn test_split_front_matter() {
+        let (metadata, body) = split_front_matter("---\r\nallowed_tools: [Read]\r\n---\r\nBody").unwrap();
+        assert_eq!(metadata.allowed_tools, Some(vec!["Read".to_string()]));
+        assert_eq!(body, "Body");
+        
+        let (metadata, body) = split_front_matter("---\n---\nBody").unwrap();
+        assert_eq!(metadata, PromptMetadata::default());
+        assert_eq!(body, "Body");
+        
+        let (_, body) = split_front_matter("Text\n---\nmodel: opus\n---\n").unwrap();
+        assert_eq!(body, "Text\n---\nmodel: opus\n---\n");
+        
+        assert!(split_front_matter("---\nmodel: opus\nBody").unwrap_err().to_string().contains("no closing ---"));
+        assert!(split_front_matter("---\nmodle: opus\n---\n").is_err());
+    }
+
+    #[test]
+    fn test_metadata_merge() {
+        let base = PromptMetadata {
+            model: Some("sonnet".to_string()),
+            temperature: Some(0.5),
+            ..Default::default()
+        };
+        let later = PromptMetadata {
+            model: Some("opus".to_string()),
+            ..Default::default()
+        };
+        let merged = base.merge(later);
+        assert_eq!(merged.model.as_deref(), Some("opus"));
+        assert_eq!(merged.temperature, Some(0.5));
+    }
+
+    #[test]
+    fn test_prompt_files_empty_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = prompt_files(&[dir.path().to_path_buf()], false).unwrap_err();
+        assert!(error.to_string().starts_with("No .md files in system prompt directory"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+//! Quiz module
+//!
+//! `--quiz` turns a session into flashcards for studying: each prompt is a
+//! question, and Claude's answer is kept hidden until Enter is pressed, so
+//! you can think of your own answer first. A timer runs from when the
+//! question is sent, is shown while the answer is hidden, and is reported
+//! when it is revealed.
+//!
+//! The timer is drawn like a [`Spinner`], so nothing is drawn when standard
+//! error is not a terminal.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::quiz::format_elapsed;
+//! use std::time::Duration;
+//!
+//! assert_eq!(format_elapsed(Duration::from_secs(7)), "0:07");
+//! assert_eq!(format_elapsed(Duration::from_secs(754)), "12:34");
+//! assert_eq!(format_elapsed(Duration::from_secs(3723)), "1:02:03");
+//! ```
+
+use anyhow::{Result, Context};
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+use crate::ui::Spinner;
+
+/// How often the timer is redrawn
+const TIMER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Run a turn while showing the time since the question was sent
+///
+/// # Arguments
+///
+/// * `started` - When the question was sent
+/// * `turn` - The future sending the question and receiving the answer
+pub async fn timed<F: Future>(started: Instant, turn: F) -> F::Output {
+    let mut spinner = Spinner::new();
+    let mut ticker = tokio::time::interval(TIMER_INTERVAL);
+    tokio::pin!(turn);
+
+    let output = loop {
+        tokio::select! {
+            output = &mut turn => break output,
+            _ = ticker.tick() => spinner.tick(&format!("{} Waiting for the answer", format_elapsed(started.elapsed()))),
+        }
+    };
+    spinner.clear();
+    output
+}
+
+/// Wait until Enter is pressed, showing the time since the question was sent
+///
+/// The end of standard input counts as Enter, so a piped session does not
+/// stop at a hidden answer.
+///
+/// # Returns
+///
+/// The time from `started` until the answer was revealed.
+///
+/// # Errors
+///
+/// Returns an error if standard input cannot be read.
+pub async fn wait_for_reveal(started: Instant) -> Result<Duration> {
+    let mut spinner = Spinner::new();
+    let mut ticker = tokio::time::interval(TIMER_INTERVAL);
+    let read = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).map(|_| ())
+    });
+    tokio::pin!(read);
+
+    let drawn = loop {
+        tokio::select! {
+            result = &mut read => {
+                result.context("Failed to wait for Enter")?.context("Failed to read standard input")?;
+                break spinner.is_drawn();
+            }
+            _ = ticker.tick() => spinner.tick(&format!("{} Press Enter to reveal the answer", format_elapsed(started.elapsed()))),
+        }
+    };
+    let elapsed = started.elapsed();
+
+    spinner.clear();
+    if drawn && io::stdin().is_terminal() {
+        // The terminal echoed Enter below the timer; erase the timer line
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\x1b[1A\r\x1b[2K");
+        let _ = stderr.flush();
+    }
+    Ok(elapsed)
+}
+
+/// A duration as minutes and seconds, with hours if it has any
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timed_returns_the_turn_output() {
+        let started = Instant::now();
+        let output = timed(started, async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            42
+        })
+        .await;
+        assert_eq!(output, 42);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_format_elapsed_rounds_down() {
+        assert_eq!(format_elapsed(Duration::from_millis(59_999)), "0:59");
+        assert_eq!(format_elapsed(Duration::from_secs(3600)), "1:00:00");
+    }
+}
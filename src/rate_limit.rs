@@ -0,0 +1,226 @@
+//! Rate limit module
+//!
+//! Recognizes rate limit errors in the output of a failed Claude command and
+//! works out how long to wait before trying again. Servers usually say when
+//! a request may be retried, e.g. with a `Retry-After` header, a message such
+//! as "try again in 30 seconds", or the Claude CLI's
+//! `Claude AI usage limit reached|<reset time>`. That hint is used whenever
+//! there is one; otherwise retries back off exponentially, starting at
+//! [`FIRST_BACKOFF`], so a rate-limited session never hammers the API.
+//!
+//! # Examples
+//!
+//! ```
+//! use chrono::Utc;
+//! use claude_dialog::rate_limit::RateLimit;
+//! use std::time::Duration;
+//!
+//! let output = "API Error: 429 Too Many Requests. Please try again in 30 seconds.";
+//! let limit = RateLimit::detect(output, Utc::now()).unwrap();
+//! assert_eq!(limit.retry_after, Some(Duration::from_secs(30)));
+//!
+//! assert_eq!(RateLimit::detect("Error: file not found", Utc::now()), None);
+//! ```
+
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use crate::ui::Spinner;
+
+/// Retries of a rate-limited prompt made by the command-line interface
+pub const DEFAULT_RETRIES: usize = 3;
+
+/// Wait before the first retry when the server gives no hint
+pub const FIRST_BACKOFF: Duration = Duration::from_secs(15);
+
+/// Longest wait before retrying automatically
+///
+/// When a rate limit lasts longer, e.g. until a usage limit resets, the
+/// error is reported instead.
+pub const MAX_WAIT: Duration = Duration::from_secs(15 * 60);
+
+/// Phrases identifying a rate limit error, in lowercase
+const MARKERS: &[&str] = &["rate limit", "rate_limit", "too many requests", "usage limit reached"];
+
+/// Phrases followed by the time to wait, in lowercase
+const RETRY_PHRASES: &[&str] = &["retry-after", "retry after", "try again in", "retry in"];
+
+/// A rate limit reported by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// How long the server asked to wait, if it said
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Recognize a rate limit error in the output of a failed command
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Error output, or any text the command printed
+    /// * `now` - The current time, to turn reset times into durations
+    ///
+    /// # Returns
+    ///
+    /// `None` if `output` does not report a rate limit
+    pub fn detect(output: &str, now: DateTime<Utc>) -> Option<Self> {
+        let lower = output.to_lowercase();
+        let is_rate_limit = MARKERS.iter().any(|marker| lower.contains(marker))
+            || lower.split(|c: char| !c.is_ascii_alphanumeric()).any(|word| word == "429");
+        if !is_rate_limit {
+            return None;
+        }
+
+        Some(Self {
+            retry_after: usage_limit_reset(&lower, now).or_else(|| retry_hint(&lower, now)),
+        })
+    }
+
+    /// How long to wait before retry number `attempt`, counting from 0
+    ///
+    /// The server's hint if there is one, otherwise [`backoff`].
+    pub fn delay(&self, attempt: usize) -> Duration {
+        self.retry_after.unwrap_or_else(|| backoff(attempt))
+    }
+}
+
+/// Exponential backoff for retries without a server hint
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::rate_limit::backoff;
+/// use std::time::Duration;
+///
+/// assert_eq!(backoff(0), Duration::from_secs(15));
+/// assert_eq!(backoff(2), Duration::from_secs(60));
+/// ```
+pub fn backoff(attempt: usize) -> Duration {
+    FIRST_BACKOFF.saturating_mul(2u32.saturating_pow(attempt as u32)).min(MAX_WAIT)
+}
+
+/// Parse the value of a `Retry-After` header
+///
+/// The value is either a number of seconds or an HTTP date. Dates in the
+/// past mean no wait.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use claude_dialog::rate_limit::parse_retry_after;
+/// use std::time::Duration;
+///
+/// let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 0).unwrap();
+/// assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+/// assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now), Some(Duration::from_secs(60)));
+/// assert_eq!(parse_retry_after("soon", now), None);
+/// ```
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| until(date.with_timezone(&Utc), now))
+}
+
+/// Count down `delay` on standard error, then return
+///
+/// The countdown is drawn like a [`Spinner`], so nothing is printed when
+/// standard error is not a terminal.
+pub async fn countdown(delay: Duration) {
+    let mut spinner = Spinner::new();
+    let mut remaining = delay;
+    while !remaining.is_zero() {
+        spinner.tick(&format!("Retrying in {}s", remaining.as_secs_f64().ceil()));
+        let step = remaining.min(Duration::from_secs(1));
+        tokio::time::sleep(step).await;
+        remaining -= step;
+    }
+    spinner.clear();
+}
+
+/// Time until the reset in `Claude AI usage limit reached|<unix time>`
+fn usage_limit_reset(output: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let (_, rest) = output.split_once("usage limit reached|")?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    let reset = DateTime::from_timestamp(digits.parse().ok()?, 0)?;
+    Some(until(reset, now))
+}
+
+/// Time to wait from a phrase such as "retry after 30s" or "Retry-After: 5"
+fn retry_hint(output: &str, now: DateTime<Utc>) -> Option<Duration> {
+    RETRY_PHRASES.iter().find_map(|phrase| {
+        let (_, rest) = output.split_once(phrase)?;
+        let rest = rest.trim_start_matches([':', '=', ' ', '"']);
+        let line = rest.lines().next().unwrap_or_default();
+
+        let number: String = line.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+        let Ok(amount) = number.parse::<f64>() else {
+            // An HTTP date, e.g. "Retry-After: Wed, 21 Oct 2015 07:28:00 GMT"
+            return parse_retry_after(line.trim_end_matches(['"', ',', '.']), now);
+        };
+
+        let unit: String = line[number.len()..]
+            .trim_start()
+            .chars()
+            .take_while(char::is_ascii_alphabetic)
+            .collect();
+        let seconds = match unit.as_str() {
+            "ms" | "millisecond" | "milliseconds" => amount / 1000.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => amount * 60.0,
+            "h" | "hour" | "hours" => amount * 3600.0,
+            _ => amount,
+        };
+        Duration::try_from_secs_f64(seconds).ok()
+    })
+}
+
+/// Duration from `now` until `time`, zero if it has passed
+fn until(time: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    (time - now).to_std().unwrap_or(Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn retry_after(output: &str) -> Option<Option<Duration>> {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        RateLimit::detect(output, now).map(|limit| limit.retry_after)
+    }
+
+    #[test]
+    fn test_detects_rate_limits() {
+        assert_eq!(retry_after("API Error: 429 {\"type\":\"rate_limit_error\"}"), Some(None));
+        assert_eq!(retry_after("Too Many Requests"), Some(None));
+        assert_eq!(retry_after("Error: 4290 bytes written"), None);
+        assert_eq!(retry_after("Error: connection reset"), None);
+    }
+
+    #[test]
+    fn test_retry_hints() {
+        let secs = |secs| Some(Some(Duration::from_secs(secs)));
+        assert_eq!(retry_after("rate limited, retry-after: 20"), secs(20));
+        assert_eq!(retry_after("Rate limit exceeded. Retry after 2 minutes."), secs(120));
+        assert_eq!(retry_after("429: please try again in 45s"), secs(45));
+        assert_eq!(retry_after("rate limit; retry in 1.5 seconds"), Some(Some(Duration::from_millis(1500))));
+        assert_eq!(retry_after("429 retry-after: Wed, 01 Jan 2025 12:01:30 GMT"), secs(90));
+        // 2025-01-01 13:00:00 UTC
+        assert_eq!(retry_after("Claude AI usage limit reached|1735736400"), secs(3600));
+        assert_eq!(retry_after("Claude AI usage limit reached|1735000000"), secs(0));
+    }
+
+    #[test]
+    fn test_delay_backs_off_without_a_hint() {
+        let limit = RateLimit { retry_after: None };
+        assert_eq!(limit.delay(0), FIRST_BACKOFF);
+        assert_eq!(limit.delay(1), FIRST_BACKOFF * 2);
+        assert_eq!(limit.delay(30), MAX_WAIT);
+
+        let limit = RateLimit { retry_after: Some(Duration::from_secs(5)) };
+        assert_eq!(limit.delay(3), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,276 @@
+//! Repository map module
+//!
+//! A repository map is a condensed outline of a project: every file with its
+//! size and the names of its top-level definitions. Sending it to Claude
+//! gives a cheap overview of a large project's structure, so questions can
+//! be answered (or files asked for) without including the sources.
+//!
+//! Files excluded by `.gitignore` or `.claude-dialog-ignore` are left out,
+//! see [`crate::ignore_rules`].
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::repo_map::RepoMap;
+//! # let dir = tempfile::tempdir().unwrap();
+//! # std::fs::create_dir(dir.path().join("src")).unwrap();
+//! # std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n\nstruct Config;\n").unwrap();
+//!
+//! // `dir` contains src/main.rs
+//! let map = RepoMap::generate(dir.path()).unwrap();
+//! assert_eq!(map.file_count(), 1);
+//! assert!(map.render().contains("src/\n  main.rs (29 B): fn main, struct Config\n"));
+//! ```
+
+use anyhow::{Result, Context};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::chunk::DEFINITION_KEYWORDS;
+use crate::context::estimate_tokens;
+use crate::ignore_rules;
+
+/// Largest number of files listed in a map
+pub const MAX_FILES: usize = 1000;
+
+/// Largest number of symbols listed per file
+const MAX_SYMBOLS: usize = 8;
+
+/// Files larger than this are listed without symbols
+const MAX_SCANNED_BYTES: u64 = 256 * 1024;
+
+/// A file in a repository map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapEntry {
+    /// Path relative to the repository root
+    pub path: PathBuf,
+
+    /// Size in bytes
+    pub size: u64,
+
+    /// Top-level definitions, e.g. `fn main`
+    pub symbols: Vec<String>,
+}
+
+/// A condensed outline of a repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoMap {
+    root: PathBuf,
+    entries: Vec<MapEntry>,
+    truncated: bool,
+}
+
+impl RepoMap {
+    /// Generate the map of the repository at `root`
+    ///
+    /// At most [`MAX_FILES`] files are listed, in path order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` cannot be read.
+    pub fn generate(root: &Path) -> Result<Self> {
+        let mut files = ignore_rules::files(root, None)
+            .with_context(|| format!("Cannot map repository at {}", root.display()))?;
+
+        let entries: Vec<MapEntry> = files
+            .by_ref()
+            .take(MAX_FILES)
+            .map(|path| map_entry(root, &path))
+            .collect();
+        let truncated = files.next().is_some();
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            entries,
+            truncated,
+        })
+    }
+
+    /// Listed files in path order
+    pub fn entries(&self) -> &[MapEntry] {
+        &self.entries
+    }
+
+    /// Number of listed files
+    pub fn file_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether files were left out because the repository has more than
+    /// [`MAX_FILES`]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Estimated tokens of the rendered map
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.render())
+    }
+
+    /// Render the map as an indented tree in a `<repo-map>` block
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    /// <repo-map root="claude-dialog" files="2">
+    /// Cargo.toml (1.2 KB)
+    /// src/
+    ///   main.rs (8.4 KB): fn main, fn run_command
+    /// </repo-map>
+    /// ```
+    pub fn render(&self) -> String {
+        let name = self
+            .root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.root.display().to_string());
+        let mut text = format!("<repo-map root=\"{}\" files=\"{}\">\n", name, self.entries.len());
+
+        let mut open_dirs: Vec<&std::ffi::OsStr> = Vec::new();
+        for entry in &self.entries {
+            let parent: Vec<_> = entry
+                .path
+                .parent()
+                .into_iter()
+                .flat_map(|parent| parent.iter())
+                .collect();
+
+            let common = open_dirs
+                .iter()
+                .zip(&parent)
+                .take_while(|(open, dir)| open == dir)
+                .count();
+            open_dirs.truncate(common);
+            for dir in &parent[common..] {
+                text.push_str(&format!("{}{}/\n", "  ".repeat(open_dirs.len()), dir.to_string_lossy()));
+                open_dirs.push(dir);
+            }
+
+            let file_name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+            text.push_str(&format!("{}{} ({})", "  ".repeat(open_dirs.len()), file_name, format_size(entry.size)));
+            if !entry.symbols.is_empty() {
+                text.push_str(": ");
+                text.push_str(&entry.symbols.join(", "));
+            }
+            text.push('\n');
+        }
+
+        if self.truncated {
+            text.push_str(&format!("(only the first {} files are listed)\n", MAX_FILES));
+        }
+        text.push_str("</repo-map>");
+        text
+    }
+
+}
+
+/// Describe a single file
+fn map_entry(root: &Path, path: &Path) -> MapEntry {
+    let size = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    let symbols = if size <= MAX_SCANNED_BYTES {
+        fs::read_to_string(path)
+            .map(|content| top_level_symbols(&content))
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    MapEntry {
+        path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+        size,
+        symbols,
+    }
+}
+
+/// Definitions at the start of unindented lines, e.g. `fn main`
+///
+/// Visibility and other modifiers before the keyword are skipped.
+fn top_level_symbols(content: &str) -> Vec<String> {
+    let mut symbols = Vec::new();
+
+    for line in content.lines() {
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let words: Vec<&str> = line
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '!'))
+            .filter(|word| !word.is_empty())
+            .take(5)
+            .collect();
+        let Some(position) = words.iter().position(|word| DEFINITION_KEYWORDS.contains(word)) else {
+            continue;
+        };
+        let is_modifier = |word: &&str| {
+            ["pub", "crate", "super", "export", "default", "async", "unsafe", "const", "extern", "abstract", "static"]
+                .contains(word)
+        };
+        if !words[..position].iter().all(is_modifier) {
+            continue;
+        }
+
+        if let Some(name) = words.get(position + 1) {
+            if symbols.len() == MAX_SYMBOLS {
+                symbols.push("...".to_string());
+                break;
+            }
+            symbols.push(format!("{} {}", words[position], name));
+        }
+    }
+
+    symbols
+}
+
+/// Human-readable file size
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_level_symbols() {
+        let source = "use std::fs;\n\npub(crate) fn load() {}\npub struct Config {\n    fn nested() {}\n}\nimpl Config {}\nlet fn_value = 1;\nexport default class App {}\n";
+        assert_eq!(
+            top_level_symbols(source),
+            vec!["fn load", "struct Config", "impl Config", "class App"]
+        );
+
+        let many: String = (0..10).map(|n| format!("fn f{}() {{}}\n", n)).collect();
+        let symbols = top_level_symbols(&many);
+        assert_eq!(symbols.len(), MAX_SYMBOLS + 1);
+        assert_eq!(symbols.last().unwrap(), "...");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1_048_576), "3.0 MB");
+    }
+
+    #[test]
+    fn test_tree_rendering_and_ignored_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in ["a/b/one.txt", "a/b/two.txt", "a/three.txt", "z.txt", ".git/config", "build/app", "node_modules/x/index.js"] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        fs::write(dir.path().join(".gitignore"), "build/\n").unwrap();
+
+        let map = RepoMap::generate(dir.path()).unwrap();
+        let rendered = map.render();
+        let body: Vec<&str> = rendered.lines().skip(1).collect();
+        assert_eq!(
+            body,
+            vec!["a/", "  b/", "    one.txt (1 B)", "    two.txt (1 B)", "  three.txt (1 B)", "z.txt (1 B)", "</repo-map>"]
+        );
+        assert!(!map.is_truncated());
+    }
+}
@@ -0,0 +1,108 @@
+//! Backend routing module
+//!
+//! [`BackendRouter`] is an executor that passes every command on to the
+//! executor registered for the command's [`Backend`], so a single dialog
+//! loop can send some turns to Claude and others to a local model (see
+//! [`crate::persona`]).
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::claude_executor::{Backend, SubprocessExecutor};
+//! use claude_dialog::router::BackendRouter;
+//!
+//! let router = BackendRouter::new().route(Backend::Claude, Box::new(SubprocessExecutor));
+//! assert!(router.handles(Backend::Claude));
+//! ```
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use crate::claude_executor::{Backend, ClaudeExecutor, ClaudeInvocation, ClaudeResponse, SubprocessExecutor};
+#[cfg(feature = "ollama")]
+use crate::ollama::OllamaExecutor;
+#[cfg(feature = "api")]
+use crate::api::ApiExecutor;
+use crate::pipeline::ResponsePipeline;
+
+/// Executor dispatching commands by their backend
+#[derive(Default)]
+pub struct BackendRouter {
+    routes: HashMap<Backend, Box<dyn ClaudeExecutor>>,
+}
+
+impl BackendRouter {
+    /// Create a router without any backend
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Router for every backend of this build
+    ///
+    /// Claude commands run the `claude` CLI, and Ollama commands go to the
+    /// server in `OLLAMA_HOST` or on localhost. API commands go to the
+    /// Anthropic API with the key in `ANTHROPIC_API_KEY`.
+    pub fn standard() -> Self {
+        let mut routes: HashMap<Backend, Box<dyn ClaudeExecutor>> = HashMap::new();
+        routes.insert(Backend::Claude, Box::new(SubprocessExecutor));
+        #[cfg(feature = "ollama")]
+        routes.insert(Backend::Ollama, Box::new(OllamaExecutor::from_env()));
+        #[cfg(feature = "api")]
+        routes.insert(Backend::Api, Box::new(ApiExecutor::from_env()));
+        Self { routes }
+    }
+
+    /// Send commands for `backend` to `executor`, replacing any previous route
+    pub fn route(mut self, backend: Backend, executor: Box<dyn ClaudeExecutor>) -> Self {
+        self.routes.insert(backend, executor);
+        self
+    }
+
+    /// Whether commands for `backend` can be run
+    pub fn handles(&self, backend: Backend) -> bool {
+        self.routes.contains_key(&backend)
+    }
+
+    fn executor(&self, backend: Backend) -> Result<&dyn ClaudeExecutor> {
+        self.routes
+            .get(&backend)
+            .map(Box::as_ref)
+            .ok_or_else(|| anyhow!("No executor for the {} backend", backend))
+    }
+}
+
+#[async_trait]
+impl ClaudeExecutor for BackendRouter {
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+        self.executor(command.options.backend)?.execute(command, pipeline).await
+    }
+
+    async fn replay(&self, command: ClaudeInvocation) -> Result<ClaudeResponse> {
+        self.executor(command.options.backend)?.replay(command).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl ClaudeExecutor for Named {
+        async fn execute(&self, _command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+            pipeline.finish().await?;
+            Ok(ClaudeResponse::new(self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commands_go_to_their_backend() {
+        let router = BackendRouter::new().route(Backend::Claude, Box::new(Named("claude")));
+        let response = router.execute(ClaudeInvocation::default(), ResponsePipeline::new()).await.unwrap();
+        assert_eq!(response.text, "claude");
+
+        let err = BackendRouter::new().execute(ClaudeInvocation::default(), ResponsePipeline::new()).await.unwrap_err();
+        assert_eq!(err.to_string(), "No executor for the claude backend");
+    }
+}
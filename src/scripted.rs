@@ -0,0 +1,236 @@
+//! Scripted backend module
+//!
+//! `--test-backend echo`, or `CLAUDE_DIALOG_TEST_BACKEND=echo`, answers
+//! every prompt locally with the prompt text as it would have been sent,
+//! and `--test-backend <file>` answers with canned responses from a fixture
+//! file. Everything else, from slash commands to transcripts and the
+//! terminal output, runs as in a real session, so the whole dialog loop
+//! can be exercised without a network connection or the `claude` CLI, e.g.
+//! in integration tests and packaging smoke tests.
+//!
+//! # Fixture Files
+//!
+//! A fixture is a YAML list of responses:
+//!
+//! ```yaml
+//! - match: "2 + 2"          # used for every prompt containing the text
+//!   response: "4"
+//! - response: "First answer" # used in order by prompts matching no entry
+//! - response: "Half an answer"
+//!   exit_code: 1
+//!   stderr: "Error: internal server error"
+//! ```
+//!
+//! The first entry whose `match` text the prompt contains answers it. A
+//! prompt matching none takes the next entry without a `match`, in file
+//! order, and is echoed once those have run out.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation};
+//! use claude_dialog::pipeline::ResponsePipeline;
+//! use claude_dialog::scripted::{Script, ScriptedExecutor};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let script = Script::parse("- match: '2 + 2'\n  response: '4'\n- response: Hello\n")?;
+//! let executor = ScriptedExecutor::new(script);
+//! let ask = |prompt: &str| ClaudeInvocation { prompt: prompt.to_string(), ..Default::default() };
+//!
+//! assert_eq!(executor.execute(ask("Say hi"), ResponsePipeline::new()).await?.text, "Hello");
+//! assert_eq!(executor.execute(ask("What is 2 + 2?"), ResponsePipeline::new()).await?.text, "4");
+//! assert_eq!(executor.execute(ask("Say hi again"), ResponsePipeline::new()).await?.text, "Say hi again");
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use crate::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeResponse};
+use crate::pipeline::ResponsePipeline;
+
+/// What `--test-backend` answers prompts with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestBackend {
+    /// Every prompt as it would have been sent
+    Echo,
+
+    /// The responses of a fixture file
+    Fixture(PathBuf),
+}
+
+impl FromStr for TestBackend {
+    type Err = anyhow::Error;
+
+    /// Parse `echo` or the path of a fixture file
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "" => anyhow::bail!("Expected `echo` or a fixture file"),
+            "echo" => Ok(TestBackend::Echo),
+            path => Ok(TestBackend::Fixture(PathBuf::from(path))),
+        }
+    }
+}
+
+impl TestBackend {
+    /// The script answering the prompts
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fixture file cannot be read or is not a list
+    /// of responses.
+    pub fn script(&self) -> Result<Script> {
+        match self {
+            TestBackend::Echo => Ok(Script::default()),
+            TestBackend::Fixture(path) => Script::load(path),
+        }
+    }
+}
+
+/// A canned response of a fixture file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptEntry {
+    /// Text a prompt must contain for this response; `None` answers the
+    /// prompts matching no entry, in order
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+
+    /// The response text
+    #[serde(default)]
+    pub response: String,
+
+    /// Exit code reported for the response
+    #[serde(default)]
+    pub exit_code: i32,
+
+    /// Error output reported with the response
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+}
+
+/// The canned responses of a scripted backend; empty to echo every prompt
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Script {
+    entries: Vec<ScriptEntry>,
+}
+
+impl Script {
+    /// A script answering with the given entries
+    pub fn new(entries: Vec<ScriptEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Read a fixture file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a list of
+    /// responses.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read fixture {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("In fixture {}", path.display()))
+    }
+
+    /// Parse the YAML list of a fixture file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not a list of responses.
+    pub fn parse(text: &str) -> Result<Self> {
+        let entries = serde_yaml_ng::from_str::<Option<Vec<ScriptEntry>>>(text)
+            .context("Expected a list of responses, each with a `response`")?;
+        Ok(Self::new(entries.unwrap_or_default()))
+    }
+
+    /// The entries of the script, in file order
+    pub fn entries(&self) -> &[ScriptEntry] {
+        &self.entries
+    }
+}
+
+/// Executor answering prompts from a [`Script`] instead of running a backend
+///
+/// Clones share their place in the script.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedExecutor {
+    script: Script,
+    // Entries without a pattern used so far
+    used: Arc<AtomicUsize>,
+}
+
+impl ScriptedExecutor {
+    /// An executor answering from `script`
+    pub fn new(script: Script) -> Self {
+        Self {
+            script,
+            used: Arc::default(),
+        }
+    }
+
+    /// The response to a prompt
+    fn answer(&self, prompt: &str) -> ClaudeResponse {
+        let entries = &self.script.entries;
+        let matched = entries
+            .iter()
+            .find(|entry| entry.pattern.as_deref().is_some_and(|pattern| prompt.contains(pattern)));
+        let entry = matched.or_else(|| {
+            let index = self.used.fetch_add(1, Ordering::Relaxed);
+            entries.iter().filter(|entry| entry.pattern.is_none()).nth(index)
+        });
+
+        match entry {
+            Some(entry) => ClaudeResponse {
+                stderr: entry.stderr.clone(),
+                exit_code: Some(entry.exit_code),
+                ..ClaudeResponse::new(entry.response.clone())
+            },
+            None => ClaudeResponse::new(prompt),
+        }
+    }
+}
+
+#[async_trait]
+impl ClaudeExecutor for ScriptedExecutor {
+    async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse> {
+        let response = self.answer(&command.prompt);
+        pipeline.send(&response.text);
+        pipeline.finish().await?;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_names() {
+        assert_eq!("echo".parse::<TestBackend>().unwrap(), TestBackend::Echo);
+        assert_eq!(
+            "tests/fixture.yaml".parse::<TestBackend>().unwrap(),
+            TestBackend::Fixture(PathBuf::from("tests/fixture.yaml"))
+        );
+        assert!(" ".parse::<TestBackend>().is_err());
+        assert_eq!(TestBackend::Echo.script().unwrap(), Script::default());
+    }
+
+    #[test]
+    fn test_failures_and_unknown_keys() {
+        let executor = ScriptedExecutor::new(Script::parse("- response: Busy\n  exit_code: 1\n  stderr: 'Error: overloaded'\n").unwrap());
+        let response = executor.answer("anything");
+        assert_eq!(response.exit_code, Some(1));
+        assert_eq!(response.error_for_status().unwrap_err().to_string(), "Claude command failed with exit code 1: Error: overloaded");
+
+        assert!(Script::parse("- reply: typo\n").is_err());
+        assert!(Script::parse("").unwrap().entries().is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+//! Numbered selection module
+//!
+//! Some lists claude-dialog shows can be picked from by typing a number at
+//! the `You>` prompt: suggested follow-up questions, the presets listed by
+//! `/preset`, the personas listed by `/persona` and the matches listed by
+//! `/search`. Such a list is the
+//! selection context of the next input only; any other input, including a
+//! number the list does not have, is handled as usual.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::selection::Selection;
+//!
+//! let selection = Selection::new(["/preset rust-reviewer", "/preset tutor"]);
+//! assert_eq!(selection.pick("2"), Some("/preset tutor"));
+//! assert_eq!(selection.pick(" 1 "), Some("/preset rust-reviewer"));
+//! assert_eq!(selection.pick("3"), None);
+//! assert_eq!(selection.pick("2 apples"), None);
+//! ```
+
+/// A numbered list the next input may pick from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// What each number stands for: a prompt or a slash command
+    choices: Vec<String>,
+}
+
+impl Selection {
+    /// A selection of `choices`, numbered from 1
+    pub fn new<S: Into<String>>(choices: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            choices: choices.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Whether there is nothing to pick
+    pub fn is_empty(&self) -> bool {
+        self.choices.is_empty()
+    }
+
+    /// The choice an input picks, if it is just the number of one
+    pub fn pick(&self, input: &str) -> Option<&str> {
+        let number: usize = input.trim().parse().ok()?;
+        self.choices.get(number.checked_sub(1)?).map(String::as_str)
+    }
+}
+
+/// Number lines for a list that can be picked from
+///
+/// # Output Format
+///
+/// ```text
+///   1. How do I test it?
+///   2. What about errors?
+/// ```
+pub fn numbered<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> String {
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| format!("  {}. {}", index + 1, line.as_ref()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_zero_and_empty() {
+        assert_eq!(Selection::new(["Why?"]).pick("0"), None);
+        assert_eq!(Selection::default().pick("1"), None);
+        assert!(Selection::default().is_empty());
+    }
+
+    #[test]
+    fn test_numbered() {
+        assert_eq!(numbered(["a", "b"]), "  1. a\n  2. b");
+        assert_eq!(numbered(Vec::<String>::new()), "");
+    }
+}
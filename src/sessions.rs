@@ -0,0 +1,369 @@
+//! Session store module
+//!
+//! The conversation of every interactive session is saved in the `sessions`
+//! directory of the data directory (see
+//! [`Paths::sessions_dir`](crate::paths::Paths::sessions_dir)) when the
+//! session ends, one file per session in the format of `/export` (see
+//! [`crate::export`]). A session's ID is the time it was saved, e.g.
+//! `20250101-120000`. `claude-dialog sessions list`, `show` and `delete`
+//! browse them, `merge` interleaves several into one document (see
+//! [`ConversationExport::merge`]), and `--from` reopens one. `archive`
+//! compresses old sessions into the store's `archive` directory (see
+//! [`crate::archive`]); they are listed, shown and deleted like the others.
+//!
+//! Incognito sessions are never saved.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::conversation::Conversation;
+//! use claude_dialog::sessions::SessionStore;
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! let store = SessionStore::new(dir.path());
+//!
+//! let mut conversation = Conversation::new();
+//! conversation.push("What is a trait?\nIn Rust");
+//! conversation.set_response("A set of methods a type can implement.");
+//! let id = store.save(&conversation, Some("opus")).unwrap();
+//!
+//! let sessions = store.list().unwrap();
+//! assert_eq!(sessions[0].id, id);
+//! assert_eq!(sessions[0].title, "What is a trait?");
+//! assert_eq!(store.load(&id).unwrap().conversation(), conversation);
+//!
+//! store.delete(&id).unwrap();
+//! assert!(store.list().unwrap().is_empty());
+//! ```
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, TimeDelta};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use crate::archive::{Archive, ArchiveEntry, Savings};
+use crate::conversation::Conversation;
+use crate::export::ConversationExport;
+use crate::paths;
+
+/// Extension of saved session files
+const EXTENSION: &str = "json";
+
+/// Longest title shown for a session, in characters
+const TITLE_LENGTH: usize = 60;
+
+/// A saved session, as listed by [`SessionStore::list`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// ID used with `claude-dialog sessions show` and `delete`
+    pub id: String,
+
+    /// File holding the conversation
+    pub path: PathBuf,
+
+    /// When the session was saved
+    pub saved_at: DateTime<Local>,
+
+    /// Number of turns of the conversation
+    pub turns: usize,
+
+    /// First line of the first prompt, shortened
+    pub title: String,
+
+    /// Whether the session was compressed by [`SessionStore::archive`]
+    pub archived: bool,
+}
+
+/// The saved sessions in a directory
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    /// The store of sessions in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory the sessions are kept in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Save a conversation as a new session
+    ///
+    /// # Returns
+    ///
+    /// The ID of the session: the current time, with a counter appended if
+    /// a session was saved in the same second
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, conversation: &Conversation, model: Option<&str>) -> Result<String> {
+        let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let id = (1..)
+            .map(|n| if n == 1 { stamp.clone() } else { format!("{}-{}", stamp, n) })
+            .find(|id| !self.path(id).exists() && !self.archived().path(id).exists())
+            .expect("some ID is free");
+        let path = self.path(&id);
+        paths::create_private_dir(&self.dir).with_context(|| format!("Failed to create directory: {}", self.dir.display()))?;
+        paths::write_private(&path, ConversationExport::new(conversation, model).to_json())
+            .with_context(|| format!("Failed to write conversation: {}", path.display()))?;
+        Ok(id)
+    }
+
+    /// The saved sessions, archived ones included, newest first
+    ///
+    /// A store whose directory does not exist yet is empty. Files that are
+    /// not conversations are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or the archive's index cannot be
+    /// read.
+    pub fn list(&self) -> Result<Vec<SessionInfo>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read sessions from {}", self.dir.display())),
+        };
+
+        let mut sessions = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != EXTENSION) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let Ok(export) = ConversationExport::load(&path) else {
+                continue;
+            };
+            sessions.push(SessionInfo {
+                id: id.to_string(),
+                saved_at: export.exported_at.with_timezone(&Local),
+                turns: export.turns.len(),
+                title: title(export.turns.first().map_or("", |turn| turn.prompt.as_str())),
+                path,
+                archived: false,
+            });
+        }
+
+        // Archived sessions are listed from the index, without decompressing them
+        let archive = self.archived();
+        for entry in archive.index()? {
+            sessions.push(SessionInfo {
+                path: archive.path(&entry.id),
+                id: entry.id,
+                saved_at: entry.saved_at,
+                turns: entry.turns,
+                title: entry.title,
+                archived: true,
+            });
+        }
+        sessions.sort_by(|a, b| b.saved_at.cmp(&a.saved_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(sessions)
+    }
+
+    /// The conversation of the session `id`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such session or its file cannot be
+    /// read.
+    pub fn load(&self, id: &str) -> Result<ConversationExport> {
+        ConversationExport::load(&self.find(id)?)
+    }
+
+    /// Delete the session `id`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such session or its file cannot be
+    /// removed.
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let path = self.find(id)?;
+        if path == self.path(id) {
+            fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))
+        } else {
+            self.archived().remove(id).map(|_| ())
+        }
+    }
+
+    /// Compress the sessions saved more than `older_than` ago into the
+    /// archive
+    ///
+    /// # Returns
+    ///
+    /// The sessions archived, oldest first, and their sizes before and
+    /// after
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a session cannot be archived; those archived
+    /// before it stay archived.
+    pub fn archive(&self, older_than: TimeDelta) -> Result<(Vec<SessionInfo>, Savings)> {
+        let cutoff = Local::now() - older_than;
+        let mut sessions: Vec<SessionInfo> = self
+            .list()?
+            .into_iter()
+            .filter(|session| !session.archived && session.saved_at < cutoff)
+            .collect();
+        sessions.reverse();
+
+        let archive = self.archived();
+        let mut total = Savings::default();
+        for session in &mut sessions {
+            let entry = ArchiveEntry {
+                id: session.id.clone(),
+                saved_at: session.saved_at,
+                turns: session.turns,
+                title: session.title.clone(),
+            };
+            let savings = archive
+                .add(&session.path, entry)
+                .with_context(|| format!("Failed to archive session {}", session.id))?;
+            total.original += savings.original;
+            total.compressed += savings.compressed;
+            session.path = archive.path(&session.id);
+            session.archived = true;
+        }
+        Ok((sessions, total))
+    }
+
+    /// The archive of compressed sessions, in the `archive` subdirectory
+    pub fn archived(&self) -> Archive {
+        Archive::new(self.dir.join("archive"))
+    }
+
+    /// The file of the session `id`, which must exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ID is not a plain file name or there is no
+    /// such session.
+    pub fn find(&self, id: &str) -> Result<PathBuf> {
+        if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+            bail!("Invalid session ID '{}'", id);
+        }
+
+        let path = self.path(id);
+        if path.is_file() {
+            return Ok(path);
+        }
+        let archived = self.archived().path(id);
+        if !archived.is_file() {
+            bail!("No saved session '{}'; `claude-dialog sessions list` shows them", id);
+        }
+        Ok(archived)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, EXTENSION))
+    }
+}
+
+/// The sessions as aligned `id  saved  turns  title` lines
+///
+/// # Output Format
+///
+/// ```text
+/// 20250102-093000  2025-01-02 09:30  4 turns    Review the parser
+/// 20250101-120000  2025-01-01 12:00  1 turn     What is a trait? (archived)
+/// ```
+pub fn table(sessions: &[SessionInfo]) -> String {
+    let width = sessions.iter().map(|session| session.id.chars().count()).max().unwrap_or(0);
+    sessions
+        .iter()
+        .map(|session| {
+            let turns = format!("{} {}", session.turns, if session.turns == 1 { "turn" } else { "turns" });
+            format!(
+                "{:<width$}  {}  {:<9}  {}",
+                session.id,
+                session.saved_at.format("%Y-%m-%d %H:%M"),
+                turns,
+                if session.archived { format!("{} (archived)", session.title) } else { session.title.clone() }
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The first non-empty line of a prompt, shortened to [`TITLE_LENGTH`]
+fn title(prompt: &str) -> String {
+    let line = prompt.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    if line.chars().count() <= TITLE_LENGTH {
+        return line.to_string();
+    }
+    let mut title: String = line.chars().take(TITLE_LENGTH - 3).collect();
+    title.push_str("...");
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_saved_in_the_same_second_get_their_own_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().join("sessions"));
+        let mut conversation = Conversation::new();
+        conversation.push("hello");
+
+        let first = store.save(&conversation, None).unwrap();
+        let second = store.save(&conversation, None).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(store.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_ids_must_name_a_saved_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path());
+        fs::write(dir.path().join("notes.json"), "{}").unwrap();
+
+        assert!(store.list().unwrap().is_empty());
+        assert!(store.find("../notes").unwrap_err().to_string().starts_with("Invalid session ID"));
+        assert!(store.delete("20250101-120000").unwrap_err().to_string().starts_with("No saved session"));
+        assert!(store.load("notes").is_err());
+    }
+
+    #[test]
+    fn test_long_titles_are_shortened() {
+        assert_eq!(title("\n  Explain this  \nin detail"), "Explain this");
+        let long = title(&"word ".repeat(20));
+        assert_eq!(long.chars().count(), TITLE_LENGTH);
+        assert!(long.ends_with("..."));
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_archived_sessions_are_still_listed_loaded_and_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path());
+        let mut conversation = Conversation::new();
+        conversation.push("What is a trait?");
+        let old = store.save(&conversation, None).unwrap();
+
+        let (archived, _) = store.archive(TimeDelta::zero()).unwrap();
+        let recent = store.save(&conversation, None).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert!(store.archive(TimeDelta::days(1)).unwrap().0.is_empty());
+
+        let sessions = store.list().unwrap();
+        assert_eq!(sessions.iter().map(|session| (session.id.as_str(), session.archived)).collect::<Vec<_>>(), [(recent.as_str(), false), (old.as_str(), true)]);
+        assert!(table(&sessions).lines().nth(1).unwrap().ends_with("What is a trait? (archived)"));
+        assert!(!dir.path().join(format!("{}.json", old)).exists());
+        assert_eq!(store.load(&old).unwrap().conversation(), conversation);
+
+        store.delete(&old).unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert!(store.load(&old).is_err());
+    }
+}
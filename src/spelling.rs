@@ -0,0 +1,364 @@
+//! Spelling module
+//!
+//! `--spellcheck` looks for likely typos in a prompt before it is sent and
+//! lets the user send it anyway or edit it first. Misspelled identifiers in
+//! particular tend to produce confident answers about code that does not
+//! exist, so they are worth a second look.
+//!
+//! Everything is checked locally:
+//!
+//! - Ordinary words are looked up in a word list, the system's
+//!   `/usr/share/dict/words` unless `--dictionary` names others, plus a
+//!   built-in list of programming terms. Without a word list, words are not
+//!   checked.
+//! - Identifiers such as `read_input` or `parseArgs` are compared with the
+//!   identifiers of the files the prompt includes and of the conversation so
+//!   far; one that is not among them but close to one of them is reported.
+//!
+//! Code blocks, inline code, `@file` references, URLs and paths are not
+//! checked, nor are acronyms, words with digits and words shorter than three
+//! letters.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::spelling::Speller;
+//!
+//! let speller = Speller::with_words("why\ndoes\nnot\nreceive\nthe\nvalue");
+//! let typos = speller.check("Why does recieve_value not recieve the value?", "fn receive_value() {}");
+//!
+//! let found: Vec<String> = typos.iter().map(ToString::to_string).collect();
+//! assert_eq!(found, ["recieve_value (receive_value?)", "recieve (receive?)"]);
+//! ```
+
+use anyhow::{Result, Context};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::language;
+
+/// Word lists used when no `--dictionary` is given, the first one found
+pub const SYSTEM_WORD_LISTS: &[&str] = &[
+    "/usr/share/dict/words",
+    "/usr/share/dict/american-english",
+    "/usr/share/dict/british-english",
+];
+
+/// Words shorter than this are not checked
+const MIN_LENGTH: usize = 3;
+
+/// Programming terms missing from most word lists
+const PROGRAMMING_WORDS: &[&str] = &[
+    "api", "apis", "args", "ascii", "async", "backend", "backtrace", "bool", "boolean", "booleans",
+    "callback", "callbacks", "changelog", "cli", "clippy", "codebase", "config", "configs", "const",
+    "css", "deserialize", "deserialized", "dir", "dirs", "dockerfile", "enum", "enums", "env",
+    "filename", "filenames", "filesystem", "frontend", "func", "github", "gitignore", "hashmap",
+    "html", "http", "https", "impl", "impls", "init", "inline", "int", "iterator", "iterators",
+    "javascript", "json", "kubernetes", "lib", "linter", "localhost", "metadata", "middleware",
+    "mutex", "namespace", "namespaces", "npm", "nullable", "param", "params", "println", "readme",
+    "refactor", "refactored", "refactoring", "regex", "repo", "repos", "runtime", "rustc",
+    "rustfmt", "serde", "serialize", "serialized", "sql", "sqlite", "src", "stderr", "stdin",
+    "stdout", "str", "struct", "structs", "subcommand", "subcommands", "subprocess", "timestamp",
+    "timestamps", "tmp", "todo", "tokio", "toml", "typescript", "unicode", "unwrap", "url",
+    "urls", "usize", "utf", "vec", "webhook", "workflow", "workflows", "yaml",
+];
+
+/// A likely typo in a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Typo {
+    /// The word as written in the prompt
+    pub word: String,
+
+    /// The closest known word, if one is close enough
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for Typo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.suggestion {
+            Some(suggestion) => write!(f, "{} ({}?)", self.word, suggestion),
+            None => write!(f, "{}", self.word),
+        }
+    }
+}
+
+/// Finds likely typos in prompts
+#[derive(Debug, Clone)]
+pub struct Speller {
+    // Lowercase
+    words: HashSet<String>,
+    word_list: bool,
+}
+
+impl Default for Speller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Speller {
+    /// Create a speller without a word list, which checks only identifiers
+    pub fn new() -> Self {
+        Self {
+            words: PROGRAMMING_WORDS.iter().map(|word| word.to_string()).collect(),
+            word_list: false,
+        }
+    }
+
+    /// Create a speller with the words of `text`, one per line, as its word list
+    pub fn with_words(text: &str) -> Self {
+        let mut speller = Self::new();
+        speller.add_words(text);
+        speller
+    }
+
+    /// Create a speller with the word lists in `paths`, or the first of
+    /// [`SYSTEM_WORD_LISTS`] found if `paths` is empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a word list cannot be read.
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        let mut speller = Self::new();
+        let system = system_word_list();
+        let paths = match (paths.is_empty(), &system) {
+            (true, Some(system)) => std::slice::from_ref(system),
+            _ => paths,
+        };
+        for path in paths {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read word list: {}", path.display()))?;
+            speller.add_words(&text);
+        }
+        Ok(speller)
+    }
+
+    /// Add the words of `text`, one per line, to the word list
+    ///
+    /// Lines starting with `#` are skipped, so word lists can have comments.
+    pub fn add_words(&mut self, text: &str) {
+        let words = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_lowercase);
+        self.words.extend(words);
+        self.word_list = true;
+    }
+
+    /// Whether ordinary words are checked, rather than only identifiers
+    pub fn has_word_list(&self) -> bool {
+        self.word_list
+    }
+
+    /// Find likely typos in a prompt
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt as typed
+    /// * `context` - Text whose words are known to be right, such as the
+    ///   included files and the conversation so far
+    ///
+    /// # Returns
+    ///
+    /// The likely typos in the order they appear, each word once.
+    pub fn check(&self, prompt: &str, context: &str) -> Vec<Typo> {
+        let known = Vocabulary::new(context);
+        let mut typos: Vec<Typo> = Vec::new();
+        for word in words(&language::prose(prompt)) {
+            if typos.iter().any(|typo| typo.word == word) {
+                continue;
+            }
+            if let Some(typo) = self.check_word(word, &known) {
+                typos.push(typo);
+            }
+        }
+        typos
+    }
+
+    fn check_word(&self, word: &str, known: &Vocabulary) -> Option<Typo> {
+        let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+        let checked = letters >= MIN_LENGTH
+            && !word.chars().any(|c| c.is_ascii_digit())
+            && word.chars().any(char::is_lowercase);
+        let lower = word.to_lowercase();
+        if !checked || known.exact.contains(word) || known.lower.contains(&lower) {
+            return None;
+        }
+
+        if is_identifier(word) {
+            // Only identifiers close to a known one are likely typos
+            let suggestion = nearest(word, known.exact.iter().filter(|known| is_identifier(known)))?;
+            return Some(Typo {
+                word: word.to_string(),
+                suggestion: Some(suggestion),
+            });
+        }
+
+        let stem = lower.split('\'').next().unwrap_or_default();
+        if !self.word_list || self.words.contains(&lower) || self.words.contains(stem) {
+            return None;
+        }
+        let suggestion = nearest(&lower, self.words.iter());
+        // Capitalized words without a close match are most likely names
+        if suggestion.is_none() && word.starts_with(char::is_uppercase) {
+            return None;
+        }
+        Some(Typo {
+            word: word.to_string(),
+            suggestion,
+        })
+    }
+}
+
+/// Words of a text that are known to be right
+struct Vocabulary {
+    exact: HashSet<String>,
+    lower: HashSet<String>,
+}
+
+impl Vocabulary {
+    fn new(text: &str) -> Self {
+        let exact: HashSet<String> = text
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|word| !word.is_empty())
+            .map(String::from)
+            .collect();
+        let lower = exact.iter().map(|word| word.to_lowercase()).collect();
+        Self { exact, lower }
+    }
+}
+
+/// The first system word list that exists
+pub fn system_word_list() -> Option<PathBuf> {
+    SYSTEM_WORD_LISTS.iter().map(PathBuf::from).find(|path| Path::is_file(path))
+}
+
+/// The words of a text, skipping paths and dotted names like `main.rs`
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .filter(|chunk| {
+            let dotted = chunk
+                .trim_end_matches(|c: char| !c.is_alphanumeric())
+                .contains(['.', '/', '\\']);
+            !dotted && !chunk.contains("::")
+        })
+        .flat_map(|chunk| chunk.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\'')))
+        .map(|word| word.trim_matches(['\'', '_']))
+        .filter(|word| !word.is_empty())
+}
+
+/// Whether a word is written like an identifier, `snake_case` or `camelCase`
+fn is_identifier(word: &str) -> bool {
+    let chars: Vec<char> = word.chars().collect();
+    word.contains('_') || chars.windows(2).any(|pair| pair[0].is_lowercase() && pair[1].is_uppercase())
+}
+
+/// The candidate closest to `word`, if it is within a typo's distance
+///
+/// Ties go to the candidate starting with the same letter, then to the
+/// first in alphabetical order.
+fn nearest<'a>(word: &str, candidates: impl Iterator<Item = &'a String>) -> Option<String> {
+    let word: Vec<char> = word.to_lowercase().chars().collect();
+    let limit = if word.len() <= 4 { 1 } else { 2 };
+
+    let mut best: Option<(usize, bool, &String)> = None;
+    for candidate in candidates {
+        let lower: Vec<char> = candidate.to_lowercase().chars().collect();
+        if lower.len().abs_diff(word.len()) > limit || lower == word {
+            continue;
+        }
+        let distance = edit_distance(&word, &lower);
+        if distance > limit {
+            continue;
+        }
+        let other_initial = lower.first() != word.first();
+        let better = match best {
+            None => true,
+            Some((best_distance, best_other_initial, best_candidate)) => {
+                (distance, other_initial, candidate) < (best_distance, best_other_initial, best_candidate)
+            }
+        };
+        if better {
+            best = Some((distance, other_initial, candidate));
+        }
+    }
+    best.map(|(_, _, candidate)| candidate.clone())
+}
+
+/// Edits needed to turn `a` into `b`, counting a swap of neighboring letters
+/// as one edit
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance_counts_swaps_once() {
+        let chars = |word: &str| word.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("teh"), &chars("the")), 1);
+        assert_eq!(edit_distance(&chars("recieve"), &chars("receive")), 1);
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+    }
+
+    #[test]
+    fn test_code_and_paths_are_not_checked() {
+        let speller = Speller::with_words("look\nat\nand");
+        let prompt = "Look at `fooo` and src/maain.rs and @notez.md and https://exampel.com\n```\nbarr\n```";
+        assert!(speller.check(prompt, "").is_empty());
+    }
+
+    #[test]
+    fn test_skipped_words() {
+        let speller = Speller::with_words("the\nlog");
+        // Acronyms, words with digits, short words and names without a close match
+        assert!(speller.check("the HTTPX log utf8 zq Zanzibar", "").is_empty());
+        // Without a word list only identifiers are checked
+        assert!(Speller::new().check("teh lgo", "").is_empty());
+        assert_eq!(Speller::with_words("the").check("teh", "")[0].suggestion.as_deref(), Some("the"));
+    }
+
+    #[test]
+    fn test_identifiers_need_a_close_known_identifier() {
+        let speller = Speller::new();
+        let context = "fn parse_args() {}\nlet configPath = 1;";
+        let typos = speller.check("Is parse_arsg or configPth or other_thing wrong?", context);
+        let found: Vec<String> = typos.iter().map(ToString::to_string).collect();
+        assert_eq!(found, ["parse_arsg (parse_args?)", "configPth (configPath?)"]);
+    }
+
+    #[test]
+    fn test_load_reads_word_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.txt");
+        fs::write(&path, "# project words\nClaude\nrustyline\n").unwrap();
+
+        let speller = Speller::load(std::slice::from_ref(&path)).unwrap();
+        assert!(speller.has_word_list());
+        assert!(speller.check("claude uses rustyline", "uses").is_empty());
+        assert!(Speller::load(&[dir.path().join("missing.txt")]).is_err());
+    }
+}
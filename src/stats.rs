@@ -0,0 +1,284 @@
+//! Usage statistics module
+//!
+//! Keeps a running tally of the tokens and estimated cost of each turn of a
+//! session, as reported by the backend: the Claude CLI reports both with
+//! `json` and `stream-json` output, Ollama reports token counts only.
+//! `--show-usage` prints the usage of each turn after its response,
+//! `/stats` (or `/cost`) shows the tally so far, and a summary is printed
+//! when the session ends.
+//!
+//! The Claude CLI caches the system prompt and earlier turns between turns
+//! on its own; the tally shows how many input tokens were read from and
+//! written to that cache, as cached tokens cost a fraction of the input
+//! price.
+//!
+//! Requests made on behalf of the session rather than as one of its turns,
+//! such as follow-up suggestions, are tallied separately as background
+//! usage and counted in the session total.
+//!
+//! Costs are the Claude CLI's estimates at API prices, not what a
+//! subscription is billed.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::stats::SessionStats;
+//! use claude_dialog::status::Usage;
+//!
+//! let mut stats = SessionStats::default();
+//! stats.record(Some(Usage { input_tokens: 1200, output_tokens: 300, cost_usd: Some(0.012), ..Default::default() }));
+//! stats.record(None);
+//! stats.record(Some(Usage { input_tokens: 800, output_tokens: 100, cost_usd: Some(0.004), ..Default::default() }));
+//!
+//! assert_eq!(stats.turns(), 3);
+//! assert_eq!(stats.reported(), 2);
+//! assert_eq!(stats.total().input_tokens, 2000);
+//! assert_eq!(
+//!     stats.summary_line(),
+//!     "Session usage: 2000 tokens in, 400 out, estimated cost $0.0160 (2 of 3 turns reported)"
+//! );
+//! ```
+
+use std::fmt;
+use crate::status::Usage;
+
+/// Token usage and cost of the turns of a session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    // One entry per turn that got a response, `None` if it reported no usage
+    turns: Vec<Option<Usage>>,
+    // Requests that were not turns, and whether any of them reported usage
+    background: Usage,
+    background_reported: bool,
+}
+
+impl SessionStats {
+    /// Record the usage of a turn, `None` if the backend did not report it
+    pub fn record(&mut self, usage: Option<Usage>) {
+        self.turns.push(usage);
+    }
+
+    /// Record the usage of a request made on behalf of the session rather
+    /// than as one of its turns, `None` if the backend did not report it
+    pub fn record_background(&mut self, usage: Option<Usage>) {
+        if let Some(usage) = usage {
+            self.background.add(&usage);
+            self.background_reported = true;
+        }
+    }
+
+    /// Number of turns recorded
+    pub fn turns(&self) -> usize {
+        self.turns.len()
+    }
+
+    /// Number of turns that reported their usage
+    pub fn reported(&self) -> usize {
+        self.turns.iter().flatten().count()
+    }
+
+    /// Usage of the most recent turn, if it was reported
+    pub fn last(&self) -> Option<&Usage> {
+        self.turns.last()?.as_ref()
+    }
+
+    /// Total usage of the turns that reported it and of background requests
+    pub fn total(&self) -> Usage {
+        let mut total = self.turn_total();
+        total.add(&self.background);
+        total
+    }
+
+    /// Usage of background requests, if any reported it
+    pub fn background(&self) -> Option<&Usage> {
+        self.background_reported.then_some(&self.background)
+    }
+
+    /// Total usage of the turns that reported it
+    fn turn_total(&self) -> Usage {
+        let mut total = Usage::default();
+        for usage in self.turns.iter().flatten() {
+            total.add(usage);
+        }
+        total
+    }
+
+    /// The tally as `(label, value)` pairs, in display order
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let reported = self.reported();
+        let mut turns = self.turns().to_string();
+        if reported < self.turns() {
+            turns.push_str(&format!(" (usage reported for {})", reported));
+        }
+        let mut entries = vec![("Turns", turns)];
+        if reported == 0 {
+            entries.push(("Tokens", "not reported".to_string()));
+            return entries;
+        }
+
+        let total = self.total();
+        let turn_total = self.turn_total();
+        let count = reported as u64;
+        entries.push((
+            "Tokens",
+            format!(
+                "{} in, {} out ({} total)",
+                total.input_tokens,
+                total.output_tokens,
+                total.input_tokens + total.output_tokens
+            ),
+        ));
+        if let Some(rate) = total.cache_hit_rate() {
+            entries.push((
+                "Cache",
+                format!(
+                    "{} read, {} written ({:.0}% of input tokens read from cache)",
+                    total.cache_read_tokens,
+                    total.cache_write_tokens,
+                    rate * 100.0
+                ),
+            ));
+        }
+        entries.push((
+            "Per turn",
+            format!("{} in, {} out on average", turn_total.input_tokens / count, turn_total.output_tokens / count),
+        ));
+        entries.push((
+            "Cost",
+            match (total.cost_usd, turn_total.cost_usd) {
+                (Some(cost), Some(turns)) => format!("${:.4} estimated (${:.4} per turn)", cost, turns / reported as f64),
+                (Some(cost), None) => format!("${:.4} estimated", cost),
+                (None, _) => "not reported".to_string(),
+            },
+        ));
+        if let Some(background) = self.background() {
+            entries.push(("Background", format_usage(background)));
+        }
+        if let Some(last) = self.last() {
+            entries.push(("Last turn", format_usage(last)));
+        }
+        entries
+    }
+
+    /// One line summing up the session, as printed when it ends
+    pub fn summary_line(&self) -> String {
+        let total = self.total();
+        let mut line = format!("Session usage: {} tokens in, {} out", total.input_tokens, total.output_tokens);
+        if let Some(cost) = total.cost_usd {
+            line.push_str(&format!(", estimated cost ${:.4}", cost));
+        }
+        if self.reported() < self.turns() {
+            line.push_str(&format!(" ({} of {} turns reported)", self.reported(), self.turns()));
+        }
+        line
+    }
+}
+
+impl fmt::Display for SessionStats {
+    /// Format the tally as aligned `Label: value` lines
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    ///     Turns: 3 (usage reported for 2)
+    ///    Tokens: 2000 in, 400 out (2400 total)
+    ///     Cache: 1500 read, 300 written (75% of input tokens read from cache)
+    ///  Per turn: 1000 in, 200 out on average
+    ///      Cost: $0.0160 estimated ($0.0080 per turn)
+    /// Last turn: 800 in, 100 out, $0.0040
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries();
+        let width = entries.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        for (label, value) in entries {
+            writeln!(f, "{:>width$}: {}", label, value, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format the usage of a turn, e.g. `1200 in (800 cached), 300 out, $0.0120`
+pub fn format_usage(usage: &Usage) -> String {
+    let mut text = format!("{} in", usage.input_tokens);
+    if usage.cache_read_tokens > 0 {
+        text.push_str(&format!(" ({} cached)", usage.cache_read_tokens));
+    }
+    text.push_str(&format!(", {} out", usage.output_tokens));
+    if let Some(cost) = usage.cost_usd {
+        text.push_str(&format!(", ${:.4}", cost));
+    }
+    text
+}
+
+/// Format the line shown after a response with `--show-usage`, e.g.
+/// `Tokens: 1200 in, 300 out, $0.0120 (session: 2000 in, 400 out, $0.0160)`
+pub fn format_turn(usage: &Usage, total: &Usage) -> String {
+    format!("Tokens: {} (session: {})", format_usage(usage), format_usage(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nothing_reported() {
+        let mut stats = SessionStats::default();
+        stats.record(None);
+        assert_eq!(stats.last(), None);
+        assert_eq!(stats.to_string(), " Turns: 1 (usage reported for 0)\nTokens: not reported\n");
+    }
+
+    #[test]
+    fn test_tally_without_cost() {
+        let mut stats = SessionStats::default();
+        stats.record(Some(Usage { input_tokens: 10, output_tokens: 4, cost_usd: None, ..Default::default() }));
+        stats.record(Some(Usage { input_tokens: 30, output_tokens: 6, cost_usd: None, ..Default::default() }));
+
+        let text = stats.to_string();
+        assert!(text.contains("    Turns: 2\n"));
+        assert!(text.contains("   Tokens: 40 in, 10 out (50 total)\n"));
+        assert!(text.contains(" Per turn: 20 in, 5 out on average\n"));
+        assert!(text.contains("     Cost: not reported\n"));
+        assert!(text.ends_with("Last turn: 30 in, 6 out\n"));
+        assert_eq!(stats.summary_line(), "Session usage: 40 tokens in, 10 out");
+    }
+
+    #[test]
+    fn test_cache_reads_and_writes() {
+        let mut stats = SessionStats::default();
+        stats.record(Some(Usage { input_tokens: 1000, output_tokens: 50, cache_write_tokens: 900, ..Default::default() }));
+        stats.record(Some(Usage { input_tokens: 1000, output_tokens: 50, cache_read_tokens: 900, ..Default::default() }));
+
+        let text = stats.to_string();
+        assert!(text.contains("    Cache: 900 read, 900 written (45% of input tokens read from cache)\n"));
+        assert!(text.ends_with("Last turn: 1000 in (900 cached), 50 out\n"));
+        assert!(!SessionStats::default().to_string().contains("Cache"));
+    }
+
+    #[test]
+    fn test_background_usage_counts_toward_the_total() {
+        let mut stats = SessionStats::default();
+        stats.record(Some(Usage { input_tokens: 100, output_tokens: 20, cost_usd: Some(0.01), ..Default::default() }));
+        stats.record_background(None);
+        assert_eq!(stats.background(), None);
+        stats.record_background(Some(Usage { input_tokens: 50, output_tokens: 10, cost_usd: Some(0.002), ..Default::default() }));
+
+        assert_eq!(stats.turns(), 1);
+        assert_eq!(stats.total().input_tokens, 150);
+        assert_eq!(stats.total().cost_usd, Some(0.012));
+        let text = stats.to_string();
+        assert!(text.contains("  Per turn: 100 in, 20 out on average\n"));
+        assert!(text.contains("      Cost: $0.0120 estimated ($0.0100 per turn)\n"));
+        assert!(text.contains("Background: 50 in, 10 out, $0.0020\n"));
+    }
+
+    #[test]
+    fn test_format_turn() {
+        let turn = Usage { input_tokens: 1200, output_tokens: 300, cost_usd: Some(0.012), ..Default::default() };
+        let total = Usage { input_tokens: 2000, output_tokens: 400, cost_usd: Some(0.016), ..Default::default() };
+        assert_eq!(
+            format_turn(&turn, &total),
+            "Tokens: 1200 in, 300 out, $0.0120 (session: 2000 in, 400 out, $0.0160)"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Session status module
+//!
+//! `/status` shows the state of the running session at a glance: where
+//! prompts go, which Claude session they continue, how many turns were
+//! exchanged and how their responses were rated, the tokens and cost
+//! reported so far, how long the session has been running, context queued
+//! for the next prompt and the last error.
+//!
+//! Token counts and costs are reported by the Claude CLI with its result;
+//! Ollama reports token counts but no cost. `/stats` breaks them down (see
+//! [`crate::stats`]).
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::status::{SessionStatus, Usage};
+//!
+//! let mut usage = Usage::default();
+//! usage.add(&Usage { input_tokens: 1200, output_tokens: 300, cost_usd: Some(0.012), ..Default::default() });
+//! usage.add(&Usage { input_tokens: 800, output_tokens: 100, cost_usd: None, ..Default::default() });
+//!
+//! let status = SessionStatus {
+//!     backend: "claude".to_string(),
+//!     turns: 2,
+//!     prompts_sent: 2,
+//!     usage,
+//!     prompts_reported: 2,
+//!     ..Default::default()
+//! };
+//! assert!(status.to_string().contains("Tokens: 2000 in, 400 out\n"));
+//! assert!(status.to_string().contains("Cost: $0.0120\n"));
+//! ```
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use crate::conversation::MAX_RATING;
+
+/// Tokens and cost of one or more turns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Tokens sent, including cached prompt tokens
+    pub input_tokens: u64,
+
+    /// Tokens generated
+    pub output_tokens: u64,
+
+    /// Input tokens read from the prompt cache, billed at a fraction of the
+    /// input price
+    pub cache_read_tokens: u64,
+
+    /// Input tokens written to the prompt cache for the following turns
+    pub cache_write_tokens: u64,
+
+    /// Cost in US dollars, `None` if it was not reported
+    pub cost_usd: Option<f64>,
+}
+
+impl Usage {
+    /// Add the usage of another turn
+    ///
+    /// The cost stays `None` only while no turn has reported one.
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+        self.cost_usd = match (self.cost_usd, other.cost_usd) {
+            (Some(cost), Some(other)) => Some(cost + other),
+            (cost, other) => cost.or(other),
+        };
+    }
+
+    /// Share of the input tokens read from the prompt cache, `None` if the
+    /// prompt cache was not used
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::status::Usage;
+    ///
+    /// let usage = Usage { input_tokens: 4312, cache_read_tokens: 4000, cache_write_tokens: 300, ..Default::default() };
+    /// assert_eq!(usage.cache_hit_rate().map(|rate| (rate * 100.0).round()), Some(93.0));
+    /// assert_eq!(Usage::default().cache_hit_rate(), None);
+    /// ```
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        if self.cache_read_tokens + self.cache_write_tokens == 0 || self.input_tokens == 0 {
+            return None;
+        }
+        Some(self.cache_read_tokens as f64 / self.input_tokens as f64)
+    }
+}
+
+/// A snapshot of the state of a dialog session
+///
+/// # Output Format
+///
+/// ```text
+///    Backend: claude
+///      Model: opus
+///    Persona: final
+///    Session: 3f2a9c1e-7b4d-4e1a-9c2f-5d8e6a0b1c3d
+///      Turns: 4
+///    Ratings: 3 responses, average 4.3 (5: 2, 3: 1)
+///     Tokens: 18200 in, 2450 out
+///       Cost: $0.0934
+///    Elapsed: 12m 04s
+///     Queued: 1 context block for the next prompt
+/// Last error: [14:02] No checkpoint named 'draft'
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SessionStatus {
+    /// Backend the next prompt is sent to
+    pub backend: String,
+
+    /// Model of the next prompt, `None` for the backend's default
+    pub model: Option<String>,
+
+    /// Active persona, `None` for the session's own backend and model
+    pub persona: Option<String>,
+
+    /// The Claude session ID, or a description of the conversation the
+    /// next prompt attaches to
+    pub session: String,
+
+    /// Turns in the conversation
+    pub turns: usize,
+
+    /// Prompts sent during the session, including turns forgotten since
+    pub prompts_sent: usize,
+
+    /// Ratings given with `/rate` during the session, including ratings of
+    /// turns forgotten since
+    pub ratings: Vec<u8>,
+
+    /// Total usage of the prompts that reported it
+    pub usage: Usage,
+
+    /// Prompts whose usage was reported
+    pub prompts_reported: usize,
+
+    /// Time since the session started
+    pub elapsed: Duration,
+
+    /// Context blocks queued for the next prompt, e.g. a repository map
+    pub queued: usize,
+
+    /// The most recent error and when it happened
+    pub last_error: Option<(DateTime<Local>, String)>,
+}
+
+impl SessionStatus {
+    /// The status as `(label, value)` pairs, in display order
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("Backend", self.backend.clone()),
+            ("Model", self.model.clone().unwrap_or_else(|| "default".to_string())),
+        ];
+        if let Some(persona) = &self.persona {
+            entries.push(("Persona", persona.clone()));
+        }
+        entries.push(("Session", self.session.clone()));
+        entries.push(("Turns", self.turns.to_string()));
+        entries.push(("Ratings", format_ratings(&self.ratings)));
+
+        if self.prompts_reported == 0 {
+            let unknown = "not reported".to_string();
+            entries.push(("Tokens", unknown.clone()));
+            entries.push(("Cost", unknown));
+        } else {
+            let mut tokens = format!("{} in, {} out", self.usage.input_tokens, self.usage.output_tokens);
+            if self.prompts_reported < self.prompts_sent {
+                tokens.push_str(&format!(" ({} of {} prompts reported)", self.prompts_reported, self.prompts_sent));
+            }
+            entries.push(("Tokens", tokens));
+            entries.push((
+                "Cost",
+                match self.usage.cost_usd {
+                    Some(cost) => format!("${:.4}", cost),
+                    None => "not reported".to_string(),
+                },
+            ));
+        }
+
+        entries.push(("Elapsed", format_elapsed(self.elapsed)));
+        entries.push(("Queued", match self.queued {
+            0 => "nothing".to_string(),
+            1 => "1 context block for the next prompt".to_string(),
+            count => format!("{} context blocks for the next prompt", count),
+        }));
+        entries.push(("Last error", match &self.last_error {
+            Some((time, message)) => format!("[{}] {}", time.format("%H:%M"), message),
+            None => "none".to_string(),
+        }));
+        entries
+    }
+}
+
+impl fmt::Display for SessionStatus {
+    /// Format the status as aligned `Label: value` lines
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries();
+        let width = entries.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        for (label, value) in entries {
+            writeln!(f, "{:>width$}: {}", label, value, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format ratings as their number, average and distribution, e.g.
+/// `3 responses, average 4.3 (5: 2, 3: 1)`
+pub fn format_ratings(ratings: &[u8]) -> String {
+    if ratings.is_empty() {
+        return "none (rate responses with /rate)".to_string();
+    }
+
+    let average = ratings.iter().map(|&rating| f64::from(rating)).sum::<f64>() / ratings.len() as f64;
+    let distribution: Vec<String> = (1..=MAX_RATING)
+        .rev()
+        .filter_map(|rating| {
+            let count = ratings.iter().filter(|&&given| given == rating).count();
+            (count > 0).then(|| format!("{}: {}", rating, count))
+        })
+        .collect();
+    let responses = if ratings.len() == 1 { "response" } else { "responses" };
+    format!("{} {}, average {:.1} ({})", ratings.len(), responses, average, distribution.join(", "))
+}
+
+/// Format a duration as `12s`, `3m 05s` or `1h 02m`
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(12)), "12s");
+        assert_eq!(format_elapsed(Duration::from_secs(185)), "3m 05s");
+        assert_eq!(format_elapsed(Duration::from_secs(3720)), "1h 02m");
+    }
+
+    #[test]
+    fn test_format_ratings() {
+        assert_eq!(format_ratings(&[5, 3, 5]), "3 responses, average 4.3 (5: 2, 3: 1)");
+        assert_eq!(format_ratings(&[2]), "1 response, average 2.0 (2: 1)");
+        assert!(format_ratings(&[]).starts_with("none"));
+    }
+
+    #[test]
+    fn test_unreported_usage() {
+        let status = SessionStatus {
+            backend: "claude".to_string(),
+            session: "continues the most recent conversation".to_string(),
+            ..Default::default()
+        };
+        let text = status.to_string();
+        assert!(text.contains("    Tokens: not reported\n"));
+        assert!(text.contains("    Queued: nothing\n"));
+        assert!(text.ends_with("Last error: none\n"));
+        assert!(!text.contains("Persona"));
+    }
+
+    #[test]
+    fn test_partly_reported_usage() {
+        let status = SessionStatus {
+            backend: "ollama".to_string(),
+            turns: 1,
+            prompts_sent: 3,
+            usage: Usage { input_tokens: 10, output_tokens: 5, cost_usd: None, ..Default::default() },
+            prompts_reported: 2,
+            queued: 2,
+            ..Default::default()
+        };
+        let text = status.to_string();
+        assert!(text.contains("Tokens: 10 in, 5 out (2 of 3 prompts reported)\n"));
+        assert!(text.contains("Cost: not reported\n"));
+        assert!(text.contains("Queued: 2 context blocks for the next prompt\n"));
+    }
+}
@@ -0,0 +1,346 @@
+//! Claude CLI `stream-json` output parsing module
+//!
+//! With `--output-format stream-json --verbose` the Claude CLI prints one
+//! JSON object per line describing everything that happens during a turn:
+//! assistant text, thinking, tool calls, tool results and finally the result
+//! message. With `--include-partial-messages` it also prints the text of each
+//! message piece by piece while it is generated. This module extracts the
+//! events `claude-dialog` cares about and ignores everything else, so new
+//! event types added by the CLI do not break parsing.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::stream_json::{StreamEvent, parse_line};
+//!
+//! let line = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Read","input":{}}]}}"#;
+//! assert_eq!(parse_line(line), vec![StreamEvent::ToolUse { name: "Read".to_string() }]);
+//!
+//! let line = r#"{"type":"result","subtype":"success","is_error":false,"result":"Done."}"#;
+//! assert_eq!(parse_line(line)[0], StreamEvent::Result { text: "Done.".to_string(), is_error: false });
+//! ```
+
+use serde_json::Value;
+use std::fmt;
+use crate::status::Usage;
+
+/// An event of interest in the `stream-json` output
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// A block of assistant text
+    Text(String),
+
+    /// A piece of assistant text as it is generated, followed by the whole
+    /// block as [`StreamEvent::Text`] once it is complete
+    TextDelta(String),
+
+    /// A block of extended thinking
+    Thinking,
+
+    /// A tool call
+    ToolUse {
+        /// Name of the tool, e.g. `Edit`
+        name: String,
+    },
+
+    /// The final result of the turn
+    Result {
+        /// The final assistant message, or the error description
+        text: String,
+
+        /// Whether the turn failed
+        is_error: bool,
+    },
+
+    /// Session and usage of the turn, reported with its result
+    Usage {
+        /// ID of the Claude session the turn belongs to
+        session_id: Option<String>,
+
+        /// Tokens and cost of the turn
+        usage: Usage,
+    },
+}
+
+/// Parse one line of `stream-json` output
+///
+/// Lines that are not JSON objects, and messages without any event of
+/// interest, yield no events.
+pub fn parse_line(line: &str) -> Vec<StreamEvent> {
+    let Ok(message) = serde_json::from_str::<Value>(line.trim()) else {
+        return Vec::new();
+    };
+
+    match message["type"].as_str() {
+        Some("assistant") => message["message"]["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(parse_content_block)
+            .collect(),
+        Some("stream_event") => {
+            let event = &message["event"];
+            match (event["type"].as_str(), event["delta"]["type"].as_str()) {
+                (Some("content_block_delta"), Some("text_delta")) => event["delta"]["text"]
+                    .as_str()
+                    .map(|text| vec![StreamEvent::TextDelta(text.to_string())])
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            }
+        }
+        Some("result") => vec![
+            StreamEvent::Result {
+                text: message["result"].as_str().unwrap_or_default().to_string(),
+                is_error: message["is_error"].as_bool().unwrap_or(false),
+            },
+            StreamEvent::Usage {
+                session_id: message["session_id"].as_str().map(String::from),
+                usage: parse_usage(&message),
+            },
+        ],
+        _ => Vec::new(),
+    }
+}
+
+fn parse_content_block(block: &Value) -> Option<StreamEvent> {
+    match block["type"].as_str()? {
+        "text" => Some(StreamEvent::Text(block["text"].as_str()?.to_string())),
+        "thinking" | "redacted_thinking" => Some(StreamEvent::Thinking),
+        "tool_use" => Some(StreamEvent::ToolUse {
+            name: block["name"].as_str().unwrap_or("tool").to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Usage of a result message; cached prompt tokens count as input tokens
+fn parse_usage(message: &Value) -> Usage {
+    let usage = &message["usage"];
+    let tokens = |field: &str| usage[field].as_u64().unwrap_or(0);
+    let cache_read_tokens = tokens("cache_read_input_tokens");
+    let cache_write_tokens = tokens("cache_creation_input_tokens");
+    Usage {
+        input_tokens: tokens("input_tokens") + cache_write_tokens + cache_read_tokens,
+        output_tokens: tokens("output_tokens"),
+        cache_read_tokens,
+        cache_write_tokens,
+        cost_usd: message["total_cost_usd"].as_f64(),
+    }
+}
+
+/// Running summary of the activity during a turn
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::stream_json::{Activity, StreamEvent};
+///
+/// let mut activity = Activity::default();
+/// activity.record(&StreamEvent::ToolUse { name: "Read".to_string() });
+/// activity.record(&StreamEvent::ToolUse { name: "Edit".to_string() });
+///
+/// assert_eq!(activity.tool_calls, 2);
+/// assert_eq!(activity.to_string(), "Working... 2 tool calls (last: Edit)");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Activity {
+    /// Number of tool calls so far
+    pub tool_calls: usize,
+
+    /// Name of the most recent tool call
+    pub last_tool: Option<String>,
+
+    /// Whether Claude is currently thinking
+    pub thinking: bool,
+}
+
+impl Activity {
+    /// Update the summary with an event
+    pub fn record(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::ToolUse { name } => {
+                self.tool_calls += 1;
+                self.last_tool = Some(name.clone());
+                self.thinking = false;
+            }
+            StreamEvent::Thinking => self.thinking = true,
+            StreamEvent::Text(_) | StreamEvent::TextDelta(_) | StreamEvent::Result { .. } => self.thinking = false,
+            StreamEvent::Usage { .. } => {}
+        }
+    }
+}
+
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.thinking { "Thinking..." } else { "Working..." };
+        match (&self.last_tool, self.tool_calls) {
+            (Some(tool), 1) => write!(f, "{} 1 tool call ({})", state, tool),
+            (Some(tool), count) => write!(f, "{} {} tool calls (last: {})", state, count, tool),
+            (None, _) => write!(f, "{}", state),
+        }
+    }
+}
+
+/// Assistant text of a turn, put together from stream events as they arrive
+///
+/// Each event yields the text to show next, if any. Text received piece by
+/// piece is not shown again when its complete block follows, and blocks of
+/// separate messages are separated by a blank line. The result message is
+/// only shown if no text arrived before it.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::stream_json::{IncrementalText, StreamEvent};
+///
+/// let mut text = IncrementalText::default();
+/// assert_eq!(text.push(&StreamEvent::TextDelta("Hel".to_string())).as_deref(), Some("Hel"));
+/// assert_eq!(text.push(&StreamEvent::TextDelta("lo".to_string())).as_deref(), Some("lo"));
+/// assert_eq!(text.push(&StreamEvent::Text("Hello".to_string())), None);
+/// assert_eq!(text.push(&StreamEvent::Text("Done.".to_string())).as_deref(), Some("\n\nDone."));
+///
+/// assert_eq!(text.text(), "Hello\n\nDone.");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncrementalText {
+    text: String,
+    in_block: bool,
+}
+
+impl IncrementalText {
+    /// Add an event, returning the text to show for it
+    pub fn push(&mut self, event: &StreamEvent) -> Option<String> {
+        let chunk = match event {
+            StreamEvent::TextDelta(delta) => {
+                let starts_block = !self.in_block;
+                self.in_block = true;
+                self.separated(delta, starts_block)
+            }
+            StreamEvent::Text(_) if self.in_block => {
+                self.in_block = false;
+                return None;
+            }
+            StreamEvent::Text(block) => self.separated(block, true),
+            StreamEvent::Result { text, is_error: false } if self.text.is_empty() => text.clone(),
+            _ => return None,
+        };
+
+        if chunk.is_empty() {
+            return None;
+        }
+        self.text.push_str(&chunk);
+        Some(chunk)
+    }
+
+    /// All text shown so far
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// `text`, preceded by a blank line if it starts a block after earlier text
+    fn separated(&self, text: &str, starts_block: bool) -> String {
+        if starts_block && !self.text.is_empty() && !text.is_empty() {
+            format!("\n\n{}", text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assistant_message_with_several_blocks() {
+        let line = r#"{"type":"assistant","message":{"content":[
+            {"type":"thinking","thinking":"hmm"},
+            {"type":"text","text":"Let me check."},
+            {"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}},
+            {"type":"image"}
+        ]}}"#
+            .replace('\n', "");
+
+        assert_eq!(
+            parse_line(&line),
+            vec![
+                StreamEvent::Thinking,
+                StreamEvent::Text("Let me check.".to_string()),
+                StreamEvent::ToolUse { name: "Bash".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uninteresting_lines() {
+        assert!(parse_line("").is_empty());
+        assert!(parse_line("warning: something").is_empty());
+        assert!(parse_line(r#"{"type":"system","subtype":"init"}"#).is_empty());
+        assert!(parse_line(r#"{"type":"user","message":{"content":[{"type":"tool_result"}]}}"#).is_empty());
+    }
+
+    #[test]
+    fn test_error_result() {
+        let events = parse_line(r#"{"type":"result","subtype":"error_max_turns","is_error":true}"#);
+        assert_eq!(events[0], StreamEvent::Result { text: String::new(), is_error: true });
+        assert_eq!(events[1], StreamEvent::Usage { session_id: None, usage: Usage::default() });
+    }
+
+    #[test]
+    fn test_result_usage() {
+        let line = r#"{"type":"result","subtype":"success","is_error":false,"result":"Done.","session_id":"3f2a9c1e","total_cost_usd":0.0125,
+            "usage":{"input_tokens":12,"cache_creation_input_tokens":300,"cache_read_input_tokens":4000,"output_tokens":85}}"#
+            .replace('\n', "");
+
+        let usage = Usage {
+            input_tokens: 4312,
+            output_tokens: 85,
+            cache_read_tokens: 4000,
+            cache_write_tokens: 300,
+            cost_usd: Some(0.0125),
+        };
+        assert_eq!(parse_line(&line)[1], StreamEvent::Usage { session_id: Some("3f2a9c1e".to_string()), usage });
+    }
+
+    #[test]
+    fn test_text_deltas() {
+        let line = r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}}"#;
+        assert_eq!(parse_line(line), vec![StreamEvent::TextDelta("Hi".to_string())]);
+
+        let line = r#"{"type":"stream_event","event":{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{"}}}"#;
+        assert!(parse_line(line).is_empty());
+        assert!(parse_line(r#"{"type":"stream_event","event":{"type":"message_start"}}"#).is_empty());
+    }
+
+    #[test]
+    fn test_incremental_text_without_deltas() {
+        let mut text = IncrementalText::default();
+        assert_eq!(text.push(&StreamEvent::Text("Let me check.".to_string())).as_deref(), Some("Let me check."));
+        assert_eq!(text.push(&StreamEvent::ToolUse { name: "Read".to_string() }), None);
+        assert_eq!(text.push(&StreamEvent::Text("Found it.".to_string())).as_deref(), Some("\n\nFound it."));
+        assert_eq!(text.push(&StreamEvent::Result { text: "Found it.".to_string(), is_error: false }), None);
+        assert_eq!(text.text(), "Let me check.\n\nFound it.");
+    }
+
+    #[test]
+    fn test_incremental_text_falls_back_to_the_result() {
+        let mut text = IncrementalText::default();
+        assert_eq!(text.push(&StreamEvent::Result { text: "Done.".to_string(), is_error: false }).as_deref(), Some("Done."));
+
+        let mut text = IncrementalText::default();
+        assert_eq!(text.push(&StreamEvent::Result { text: "Overloaded".to_string(), is_error: true }), None);
+        assert_eq!(text.text(), "");
+    }
+
+    #[test]
+    fn test_activity_display() {
+        let mut activity = Activity::default();
+        assert_eq!(activity.to_string(), "Working...");
+
+        activity.record(&StreamEvent::Thinking);
+        assert_eq!(activity.to_string(), "Thinking...");
+
+        activity.record(&StreamEvent::ToolUse { name: "Read".to_string() });
+        assert_eq!(activity.to_string(), "Working... 1 tool call (Read)");
+    }
+}
@@ -0,0 +1,186 @@
+//! Conversation tee module
+//!
+//! `--tee <path>` mirrors the conversation to a file or named pipe while it
+//! happens: each prompt as it is sent, followed by the response as it
+//! streams in, unrendered. Following the file from a second terminal
+//! (`tail -f`, or `cat` on a named pipe) shows the session live, e.g. to a
+//! pairing partner, while it is also being recorded or saved.
+//!
+//! A file is appended to. Opening a named pipe waits until a reader opens
+//! it; if the reader goes away later, mirroring stops with a warning and the
+//! session carries on.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::pipeline::ResponsePipeline;
+//! use claude_dialog::tee::Tee;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let dir = tempfile::tempdir()?;
+//! let path = dir.path().join("session.log");
+//! let tee = Tee::open(&path)?;
+//!
+//! tee.prompt("Say hi");
+//! let mut pipeline = ResponsePipeline::new();
+//! pipeline.add_sink(tee.sink());
+//! pipeline.send("Hi");
+//! pipeline.send("!");
+//! pipeline.finish().await?;
+//!
+//! assert_eq!(std::fs::read_to_string(&path)?, "You> Say hi\nClaude>\nHi!\n\n");
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use crate::pipeline::ResponseSink;
+use crate::ui::UI;
+
+/// A file or named pipe the conversation is mirrored to
+///
+/// Clones write to the same file.
+#[derive(Debug, Clone)]
+pub struct Tee {
+    path: PathBuf,
+    shared: Arc<Shared>,
+}
+
+/// State shared by a tee and its sinks
+#[derive(Debug)]
+struct Shared {
+    file: Mutex<File>,
+    // Set after a failed write, which stops mirroring
+    failed: AtomicBool,
+    // Whether the last text written ended with a newline
+    at_line_start: AtomicBool,
+}
+
+impl Tee {
+    /// Open `path` for mirroring, creating it if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened for writing.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Cannot open {} for --tee", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            shared: Arc::new(Shared {
+                file: Mutex::new(file),
+                failed: AtomicBool::new(false),
+                at_line_start: AtomicBool::new(true),
+            }),
+        })
+    }
+
+    /// The file or named pipe mirrored to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Mirror a prompt being sent; the response follows through [`Tee::sink`]
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    /// You> How do I read a file?
+    /// Claude>
+    /// ```
+    pub fn prompt(&self, prompt: &str) {
+        self.write(&format!("You> {}\nClaude>\n", prompt));
+    }
+
+    /// A sink mirroring a response, ending it with a blank line
+    pub fn sink(&self) -> TeeSink {
+        TeeSink { tee: self.clone() }
+    }
+
+    /// Write text right away, stopping with a warning on the first failure
+    fn write(&self, text: &str) {
+        if text.is_empty() || self.shared.failed.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut file = self.shared.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(e) = file.write_all(text.as_bytes()).and_then(|()| file.flush()) {
+            self.shared.failed.store(true, Ordering::Relaxed);
+            UI::print_warning(&format!("Stopped mirroring to {}: {}", self.path.display(), e));
+            return;
+        }
+        self.shared.at_line_start.store(text.ends_with('\n'), Ordering::Relaxed);
+    }
+}
+
+/// Sink mirroring a response to a [`Tee`]
+#[derive(Debug)]
+pub struct TeeSink {
+    tee: Tee,
+}
+
+impl ResponseSink for TeeSink {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        self.tee.write(chunk);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let end = if self.tee.shared.at_line_start.load(Ordering::Relaxed) { "\n" } else { "\n\n" };
+        self.tee.write(end);
+        Ok(())
+    }
+}
+
+/// Whether `path` is a named pipe, whose opening waits for a reader
+#[cfg(unix)]
+pub fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    path.metadata().is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+/// Whether `path` is a named pipe, whose opening waits for a reader
+#[cfg(not(unix))]
+pub fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::ResponsePipeline;
+
+    #[tokio::test]
+    async fn test_response_ending_with_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tee.log");
+        std::fs::write(&path, "earlier\n").unwrap();
+
+        let tee = Tee::open(&path).unwrap();
+        tee.prompt("List two");
+        let mut pipeline = ResponsePipeline::new();
+        pipeline.add_sink(tee.sink());
+        pipeline.send("- a\n- b\n");
+        pipeline.finish().await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "earlier\nYou> List two\nClaude>\n- a\n- b\n\n");
+    }
+
+    #[test]
+    fn test_unwritable_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = Tee::open(&dir.path().join("missing/tee.log")).unwrap_err();
+        assert!(error.to_string().starts_with("Cannot open"));
+        assert!(!is_fifo(dir.path()));
+    }
+}
@@ -0,0 +1,140 @@
+//! Tips and release notes module
+//!
+//! Helps users discover features: the welcome banner shows one tip from an
+//! embedded list, a different one on every start, and `claude-dialog
+//! whatsnew` prints the changelog section of the installed version.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::tips;
+//!
+//! let tip = tips::tip(3);
+//! assert!(!tip.is_empty());
+//!
+//! let notes = tips::release_notes(env!("CARGO_PKG_VERSION")).unwrap();
+//! assert!(notes.starts_with("## "));
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One-line tips shown under the welcome banner
+pub const TIPS: &[&str] = &[
+    "Mention a file with @path to send it along, e.g. Why does @src/main.rs panic?",
+    "Send part of a file with a selector: @src/main.rs#120-200 or @src/main.rs#fn main",
+    "Globs include several files at once: @src/**/*.rs",
+    "/repo-map sends an outline of the repository's files and definitions",
+    "/attach <file> sends a whole file with your next prompt, no @mention needed",
+    "/copy puts the last answer on the clipboard; /paste <question> sends the clipboard after it",
+    "/edit or Ctrl+X Ctrl+E opens $EDITOR for writing a long prompt",
+    "config.toml files from older releases are upgraded for you, with a .bak copy kept whenever one is rewritten",
+    "/preview <prompt> shows exactly what will be sent before sending it",
+    "/find <text> searches everything said so far in this conversation",
+    "/search <text> numbers its matches; type a number to see that whole turn again",
+    "/last shows the most recent response again after tool output pushed it off the screen",
+    "/checkpoint <name> saves the conversation, /restore <name> goes back to it",
+    "/help lists every slash command; each one explains itself with --help, e.g. /find --help",
+    "/model <name> and /system <file> change the model or system prompt mid-conversation",
+    "--system-prompt prompts/ loads every .md file in the directory, in name order",
+    "Front matter such as model: opus at the top of a prompt file sets options for that prompt",
+    "--preset <name> or /preset <name> uses a system prompt from your prompt library; claude-dialog prompts list shows them",
+    "After /preset or /persona lists them, type a number to pick one",
+    "/clear forgets the conversation and starts a new one",
+    "--cwd <dir> runs Claude in another project; /cd <dir> switches projects mid-session",
+    "--mcp-config <file> lets Claude use the tools of MCP servers; /mcp lists them",
+    "--claude-arg passes an option claude-dialog does not know on to claude, e.g. --claude-arg --verbose",
+    "claude-dialog sessions list shows your past conversations; sessions show <id> prints one",
+    "--spellcheck catches typos and misspelled identifiers before a prompt is sent",
+    "A prompt that looks cut off asks before it is sent; --send-unfinished skips the question",
+    "--turn-metadata tells Claude the time, working directory and OS with every prompt",
+    "--suggest-follow-ups offers follow-up questions after each answer; type a number to ask one",
+    "--language en asks Claude to answer prompts in other languages in their own language",
+    "Ctrl+C stops a long answer and keeps the session; press it again to quit",
+    "Ctrl+R searches everything you typed before, also in earlier sessions",
+    "Alt+Enter starts a new line; or wrap a multi-line prompt in \"\"\" lines",
+    "/status shows the session ID, tokens and cost so far, and the last error",
+    "--show-usage prints the tokens and estimated cost of every turn; /stats sums them up",
+    "--timeout 300 stops a claude command that stalls instead of waiting forever",
+    "--dry-run prints the claude command line of each prompt instead of running it",
+    "/save writes the session to a Markdown file; --save-transcript does it on exit",
+    "Transcripts note under each prompt which system prompt and added context it was sent with",
+    "/rate 1-5 scores the last response; /status sums up the session's ratings",
+    "/note <text> annotates the last turn for you only; Claude never sees notes",
+    "/export hands the conversation over; continue it elsewhere with --from <file>",
+    "/export html writes the session as a web page to share with teammates",
+    "Define your own slash commands with --alias 'name=/command {{args}}'",
+    "claude-dialog -p \"question\" answers once and exits; pipe a prompt in with -p -",
+    "Giving a talk? claude-dialog present <transcript> replays a session without calling Claude",
+    "--record-cast demo.cast records the session for asciinema play and the web player",
+    "Pairing? --tee <fifo> mirrors the conversation so a second terminal can follow it live",
+    "Studying? --quiz hides each answer until you press Enter and times how long you thought",
+    "--batch prompts.yaml runs every prompt of a file and writes the answers to JSON",
+    "--batch prompts.yaml --repeat 5 shows how much the answers vary from run to run",
+    "Responses are rendered as Markdown; --raw prints them exactly as Claude wrote them",
+    "Code blocks are highlighted in their fence's language; --no-highlight turns that off",
+    "Sharing your screen? --mask-pii blacks out emails, phone numbers and keys in responses",
+    "--final-only hides tool activity and shows only Claude's final answer",
+    "Keep secrets out of globs and repository maps with a .claude-dialog-ignore file",
+    "--incognito writes nothing about the session to disk",
+    "Put your usual options in config.toml, and switch sets of them with --profile",
+    "--backend ollama --model llama3 runs the same session against a local model",
+    "--backend api --model claude-sonnet-4-5 needs only ANTHROPIC_API_KEY, not the claude CLI",
+    "Define personas with --persona draft=ollama:llama3 and switch between them with /persona",
+    "claude-dialog whatsnew lists the changes in this version",
+];
+
+/// The changelog, embedded at build time
+const CHANGELOG: &str = include_str!("../CHANGELOG.md");
+
+/// Tip number `index`, wrapping around the list
+pub fn tip(index: usize) -> &'static str {
+    TIPS[index % TIPS.len()]
+}
+
+/// A tip that changes from one start to the next
+pub fn rotating_tip() -> &'static str {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    tip(seconds as usize)
+}
+
+/// Changelog section of a version, including its `## version` heading
+///
+/// # Returns
+///
+/// `None` if the changelog has no section for `version`
+pub fn release_notes(version: &str) -> Option<&'static str> {
+    let heading = format!("\n## {}", version);
+    let start = CHANGELOG
+        .match_indices(&heading)
+        .map(|(index, _)| index + 1)
+        .find(|&index| {
+            let after = &CHANGELOG[index + heading.len() - 1..];
+            after.is_empty() || after.starts_with(['\n', ' ', '\r'])
+        })?;
+
+    let section = &CHANGELOG[start..];
+    let end = section[1..].find("\n## ").map_or(section.len(), |end| end + 1);
+    Some(section[..end].trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tip_wraps_around() {
+        assert_eq!(tip(0), TIPS[0]);
+        assert_eq!(tip(TIPS.len() + 1), TIPS[1]);
+    }
+
+    #[test]
+    fn test_release_notes_of_installed_version() {
+        let notes = release_notes(env!("CARGO_PKG_VERSION")).unwrap();
+        assert!(notes.starts_with(&format!("## {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(!notes.contains("\n## "));
+        assert_eq!(release_notes("0.0.0-missing"), None);
+    }
+}
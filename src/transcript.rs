@@ -0,0 +1,342 @@
+//! Conversation transcript module
+//!
+//! A transcript records every prompt and response of a session, with the
+//! time and model of each turn and what the prompt was sent with (see
+//! [`Provenance`]), and writes them to a Markdown file. Unlike
+//! the [`Conversation`](crate::conversation::Conversation), it keeps turns
+//! forgotten by `/clear` or rolled back by `/restore`, so the file shows the
+//! whole session as it happened.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::conversation::Turn;
+//! use claude_dialog::transcript::Transcript;
+//!
+//! let mut transcript = Transcript::new();
+//! transcript.record(Turn::new("What is a lifetime?"), Some("opus"));
+//! transcript.set_response("A lifetime is the scope a reference is valid for.");
+//!
+//! let markdown = transcript.to_markdown();
+//! assert!(markdown.contains("\n\nWhat is a lifetime?\n\n"));
+//! assert!(markdown.contains("## Claude (opus)\n\nA lifetime is"));
+//! ```
+
+use anyhow::{Result, Context};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::conversation::{MAX_RATING, Turn};
+
+/// A turn of the transcript with when and how it was sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    /// When the prompt was sent
+    pub time: DateTime<Local>,
+
+    /// Model the prompt was sent to, `None` for the CLI's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// The prompt, response and included files
+    pub turn: Turn,
+
+    /// What else the prompt was sent with
+    #[serde(default, skip_serializing_if = "Provenance::is_empty")]
+    pub provenance: Provenance,
+}
+
+/// What a prompt was sent with besides its text and included files, so a
+/// reader can reproduce the conditions of a turn
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::transcript::Provenance;
+///
+/// let provenance = Provenance {
+///     system_prompt: Some("review.md (--system-prompt)".to_string()),
+///     temperature: None,
+///     layers: vec!["repository map".to_string(), "turn metadata".to_string()],
+/// };
+/// assert_eq!(
+///     provenance.to_string(),
+///     "system prompt review.md (--system-prompt); repository map; turn metadata"
+/// );
+/// assert!(Provenance::default().is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Where the system prompt came from, `None` for Claude's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Temperature set by the system prompt's front matter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// What was added to the typed prompt before sending, in order, e.g.
+    /// a repository map or a language hint
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<String>,
+}
+
+impl Provenance {
+    /// Whether the prompt was sent as typed, with the default system prompt
+    pub fn is_empty(&self) -> bool {
+        self.system_prompt.is_none() && self.temperature.is_none() && self.layers.is_empty()
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(system_prompt) = &self.system_prompt {
+            parts.push(format!("system prompt {}", system_prompt));
+        }
+        if let Some(temperature) = self.temperature {
+            parts.push(format!("temperature {}", temperature));
+        }
+        parts.extend(self.layers.iter().cloned());
+        f.write_str(&parts.join("; "))
+    }
+}
+
+/// Every turn of a session, in the order they were sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    started: DateTime<Local>,
+    entries: Vec<TranscriptEntry>,
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transcript {
+    /// Start an empty transcript
+    pub fn new() -> Self {
+        Self {
+            started: Local::now(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// When the transcript was started
+    pub fn started(&self) -> DateTime<Local> {
+        self.started
+    }
+
+    /// A transcript of turns recorded elsewhere, e.g. read from a
+    /// conversation file
+    pub fn from_entries(started: DateTime<Local>, entries: Vec<TranscriptEntry>) -> Self {
+        Self { started, entries }
+    }
+
+    /// Recorded turns, oldest first
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    /// Number of recorded turns
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no turn has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record a turn sent to `model`
+    pub fn record(&mut self, turn: Turn, model: Option<&str>) {
+        self.entries.push(TranscriptEntry {
+            time: Local::now(),
+            model: model.map(String::from),
+            turn,
+            provenance: Provenance::default(),
+        });
+    }
+
+    /// Set what the most recent turn was sent with
+    pub fn set_provenance(&mut self, provenance: Provenance) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.provenance = provenance;
+        }
+    }
+
+    /// Set the response of the most recent turn, normalized by
+    /// [`Turn::set_response`]
+    pub fn set_response(&mut self, response: impl AsRef<str>) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.turn.set_response(response.as_ref());
+        }
+    }
+
+    /// Attach a note to the most recent turn
+    pub fn add_note(&mut self, note: impl Into<String>) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.turn.notes.push(note.into());
+        }
+    }
+
+    /// Rate the most recent turn
+    pub fn rate(&mut self, rating: u8) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.turn.rating = Some(rating);
+        }
+    }
+
+    /// Render the transcript as Markdown
+    ///
+    /// Prompts and responses are included as they are, since both are
+    /// usually Markdown already. The included files and what else the prompt
+    /// was sent with follow the prompt; ratings and notes follow the
+    /// response.
+    ///
+    /// # Output Format
+    ///
+    /// ```text
+    /// # Claude Dialog transcript
+    ///
+    /// Started 2025-01-01 12:00, 1 turn
+    ///
+    /// ## You (12:00:05, research)
+    ///
+    /// Review @src/main.rs
+    ///
+    /// *Files: src/main.rs (lines 1-120)*
+    ///
+    /// *Sent with: system prompt review.md (--system-prompt); turn metadata*
+    ///
+    /// ## Claude (opus)
+    ///
+    /// The error handling in `main` ...
+    ///
+    /// *Rated 4/5*
+    ///
+    /// > **Note:** used in PR #42
+    /// ```
+    ///
+    /// The label of the session a turn was merged from follows its time.
+    pub fn to_markdown(&self) -> String {
+        let turns = if self.entries.len() == 1 { "turn" } else { "turns" };
+        let mut markdown = format!(
+            "# Claude Dialog transcript\n\nStarted {}, {} {}\n",
+            self.started.format("%Y-%m-%d %H:%M"),
+            self.entries.len(),
+            turns
+        );
+
+        for entry in &self.entries {
+            markdown.push_str(&format!(
+                "\n## You ({})\n\n{}\n",
+                heading_details(entry),
+                entry.turn.prompt.trim_end()
+            ));
+
+            if !entry.turn.sources.is_empty() {
+                let files: Vec<String> = entry.turn.sources.iter().map(ToString::to_string).collect();
+                markdown.push_str(&format!("\n*Files: {}*\n", files.join(", ")));
+            }
+            if !entry.provenance.is_empty() {
+                markdown.push_str(&format!("\n*Sent with: {}*\n", entry.provenance));
+            }
+
+            match &entry.model {
+                Some(model) => markdown.push_str(&format!("\n## Claude ({})\n\n", model)),
+                None => markdown.push_str("\n## Claude\n\n"),
+            }
+            match &entry.turn.response {
+                Some(response) => markdown.push_str(response.trim_end()),
+                None => markdown.push_str("*No response*"),
+            }
+            markdown.push('\n');
+
+            if let Some(rating) = entry.turn.rating {
+                markdown.push_str(&format!("\n*Rated {}/{}*\n", rating, MAX_RATING));
+            }
+            for note in &entry.turn.notes {
+                markdown.push_str(&format!("\n> **Note:** {}\n", note.trim().replace('\n', "\n> ")));
+            }
+        }
+
+        markdown
+    }
+
+    /// Write the transcript to a Markdown file, replacing it if it exists
+    ///
+    /// Missing parent directories are created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        fs::write(path, self.to_markdown())
+            .with_context(|| format!("Failed to write transcript: {}", path.display()))
+    }
+}
+
+/// The time of a turn, followed by the label of the session it was merged
+/// from, e.g. `12:00:05, research`
+fn heading_details(entry: &TranscriptEntry) -> String {
+    let time = entry.time.format("%H:%M:%S").to_string();
+    match &entry.turn.origin {
+        Some(origin) => format!("{}, {}", time, origin),
+        None => time,
+    }
+}
+
+/// File name for a transcript saved without a path, e.g.
+/// `claude-dialog-20250101-120000.md`
+pub fn default_file_name(time: DateTime<Local>) -> PathBuf {
+    PathBuf::from(format!("claude-dialog-{}.md", time.format("%Y%m%d-%H%M%S")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::FileInclusion;
+
+    #[test]
+    fn test_markdown_rendering() {
+        let mut transcript = Transcript::new();
+        let turn = Turn::new("Review @notes.md\n").with_sources(vec![FileInclusion::whole("notes.md", 3)]);
+        transcript.record(turn, None);
+        transcript.set_provenance(Provenance {
+            system_prompt: Some("review.md (/system)".to_string()),
+            temperature: Some(0.2),
+            layers: vec!["language hint (German)".to_string()],
+        });
+        transcript.set_response("Looks good.\n");
+        transcript.rate(5);
+        transcript.add_note("checked by hand");
+        transcript.record(Turn::new("Thanks"), Some("haiku"));
+
+        let markdown = transcript.to_markdown();
+        assert!(markdown.starts_with("# Claude Dialog transcript\n\nStarted "));
+        assert!(markdown.contains(", 2 turns\n"));
+        assert!(markdown.contains("\n\nReview @notes.md\n\n*Files: notes.md (lines 1-3)*\n\n*Sent with: system prompt review.md (/system); temperature 0.2; language hint (German)*\n\n## Claude\n\nLooks good.\n\n*Rated 5/5*\n\n> **Note:** checked by hand\n\n## You"));
+        assert!(markdown.ends_with("\n\nThanks\n\n## Claude (haiku)\n\n*No response*\n"));
+    }
+
+    #[test]
+    fn test_save_creates_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transcripts/session.md");
+
+        let mut transcript = Transcript::new();
+        transcript.record(Turn::new("Hello"), None);
+        transcript.save(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), transcript.to_markdown());
+    }
+}
@@ -6,10 +6,11 @@
 //! # Examples
 //!
 //! ```
-//! use claude_dialog::ui::UI;
+//! use claude_dialog::ui::{Banner, BannerStyle, UI};
 //!
 //! // Print welcome message
-//! UI::print_welcome("Using default prompt", Some(&"claude-3-opus".to_string()));
+//! let banner = Banner::new().entry("Model", "claude-3-opus");
+//! UI::print_welcome(&banner, BannerStyle::Full);
 //!
 //! // Print prompts
 //! UI::print_user_prompt();
@@ -19,7 +20,180 @@
 //! UI::print_exit_message();
 //! ```
 
+use chrono::{DateTime, Local};
 use colored::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "dialog")]
+use crate::context::ContextFile;
+use crate::context::{FileInclusion, estimate_tokens};
+use crate::conversation::{MAX_RATING, SearchHit, Speaker, Turn};
+use crate::selection;
+use crate::spelling::Typo;
+use crate::unfinished::Unfinished;
+
+/// Set while standard output carries machine-readable output only, see
+/// [`UI::reserve_stdout`]
+static INFO_ON_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Longest search hit line shown by [`UI::print_search_hit`], in characters
+const SNIPPET_WIDTH: usize = 80;
+
+/// Characters of context kept before the first match of a shortened line
+const SNIPPET_CONTEXT: usize = 20;
+
+/// How much of the welcome banner to print
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum BannerStyle {
+    /// Title and the effective configuration
+    #[default]
+    Full,
+
+    /// Title only
+    Minimal,
+
+    /// No banner at all
+    Off,
+}
+
+/// Effective configuration shown in the welcome banner
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::ui::Banner;
+///
+/// let banner = Banner::new()
+///     .entry("Model", "sonnet (--model)")
+///     .entry("Allowed Tools", "Write, Edit");
+///
+/// assert_eq!(banner.get("Model"), Some("sonnet (--model)"));
+/// assert_eq!(banner.entries().len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Banner {
+    entries: Vec<(String, String)>,
+    tip: Option<String>,
+}
+
+impl Banner {
+    /// Create a banner without entries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry, keeping the order entries were added in
+    pub fn entry(mut self, label: impl Into<String>, value: impl Into<String>) -> Self {
+        self.entries.push((label.into(), value.into()));
+        self
+    }
+
+    /// Show a one-line tip under the banner
+    pub fn tip(mut self, tip: impl Into<String>) -> Self {
+        self.tip = Some(tip.into());
+        self
+    }
+
+    /// All entries as `(label, value)` pairs
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    /// Value of the entry with the given label
+    pub fn get(&self, label: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry == label)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The tip shown under the banner, if any
+    pub fn get_tip(&self) -> Option<&str> {
+        self.tip.as_deref()
+    }
+}
+
+/// Animation frames of the [`Spinner`]
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Single-line progress indicator drawn on standard error
+///
+/// Each call to [`Spinner::tick`] redraws the line with the next animation
+/// frame. Nothing is drawn when standard error is not a terminal, so piped
+/// output stays clean.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::ui::Spinner;
+///
+/// let mut spinner = Spinner::new();
+/// spinner.tick("Working... 1 tool call (Read)");
+/// spinner.clear();
+/// ```
+#[derive(Debug)]
+pub struct Spinner {
+    frame: usize,
+    enabled: bool,
+    drawn: bool,
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spinner {
+    /// Create a spinner, enabled only if standard error is a terminal
+    pub fn new() -> Self {
+        Self {
+            frame: 0,
+            enabled: io::stderr().is_terminal(),
+            drawn: false,
+        }
+    }
+
+    /// Draw the next frame with the given message
+    pub fn tick(&mut self, message: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        let frame = SPINNER_FRAMES[self.frame % SPINNER_FRAMES.len()];
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r\x1b[2K{} {}", frame.cyan(), message.dimmed());
+        let _ = stderr.flush();
+        self.frame += 1;
+        self.drawn = true;
+    }
+
+    /// Whether the spinner line is drawn
+    pub fn is_drawn(&self) -> bool {
+        self.drawn
+    }
+
+    /// Erase the spinner line
+    pub fn clear(&mut self) {
+        if self.drawn {
+            let mut stderr = io::stderr();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+            self.drawn = false;
+        }
+    }
+}
+
+impl Drop for Spinner {
+    // A turn cancelled with Ctrl+C drops its spinner without clearing it
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
 
 /// User interface handler for terminal output
 ///
@@ -29,16 +203,17 @@ use colored::*;
 /// # Color Scheme
 ///
 /// - Blue: Separators and Claude prompt
-/// - Yellow: Title and exit message
-/// - Green: System prompt info, model info, and user prompt
+/// - Yellow: Title, exit message, and warnings
+/// - Green: System prompt info, model info, user prompt, and informational messages
+/// - Red: Error messages
 ///
 /// # Examples
 ///
 /// ```
-/// use claude_dialog::ui::UI;
+/// use claude_dialog::ui::{Banner, BannerStyle, UI};
 ///
 /// // Display welcome screen
-/// UI::print_welcome("Custom system prompt loaded", None);
+/// UI::print_welcome(&Banner::new().entry("System Prompt", "custom.md"), BannerStyle::Full);
 ///
 /// // Show user prompt (typically followed by user input)
 /// UI::print_user_prompt();
@@ -46,50 +221,76 @@ use colored::*;
 pub struct UI;
 
 impl UI {
-    /// Print the welcome banner with system information
+    /// Print the welcome banner
     ///
-    /// Displays a formatted welcome message including the system prompt
-    /// status and optionally the model being used.
+    /// In [`BannerStyle::Full`] the effective configuration is shown as a
+    /// compact table with right-aligned labels, followed by the banner's tip
+    /// if it has one. [`BannerStyle::Minimal`]
+    /// prints only the title, and [`BannerStyle::Off`] prints nothing.
     ///
     /// # Arguments
     ///
-    /// * `system_prompt_info` - Description of the system prompt configuration
-    /// * `model` - Optional model name to display
+    /// * `banner` - The configuration entries to display
+    /// * `style` - How much of the banner to print
     ///
     /// # Output Format
     ///
     /// ```text
     /// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
     /// Claude Dialog Shell
-    /// System Prompt: [system_prompt_info]
-    /// Model: [model] (if provided)
+    /// System Prompt: review.md (--system-prompt)
+    ///         Model: claude-3-opus (--model)
     /// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
     /// 
     /// Type 'exit' or 'quit' to end the conversation
+    /// Tip: /find <text> searches everything said so far in this conversation
     /// ```
     ///
     /// # Examples
     ///
     /// ```
-    /// use claude_dialog::ui::UI;
+    /// use claude_dialog::ui::{Banner, BannerStyle, UI};
     ///
-    /// // With model specified
-    /// UI::print_welcome("Using custom prompt from file", Some(&"claude-3-opus".to_string()));
+    /// let banner = Banner::new()
+    ///     .entry("System Prompt", "Default")
+    ///     .entry("Model", "claude-3-opus (--model)");
     ///
-    /// // Without model
-    /// UI::print_welcome("Using default system prompt", None);
+    /// UI::print_welcome(&banner, BannerStyle::Full);
+    /// UI::print_welcome(&banner, BannerStyle::Minimal);
     /// ```
-    pub fn print_welcome(system_prompt_info: &str, model: Option<&String>) {
-        println!("{}", "━".repeat(60).blue());
-        println!("{}", "Claude Dialog Shell".yellow().bold());
-        println!("System Prompt: {}", system_prompt_info.green());
-        if let Some(model) = model {
-            println!("Model: {}", model.green());
+    pub fn print_welcome(banner: &Banner, style: BannerStyle) {
+        match style {
+            BannerStyle::Off => {}
+            BannerStyle::Minimal => {
+                println!(
+                    "{} {}",
+                    "Claude Dialog Shell".yellow().bold(),
+                    "- type 'exit' or 'quit' to end the conversation".dimmed()
+                );
+                println!();
+            }
+            BannerStyle::Full => {
+                let width = banner
+                    .entries()
+                    .iter()
+                    .map(|(label, _)| label.chars().count())
+                    .max()
+                    .unwrap_or(0);
+
+                println!("{}", "━".repeat(60).blue());
+                println!("{}", "Claude Dialog Shell".yellow().bold());
+                for (label, value) in banner.entries() {
+                    println!("{:>width$}: {}", label, value.green(), width = width);
+                }
+                println!("{}", "━".repeat(60).blue());
+                println!();
+                println!("Type 'exit' or 'quit' to end the conversation");
+                if let Some(tip) = banner.get_tip() {
+                    println!("{} {}", "Tip:".cyan(), tip.dimmed());
+                }
+                println!();
+            }
         }
-        println!("{}", "━".repeat(60).blue());
-        println!();
-        println!("Type 'exit' or 'quit' to end the conversation");
-        println!();
     }
     
     /// Print the user input prompt
@@ -112,7 +313,63 @@ impl UI {
     /// // User types their input here
     /// ```
     pub fn print_user_prompt() {
-        print!("{} ", "You>".green());
+        print!("{}", Self::user_prompt(false).1);
+    }
+    
+    /// Print the user input prompt of an incognito session
+    ///
+    /// # Output
+    ///
+    /// Prints "You (incognito)> " in green color, with "(incognito)" in
+    /// magenta, without a newline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_incognito_user_prompt();
+    /// ```
+    pub fn print_incognito_user_prompt() {
+        print!("{}", Self::user_prompt(true).1);
+    }
+    
+    /// The user input prompt, without and with colors
+    ///
+    /// The line editor needs the plain text to measure the prompt's width.
+    ///
+    /// # Output
+    ///
+    /// "You> " in green color, or "You (incognito)> " with "(incognito)" in
+    /// magenta for an incognito session.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// let (plain, _colored) = UI::user_prompt(true);
+    /// assert_eq!(plain, "You (incognito)> ");
+    /// ```
+    pub fn user_prompt(incognito: bool) -> (String, String) {
+        if incognito {
+            (
+                "You (incognito)> ".to_string(),
+                format!("{} {}{} ", "You".green(), "(incognito)".magenta(), ">".green()),
+            )
+        } else {
+            ("You> ".to_string(), format!("{} ", "You>".green()))
+        }
+    }
+    
+    /// The prompt for the following lines of a multi-line input, without
+    /// and with colors
+    ///
+    /// # Output
+    ///
+    /// "...  " dimmed, as wide as "You> ".
+    pub fn continuation_prompt() -> (String, String) {
+        ("...  ".to_string(), format!("{}  ", "...".dimmed()))
     }
     
     /// Print the Claude response prompt
@@ -136,6 +393,16 @@ impl UI {
         println!("{}", "Claude>".blue());
     }
     
+    /// Print the Claude response prompt with the number of the turn
+    ///
+    /// # Output
+    ///
+    /// Prints "Claude #3>" in blue color with a newline; `/show 3` shows
+    /// the response again.
+    pub fn print_numbered_claude_prompt(number: usize) {
+        println!("{}", format!("Claude #{}>", number).blue());
+    }
+    
     /// Print the exit message
     ///
     /// Displays a farewell message when the user exits the conversation.
@@ -155,6 +422,530 @@ impl UI {
     pub fn print_exit_message() {
         println!("{}", "Exiting conversation...".yellow());
     }
+    
+    /// A question asked at the prompt of
+    /// [`LineEditor::read_answer`](crate::line_editor::LineEditor::read_answer),
+    /// without and with colors
+    ///
+    /// # Output
+    ///
+    /// The question in yellow, followed by a space.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// assert_eq!(UI::question("Send this prompt? [y/N]").0, "Send this prompt? [y/N] ");
+    /// ```
+    pub fn question(text: &str) -> (String, String) {
+        (format!("{} ", text), format!("{} ", text.yellow()))
+    }
+
+    /// The offer to recover a session that did not exit cleanly, see
+    /// [`UI::question`]
+    ///
+    /// Sessions started today are shown with their time only.
+    ///
+    /// # Arguments
+    ///
+    /// * `started` - When the unsaved session started
+    /// * `turns` - Number of turns that can be recovered
+    ///
+    /// # Output
+    ///
+    /// "Recover unsaved session from 10:32 (3 turns)? [y/N] "
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    /// use chrono::Local;
+    ///
+    /// let (question, _) = UI::recovery_question(&Local::now(), 3);
+    /// assert!(question.ends_with("(3 turns)? [y/N] "));
+    /// ```
+    pub fn recovery_question(started: &DateTime<Local>, turns: usize) -> (String, String) {
+        let when = if started.date_naive() == Local::now().date_naive() {
+            started.format("%H:%M").to_string()
+        } else {
+            started.format("%Y-%m-%d %H:%M").to_string()
+        };
+        
+        Self::question(&format!("Recover unsaved session from {} ({} turns)? [y/N]", when, turns))
+    }
+    
+    /// Print an informational message
+    ///
+    /// Used to report the outcome of local slash commands. Goes to standard
+    /// error after [`UI::reserve_stdout`].
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to display
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_info("Checkpoint 'before-refactor' saved");
+    /// ```
+    pub fn print_info(message: &str) {
+        if INFO_ON_STDERR.load(Ordering::Relaxed) {
+            eprintln!("{}", message.green());
+        } else {
+            println!("{}", message.green());
+        }
+    }
+    
+    /// Send informational messages to standard error from now on, keeping
+    /// standard output for machine-readable output such as
+    /// `--output-format json`
+    pub fn reserve_stdout() {
+        INFO_ON_STDERR.store(true, Ordering::Relaxed);
+    }
+    
+    /// Print the token usage of a turn or session
+    ///
+    /// Dimmed, so it does not draw attention away from the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The usage to display
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_usage("Tokens: 1200 in, 300 out, $0.0120");
+    /// ```
+    pub fn print_usage(message: &str) {
+        println!("{}", message.dimmed());
+    }
+    
+    /// Print a command line, as shown by `--dry-run`
+    ///
+    /// Printed as it is, so it can be copied into a shell.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The command line
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_command_line("claude --continue -p 'Hello'");
+    /// ```
+    pub fn print_command_line(line: &str) {
+        println!("{}", line);
+    }
+    
+    /// Print a warning message
+    ///
+    /// Used for problems that do not prevent the conversation, such as an
+    /// outdated Claude CLI.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The warning to display
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_warning("claude CLI 0.2.9 is older than 1.0.0");
+    /// ```
+    pub fn print_warning(message: &str) {
+        eprintln!("{}", Self::warning(message));
+    }
+    
+    /// A warning as printed by [`UI::print_warning`]
+    pub fn warning(message: &str) -> String {
+        format!("{} {}", "Warning:".yellow().bold(), message.yellow())
+    }
+    
+    /// Print an error message
+    ///
+    /// Used for recoverable errors, such as malformed slash commands,
+    /// that should not end the conversation.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The error message to display
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_error("Unknown command: /foo");
+    /// ```
+    pub fn print_error(message: &str) {
+        eprintln!("{}", message.red());
+    }
+
+    /// Print a line found by `/find` with the search text highlighted
+    ///
+    /// Long lines are shortened around the first match.
+    ///
+    /// # Arguments
+    ///
+    /// * `hit` - The matching line
+    ///
+    /// # Output
+    ///
+    /// Prints "#3 Claude: ...the matching line..." with the turn number in
+    /// blue and every match in bold yellow.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::Conversation;
+    /// use claude_dialog::ui::UI;
+    ///
+    /// let mut conversation = Conversation::new();
+    /// conversation.push("Explain lifetimes");
+    ///
+    /// for hit in conversation.find("lifetime") {
+    ///     UI::print_search_hit(&hit);
+    /// }
+    /// ```
+    pub fn print_search_hit(hit: &SearchHit) {
+        println!("{} {}: {}", format!("#{}", hit.turn + 1).blue(), speaker(hit), highlighted(hit));
+    }
+
+    /// Print a numbered match found by `/search`, with the lines around it
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Number to type to show the turn, starting at 1
+    /// * `hit` - The matching line and its context
+    ///
+    /// # Output
+    ///
+    /// Prints the number, turn and speaker, then the context lines dimmed
+    /// and the matching line marked with `>` and its matches in bold yellow:
+    ///
+    /// ```text
+    ///   1. #3 Claude
+    ///      The index:
+    ///    > let x = v[10];
+    ///      is out of bounds.
+    /// ```
+    pub fn print_search_match(number: usize, hit: &SearchHit) {
+        println!("{:>3}. {} {}", number, format!("#{}", hit.turn + 1).blue(), speaker(hit));
+        for line in &hit.before {
+            println!("     {}", snippet(line, &[]).0.dimmed());
+        }
+        println!("   {} {}", ">".yellow(), highlighted(hit));
+        for line in &hit.after {
+            println!("     {}", snippet(line, &[]).0.dimmed());
+        }
+    }
+
+    /// Redisplay a complete turn of the conversation
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Turn number shown to the user, starting at 1
+    /// * `turn` - The turn to display
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::conversation::Turn;
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_turn(1, &Turn::new("Explain lifetimes"));
+    /// ```
+    pub fn print_turn(number: usize, turn: &Turn) {
+        println!("{}", format!("── Turn {} ──", number).blue());
+        println!("{} {}", "You>".green(), turn.prompt);
+        if let Some(response) = &turn.response {
+            println!("{}", "Claude>".blue());
+            println!("{}", response);
+        }
+        Self::print_sources(&turn.sources);
+        if let Some(rating) = turn.rating {
+            println!("{} {}/{}", "Rated:".magenta(), rating, MAX_RATING);
+        }
+        for note in &turn.notes {
+            println!("{} {}", "Note:".magenta(), note);
+        }
+    }
+
+    /// Print a prompt exactly as it would be sent to Claude
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The outgoing prompt after all preprocessing
+    ///
+    /// # Output
+    ///
+    /// ```text
+    /// ── Preview: ~42 tokens ──
+    /// Why does src/main.rs [1] panic?
+    ///
+    /// <file index="1" path="src/main.rs" lines="1-120">
+    /// ...
+    /// </file>
+    /// ── End of preview ──
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_preview("Explain lifetimes");
+    /// ```
+    pub fn print_preview(prompt: &str) {
+        println!("{}", format!("── Preview: ~{} tokens ──", estimate_tokens(prompt)).blue());
+        println!("{}", prompt);
+        println!("{}", "── End of preview ──".blue());
+    }
+
+    /// The confirmation asked after a preview, see [`UI::question`]
+    ///
+    /// # Output
+    ///
+    /// "Send this prompt? [y/N] "
+    pub fn send_question() -> (String, String) {
+        Self::question("Send this prompt? [y/N]")
+    }
+
+    /// Print the likely typos found by `--spellcheck`
+    ///
+    /// [`UI::typos_question`] then asks whether to send the prompt anyway.
+    ///
+    /// # Arguments
+    ///
+    /// * `typos` - The likely typos, in the order they appear
+    ///
+    /// # Output
+    ///
+    /// ```text
+    /// Possible typos: recieve (receive?), parse_arsg (parse_args?)
+    /// ```
+    ///
+    /// The typos are shown in bold red and suggestions in green.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::spelling::Typo;
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_typos(&[Typo { word: "recieve".to_string(), suggestion: Some("receive".to_string()) }]);
+    /// ```
+    pub fn print_typos(typos: &[Typo]) {
+        let typos: Vec<String> = typos
+            .iter()
+            .map(|typo| match &typo.suggestion {
+                Some(suggestion) => format!("{} ({}?)", typo.word.red().bold(), suggestion.green()),
+                None => typo.word.red().bold().to_string(),
+            })
+            .collect();
+        println!("{} {}", "Possible typos:".yellow(), typos.join(", "));
+    }
+
+    /// Whether to send a prompt with likely typos anyway, see
+    /// [`UI::question`]
+    ///
+    /// # Output
+    ///
+    /// "Send anyway, or edit the prompt? [s/E] "
+    pub fn typos_question() -> (String, String) {
+        Self::question("Send anyway, or edit the prompt? [s/E]")
+    }
+
+    /// Whether to send a prompt that looks unfinished, see [`UI::question`]
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` - Why the prompt looks unfinished
+    ///
+    /// # Output
+    ///
+    /// "This prompt ends with an unclosed (. Send to Claude anyway? [y/N] "
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::unfinished::Unfinished;
+    /// use claude_dialog::ui::UI;
+    ///
+    /// let (question, _) = UI::unfinished_question(&Unfinished::LoneSlash);
+    /// assert!(question.ends_with("Send to Claude anyway? [y/N] "));
+    /// ```
+    pub fn unfinished_question(reason: &Unfinished) -> (String, String) {
+        Self::question(&format!("This prompt {}. Send to Claude anyway? [y/N]", reason))
+    }
+
+    /// Print the included files when they exceed the context budget
+    ///
+    /// [`UI::drop_files_question`] then asks which ones to drop.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The included files in footnote order
+    /// * `budget` - The context budget in tokens
+    ///
+    /// # Output
+    ///
+    /// ```text
+    /// Included files are ~12000 tokens, over the context budget of 8000:
+    ///   [1] src/main.rs ~9000 tokens
+    ///   [2] notes.md ~3000 tokens
+    /// ```
+    #[cfg(feature = "dialog")]
+    pub fn print_context_files(files: &[ContextFile], budget: usize) {
+        let tokens: usize = files.iter().map(ContextFile::tokens).sum();
+        println!(
+            "{}",
+            format!("Included files are ~{} tokens, over the context budget of {}:", tokens, budget).yellow()
+        );
+        for (index, file) in files.iter().enumerate() {
+            println!("  [{}] {} ~{} tokens", index + 1, file.path().display(), file.tokens());
+        }
+    }
+
+    /// Which included files to drop, see [`UI::question`]
+    ///
+    /// # Output
+    ///
+    /// "Drop which files? (numbers, Enter to trim instead) "
+    pub fn drop_files_question() -> (String, String) {
+        Self::question("Drop which files? (numbers, Enter to trim instead)")
+    }
+    
+    /// Print the footer listing the files sent with a prompt
+    ///
+    /// Prints nothing when no file was included.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - The included files in footnote order
+    ///
+    /// # Output
+    ///
+    /// ```text
+    /// Sources:
+    ///   [1] src/main.rs (lines 1-120)
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::context::FileInclusion;
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_sources(&[FileInclusion::whole("src/main.rs", 120)]);
+    /// ```
+    pub fn print_sources(sources: &[FileInclusion]) {
+        if sources.is_empty() {
+            return;
+        }
+
+        println!("{}", "Sources:".dimmed());
+        for (index, source) in sources.iter().enumerate() {
+            println!("{}", format!("  [{}] {}", index + 1, source).dimmed());
+        }
+    }
+    
+    /// Print suggested follow-up questions, numbered for picking
+    ///
+    /// Prints nothing when there are no suggestions.
+    ///
+    /// # Output
+    ///
+    /// ```text
+    /// Follow-ups (type a number to ask):
+    ///   1. How do I test it?
+    ///   2. What about errors?
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::ui::UI;
+    ///
+    /// UI::print_suggestions(&["How do I test it?".to_string()]);
+    /// ```
+    pub fn print_suggestions(suggestions: &[String]) {
+        if suggestions.is_empty() {
+            return;
+        }
+        
+        println!("{}", Self::suggestions(suggestions));
+    }
+    
+    /// Suggested follow-up questions as printed by [`UI::print_suggestions`]
+    pub fn suggestions(suggestions: &[String]) -> String {
+        format!(
+            "{}\n{}",
+            "Follow-ups (type a number to ask):".dimmed(),
+            selection::numbered(suggestions).dimmed()
+        )
+    }
+}
+
+/// Who wrote a search hit, as shown before it
+fn speaker(hit: &SearchHit) -> &'static str {
+    match hit.speaker {
+        Speaker::User => "You",
+        Speaker::Claude => "Claude",
+    }
+}
+
+/// The line of a search hit, shortened, with its matches in bold yellow
+fn highlighted(hit: &SearchHit) -> String {
+    let (line, matches) = snippet(&hit.line, &hit.matches);
+
+    let mut highlighted = String::new();
+    let mut position = 0;
+    for range in matches {
+        highlighted.push_str(&line[position..range.start]);
+        highlighted.push_str(&line[range.clone()].yellow().bold().to_string());
+        position = range.end;
+    }
+    highlighted.push_str(&line[position..]);
+    highlighted
+}
+
+/// Shorten a line to [`SNIPPET_WIDTH`] characters around its first match
+///
+/// Returns the shortened line and the match ranges within it.
+fn snippet(line: &str, matches: &[Range<usize>]) -> (String, Vec<Range<usize>>) {
+    if line.chars().count() <= SNIPPET_WIDTH {
+        return (line.to_string(), matches.to_vec());
+    }
+
+    let first = matches.first().map_or(0, |range| range.start);
+    let start = line[..first]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map_or(0, |(index, _)| index);
+    let end = line[start..]
+        .char_indices()
+        .nth(SNIPPET_WIDTH)
+        .map_or(line.len(), |(index, _)| start + index);
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < line.len() { "…" } else { "" };
+    let shifted = matches
+        .iter()
+        .filter(|range| range.start >= start && range.end <= end)
+        .map(|range| range.start - start + prefix.len()..range.end - start + prefix.len())
+        .collect();
+
+    (format!("{}{}{}", prefix, &line[start..end], suffix), shifted)
 }
 
 #[cfg(test)]
@@ -167,6 +958,16 @@ mod tests {
         let _ui = UI;
     }
 
+    #[test]
+    fn test_banner_entries() {
+        let banner = Banner::new().entry("Model", "opus").entry("Journal", "off");
+        assert_eq!(banner.get("Journal"), Some("off"));
+        assert_eq!(banner.get("Profile"), None);
+        assert_eq!(banner.entries()[0], ("Model".to_string(), "opus".to_string()));
+        assert_eq!(banner.get_tip(), None);
+        assert_eq!(banner.tip("Try /find").get_tip(), Some("Try /find"));
+    }
+
     #[test]
     fn test_colored_output() {
         // Test that colored strings are created without panic
@@ -175,5 +976,24 @@ mod tests {
         let _title = "Claude Dialog Shell".yellow().bold();
         let _separator = "━".repeat(60).blue();
         let _exit_msg = "Exiting conversation...".yellow();
+        let _error_msg = "Unknown command".red();
+    }
+
+    #[test]
+    fn test_snippet_keeps_short_lines() {
+        let (line, matches) = snippet("find me, me", &[5..7, 9..11]);
+        assert_eq!(line, "find me, me");
+        assert_eq!(matches, vec![5..7, 9..11]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_snippet_shortens_around_first_match() {
+        let line = format!("{}needle{}needle", "a".repeat(100), "b".repeat(100));
+        let (shortened, matches) = snippet(&line, &[100..106, 206..212]);
+
+        let expected = format!("…{}needle{}…", "a".repeat(20), "b".repeat(54));
+        assert_eq!(shortened, expected);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(&shortened[matches[0].clone()], "needle");
+    }
+}
@@ -0,0 +1,196 @@
+//! Unfinished input module
+//!
+//! Enter sends a prompt, so a key pressed a moment too early sends half of
+//! one and spends a request on it. Before a prompt is sent, [`check`] looks
+//! for signs that it was not finished:
+//!
+//! - The last line leaves a quote, inline code span or bracket open, e.g.
+//!   `Why does parse(`.
+//! - A ```` ``` ```` code block is never closed.
+//! - The input is a lone `/`, the start of a slash command.
+//! - The input is shell input typed into the wrong window, such as
+//!   `git status` or `cd ..`, or ends with a shell operator like `|`, `&&`
+//!   or a `\` line continuation.
+//!
+//! Such a prompt is sent only once the user confirms it; otherwise it is
+//! put back at the input prompt to finish. `--send-unfinished`, or
+//! `confirm_unfinished = false` in the configuration file, sends without
+//! asking.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::unfinished::{check, Unfinished};
+//!
+//! assert_eq!(check("Why does parse("), Some(Unfinished::Open('(')));
+//! assert_eq!(check("git status"), Some(Unfinished::ShellCommand("git status".to_string())));
+//! assert_eq!(check("Why does parse() fail?"), None);
+//! ```
+
+use std::fmt;
+
+/// Operators that leave a shell command unfinished when they end it
+const SHELL_OPERATORS: &[&str] = &["|", "||", "&&", "\\", ">", ">>"];
+
+/// Commands typed into a terminal, or an editor, far more often than asked
+/// about on their own
+const SHELL_COMMANDS: &[&str] = &["ls", "ll", "cd", "pwd", "clear", "cls", "git", ":q", ":w", ":wq"];
+
+/// Most words of input taken for a shell command
+const SHELL_COMMAND_WORDS: usize = 3;
+
+/// Why a prompt looks unfinished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unfinished {
+    /// The last line leaves this quote, backtick or bracket open
+    Open(char),
+
+    /// A ```` ``` ```` code block is not closed
+    OpenCodeBlock,
+
+    /// The input is a lone `/`
+    LoneSlash,
+
+    /// The input ends with this shell operator
+    ShellOperator(String),
+
+    /// The input is this shell command
+    ShellCommand(String),
+}
+
+impl fmt::Display for Unfinished {
+    /// Why the prompt looks unfinished, to follow "it" in a sentence
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unfinished::Open(c) => write!(f, "ends with an unclosed {}", c),
+            Unfinished::OpenCodeBlock => write!(f, "ends inside a ``` code block"),
+            Unfinished::LoneSlash => write!(f, "is a lone /"),
+            Unfinished::ShellOperator(operator) => write!(f, "ends with the shell operator {}", operator),
+            Unfinished::ShellCommand(command) => write!(f, "looks like a shell command: {}", command),
+        }
+    }
+}
+
+/// Look for signs that a prompt was sent before it was finished
+///
+/// # Returns
+///
+/// The first sign found, or `None` if the prompt looks finished
+pub fn check(input: &str) -> Option<Unfinished> {
+    let input = input.trim_end();
+    if input == "/" {
+        return Some(Unfinished::LoneSlash);
+    }
+    if let Some(sign) = shell_input(input) {
+        return Some(sign);
+    }
+
+    // Quotes and brackets are matched through the whole input, outside code
+    // blocks, but only those left open on the last line count: one
+    // forgotten early in a long prompt says nothing about its end
+    let last_line = input.rfind('\n').map_or(0, |newline| newline + 1);
+    let mut open: Vec<(char, usize)> = Vec::new();
+    let mut in_code_block = false;
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block {
+            scan(line, offset, &mut open);
+        }
+        offset += line.len();
+    }
+
+    if in_code_block {
+        return Some(Unfinished::OpenCodeBlock);
+    }
+    open.last()
+        .filter(|(_, at)| *at >= last_line)
+        .map(|(c, _)| Unfinished::Open(*c))
+}
+
+/// Track the quotes and brackets a line opens and closes
+///
+/// `open` holds the delimiters still open with their byte offsets in the
+/// input; the line starts at `offset`. Inside quotes and inline code,
+/// nothing but the closing quote counts.
+fn scan(line: &str, offset: usize, open: &mut Vec<(char, usize)>) {
+    let mut previous = None;
+    for (index, c) in line.char_indices() {
+        match open.last() {
+            Some(&(quote @ ('"' | '`'), _)) => {
+                if c == quote {
+                    open.pop();
+                }
+            }
+            _ => match c {
+                '"' | '`' | '[' | '{' => open.push((c, offset + index)),
+                // Not the frown of :( or ;(
+                '(' if !matches!(previous, Some(':' | ';')) => open.push((c, offset + index)),
+                ')' | ']' | '}' if open.last().is_some_and(|&(opener, _)| closer(opener) == c) => {
+                    open.pop();
+                }
+                _ => {}
+            },
+        }
+        previous = Some(c);
+    }
+}
+
+/// The bracket closing `opener`
+fn closer(opener: char) -> char {
+    match opener {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        other => other,
+    }
+}
+
+/// Input that looks meant for a shell rather than Claude
+fn shell_input(input: &str) -> Option<Unfinished> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let (&first, &last) = (words.first()?, words.last()?);
+
+    if words.len() > 1 && SHELL_OPERATORS.contains(&last) {
+        return Some(Unfinished::ShellOperator(last.to_string()));
+    }
+    if !input.contains('\n') && words.len() <= SHELL_COMMAND_WORDS && SHELL_COMMANDS.contains(&first) && !input.ends_with('?') {
+        return Some(Unfinished::ShellCommand(input.trim().to_string()));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_quotes_and_brackets_on_the_last_line() {
+        assert_eq!(check("Rename \"parse_args"), Some(Unfinished::Open('"')));
+        assert_eq!(check("What does `Vec::with_capacity"), Some(Unfinished::Open('`')));
+        assert_eq!(check("Fix {\n  \"a\": [1, 2"), Some(Unfinished::Open('[')));
+        assert_eq!(check("Explain (briefly) the \"(\" in `f(`"), None);
+        assert_eq!(check("That failed :(\n"), None);
+        assert_eq!(check("Review this (first\ndraft) please"), None);
+        assert_eq!(check("I forgot to close this (\n\nWhat is a trait?"), None);
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        assert_eq!(check("Why does this fail?\n```rust\nfn main() {"), Some(Unfinished::OpenCodeBlock));
+        assert_eq!(check("Why does this fail?\n```rust\nfn main() {\n```"), None);
+    }
+
+    #[test]
+    fn test_shell_input() {
+        assert_eq!(check("/"), Some(Unfinished::LoneSlash));
+        assert_eq!(check("cat notes.txt |"), Some(Unfinished::ShellOperator("|".to_string())));
+        assert_eq!(check("cargo build &&"), Some(Unfinished::ShellOperator("&&".to_string())));
+        assert_eq!(check(":wq"), Some(Unfinished::ShellCommand(":wq".to_string())));
+        assert_eq!(check("cd .."), Some(Unfinished::ShellCommand("cd ..".to_string())));
+        assert_eq!(check("git rebase?"), None);
+        assert_eq!(check("git rebase or merge for this branch"), None);
+        assert_eq!(check("|"), None);
+    }
+}
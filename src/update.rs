@@ -0,0 +1,349 @@
+//! Binary self-update module
+//!
+//! Most users install `claude-dialog` from the prebuilt release binaries
+//! rather than through cargo, so the binary can update itself: the latest
+//! GitHub release is looked up, the asset for the current platform is
+//! downloaded and staged next to the running executable, the SHA-256
+//! checksum of the staged file is verified against the `.sha256` file
+//! published next to it, and the running executable is replaced.
+//!
+//! Release assets are named `claude-dialog-<arch>-<os>` (with `.exe` on
+//! Windows), e.g. `claude-dialog-x86_64-linux` and
+//! `claude-dialog-x86_64-linux.sha256`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use claude_dialog::update::{self, UpdateOutcome};
+//!
+//! match update::self_update(true).unwrap() {
+//!     UpdateOutcome::UpToDate(version) => println!("Up to date ({})", version),
+//!     UpdateOutcome::Available { latest, .. } => println!("{} is available", latest),
+//!     UpdateOutcome::Updated { to, .. } => println!("Updated to {}", to),
+//! }
+//! ```
+
+use anyhow::{Result, Context, anyhow, bail};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use tempfile::{Builder, NamedTempFile};
+
+/// GitHub API endpoint describing the latest release
+pub const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/katsuhirohonda/claude-dialog/releases/latest";
+
+/// Largest release binary that will be downloaded
+const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// A published GitHub release
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    /// Git tag of the release, e.g. `v0.2.0`
+    pub tag_name: String,
+
+    /// Files attached to the release
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// A file attached to a GitHub release
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    /// File name of the asset
+    pub name: String,
+
+    /// URL the asset can be downloaded from
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// The version this release was tagged with
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tag is not a semantic version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use claude_dialog::update::Release;
+    ///
+    /// let release = Release { tag_name: "v0.2.0".to_string(), assets: vec![] };
+    /// assert_eq!(release.version().unwrap().to_string(), "0.2.0");
+    /// ```
+    pub fn version(&self) -> Result<Version> {
+        let tag = self.tag_name.trim_start_matches('v');
+        Version::parse(tag)
+            .with_context(|| format!("Release tag '{}' is not a version number", self.tag_name))
+    }
+
+    /// Find an asset by file name
+    pub fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// Result of [`self_update`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// The running binary is already the latest release
+    UpToDate(Version),
+
+    /// A newer release exists but was not installed
+    Available {
+        /// Version of the running binary
+        current: Version,
+        /// Version of the latest release
+        latest: Version,
+    },
+
+    /// The running binary was replaced by a newer release
+    Updated {
+        /// Version that was replaced
+        from: Version,
+        /// Version that was installed
+        to: Version,
+    },
+}
+
+/// Version of the running binary
+pub fn current_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).expect("package version is valid semver")
+}
+
+/// Name of the release asset for the current platform
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::update::platform_asset_name;
+///
+/// assert!(platform_asset_name().starts_with("claude-dialog-"));
+/// ```
+pub fn platform_asset_name() -> String {
+    format!(
+        "claude-dialog-{}-{}{}",
+        env::consts::ARCH,
+        env::consts::OS,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+/// Check that `data` matches the checksum published for `asset_name`
+///
+/// `checksums` is the content of a checksum file in `sha256sum` format. A
+/// file holding only a bare digest is accepted as well.
+///
+/// # Errors
+///
+/// Returns an error if no checksum for the asset is listed or the digest
+/// does not match.
+///
+/// # Examples
+///
+/// ```
+/// use claude_dialog::update::verify_checksum;
+///
+/// let checksums = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  greeting.txt\n";
+/// assert!(verify_checksum(b"hello", checksums, "greeting.txt").is_ok());
+/// assert!(verify_checksum(b"hullo", checksums, "greeting.txt").is_err());
+/// ```
+pub fn verify_checksum(data: &[u8], checksums: &str, asset_name: &str) -> Result<()> {
+    let expected = checksums
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let digest = fields.next()?;
+            match fields.next() {
+                // `sha256sum` marks binary mode with a leading `*`
+                Some(name) if name.trim_start_matches('*') != asset_name => None,
+                _ => Some(digest),
+            }
+        })
+        .next()
+        .ok_or_else(|| anyhow!("No checksum listed for {}", asset_name))?;
+
+    let actual: String = Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("Checksum mismatch for {}: expected {}, got {}", asset_name, expected, actual);
+    }
+    Ok(())
+}
+
+/// Look up the latest release
+///
+/// # Errors
+///
+/// Returns an error if the request fails or the response is not a release.
+pub fn fetch_latest_release(url: &str) -> Result<Release> {
+    let response = request(url)?;
+    response
+        .into_json()
+        .with_context(|| format!("Invalid release information from {}", url))
+}
+
+/// Update the running binary to the latest release
+///
+/// # Arguments
+///
+/// * `check_only` - Only report whether an update is available
+///
+/// # Errors
+///
+/// Returns an error if the release cannot be fetched, has no binary for this
+/// platform, fails checksum verification or cannot replace the executable.
+pub fn self_update(check_only: bool) -> Result<UpdateOutcome> {
+    let current = current_version();
+    let release = fetch_latest_release(LATEST_RELEASE_URL)?;
+    let latest = release.version()?;
+
+    if latest <= current {
+        return Ok(UpdateOutcome::UpToDate(current));
+    }
+    if check_only {
+        return Ok(UpdateOutcome::Available { current, latest });
+    }
+
+    let asset_name = platform_asset_name();
+    let binary = release
+        .asset(&asset_name)
+        .ok_or_else(|| anyhow!("Release {} has no binary for this platform ({})", release.tag_name, asset_name))?;
+    let checksum_name = format!("{}.sha256", asset_name);
+    let checksum = release
+        .asset(&checksum_name)
+        .ok_or_else(|| anyhow!("Release {} has no checksum file {}", release.tag_name, checksum_name))?;
+
+    let checksums = String::from_utf8(download(&checksum.browser_download_url)?)
+        .with_context(|| format!("Checksum file {} is not text", checksum_name))?;
+    let data = download(&binary.browser_download_url)?;
+
+    replace_executable(&data, &checksums, &asset_name)?;
+    Ok(UpdateOutcome::Updated { from: current, to: latest })
+}
+
+/// Send a GET request with the headers GitHub expects
+fn request(url: &str) -> Result<ureq::Response> {
+    ureq::get(url)
+        .set("User-Agent", concat!("claude-dialog/", env!("CARGO_PKG_VERSION")))
+        .set("Accept", "application/vnd.github+json, application/octet-stream")
+        .call()
+        .with_context(|| format!("Request to {} failed", url))
+}
+
+/// Download a release asset into memory
+fn download(url: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    request(url)?
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut data)
+        .with_context(|| format!("Failed to download {}", url))?;
+
+    if data.len() as u64 > MAX_DOWNLOAD_BYTES {
+        bail!("Download from {} exceeds {} bytes", url, MAX_DOWNLOAD_BYTES);
+    }
+    Ok(data)
+}
+
+/// Replace the running executable with `data` once the staged copy
+/// matches its checksum
+fn replace_executable(data: &[u8], checksums: &str, asset_name: &str) -> Result<()> {
+    let exe = env::current_exe().context("Failed to locate the current executable")?;
+    let dir = exe
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", exe.display()))?;
+    let staged = stage(data, dir, checksums, asset_name)?;
+
+    self_replace::self_replace(staged.path()).context("Failed to replace the current executable")
+}
+
+/// Write `data` to a new file in `dir` and verify its checksum
+///
+/// The file is created exclusively, only accessible to the user, and the
+/// checksum is verified on what is read back from it, so that what
+/// replaces the executable is what was verified. The file is removed when
+/// the returned handle is dropped.
+fn stage(data: &[u8], dir: &Path, checksums: &str, asset_name: &str) -> Result<NamedTempFile> {
+    let mut staged = Builder::new()
+        .prefix(".claude-dialog-update-")
+        .tempfile_in(dir)
+        .with_context(|| format!("Failed to stage update in {}", dir.display()))?;
+    staged
+        .write_all(data)
+        .and_then(|_| staged.as_file().sync_all())
+        .with_context(|| format!("Failed to stage update at {}", staged.path().display()))?;
+
+    let mut persisted = Vec::with_capacity(data.len());
+    staged
+        .seek(SeekFrom::Start(0))
+        .and_then(|_| staged.read_to_end(&mut persisted))
+        .with_context(|| format!("Failed to read staged update at {}", staged.path().display()))?;
+    verify_checksum(&persisted, checksums, asset_name)?;
+
+    Ok(staged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn test_checksum_file_formats() {
+        let listing = format!("{}  other\n{} *greeting.txt\n", "0".repeat(64), HELLO_SHA256);
+        assert!(verify_checksum(b"hello", &listing, "greeting.txt").is_ok());
+        assert!(verify_checksum(b"hello", &format!("{}\n", HELLO_SHA256.to_uppercase()), "x").is_ok());
+
+        let err = verify_checksum(b"hello", &listing, "missing").unwrap_err();
+        assert_eq!(err.to_string(), "No checksum listed for missing");
+    }
+
+    #[test]
+    fn test_staged_update_is_verified_and_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let staged = stage(b"hello", dir.path(), HELLO_SHA256, "greeting").unwrap();
+        assert_eq!(std::fs::read(staged.path()).unwrap(), b"hello");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(staged.path().metadata().unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        drop(staged);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let err = stage(b"tampered", dir.path(), HELLO_SHA256, "greeting").unwrap_err();
+        assert!(err.to_string().contains("Checksum"), "{}", err);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_release_from_github_json() {
+        let json = r#"{
+            "tag_name": "v1.2.3",
+            "name": "1.2.3",
+            "assets": [
+                {"name": "claude-dialog-x86_64-linux", "browser_download_url": "https://example.com/bin", "size": 1}
+            ]
+        }"#;
+        let release: Release = serde_json::from_str(json).unwrap();
+
+        assert_eq!(release.version().unwrap(), Version::new(1, 2, 3));
+        assert!(release.asset("claude-dialog-x86_64-linux").is_some());
+        assert!(release.asset("claude-dialog-aarch64-macos").is_none());
+    }
+
+    #[test]
+    fn test_invalid_tag() {
+        let release = Release { tag_name: "nightly".to_string(), assets: vec![] };
+        assert!(release.version().is_err());
+    }
+}
@@ -0,0 +1,463 @@
+//! Transcript viewer module
+//!
+//! `claude-dialog view <file>` opens a saved conversation in a read-only
+//! pager, to read or search it without starting a Claude session. The file
+//! is a Markdown transcript written by `--save-transcript` or `/save`, or a
+//! conversation written by `/export`; the ID of a saved session (see
+//! [`crate::sessions`]) opens that session.
+//!
+//! # Controls
+//!
+//! ↑ and ↓ (or `k` and `j`) scroll a line, Space and PageDown (or `b` and
+//! PageUp) a page, Home and End (or `g` and `G`) go to the start and end.
+//! `/` searches for text, ignoring case, and `n` and `N` go to the next
+//! and previous line containing it. `q` or Esc closes the viewer. When
+//! standard output is not a terminal, the conversation is printed as it is.
+//!
+//! # Examples
+//!
+//! ```
+//! use claude_dialog::conversation::Conversation;
+//! use claude_dialog::viewer::{self, Pager};
+//!
+//! let mut conversation = Conversation::new();
+//! conversation.push("What is a lifetime?");
+//! conversation.set_response("How long a reference is valid.");
+//!
+//! let mut pager = Pager::new(viewer::lines(conversation.turns()), 80, 1);
+//! assert_eq!(pager.rows()[0], "You");
+//! assert!(pager.search("REFERENCE"));
+//! assert_eq!(pager.rows()[0], "How long a reference is valid.");
+//! ```
+
+use anyhow::{Context, Result};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, IsTerminal, Write};
+use unicode_width::UnicodeWidthChar;
+use crate::conversation::Turn;
+
+/// Spaces a tab is shown as
+const TAB: &str = "    ";
+
+/// Headings of the prompts and responses in [`lines`]
+const HEADINGS: &[&str] = &["You", "Claude"];
+
+/// The lines showing a conversation in the viewer
+///
+/// Each prompt and response is put under a `You` or `Claude` heading, with
+/// the time of the prompt, the session it was merged from, its rating and
+/// its notes where known.
+pub fn lines(turns: &[Turn]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for turn in turns {
+        let mut details = Vec::new();
+        if let Some(time) = turn.time {
+            details.push(time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+        }
+        if let Some(origin) = &turn.origin {
+            details.push(origin.clone());
+        }
+        lines.push(if details.is_empty() {
+            HEADINGS[0].to_string()
+        } else {
+            format!("{} ({})", HEADINGS[0], details.join(", "))
+        });
+        lines.extend(turn.prompt.trim_end().lines().map(String::from));
+        lines.push(String::new());
+
+        lines.push(HEADINGS[1].to_string());
+        match &turn.response {
+            Some(response) => lines.extend(response.trim_end().lines().map(String::from)),
+            None => lines.push("No response".to_string()),
+        }
+        if let Some(rating) = turn.rating {
+            lines.push(format!("Rated {}/{}", rating, crate::conversation::MAX_RATING));
+        }
+        lines.extend(turn.notes.iter().map(|note| format!("Note: {}", note)));
+        lines.push(String::new());
+    }
+    lines.pop();
+    lines
+}
+
+/// A read-only view of lines wrapped to the width of a screen
+#[derive(Debug, Clone)]
+pub struct Pager {
+    lines: Vec<String>,
+    // Wrapped rows with the index of the line they belong to
+    rows: Vec<(usize, String)>,
+    height: usize,
+    top: usize,
+    query: Option<String>,
+    // The line last found by a search, with the top row it left
+    found: Option<(usize, usize)>,
+}
+
+impl Pager {
+    /// Show `lines` on a screen of `width` columns and `height` rows of text
+    pub fn new(lines: Vec<String>, width: usize, height: usize) -> Self {
+        let mut pager = Self {
+            lines,
+            rows: Vec::new(),
+            height: height.max(1),
+            top: 0,
+            query: None,
+            found: None,
+        };
+        pager.rows = pager.wrap(width);
+        pager
+    }
+
+    /// The rows on screen, from the top
+    pub fn rows(&self) -> Vec<&str> {
+        self.rows
+            .iter()
+            .skip(self.top)
+            .take(self.height)
+            .map(|(_, row)| row.as_str())
+            .collect()
+    }
+
+    /// Index of the first row on screen
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// The text last searched for
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Scroll by `delta` rows, down when positive, without going past the
+    /// start or end
+    pub fn scroll(&mut self, delta: isize) {
+        self.top = self.top.saturating_add_signed(delta).min(self.last_top());
+    }
+
+    /// Scroll by a screen, down when `down`
+    pub fn page(&mut self, down: bool) {
+        let height = self.height as isize;
+        self.scroll(if down { height } else { -height });
+    }
+
+    /// Go to the start
+    pub fn home(&mut self) {
+        self.top = 0;
+    }
+
+    /// Go to the end
+    pub fn end(&mut self) {
+        self.top = self.last_top();
+    }
+
+    /// Fit the rows to a screen of another size, keeping the line at the top
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let line = self.rows.get(self.top).map_or(0, |&(line, _)| line);
+        self.rows = self.wrap(width);
+        self.height = height.max(1);
+        self.top = self.first_row(line).min(self.last_top());
+    }
+
+    /// Search for `query`, ignoring case, from the line at the top
+    ///
+    /// Returns whether a line contains it; the first one is brought to the
+    /// top. An empty query repeats the last search.
+    pub fn search(&mut self, query: &str) -> bool {
+        if !query.is_empty() {
+            self.query = Some(query.to_lowercase());
+        }
+        let from = self.rows.get(self.top).map_or(0, |&(line, _)| line);
+        self.find(from, true)
+    }
+
+    /// Go to the next line containing the text searched for, or the
+    /// previous one unless `forward`
+    ///
+    /// Returns whether there is one.
+    pub fn next_match(&mut self, forward: bool) -> bool {
+        // Until scrolled away, from the match rather than the top row, which
+        // differs for matches on the last screen
+        let line = match self.found {
+            Some((line, top)) if top == self.top => line,
+            _ => self.rows.get(self.top).map_or(0, |&(line, _)| line),
+        };
+        if forward {
+            self.find(line + 1, true)
+        } else {
+            line > 0 && self.find(line - 1, false)
+        }
+    }
+
+    /// The status line: the rows shown and the keys
+    pub fn status(&self) -> String {
+        let last = (self.top + self.height).min(self.rows.len());
+        let percent = if self.rows.is_empty() { 100 } else { last * 100 / self.rows.len() };
+        format!(
+            "rows {}-{} of {} ({}%)  ↑↓ scroll  / search  n/N next/previous  q quit",
+            (self.top + 1).min(last),
+            last,
+            self.rows.len(),
+            percent
+        )
+    }
+
+    /// Bring the first line containing the query at or after `from` (or
+    /// at or before it unless `forward`) to the top
+    fn find(&mut self, from: usize, forward: bool) -> bool {
+        let Some(query) = &self.query else {
+            return false;
+        };
+        let matches = |index: &usize| self.lines[*index].to_lowercase().contains(query.as_str());
+        let found = if forward {
+            (from..self.lines.len()).find(matches)
+        } else {
+            (0..=from.min(self.lines.len().saturating_sub(1))).rev().find(matches)
+        };
+        let Some(line) = found else {
+            return false;
+        };
+        self.top = self.first_row(line).min(self.last_top());
+        self.found = Some((line, self.top));
+        true
+    }
+
+    /// Index of the first row of `line`
+    fn first_row(&self, line: usize) -> usize {
+        self.rows.iter().position(|&(index, _)| index >= line).unwrap_or(self.rows.len())
+    }
+
+    /// The top row when the end is on screen
+    fn last_top(&self) -> usize {
+        self.rows.len().saturating_sub(self.height)
+    }
+
+    /// The lines cut into rows of at most `width` columns
+    fn wrap(&self, width: usize) -> Vec<(usize, String)> {
+        let width = width.max(1);
+        let mut rows = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            let mut row = String::new();
+            let mut columns = 0;
+            for c in line.replace('\t', TAB).chars() {
+                let c_width = c.width().unwrap_or(0);
+                if columns + c_width > width && !row.is_empty() {
+                    rows.push((index, std::mem::take(&mut row)));
+                    columns = 0;
+                }
+                row.push(c);
+                columns += c_width;
+            }
+            rows.push((index, row));
+        }
+        rows
+    }
+}
+
+/// Show `lines` in the pager until it is closed
+///
+/// When standard output is not a terminal, the lines are printed instead.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be set up, read from or written
+/// to.
+pub fn view(lines: Vec<String>) -> Result<()> {
+    let mut stdout = io::stdout();
+    if !stdout.is_terminal() {
+        for line in &lines {
+            writeln!(stdout, "{}", line)?;
+        }
+        return Ok(());
+    }
+
+    let (width, height) = terminal::size().context("Failed to read the terminal size")?;
+    // The last row is the status line
+    let mut pager = Pager::new(lines, width as usize, height.saturating_sub(1) as usize);
+    let _screen = Screen::enter()?;
+    let mut search: Option<String> = None;
+    let mut message = None;
+    loop {
+        draw(&mut stdout, &pager, search.as_deref(), message.take())?;
+        let key = match event::read().context("Failed to read a key")? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            Event::Resize(width, height) => {
+                pager.resize(width as usize, height.saturating_sub(1) as usize);
+                continue;
+            }
+            _ => continue,
+        };
+        if let Some(query) = &mut search {
+            match key.code {
+                KeyCode::Enter => {
+                    if !pager.search(query) {
+                        message = Some(format!("Not found: {}", query));
+                    }
+                    search = None;
+                }
+                KeyCode::Esc => search = None,
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(c) => query.push(c),
+                _ => {}
+            }
+            continue;
+        }
+        if is_quit(&key) {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => pager.scroll(1),
+            KeyCode::Up | KeyCode::Char('k') => pager.scroll(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') => pager.page(true),
+            KeyCode::PageUp | KeyCode::Char('b') => pager.page(false),
+            KeyCode::Home | KeyCode::Char('g') => pager.home(),
+            KeyCode::End | KeyCode::Char('G') => pager.end(),
+            KeyCode::Char('/') => search = Some(String::new()),
+            KeyCode::Char(c @ ('n' | 'N')) if !pager.next_match(c == 'n') => {
+                message = Some(match pager.query() {
+                    Some(query) => format!("No more matches for {}", query),
+                    None => "Press / to search".to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a key closes the viewer
+fn is_quit(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+/// Draw the rows on screen and the status line, or the search being typed
+fn draw(stdout: &mut io::Stdout, pager: &Pager, search: Option<&str>, message: Option<String>) -> Result<()> {
+    let rows = pager.rows();
+    let (_, height) = terminal::size().context("Failed to read the terminal size")?;
+    let text_rows = height.saturating_sub(1);
+    for y in 0..text_rows {
+        queue!(stdout, MoveTo(0, y), Clear(ClearType::CurrentLine))?;
+        let Some(row) = rows.get(y as usize) else {
+            continue;
+        };
+        let emphasis = if pager.query().is_some_and(|query| row.to_lowercase().contains(query)) {
+            Attribute::Reverse
+        } else if HEADINGS.iter().any(|heading| row.starts_with(heading)) {
+            Attribute::Bold
+        } else {
+            Attribute::Reset
+        };
+        queue!(stdout, SetAttribute(emphasis), Print(row), SetAttribute(Attribute::Reset))?;
+    }
+    let status = match (search, message) {
+        (Some(query), _) => format!("/{}", query),
+        (None, Some(message)) => message,
+        (None, None) => pager.status(),
+    };
+    queue!(
+        stdout,
+        MoveTo(0, text_rows),
+        Clear(ClearType::CurrentLine),
+        SetAttribute(Attribute::Reverse),
+        Print(status),
+        SetAttribute(Attribute::Reset)
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The pager's screen, left again when dropped
+struct Screen;
+
+impl Screen {
+    /// Switch to the alternate screen and read keys as they are pressed
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode().context("Failed to read keys from the terminal")?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide).context("Failed to open the viewer")?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::Conversation;
+
+    fn numbered(count: usize) -> Vec<String> {
+        (1..=count).map(|n| format!("line {}", n)).collect()
+    }
+
+    #[test]
+    fn test_conversation_lines() {
+        let mut conversation = Conversation::new();
+        conversation.push("First\nquestion");
+        conversation.set_response("Answer");
+        conversation.push("Second");
+
+        assert_eq!(lines(conversation.turns()), ["You", "First", "question", "", "Claude", "Answer", "", "You", "Second", "", "Claude", "No response"]);
+    }
+
+    #[test]
+    fn test_scrolling_stops_at_the_start_and_end() {
+        let mut pager = Pager::new(numbered(10), 80, 4);
+        pager.scroll(-1);
+        assert_eq!(pager.top(), 0);
+        pager.page(true);
+        assert_eq!(pager.rows(), ["line 5", "line 6", "line 7", "line 8"]);
+        pager.page(true);
+        assert_eq!(pager.rows(), ["line 7", "line 8", "line 9", "line 10"]);
+        pager.home();
+        assert_eq!(pager.top(), 0);
+        pager.end();
+        assert_eq!(pager.top(), 6);
+        assert_eq!(pager.status(), "rows 7-10 of 10 (100%)  ↑↓ scroll  / search  n/N next/previous  q quit");
+    }
+
+    #[test]
+    fn test_long_lines_are_wrapped() {
+        let mut pager = Pager::new(vec!["abcdefghij".to_string(), "日本語です".to_string(), String::new()], 4, 10);
+        assert_eq!(pager.rows(), ["abcd", "efgh", "ij", "日本", "語で", "す", ""]);
+
+        // The line at the top stays there
+        pager.resize(4, 2);
+        pager.scroll(3);
+        pager.resize(6, 2);
+        assert_eq!(pager.rows(), ["日本語", "です"]);
+    }
+
+    #[test]
+    fn test_search_goes_through_the_matches() {
+        let mut lines = numbered(20);
+        lines[4] = "A Lifetime".to_string();
+        lines[14] = "another lifetime".to_string();
+        let mut pager = Pager::new(lines, 80, 3);
+
+        assert!(pager.search("LIFETIME"));
+        assert_eq!(pager.rows()[0], "A Lifetime");
+        assert!(pager.next_match(true));
+        assert_eq!(pager.rows()[0], "another lifetime");
+        assert!(!pager.next_match(true));
+        assert!(pager.next_match(false));
+        assert_eq!(pager.top(), 4);
+        assert!(!pager.search("borrow"));
+        assert_eq!(pager.top(), 4);
+
+        // A match on the last screen is not found again and again
+        let mut pager = Pager::new(numbered(10), 80, 4);
+        assert!(pager.search("line 9"));
+        assert_eq!(pager.top(), 6);
+        assert!(!pager.next_match(true));
+    }
+}
@@ -0,0 +1,210 @@
+#![cfg(feature = "api")]
+
+mod common;
+
+use claude_dialog::api::{ApiExecutor, DEFAULT_MAX_TOKENS};
+use claude_dialog::chat::ChatMessage;
+use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeOptions, OutputFormat, SessionMode};
+#[cfg(feature = "cli")]
+use claude_dialog::dialog::{DialogConfig, DialogLoop};
+use claude_dialog::pipeline::ResponsePipeline;
+use common::{Request, http_stub, http_stub_with_headers};
+use serde_json::json;
+use chrono::Utc;
+use claude_dialog::rate_limit::RateLimit;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+/// A stand-in Anthropic API, see [`http_stub`]
+fn api_server(responses: Vec<(&'static str, String)>) -> (String, Receiver<Request>) {
+    http_stub("text/event-stream", responses)
+}
+
+/// A streamed answer split into one event per word, ending for `stop_reason`
+fn events(text: &str, stop_reason: &str) -> (&'static str, String) {
+    let mut body = String::from("event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":10,\"output_tokens\":1}}}\n\n");
+    for word in text.split_inclusive(' ') {
+        let event = serde_json::json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": word } });
+        body.push_str(&format!("event: content_block_delta\ndata: {}\n\n", event));
+    }
+    let end = serde_json::json!({ "type": "message_delta", "delta": { "stop_reason": stop_reason }, "usage": { "output_tokens": 7 } });
+    body.push_str(&format!("event: message_delta\ndata: {}\n\n", end));
+    body.push_str("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n");
+    ("200 OK", body)
+}
+
+fn answer(text: &str) -> (&'static str, String) {
+    events(text, "end_turn")
+}
+
+fn options() -> ClaudeOptions {
+    common::options("claude-sonnet-4-5")
+}
+
+fn command(prompt: &str) -> ClaudeInvocation {
+    common::command("claude-sonnet-4-5", prompt)
+}
+
+fn api(url: &str) -> ApiExecutor {
+    ApiExecutor::new(url, Some("test-key".to_string()))
+}
+
+#[tokio::test]
+async fn test_conversation_history_is_sent_with_every_prompt() {
+    let (url, requests) = api_server(vec![answer("Hello there"), answer("Your name is Ada"), answer("Hi")]);
+    let api = api(&url);
+
+    let first = ClaudeInvocation {
+        prompt: "My name is Ada".to_string(),
+        options: ClaudeOptions {
+            system_prompt: Some("Be brief.".to_string()),
+            ..options()
+        },
+    };
+    let response = api.execute(first, ResponsePipeline::new()).await.unwrap();
+    assert_eq!(response.text, "Hello there");
+    assert_eq!(response.usage.unwrap().output_tokens, 7);
+
+    let Request { headers, body: request } = requests.recv().unwrap();
+    assert!(headers.contains(&"x-api-key: test-key".to_string()));
+    assert!(headers.iter().any(|header| header.starts_with("anthropic-version:")));
+    assert_eq!(request["model"], "claude-sonnet-4-5");
+    // The system prompt and the latest prompt are cache breakpoints
+    assert_eq!(request["system"], json!([{ "type": "text", "text": "Be brief.", "cache_control": { "type": "ephemeral" } }]));
+    assert_eq!(request["messages"], json!([{
+        "role": "user",
+        "content": [{ "type": "text", "text": "My name is Ada", "cache_control": { "type": "ephemeral" } }]
+    }]));
+    assert_eq!(request["stream"], true);
+    assert_eq!(request["max_tokens"], DEFAULT_MAX_TOKENS);
+    assert!(request["temperature"].is_null());
+
+    api.execute(command("What is my name?"), ResponsePipeline::new()).await.unwrap();
+    let messages = requests.recv().unwrap().body["messages"].clone();
+    assert_eq!(messages.as_array().unwrap().len(), 3);
+    assert_eq!(messages[0]["content"], "My name is Ada");
+    assert_eq!(messages[1]["content"], "Hello there");
+    assert_eq!(messages[2]["content"][0]["text"], "What is my name?");
+
+    let fresh = ClaudeInvocation {
+        prompt: "Hello".to_string(),
+        options: ClaudeOptions {
+            session: SessionMode::Fresh,
+            output: OutputFormat::StreamJson,
+            temperature: Some(0.25),
+            max_tokens: Some(256),
+            ..options()
+        },
+    };
+    api.execute(fresh, ResponsePipeline::new()).await.unwrap();
+    let request = requests.recv().unwrap().body;
+    assert_eq!(request["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(request["temperature"], 0.25);
+    assert_eq!(request["max_tokens"], 256);
+    assert_eq!(api.history(), [ChatMessage::new("user", "Hello"), ChatMessage::new("assistant", "Hi")]);
+}
+
+#[tokio::test]
+async fn test_requests_without_prompt_caching() {
+    let (url, requests) = api_server(vec![answer("Hello")]);
+    let command = ClaudeInvocation {
+        prompt: "Hi".to_string(),
+        options: ClaudeOptions {
+            system_prompt: Some("Be brief.".to_string()),
+            no_prompt_cache: true,
+            ..options()
+        },
+    };
+
+    api(&url).execute(command, ResponsePipeline::new()).await.unwrap();
+    let request = requests.recv().unwrap().body;
+    assert_eq!(request["system"], json!([{ "type": "text", "text": "Be brief." }]));
+    assert_eq!(request["messages"], json!([{ "role": "user", "content": "Hi" }]));
+}
+
+#[tokio::test]
+async fn test_truncated_responses() {
+    let (url, _requests) = api_server(vec![events("A long", "max_tokens")]);
+
+    let response = api(&url).execute(command("Tell me everything"), ResponsePipeline::new()).await.unwrap();
+    assert!(response.is_success());
+    assert!(response.truncated);
+    assert_eq!(response.text, "A long");
+    assert!(response.stderr.is_empty());
+}
+
+#[tokio::test]
+async fn test_api_errors_fail_the_response() {
+    let body = "{\"type\":\"error\",\"error\":{\"type\":\"not_found_error\",\"message\":\"model: claude-sonnet-9\"}}".to_string();
+    let (url, _requests) = api_server(vec![("404 Not Found", body)]);
+    let api = api(&url);
+
+    let response = api.execute(command("Hello"), ResponsePipeline::new()).await.unwrap();
+    assert!(!response.is_success());
+    assert_eq!(response.stderr, "Anthropic API error (HTTP 404): model: claude-sonnet-9");
+    assert!(api.history().is_empty());
+}
+
+#[tokio::test]
+async fn test_rate_limits_keep_the_requested_wait() {
+    let body = "{\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\",\"message\":\"Rate limited\"}}".to_string();
+    let (url, _requests) = http_stub_with_headers("application/json", vec![("429 Too Many Requests", vec!["Retry-After: 2"], body)]);
+
+    let response = api(&url).execute(command("Hello"), ResponsePipeline::new()).await.unwrap();
+    assert_eq!(response.stderr, "Anthropic API error (HTTP 429): Rate limited; retry after 2 seconds");
+    let limit = RateLimit::detect(&response.stderr, Utc::now()).unwrap();
+    assert_eq!(limit.retry_after, Some(Duration::from_secs(2)));
+}
+
+#[cfg(feature = "cli")]
+#[tokio::test]
+async fn test_dialog_retries_after_the_requested_wait() {
+    let body = "{\"type\":\"error\",\"error\":{\"type\":\"rate_limit_error\",\"message\":\"Rate limited\"}}".to_string();
+    let (status, events) = answer("Hello");
+    let (url, requests) = http_stub_with_headers(
+        "text/event-stream",
+        vec![("429 Too Many Requests", vec!["Retry-After: 2"], body), (status, Vec::new(), events)],
+    );
+    let config = DialogConfig {
+        model: Some("claude-sonnet-4-5".to_string()),
+        rate_limit_retries: 1,
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(api(&url)));
+
+    let started = Instant::now();
+    dialog.submit("Hello").await.unwrap();
+    assert!(started.elapsed() >= Duration::from_secs(2));
+    assert_eq!(requests.iter().take(2).count(), 2);
+    assert_eq!(dialog.conversation().turns()[0].response.as_deref(), Some("Hello"));
+}
+
+#[tokio::test]
+async fn test_missing_key_or_model() {
+    let url = common::unreachable_url();
+
+    let err = ApiExecutor::new(&url, None).execute(command("Hello"), ResponsePipeline::new()).await.unwrap_err();
+    assert!(err.to_string().contains("ANTHROPIC_API_KEY"));
+
+    let no_model = ClaudeInvocation {
+        prompt: "Hello".to_string(),
+        options: ClaudeOptions::default(),
+    };
+    assert!(api(&url).execute(no_model, ResponsePipeline::new()).await.is_err());
+    assert!(api(&url).execute(command("Hello"), ResponsePipeline::new()).await.is_err());
+}
+
+#[cfg(feature = "cli")]
+#[tokio::test]
+async fn test_dialog_with_the_api() {
+    let (url, _requests) = api_server(vec![answer("Borrowing lends a reference")]);
+    let config = DialogConfig {
+        model: Some("claude-sonnet-4-5".to_string()),
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(api(&url)));
+
+    dialog.submit("What is borrowing?").await.unwrap();
+    assert_eq!(dialog.conversation().turns()[0].response.as_deref(), Some("Borrowing lends a reference"));
+    assert!(dialog.transcript().to_markdown().contains("## Claude (claude-sonnet-4-5)\n\nBorrowing lends a reference\n"));
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "cli")]
+
+use claude_dialog::checkpoint::{Checkpoint, CheckpointStore, WorkspaceSnapshot};
+use std::fs;
+
+#[test]
+fn test_store_lookup() {
+    let mut store = CheckpointStore::new();
+    store.save(Checkpoint {
+        name: "before-refactor".to_string(),
+        turn_count: 2,
+        files: None,
+    });
+    
+    assert_eq!(store.get("before-refactor").unwrap().turn_count, 2);
+    assert!(store.get("after-refactor").is_none());
+}
+
+#[test]
+fn test_snapshot_restores_modified_and_deleted_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let main_rs = dir.path().join("src/main.rs");
+    let readme = dir.path().join("README.md");
+    fs::create_dir_all(main_rs.parent().unwrap()).unwrap();
+    fs::write(&main_rs, "fn main() {}").unwrap();
+    fs::write(&readme, "# Project").unwrap();
+    
+    let snapshot = WorkspaceSnapshot::capture(dir.path()).unwrap();
+    assert_eq!(snapshot.len(), 2);
+    
+    fs::write(&main_rs, "fn main() { broken").unwrap();
+    fs::remove_file(&readme).unwrap();
+    fs::write(dir.path().join("notes.txt"), "new").unwrap();
+    
+    let report = snapshot.restore().unwrap();
+    assert_eq!(fs::read_to_string(&main_rs).unwrap(), "fn main() {}");
+    assert_eq!(fs::read_to_string(&readme).unwrap(), "# Project");
+    assert_eq!(report.restored.len(), 2);
+    assert_eq!(report.untracked, vec![dir.path().join("notes.txt")]);
+}
+
+#[test]
+fn test_snapshot_skips_unchanged_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("lib.rs"), "pub fn f() {}").unwrap();
+    
+    let snapshot = WorkspaceSnapshot::capture(dir.path()).unwrap();
+    let report = snapshot.restore().unwrap();
+    assert!(report.restored.is_empty());
+    assert!(report.untracked.is_empty());
+}
+
+#[test]
+fn test_snapshot_restores_hidden_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let env = dir.path().join(".env");
+    let workflow = dir.path().join(".github/workflows/ci.yml");
+    fs::create_dir_all(workflow.parent().unwrap()).unwrap();
+    fs::write(&env, "MODE=dev").unwrap();
+    fs::write(&workflow, "on: push").unwrap();
+    fs::create_dir(dir.path().join(".git")).unwrap();
+    fs::write(dir.path().join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+    
+    let snapshot = WorkspaceSnapshot::capture(dir.path()).unwrap();
+    assert_eq!(snapshot.len(), 2);
+    
+    fs::write(&env, "MODE=prod").unwrap();
+    fs::remove_file(&workflow).unwrap();
+    
+    let report = snapshot.restore().unwrap();
+    assert_eq!(report.restored, [env.clone(), workflow.clone()]);
+    assert_eq!(fs::read_to_string(&env).unwrap(), "MODE=dev");
+    assert_eq!(fs::read_to_string(&workflow).unwrap(), "on: push");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_restores_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("build.sh");
+    fs::write(&script, "#!/bin/sh\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    
+    let snapshot = WorkspaceSnapshot::capture(dir.path()).unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+    
+    let report = snapshot.restore().unwrap();
+    assert_eq!(report.restored, vec![script.clone()]);
+    assert_eq!(fs::metadata(&script).unwrap().permissions().mode() & 0o777, 0o755);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_never_writes_through_symlinks() {
+    use std::os::unix::fs::symlink;
+    
+    let dir = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    let secret = outside.path().join("secret.txt");
+    fs::write(&secret, "outside").unwrap();
+    
+    let notes = dir.path().join("notes.txt");
+    let link = dir.path().join("latest");
+    fs::write(&notes, "inside").unwrap();
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs/guide.md"), "guide").unwrap();
+    symlink("notes.txt", &link).unwrap();
+    
+    let snapshot = WorkspaceSnapshot::capture(dir.path()).unwrap();
+    assert_eq!(snapshot.len(), 3);
+    
+    // A file replaced by a link to outside the workspace, a link pointed
+    // elsewhere and a directory replaced by a link
+    fs::remove_file(&notes).unwrap();
+    symlink(&secret, &notes).unwrap();
+    fs::remove_file(&link).unwrap();
+    symlink(&secret, &link).unwrap();
+    fs::remove_dir_all(dir.path().join("docs")).unwrap();
+    symlink(outside.path(), dir.path().join("docs")).unwrap();
+    
+    let report = snapshot.restore().unwrap();
+    assert_eq!(fs::read_to_string(&secret).unwrap(), "outside");
+    assert!(!outside.path().join("guide.md").exists());
+    assert_eq!(report.restored, [link.clone(), notes.clone()]);
+    assert_eq!(report.skipped, [dir.path().join("docs/guide.md")]);
+    assert!(!fs::symlink_metadata(&notes).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&notes).unwrap(), "inside");
+    assert_eq!(fs::read_link(&link).unwrap(), std::path::Path::new("notes.txt"));
+}
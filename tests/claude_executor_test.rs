@@ -1,69 +1,84 @@
-use claude_dialog::claude_executor::ClaudeCommand;
+use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeOptions, SessionMode};
 
 #[test]
 fn test_claude_command_basic() {
-    let cmd = ClaudeCommand {
+    let cmd = ClaudeInvocation {
         prompt: "Hello".to_string(),
-        system_prompt: None,
-        append_prompt: None,
-        model: None,
+        options: ClaudeOptions {
+            system_prompt: None,
+            append_prompt: None,
+            model: None,
+            ..Default::default()
+        },
     };
     
     assert_eq!(cmd.prompt, "Hello");
-    assert!(cmd.system_prompt.is_none());
-    assert!(cmd.append_prompt.is_none());
-    assert!(cmd.model.is_none());
+    assert!(cmd.options.system_prompt.is_none());
+    assert!(cmd.options.append_prompt.is_none());
+    assert!(cmd.options.model.is_none());
 }
 
 #[test]
 fn test_claude_command_with_system_prompt() {
-    let cmd = ClaudeCommand {
+    let cmd = ClaudeInvocation {
         prompt: "Hello".to_string(),
-        system_prompt: Some("You are helpful".to_string()),
-        append_prompt: None,
-        model: None,
+        options: ClaudeOptions {
+            system_prompt: Some("You are helpful".to_string()),
+            append_prompt: None,
+            model: None,
+            ..Default::default()
+        },
     };
     
-    assert!(cmd.system_prompt.is_some());
-    assert_eq!(cmd.system_prompt.unwrap(), "You are helpful");
+    assert!(cmd.options.system_prompt.is_some());
+    assert_eq!(cmd.options.system_prompt.unwrap(), "You are helpful");
 }
 
 #[test]
 fn test_claude_command_with_append_prompt() {
-    let cmd = ClaudeCommand {
+    let cmd = ClaudeInvocation {
         prompt: "Hello".to_string(),
-        system_prompt: None,
-        append_prompt: Some("Additional context".to_string()),
-        model: None,
+        options: ClaudeOptions {
+            system_prompt: None,
+            append_prompt: Some("Additional context".to_string()),
+            model: None,
+            ..Default::default()
+        },
     };
     
-    assert!(cmd.append_prompt.is_some());
-    assert_eq!(cmd.append_prompt.unwrap(), "Additional context");
+    assert!(cmd.options.append_prompt.is_some());
+    assert_eq!(cmd.options.append_prompt.unwrap(), "Additional context");
 }
 
 #[test]
 fn test_claude_command_with_model() {
-    let cmd = ClaudeCommand {
+    let cmd = ClaudeInvocation {
         prompt: "Hello".to_string(),
-        system_prompt: None,
-        append_prompt: None,
-        model: Some("claude-3-opus".to_string()),
+        options: ClaudeOptions {
+            system_prompt: None,
+            append_prompt: None,
+            model: Some("claude-3-opus".to_string()),
+            ..Default::default()
+        },
     };
     
-    assert!(cmd.model.is_some());
-    assert_eq!(cmd.model.unwrap(), "claude-3-opus");
+    assert!(cmd.options.model.is_some());
+    assert_eq!(cmd.options.model.unwrap(), "claude-3-opus");
 }
 
 #[test]
 fn test_build_command_args() {
-    let cmd = ClaudeCommand {
+    let cmd = ClaudeInvocation {
         prompt: "Hello".to_string(),
-        system_prompt: Some("System".to_string()),
-        append_prompt: None,
-        model: Some("claude-3".to_string()),
+        options: ClaudeOptions {
+            system_prompt: Some("System".to_string()),
+            append_prompt: None,
+            model: Some("claude-3".to_string()),
+            ..Default::default()
+        },
     };
     
-    let args = cmd.build_args();
+    let args = cmd.to_args();
     assert!(args.contains(&"--continue".to_string()));
     assert!(args.contains(&"-p".to_string()));
     assert!(args.contains(&"Hello".to_string()));
@@ -78,15 +93,38 @@ fn test_build_command_args() {
 
 #[test]
 fn test_build_command_args_append() {
-    let cmd = ClaudeCommand {
+    let cmd = ClaudeInvocation {
         prompt: "Hello".to_string(),
-        system_prompt: None,
-        append_prompt: Some("Append".to_string()),
-        model: None,
+        options: ClaudeOptions {
+            system_prompt: None,
+            append_prompt: Some("Append".to_string()),
+            model: None,
+            ..Default::default()
+        },
     };
     
-    let args = cmd.build_args();
+    let args = cmd.to_args();
     assert!(args.contains(&"--append-system-prompt".to_string()));
     assert!(args.contains(&"Append".to_string()));
     assert!(!args.contains(&"--system-prompt".to_string()));
-}
\ No newline at end of file
+}
+#[test]
+fn test_claude_command_serde() {
+    let cmd = ClaudeInvocation {
+        prompt: "Hello".to_string(),
+        options: ClaudeOptions {
+            model: Some("claude-3-opus".to_string()),
+            session: SessionMode::Fresh,
+            ..Default::default()
+        },
+    };
+    
+    let json = serde_json::to_string(&cmd).unwrap();
+    assert!(json.contains(r#""session":"fresh""#));
+    
+    let parsed: ClaudeInvocation = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.to_args(), cmd.to_args());
+    
+    let parsed: ClaudeInvocation = serde_json::from_str(r#"{"prompt": "Hi"}"#).unwrap();
+    assert_eq!(parsed.options.session, SessionMode::Continue);
+}
@@ -0,0 +1,41 @@
+use claude_dialog::claude_version::{Capabilities, Capability, ClaudeVersion, detect_version};
+
+#[test]
+fn test_parse_claude_version_output() {
+    let version: ClaudeVersion = "1.0.35 (Claude Code)\n".parse().unwrap();
+    assert_eq!(version, ClaudeVersion::new(1, 0, 35));
+    assert_eq!(version.to_string(), "1.0.35");
+}
+
+#[test]
+fn test_unknown_version_gates_nothing() {
+    let capabilities = Capabilities::for_version(None);
+    assert!(capabilities.version().is_none());
+    assert!(capabilities.supports(Capability::SystemPrompt));
+    assert!(capabilities.compatibility_warnings().is_empty());
+}
+
+#[test]
+fn test_capability_flags() {
+    assert_eq!(Capability::AppendSystemPrompt.flag(), "--append-system-prompt");
+    assert_eq!(Capability::StreamJsonOutput.flag(), "--output-format stream-json");
+}
+
+#[test]
+fn test_detect_missing_binary() {
+    let result = detect_version(std::path::Path::new("/nonexistent/claude"));
+    assert!(result.is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_detect_version_from_binary() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("claude");
+    std::fs::write(&path, "#!/bin/sh\necho '1.0.51 (Claude Code)'\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    
+    assert_eq!(detect_version(&path).unwrap(), ClaudeVersion::new(1, 0, 51));
+}
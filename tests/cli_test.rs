@@ -1,4 +1,25 @@
-use claude_dialog::cli::parse_args;
+#![cfg(feature = "cli")]
+
+use claude_dialog::cli::{Command, parse_args};
+use claude_dialog::export::ImportMode;
+use claude_dialog::language::Language;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Create prompt files with the given names, returning their paths
+fn prompt_files(names: &[&str]) -> (TempDir, Vec<String>) {
+    let dir = tempfile::tempdir().unwrap();
+    let paths = names
+        .iter()
+        .map(|name| {
+            let path = dir.path().join(name);
+            fs::write(&path, "prompt").unwrap();
+            path.to_str().unwrap().to_string()
+        })
+        .collect();
+    (dir, paths)
+}
 
 #[test]
 fn test_default_args() {
@@ -13,35 +34,66 @@ fn test_default_args() {
 
 #[test]
 fn test_single_system_prompt() {
-    let args = vec!["claude-dialog", "--system-prompt", "prompt.md"];
+    let (_dir, files) = prompt_files(&["prompt.md"]);
+    let args = vec!["claude-dialog", "--system-prompt", &files[0]];
     let result = parse_args(args);
     assert!(result.is_ok());
     let args = result.unwrap();
-    assert_eq!(args.system_prompt_files, vec!["prompt.md"]);
+    assert_eq!(args.system_prompt_files, vec![PathBuf::from(&files[0])]);
     assert!(args.append_prompt_file.is_none());
 }
 
 #[test]
 fn test_multiple_system_prompts() {
+    let (_dir, files) = prompt_files(&["prompt1.md", "prompt2.md"]);
     let args = vec![
         "claude-dialog",
-        "--system-prompt", "prompt1.md",
-        "--system-prompt", "prompt2.md"
+        "--system-prompt", &files[0],
+        "--system-prompt", &files[1]
     ];
     let result = parse_args(args);
     assert!(result.is_ok());
     let args = result.unwrap();
-    assert_eq!(args.system_prompt_files, vec!["prompt1.md", "prompt2.md"]);
+    assert_eq!(args.system_prompt_files, vec![PathBuf::from(&files[0]), PathBuf::from(&files[1])]);
+}
+
+#[test]
+fn test_system_prompt_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().to_str().unwrap();
+    
+    let args = parse_args(vec!["claude-dialog", "--system-prompt", path]).unwrap();
+    assert_eq!(args.system_prompt_files, vec![dir.path().to_path_buf()]);
+    assert!(!args.recursive);
+    
+    let args = parse_args(vec!["claude-dialog", "--system-prompt", path, "--recursive"]).unwrap();
+    assert!(args.recursive);
+}
+
+#[test]
+fn test_preset_options() {
+    use claude_dialog::cli::PromptsCommand;
+
+    let args = parse_args(vec!["claude-dialog", "--preset", "rust-reviewer"]).unwrap();
+    assert_eq!(args.preset.as_deref(), Some("rust-reviewer"));
+
+    let (_dir, files) = prompt_files(&["custom.md"]);
+    assert!(parse_args(vec!["claude-dialog", "--preset", "tutor", "--system-prompt", &files[0]]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--preset", "tutor", "--append-system-prompt", &files[0]]).is_err());
+
+    let args = parse_args(vec!["claude-dialog", "prompts", "list"]).unwrap();
+    assert_eq!(args.command, Some(Command::Prompts { action: PromptsCommand::List }));
 }
 
 #[test]
 fn test_append_prompt() {
-    let args = vec!["claude-dialog", "--append-system-prompt", "append.md"];
+    let (_dir, files) = prompt_files(&["append.md"]);
+    let args = vec!["claude-dialog", "--append-system-prompt", &files[0]];
     let result = parse_args(args);
     assert!(result.is_ok());
     let args = result.unwrap();
     assert!(args.system_prompt_files.is_empty());
-    assert_eq!(args.append_prompt_file, Some("append.md".to_string()));
+    assert_eq!(args.append_prompt_file, Some(PathBuf::from(&files[0])));
 }
 
 #[test]
@@ -55,10 +107,11 @@ fn test_model_option() {
 
 #[test]
 fn test_system_and_append_conflict() {
+    let (_dir, files) = prompt_files(&["prompt.md", "append.md"]);
     let args = vec![
         "claude-dialog",
-        "--system-prompt", "prompt.md",
-        "--append-system-prompt", "append.md"
+        "--system-prompt", &files[0],
+        "--append-system-prompt", &files[1]
     ];
     let result = parse_args(args);
     assert!(result.is_err());
@@ -71,4 +124,471 @@ fn test_help_option() {
     let result = parse_args(args);
     // Help should cause an early exit, which we handle as an error in tests
     assert!(result.is_err());
-}
\ No newline at end of file
+}
+#[test]
+fn test_alias_option() {
+    let args = vec![
+        "claude-dialog",
+        "--alias", "cp=/checkpoint {{1}}",
+        "--alias", "back=/restore",
+    ];
+    let args = parse_args(args).unwrap();
+    assert_eq!(args.aliases.len(), 2);
+    assert_eq!(args.aliases[0].name, "cp");
+    assert_eq!(args.aliases[0].expansion, "/checkpoint {{1}}");
+    assert_eq!(args.aliases[1].name, "back");
+}
+
+#[test]
+fn test_invalid_alias_option() {
+    let args = vec!["claude-dialog", "--alias", "cp"];
+    let result = parse_args(args);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("NAME=COMMAND"));
+}
+
+#[test]
+fn test_self_update_subcommand() {
+    let args = parse_args(vec!["claude-dialog", "self-update", "--check"]).unwrap();
+    assert_eq!(args.command, Some(Command::SelfUpdate { check: true }));
+
+    let args = parse_args(vec!["claude-dialog", "self-update"]).unwrap();
+    assert_eq!(args.command, Some(Command::SelfUpdate { check: false }));
+
+    let args = parse_args(vec!["claude-dialog"]).unwrap();
+    assert!(args.command.is_none());
+}
+
+#[test]
+fn test_whatsnew_subcommand() {
+    let args = parse_args(vec!["claude-dialog", "whatsnew"]).unwrap();
+    assert_eq!(args.command, Some(Command::Whatsnew));
+}
+
+#[test]
+fn test_view_subcommand() {
+    let args = parse_args(vec!["claude-dialog", "view", "20250101-120000"]).unwrap();
+    assert_eq!(args.command, Some(Command::View { source: "20250101-120000".to_string() }));
+    assert!(parse_args(vec!["claude-dialog", "view"]).is_err());
+}
+
+#[test]
+fn test_present_subcommand() {
+    let (_dir, files) = prompt_files(&["demo.md"]);
+
+    let args = parse_args(vec!["claude-dialog", "present", &files[0]]).unwrap();
+    assert_eq!(args.command, Some(Command::Present { file: PathBuf::from(&files[0]), speed: 1.0, auto: false }));
+
+    let args = parse_args(vec!["claude-dialog", "present", &files[0], "--speed", "2.5", "--auto"]).unwrap();
+    assert_eq!(args.command, Some(Command::Present { file: PathBuf::from(&files[0]), speed: 2.5, auto: true }));
+
+    assert!(parse_args(vec!["claude-dialog", "present", &files[0], "--speed", "0"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "present", "missing.md"]).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_prompt_path() {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    
+    let dir = tempfile::tempdir().unwrap();
+    let mut path = dir.path().as_os_str().to_owned();
+    path.push("/");
+    path.push(OsString::from_vec(b"prompt-\xff.md".to_vec()));
+    fs::write(&path, "prompt").unwrap();
+    
+    let args = parse_args([OsString::from("claude-dialog"), OsString::from("--system-prompt"), path.clone()]).unwrap();
+    assert_eq!(args.system_prompt_files[0].as_os_str(), path);
+}
+
+#[test]
+fn test_prompt_file_validation() {
+    let err = parse_args(vec!["claude-dialog", "--system-prompt", "missing.md"]).unwrap_err();
+    assert!(err.to_string().contains("invalid value 'missing.md' for '--system-prompt <FILE|DIR>'"));
+    
+    let dir = tempfile::tempdir().unwrap();
+    let err = parse_args(vec!["claude-dialog", "--append-system-prompt", dir.path().to_str().unwrap()]).unwrap_err();
+    assert!(err.to_string().contains("is a directory"));
+    
+    let err = parse_args(vec!["claude-dialog", "--system-prompt", "$CLAUDE_DIALOG_TEST_UNSET/p.md"]).unwrap_err();
+    assert!(err.to_string().contains("Environment variable $CLAUDE_DIALOG_TEST_UNSET is not set"));
+}
+
+#[test]
+fn test_banner_option() {
+    use claude_dialog::ui::BannerStyle;
+    
+    assert_eq!(parse_args(vec!["claude-dialog"]).unwrap().banner, BannerStyle::Full);
+    assert_eq!(parse_args(vec!["claude-dialog", "--banner", "off"]).unwrap().banner, BannerStyle::Off);
+    assert!(parse_args(vec!["claude-dialog", "--banner", "loud"]).is_err());
+}
+
+#[test]
+fn test_context_budget_options() {
+    use claude_dialog::context::BudgetStrategy;
+
+    let args = parse_args(vec!["claude-dialog"]).unwrap();
+    assert_eq!(args.context_budget, 100_000);
+    assert_eq!(args.context_strategy, BudgetStrategy::HeadTail);
+
+    let args = parse_args(vec!["claude-dialog", "--context-budget", "0", "--context-strategy", "ask"]).unwrap();
+    assert_eq!(args.context_budget, 0);
+    assert_eq!(args.context_strategy, BudgetStrategy::Ask);
+
+    assert!(parse_args(vec!["claude-dialog", "--context-budget", "-1"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--context-strategy", "random"]).is_err());
+}
+
+#[test]
+fn test_history_options() {
+    use claude_dialog::cli::HistoryCommand;
+
+    let args = parse_args(vec!["claude-dialog"]).unwrap();
+    assert!(!args.private);
+    assert!(args.history_ignore.is_empty());
+
+    let args = parse_args(vec![
+        "claude-dialog",
+        "--private",
+        "--history-ignore", "*password*",
+        "--history-ignore", "*token*",
+    ]).unwrap();
+    assert!(args.private);
+    assert_eq!(args.history_ignore, vec!["*password*", "*token*"]);
+
+    let err = parse_args(vec!["claude-dialog", "--history-ignore", "[abc"]).unwrap_err();
+    assert!(err.to_string().contains("Invalid history ignore pattern '[abc'"));
+
+    let args = parse_args(vec!["claude-dialog", "history", "clear"]).unwrap();
+    assert_eq!(args.command, Some(Command::History { action: HistoryCommand::Clear }));
+}
+
+#[test]
+fn test_output_format_option() {
+    use claude_dialog::output::PrintFormat;
+
+    assert_eq!(parse_args(vec!["claude-dialog", "-p", "hi"]).unwrap().output_format, PrintFormat::Text);
+    let args = parse_args(vec!["claude-dialog", "-p", "hi", "--output-format", "json"]).unwrap();
+    assert_eq!(args.output_format, PrintFormat::Json);
+    assert!(parse_args(vec!["claude-dialog", "-p", "hi", "--output-format", "yaml"]).is_err());
+    // Conversations print their responses
+    assert!(parse_args(vec!["claude-dialog", "--output-format", "json"]).is_err());
+}
+
+#[test]
+fn test_test_backend_option() {
+    use claude_dialog::scripted::TestBackend;
+    use std::path::PathBuf;
+
+    let args = parse_args(vec!["claude-dialog", "--test-backend", "echo"]).unwrap();
+    assert_eq!(args.test_backend, Some(TestBackend::Echo));
+    let args = parse_args(vec!["claude-dialog", "--test-backend", "tests/answers.yaml"]).unwrap();
+    assert_eq!(args.test_backend, Some(TestBackend::Fixture(PathBuf::from("tests/answers.yaml"))));
+    // Dry runs print command lines instead of answering
+    assert!(parse_args(vec!["claude-dialog", "--test-backend", "echo", "--dry-run"]).is_err());
+}
+
+#[test]
+fn test_chat_subcommand() {
+    let args = parse_args(vec!["claude-dialog", "chat", "--model", "opus"]).unwrap();
+    assert_eq!(args.command, None);
+    assert_eq!(args.model, Some("opus".to_string()));
+
+    let args = parse_args(vec!["claude-dialog", "--model", "opus", "chat"]).unwrap();
+    assert_eq!(args.command, Some(Command::Chat));
+}
+
+#[test]
+fn test_sessions_subcommand() {
+    use claude_dialog::cli::SessionsCommand;
+
+    let args = parse_args(vec!["claude-dialog", "sessions", "list"]).unwrap();
+    assert_eq!(args.command, Some(Command::Sessions { action: SessionsCommand::List }));
+
+    let args = parse_args(vec!["claude-dialog", "sessions", "delete", "20250101-120000"]).unwrap();
+    let id = "20250101-120000".to_string();
+    assert_eq!(args.command, Some(Command::Sessions { action: SessionsCommand::Delete { id } }));
+    assert!(parse_args(vec!["claude-dialog", "sessions", "show"]).is_err());
+    
+    let args = parse_args(vec!["claude-dialog", "sessions", "merge", "a", "b.json", "--label", "research", "--out", "merged.md"]).unwrap();
+    let Some(Command::Sessions { action: SessionsCommand::Merge { sources, labels, out } }) = args.command else {
+        panic!("expected sessions merge");
+    };
+    assert_eq!(sources, ["a", "b.json"]);
+    assert_eq!(labels, ["research"]);
+    assert_eq!(out, std::path::PathBuf::from("merged.md"));
+    assert!(parse_args(vec!["claude-dialog", "sessions", "merge", "a", "--out", "merged.md"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "sessions", "merge", "a", "b"]).is_err());
+
+    let args = parse_args(vec!["claude-dialog", "sessions", "archive", "--older-than", "30d"]).unwrap();
+    let older_than = chrono::TimeDelta::days(30);
+    assert_eq!(args.command, Some(Command::Sessions { action: SessionsCommand::Archive { older_than } }));
+    assert!(parse_args(vec!["claude-dialog", "sessions", "archive", "--older-than", "30"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "sessions", "archive"]).is_err());
+}
+
+#[test]
+fn test_incognito_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().incognito);
+    assert!(parse_args(vec!["claude-dialog", "--incognito"]).unwrap().incognito);
+}
+
+#[test]
+fn test_repo_map_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().repo_map);
+    assert!(parse_args(vec!["claude-dialog", "--repo-map"]).unwrap().repo_map);
+}
+
+#[test]
+fn test_attach_option() {
+    let (_dir, files) = prompt_files(&["parser.rs", "lexer.rs"]);
+
+    assert!(parse_args(vec!["claude-dialog"]).unwrap().attachments.is_empty());
+    let args = parse_args(vec!["claude-dialog", "--attach", &files[0], "--attach", &files[1]]).unwrap();
+    assert_eq!(args.attachments, vec![PathBuf::from(&files[0]), PathBuf::from(&files[1])]);
+    assert!(parse_args(vec!["claude-dialog", "--attach", "missing.rs"]).is_err());
+}
+
+#[test]
+fn test_rate_limit_retries_option() {
+    assert_eq!(parse_args(vec!["claude-dialog"]).unwrap().rate_limit_retries, 3);
+
+    let args = parse_args(vec!["claude-dialog", "--rate-limit-retries", "0"]).unwrap();
+    assert_eq!(args.rate_limit_retries, 0);
+    assert!(parse_args(vec!["claude-dialog", "--rate-limit-retries", "-1"]).is_err());
+}
+
+#[test]
+fn test_timeout_option() {
+    assert_eq!(parse_args(vec!["claude-dialog"]).unwrap().timeout, None);
+    assert_eq!(parse_args(vec!["claude-dialog", "--timeout", "300"]).unwrap().timeout, Some(300));
+    assert!(parse_args(vec!["claude-dialog", "--timeout", "0"]).is_err());
+}
+
+#[test]
+fn test_stream_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().stream);
+    assert!(parse_args(vec!["claude-dialog", "--stream"]).unwrap().stream);
+    assert!(parse_args(vec!["claude-dialog", "--stream", "--final-only"]).is_err());
+}
+
+#[test]
+fn test_raw_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().raw);
+    assert!(parse_args(vec!["claude-dialog", "--raw"]).unwrap().raw);
+    assert!(parse_args(vec!["claude-dialog", "--raw", "--stream"]).unwrap().raw);
+}
+
+#[test]
+fn test_quiz_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().quiz);
+    assert!(parse_args(vec!["claude-dialog", "--quiz"]).unwrap().quiz);
+    assert!(parse_args(vec!["claude-dialog", "--quiz", "--stream"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--quiz", "-p", "question"]).is_err());
+}
+
+#[test]
+fn test_show_usage_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().show_usage);
+    assert!(parse_args(vec!["claude-dialog", "--show-usage", "--stream"]).unwrap().show_usage);
+}
+
+#[test]
+fn test_turn_metadata_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().turn_metadata);
+    assert!(parse_args(vec!["claude-dialog", "--turn-metadata"]).unwrap().turn_metadata);
+}
+
+#[test]
+fn test_suggest_follow_ups_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().suggest_follow_ups);
+    assert!(parse_args(vec!["claude-dialog", "--suggest-follow-ups"]).unwrap().suggest_follow_ups);
+    assert!(parse_args(vec!["claude-dialog", "--suggest-follow-ups", "-p", "Hello"]).is_err());
+}
+
+#[test]
+fn test_dry_run_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().dry_run);
+    assert!(parse_args(vec!["claude-dialog", "--dry-run", "-p", "Hello"]).unwrap().dry_run);
+}
+
+#[test]
+fn test_no_highlight_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().no_highlight);
+    assert!(parse_args(vec!["claude-dialog", "--no-highlight"]).unwrap().no_highlight);
+}
+
+#[test]
+fn test_mask_options() {
+    let (_dir, files) = prompt_files(&["customers.txt"]);
+
+    let args = parse_args(vec!["claude-dialog"]).unwrap();
+    assert!(!args.mask_pii && !args.mask_transcripts);
+    assert!(args.mask_words.is_empty() && args.mask_patterns.is_empty());
+
+    let args = parse_args(vec![
+        "claude-dialog", "--mask-pii", "--mask-words", &files[0], "--mask-pattern", r"ACME-\d+", "--mask-transcripts",
+    ])
+    .unwrap();
+    assert!(args.mask_pii && args.mask_transcripts);
+    assert_eq!(args.mask_words, vec![PathBuf::from(&files[0])]);
+    assert_eq!(args.mask_patterns, vec![r"ACME-\d+".to_string()]);
+
+    assert!(parse_args(vec!["claude-dialog", "--mask-pattern", "(unclosed"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--mask-words", "missing.txt"]).is_err());
+}
+
+#[test]
+fn test_tee_option() {
+    assert!(parse_args(vec!["claude-dialog"]).unwrap().tee.is_none());
+
+    let args = parse_args(vec!["claude-dialog", "--tee", "/tmp/dialog", "--prompt", "Hi"]).unwrap();
+    assert_eq!(args.tee, Some("/tmp/dialog".into()));
+    assert!(parse_args(vec!["claude-dialog", "--tee", "/tmp/dialog", "--incognito"]).is_err());
+}
+
+#[test]
+fn test_save_transcript_option() {
+    assert!(parse_args(vec!["claude-dialog"]).unwrap().save_transcript.is_none());
+
+    let args = parse_args(vec!["claude-dialog", "--save-transcript", "notes/session.md"]).unwrap();
+    assert_eq!(args.save_transcript, Some("notes/session.md".into()));
+    assert!(parse_args(vec!["claude-dialog", "--save-transcript", "t.md", "--incognito"]).is_err());
+}
+
+#[test]
+fn test_record_cast_option() {
+    assert!(parse_args(vec!["claude-dialog"]).unwrap().record_cast.is_none());
+
+    let args = parse_args(vec!["claude-dialog", "--record-cast", "demo.cast"]).unwrap();
+    assert_eq!(args.record_cast, Some("demo.cast".into()));
+    assert!(parse_args(vec!["claude-dialog", "--record-cast", "demo.cast", "-p", "question"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--record-cast", "demo.cast", "--incognito"]).is_err());
+}
+
+#[cfg(feature = "ollama")]
+#[test]
+fn test_backend_option() {
+    use claude_dialog::claude_executor::Backend;
+
+    assert_eq!(parse_args(vec!["claude-dialog"]).unwrap().backend, Backend::Claude);
+    let args = parse_args(vec!["claude-dialog", "--backend", "ollama", "--model", "llama3"]).unwrap();
+    assert_eq!(args.backend, Backend::Ollama);
+    assert!(parse_args(vec!["claude-dialog", "--backend", "gpt"]).is_err());
+}
+
+#[test]
+fn test_session_options() {
+    use claude_dialog::claude_executor::SessionMode;
+
+    assert_eq!(parse_args(vec!["claude-dialog", "--new-session"]).unwrap().session(), SessionMode::Fresh);
+    let args = parse_args(vec!["claude-dialog", "--resume", "3f2a9c1e"]).unwrap();
+    assert_eq!(args.session(), SessionMode::Resume("3f2a9c1e".to_string()));
+    assert!(parse_args(vec!["claude-dialog", "--resume", ""]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--resume", "3f2a9c1e", "--new-session"]).is_err());
+}
+
+#[test]
+fn test_recover_flag() {
+    assert!(!parse_args(vec!["claude-dialog"]).unwrap().recover);
+    assert!(parse_args(vec!["claude-dialog", "--recover"]).unwrap().recover);
+    assert!(parse_args(vec!["claude-dialog", "--recover", "--new-session"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--recover", "--incognito"]).is_err());
+}
+
+#[test]
+fn test_persona_option() {
+    let args = parse_args(vec!["claude-dialog", "--persona", "final=claude:opus", "--persona", "quick=claude:haiku"]).unwrap();
+    let personas: Vec<String> = args.personas.iter().map(ToString::to_string).collect();
+    assert_eq!(personas, ["final=claude:opus", "quick=claude:haiku"]);
+    assert!(parse_args(vec!["claude-dialog", "--persona", "final"]).is_err());
+}
+
+#[test]
+fn test_tool_options() {
+    let args = parse_args(vec![
+        "claude-dialog",
+        "--allowed-tools", "Read, Edit",
+        "--allowed-tools", "Bash(git log:*)",
+        "--disallowed-tools", "WebFetch",
+    ])
+    .unwrap();
+    assert_eq!(args.allowed_tools, ["Read", "Edit", "Bash(git log:*)"]);
+    assert_eq!(args.disallowed_tools, ["WebFetch"]);
+
+    assert!(parse_args(vec!["claude-dialog", "--allowed-tools", "Read,,Edit"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--disallowed-tools", " "]).is_err());
+}
+
+#[test]
+fn test_prompt_option() {
+    let args = parse_args(vec!["claude-dialog", "--prompt", "Summarize @CHANGELOG.md"]).unwrap();
+    assert_eq!(args.prompt.as_deref(), Some("Summarize @CHANGELOG.md"));
+
+    let args = parse_args(vec!["claude-dialog", "-p", "-"]).unwrap();
+    assert_eq!(args.prompt.as_deref(), Some("-"));
+
+    assert!(parse_args(vec!["claude-dialog"]).unwrap().prompt.is_none());
+}
+
+#[test]
+fn test_batch_options() {
+    let (_dir, files) = prompt_files(&["prompts.yaml"]);
+    let path = files[0].as_str();
+
+    let args = parse_args(vec!["claude-dialog", "--batch", path]).unwrap();
+    assert_eq!(args.batch, Some(PathBuf::from(path)));
+    assert!(args.batch_output.is_none());
+
+    let args = parse_args(vec!["claude-dialog", "--batch", path, "--batch-output", "out/report.json"]).unwrap();
+    assert_eq!(args.batch_output, Some(PathBuf::from("out/report.json")));
+
+    assert!(parse_args(vec!["claude-dialog", "--batch", "missing.yaml"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--batch", path, "--prompt", "Hi"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--batch-output", "report.json"]).is_err());
+}
+
+#[test]
+fn test_spellcheck_options() {
+    let (_dir, files) = prompt_files(&["words.txt", "project.txt"]);
+
+    let args = parse_args(vec!["claude-dialog"]).unwrap();
+    assert!(!args.spellcheck);
+    assert!(args.dictionaries.is_empty());
+
+    let args = parse_args(vec!["claude-dialog", "--spellcheck", "--dictionary", &files[0], "--dictionary", &files[1]]).unwrap();
+    assert!(args.spellcheck);
+    assert_eq!(args.dictionaries, vec![PathBuf::from(&files[0]), PathBuf::from(&files[1])]);
+
+    assert!(parse_args(vec!["claude-dialog", "--dictionary", "missing.txt"]).is_err());
+}
+
+#[test]
+fn test_language_option() {
+    let args = parse_args(vec!["claude-dialog", "--language", "ja"]).unwrap();
+    assert_eq!(args.language, Some(Language::Japanese));
+
+    let args = parse_args(vec!["claude-dialog", "--language", "German"]).unwrap();
+    assert_eq!(args.language, Some(Language::German));
+
+    assert!(parse_args(vec!["claude-dialog"]).unwrap().language.is_none());
+    assert!(parse_args(vec!["claude-dialog", "--language", "elvish"]).is_err());
+}
+
+#[test]
+fn test_from_options() {
+    let (_dir, files) = prompt_files(&["handoff.json"]);
+    let path = files[0].as_str();
+
+    let args = parse_args(vec!["claude-dialog", "--from", path]).unwrap();
+    assert_eq!(args.from, Some(PathBuf::from(path)));
+    assert_eq!(args.from_mode, ImportMode::Replay);
+
+    let args = parse_args(vec!["claude-dialog", "--from", path, "--from-mode", "summary"]).unwrap();
+    assert_eq!(args.from_mode, ImportMode::Summary);
+
+    assert!(parse_args(vec!["claude-dialog", "--from", "missing.json"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--from-mode", "summary"]).is_err());
+    assert!(parse_args(vec!["claude-dialog", "--from", path, "--resume", "3f2a9c1e"]).is_err());
+}
@@ -0,0 +1,309 @@
+use claude_dialog::commands::{CommandAction, CommandRegistry};
+use claude_dialog::export::ExportFormat;
+
+#[test]
+fn test_regular_prompt_is_not_a_command() {
+    let registry = CommandRegistry::new();
+    assert_eq!(registry.parse("How do I read a file?").unwrap(), None);
+    assert_eq!(registry.parse("/etc/hosts looks wrong").unwrap(), None);
+}
+
+#[test]
+fn test_checkpoint_command() {
+    let registry = CommandRegistry::new();
+    let action = registry.parse("/checkpoint before-refactor").unwrap();
+    assert_eq!(action, Some(CommandAction::Checkpoint {
+        name: "before-refactor".to_string(),
+        include_files: false,
+    }));
+}
+
+#[test]
+fn test_restore_command() {
+    let registry = CommandRegistry::new();
+    let action = registry.parse("/restore before-refactor").unwrap();
+    assert_eq!(action, Some(CommandAction::Restore {
+        name: "before-refactor".to_string(),
+    }));
+}
+
+#[test]
+fn test_unknown_command() {
+    let registry = CommandRegistry::new();
+    let result = registry.parse("/frobnicate");
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Unknown command: /frobnicate"));
+}
+
+#[test]
+fn test_restore_extra_argument() {
+    let registry = CommandRegistry::new();
+    let result = registry.parse("/restore a b");
+    assert!(result.unwrap_err().to_string().contains("Unexpected argument: b"));
+}
+
+#[test]
+fn test_per_command_help() {
+    let registry = CommandRegistry::new();
+    let action = registry.parse("/checkpoint --help").unwrap();
+    match action {
+        Some(CommandAction::Help(text)) => {
+            assert!(text.contains("Usage: /checkpoint <name> [--files]"));
+            assert!(text.contains("<name>  Name of the checkpoint"));
+        }
+        other => panic!("expected help, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_quoted_arguments() {
+    let registry = CommandRegistry::new();
+    let action = registry.parse("/checkpoint 'before big refactor' --files").unwrap();
+    assert_eq!(action, Some(CommandAction::Checkpoint {
+        name: "before big refactor".to_string(),
+        include_files: true,
+    }));
+
+    let result = registry.parse("/restore \"unterminated");
+    assert!(result.unwrap_err().to_string().contains("Unterminated quote"));
+}
+
+#[test]
+fn test_aliases_are_registered() {
+    let mut registry = CommandRegistry::new();
+    registry.register_alias("cp=/checkpoint --files".parse().unwrap()).unwrap();
+    
+    assert_eq!(registry.aliases().len(), 1);
+    assert!(registry.get_alias("cp").is_some());
+    
+    let action = registry.parse("/cp snap").unwrap();
+    assert_eq!(action, Some(CommandAction::Checkpoint {
+        name: "snap".to_string(),
+        include_files: true,
+    }));
+}
+
+#[test]
+fn test_alias_to_unknown_command() {
+    let mut registry = CommandRegistry::new();
+    let result = registry.register_alias("r=/retry".parse().unwrap());
+    assert!(result.unwrap_err().to_string().contains("unknown command: /retry"));
+}
+
+#[test]
+fn test_restricted_commands_are_refused() {
+    let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+    let mut registry = CommandRegistry::new();
+    registry.register_alias("sys=/system".parse().unwrap()).unwrap();
+    registry.restrict(None, &names(&["/system", "export"])).unwrap();
+    
+    assert_eq!(registry.disabled(), ["system", "export"]);
+    for input in ["/system review.md", "/system --help", "/sys", "/export html"] {
+        let err = registry.parse(input).unwrap_err().to_string();
+        assert!(err.ends_with("is disabled by the configuration"), "{}: {}", input, err);
+    }
+    assert!(registry.help(Some("export")).is_err());
+    let help = registry.help(None).unwrap();
+    assert!(!help.contains("/export") && !help.contains("/sys"));
+    assert!(registry.parse("/find bug").unwrap().is_some());
+    
+    // Unknown names are reported, and the known ones still apply
+    let err = registry.restrict(Some(&names(&["find", "fnid"])), &[]).unwrap_err();
+    assert_eq!(err.to_string(), "Cannot restrict unknown command(s) /fnid");
+    assert!(registry.is_enabled("find") && registry.is_enabled("help") && registry.is_enabled("exit"));
+    assert!(!registry.is_enabled("checkpoint"));
+    
+    let err = registry.restrict(None, &names(&["exit"])).unwrap_err();
+    assert_eq!(err.to_string(), "/exit cannot be disabled");
+    assert!(registry.is_enabled("exit"));
+}
+
+#[test]
+fn test_search_and_turn_commands() {
+    let registry = CommandRegistry::new();
+    
+    assert_eq!(
+        registry.parse("/search borrow checker").unwrap(),
+        Some(CommandAction::Search { query: "borrow checker".to_string(), context: 1 })
+    );
+    assert_eq!(
+        registry.parse("/search --context 3 panic").unwrap(),
+        Some(CommandAction::Search { query: "panic".to_string(), context: 3 })
+    );
+    assert!(registry.parse("/search --context many panic").is_err());
+    assert!(registry.parse("/search").is_err());
+    
+    assert_eq!(registry.parse("/turn 12").unwrap(), Some(CommandAction::Turn { number: 12 }));
+    assert_eq!(registry.parse("/show 3").unwrap(), Some(CommandAction::Show { number: 3 }));
+    assert_eq!(registry.parse("/last").unwrap(), Some(CommandAction::Last));
+    assert!(registry.parse("/show last").is_err());
+    for invalid in ["/turn", "/turn 0", "/turn last"] {
+        assert!(registry.parse(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_find_command() {
+    let registry = CommandRegistry::new();
+
+    let action = registry.parse("/find borrow checker").unwrap();
+    assert_eq!(action, Some(CommandAction::Find {
+        query: "borrow checker".to_string(),
+        full: false,
+    }));
+
+    let action = registry.parse("/find --full \"two  spaces\"").unwrap();
+    assert_eq!(action, Some(CommandAction::Find {
+        query: "two  spaces".to_string(),
+        full: true,
+    }));
+
+    assert!(registry.parse("/find").is_err());
+}
+
+#[test]
+fn test_preview_command_keeps_the_prompt_as_typed() {
+    let registry = CommandRegistry::new();
+
+    let action = registry.parse("/preview  Summarize @\"my notes.md\"  --briefly ").unwrap();
+    assert_eq!(action, Some(CommandAction::Preview {
+        text: "Summarize @\"my notes.md\"  --briefly".to_string(),
+    }));
+
+    let action = registry.parse("/preview --verbose is a flag?").unwrap();
+    assert_eq!(action, Some(CommandAction::Preview {
+        text: "--verbose is a flag?".to_string(),
+    }));
+
+    assert!(matches!(registry.parse("/preview --help").unwrap(), Some(CommandAction::Help(_))));
+    assert!(registry.parse("/preview").is_err());
+}
+
+#[test]
+fn test_repo_map_command() {
+    let registry = CommandRegistry::new();
+
+    assert_eq!(registry.parse("/repo-map").unwrap(), Some(CommandAction::RepoMap { show: false }));
+    assert_eq!(registry.parse("/repo-map --show").unwrap(), Some(CommandAction::RepoMap { show: true }));
+    assert!(registry.parse("/repo-map src").is_err());
+}
+
+#[test]
+fn test_clipboard_commands() {
+    let registry = CommandRegistry::new();
+
+    assert_eq!(registry.parse("/copy").unwrap(), Some(CommandAction::Copy));
+    assert!(registry.parse("/copy all").is_err());
+    assert_eq!(registry.parse("/paste").unwrap(), Some(CommandAction::Paste { prompt: None }));
+    assert_eq!(
+        registry.parse("/paste --why does it fail?").unwrap(),
+        Some(CommandAction::Paste { prompt: Some("--why does it fail?".to_string()) })
+    );
+}
+
+#[test]
+fn test_edit_command() {
+    let registry = CommandRegistry::new();
+
+    assert_eq!(registry.parse("/edit").unwrap(), Some(CommandAction::Edit { text: None }));
+    assert_eq!(
+        registry.parse("/edit Review @src/main.rs:").unwrap(),
+        Some(CommandAction::Edit { text: Some("Review @src/main.rs:".to_string()) })
+    );
+}
+
+#[test]
+fn test_attach_command() {
+    let registry = CommandRegistry::new();
+
+    assert_eq!(
+        registry.parse("/attach src/main.rs").unwrap(),
+        Some(CommandAction::Attach { path: "src/main.rs".into() })
+    );
+    assert!(registry.parse("/attach").is_err());
+}
+
+#[test]
+fn test_help_command_lists_commands_and_aliases() {
+    let mut registry = CommandRegistry::new();
+    registry.register_alias("cp=/checkpoint {{1}}".parse().unwrap()).unwrap();
+
+    assert_eq!(registry.parse("/help").unwrap(), Some(CommandAction::ShowHelp { command: None }));
+    assert_eq!(
+        registry.parse("/help find").unwrap(),
+        Some(CommandAction::ShowHelp { command: Some("find".to_string()) })
+    );
+
+    let help = registry.help(None).unwrap();
+    for command in ["/help [command]", "/model [name]", "/system [file] [--reset]", "/clear", "/exit"] {
+        assert!(help.contains(command), "{} missing from {}", command, help);
+    }
+    assert!(help.contains("Aliases:\n  /cp  /checkpoint {{1}}"));
+    assert_eq!(registry.help(Some("/cp")).unwrap(), "/cp is an alias for /checkpoint {{1}}");
+}
+
+#[test]
+fn test_session_commands() {
+    let registry = CommandRegistry::new();
+
+    assert_eq!(registry.parse("/model").unwrap(), Some(CommandAction::Model { name: None }));
+    assert_eq!(
+        registry.parse("/model opus").unwrap(),
+        Some(CommandAction::Model { name: Some("opus".to_string()) })
+    );
+    assert_eq!(
+        registry.parse("/system prompts/review.md").unwrap(),
+        Some(CommandAction::System { file: Some("prompts/review.md".into()), reset: false })
+    );
+    assert_eq!(registry.parse("/system --reset").unwrap(), Some(CommandAction::System { file: None, reset: true }));
+    assert!(registry.parse("/system review.md --reset").is_err());
+    assert_eq!(registry.parse("/save").unwrap(), Some(CommandAction::Save { path: None }));
+    assert_eq!(
+        registry.parse("/save \"my notes.md\"").unwrap(),
+        Some(CommandAction::Save { path: Some("my notes.md".into()) })
+    );
+    assert_eq!(
+        registry.parse("/export").unwrap(),
+        Some(CommandAction::Export { format: ExportFormat::Json, path: None })
+    );
+    assert_eq!(
+        registry.parse("/note this answer's \"fix\" was wrong").unwrap(),
+        Some(CommandAction::Note { text: "this answer's \"fix\" was wrong".to_string() })
+    );
+    assert!(registry.parse("/note").is_err());
+    assert_eq!(registry.parse("/cd ../shop").unwrap(), Some(CommandAction::Cd { dir: "../shop".into() }));
+    assert!(registry.parse("/cd").is_err());
+    assert_eq!(registry.parse("/mcp").unwrap(), Some(CommandAction::Mcp));
+    assert_eq!(registry.parse("/rate 4").unwrap(), Some(CommandAction::Rate { rating: 4 }));
+    for invalid in ["/rate", "/rate 0", "/rate 6", "/rate 3.5", "/rate good"] {
+        assert!(registry.parse(invalid).is_err(), "{}", invalid);
+    }
+    assert_eq!(
+        registry.parse("/export handoff.json").unwrap(),
+        Some(CommandAction::Export { format: ExportFormat::Json, path: Some("handoff.json".into()) })
+    );
+    assert_eq!(
+        registry.parse("/export html debugging.html").unwrap(),
+        Some(CommandAction::Export { format: ExportFormat::Html, path: Some("debugging.html".into()) })
+    );
+    assert_eq!(
+        registry.parse("/export HTML").unwrap(),
+        Some(CommandAction::Export { format: ExportFormat::Html, path: None })
+    );
+    assert!(registry.parse("/export pdf out.pdf").unwrap_err().to_string().contains("Unknown export format 'pdf'"));
+    assert_eq!(
+        registry.parse("/persona draft").unwrap(),
+        Some(CommandAction::Persona { name: Some("draft".to_string()) })
+    );
+    assert_eq!(registry.parse("/preset").unwrap(), Some(CommandAction::Preset { name: None }));
+    assert_eq!(
+        registry.parse("/preset rust-reviewer").unwrap(),
+        Some(CommandAction::Preset { name: Some("rust-reviewer".to_string()) })
+    );
+    assert_eq!(registry.parse("/status").unwrap(), Some(CommandAction::Status));
+    assert_eq!(registry.parse("/stats").unwrap(), Some(CommandAction::Stats));
+    assert_eq!(registry.parse("/cost").unwrap(), Some(CommandAction::Stats));
+    assert_eq!(registry.parse("/clear").unwrap(), Some(CommandAction::Clear));
+    assert_eq!(registry.parse("/exit").unwrap(), Some(CommandAction::Exit));
+    assert!(registry.parse("/exit now").is_err());
+}
@@ -0,0 +1,128 @@
+//! Helpers shared by the tests of the HTTP backends
+
+#![allow(dead_code)]
+
+use claude_dialog::claude_executor::{ClaudeInvocation, ClaudeOptions};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// A request received by [`http_stub`]
+pub struct Request {
+    /// Header lines, lowercased, e.g. `x-api-key: test-key`
+    pub headers: Vec<String>,
+
+    /// The JSON body
+    pub body: Value,
+}
+
+/// A stand-in HTTP server answering `responses` one connection at a time
+///
+/// Each response is an HTTP status line and body, sent with
+/// `content_type`. Returns the server's URL and a channel receiving every
+/// request.
+pub fn http_stub(content_type: &'static str, responses: Vec<(&'static str, String)>) -> (String, Receiver<Request>) {
+    let responses = responses.into_iter().map(|(status, body)| (status, Vec::new(), body)).collect();
+    http_stub_with_headers(content_type, responses)
+}
+
+/// Like [`http_stub`], with extra header lines for each response, e.g.
+/// `Retry-After: 2`
+pub fn http_stub_with_headers(
+    content_type: &'static str,
+    responses: Vec<(&'static str, Vec<&'static str>, String)>,
+) -> (String, Receiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (requests, received) = mpsc::channel();
+
+    thread::spawn(move || {
+        for (status, headers, response) in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let (request, mut stream) = read_request(stream);
+            requests.send(request).unwrap();
+            let headers: String = headers.iter().map(|header| format!("{}\r\n", header)).collect();
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: {}\r\n{}Connection: close\r\n\r\n{}",
+                status, content_type, headers, response
+            )
+            .unwrap();
+        }
+    });
+
+    (url, received)
+}
+
+/// A stand-in HTTP server answering one request with `parts` of a body,
+/// pausing for `pause` between them
+///
+/// Returns the server's URL.
+pub fn paused_stub(content_type: &'static str, parts: Vec<String>, pause: Duration) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let (_, mut stream) = read_request(stream);
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nConnection: close\r\n\r\n", content_type).unwrap();
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                thread::sleep(pause);
+            }
+            // The client may have stopped reading
+            if stream.write_all(part.as_bytes()).is_err() {
+                break;
+            }
+        }
+    });
+
+    url
+}
+
+/// Read a request's headers and JSON body
+fn read_request(stream: TcpStream) -> (Request, TcpStream) {
+    let mut reader = BufReader::new(stream);
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap();
+        }
+        headers.push(line.trim().to_lowercase());
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    (Request { headers, body: serde_json::from_slice(&body).unwrap() }, reader.into_inner())
+}
+
+/// The URL of a port nothing listens on
+pub fn unreachable_url() -> String {
+    format!("http://{}", TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap())
+}
+
+/// A command sending `prompt` to `model`
+pub fn command(model: &str, prompt: &str) -> ClaudeInvocation {
+    ClaudeInvocation {
+        prompt: prompt.to_string(),
+        options: options(model),
+    }
+}
+
+/// Options naming `model`, for commands with further options
+pub fn options(model: &str) -> ClaudeOptions {
+    ClaudeOptions {
+        model: Some(model.to_string()),
+        ..Default::default()
+    }
+}
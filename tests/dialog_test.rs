@@ -1,5 +1,40 @@
-use claude_dialog::dialog::{DialogLoop, DialogConfig};
+#![cfg(feature = "cli")]
+
+use anyhow::Result;
+use async_trait::async_trait;
+use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeResponse, OutputFormat, SessionMode};
+use claude_dialog::dialog::{DialogLoop, DialogConfig, FlagSettings};
+use claude_dialog::export::{ConversationExport, ImportMode};
+use claude_dialog::follow_up;
+use claude_dialog::interrupt::Interrupt;
+use claude_dialog::language::Language;
+use claude_dialog::pipeline::ResponsePipeline;
+use claude_dialog::policy::Policy;
+use claude_dialog::scripted::Script;
+use claude_dialog::status::Usage;
+use mockall::mock;
 use std::io::Cursor;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+mock! {
+    Executor {}
+
+    #[async_trait]
+    impl ClaudeExecutor for Executor {
+        async fn execute(&self, command: ClaudeInvocation, pipeline: ResponsePipeline) -> Result<ClaudeResponse>;
+        async fn replay(&self, command: ClaudeInvocation) -> Result<ClaudeResponse>;
+    }
+}
+
+/// An executor answering every prompt with "Answer to <prompt>"
+fn answering_executor() -> MockExecutor {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .returning(|command, _| Ok(ClaudeResponse::new(format!("Answer to {}", command.prompt))));
+    executor
+}
 
 #[test]
 fn test_dialog_config() {
@@ -7,6 +42,7 @@ fn test_dialog_config() {
         system_prompt: Some("Test prompt".to_string()),
         append_prompt: None,
         model: None,
+        ..Default::default()
     };
     
     assert_eq!(config.system_prompt, Some("Test prompt".to_string()));
@@ -20,6 +56,7 @@ fn test_is_exit_command() {
         system_prompt: None,
         append_prompt: None,
         model: None,
+        ..Default::default()
     });
     
     assert!(dialog.is_exit_command("exit"));
@@ -40,6 +77,7 @@ fn test_read_input_empty() {
         system_prompt: None,
         append_prompt: None,
         model: None,
+        ..Default::default()
     });
     
     let result = dialog.read_input(&mut cursor).unwrap();
@@ -54,6 +92,7 @@ fn test_read_input_valid() {
         system_prompt: None,
         append_prompt: None,
         model: None,
+        ..Default::default()
     });
     
     let result = dialog.read_input(&mut cursor).unwrap();
@@ -68,8 +107,1072 @@ fn test_read_input_exit() {
         system_prompt: None,
         append_prompt: None,
         model: None,
+        ..Default::default()
     });
     
     let result = dialog.read_input(&mut cursor).unwrap();
     assert_eq!(result, Some("exit".to_string()));
-}
\ No newline at end of file
+}
+#[test]
+fn test_dialog_config_from_json() {
+    let config: DialogConfig = serde_json::from_str(
+        r#"{"model": "claude-3-opus", "aliases": ["cp=/checkpoint {{1}}"]}"#,
+    ).unwrap();
+    
+    assert_eq!(config.model, Some("claude-3-opus".to_string()));
+    assert!(config.system_prompt.is_none());
+    assert_eq!(config.aliases[0].name, "cp");
+    
+    let json = serde_json::to_value(&config).unwrap();
+    assert!(json.get("capabilities").is_none());
+}
+
+#[test]
+fn test_final_only_requires_stream_json() {
+    use claude_dialog::claude_version::{Capabilities, ClaudeVersion};
+    
+    let mut config = DialogConfig {
+        final_only: true,
+        capabilities: Capabilities::for_version(Some(ClaudeVersion::new(0, 2, 0))),
+        ..Default::default()
+    };
+    
+    let warnings = config.apply_capabilities();
+    assert!(!config.final_only);
+    assert!(warnings[0].starts_with("Ignoring --final-only"));
+}
+
+#[tokio::test]
+async fn test_prompts_are_run_by_the_executor() {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| {
+            command.prompt == "Hello"
+                && command.options.model.as_deref() == Some("haiku")
+                && command.options.session == SessionMode::Continue
+                && command.options.output == OutputFormat::Json
+        })
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Hi!")));
+    
+    let config = DialogConfig {
+        model: Some("haiku".to_string()),
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Hello").await.unwrap();
+    
+    let turns = dialog.conversation().turns();
+    assert_eq!(turns.len(), 1);
+    assert_eq!(turns[0].prompt, "Hello");
+    assert_eq!(turns[0].response.as_deref(), Some("Hi!"));
+}
+
+#[tokio::test]
+async fn test_first_prompt_resumes_the_configured_session() {
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    let resume = SessionMode::Resume("3f2a9c1e".to_string());
+    for session in [resume.clone(), SessionMode::Continue] {
+        executor
+            .expect_execute()
+            .withf(move |command, _| command.options.session == session)
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(ClaudeResponse::new("Hi")));
+    }
+    
+    let config = DialogConfig {
+        session: resume,
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Where were we?").await.unwrap();
+    dialog.submit("Go on").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_final_only_requests_stream_json() {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| command.options.output == OutputFormat::StreamJson)
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("")));
+    
+    let config = DialogConfig {
+        final_only: true,
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Fix the tests").await.unwrap();
+}
+
+#[test]
+fn test_stream_requires_partial_messages() {
+    use claude_dialog::claude_version::{Capabilities, ClaudeVersion};
+    
+    let mut config = DialogConfig {
+        stream: true,
+        capabilities: Capabilities::for_version(Some(ClaudeVersion::new(1, 0, 35))),
+        ..Default::default()
+    };
+    
+    let warnings = config.apply_capabilities();
+    assert!(!config.stream);
+    assert!(warnings[0].starts_with("Ignoring --stream: requires --include-partial-messages"));
+}
+
+#[tokio::test]
+async fn test_stream_requests_partial_messages() {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| command.options.output == OutputFormat::StreamJsonPartial)
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Hi")));
+    
+    let config = DialogConfig {
+        stream: true,
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Hello").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_slash_commands_are_not_sent() {
+    let mut executor = MockExecutor::new();
+    executor.expect_execute().never();
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit("/find anything").await.unwrap();
+    dialog.submit("/unknown").await.unwrap();
+    assert!(dialog.conversation().is_empty());
+}
+
+#[tokio::test]
+async fn test_restore_replays_through_the_executor() {
+    let mut executor = answering_executor();
+    executor
+        .expect_replay()
+        .withf(|command| command.prompt == "one" && command.options.session == SessionMode::Fresh)
+        .times(1)
+        .returning(|_| Ok(ClaudeResponse::new("")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit("one").await.unwrap();
+    dialog.submit("/checkpoint first").await.unwrap();
+    dialog.submit("two").await.unwrap();
+    dialog.submit("/restore first").await.unwrap();
+    
+    let turns = dialog.conversation().turns();
+    assert_eq!(turns.len(), 1);
+    assert_eq!(turns[0].response.as_deref(), Some("Answer to one"));
+}
+
+#[tokio::test]
+async fn test_restore_replays_against_the_restored_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    std::fs::write(&notes, "Original plan").unwrap();
+    
+    let mut executor = answering_executor();
+    executor
+        .expect_replay()
+        .withf(|command| command.prompt.contains("Original plan") && !command.prompt.contains("Rewritten plan"))
+        .times(1)
+        .returning(|_| Ok(ClaudeResponse::new("")));
+    let config = DialogConfig {
+        working_dir: Some(dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Review @notes.md").await.unwrap();
+    dialog.submit("/checkpoint first --files").await.unwrap();
+    std::fs::write(&notes, "Rewritten plan").unwrap();
+    dialog.submit("two").await.unwrap();
+    dialog.submit("/restore first").await.unwrap();
+    
+    assert_eq!(std::fs::read_to_string(&notes).unwrap(), "Original plan");
+    assert_eq!(dialog.conversation().turns().len(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_restores_keep_the_conversation() {
+    let mut executor = answering_executor();
+    executor
+        .expect_replay()
+        .returning(|_| Err(anyhow::anyhow!("claude is not installed")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit("one").await.unwrap();
+    dialog.submit("/checkpoint first").await.unwrap();
+    dialog.submit("two").await.unwrap();
+    dialog.submit("/restore first").await.unwrap();
+    
+    assert_eq!(dialog.conversation().turns().len(), 2);
+}
+
+#[tokio::test]
+async fn test_executor_errors_are_returned() {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .returning(|_, _| Err(anyhow::anyhow!("claude is not installed")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    let err = dialog.submit("Hello").await.unwrap_err();
+    assert_eq!(err.to_string(), "claude is not installed");
+}
+
+#[tokio::test]
+async fn test_failed_responses_are_kept_and_reported() {
+    let mut executor = MockExecutor::new();
+    executor.expect_execute().returning(|_, _| {
+        Ok(ClaudeResponse {
+            text: "Partial answ".to_string(),
+            stderr: "Error: connection reset\n".to_string(),
+            exit_code: Some(2),
+            ..ClaudeResponse::new("")
+        })
+    });
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    let err = dialog.submit("Hello").await.unwrap_err();
+    assert_eq!(err.to_string(), "Claude command failed with exit code 2: Error: connection reset");
+    assert_eq!(dialog.conversation().turns()[0].response.as_deref(), Some("Partial answ"));
+}
+
+fn rate_limited(stderr: &str) -> ClaudeResponse {
+    ClaudeResponse {
+        text: String::new(),
+        stderr: stderr.to_string(),
+        exit_code: Some(1),
+        ..ClaudeResponse::new("")
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limited_prompts_are_retried() {
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(rate_limited("API Error: 429 rate_limit_error, retry-after: 0\n")));
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .withf(|command, _| command.prompt == "Hello")
+        .returning(|_, _| Ok(ClaudeResponse::new("Hi")));
+    
+    let config = DialogConfig { rate_limit_retries: 3, ..Default::default() };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Hello").await.unwrap();
+    assert_eq!(dialog.conversation().turns()[0].response.as_deref(), Some("Hi"));
+}
+
+#[tokio::test]
+async fn test_rate_limit_retries_are_limited() {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .times(3)
+        .returning(|_, _| Ok(rate_limited("Rate limit exceeded, try again in 0s\n")));
+    
+    let config = DialogConfig { rate_limit_retries: 2, ..Default::default() };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    let err = dialog.submit("Hello").await.unwrap_err();
+    assert!(err.to_string().contains("Rate limit exceeded"));
+}
+
+#[tokio::test]
+async fn test_long_rate_limits_are_not_waited_for() {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .times(1)
+        .returning(|_, _| Ok(rate_limited("429 Too Many Requests, retry after 2 hours\n")));
+    
+    let config = DialogConfig { rate_limit_retries: 3, ..Default::default() };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    assert!(dialog.submit("Hello").await.is_err());
+}
+
+#[tokio::test]
+async fn test_model_and_system_prompt_can_be_switched() {
+    let dir = tempfile::tempdir().unwrap();
+    let prompt = dir.path().join("review.md");
+    std::fs::write(&prompt, "Review code strictly.").unwrap();
+    
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| {
+            command.options.model.as_deref() == Some("opus")
+                && command.options.system_prompt.as_deref() == Some("Review code strictly.")
+                && command.options.append_prompt.is_none()
+        })
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Looks fine")));
+    
+    let config = DialogConfig {
+        model: Some("haiku".to_string()),
+        append_prompt: Some("Be brief.".to_string()),
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("/model opus").await.unwrap();
+    dialog.submit(&format!("/system {}", prompt.display())).await.unwrap();
+    dialog.submit("Review this").await.unwrap();
+    
+    dialog.submit("/model default").await.unwrap();
+    dialog.submit("/system --reset").await.unwrap();
+    assert!(dialog.config().model.is_none());
+    assert!(dialog.config().system_prompt.is_none());
+}
+
+#[tokio::test]
+async fn test_preset_replaces_the_system_prompt() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("tutor.md"), "Explain like a patient teacher.").unwrap();
+    
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| command.options.system_prompt.as_deref() == Some("Explain like a patient teacher."))
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Sure")));
+    
+    let config = DialogConfig {
+        prompts_dir: Some(dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("/preset").await.unwrap();
+    dialog.submit("/preset reviewer").await.unwrap();
+    dialog.submit("/preset tutor").await.unwrap();
+    dialog.submit("What is a lifetime?").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_listed_presets_are_picked_by_number() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("reviewer.md"), "Review strictly.").unwrap();
+    std::fs::write(dir.path().join("tutor.md"), "Explain patiently.").unwrap();
+    
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| command.prompt == "2" && command.options.system_prompt.as_deref() == Some("Explain patiently."))
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Two")));
+    
+    let config = DialogConfig {
+        prompts_dir: Some(dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("/preset").await.unwrap();
+    dialog.submit("2").await.unwrap();
+    assert_eq!(dialog.config().system_prompt.as_deref(), Some("Explain patiently."));
+    
+    // The list only applies to the input right after it
+    dialog.submit("2").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_system_prompt_front_matter_is_applied() {
+    let dir = tempfile::tempdir().unwrap();
+    let prompt = dir.path().join("review.md");
+    std::fs::write(&prompt, "---\nmodel: opus\nallowed_tools: []\n---\nReview code strictly.").unwrap();
+    
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| {
+            command.options.system_prompt.as_deref() == Some("Review code strictly.")
+                && command.options.model.as_deref() == Some("opus")
+                && command.options.allowed_tools == Some(Vec::new())
+        })
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Looks fine")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit(&format!("/system {}", prompt.display())).await.unwrap();
+    dialog.submit("Review this").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_front_matter_does_not_override_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    let prompt = dir.path().join("review.md");
+    std::fs::write(&prompt, "---\nmodel: opus\nallowed_tools: []\ntemperature: 0.9\n---\nReview code strictly.").unwrap();
+    
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| {
+            command.options.system_prompt.as_deref() == Some("Review code strictly.")
+                && command.options.model.as_deref() == Some("sonnet")
+                && command.options.allowed_tools == Some(Vec::new())
+                && command.options.temperature == Some(0.2)
+        })
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Looks fine")));
+    
+    // --model and --temperature were given, the tools were not
+    let config = DialogConfig {
+        model: Some("sonnet".to_string()),
+        temperature: Some(0.2),
+        flag_settings: FlagSettings {
+            model: true,
+            temperature: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit(&format!("/system {}", prompt.display())).await.unwrap();
+    dialog.submit("Review this").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_clear_starts_a_new_conversation() {
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    for session in [SessionMode::Continue, SessionMode::Fresh] {
+        executor
+            .expect_execute()
+            .withf(move |command, _| command.options.session == session)
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(ClaudeResponse::new("Hi")));
+    }
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit("Hello").await.unwrap();
+    dialog.submit("/clear").await.unwrap();
+    assert!(dialog.conversation().is_empty());
+    dialog.submit("Hello again").await.unwrap();
+    assert_eq!(dialog.conversation().len(), 1);
+}
+
+#[tokio::test]
+async fn test_exit_command_finishes_the_dialog() {
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(MockExecutor::new()));
+    assert!(!dialog.is_finished());
+    dialog.submit("/exit").await.unwrap();
+    assert!(dialog.is_finished());
+}
+
+#[tokio::test]
+async fn test_save_writes_the_whole_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.md");
+    
+    let config = DialogConfig {
+        model: Some("opus".to_string()),
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(answering_executor()));
+    dialog.submit("First question").await.unwrap();
+    dialog.submit("/clear").await.unwrap();
+    dialog.submit("Second question").await.unwrap();
+    dialog.submit(&format!("/save {}", path.display())).await.unwrap();
+    
+    assert_eq!(dialog.conversation().len(), 1);
+    assert_eq!(dialog.transcript().len(), 2);
+    let markdown = std::fs::read_to_string(&path).unwrap();
+    assert!(markdown.contains("First question\n\n## Claude (opus)\n\nAnswer to First question\n"));
+    assert!(markdown.contains("Second question\n\n## Claude (opus)\n\nAnswer to Second question\n"));
+}
+
+#[tokio::test]
+async fn test_incognito_sessions_save_no_transcript() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.md");
+    
+    let config = DialogConfig {
+        incognito: true,
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(answering_executor()));
+    dialog.submit("Hello").await.unwrap();
+    dialog.submit(&format!("/save {}", path.display())).await.unwrap();
+    assert!(!path.exists());
+}
+
+/// Export a conversation of two answered prompts and load it again
+async fn exported_conversation() -> ConversationExport {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("handoff.json");
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(answering_executor()));
+    dialog.submit("one").await.unwrap();
+    dialog.submit("two").await.unwrap();
+    dialog.submit(&format!("/export {}", path.display())).await.unwrap();
+    ConversationExport::load(&path).unwrap()
+}
+
+#[tokio::test]
+async fn test_export_html_writes_the_transcript_page() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared/debugging.html");
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(answering_executor()));
+    assert!(dialog.submit(&format!("/export html {}", path.display())).await.is_ok());
+    assert!(!path.exists());
+    
+    dialog.submit("Why does <T> fail?").await.unwrap();
+    dialog.submit(&format!("/export html {}", path.display())).await.unwrap();
+    let page = std::fs::read_to_string(&path).unwrap();
+    assert!(page.contains("<div class=\"prompt\">Why does &lt;T&gt; fail?</div>"));
+    assert!(page.contains("Answer to Why does &lt;T&gt; fail?"));
+}
+
+#[tokio::test]
+async fn test_imported_prompts_are_replayed() {
+    let export = exported_conversation().await;
+    assert_eq!(export.turns[1].response.as_deref(), Some("Answer to two"));
+    
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    for (prompt, session) in [("one", SessionMode::Fresh), ("two", SessionMode::Continue)] {
+        executor
+            .expect_replay()
+            .withf(move |command| command.prompt == prompt && command.options.session == session)
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(ClaudeResponse::new("")));
+    }
+    executor
+        .expect_execute()
+        .withf(|command, _| command.prompt == "three" && command.options.session == SessionMode::Continue)
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Answer to three")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.import(&export, ImportMode::Replay).await.unwrap();
+    dialog.submit("three").await.unwrap();
+    
+    assert_eq!(dialog.conversation().len(), 3);
+    assert_eq!(dialog.transcript().len(), 1);
+}
+
+#[tokio::test]
+async fn test_imported_summary_is_sent_with_the_next_prompt() {
+    let export = exported_conversation().await;
+    
+    let mut executor = MockExecutor::new();
+    executor.expect_replay().never();
+    executor
+        .expect_execute()
+        .withf(|command, _| {
+            command.options.session == SessionMode::Fresh
+                && command.prompt.starts_with("three\n\n")
+                && command.prompt.contains("\nUser: one\n\nAssistant: Answer to one\n")
+        })
+        .times(1)
+        .returning(|_, _| Ok(ClaudeResponse::new("Answer to three")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.import(&export, ImportMode::Summary).await.unwrap();
+    assert_eq!(dialog.status().queued, 1);
+    dialog.submit("three").await.unwrap();
+    assert_eq!(dialog.conversation().len(), 3);
+}
+
+#[tokio::test]
+async fn test_notes_are_kept_but_never_sent() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("handoff.json");
+    
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| !command.prompt.contains("wrong"))
+        .times(2)
+        .returning(|command, _| Ok(ClaudeResponse::new(format!("Answer to {}", command.prompt))));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit("/note too early").await.unwrap();
+    dialog.submit("one").await.unwrap();
+    dialog.submit("/note this answer was wrong").await.unwrap();
+    dialog.submit("two").await.unwrap();
+    dialog.submit(&format!("/export {}", path.display())).await.unwrap();
+    
+    assert_eq!(dialog.conversation().turns()[0].notes, ["this answer was wrong"]);
+    assert!(dialog.conversation().turns()[1].notes.is_empty());
+    assert_eq!(dialog.transcript().entries()[0].turn.notes, ["this answer was wrong"]);
+    assert!(dialog.status().last_error.unwrap().1.contains("no turn to attach a note to"));
+    
+    let export = ConversationExport::load(&path).unwrap();
+    assert_eq!(export.turns[0].notes, ["this answer was wrong"]);
+}
+
+#[tokio::test]
+async fn test_ratings_are_kept_with_their_turns() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rated.json");
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(answering_executor()));
+    dialog.submit("/rate 3").await.unwrap();
+    assert!(dialog.status().last_error.unwrap().1.contains("no response to rate"));
+    
+    dialog.submit("one").await.unwrap();
+    dialog.submit("/rate 2").await.unwrap();
+    dialog.submit("/rate 4").await.unwrap();
+    dialog.submit(&format!("/export {}", path.display())).await.unwrap();
+    dialog.submit("/clear").await.unwrap();
+    dialog.submit("two").await.unwrap();
+    dialog.submit("/rate 5").await.unwrap();
+    
+    let export = ConversationExport::load(&path).unwrap();
+    assert_eq!(export.turns[0].rating, Some(4));
+    assert_eq!(dialog.conversation().turns()[0].rating, Some(5));
+    
+    let status = dialog.status();
+    assert_eq!(status.ratings, [4, 5]);
+    assert!(status.to_string().contains("Ratings: 2 responses, average 4.5 (5: 1, 4: 1)\n"));
+}
+
+#[tokio::test]
+async fn test_empty_and_incognito_conversations_are_not_exported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("handoff.json");
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(answering_executor()));
+    dialog.submit(&format!("/export {}", path.display())).await.unwrap();
+    assert!(!path.exists());
+    
+    let config = DialogConfig {
+        incognito: true,
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(answering_executor()));
+    dialog.submit("Hello").await.unwrap();
+    dialog.submit(&format!("/export {}", path.display())).await.unwrap();
+    assert!(!path.exists());
+}
+
+#[cfg(feature = "ollama")]
+#[tokio::test]
+async fn test_personas_route_turns_to_their_backend() {
+    use claude_dialog::claude_executor::Backend;
+    
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| command.options.backend == Backend::Claude && command.prompt == "Hello")
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(ClaudeResponse::new("Hi from Claude")));
+    executor
+        .expect_execute()
+        .withf(|command, _| {
+            command.options.backend == Backend::Ollama
+                && command.options.model.as_deref() == Some("llama3")
+                && command.prompt.contains("User: Hello\n\nAssistant: Hi from Claude\n")
+                && command.prompt.ends_with("---\n\nDraft a poem")
+        })
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(ClaudeResponse::new("Roses are red")));
+    executor
+        .expect_execute()
+        .withf(|command, _| {
+            command.options.backend == Backend::Claude
+                && command.options.session == SessionMode::Continue
+                && command.options.model.as_deref() == Some("opus")
+                && !command.prompt.contains("User: Hello")
+                && command.prompt.contains("User: Draft a poem\n\nAssistant: Roses are red\n")
+        })
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(ClaudeResponse::new("Roses are crimson")));
+    executor
+        .expect_execute()
+        .withf(|command, _| command.options.backend == Backend::Claude && command.prompt == "Thanks")
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(ClaudeResponse::new("You're welcome")));
+    
+    let config = DialogConfig {
+        personas: vec!["draft=ollama:llama3".parse().unwrap(), "final=claude:opus".parse().unwrap()],
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Hello").await.unwrap();
+    dialog.submit("/persona draft").await.unwrap();
+    dialog.submit("Draft a poem").await.unwrap();
+    dialog.submit("/persona final").await.unwrap();
+    dialog.submit("Polish it").await.unwrap();
+    dialog.submit("Thanks").await.unwrap();
+    
+    assert_eq!(dialog.conversation().len(), 4);
+    assert!(dialog.transcript().to_markdown().contains("## Claude (ollama:llama3)\n\nRoses are red\n"));
+    
+    dialog.submit("/persona default").await.unwrap();
+    assert_eq!(dialog.config().backend, Backend::Claude);
+    assert!(dialog.config().model.is_none());
+}
+
+#[tokio::test]
+async fn test_policy_stops_prompts_at_its_cost_limit() {
+    let mut executor = MockExecutor::new();
+    executor.expect_execute().times(2).returning(|_, _| {
+        Ok(ClaudeResponse {
+            usage: Some(Usage { input_tokens: 1000, output_tokens: 200, cost_usd: Some(0.6), ..Default::default() }),
+            truncated: false,
+            ..ClaudeResponse::new("Done")
+        })
+    });
+    let config = DialogConfig {
+        policy: Policy::parse("max_session_cost_usd = 1.0").unwrap(),
+        ..Default::default()
+    };
+    
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Fix the tests").await.unwrap();
+    dialog.submit("And the docs").await.unwrap();
+    let error = dialog.submit("One more").await.unwrap_err();
+    
+    assert_eq!(
+        error.to_string(),
+        "This session has reached the cost limit of $1.00 set by the policy (estimated $1.2000 so far)"
+    );
+    assert_eq!(dialog.conversation().len(), 2);
+}
+
+#[tokio::test]
+async fn test_replays_count_toward_the_cost_limit() {
+    let mut executor = MockExecutor::new();
+    executor.expect_execute().times(2).returning(|_, _| {
+        Ok(ClaudeResponse {
+            usage: Some(Usage { input_tokens: 500, output_tokens: 100, cost_usd: Some(0.3), ..Default::default() }),
+            truncated: false,
+            ..ClaudeResponse::new("Done")
+        })
+    });
+    executor
+        .expect_replay()
+        .times(1)
+        .returning(|_| {
+            Ok(ClaudeResponse {
+                usage: Some(Usage { input_tokens: 1000, output_tokens: 200, cost_usd: Some(0.6), ..Default::default() }),
+                ..ClaudeResponse::new("")
+            })
+        });
+    let config = DialogConfig {
+        policy: Policy::parse("max_session_cost_usd = 1.0").unwrap(),
+        ..Default::default()
+    };
+    
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("one").await.unwrap();
+    dialog.submit("/checkpoint first").await.unwrap();
+    dialog.submit("two").await.unwrap();
+    dialog.submit("/restore first").await.unwrap();
+    let error = dialog.submit("three").await.unwrap_err();
+    
+    assert_eq!(
+        error.to_string(),
+        "This session has reached the cost limit of $1.00 set by the policy (estimated $1.2000 so far)"
+    );
+    assert_eq!(dialog.status().usage.cost_usd, Some(1.2));
+}
+
+#[tokio::test]
+async fn test_status_adds_up_the_reported_usage() {
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    for cost in [Some(0.02), None] {
+        executor
+            .expect_execute()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(move |_, _| {
+                Ok(ClaudeResponse {
+                    session_id: cost.map(|_| "3f2a9c1e".to_string()),
+                    usage: cost.map(|cost| Usage { input_tokens: 1000, output_tokens: 200, cost_usd: Some(cost), ..Default::default() }),
+                    truncated: false,
+                    ..ClaudeResponse::new("Done")
+                })
+            });
+    }
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit("Fix the tests").await.unwrap();
+    dialog.submit("/persona missing").await.unwrap();
+    dialog.submit("Thanks").await.unwrap();
+    
+    let status = dialog.status();
+    assert_eq!(status.session, "3f2a9c1e");
+    assert_eq!(status.turns, 2);
+    assert_eq!(status.usage, Usage { input_tokens: 1000, output_tokens: 200, cost_usd: Some(0.02), ..Default::default() });
+    assert_eq!(status.last_error.as_ref().unwrap().1, "No persona named 'missing'; /persona lists them");
+    assert!(status.to_string().contains("Tokens: 1000 in, 200 out (1 of 2 prompts reported)\n"));
+    
+    dialog.submit("/clear").await.unwrap();
+    let status = dialog.status();
+    assert_eq!(status.session, "new conversation with the next prompt");
+    assert_eq!(status.prompts_sent, 2);
+}
+
+#[tokio::test]
+async fn test_cancelled_turn_returns_to_the_prompt() {
+    let interrupt = Arc::new(OnceLock::<Interrupt>::new());
+    let canceller = interrupt.clone();
+    
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(move |_, _| {
+            // Ctrl+C reaches the claude process too, which dies of it
+            assert!(canceller.get().unwrap().cancel());
+            Ok(ClaudeResponse { exit_code: None, ..ClaudeResponse::new("Partial") })
+        });
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(ClaudeResponse::new("Short answer")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    interrupt.set(dialog.interrupt()).unwrap();
+    
+    dialog.submit("Write a long essay").await.unwrap();
+    assert_eq!(dialog.conversation().turns()[0].response, None);
+    assert!(dialog.status().last_error.is_none());
+    
+    dialog.submit("Keep it short").await.unwrap();
+    assert_eq!(dialog.conversation().turns()[1].response.as_deref(), Some("Short answer"));
+}
+
+#[tokio::test]
+async fn test_ctrl_c_between_prompts_stops_a_batch_after_saving_it() {
+    let interrupt: Arc<OnceLock<Interrupt>> = Arc::default();
+    let quitter = Arc::clone(&interrupt);
+    let mut executor = MockExecutor::new();
+    executor.expect_execute().times(1).returning(move |_, _| {
+        // Ctrl+C pressed while no turn is guarded, e.g. as the answer arrives
+        assert!(!quitter.get().unwrap().request_quit());
+        Ok(ClaudeResponse::new("First answer"))
+    });
+    
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("report.json");
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    interrupt.set(dialog.interrupt()).unwrap();
+    let prompts = ["one".to_string(), "two".to_string()];
+    let code = dialog.run_batch(&dir.path().join("prompts.yaml"), &prompts, &output, 1).await.unwrap();
+    
+    assert_eq!(code, 130);
+    let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(report["results"].as_array().unwrap().len(), 1);
+    assert_eq!(dialog.conversation().len(), 1);
+}
+
+#[tokio::test]
+async fn test_prompts_in_another_language_ask_for_replies_in_it() {
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .withf(|command, _| command.prompt.ends_with("zu ändern?\n\n(Reply in German, the language of this message.)"))
+        .returning(|_, _| Ok(ClaudeResponse::new("Mit einem Mock.")));
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .withf(|command, _| command.prompt == "And how do I do that with the real file?")
+        .returning(|_, _| Ok(ClaudeResponse::new("With a temporary directory.")));
+    
+    let config = DialogConfig { language: Some(Language::English), ..Default::default() };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("Wie kann ich diese Funktion testen, ohne die Datei zu ändern?").await.unwrap();
+    dialog.submit("And how do I do that with the real file?").await.unwrap();
+    
+    // The hint is not part of the conversation
+    assert_eq!(dialog.conversation().turns()[0].prompt, "Wie kann ich diese Funktion testen, ohne die Datei zu ändern?");
+}
+
+#[tokio::test]
+async fn test_turn_metadata_is_sent_but_not_recorded() {
+    let cwd = std::env::current_dir().unwrap();
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .times(1)
+        .withf(move |command, _| {
+            let (prompt, footer) = command.prompt.split_once("\n\n").unwrap();
+            prompt == "What changed since yesterday?"
+                && footer.starts_with("(Current time: ")
+                && footer.ends_with(&format!(", CWD: {}, OS: {})", cwd.display(), std::env::consts::OS))
+        })
+        .returning(|_, _| Ok(ClaudeResponse::new("Nothing yet.")));
+    
+    let config = DialogConfig { turn_metadata: true, ..Default::default() };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("What changed since yesterday?").await.unwrap();
+    
+    assert_eq!(dialog.conversation().turns()[0].prompt, "What changed since yesterday?");
+}
+
+#[tokio::test]
+async fn test_transcript_records_what_prompts_were_sent_with() {
+    let config = DialogConfig {
+        system_prompt: Some("Review code strictly.".to_string()),
+        system_prompt_source: Some("review.md (--system-prompt)".to_string()),
+        language: Some(Language::English),
+        turn_metadata: true,
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(answering_executor()));
+    dialog.submit("/repo-map").await.unwrap();
+    dialog.submit("Wie kann ich diese Funktion testen, ohne die Datei zu ändern?").await.unwrap();
+    dialog.submit("/system --reset").await.unwrap();
+    dialog.submit("And now?").await.unwrap();
+    
+    let entries = dialog.transcript().entries();
+    assert_eq!(
+        entries[0].provenance.to_string(),
+        "system prompt review.md (--system-prompt); repository map; language hint (German); turn metadata"
+    );
+    assert_eq!(entries[1].provenance.to_string(), "turn metadata");
+    assert!(dialog.transcript().to_markdown().contains("\n*Sent with: turn metadata*\n"));
+}
+
+#[tokio::test]
+async fn test_attached_files_are_sent_with_the_next_prompt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("parser.rs");
+    std::fs::write(&path, "fn parse() {}").unwrap();
+    let binary = dir.path().join("parser.o");
+    std::fs::write(&binary, b"\0\x01").unwrap();
+    
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    let expected = format!("Review it\n\nAttached file {}:\n\n```rs\nfn parse() {{}}\n```", path.display());
+    executor
+        .expect_execute()
+        .withf(move |command, _| command.prompt == expected)
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(ClaudeResponse::new("Looks fine")));
+    executor
+        .expect_execute()
+        .withf(|command, _| command.prompt == "Thanks")
+        .times(1)
+        .in_sequence(&mut sequence)
+        .returning(|_, _| Ok(ClaudeResponse::new("You're welcome")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit(&format!("/attach {}", binary.display())).await.unwrap();
+    dialog.submit(&format!("/attach {}", path.display())).await.unwrap();
+    assert_eq!(dialog.status().queued, 1);
+    dialog.submit("Review it").await.unwrap();
+    dialog.submit("Thanks").await.unwrap();
+    
+    assert_eq!(
+        dialog.transcript().entries()[0].provenance.layers,
+        [format!("attached file {}", path.display())]
+    );
+}
+
+#[tokio::test]
+async fn test_follow_up_suggestions_are_picked_by_number() {
+    let mut executor = MockExecutor::new();
+    let mut sequence = mockall::Sequence::new();
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .withf(|command, _| command.prompt == "How do I read a file?")
+        .returning(|_, _| Ok(ClaudeResponse { session_id: Some("3f2a9c1e".to_string()), ..ClaudeResponse::new("Use fs::read_to_string.") }));
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .withf(|command, _| {
+            command.options.model.as_deref() == Some("haiku")
+                && command.options.session == SessionMode::Fresh
+                && command.options.allowed_tools == Some(Vec::new())
+                && command.options.working_dir.as_ref().is_some_and(|dir| {
+                    dir.file_name().unwrap().to_string_lossy().starts_with(follow_up::DIR_PREFIX)
+                })
+                && command.prompt.ends_with("Question:\nHow do I read a file?\n\nAnswer:\nUse fs::read_to_string.")
+        })
+        .returning(|_, _| {
+            Ok(ClaudeResponse {
+                usage: Some(Usage { input_tokens: 300, output_tokens: 20, cost_usd: Some(0.001), ..Default::default() }),
+                ..ClaudeResponse::new("1. What if it is not UTF-8?\n2. How do I read it line by line?\n")
+            })
+        });
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .withf(|command, _| {
+            command.prompt == "How do I read it line by line?"
+                && command.options.session == SessionMode::Continue
+        })
+        .returning(|_, _| Ok(ClaudeResponse::new("Use BufRead::lines.")));
+    executor
+        .expect_execute()
+        .times(1)
+        .in_sequence(&mut sequence)
+        .withf(|command, _| command.options.session == SessionMode::Fresh)
+        .returning(|_, _| Ok(ClaudeResponse::new("")));
+    
+    let config = DialogConfig { suggest_follow_ups: true, ..Default::default() };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(executor));
+    dialog.submit("How do I read a file?").await.unwrap();
+    // The suggestions are asked for in the background
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    dialog.submit("2").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    
+    // Suggestions are not part of the conversation
+    let prompts: Vec<_> = dialog.conversation().turns().iter().map(|turn| turn.prompt.as_str()).collect();
+    assert_eq!(prompts, ["How do I read a file?", "How do I read it line by line?"]);
+    // But their cost is
+    assert_eq!(dialog.status().usage.input_tokens, 300);
+}
+
+#[tokio::test]
+async fn test_no_follow_up_suggestions_from_test_scripts() {
+    let script = Script::parse("- response: First\n- response: Second\n").unwrap();
+    let config = DialogConfig { suggest_follow_ups: true, test_script: Some(script), ..Default::default() };
+    let mut dialog = DialogLoop::new(config);
+    dialog.submit("Hello").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    dialog.submit("Again").await.unwrap();
+    
+    // The request for suggestions did not take a response of the script
+    assert_eq!(dialog.conversation().turns()[1].response.as_deref(), Some("Second"));
+}
+
+#[tokio::test]
+async fn test_no_language_hints_without_a_configured_language() {
+    let mut executor = MockExecutor::new();
+    executor
+        .expect_execute()
+        .withf(|command, _| !command.prompt.contains("Reply in"))
+        .returning(|_, _| Ok(ClaudeResponse::new("Mit einem Mock.")));
+    
+    let mut dialog = DialogLoop::with_executor(DialogConfig::default(), Box::new(executor));
+    dialog.submit("Wie kann ich diese Funktion testen, ohne die Datei zu ändern?").await.unwrap();
+}
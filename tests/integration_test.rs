@@ -1,11 +1,73 @@
+#![cfg(feature = "cli")]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::NamedTempFile;
 use std::io::Write;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Command for the binary with its own application directories
+///
+/// Keeps files out of the user's directories and prevents a journal left
+/// behind by one test from triggering the recovery prompt in another. On
+/// Unix a stand-in `claude` that prints nothing is put on `PATH`, so startup
+/// validation passes without the real CLI installed.
+fn claude_dialog() -> Command {
+    Command::from_std(claude_dialog_process())
+}
+
+/// [`claude_dialog`] as a process to spawn, for tests that signal it
+fn claude_dialog_process() -> std::process::Command {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let state_dir = format!(
+        "{}/state-{}-{}",
+        env!("CARGO_TARGET_TMPDIR"),
+        std::process::id(),
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    
+    let mut cmd = std::process::Command::new(assert_cmd::cargo::cargo_bin("claude-dialog"));
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", format!("{}/config", state_dir))
+        .env("CLAUDE_DIALOG_DATA_DIR", format!("{}/data", state_dir))
+        .env("CLAUDE_DIALOG_CACHE_DIR", format!("{}/cache", state_dir))
+        .env("CLAUDE_DIALOG_STATE_DIR", &state_dir);
+    #[cfg(unix)]
+    cmd.env("PATH", path_with(default_claude()));
+    cmd
+}
+
+/// Directory holding a silent stand-in `claude` shared by all tests
+#[cfg(unix)]
+fn default_claude() -> &'static std::path::Path {
+    static DIR: std::sync::OnceLock<tempfile::TempDir> = std::sync::OnceLock::new();
+    DIR.get_or_init(|| fake_claude(r#"[ "$1" = "--version" ] && echo "1.0.35 (Claude Code)"; exit 0"#))
+        .path()
+}
+
+/// Install a fake `claude` executable running the given shell script body
+///
+/// Returns the directory to prepend to `PATH`.
+#[cfg(unix)]
+fn fake_claude(script: &str) -> tempfile::TempDir {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("claude");
+    fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    dir
+}
+
+/// `PATH` with the given directory in front
+#[cfg(unix)]
+fn path_with(dir: &std::path::Path) -> String {
+    format!("{}:{}", dir.display(), std::env::var("PATH").unwrap_or_default())
+}
 
 #[test]
 fn test_help_command() {
-    let mut cmd = Command::cargo_bin("claude-dialog").unwrap();
+    let mut cmd = claude_dialog();
     cmd.arg("--help");
     
     cmd.assert()
@@ -15,7 +77,7 @@ fn test_help_command() {
 
 #[test]
 fn test_version_command() {
-    let mut cmd = Command::cargo_bin("claude-dialog").unwrap();
+    let mut cmd = claude_dialog();
     cmd.arg("--version");
     
     cmd.assert()
@@ -25,7 +87,7 @@ fn test_version_command() {
 
 #[test]
 fn test_exit_command() {
-    let mut cmd = Command::cargo_bin("claude-dialog").unwrap();
+    let mut cmd = claude_dialog();
     cmd.write_stdin("exit\n");
     
     cmd.assert()
@@ -37,7 +99,7 @@ fn test_exit_command() {
 
 #[test]
 fn test_quit_command() {
-    let mut cmd = Command::cargo_bin("claude-dialog").unwrap();
+    let mut cmd = claude_dialog();
     cmd.write_stdin("quit\n");
     
     cmd.assert()
@@ -52,7 +114,7 @@ fn test_with_system_prompt_file() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(temp_file, "You are a helpful assistant").unwrap();
     
-    let mut cmd = Command::cargo_bin("claude-dialog").unwrap();
+    let mut cmd = claude_dialog();
     cmd.arg("--system-prompt")
         .arg(temp_file.path())
         .write_stdin("exit\n");
@@ -65,7 +127,7 @@ fn test_with_system_prompt_file() {
 
 #[test]
 fn test_with_model() {
-    let mut cmd = Command::cargo_bin("claude-dialog").unwrap();
+    let mut cmd = claude_dialog();
     cmd.arg("--model")
         .arg("claude-3-opus")
         .write_stdin("exit\n");
@@ -73,4 +135,1983 @@ fn test_with_model() {
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Model: claude-3-opus"));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_checkpoint_commands_are_handled_locally() {
+    let mut cmd = claude_dialog();
+    cmd.write_stdin("/checkpoint start\n/restore start\n/restore missing\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Checkpoint 'start' saved at turn 0"))
+        .stdout(predicate::str::contains("Restored checkpoint 'start' at turn 0"))
+        .stderr(predicate::str::contains("No checkpoint named 'missing'"));
+}
+
+
+#[test]
+fn test_command_usage_errors_are_not_sent() {
+    let mut cmd = claude_dialog();
+    cmd.write_stdin("/checkpoint\n/restore --help\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Claude>").not())
+        .stdout(predicate::str::contains("Usage: /restore <name>"))
+        .stderr(predicate::str::contains("Missing argument <name>"));
+}
+
+#[test]
+fn test_alias_expands_to_command() {
+    let mut cmd = claude_dialog();
+    cmd.arg("--alias")
+        .arg("cp=/checkpoint {{1}}")
+        .write_stdin("/cp start\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Checkpoint 'start' saved at turn 0"));
+}
+
+#[test]
+fn test_end_of_input_exits() {
+    let mut cmd = claude_dialog();
+    cmd.write_stdin("");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Exiting conversation"));
+}
+
+#[test]
+fn test_recover_unsaved_session() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let journal = state_dir.path().join("journal-20240101-103200-1.jsonl");
+    fs::write(
+        &journal,
+        concat!(
+            "{\"event\":\"start\",\"timestamp\":\"2024-01-01T10:32:00+00:00\"}\n",
+            "{\"event\":\"turn\",\"timestamp\":\"2024-01-01T10:33:00+00:00\",\"prompt\":\"hello\"}\n",
+        ),
+    )
+    .unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .write_stdin("y\n/checkpoint recovered\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Recover unsaved session from"))
+        .stdout(predicate::str::contains("(1 turns)"))
+        .stdout(predicate::str::contains("Recovered 1 turn(s)"))
+        .stdout(predicate::str::contains("Checkpoint 'recovered' saved at turn 1"));
+    
+    assert!(!journal.exists());
+    // Only the input history is left in the state directory
+    let names: Vec<_> = fs::read_dir(state_dir.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(names, ["history"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_recover_flag_resumes_the_claude_session() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let journal = state_dir.path().join("journal-20240101-103200-1.jsonl");
+    fs::write(
+        &journal,
+        concat!(
+            "{\"event\":\"start\",\"timestamp\":\"2024-01-01T10:32:00+00:00\",\"version\":1}\n",
+            "{\"event\":\"turn\",\"timestamp\":\"2024-01-01T10:33:00+00:00\",\"prompt\":\"hello\"}\n",
+            "{\"event\":\"session\",\"id\":\"3f2a9c1e\"}\n",
+        ),
+    )
+    .unwrap();
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+printf 'Args: %s' "$*""#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .env("PATH", path_with(bin.path()))
+        .arg("--recover")
+        .write_stdin("and then?\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Recover unsaved session from").not())
+        .stdout(predicate::str::contains("Recovered 1 turn(s); resuming Claude session 3f2a9c1e"))
+        .stdout(predicate::str::contains("--resume 3f2a9c1e"));
+    assert!(!journal.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_response_is_streamed_to_terminal() {
+    let bin = fake_claude("printf 'Hello from '; printf 'fake claude'");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .write_stdin("hi\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Claude #1>\nHello from fake claude\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_conversation_is_mirrored_with_tee() {
+    let bin = fake_claude("printf 'Hello from '; printf 'fake claude'");
+    let dir = tempfile::tempdir().unwrap();
+    let tee = dir.path().join("session.log");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .args(["--tee", tee.to_str().unwrap()])
+        .write_stdin("hi\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Tee: conversation mirrored to"));
+    assert_eq!(fs::read_to_string(&tee).unwrap(), "You> hi\nClaude>\nHello from fake claude\n\n");
+}
+
+#[cfg(all(target_os = "linux", feature = "clipboard"))]
+#[test]
+fn test_clipboard_commands_without_a_display() {
+    let mut cmd = claude_dialog();
+    cmd.env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .write_stdin("/paste Why does this fail?\n/copy\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("No system clipboard is available"))
+        .stderr(predicate::str::contains("There is no response to copy yet"))
+        .stdout(predicate::str::contains("Exiting conversation"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_prompt_written_in_the_editor_is_sent() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ $# -gt 0 ]; do [ "$1" = "-p" ] && printf 'Prompt: %s' "$2"; shift; done"#);
+    let editor = fake_claude(r#"printf 'in one sentence\n\n' >> "$1""#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .env("VISUAL", editor.path().join("claude"))
+        .write_stdin("/edit Explain ownership\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Prompt: Explain ownership\nin one sentence\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failed_claude_command_is_reported() {
+    let bin = fake_claude("echo 'boom' >&2; exit 3");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .write_stdin("hi\nexit\n");
+    
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("boom"))
+        .stderr(predicate::str::contains("Claude command failed"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_outdated_claude_cli_options_are_dropped() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && echo "0.2.9 (Claude Code)""#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--model")
+        .arg("claude-3-opus")
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("claude CLI 0.2.9 is older than 1.0.0"))
+        .stderr(predicate::str::contains("Ignoring --model: requires claude CLI 1.0.0 or newer"))
+        .stdout(predicate::str::contains("Model: default"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_supported_claude_cli_has_no_warnings() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && echo "1.0.35 (Claude Code)""#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--model")
+        .arg("claude-3-opus")
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Warning").not())
+        .stdout(predicate::str::contains("Model: claude-3-opus"));
+}
+
+#[test]
+fn test_paths_command() {
+    let mut cmd = claude_dialog();
+    cmd.arg("paths")
+        .env("CLAUDE_DIALOG_CONFIG_DIR", "/opt/claude-dialog/config")
+        .env("CLAUDE_DIALOG_DATA_DIR", "/opt/claude-dialog/data");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("config   /opt/claude-dialog/config/config.toml"))
+        .stdout(predicate::str::contains("sessions /opt/claude-dialog/data/sessions"));
+}
+
+#[test]
+fn test_prompt_library() {
+    let config_dir = tempfile::tempdir().unwrap();
+    let prompts = config_dir.path().join("prompts");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path()).args(["prompts", "list"]);
+    cmd.assert().success().stdout(predicate::str::contains("There are no presets yet"));
+    
+    fs::create_dir(&prompts).unwrap();
+    fs::write(prompts.join("rust-reviewer.md"), "# Review Rust code strictly\n").unwrap();
+    fs::write(prompts.join("tutor.md"), "Explain like a patient teacher.").unwrap();
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path()).args(["prompts", "list"]);
+    cmd.assert()
+        .success()
+        .stdout("rust-reviewer  Review Rust code strictly\ntutor          Explain like a patient teacher.\n");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path())
+        .args(["--preset", "tutor"])
+        .write_stdin("exit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("System Prompt: {} (--preset)", prompts.join("tutor.md").display())));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path())
+        .args(["--preset", "reviewer"])
+        .write_stdin("exit\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No preset named 'reviewer'"));
+}
+
+#[test]
+fn test_whatsnew_command() {
+    let mut cmd = claude_dialog();
+    cmd.arg("whatsnew");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!("## {}\n", env!("CARGO_PKG_VERSION"))))
+        .stdout(predicate::str::contains("claude-dialog whatsnew"));
+}
+
+#[test]
+fn test_present_plays_back_a_transcript() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("demo.md");
+    fs::write(
+        &path,
+        "# Claude Dialog transcript\n\nStarted 2025-01-01 12:00, 2 turns\n\n\
+         ## You (12:00:05)\n\nWhat is a slice?\n\n## Claude (opus)\n\nA view into a sequence.\n\n*Rated 5/5*\n\n\
+         ## You (12:01:10)\n\nShow one\n\n## Claude\n\n`&v[1..3]`\n",
+    )
+    .unwrap();
+    
+    // Without a terminal, playback continues by itself
+    claude_dialog()
+        .env("PATH", "")
+        .arg("present")
+        .arg(&path)
+        .args(["--speed", "1000"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("You> What is a slice?\nClaude>\nA view into a sequence.\n"))
+        .stdout(predicate::str::contains("You> Show one\nClaude>\n`&v[1..3]`\n"))
+        .stdout(predicate::str::contains("Rated").not())
+        .stdout(predicate::str::contains("End of the conversation"));
+}
+
+#[test]
+fn test_view_prints_a_conversation_without_a_terminal() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    fs::write(
+        &path,
+        "# Claude Dialog transcript\n\nStarted 2025-01-01 12:00, 1 turn\n\n\
+         ## You (12:00:05)\n\nWhat is a slice?\n\n## Claude (opus)\n\nA view into a sequence.\n\n*Rated 5/5*\n",
+    )
+    .unwrap();
+    
+    claude_dialog()
+        .env("PATH", "")
+        .arg("view")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("You\nWhat is a slice?\n\nClaude\nA view into a sequence.\n");
+    
+    // Anything else is a saved session
+    claude_dialog()
+        .env("CLAUDE_DIALOG_DATA_DIR", dir.path())
+        .args(["view", "20250101-120000"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No saved session '20250101-120000'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_input_history_is_saved_unless_private() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let history = state_dir.path().join("history");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .args(["--history-ignore", "*password*"])
+        .write_stdin("Hello\nmy password is hunter2\n/find Hello\nHello\nexit\n");
+    cmd.assert().success();
+    assert_eq!(fs::read_to_string(&history).unwrap(), "/find Hello\nHello\n");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .arg("--private")
+        .write_stdin("Something sensitive\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("History: off"));
+    assert_eq!(fs::read_to_string(&history).unwrap(), "/find Hello\nHello\n");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .args(["history", "clear"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Deleted {}", history.display())));
+    assert!(!history.exists());
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .args(["history", "clear"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("There is no input history to delete"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_history_settings_in_the_configuration_file() {
+    let config_dir = tempfile::tempdir().unwrap();
+    let state_dir = tempfile::tempdir().unwrap();
+    let history = state_dir.path().join("history");
+    fs::write(
+        config_dir.path().join("config.toml"),
+        "history_ignore = [\"*password*\"]\n\n[profiles.shared]\nhistory = false\n",
+    )
+    .unwrap();
+    
+    // Patterns of the file and the command line both apply
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path())
+        .env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .args(["--history-ignore", "*token*"])
+        .write_stdin("/find Hello\nmy password is hunter2\nmy token is abc\nexit\n");
+    cmd.assert().success();
+    assert_eq!(fs::read_to_string(&history).unwrap(), "/find Hello\n");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path())
+        .env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .args(["--profile", "shared"])
+        .write_stdin("/find Something sensitive\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("History: off"));
+    assert_eq!(fs::read_to_string(&history).unwrap(), "/find Hello\n");
+    
+    fs::write(config_dir.path().join("config.toml"), "history_ignore = [\"[unclosed\"]\n").unwrap();
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path())
+        .env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .write_stdin("exit\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid history ignore pattern '[unclosed'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_incognito_session_writes_nothing() {
+    // Report how many journals exist while the session runs
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+printf 'journals: %s' "$(find "$CLAUDE_DIALOG_STATE_DIR" -name 'journal-*' | wc -l | tr -d ' ')""#);
+    let state_dir = tempfile::tempdir().unwrap();
+    let data_dir = tempfile::tempdir().unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .env("CLAUDE_DIALOG_STATE_DIR", state_dir.path())
+        .env("CLAUDE_DIALOG_DATA_DIR", data_dir.path())
+        .arg("--incognito")
+        .write_stdin("Something sensitive\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Incognito: nothing about this session is saved to disk"))
+        .stdout(predicate::str::contains("Journal: off (--incognito)"))
+        .stdout(predicate::str::contains("History: off (--incognito)"))
+        .stdout(predicate::str::contains("You (incognito)> "))
+        .stdout(predicate::str::contains("journals: 0"));
+    assert!(!state_dir.path().join("history").exists());
+    assert!(!data_dir.path().join("sessions").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sessions_are_saved_listed_and_deleted() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+printf 'Borrowing rules'"#);
+    let data_dir = tempfile::tempdir().unwrap();
+    let sessions = |args: &[&str]| {
+        let mut cmd = claude_dialog();
+        cmd.env("CLAUDE_DIALOG_DATA_DIR", data_dir.path()).arg("sessions").args(args);
+        cmd
+    };
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .env("CLAUDE_DIALOG_DATA_DIR", data_dir.path())
+        .args(["chat", "--model", "opus"])
+        .write_stdin("Explain borrowing\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Session saved as "));
+    
+    let listed = sessions(&["list"]).assert().success().get_output().stdout.clone();
+    let listed = String::from_utf8(listed).unwrap();
+    assert!(listed.contains("  1 turn     Explain borrowing"), "{}", listed);
+    let id = listed.split_whitespace().next().unwrap().to_string();
+    
+    sessions(&["show", &id])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("You> Explain borrowing\n\nClaude> Borrowing rules"))
+        .stdout(predicate::str::contains("Continue this conversation with: claude-dialog --from"));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_DATA_DIR", data_dir.path()).args(["view", &id]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Explain borrowing\n\nClaude\nBorrowing rules"));
+    
+    sessions(&["delete", &id])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Deleted session {}", id)));
+    sessions(&["show", &id])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No saved session"));
+    sessions(&["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("There are no saved sessions yet"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_claude_errors_are_shown_and_reported() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+echo "Error: Invalid API key" >&2; exit 1"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .write_stdin("Hello\n");
+    
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: Invalid API key\n"))
+        .stderr(predicate::str::contains("Claude command failed with exit code 1: Error: Invalid API key"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_rate_limited_prompts_are_retried_after_waiting() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+if [ ! -e "$MARKER" ]; then
+    touch "$MARKER"
+    echo "API Error: 429 Too Many Requests, retry after 1 second" >&2; exit 1
+fi
+echo "Answer after waiting""#);
+    let marker = bin.path().join("rate-limited");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .env("MARKER", &marker)
+        .write_stdin("Hello\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Rate limited by Claude; retrying in 1s (retry 1 of 3)"))
+        .stdout(predicate::str::contains("Answer after waiting"));
+    
+    let mut cmd = claude_dialog();
+    fs::remove_file(&marker).unwrap();
+    cmd.env("PATH", path_with(bin.path()))
+        .env("MARKER", &marker)
+        .args(["--rate-limit-retries", "0"])
+        .write_stdin("Hello\nexit\n");
+    
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Claude command failed with exit code 1: API Error: 429"));
+}
+
+#[test]
+fn test_missing_prompt_file_is_a_usage_error() {
+    let mut cmd = claude_dialog();
+    cmd.args(["--system-prompt", "does-not-exist.md"]);
+    
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::str::contains("Claude Dialog Shell").not())
+        .stderr(predicate::str::contains("invalid value 'does-not-exist.md' for '--system-prompt <FILE|DIR>'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_missing_claude_cli_is_reported_before_banner() {
+    let empty = tempfile::tempdir().unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", empty.path())
+        .args(["--model", "", "--alias", "checkpoint=/restore"])
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Claude Dialog Shell").not())
+        .stderr(predicate::str::contains("claude CLI not found on PATH"))
+        .stderr(predicate::str::contains("Model name must not be empty"))
+        .stderr(predicate::str::contains("Alias /checkpoint conflicts with a built-in command"))
+        .stderr(predicate::str::contains("3 problem(s) found"));
+}
+
+#[cfg(all(unix, feature = "ollama"))]
+#[test]
+fn test_ollama_backend_does_not_need_claude() {
+    let empty = tempfile::tempdir().unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", empty.path())
+        .args(["--backend", "ollama"])
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("claude CLI not found").not())
+        .stderr(predicate::str::contains("The ollama backend needs a model, e.g. --model llama3"));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", empty.path())
+        .env("OLLAMA_HOST", "127.0.0.1:11999")
+        .args(["--backend", "ollama", "--model", "llama3"])
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Unknown model").not())
+        .stdout(predicate::str::contains("Backend: ollama at http://127.0.0.1:11999 (--backend)"))
+        .stdout(predicate::str::contains("Allowed Tools").not());
+}
+
+#[cfg(all(unix, feature = "api"))]
+#[test]
+fn test_api_backend_does_not_need_claude() {
+    let empty = tempfile::tempdir().unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", empty.path())
+        .env_remove("ANTHROPIC_API_KEY")
+        .args(["--backend", "api", "--model", "sonnet"])
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("claude CLI not found").not())
+        .stderr(predicate::str::contains("The api backend needs an API key in ANTHROPIC_API_KEY"))
+        .stderr(predicate::str::contains("not the claude CLI alias sonnet"));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", empty.path())
+        .env("ANTHROPIC_API_KEY", "test-key")
+        .env("ANTHROPIC_BASE_URL", "http://127.0.0.1:11999")
+        .args(["--backend", "api", "--model", "claude-sonnet-4-5", "--temperature", "0.2", "--max-tokens", "512"])
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Backend: Anthropic API at http://127.0.0.1:11999 (--backend)"))
+        .stdout(predicate::str::contains("Temperature: 0.2 (--temperature)"))
+        .stdout(predicate::str::contains("Max Tokens: 512 (--max-tokens)"))
+        .stdout(predicate::str::contains("Allowed Tools").not());
+}
+
+#[test]
+fn test_front_matter_sets_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let prompt = dir.path().join("review.md");
+    fs::write(&prompt, "---\nmodel: opus\nallowed_tools: [Read, Grep]\n---\nReview strictly.\n").unwrap();
+    let prompt = prompt.to_str().unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.args(["--system-prompt", prompt]).write_stdin("exit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Model: opus (system prompt front matter)"))
+        .stdout(predicate::str::contains("Allowed Tools: Read, Grep (system prompt front matter)"));
+    
+    let mut cmd = claude_dialog();
+    cmd.args(["--system-prompt", prompt, "--model", "haiku"]).write_stdin("exit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Model: haiku (--model)"));
+}
+
+#[test]
+fn test_unknown_model_is_a_warning() {
+    let mut cmd = claude_dialog();
+    cmd.args(["--model", "gpt-4"])
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Unknown model 'gpt-4'"))
+        .stdout(predicate::str::contains("Model: gpt-4"));
+}
+
+#[test]
+fn test_banner_shows_effective_configuration() {
+    let mut cmd = claude_dialog();
+    cmd.args(["--model", "opus", "--alias", "cp=/checkpoint"])
+        .write_stdin("exit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("System Prompt: Default\n"))
+        .stdout(predicate::str::contains("        Model: opus (--model)\n"))
+        .stdout(predicate::str::contains("Allowed Tools: Write, Edit\n"))
+        .stdout(predicate::str::contains("      Context: 100000 tokens, head-tail\n"))
+        .stdout(predicate::str::contains("      Aliases: /cp\n"))
+        .stdout(predicate::str::contains("Tip: "));
+}
+
+#[test]
+fn test_banner_styles() {
+    let mut cmd = claude_dialog();
+    cmd.args(["--banner", "minimal"]).write_stdin("exit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Claude Dialog Shell"))
+        .stdout(predicate::str::contains("System Prompt:").not())
+        .stdout(predicate::str::contains("Tip: ").not());
+    
+    let mut cmd = claude_dialog();
+    cmd.args(["--banner", "off"]).write_stdin("exit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Claude Dialog Shell").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_final_only_shows_only_the_final_answer() {
+    let bin = fake_claude(r#"
+[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *"--output-format stream-json --verbose"*) ;; *) echo "missing stream-json flags"; exit 1;; esac
+echo '{"type":"system","subtype":"init"}'
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Let me look around"},{"type":"tool_use","name":"Read","input":{}}]}}'
+echo '{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Edit","input":{}}]}}'
+echo '{"type":"result","subtype":"success","is_error":false,"result":"Fixed the bug."}'"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--final-only")
+        .write_stdin("fix it\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Output: final answer only"))
+        .stdout(predicate::str::contains("Claude #1>\nFixed the bug.\n"))
+        .stdout(predicate::str::contains("Let me look around").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_status_shows_the_session_and_usage() {
+    let bin = fake_claude(r#"
+[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+echo '{"type":"result","subtype":"success","is_error":false,"result":"Done.","session_id":"3f2a9c1e","total_cost_usd":0.0042,"usage":{"input_tokens":1500,"output_tokens":250}}'"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .args(["--final-only", "--model", "opus"])
+        .write_stdin("fix it\n/status\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("   Backend: claude\n"))
+        .stdout(predicate::str::contains("     Model: opus\n"))
+        .stdout(predicate::str::contains("   Session: 3f2a9c1e\n"))
+        .stdout(predicate::str::contains("     Turns: 1\n"))
+        .stdout(predicate::str::contains("    Tokens: 1500 in, 250 out\n"))
+        .stdout(predicate::str::contains("      Cost: $0.0042\n"))
+        .stdout(predicate::str::contains("Last error: none"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_usage_is_tallied_per_turn_and_summed_up_on_exit() {
+    let bin = fake_claude(r#"
+[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *"--output-format json"*) ;; *) echo "missing json flag"; exit 1;; esac
+echo '{"type":"result","subtype":"success","is_error":false,"result":"Done.","session_id":"3f2a9c1e","total_cost_usd":0.0042,"usage":{"input_tokens":1000,"cache_read_input_tokens":500,"output_tokens":250}}'"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--show-usage")
+        .write_stdin("first\nsecond\n/stats\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Usage: tokens and estimated cost shown after each turn"))
+        .stdout(predicate::str::contains("Claude #1>\nDone.\n"))
+        .stdout(predicate::str::contains("\"type\"").not())
+        .stdout(predicate::str::contains("Tokens: 1500 in (500 cached), 250 out, $0.0042 (session: 1500 in (500 cached), 250 out, $0.0042)"))
+        .stdout(predicate::str::contains("Tokens: 1500 in (500 cached), 250 out, $0.0042 (session: 3000 in (1000 cached), 500 out, $0.0084)"))
+        .stdout(predicate::str::contains("   Tokens: 3000 in, 500 out (3500 total)\n"))
+        .stdout(predicate::str::contains("    Cache: 1000 read, 0 written (33% of input tokens read from cache)\n"))
+        .stdout(predicate::str::contains("     Cost: $0.0084 estimated ($0.0042 per turn)\n"))
+        .stdout(predicate::str::contains("Session usage: 3000 tokens in, 500 out, estimated cost $0.0084"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dry_run_prints_command_lines_without_running_claude() {
+    let bin = fake_claude(r#"
+[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+echo "claude should not run"; exit 1"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .args(["--dry-run", "--model", "opus", "--allowed-tools", "Bash(git log:*)"])
+        .write_stdin("What's new?\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Dry run: claude command lines printed, not run"))
+        .stdout(predicate::str::contains(
+            "claude --continue -p 'What'\\''s new?' --model opus --output-format json --allowedTools 'Bash(git log:*)'\n"
+        ))
+        .stdout(predicate::str::contains("claude should not run").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stream_shows_text_as_it_is_generated() {
+    let bin = fake_claude(r#"
+[ "$1" = "--version" ] && { echo "1.0.90 (Claude Code)"; exit 0; }
+case "$*" in *"--output-format stream-json --verbose --include-partial-messages"*) ;; *) echo "missing flags"; exit 1;; esac
+delta() { echo "{\"type\":\"stream_event\",\"event\":{\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"$1\"}}}"; }
+delta "Let me "
+delta "look."
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Let me look."}]}}'
+echo '{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Read","input":{}}]}}'
+delta "Found it."
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Found it."}]}}'
+echo '{"type":"result","subtype":"success","is_error":false,"result":"Found it."}'"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--stream")
+        .write_stdin("where is it\n/find look\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Output: streamed as generated (--stream)"))
+        .stdout(predicate::str::contains("Claude #1>\nLet me look.\n\nFound it.\n"))
+        .stdout(predicate::str::contains("#1 Claude: Let me look."));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_session_slash_commands() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *"--model opus"*) echo "answered by opus";; *) echo "answered by default";; esac"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .write_stdin("/help\n/model opus\nHello\n/exit\nnever sent\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Commands:\n  /help [command]"))
+        .stdout(predicate::str::contains("Using model opus for the following prompts"))
+        .stdout(predicate::str::contains("answered by opus"))
+        .stdout(predicate::str::contains("Exiting conversation..."))
+        .stdout(predicate::str::contains("never sent").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resume_attaches_the_first_prompt_to_a_session() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; echo "session args: $1 $2""#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .args(["--resume", "3f2a9c1e"])
+        .write_stdin("Where were we?\nGo on\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Session: resumes 3f2a9c1e (--resume)"))
+        .stdout(predicate::str::contains("session args: --resume 3f2a9c1e\n"))
+        .stdout(predicate::str::contains("session args: --continue -p\n"));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--new-session")
+        .write_stdin("Hello\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Session: new conversation (--new-session)"))
+        .stdout(predicate::str::contains("session args: -p Hello\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tool_options_are_passed_to_claude() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *"--allowedTools Read Edit --disallowedTools WebFetch") echo "tools passed";; *) echo "unexpected: $*";; esac"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .args(["--allowed-tools", "Read,Edit,Read", "--disallowed-tools", "WebFetch"])
+        .write_stdin("Hello\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Allowed Tools: Read, Edit (--allowed-tools)"))
+        .stdout(predicate::str::contains("Disallowed Tools: WebFetch (--disallowed-tools)"))
+        .stdout(predicate::str::contains("tools passed"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_personas_are_listed_and_switched() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *"--model haiku"*) echo "quick answer";; *) echo "default answer";; esac"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .args(["--persona", "quick=claude:haiku"])
+        .write_stdin("/persona quick\n/persona\nHello\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Personas: quick (claude:haiku)\n"))
+        .stdout(predicate::str::contains("Sending the following prompts to quick (claude:haiku)"))
+        .stdout(predicate::str::contains("  1.   default    claude\n  2. * quick      claude:haiku"))
+        .stdout(predicate::str::contains("quick answer"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_transcript_is_saved_when_the_session_ends() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; echo "Ownership moves values""#);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes/session.md");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--save-transcript")
+        .arg(&path)
+        .write_stdin("Explain ownership\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Transcript saved to {}", path.display())));
+    
+    let markdown = fs::read_to_string(&path).unwrap();
+    assert!(markdown.starts_with("# Claude Dialog transcript\n"));
+    assert!(markdown.contains("\n\nExplain ownership\n\n## Claude\n\nOwnership moves values\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_session_is_recorded_as_an_asciinema_cast() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; echo "Ownership moves values""#);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("demo.cast");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--record-cast")
+        .arg(&path)
+        .write_stdin("Explain ownership\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Claude #1>\nOwnership moves values\n"))
+        .stdout(predicate::str::contains(format!("Recording saved to {}", path.display())));
+    
+    let cast = fs::read_to_string(&path).unwrap();
+    let mut lines = cast.lines();
+    let header: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(header["version"], 2);
+    assert_eq!(header["width"], 80);
+    
+    let mut output = String::new();
+    let mut last_time = 0.0;
+    for line in lines {
+        let event: (f64, String, String) = serde_json::from_str(line).unwrap();
+        assert_eq!(event.1, "o");
+        assert!(event.0 >= last_time);
+        last_time = event.0;
+        output.push_str(&event.2);
+    }
+    assert!(output.contains("Recording: "));
+    assert!(output.contains("Claude #1>\r\nOwnership moves values\r\n"));
+    assert!(!output.contains("Recording saved"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_masked_responses_keep_the_original_in_transcripts() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; echo "Ask Initech at ops@initech.com""#);
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("customers.txt"), "# customers\ninitech\n").unwrap();
+    let path = dir.path().join("session.md");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(dir.path())
+        .args(["--mask-pii", "--mask-words", "customers.txt", "--save-transcript"])
+        .arg(&path)
+        .write_stdin("Who do I ask?\n/find ask\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Ask ███████ at ███████████████\n"))
+        .stdout(predicate::str::contains("#1 Claude: Ask ███████ at ███████████████\n"))
+        .stdout(predicate::str::contains("initech").not());
+    assert!(fs::read_to_string(&path).unwrap().contains("Ask Initech at ops@initech.com\n"));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(dir.path())
+        .args(["--mask-pii", "--mask-transcripts", "--save-transcript"])
+        .arg(&path)
+        .write_stdin("Who do I ask?\nexit\n");
+    
+    cmd.assert().success();
+    assert!(fs::read_to_string(&path).unwrap().contains("Ask Initech at ███████████████\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unreadable_mask_word_list_stops_the_start() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), "mask_words = [\"missing.txt\"]\n").unwrap();
+    
+    claude_dialog()
+        .env("CLAUDE_DIALOG_CONFIG_DIR", dir.path())
+        .write_stdin("exit\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read mask word list"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_find_searches_the_conversation() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; printf 'The Borrow checker\nenforces rules'"#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .write_stdin("explain borrowing\n/find borrow\n/find BORROW checker --full\n/find lifetimes\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("#1 You: explain borrowing\n#1 Claude: The Borrow checker\n"))
+        .stdout(predicate::str::contains("2 match(es) in 1 turn(s)"))
+        .stdout(predicate::str::contains("── Turn 1 ──\nYou> explain borrowing\nClaude>\nThe Borrow checker\nenforces rules\n"))
+        .stdout(predicate::str::contains("No matches for 'lifetimes'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_references_are_sent_and_cited() {
+    // Echo the prompt back, which follows -p
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf '%s' "$2""#);
+    let workspace = tempfile::tempdir().unwrap();
+    fs::write(workspace.path().join("notes.md"), "alpha\nbeta\n").unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .write_stdin("Summarize @notes.md.\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Summarize notes.md [1].\n\n<file index=\"1\" path=\"notes.md\" lines=\"1-2\">\nalpha\nbeta\n</file>"))
+        .stdout(predicate::str::contains("Sources:\n  [1] notes.md (lines 1-2)\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_repo_map_is_sent_with_the_next_prompt() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf '%s' "$2""#);
+    let workspace = tempfile::tempdir().unwrap();
+    fs::create_dir(workspace.path().join("src")).unwrap();
+    fs::write(workspace.path().join("src/lib.rs"), "pub fn parse() {}\n").unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .write_stdin("/repo-map\nWhere is parsing done?\nAnd now?\nexit\n");
+    
+    let output = cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Repository map of 1 file(s)"))
+        .stdout(predicate::str::contains("Where is parsing done?\n\n<repo-map root=\""))
+        .stdout(predicate::str::contains("src/\n  lib.rs (18 B): fn parse\n</repo-map>"))
+        .get_output()
+        .stdout
+        .clone();
+    
+    // The map is only sent once
+    assert_eq!(String::from_utf8(output).unwrap().matches("<repo-map").count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_repo_map_flag_sends_the_map_with_the_first_prompt() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf '%s' "$2""#);
+    let workspace = tempfile::tempdir().unwrap();
+    fs::write(workspace.path().join("main.py"), "def main():\n    pass\n").unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .arg("--repo-map")
+        .write_stdin("Hello\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Hello\n\n<repo-map root=\""))
+        .stdout(predicate::str::contains("main.py (21 B): def main\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_glob_references_skip_ignored_files() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf '%s' "$2""#);
+    let workspace = tempfile::tempdir().unwrap();
+    fs::create_dir(workspace.path().join("config")).unwrap();
+    fs::write(workspace.path().join("config/app.toml"), "port = 8080\n").unwrap();
+    fs::write(workspace.path().join("config/secrets.toml"), "token = \"hunter2\"\n").unwrap();
+    fs::write(workspace.path().join(".claude-dialog-ignore"), "secrets.toml\n").unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .write_stdin("Check @config/*.toml\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Check config/*.toml [1]\n\n<file index=\"1\" path=\"config/app.toml\""))
+        .stdout(predicate::str::contains("hunter2").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_preview_shows_the_prompt_and_asks_before_sending() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf 'sent: %s' "$2""#);
+    let workspace = tempfile::tempdir().unwrap();
+    fs::write(workspace.path().join("notes.md"), "alpha\n").unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .write_stdin("/preview Draft from @notes.md\nn\n/preview Final @notes.md\ny\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("── Preview: ~"))
+        .stdout(predicate::str::contains("Draft from notes.md [1]\n\n<file index=\"1\" path=\"notes.md\" lines=\"1\">\nalpha\n</file>\n── End of preview ──"))
+        .stdout(predicate::str::contains("Not sent"))
+        .stdout(predicate::str::contains("sent: Draft").not())
+        .stdout(predicate::str::contains("sent: Final notes.md [1]"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_included_files_are_kept_within_the_context_budget() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf '%s' "$2""#);
+    let workspace = tempfile::tempdir().unwrap();
+    let log: String = (1..=100).map(|n| format!("line {:03}\n", n)).collect();
+    fs::write(workspace.path().join("build.log"), log).unwrap();
+    fs::write(workspace.path().join("notes.md"), "alpha\n").unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .args(["--context-budget", "50"])
+        .write_stdin("Why did @build.log fail?\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("over the context budget of 50; trimmed to ~"))
+        .stdout(predicate::str::contains("line 001\n"))
+        .stdout(predicate::str::contains("lines omitted ...]\n"))
+        .stdout(predicate::str::contains("line 100\n"))
+        .stdout(predicate::str::contains("line 050").not())
+        .stdout(predicate::str::contains("[1] build.log (lines 1-"));
+    
+    // Asking lets the user drop files instead
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .args(["--context-budget", "50", "--context-strategy", "ask"])
+        .write_stdin("Compare @build.log with @notes.md\n1\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("  [1] build.log ~225 tokens\n  [2] notes.md ~2 tokens\n"))
+        .stdout(predicate::str::contains("line 001").not())
+        .stdout(predicate::str::contains("Sources:\n  [1] build.log (not sent)\n  [2] notes.md (line 1)\n"));
+}
+
+#[test]
+fn test_config_file_and_profiles() {
+    let config_dir = tempfile::tempdir().unwrap();
+    fs::create_dir(config_dir.path().join("prompts")).unwrap();
+    fs::write(config_dir.path().join("prompts/review.md"), "Review carefully.").unwrap();
+    fs::write(
+        config_dir.path().join("config.toml"),
+        r#"
+model = "sonnet"
+color = "never"
+
+[profiles.work]
+model = "opus"
+system_prompt = ["review.md"]
+allowed_tools = ["Read"]
+"#,
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = claude_dialog();
+        cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path()).args(args).write_stdin("exit\n");
+        cmd.assert()
+    };
+    
+    run(&[]).success().stdout(predicate::str::contains("Model: sonnet (config.toml)"));
+    
+    let review = config_dir.path().join("prompts/review.md");
+    run(&["--profile", "work"])
+        .success()
+        .stdout(predicate::str::contains("Model: opus (profile work)"))
+        .stdout(predicate::str::contains(format!("System Prompt: {} (profile work)", review.display())))
+        .stdout(predicate::str::contains("Allowed Tools: Read (profile work)"));
+    
+    run(&["--profile", "work", "--model", "haiku", "--allowed-tools", "Edit"])
+        .success()
+        .stdout(predicate::str::contains("Model: haiku (--model)"))
+        .stdout(predicate::str::contains("Allowed Tools: Edit (--allowed-tools)"));
+    
+    run(&["--profile", "home"])
+        .failure()
+        .stderr(predicate::str::contains("No profile named 'home'; available profiles: work"));
+    
+    fs::write(config_dir.path().join("config.toml"), "modle = \"opus\"\n").unwrap();
+    run(&[])
+        .failure()
+        .stderr(predicate::str::contains("Invalid configuration file"))
+        .stderr(predicate::str::contains("modle"));
+}
+
+#[test]
+fn test_locked_down_profile_disables_commands() {
+    let config_dir = tempfile::tempdir().unwrap();
+    fs::write(
+        config_dir.path().join("config.toml"),
+        "[profiles.support]\ndisallowed_commands = [\"system\", \"export\"]\n\n[profiles.typo]\ndisallowed_commands = [\"sytem\"]\n",
+    )
+    .unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path())
+        .args(["--profile", "support"])
+        .write_stdin("/system other.md\n/help\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Commands: /system, /export disabled (profile support)"))
+        .stdout(predicate::str::contains("/checkpoint <name>"))
+        .stdout(predicate::str::contains("/system [file]").not())
+        .stderr(predicate::str::contains("/system is disabled by the configuration"));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("CLAUDE_DIALOG_CONFIG_DIR", config_dir.path())
+        .args(["--profile", "typo"])
+        .write_stdin("exit\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot restrict unknown command(s) /sytem (profile typo)"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exported_conversation_is_continued_with_from() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; echo "args: $*""#);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("handoff.json");
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .write_stdin(format!("one\ntwo\n/export {}\nexit\n", path.display()));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Exported 2 turn(s) to {}", path.display())));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--from")
+        .arg(&path)
+        .write_stdin("three\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Import: "))
+        .stdout(predicate::str::contains("Imported 2 turn(s), replayed"))
+        .stdout(predicate::str::contains("args: --continue -p three"));
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .arg("--from")
+        .arg(&path)
+        .args(["--from-mode", "summary"])
+        .write_stdin("three\nexit\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 2 turn(s), summarized for the next prompt"))
+        .stdout(predicate::str::contains("args: -p three"))
+        .stdout(predicate::str::contains("User: two\n\nAssistant: args: --continue -p two"));
+    
+    fs::write(&path, "{\"format\": \"something else\"}").unwrap();
+    let mut cmd = claude_dialog();
+    cmd.arg("--from").arg(&path).write_stdin("exit\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Not a claude-dialog conversation file"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_blocks_are_sent_as_one_prompt() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; printf 'prompt: [%s]\n' "$3""#);
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .write_stdin("\"\"\"Why does this fail?\nfn main() {\n    run();\n}\n\"\"\"\n\"\"\"one line\"\"\"\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("prompt: [Why does this fail?\nfn main() {\n    run();\n}]"))
+        .stdout(predicate::str::contains("prompt: [one line]"))
+        .stdout(predicate::str::contains("prompt: [fn main").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ctrl_c_cancels_the_turn_and_not_the_session() {
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+    
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *slow*) touch "$(dirname "$0")/started"; exec sleep 30;; *) echo "answered quickly";; esac"#);
+    let started = bin.path().join("started");
+    
+    let mut child = claude_dialog_process()
+        .env("PATH", path_with(bin.path()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"slow\nfast\nexit\n").unwrap();
+    
+    let begun = Instant::now();
+    while !started.exists() {
+        assert!(begun.elapsed() < Duration::from_secs(10), "the slow turn never started");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(begun.elapsed() < Duration::from_secs(20));
+    assert!(stdout.contains("Turn cancelled; press Ctrl+C again or type exit to quit"));
+    assert!(stdout.contains("answered quickly"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_timeout_stops_a_stalled_command() {
+    use std::time::{Duration, Instant};
+    
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+exec sleep 30"#);
+    
+    let begun = Instant::now();
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .args(["--timeout", "1", "-p", "Hello"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Claude did not answer within 1s; the command was stopped (--timeout)"));
+    assert!(begun.elapsed() < Duration::from_secs(20));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_one_shot_prompt_prints_only_the_response() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *fail*) echo "half an answer"; exit 3;; *) echo "prompt: $3";; esac"#);
+    
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .args(["--prompt", "Summarize this"])
+        .assert()
+        .success()
+        .stdout("prompt: Summarize this\n");
+    
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .args(["-p", "-"])
+        .write_stdin("Review this diff\n+ added line\n")
+        .assert()
+        .success()
+        .stdout("prompt: Review this diff\n+ added line\n");
+    
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .args(["--prompt", "please fail"])
+        .assert()
+        .code(3)
+        .stdout("half an answer\n");
+    
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .args(["--prompt", "-"])
+        .write_stdin("  \n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("The prompt is empty"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_batch_runs_every_prompt_and_writes_a_report() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *fail*) echo "half an answer"; exit 3;; *) echo "answer to: $3";; esac"#);
+    let dir = tempfile::tempdir().unwrap();
+    let prompts = dir.path().join("prompts.yaml");
+    fs::write(&prompts, "- What is 2 + 2?\n- |\n  Two\n  lines\n").unwrap();
+    
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .arg("--batch")
+        .arg(&prompts)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("answer to: What is 2 + 2?"))
+        .stdout(predicate::str::contains("Ran 2 prompt(s), 0 failed"));
+    
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("prompts.results.json")).unwrap()).unwrap();
+    assert_eq!(report["format"], "claude-dialog-batch");
+    assert_eq!(report["results"][1]["prompt"], "Two\nlines");
+    assert_eq!(report["results"][1]["response"], "answer to: Two\nlines\n");
+    assert_eq!(report["results"][1]["exit_code"], 0);
+    
+    // Failed and unsendable prompts are reported and the run goes on
+    let prompts = dir.path().join("prompts.txt");
+    let notes = dir.path().join("notes.md");
+    fs::write(&notes, "one line\n").unwrap();
+    fs::write(&prompts, format!("# smoke tests\nplease fail\nReview @{}#40-50\nName a prime\n", notes.display())).unwrap();
+    let output = dir.path().join("reports/smoke.json");
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .arg("--batch")
+        .arg(&prompts)
+        .arg("--batch-output")
+        .arg(&output)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("Ran 3 prompt(s), 2 failed"));
+    
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    let results = report["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["exit_code"], 3);
+    assert!(results[0]["error"].as_str().unwrap().contains("exit code 3"));
+    assert!(results[1]["error"].as_str().unwrap().contains("outside the file"));
+    assert!(results[1].get("response").is_none());
+    assert_eq!(results[2]["response"], "answer to: Name a prime\n");
+    
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .arg("--batch")
+        .arg(dir.path().join("reports"))
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_json_output_prints_one_record_per_turn() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+case "$*" in *fail*) echo "half an answer"; exit 3;; *) echo "answer to: $3";; esac"#);
+    let parse = |output: &std::process::Output| -> Vec<serde_json::Value> {
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("only JSON records on stdout"))
+            .collect()
+    };
+    
+    let output = claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .args(["--prompt", "What is 2 + 2?", "--model", "opus", "--output-format", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let records = parse(&output);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["prompt"], "What is 2 + 2?");
+    assert_eq!(records[0]["response"], "answer to: What is 2 + 2?\n");
+    assert_eq!(records[0]["model"], "opus");
+    assert_eq!(records[0]["exit_code"], 0);
+    assert!(records[0]["duration_ms"].is_u64());
+    
+    let dir = tempfile::tempdir().unwrap();
+    let prompts = dir.path().join("prompts.txt");
+    fs::write(&prompts, "Name a prime\nplease fail\n").unwrap();
+    let output = claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .arg("--batch")
+        .arg(&prompts)
+        .args(["--output-format", "json"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Ran 2 prompt(s), 1 failed"))
+        .get_output()
+        .clone();
+    let records = parse(&output);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["response"], "answer to: Name a prime\n");
+    assert!(records[1]["error"].as_str().unwrap().contains("exit code 3"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_spellcheck_asks_before_sending_likely_typos() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf 'sent: %s' "$2""#);
+    let workspace = tempfile::tempdir().unwrap();
+    fs::write(workspace.path().join("words.txt"), "please\nexplain\nwhy\nfails\n").unwrap();
+    fs::write(workspace.path().join("lib.rs"), "fn parse_args() {}\n").unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .args(["--spellcheck", "--dictionary", "words.txt"])
+        .write_stdin("pleese explain\n\nwhy parse_arsg fails @lib.rs\ns\nplease explain\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Possible typos: pleese (please?)\n"))
+        .stdout(predicate::str::contains("Not sent; correct the prompt"))
+        .stdout(predicate::str::contains("sent: pleese").not())
+        .stdout(predicate::str::contains("Possible typos: parse_arsg (parse_args?)\n"))
+        .stdout(predicate::str::contains("sent: why parse_arsg fails lib.rs [1]"))
+        .stdout(predicate::str::contains("sent: please explain"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_mcp_config_is_passed_on_and_listed() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+printf 'args: %s' "$*""#);
+    let workspace = tempfile::tempdir().unwrap();
+    fs::write(
+        workspace.path().join("tools.json"),
+        r#"{"mcpServers": {"github": {"command": "npx", "args": ["-y", "@modelcontextprotocol/server-github"]}}}"#,
+    )
+    .unwrap();
+    fs::write(workspace.path().join("broken.json"), r#"{"mcpServers": {"github": {}}}"#).unwrap();
+
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .current_dir(workspace.path())
+        .args(["--mcp-config", "tools.json", "--strict-mcp-config"])
+        .write_stdin("/mcp\nList my pull requests\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MCP Servers: github (--mcp-config, only these)"))
+        .stdout(predicate::str::contains("github  stdio  npx -y @modelcontextprotocol/server-github  (tools.json)"))
+        .stdout(predicate::str::contains("Only these servers are used (--strict-mcp-config)"))
+        .stdout(predicate::str::contains("--mcp-config tools.json --strict-mcp-config"));
+
+    claude_dialog()
+        .current_dir(workspace.path())
+        .args(["--mcp-config", "broken.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("MCP server 'github' in broken.json has neither a command nor a url"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cwd_and_cd_choose_where_claude_runs() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+printf 'running in %s' "$(pwd)""#);
+    let workspace = tempfile::tempdir().unwrap();
+    let (shop, blog) = (workspace.path().join("shop"), workspace.path().join("blog"));
+    fs::create_dir_all(&shop).unwrap();
+    fs::create_dir_all(&blog).unwrap();
+    fs::write(blog.join("post.md"), "Draft post").unwrap();
+    let (shop, blog) = (shop.canonicalize().unwrap(), blog.canonicalize().unwrap());
+
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .arg("--cwd")
+        .arg(&shop)
+        .write_stdin("Where are you?\n/cd missing\n/cd ../blog\nSummarize @post.md\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Working Directory: {} (--cwd)", shop.display())))
+        .stdout(predicate::str::contains(format!("running in {}", shop.display())))
+        .stderr(predicate::str::contains("No such directory"))
+        .stderr(predicate::str::contains("post.md").not())
+        .stdout(predicate::str::contains("the next prompt starts a new one"))
+        .stdout(predicate::str::contains(format!("Working in {}", blog.display())))
+        .stdout(predicate::str::contains(format!("running in {}", blog.display())));
+
+    claude_dialog()
+        .args(["--cwd", "surely-missing-project"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No such directory"));
+}
+
+#[test]
+fn test_unfinished_prompts_ask_before_sending() {
+    claude_dialog()
+        .args(["--test-backend", "echo"])
+        .write_stdin("Why does parse(\n\ngit status\ny\nWhy does parse() fail?\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("This prompt ends with an unclosed (. Send to Claude anyway? [y/N]"))
+        .stdout(predicate::str::contains("Not sent; finish the prompt"))
+        .stdout(predicate::str::contains("Claude #1>\ngit status\n"))
+        .stdout(predicate::str::contains("Claude #2>\nWhy does parse() fail?\n"));
+
+    claude_dialog()
+        .args(["--test-backend", "echo", "--send-unfinished"])
+        .write_stdin("Why does parse(\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Send to Claude anyway").not())
+        .stdout(predicate::str::contains("Claude #1>\nWhy does parse(\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_quiz_hides_answers_until_enter() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+while [ "$1" != "-p" ]; do shift; done; printf 'answer to: %s' "$2""#);
+    
+    let output = claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .arg("--quiz")
+        .write_stdin("Capital of France?\n\nLargest planet?\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let ready = stdout.find("Answer ready; press Enter to reveal it").unwrap();
+    let revealed = stdout.find("Revealed after 0:0").unwrap();
+    let answer = stdout.find("Claude #1>\nanswer to: Capital of France?").unwrap();
+    assert!(ready < revealed && revealed < answer);
+    // The end of input reveals the last answer
+    assert!(stdout.contains("answer to: Largest planet?"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_markdown_is_printed_raw_when_not_a_terminal() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+printf '## Result\n\n- **done**\n'"#);
+    
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .env("CLICOLOR_FORCE", "1")
+        .args(["--prompt", "Summarize"])
+        .assert()
+        .success()
+        .stdout("## Result\n\n- **done**\n");
+}
+
+#[test]
+fn test_echo_test_backend_runs_without_claude() {
+    let empty = tempfile::tempdir().unwrap();
+    
+    let mut cmd = claude_dialog();
+    cmd.env("PATH", empty.path())
+        .args(["--test-backend", "echo"])
+        .write_stdin("What is 2 + 2?\n/checkpoint start\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("prompts answered locally, nothing sent (--test-backend)"))
+        .stdout(predicate::str::contains("Claude #1>\nWhat is 2 + 2?"))
+        .stdout(predicate::str::contains("Checkpoint 'start' saved at turn 1"));
+}
+
+#[test]
+fn test_fixture_test_backend_answers_from_the_file() {
+    let empty = tempfile::tempdir().unwrap();
+    let mut fixture = NamedTempFile::new().unwrap();
+    writeln!(
+        fixture,
+        "- match: '2 + 2'\n  response: '4'\n- response: 'Half an answer'\n  exit_code: 1\n  stderr: 'Error: internal server error'"
+    )
+    .unwrap();
+    let prompts = NamedTempFile::new().unwrap();
+    fs::write(prompts.path(), "What is 2 + 2?\nName a prime\n").unwrap();
+    
+    let output = claude_dialog()
+        .env("PATH", empty.path())
+        .env("CLAUDE_DIALOG_TEST_BACKEND", fixture.path())
+        .arg("--batch")
+        .arg(prompts.path())
+        .args(["--output-format", "json"])
+        .assert()
+        .code(1)
+        .get_output()
+        .clone();
+    let records: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records[0]["response"], "4");
+    assert_eq!(records[1]["exit_code"], 1);
+    assert!(records[1]["error"].as_str().unwrap().contains("Error: internal server error"));
+    
+    claude_dialog()
+        .args(["--test-backend", "no-such-fixture.yaml"])
+        .write_stdin("exit\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read fixture no-such-fixture.yaml"));
+}
+
+#[test]
+fn test_export_html_writes_a_page() {
+    let dir = tempfile::tempdir().unwrap();
+    let page = dir.path().join("session.html");
+    
+    let mut cmd = claude_dialog();
+    cmd.args(["--test-backend", "echo"])
+        .write_stdin(format!("Explain `Option<T>`\n/export html {}\nexit\n", page.display()));
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Exported 1 turn(s) to"));
+    let html = fs::read_to_string(&page).unwrap();
+    assert!(html.contains("<div class=\"prompt\">Explain `Option&lt;T&gt;`</div>"));
+    assert!(html.contains("<p>Explain <code>Option&lt;T&gt;</code></p>"));
+}
+
+#[test]
+fn test_search_matches_can_be_picked_by_number() {
+    let mut cmd = claude_dialog();
+    cmd.args(["--test-backend", "echo"])
+        .write_stdin("What is a lifetime?\nAnd a trait?\n/search LIFETIME\n2\n/turn 3\nexit\n");
+    
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("  1. #1 You\n"))
+        .stdout(predicate::str::contains("  2. #1 Claude\n"))
+        .stdout(predicate::str::contains("2 match(es) in 1 turn(s); type a number to show the turn of that match"))
+        .stdout(predicate::str::contains("Running: /turn 1\n── Turn 1 ──"))
+        .stderr(predicate::str::contains("There is no turn 3; the conversation has 2 turn(s)"));
+}
+
+#[test]
+fn test_policy_file_limits_what_options_ask_for() {
+    let dir = tempfile::tempdir().unwrap();
+    let policy = dir.path().join("policy.toml");
+    fs::write(
+        &policy,
+        "allowed_tools = [\"Read\"]\ndisallowed_commands = [\"export\"]\nmax_context_budget = 20000\n",
+    )
+    .unwrap();
+    
+    claude_dialog()
+        .env("CLAUDE_DIALOG_POLICY_FILE", &policy)
+        .args(["--test-backend", "echo", "--allowed-tools", "Read,WebFetch", "--context-budget", "0"])
+        .write_stdin("/export\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Policy: {}", policy.display())))
+        .stdout(predicate::str::contains(format!("Allowed Tools: Read (--allowed-tools, limited by the policy {})", policy.display())))
+        .stdout(predicate::str::contains("Context: 20000 tokens"))
+        .stderr(predicate::str::contains("Ignoring allowed tool WebFetch: not permitted by the policy"))
+        .stderr(predicate::str::contains("/export is disabled by the configuration"));
+    
+    fs::write(&policy, "disallowed_backends = [\"claude\"]\n").unwrap();
+    claude_dialog()
+        .env("CLAUDE_DIALOG_POLICY_FILE", &policy)
+        .args(["--test-backend", "echo"])
+        .write_stdin("exit\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("The claude backend is disallowed by the policy"));
+    
+    // A typo must not switch a limit off
+    fs::write(&policy, "max_budget = 100\n").unwrap();
+    claude_dialog()
+        .env("CLAUDE_DIALOG_POLICY_FILE", &policy)
+        .args(["--test-backend", "echo"])
+        .write_stdin("exit\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid policy file"));
+}
+
+#[test]
+fn test_sessions_merge_interleaves_conversations() {
+    let dir = tempfile::tempdir().unwrap();
+    let conversation = |turns: &[(&str, &str)]| {
+        let turns: Vec<String> = turns
+            .iter()
+            .map(|(prompt, time)| format!(r#"{{"prompt":"{}","response":"ok","time":"{}"}}"#, prompt, time))
+            .collect();
+        format!(
+            r#"{{"format":"claude-dialog-conversation","version":1,"exported_at":"2025-01-01T18:00:00Z","turns":[{}]}}"#,
+            turns.join(",")
+        )
+    };
+    let research = dir.path().join("research.json");
+    let implementation = dir.path().join("implementation.json");
+    fs::write(&research, conversation(&[("Compare the crates", "2025-01-01T09:00:00Z"), ("Which is faster?", "2025-01-01T11:00:00Z")])).unwrap();
+    fs::write(&implementation, conversation(&[("Add the dependency", "2025-01-01T10:00:00Z")])).unwrap();
+    let out = dir.path().join("feature.md");
+    
+    claude_dialog()
+        .args(["sessions", "merge"])
+        .arg(&research)
+        .arg(&implementation)
+        .args(["--label", "research", "--out"])
+        .arg(&out)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Merged 3 turn(s) of 2 sessions into"));
+    
+    let markdown = fs::read_to_string(&out).unwrap();
+    let order: Vec<usize> = ["Compare the crates", "Add the dependency", "Which is faster?"]
+        .iter()
+        .map(|prompt| markdown.find(prompt).unwrap())
+        .collect();
+    assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(markdown.contains(", implementation)\n\nAdd the dependency\n"));
+    assert!(markdown.contains(", research)\n\nWhich is faster?\n"));
+    
+    claude_dialog()
+        .args(["sessions", "merge", "20250101-120000"])
+        .arg(&research)
+        .args(["--out", "merged.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No saved session '20250101-120000'"));
+}
+
+#[cfg(feature = "archive")]
+#[test]
+fn test_sessions_archive_compresses_old_sessions() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let sessions_dir = data_dir.path().join("sessions");
+    fs::create_dir_all(&sessions_dir).unwrap();
+    let session = |exported_at: &str| {
+        format!(
+            r#"{{"format":"claude-dialog-conversation","version":1,"exported_at":"{}","turns":[{{"prompt":"Compare the crates","response":"ok"}}]}}"#,
+            exported_at
+        )
+    };
+    let recent = chrono::Utc::now().to_rfc3339();
+    fs::write(sessions_dir.join("20250101-120000.json"), session("2025-01-01T12:00:00Z")).unwrap();
+    fs::write(sessions_dir.join("recent.json"), session(&recent)).unwrap();
+    let sessions = |args: &[&str]| {
+        let mut cmd = claude_dialog();
+        cmd.env("CLAUDE_DIALOG_DATA_DIR", data_dir.path()).arg("sessions").args(args);
+        cmd
+    };
+
+    sessions(&["archive", "--older-than", "30d"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Archived 1 session(s) into"));
+    assert!(!sessions_dir.join("20250101-120000.json").exists());
+    assert!(sessions_dir.join("archive/20250101-120000.json.zst").is_file());
+    assert!(sessions_dir.join("recent.json").is_file());
+
+    sessions(&["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compare the crates (archived)"));
+    sessions(&["show", "20250101-120000"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("You> Compare the crates"));
+    sessions(&["archive", "--older-than", "30d"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("There are no sessions to archive"));
+}
+
+#[test]
+fn test_responses_are_numbered_and_shown_again() {
+    let output = claude_dialog()
+        .args(["--test-backend", "echo"])
+        .write_stdin("first question\nsecond question\n/show 1\n/last\n/show 3\nexit\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("There is no turn 3; the conversation has 2 turn(s)"))
+        .get_output()
+        .clone();
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("Claude #1>\nfirst question\n").count(), 2);
+    assert_eq!(stdout.matches("Claude #2>\nsecond question\n").count(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_recorded_responses_are_normalized() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; printf 'Steps:\n\tbuild\n```rust\nfn main() {}\n'"#);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("normalized.json");
+
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .write_stdin(format!("Show me main\n/export {}\nexit\n", path.display()))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\tbuild"));
+
+    let export: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let turn = &export["turns"][0];
+    assert_eq!(turn["response"], "Steps:\n    build\n```rust\nfn main() {}\n```");
+    assert_eq!(turn["code_languages"], serde_json::json!(["rust"]));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_claude_bin_and_passed_arguments() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }; echo "args: $*""#);
+    let claude = bin.path().join("claude");
+
+    claude_dialog()
+        .env("PATH", "/usr/bin:/bin")
+        .arg("--claude-bin")
+        .arg(&claude)
+        .args(["--claude-arg", "--max-turns", "--claude-arg", "5"])
+        .write_stdin("Hello\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Claude CLI: 1.0.35 ({}, --claude-bin)", claude.display())))
+        .stdout(predicate::str::contains("Claude Arguments: --max-turns 5 (--claude-arg)"))
+        .stdout(predicate::str::contains("args: --continue -p Hello"))
+        .stdout(predicate::str::contains("--max-turns 5\n"));
+
+    claude_dialog()
+        .args(["--claude-bin", "/nonexistent/claude"])
+        .write_stdin("exit\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("claude CLI /nonexistent/claude not found (--claude-bin)"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_batch_repeat_reports_stability() {
+    let bin = fake_claude(r#"[ "$1" = "--version" ] && { echo "1.0.35 (Claude Code)"; exit 0; }
+dir=$(dirname "$0"); echo "$*" >> "$dir/calls"
+n=$(($(wc -l < "$dir/calls") % 2)); echo "prime $n""#);
+    let dir = tempfile::tempdir().unwrap();
+    let prompts = dir.path().join("prompts.txt");
+    fs::write(&prompts, "Name a prime\n").unwrap();
+
+    claude_dialog()
+        .env("PATH", path_with(bin.path()))
+        .arg("--batch")
+        .arg(&prompts)
+        .args(["--repeat", "3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[run 3/3, 1/1] Name a prime"))
+        .stdout(predicate::str::contains("Stability over 3 runs:\n#1  3 run(s)  length 8-8 (mean 8)  distance 0.33 (max 0.50)  Name a prime"))
+        .stdout(predicate::str::contains("Ran 3 prompt(s), 0 failed"));
+
+    let calls = fs::read_to_string(bin.path().join("calls")).unwrap();
+    assert!(calls.lines().all(|call| call.starts_with("-p Name a prime")), "{}", calls);
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("prompts.results.json")).unwrap()).unwrap();
+    assert_eq!(report["results"][2]["run"], 3);
+    assert_eq!(report["stability"][0]["runs"], 3);
+    assert_eq!(report["stability"][0]["max_distance"], 0.5);
+
+    claude_dialog()
+        .arg("--repeat")
+        .arg("2")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--batch"));
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "cli")]
+
+use claude_dialog::context::FileInclusion;
+use claude_dialog::conversation::Turn;
+use claude_dialog::journal::{Journal, find_unsaved};
+use std::fs;
+
+#[test]
+fn test_no_journal_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    assert!(find_unsaved(&missing).unwrap().is_none());
+}
+
+#[test]
+fn test_orphaned_journal_is_recovered() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = {
+        let mut journal = Journal::create(dir.path()).unwrap();
+        journal.append(&Turn::new("first")).unwrap();
+        journal.append(&Turn::new("second")).unwrap();
+        journal.append(&Turn::new("third")).unwrap();
+        journal.truncate(2).unwrap();
+        journal.path().to_path_buf()
+        // Dropping the journal without discarding it simulates a crash
+    };
+    
+    let unsaved = find_unsaved(dir.path()).unwrap().unwrap();
+    assert_eq!(unsaved.path, path);
+    assert_eq!(unsaved.conversation.prompts(), vec!["first", "second"]);
+    
+    unsaved.discard().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_empty_orphaned_journal_is_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    drop(Journal::create(dir.path()).unwrap());
+    
+    assert!(find_unsaved(dir.path()).unwrap().is_none());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_responses_are_recovered() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let mut journal = Journal::create(dir.path()).unwrap();
+        journal.append(&Turn::new("question")).unwrap();
+        journal.append_response("answer").unwrap();
+    }
+    
+    let unsaved = find_unsaved(dir.path()).unwrap().unwrap();
+    let turn = &unsaved.conversation.turns()[0];
+    assert_eq!(turn.prompt, "question");
+    assert_eq!(turn.response.as_deref(), Some("answer"));
+}
+
+#[test]
+fn test_sources_are_recovered() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = FileInclusion::whole("src/main.rs", 40);
+    {
+        let mut journal = Journal::create(dir.path()).unwrap();
+        journal.append(&Turn::new("Why does @src/main.rs panic?").with_sources(vec![source.clone()])).unwrap();
+        journal.append_response("It unwraps None.").unwrap();
+    }
+    
+    let unsaved = find_unsaved(dir.path()).unwrap().unwrap();
+    assert_eq!(unsaved.conversation.turns()[0].sources, vec![source]);
+}
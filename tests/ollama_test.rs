@@ -0,0 +1,183 @@
+#![cfg(feature = "ollama")]
+
+mod common;
+
+use claude_dialog::claude_executor::{ClaudeExecutor, ClaudeInvocation, ClaudeOptions, OutputFormat, SessionMode};
+#[cfg(feature = "cli")]
+use claude_dialog::dialog::{DialogConfig, DialogLoop};
+use claude_dialog::ollama::{ChatMessage, OllamaExecutor};
+use claude_dialog::pipeline::{ResponsePipeline, ResponseSink};
+use common::http_stub;
+use serde_json::Value;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A stand-in Ollama server, see [`http_stub`]; receives the request bodies
+fn ollama_server(responses: Vec<(&'static str, String)>) -> (String, Receiver<common::Request>) {
+    http_stub("application/x-ndjson", responses)
+}
+
+/// The lines of a streamed answer, one per word
+fn chunks(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = text
+        .split_inclusive(' ')
+        .map(|word| format!("{}\n", serde_json::json!({ "message": { "role": "assistant", "content": word }, "done": false })))
+        .collect();
+    lines.push("{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}\n".to_string());
+    lines
+}
+
+/// A streamed answer split into one line per word
+fn answer(text: &str) -> (&'static str, String) {
+    ("200 OK", chunks(text).concat())
+}
+
+fn options() -> ClaudeOptions {
+    common::options("llama3")
+}
+
+fn command(prompt: &str) -> ClaudeInvocation {
+    common::command("llama3", prompt)
+}
+
+fn body(requests: &Receiver<common::Request>) -> Value {
+    requests.recv().unwrap().body
+}
+
+#[tokio::test]
+async fn test_conversation_history_is_sent_with_every_prompt() {
+    let (address, requests) = ollama_server(vec![answer("Hello there"), answer("Your name is Ada"), answer("Hi")]);
+    let ollama = OllamaExecutor::new(&address);
+
+    let first = ClaudeInvocation {
+        prompt: "My name is Ada".to_string(),
+        options: ClaudeOptions {
+            system_prompt: Some("Be brief.".to_string()),
+            ..options()
+        },
+    };
+    let response = ollama.execute(first, ResponsePipeline::new()).await.unwrap();
+    assert_eq!(response.text, "Hello there");
+
+    let request = body(&requests);
+    assert_eq!(request["model"], "llama3");
+    assert_eq!(request["stream"], true);
+    assert_eq!(request["messages"][0]["role"], "system");
+    assert!(request["options"].is_null());
+
+    ollama.execute(command("What is my name?"), ResponsePipeline::new()).await.unwrap();
+    let messages = body(&requests)["messages"].clone();
+    let contents: Vec<&str> = messages.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap()).collect();
+    assert_eq!(contents, ["My name is Ada", "Hello there", "What is my name?"]);
+
+    let fresh = ClaudeInvocation {
+        prompt: "Hello".to_string(),
+        options: ClaudeOptions {
+            session: SessionMode::Fresh,
+            output: OutputFormat::StreamJson,
+            temperature: Some(0.25),
+            ..options()
+        },
+    };
+    ollama.execute(fresh, ResponsePipeline::new()).await.unwrap();
+    let request = body(&requests);
+    assert_eq!(request["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(request["options"]["temperature"], 0.25);
+    assert_eq!(ollama.history(), [ChatMessage::new("user", "Hello"), ChatMessage::new("assistant", "Hi")]);
+}
+
+#[tokio::test]
+async fn test_conversation_is_trimmed_to_the_context_window_before_sending() {
+    let (address, requests) = ollama_server(vec![answer("Noted"), answer("Noted again"), answer("Done")]);
+    let ollama = OllamaExecutor::new(&address);
+    let small = |prompt: String| ClaudeInvocation {
+        prompt,
+        options: ClaudeOptions {
+            num_ctx: Some(400),
+            ..options()
+        },
+    };
+
+    // 300 of the 400 tokens are left for the conversation, ~100 each
+    ollama.execute(small("a".repeat(400)), ResponsePipeline::new()).await.unwrap();
+    assert_eq!(body(&requests)["options"]["num_ctx"], 400);
+    ollama.execute(small("b".repeat(400)), ResponsePipeline::new()).await.unwrap();
+    assert_eq!(body(&requests)["messages"].as_array().unwrap().len(), 3);
+
+    // The first exchange no longer fits with the third prompt, so it is
+    // dropped before the prompt is sent
+    ollama.execute(small("c".repeat(400)), ResponsePipeline::new()).await.unwrap();
+    let messages = body(&requests)["messages"].clone();
+    let contents: Vec<&str> = messages.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap()).collect();
+    assert_eq!(contents, ["b".repeat(400).as_str(), "Noted again", "c".repeat(400).as_str()]);
+}
+
+#[tokio::test]
+async fn test_server_errors_fail_the_response() {
+    let body = "{\"error\":\"model \\\"llama9\\\" not found, try pulling it first\"}".to_string();
+    let (address, _requests) = ollama_server(vec![("404 Not Found", body)]);
+    let ollama = OllamaExecutor::new(&address);
+
+    let response = ollama.execute(command("Hello"), ResponsePipeline::new()).await.unwrap();
+    assert!(!response.is_success());
+    assert_eq!(response.stderr, "Ollama error (HTTP 404): model \"llama9\" not found, try pulling it first");
+    assert!(ollama.history().is_empty());
+}
+
+/// Collects what is written to it
+struct Collect(Arc<Mutex<String>>);
+
+impl ResponseSink for Collect {
+    fn write_chunk(&mut self, chunk: &str) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push_str(chunk);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_cancelled_turns_stop_streaming() {
+    // The answer stalls after its first word
+    let address = common::paused_stub("application/x-ndjson", chunks("Hello there"), Duration::from_millis(500));
+    let ollama = OllamaExecutor::new(&address);
+    let text = Arc::new(Mutex::new(String::new()));
+    let mut pipeline = ResponsePipeline::new();
+    pipeline.add_sink(Collect(text.clone()));
+
+    // As with Ctrl+C or `--timeout`, the turn's future is dropped
+    let turn = tokio::time::timeout(Duration::from_millis(250), ollama.execute(command("Hello"), pipeline)).await;
+    assert!(turn.is_err());
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    assert_eq!(*text.lock().unwrap(), "Hello ");
+    assert!(ollama.history().is_empty());
+}
+
+#[tokio::test]
+async fn test_unreachable_server() {
+    let ollama = OllamaExecutor::new(&common::unreachable_url());
+
+    let err = ollama.execute(command("Hello"), ResponsePipeline::new()).await.unwrap_err();
+    assert!(err.to_string().contains("is `ollama serve` running?"));
+
+    let no_model = ClaudeInvocation {
+        prompt: "Hello".to_string(),
+        options: ClaudeOptions::default(),
+    };
+    assert!(ollama.execute(no_model, ResponsePipeline::new()).await.is_err());
+}
+
+#[cfg(feature = "cli")]
+#[tokio::test]
+async fn test_dialog_with_ollama() {
+    let (address, _requests) = ollama_server(vec![answer("Borrowing lends a reference")]);
+    let config = DialogConfig {
+        model: Some("llama3".to_string()),
+        ..Default::default()
+    };
+    let mut dialog = DialogLoop::with_executor(config, Box::new(OllamaExecutor::new(&address)));
+
+    dialog.submit("What is borrowing?").await.unwrap();
+    assert_eq!(dialog.conversation().turns()[0].response.as_deref(), Some("Borrowing lends a reference"));
+    assert!(dialog.transcript().to_markdown().contains("## Claude (llama3)\n\nBorrowing lends a reference\n"));
+}
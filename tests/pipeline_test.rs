@@ -0,0 +1,50 @@
+use anyhow::Result;
+use claude_dialog::pipeline::{ResponsePipeline, ResponseSink};
+use std::sync::{Arc, Mutex};
+
+struct Recorder {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl ResponseSink for Recorder {
+    fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        self.log.lock().unwrap().push(format!("{}:{}", self.name, chunk));
+        Ok(())
+    }
+    
+    fn finish(&mut self) -> Result<()> {
+        self.log.lock().unwrap().push(format!("{}:done", self.name));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_chunks_arrive_in_order_followed_by_finish() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    
+    let mut pipeline = ResponsePipeline::new();
+    pipeline.add_sink(Recorder { name: "a", log: log.clone() });
+    
+    pipeline.send("one");
+    pipeline.send("two");
+    pipeline.finish().await.unwrap();
+    
+    assert_eq!(*log.lock().unwrap(), vec!["a:one", "a:two", "a:done"]);
+}
+
+#[tokio::test]
+async fn test_fan_out_to_multiple_sinks() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    
+    let mut pipeline = ResponsePipeline::new();
+    pipeline.add_sink(Recorder { name: "a", log: log.clone() });
+    pipeline.add_sink(Recorder { name: "b", log: log.clone() });
+    
+    pipeline.send("chunk");
+    pipeline.finish().await.unwrap();
+    
+    let mut entries = log.lock().unwrap().clone();
+    entries.sort();
+    assert_eq!(entries, vec!["a:chunk", "a:done", "b:chunk", "b:done"]);
+}
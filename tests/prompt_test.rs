@@ -1,3 +1,5 @@
+#![cfg(feature = "cli")]
+
 use claude_dialog::prompt::{load_system_prompt, SystemPromptConfig};
 use tempfile::NamedTempFile;
 use std::io::Write;
@@ -7,9 +9,10 @@ fn test_no_system_prompt() {
     let config = SystemPromptConfig {
         system_prompt_files: vec![],
         append_prompt_file: None,
+        recursive: false,
     };
     let result = load_system_prompt(config).unwrap();
-    assert_eq!(result, "");
+    assert_eq!(result.body, "");
 }
 
 #[test]
@@ -18,12 +21,13 @@ fn test_single_system_prompt_file() {
     writeln!(temp_file, "This is a test prompt").unwrap();
     
     let config = SystemPromptConfig {
-        system_prompt_files: vec![temp_file.path().to_str().unwrap().to_string()],
+        system_prompt_files: vec![temp_file.path().to_path_buf()],
         append_prompt_file: None,
+        recursive: false,
     };
     
     let result = load_system_prompt(config).unwrap();
-    assert_eq!(result, "This is a test prompt\n");
+    assert_eq!(result.body, "This is a test prompt\n");
 }
 
 #[test]
@@ -36,14 +40,15 @@ fn test_multiple_system_prompt_files() {
     
     let config = SystemPromptConfig {
         system_prompt_files: vec![
-            temp_file1.path().to_str().unwrap().to_string(),
-            temp_file2.path().to_str().unwrap().to_string(),
+            temp_file1.path().to_path_buf(),
+            temp_file2.path().to_path_buf(),
         ],
         append_prompt_file: None,
+        recursive: false,
     };
     
     let result = load_system_prompt(config).unwrap();
-    assert_eq!(result, "First prompt\n\n\nSecond prompt\n");
+    assert_eq!(result.body, "First prompt\n\n\nSecond prompt\n");
 }
 
 #[test]
@@ -53,18 +58,20 @@ fn test_append_prompt_file() {
     
     let config = SystemPromptConfig {
         system_prompt_files: vec![],
-        append_prompt_file: Some(temp_file.path().to_str().unwrap().to_string()),
+        append_prompt_file: Some(temp_file.path().to_path_buf()),
+        recursive: false,
     };
     
     let result = load_system_prompt(config).unwrap();
-    assert_eq!(result, "Additional prompt\n");
+    assert_eq!(result.body, "Additional prompt\n");
 }
 
 #[test]
 fn test_missing_file() {
     let config = SystemPromptConfig {
-        system_prompt_files: vec!["nonexistent.md".to_string()],
+        system_prompt_files: vec!["nonexistent.md".into()],
         append_prompt_file: None,
+        recursive: false,
     };
     
     let result = load_system_prompt(config);
@@ -78,10 +85,73 @@ fn test_empty_file() {
     // Don't write anything to the file
     
     let config = SystemPromptConfig {
-        system_prompt_files: vec![temp_file.path().to_str().unwrap().to_string()],
+        system_prompt_files: vec![temp_file.path().to_path_buf()],
         append_prompt_file: None,
+        recursive: false,
     };
     
     let result = load_system_prompt(config).unwrap();
-    assert_eq!(result, "");
-}
\ No newline at end of file
+    assert_eq!(result.body, "");
+}
+#[test]
+fn test_config_from_json() {
+    let config: SystemPromptConfig = serde_json::from_str(r#"{"append_prompt_file": "extra.md"}"#).unwrap();
+    assert!(config.system_prompt_files.is_empty());
+    assert_eq!(config.append_prompt_file, Some("extra.md".into()));
+}
+
+#[test]
+fn test_front_matter_of_several_files() {
+    let mut base = NamedTempFile::new().unwrap();
+    write!(base, "---\nmodel: sonnet\nallowed_tools: [Read]\n---\nYou review code.").unwrap();
+    let mut strict = NamedTempFile::new().unwrap();
+    write!(strict, "---\nmodel: opus\n---\nBe strict.").unwrap();
+    
+    let config = SystemPromptConfig {
+        system_prompt_files: vec![base.path().to_path_buf(), strict.path().to_path_buf()],
+        append_prompt_file: None,
+        recursive: false,
+    };
+    let result = load_system_prompt(config).unwrap();
+    assert_eq!(result.body, "You review code.\n\nBe strict.");
+    assert_eq!(result.metadata.model.as_deref(), Some("opus"));
+    assert_eq!(result.metadata.allowed_tools, Some(vec!["Read".to_string()]));
+}
+
+#[test]
+fn test_invalid_front_matter_names_the_file() {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "---\ntemprature: 0.2\n---\nBe brief.").unwrap();
+    
+    let config = SystemPromptConfig {
+        system_prompt_files: vec![file.path().to_path_buf()],
+        append_prompt_file: None,
+        recursive: false,
+    };
+    let error = format!("{:#}", load_system_prompt(config).unwrap_err());
+    assert!(error.contains(&file.path().display().to_string()));
+    assert!(error.contains("unknown field `temprature`"));
+}
+
+#[test]
+fn test_system_prompt_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("rust")).unwrap();
+    std::fs::write(dir.path().join("b-style.md"), "Be brief.").unwrap();
+    std::fs::write(dir.path().join("a-role.md"), "You review code.").unwrap();
+    std::fs::write(dir.path().join("rust/idioms.md"), "Prefer iterators.").unwrap();
+    
+    let mut extra = NamedTempFile::new().unwrap();
+    write!(extra, "Answer in English.").unwrap();
+    
+    let config = SystemPromptConfig {
+        system_prompt_files: vec![dir.path().to_path_buf(), extra.path().to_path_buf()],
+        append_prompt_file: None,
+        recursive: false,
+    };
+    let result = load_system_prompt(config.clone()).unwrap();
+    assert_eq!(result.body, "You review code.\n\nBe brief.\n\nAnswer in English.");
+    
+    let result = load_system_prompt(SystemPromptConfig { recursive: true, ..config }).unwrap();
+    assert_eq!(result.body, "You review code.\n\nBe brief.\n\nPrefer iterators.\n\nAnswer in English.");
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "self-update")]
+
+use claude_dialog::update::{Release, ReleaseAsset, current_version, platform_asset_name, verify_checksum};
+
+#[test]
+fn test_current_version_matches_package() {
+    assert_eq!(current_version().to_string(), env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn test_platform_asset_lookup() {
+    let name = platform_asset_name();
+    let release = Release {
+        tag_name: "v99.0.0".to_string(),
+        assets: vec![ReleaseAsset {
+            name: name.clone(),
+            browser_download_url: "https://example.com/download".to_string(),
+        }],
+    };
+
+    assert!(release.version().unwrap() > current_version());
+    assert_eq!(release.asset(&name).unwrap().browser_download_url, "https://example.com/download");
+}
+
+#[test]
+fn test_checksum_mismatch_is_rejected() {
+    let checksums = format!("{}  claude-dialog-x86_64-linux\n", "ab".repeat(32));
+    let err = verify_checksum(b"binary", &checksums, "claude-dialog-x86_64-linux").unwrap_err();
+    assert!(err.to_string().starts_with("Checksum mismatch for claude-dialog-x86_64-linux"));
+}